@@ -0,0 +1,56 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use d10_bench::{encoded_test_image, test_image_with_alpha, IMAGE_SIZE};
+use d10_codecs::{decode_buffer, encode, EncodingFormat};
+
+/// Formats to benchmark with the image size they are used with
+///
+/// Ico only supports images up to 256x256 pixels.
+fn formats() -> Vec<(&'static str, EncodingFormat, (u32, u32))> {
+    vec![
+        ("jpeg", EncodingFormat::jpeg_default(), IMAGE_SIZE),
+        ("png", EncodingFormat::png_default(), IMAGE_SIZE),
+        ("gif", EncodingFormat::gif_default(), IMAGE_SIZE),
+        ("bmp", EncodingFormat::bmp_default(), IMAGE_SIZE),
+        ("ico", EncodingFormat::ico_default(), (256, 256)),
+        ("webp", EncodingFormat::webp_default(), IMAGE_SIZE),
+        ("dds", EncodingFormat::dds_default(), IMAGE_SIZE),
+    ]
+}
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+
+    for (name, format, (width, height)) in formats() {
+        let data = encoded_test_image(width, height, format);
+
+        group.throughput(Throughput::Elements((width * height) as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &data, |b, data| {
+            b.iter(|| decode_buffer(black_box(data)).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+fn encode_formats(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+
+    for (name, format, (width, height)) in formats() {
+        let buffer = test_image_with_alpha(width, height);
+
+        group.throughput(Throughput::Elements((width * height) as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &buffer, |b, buffer| {
+            b.iter(|| {
+                let mut data = vec![];
+                encode(&mut data, black_box(buffer), format.clone()).unwrap();
+                data
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, decode, encode_formats);
+criterion_main!(benches);
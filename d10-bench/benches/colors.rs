@@ -0,0 +1,42 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use d10_bench::{test_image, IMAGE_SIZE};
+use d10_core::color::{Color, DefaultLab, Rgb, Srgb};
+use d10_core::pixelbuffer::PixelBuffer;
+
+fn conversions(c: &mut Criterion) {
+    let (width, height) = IMAGE_SIZE;
+    let buffer = test_image(width, height);
+    let srgb: PixelBuffer<Srgb> = buffer.map_colors(|c| c.to_srgb());
+    let lab: PixelBuffer<DefaultLab> = buffer.map_colors(|c| c.to_lab());
+
+    let mut group = c.benchmark_group("colors");
+    group.throughput(Throughput::Elements((width * height) as u64));
+
+    group.bench_function("rgb_to_srgb", |b| {
+        b.iter(|| black_box(&buffer).map_colors(|c| c.to_srgb()))
+    });
+    group.bench_function("srgb_to_rgb", |b| {
+        b.iter(|| black_box(&srgb).map_colors(|c| c.to_rgb()))
+    });
+    group.bench_function("rgb_to_hsl", |b| {
+        b.iter(|| black_box(&buffer).map_colors(|c| c.to_hsl()))
+    });
+    group.bench_function("rgb_to_hsv", |b| {
+        b.iter(|| black_box(&buffer).map_colors(|c| c.to_hsv()))
+    });
+    group.bench_function("rgb_to_lab", |b| {
+        b.iter(|| -> PixelBuffer<DefaultLab> { black_box(&buffer).map_colors(|c| c.to_lab()) })
+    });
+    group.bench_function("lab_to_rgb", |b| {
+        b.iter(|| -> PixelBuffer<Rgb> { black_box(&lab).map_colors(|c| c.to_rgb()) })
+    });
+    group.bench_function("rgb_to_gray", |b| {
+        b.iter(|| black_box(&buffer).map_colors(|c| c.to_gray()))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, conversions);
+criterion_main!(benches);
@@ -0,0 +1,75 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use d10_bench::{test_image, IMAGE_SIZE};
+use d10_ops::{gaussian_blur, resize, FilterMode, Pipeline};
+
+fn resize_filters(c: &mut Criterion) {
+    let (width, height) = IMAGE_SIZE;
+    let buffer = test_image(width, height);
+
+    let mut group = c.benchmark_group("resize");
+    group.throughput(Throughput::Elements((width * height) as u64));
+
+    for (name, filter) in [
+        ("nearest", FilterMode::Nearest),
+        ("bilinear", FilterMode::Bilinear),
+        ("bicubic", FilterMode::Bicubic),
+        ("lanczos3", FilterMode::Lanczos3),
+    ] {
+        group.bench_function(BenchmarkId::new("down", name), |b| {
+            b.iter(|| resize(black_box(&buffer), width / 3, height / 3, filter))
+        });
+        group.bench_function(BenchmarkId::new("up", name), |b| {
+            b.iter(|| resize(black_box(&buffer), width * 2, height * 2, filter))
+        });
+    }
+
+    group.finish();
+}
+
+fn blur(c: &mut Criterion) {
+    let (width, height) = IMAGE_SIZE;
+    let buffer = test_image(width, height);
+
+    let mut group = c.benchmark_group("gaussian_blur");
+    group.throughput(Throughput::Elements((width * height) as u64));
+
+    // Radius 1 to 3 use optimized kernels, larger ones the generic implementation
+    for radius in [1, 3, 8, 20] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(radius),
+            &radius,
+            |b, &radius| b.iter(|| gaussian_blur(black_box(&buffer), radius, None)),
+        );
+    }
+
+    group.finish();
+}
+
+fn pipeline(c: &mut Criterion) {
+    let (width, height) = IMAGE_SIZE;
+    let buffer = test_image(width, height);
+
+    let mut group = c.benchmark_group("pipeline");
+    group.throughput(Throughput::Elements((width * height) as u64));
+
+    group.bench_function("chained", |b| {
+        b.iter(|| {
+            black_box(&buffer)
+                .map_colors(|c| c.with_brightness(0.1))
+                .map_colors(|c| c.with_contrast(1.2))
+                .map_colors(|c| c.with_saturation(0.8))
+        })
+    });
+
+    let fused = Pipeline::new()
+        .brightness(0.1)
+        .contrast(1.2)
+        .saturation(0.8);
+    group.bench_function("fused", |b| b.iter(|| fused.apply(black_box(&buffer))));
+
+    group.finish();
+}
+
+criterion_group!(benches, resize_filters, blur, pipeline);
+criterion_main!(benches);
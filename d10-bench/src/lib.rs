@@ -0,0 +1,60 @@
+//! Shared inputs of the d10 benchmarks
+//!
+//! The benches live in `benches/` and are run with `cargo bench -p d10-bench`.
+//! See the README of this crate for the full workflow.
+
+use d10_codecs::{encode, EncodingFormat};
+use d10_core::color::{Color, Rgb, Srgb};
+use d10_core::pixelbuffer::PixelBuffer;
+
+/// Size of the images used by most benches
+pub const IMAGE_SIZE: (u32, u32) = (640, 480);
+
+/// Cheap deterministic noise between 0.0 and 1.0 to keep results comparable between runs
+fn noise(x: u32, y: u32) -> f32 {
+    let mut v = x.wrapping_mul(0x9E37_79B1) ^ y.wrapping_mul(0x85EB_CA77);
+    v ^= v >> 15;
+    v = v.wrapping_mul(0x2C1B_3C6D);
+    v ^= v >> 12;
+
+    (v & 0xFFFF) as f32 / 65535.0
+}
+
+/// Create an image with properties of a typical photo
+///
+/// Smooth gradients are mixed with fine grain and hard edges, so neither codecs nor filters
+/// can take shortcuts that wouldn't work on real images.
+pub fn test_image(width: u32, height: u32) -> PixelBuffer<Rgb> {
+    PixelBuffer::new_from_func(width, height, |x, y| {
+        let fx = x as f32 / width as f32;
+        let fy = y as f32 / height as f32;
+
+        let grain = (noise(x, y) - 0.5) * 0.08;
+        let stripe = if (x / 32 + y / 48) % 3 == 0 {
+            0.15
+        } else {
+            0.0
+        };
+
+        Srgb::new(
+            fx * 0.8 + stripe + grain,
+            fy * 0.7 + 0.1 + grain,
+            (1.0 - fx) * 0.5 + fy * 0.3 - stripe + grain,
+        )
+        .to_rgb()
+    })
+}
+
+/// Create an image with transparent areas for codecs with alpha support
+pub fn test_image_with_alpha(width: u32, height: u32) -> PixelBuffer<Rgb> {
+    let buffer = test_image(width, height);
+
+    buffer.map_colors_enumerated(|x, _, c| c.with_alpha(x as f32 / width as f32))
+}
+
+/// Encode a test image into `format`
+pub fn encoded_test_image(width: u32, height: u32, format: EncodingFormat) -> Vec<u8> {
+    let mut data = vec![];
+    encode(&mut data, &test_image_with_alpha(width, height), format).unwrap();
+    data
+}
@@ -1,36 +1,64 @@
-use d10::{FilterMode, Intensity};
+use d10::ops::{
+    BorderFill, BorderStyle, DiffMode, HalftoneShape, LookPreset, ScanMode, DEFAULT_DIFF_THRESHOLD,
+};
+use d10::{Color, FilterMode, Intensity, Rgb, Srgb};
 
-use d10_commands::{Cmd, Cmd::*, Queue};
+use d10_commands::{Cmd, Cmd::*, CommandError, CommandFailure, Queue, TrackingAllocator};
 use std::ffi::OsString;
 use std::process::exit;
 
+// Lets `profile` report the peak memory of every command
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
 fn main() {
-    let args: Vec<OsString> = std::env::args_os().collect();
+    let mut args: Vec<OsString> = std::env::args_os().collect();
+
+    // Global flag that is valid at any position
+    let args_len = args.len();
+    args.retain(|arg| arg != "--json-errors" && arg != "-json-errors");
+    let json_errors = args.len() != args_len;
 
     if args.len() == 1 {
-        eprintln!("Missing arguments");
-        exit(1);
+        fail(
+            CommandError::InvalidArgument("Missing arguments".to_owned()).into(),
+            json_errors,
+        );
+    }
+
+    let queue = match create_args().parse(args) {
+        Ok(q) => q,
+        Err(err) => fail(CommandError::InvalidArgument(err).into(), json_errors),
+    };
+
+    if let Err(failure) = queue.run_detailed() {
+        fail(failure, json_errors);
+    }
+}
+
+fn fail(failure: CommandFailure, json: bool) -> ! {
+    if json {
+        eprintln!("{}", failure.to_json());
     } else {
-        let queue = match create_args().parse(args) {
-            Ok(q) => q,
-            Err(err) => {
-                eprintln!("{}", err);
-                exit(1);
-            }
-        };
+        eprintln!("{}", failure);
+    }
 
-        if let Err(err) = queue.run() {
-            eprintln!("{}", err);
-            exit(1);
-        }
+    // Allows scripts to tell failed comparisons apart from other errors
+    if failure.error.is_comparison_failure() {
+        exit(2);
     }
+
+    exit(1);
 }
 
 fn create_args() -> Args {
     Args::new()
         .none_arg("silent", || Silent)
+        .none_arg("profile", || Profile)
         .os_string_arg("open", |v| Ok(Open(v.into())))
         .os_string_arg("save", |v| Ok(Save(v.into())))
+        .none_arg("strip", || Strip)
+        .os_string_arg("save-icons", |v| Ok(SaveIcons(v.into())))
         .string_arg("grayscale", |v| Ok(ToGray(parse_intensity(&v)?)))
         .none_arg("invert", || Invert)
         .number_arg("gamma", |v| Ok(Gamma(v)))
@@ -59,15 +87,172 @@ fn create_args() -> Args {
                 filter: FilterMode::Bilinear,
             })
         })
+        .number_arg("straighten", |v| Ok(Straighten(v)))
+        .number2_arg("offset", |v1, v2| {
+            Ok(Offset {
+                dx: v1 as i32,
+                dy: v2 as i32,
+            })
+        })
+        .number_arg("make-seamless", |v| Ok(MakeSeamless(v as u32)))
+        .number_arg("upscale-enhanced", |v| Ok(UpscaleEnhanced(v)))
         .number_arg("random-noise", |v| Ok(RandomNoise(v)))
         .number_arg("salt-n-pepper-noise", |v| Ok(SaltNPepperNoise(v)))
         .number_arg("rgb-noise", |v| Ok(RgbNoise(v)))
+        .string_arg("halftone", |v| parse_halftone(&v))
+        .string_arg("duotone", |v| parse_duotone(&v))
+        .string_arg("border", |v| parse_border(&v))
+        .string_arg("look", |v| Ok(Look(parse_look(&v)?)))
+        .number_arg("auto-enhance", |v| Ok(AutoEnhance(v)))
+        .string_arg("scan-enhance", |v| Ok(ScanEnhance(parse_scan_mode(&v)?)))
+        .number2_arg("contact-sheet", |v1, v2| {
+            Ok(ContactSheet {
+                columns: v1 as u32,
+                thumb_size: v2 as u32,
+            })
+        })
+        .os_string_arg("diff", |v| {
+            Ok(Diff {
+                other: v.into(),
+                mode: DiffMode::SideBySide,
+                threshold: DEFAULT_DIFF_THRESHOLD,
+            })
+        })
+        .os_string_arg("compare", |v| Ok(Compare(v.into())))
+        .string_arg("metric", |v| Ok(Metric(parse_metric(&v)?)))
+        .number_arg("threshold", |v| Ok(Threshold(v)))
+        .none_arg("identify", || Identify { json: false })
+        .none_arg("identify-json", || Identify { json: true })
+        .os_string_arg("histogram", |v| Ok(Histogram(v.into())))
+        .number2_arg("fit", |v1, v2| {
+            Ok(Fit {
+                width: v1 as u32,
+                height: v2 as u32,
+            })
+        })
+        .number2_arg("cover", |v1, v2| {
+            Ok(Cover {
+                width: v1 as u32,
+                height: v2 as u32,
+            })
+        })
+        .number2_arg("smart-crop", |v1, v2| {
+            Ok(SmartCrop {
+                width: v1 as u32,
+                height: v2 as u32,
+            })
+        })
+        .number2_arg("unsharp", |v1, v2| {
+            Ok(Unsharp {
+                radius: v1 as u32,
+                factor: v2,
+            })
+        })
+        .string_arg("preset", |v| Ok(Preset(v)))
 }
 
 fn parse_intensity(arg: &str) -> Result<Intensity, String> {
     arg.parse::<Intensity>().map_err(|err| err.to_string())
 }
 
+fn parse_metric(arg: &str) -> Result<d10::ops::Metric, String> {
+    arg.parse::<d10::ops::Metric>()
+        .map_err(|err| err.to_string())
+}
+
+fn parse_look(arg: &str) -> Result<LookPreset, String> {
+    arg.parse::<LookPreset>().map_err(|err| err.to_string())
+}
+
+fn parse_scan_mode(arg: &str) -> Result<ScanMode, String> {
+    arg.parse::<ScanMode>().map_err(|err| err.to_string())
+}
+
+/// Parse a color in the hex notation `#RRGGBB` or `RRGGBB`
+fn parse_color(arg: &str) -> Result<Rgb, String> {
+    let hex = arg.strip_prefix('#').unwrap_or(arg);
+
+    if hex.len() != 6 || !hex.is_ascii() {
+        return Err(format!("Bad color: {}", arg));
+    }
+
+    let channel = |i: usize| {
+        u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map(|v| v as f32 / 255.0)
+            .map_err(|_| format!("Bad color: {}", arg))
+    };
+
+    Ok(Srgb::new(channel(0)?, channel(1)?, channel(2)?).to_rgb())
+}
+
+fn parse_halftone(arg: &str) -> Result<Cmd, String> {
+    let bad_argument = || format!("Bad argument for parameter halftone: {}", arg);
+
+    let values: Vec<&str> = arg.split(',').collect();
+
+    if values.len() < 2 || values.len() > 3 {
+        return Err(bad_argument());
+    }
+
+    let cell_size = values[0].parse().map_err(|_| bad_argument())?;
+    let angle = values[1].parse().map_err(|_| bad_argument())?;
+    let shape = match values.get(2) {
+        Some(shape) => shape
+            .parse::<HalftoneShape>()
+            .map_err(|err| err.to_string())?,
+        None => HalftoneShape::Dot,
+    };
+
+    Ok(Halftone {
+        cell_size,
+        angle,
+        shape,
+    })
+}
+
+fn parse_duotone(arg: &str) -> Result<Cmd, String> {
+    match arg.split_once(',') {
+        Some((dark_color, light_color)) => Ok(Duotone {
+            dark_color: parse_color(dark_color)?,
+            light_color: parse_color(light_color)?,
+        }),
+        None => Err(format!("Bad argument for parameter duotone: {}", arg)),
+    }
+}
+
+/// Parse `polaroid` or `WIDTH,COLOR[,STYLE]` with `COLOR` being a color or a gradient from the
+/// image to the outer edge like `#ffffff:#000000`
+fn parse_border(arg: &str) -> Result<Cmd, String> {
+    if arg == "polaroid" {
+        return Ok(Polaroid);
+    }
+
+    let bad_argument = || format!("Bad argument for parameter border: {}", arg);
+
+    let values: Vec<&str> = arg.split(',').collect();
+
+    if values.len() < 2 || values.len() > 3 {
+        return Err(bad_argument());
+    }
+
+    let width = values[0].parse().map_err(|_| bad_argument())?;
+    let fill = match values[1].split_once(':') {
+        Some((inner, outer)) => BorderFill::Gradient {
+            inner: parse_color(inner)?,
+            outer: parse_color(outer)?,
+        },
+        None => BorderFill::Color(parse_color(values[1])?),
+    };
+    let style = match values.get(2) {
+        Some(style) => style
+            .parse::<BorderStyle>()
+            .map_err(|err| err.to_string())?,
+        None => BorderStyle::Solid,
+    };
+
+    Ok(Border { width, fill, style })
+}
+
 enum ArgHandler {
     None(fn() -> Cmd),
     String(fn(String) -> Result<Cmd, String>),
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use d10_codecs::decode_buffer;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decode_buffer(data);
+});
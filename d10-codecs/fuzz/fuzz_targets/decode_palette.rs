@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use d10_codecs::{decode_palette, PaletteFormat};
+
+fuzz_target!(|data: &[u8]| {
+    for format in [PaletteFormat::Gpl, PaletteFormat::Aco, PaletteFormat::Ase] {
+        let _ = decode_palette(data, format);
+    }
+});
@@ -0,0 +1,11 @@
+#![no_main]
+
+use std::io::Cursor;
+
+use libfuzzer_sys::fuzz_target;
+
+use d10_codecs::decode_progressive;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decode_progressive(Cursor::new(data));
+});
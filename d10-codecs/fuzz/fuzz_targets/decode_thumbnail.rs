@@ -0,0 +1,13 @@
+#![no_main]
+
+use std::io::Cursor;
+
+use libfuzzer_sys::fuzz_target;
+
+use d10_codecs::decode_thumbnail;
+
+fuzz_target!(|data: &[u8]| {
+    if let Some((max_edge, data)) = data.split_first() {
+        let _ = decode_thumbnail(Cursor::new(data), *max_edge as u32);
+    }
+});
@@ -0,0 +1,26 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use d10_codecs::{decode_buffer_with_hint, Format};
+
+const FORMATS: [Format; 9] = [
+    Format::Jpeg,
+    Format::Png,
+    Format::Gif,
+    Format::Bmp,
+    Format::Ico,
+    Format::WebP,
+    Format::Dds,
+    Format::Psd,
+    Format::Ora,
+];
+
+// The first byte selects the decoder used if the format can't be detected,
+// so every decoder gets fuzzed without the need to find its magic bytes first.
+fuzz_target!(|data: &[u8]| {
+    if let Some((selector, data)) = data.split_first() {
+        let hint = FORMATS[*selector as usize % FORMATS.len()];
+        let _ = decode_buffer_with_hint(data, Some(hint));
+    }
+});
@@ -0,0 +1,97 @@
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::task::{spawn_blocking, JoinError};
+
+use d10_core::color::Rgb;
+use d10_core::pixelbuffer::PixelBuffer;
+
+use crate::{decode_buffer, encode, DecodedImage, DecodingError, EncodingError, EncodingFormat};
+
+fn join_error(err: JoinError) -> io::Error {
+    if err.is_panic() {
+        std::panic::resume_unwind(err.into_panic())
+    } else {
+        io::Error::other(err)
+    }
+}
+
+/// Read an image from an async reader
+///
+/// Decoding runs on the blocking thread pool of the current tokio runtime.
+pub async fn decode_async<R>(mut reader: R) -> Result<DecodedImage, DecodingError>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut data = vec![];
+    reader.read_to_end(&mut data).await?;
+
+    spawn_blocking(move || decode_buffer(&data))
+        .await
+        .map_err(join_error)?
+}
+
+/// Write an image to an async writer
+///
+/// Encoding runs on the blocking thread pool of the current tokio runtime on a copy of the buffer.
+pub async fn encode_async<W>(
+    mut w: W,
+    buffer: &PixelBuffer<Rgb>,
+    format: EncodingFormat,
+) -> Result<(), EncodingError>
+where
+    W: AsyncWrite + Unpin,
+{
+    let buffer = buffer.clone();
+
+    let data = spawn_blocking(move || {
+        let mut data = vec![];
+        encode(&mut data, &buffer, format).map(|_| data)
+    })
+    .await
+    .map_err(join_error)??;
+
+    w.write_all(&data).await?;
+    w.flush().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_on<F: std::future::Future>(f: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(f)
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let buffer =
+            PixelBuffer::new_from_func(5, 3, |x, y| Rgb::new(x as f32 / 4.0, y as f32 / 2.0, 0.5));
+
+        let mut data = vec![];
+        block_on(encode_async(
+            &mut data,
+            &buffer,
+            EncodingFormat::png_default(),
+        ))
+        .unwrap();
+
+        let decoded = block_on(decode_async(&data[..])).unwrap().buffer;
+        let expected = decode_buffer(&data).unwrap().buffer;
+
+        assert_eq!(decoded.data(), expected.data());
+        assert_eq!(decoded.width(), 5);
+        assert_eq!(decoded.height(), 3);
+    }
+
+    #[test]
+    fn test_decode_error() {
+        let result = block_on(decode_async(&[1u8, 2, 3][..]));
+        assert!(matches!(result, Err(DecodingError::UnknownFormat)));
+    }
+}
@@ -1,3 +1,5 @@
+#[cfg(feature = "mmap")]
+use d10_core::color::Color;
 use d10_core::color::Rgb;
 use d10_core::errors::ParseEnumError;
 use d10_core::pixelbuffer::PixelBuffer;
@@ -8,8 +10,12 @@ use std::str::FromStr;
 use image::codecs::bmp::{BmpDecoder, BmpEncoder};
 use image::{ColorType, DynamicImage, ImageError};
 
-use crate::utils::{read_into_buffer, to_l8_vec, to_la8_vec, to_rgb8_vec, to_rgba8_vec};
-use crate::{DecodedImage, DecodingError, EncodingError};
+#[cfg(feature = "mmap")]
+use crate::utils::as_u8;
+use crate::utils::{
+    check_decoder_size, read_into_buffer, to_l8_vec, to_la8_vec, to_rgb8_vec, to_rgba8_vec,
+};
+use crate::{DecodedImage, DecodingError, EncodingError, Format};
 
 #[derive(Copy, Clone, Debug)]
 pub enum BmpColorType {
@@ -43,10 +49,10 @@ where
     W: Write,
 {
     let (out, color_type) = match color_type {
-        BmpColorType::L8 => (to_l8_vec(buffer), ColorType::L8),
-        BmpColorType::La8 => (to_la8_vec(buffer), ColorType::La8),
-        BmpColorType::Rgb8 => (to_rgb8_vec(buffer), ColorType::Rgb8),
-        BmpColorType::Rgba8 => (to_rgba8_vec(buffer), ColorType::Rgba8),
+        BmpColorType::L8 => (to_l8_vec(buffer.data()), ColorType::L8),
+        BmpColorType::La8 => (to_la8_vec(buffer.data()), ColorType::La8),
+        BmpColorType::Rgb8 => (to_rgb8_vec(buffer.data()), ColorType::Rgb8),
+        BmpColorType::Rgba8 => (to_rgba8_vec(buffer.data()), ColorType::Rgba8),
     };
 
     if let Err(err) =
@@ -54,26 +60,129 @@ where
     {
         Err(match err {
             ImageError::IoError(err) => EncodingError::IoError(err),
-            err => EncodingError::Encoding(err.to_string()),
+            err => EncodingError::codec(Format::Bmp, err),
         })
     } else {
         Ok(())
     }
 }
 
+/// Size of the info header, bytes per pixel and number of palette colors of the written files
+///
+/// Gray images are written with a palette and alpha needs a larger header.
+#[cfg(feature = "mmap")]
+fn bmp_layout(color_type: BmpColorType) -> (u32, u32, u32) {
+    match color_type {
+        BmpColorType::L8 | BmpColorType::La8 => (40, 1, 256),
+        BmpColorType::Rgb8 => (40, 3, 0),
+        BmpColorType::Rgba8 => (108, 4, 0),
+    }
+}
+
+/// Size of the encoded file
+#[cfg(feature = "mmap")]
+pub(crate) fn bmp_encoded_size(width: u32, height: u32, color_type: BmpColorType) -> u64 {
+    let (info_size, bytes_per_pixel, palette_colors) = bmp_layout(color_type);
+
+    // Rows are padded to a multiple of 4 bytes
+    let row_size = (width as u64 * bytes_per_pixel as u64).div_ceil(4) * 4;
+
+    14 + info_size as u64 + palette_colors as u64 * 4 + row_size * height as u64
+}
+
+/// Encode directly into `out` which must have the size returned by `bmp_encoded_size()`
+///
+/// This writes the same data as `encode_bmp()` without converting the whole image first.
+#[cfg(feature = "mmap")]
+pub(crate) fn encode_bmp_into(out: &mut [u8], buffer: &PixelBuffer<Rgb>, color_type: BmpColorType) {
+    let width = buffer.width();
+    let height = buffer.height();
+
+    let (info_size, bytes_per_pixel, palette_colors) = bmp_layout(color_type);
+    let row_size = (width * bytes_per_pixel).div_ceil(4) * 4;
+    let data_offset = 14 + info_size + palette_colors * 4;
+    let with_masks = info_size >= 108;
+
+    let mut header = Vec::with_capacity(data_offset as usize);
+    header.extend_from_slice(b"BM");
+    header.extend_from_slice(&(out.len() as u32).to_le_bytes());
+    header.extend_from_slice(&[0; 4]);
+    header.extend_from_slice(&data_offset.to_le_bytes());
+
+    header.extend_from_slice(&info_size.to_le_bytes());
+    header.extend_from_slice(&width.to_le_bytes());
+    header.extend_from_slice(&height.to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&(bytes_per_pixel as u16 * 8).to_le_bytes());
+    // Bitfields compression for the channel masks and no compression otherwise
+    header.extend_from_slice(&(if with_masks { 3u32 } else { 0 }).to_le_bytes());
+    header.extend_from_slice(&(row_size * height).to_le_bytes());
+    header.extend_from_slice(&[0; 8]);
+    header.extend_from_slice(&palette_colors.to_le_bytes());
+    header.extend_from_slice(&[0; 4]);
+
+    if with_masks {
+        for mask in [0x00FF_0000u32, 0x0000_FF00, 0x0000_00FF, 0xFF00_0000] {
+            header.extend_from_slice(&mask.to_le_bytes());
+        }
+        header.extend_from_slice(b"BGRs");
+        // Endpoints and gamma of the color space
+        header.extend_from_slice(&[0; 48]);
+    }
+
+    // Gray palette with the index as value
+    for value in 0..palette_colors {
+        header.extend_from_slice(&[value as u8, value as u8, value as u8, 0]);
+    }
+
+    let (head, rows) = out.split_at_mut(data_offset as usize);
+    head.copy_from_slice(&header);
+
+    // Rows are stored from the bottom up
+    for (row, y) in rows
+        .chunks_exact_mut(row_size as usize)
+        .zip((0..height).rev())
+    {
+        let (pixels, padding) = row.split_at_mut((width * bytes_per_pixel) as usize);
+        padding.fill(0);
+
+        for (pixel, x) in pixels
+            .chunks_exact_mut(bytes_per_pixel as usize)
+            .zip(0..width)
+        {
+            let color = buffer.get_pixel(x, y);
+
+            match color_type {
+                BmpColorType::L8 | BmpColorType::La8 => {
+                    pixel[0] = as_u8(color.to_gray().to_srgb().red());
+                }
+                BmpColorType::Rgb8 | BmpColorType::Rgba8 => {
+                    let color = color.to_srgb();
+                    let bgra = [color.blue(), color.green(), color.red(), color.alpha()];
+                    for (v, c) in pixel.iter_mut().zip(bgra) {
+                        *v = as_u8(c);
+                    }
+                }
+            }
+        }
+    }
+}
+
 pub(crate) fn decode_bmp<T>(reader: T) -> Result<DecodedImage, DecodingError>
 where
     T: Read + Seek + BufRead,
 {
     let decoder = BmpDecoder::new(reader).map_err(|err| match err {
         ImageError::IoError(err) => DecodingError::IoError(err),
-        err => DecodingError::Decoding(err.to_string()),
+        err => DecodingError::codec(Format::Bmp, err),
     })?;
 
+    check_decoder_size(&decoder)?;
+
     let img = DynamicImage::from_decoder(decoder).map_err(|err| match err {
         ImageError::IoError(err) => DecodingError::IoError(err),
-        err => DecodingError::Decoding(err.to_string()),
+        err => DecodingError::codec(Format::Bmp, err),
     })?;
 
-    read_into_buffer(img).map(|buffer| DecodedImage { buffer })
+    read_into_buffer(img, Format::Bmp).map(|buffer| DecodedImage { buffer })
 }
@@ -0,0 +1,565 @@
+use d10_core::color::{Color, Rgb, Srgb};
+use d10_core::pixelbuffer::{is_valid_buffer_size, PixelBuffer};
+
+use std::io::{Read, Write};
+
+#[cfg(feature = "mmap")]
+use crate::utils::as_u8;
+use crate::utils::{from_u8, to_rgba8_vec};
+use crate::{DecodedImage, DecodingError, EncodingError, Format};
+
+/// Size of the header following the magic bytes
+const HEADER_SIZE: usize = 124;
+
+/// Size of the pixel format structure inside of the header
+const PIXEL_FORMAT_SIZE: u32 = 32;
+
+const DDSD_CAPS: u32 = 0x1;
+const DDSD_HEIGHT: u32 = 0x2;
+const DDSD_WIDTH: u32 = 0x4;
+const DDSD_PITCH: u32 = 0x8;
+const DDSD_PIXELFORMAT: u32 = 0x1000;
+
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDPF_LUMINANCE: u32 = 0x20000;
+
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+
+/// Block compressed formats
+#[derive(Copy, Clone, Debug)]
+enum Compression {
+    /// DXT1
+    Bc1,
+    /// DXT3
+    Bc2,
+    /// DXT5
+    Bc3,
+}
+
+impl Compression {
+    fn block_size(&self) -> usize {
+        match self {
+            Compression::Bc1 => 8,
+            Compression::Bc2 | Compression::Bc3 => 16,
+        }
+    }
+}
+
+/// Uncompressed pixels described by bit masks
+#[derive(Copy, Clone, Debug)]
+struct Masks {
+    bits: u32,
+    red: u32,
+    green: u32,
+    blue: u32,
+    alpha: u32,
+    luminance: bool,
+}
+
+fn invalid_data(message: &str) -> DecodingError {
+    DecodingError::InvalidData {
+        format: Format::Dds,
+        message: message.to_owned(),
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+/// Expand a RGB565 color into 8 bit channels
+fn rgb565(value: u16) -> [u8; 3] {
+    let r = ((value >> 11) & 0x1F) as u8;
+    let g = ((value >> 5) & 0x3F) as u8;
+    let b = (value & 0x1F) as u8;
+
+    [
+        (r << 3) | (r >> 2),
+        (g << 2) | (g >> 4),
+        (b << 3) | (b >> 2),
+    ]
+}
+
+fn mix(c1: [u8; 3], c2: [u8; 3], w1: u16, w2: u16) -> [u8; 3] {
+    let channel = |i: usize| ((c1[i] as u16 * w1 + c2[i] as u16 * w2) / (w1 + w2)) as u8;
+    [channel(0), channel(1), channel(2)]
+}
+
+/// Decode the color part of a BC1, BC2 or BC3 block into 16 RGBA pixels
+///
+/// `punch_through` enables the three color mode of BC1 with transparent black.
+fn decode_color_block(block: &[u8], punch_through: bool, out: &mut [[u8; 4]; 16]) {
+    let v0 = u16::from_le_bytes([block[0], block[1]]);
+    let v1 = u16::from_le_bytes([block[2], block[3]]);
+
+    let c0 = rgb565(v0);
+    let c1 = rgb565(v1);
+
+    let palette = if v0 > v1 || !punch_through {
+        [
+            [c0[0], c0[1], c0[2], 255],
+            [c1[0], c1[1], c1[2], 255],
+            with_alpha(mix(c0, c1, 2, 1), 255),
+            with_alpha(mix(c0, c1, 1, 2), 255),
+        ]
+    } else {
+        [
+            [c0[0], c0[1], c0[2], 255],
+            [c1[0], c1[1], c1[2], 255],
+            with_alpha(mix(c0, c1, 1, 1), 255),
+            [0, 0, 0, 0],
+        ]
+    };
+
+    let indices = read_u32(block, 4);
+
+    for (i, pixel) in out.iter_mut().enumerate() {
+        *pixel = palette[((indices >> (2 * i)) & 0x3) as usize];
+    }
+}
+
+fn with_alpha(c: [u8; 3], alpha: u8) -> [u8; 4] {
+    [c[0], c[1], c[2], alpha]
+}
+
+/// Decode the interpolated alpha part of a BC3 block
+fn decode_bc3_alpha(block: &[u8], out: &mut [[u8; 4]; 16]) {
+    let a0 = block[0] as u16;
+    let a1 = block[1] as u16;
+
+    let mut palette = [0u8; 8];
+    palette[0] = a0 as u8;
+    palette[1] = a1 as u8;
+
+    if a0 > a1 {
+        for i in 1..7 {
+            palette[i + 1] = (((7 - i as u16) * a0 + i as u16 * a1) / 7) as u8;
+        }
+    } else {
+        for i in 1..5 {
+            palette[i + 1] = (((5 - i as u16) * a0 + i as u16 * a1) / 5) as u8;
+        }
+        palette[6] = 0;
+        palette[7] = 255;
+    }
+
+    let indices = block[2..8]
+        .iter()
+        .rev()
+        .fold(0u64, |acc, v| (acc << 8) | *v as u64);
+
+    for (i, pixel) in out.iter_mut().enumerate() {
+        pixel[3] = palette[((indices >> (3 * i)) & 0x7) as usize];
+    }
+}
+
+/// Decode the explicit 4 bit alpha part of a BC2 block
+fn decode_bc2_alpha(block: &[u8], out: &mut [[u8; 4]; 16]) {
+    for (i, pixel) in out.iter_mut().enumerate() {
+        let value = (block[i / 2] >> (4 * (i % 2))) & 0xF;
+        pixel[3] = value * 17;
+    }
+}
+
+fn decode_compressed(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    compression: Compression,
+) -> Result<Vec<u8>, DecodingError> {
+    let blocks_x = width.div_ceil(4) as usize;
+    let blocks_y = height.div_ceil(4) as usize;
+    let block_size = compression.block_size();
+
+    if data.len() < blocks_x * blocks_y * block_size {
+        return Err(invalid_data("Truncated image data"));
+    }
+
+    let mut out = vec![0u8; width as usize * height as usize * 4];
+    let mut pixels = [[0u8; 4]; 16];
+
+    for (i, block) in data
+        .chunks_exact(block_size)
+        .take(blocks_x * blocks_y)
+        .enumerate()
+    {
+        match compression {
+            Compression::Bc1 => decode_color_block(block, true, &mut pixels),
+            Compression::Bc2 => {
+                decode_color_block(&block[8..], false, &mut pixels);
+                decode_bc2_alpha(&block[..8], &mut pixels);
+            }
+            Compression::Bc3 => {
+                decode_color_block(&block[8..], false, &mut pixels);
+                decode_bc3_alpha(&block[..8], &mut pixels);
+            }
+        }
+
+        let bx = (i % blocks_x) as u32 * 4;
+        let by = (i / blocks_x) as u32 * 4;
+
+        for (j, pixel) in pixels.iter().enumerate() {
+            let x = bx + j as u32 % 4;
+            let y = by + j as u32 / 4;
+
+            if x < width && y < height {
+                let start = (y * width + x) as usize * 4;
+                out[start..start + 4].copy_from_slice(pixel);
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+/// Extract a channel with an arbitrary bit mask and scale it to 8 bits
+fn masked(value: u32, mask: u32) -> Option<u8> {
+    if mask == 0 {
+        return None;
+    }
+
+    let max = mask >> mask.trailing_zeros();
+    let v = (value & mask) >> mask.trailing_zeros();
+
+    Some(((v as u64 * 255 + max as u64 / 2) / max as u64) as u8)
+}
+
+fn decode_uncompressed(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    masks: Masks,
+) -> Result<Vec<u8>, DecodingError> {
+    let bytes_per_pixel = match masks.bits {
+        8 | 16 | 24 | 32 => masks.bits as usize / 8,
+        bits => {
+            return Err(DecodingError::Unsupported {
+                format: Format::Dds,
+                message: format!("{} bits per pixel", bits),
+            })
+        }
+    };
+
+    let len = width as usize * height as usize * bytes_per_pixel;
+
+    if data.len() < len {
+        return Err(invalid_data("Truncated image data"));
+    }
+
+    let mut out = Vec::with_capacity(width as usize * height as usize * 4);
+
+    for chunk in data[..len].chunks_exact(bytes_per_pixel) {
+        let value = chunk
+            .iter()
+            .rev()
+            .fold(0u32, |acc, v| (acc << 8) | *v as u32);
+
+        let red = masked(value, masks.red).unwrap_or(0);
+        let (green, blue) = if masks.luminance {
+            (red, red)
+        } else {
+            (
+                masked(value, masks.green).unwrap_or(0),
+                masked(value, masks.blue).unwrap_or(0),
+            )
+        };
+        let alpha = masked(value, masks.alpha).unwrap_or(255);
+
+        out.extend_from_slice(&[red, green, blue, alpha]);
+    }
+
+    Ok(out)
+}
+
+/// Decode the main surface of a dds texture
+///
+/// Supported are BC1 (DXT1), BC2 (DXT3), BC3 (DXT5) and uncompressed RGB, RGBA and luminance data.
+/// Mipmaps, cube map faces and DX10 formats are ignored or rejected.
+pub(crate) fn decode_dds<T>(mut reader: T) -> Result<DecodedImage, DecodingError>
+where
+    T: Read,
+{
+    let mut data = vec![];
+    reader.read_to_end(&mut data)?;
+
+    if data.len() < 4 + HEADER_SIZE || &data[0..4] != b"DDS " {
+        return Err(invalid_data("Missing dds header"));
+    }
+
+    let header = &data[4..4 + HEADER_SIZE];
+
+    if read_u32(header, 0) as usize != HEADER_SIZE || read_u32(header, 72) != PIXEL_FORMAT_SIZE {
+        return Err(invalid_data("Bad header size"));
+    }
+
+    let height = read_u32(header, 8);
+    let width = read_u32(header, 12);
+
+    if !is_valid_buffer_size(width, height) {
+        return Err(DecodingError::InvalidBufferSize { width, height });
+    }
+
+    let pf_flags = read_u32(header, 76);
+    let four_cc = &header[80..84];
+    let pixels = &data[4 + HEADER_SIZE..];
+
+    let raw = if pf_flags & DDPF_FOURCC != 0 {
+        let compression = match four_cc {
+            b"DXT1" => Compression::Bc1,
+            b"DXT2" | b"DXT3" => Compression::Bc2,
+            b"DXT4" | b"DXT5" => Compression::Bc3,
+            _ => {
+                return Err(DecodingError::Unsupported {
+                    format: Format::Dds,
+                    message: format!("FourCC {}", String::from_utf8_lossy(four_cc)),
+                })
+            }
+        };
+
+        decode_compressed(pixels, width, height, compression)?
+    } else if pf_flags & (DDPF_RGB | DDPF_LUMINANCE) != 0 {
+        let masks = Masks {
+            bits: read_u32(header, 84),
+            red: read_u32(header, 88),
+            green: read_u32(header, 92),
+            blue: read_u32(header, 96),
+            alpha: if pf_flags & DDPF_ALPHAPIXELS != 0 {
+                read_u32(header, 100)
+            } else {
+                0
+            },
+            luminance: pf_flags & DDPF_LUMINANCE != 0,
+        };
+
+        decode_uncompressed(pixels, width, height, masks)?
+    } else {
+        return Err(DecodingError::Unsupported {
+            format: Format::Dds,
+            message: format!("Pixel format flags {:#x}", pf_flags),
+        });
+    };
+
+    let data = raw
+        .chunks_exact(4)
+        .map(|c| {
+            Srgb::new_with_alpha(from_u8(c[0]), from_u8(c[1]), from_u8(c[2]), from_u8(c[3]))
+                .to_rgb()
+        })
+        .collect();
+
+    Ok(DecodedImage {
+        buffer: PixelBuffer::new_from_raw(width, height, data),
+    })
+}
+
+/// Size of the file written by `encode_dds()`
+#[cfg(feature = "mmap")]
+pub(crate) fn dds_encoded_size(width: u32, height: u32) -> u64 {
+    4 + HEADER_SIZE as u64 + width as u64 * height as u64 * 4
+}
+
+/// Encode an uncompressed 32 bit BGRA texture without mipmaps
+pub(crate) fn encode_dds<W>(mut w: W, buffer: &PixelBuffer<Rgb>) -> Result<(), EncodingError>
+where
+    W: Write,
+{
+    let width = buffer.width();
+    let height = buffer.height();
+
+    if width == 0 || height == 0 {
+        return Err(EncodingError::BadDimensions {
+            format: Format::Dds,
+            width,
+            height,
+        });
+    }
+
+    write_header(&mut w, width, height)?;
+
+    let mut data = to_rgba8_vec(buffer.data());
+    for pixel in data.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
+
+    w.write_all(&data)?;
+
+    Ok(())
+}
+
+/// Encode directly into `out` which must have the size returned by `dds_encoded_size()`
+///
+/// This writes the same data as `encode_dds()` without converting the whole image first.
+#[cfg(feature = "mmap")]
+pub(crate) fn encode_dds_into(
+    mut out: &mut [u8],
+    buffer: &PixelBuffer<Rgb>,
+) -> Result<(), EncodingError> {
+    write_header(&mut out, buffer.width(), buffer.height())?;
+
+    for (pixel, color) in out.chunks_exact_mut(4).zip(buffer.data()) {
+        let color = color.to_srgb();
+        pixel.copy_from_slice(&[
+            as_u8(color.blue()),
+            as_u8(color.green()),
+            as_u8(color.red()),
+            as_u8(color.alpha()),
+        ]);
+    }
+
+    Ok(())
+}
+
+/// Write the magic bytes and the header of an uncompressed 32 bit BGRA texture
+fn write_header<W: Write>(mut w: W, width: u32, height: u32) -> Result<(), EncodingError> {
+    let mut header = [0u32; HEADER_SIZE / 4];
+    header[0] = HEADER_SIZE as u32;
+    header[1] = DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PITCH | DDSD_PIXELFORMAT;
+    header[2] = height;
+    header[3] = width;
+    header[4] = width * 4;
+    // Pixel format
+    header[18] = PIXEL_FORMAT_SIZE;
+    header[19] = DDPF_RGB | DDPF_ALPHAPIXELS;
+    header[21] = 32;
+    header[22] = 0x00FF_0000;
+    header[23] = 0x0000_FF00;
+    header[24] = 0x0000_00FF;
+    header[25] = 0xFF00_0000;
+    header[26] = DDSCAPS_TEXTURE;
+
+    w.write_all(b"DDS ")?;
+    for value in header {
+        w.write_all(&value.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(width: u32, height: u32, pf_flags: u32, four_cc: &[u8; 4]) -> Vec<u8> {
+        let mut data = b"DDS ".to_vec();
+        let mut header = [0u8; HEADER_SIZE];
+        header[0..4].copy_from_slice(&(HEADER_SIZE as u32).to_le_bytes());
+        header[8..12].copy_from_slice(&height.to_le_bytes());
+        header[12..16].copy_from_slice(&width.to_le_bytes());
+        header[72..76].copy_from_slice(&PIXEL_FORMAT_SIZE.to_le_bytes());
+        header[76..80].copy_from_slice(&pf_flags.to_le_bytes());
+        header[80..84].copy_from_slice(four_cc);
+        data.extend_from_slice(&header);
+        data
+    }
+
+    fn rgba(buffer: &PixelBuffer<Rgb>, x: u32, y: u32) -> [u8; 4] {
+        let c = buffer.get_pixel(x, y).to_srgb();
+        [c.red(), c.green(), c.blue(), c.alpha()].map(|v| (v * 255.0).round() as u8)
+    }
+
+    #[test]
+    fn test_decode_bc1() {
+        let mut data = header(4, 4, DDPF_FOURCC, b"DXT1");
+        // Red and blue in four color mode with every row using another palette entry
+        data.extend_from_slice(&0xF800u16.to_le_bytes());
+        data.extend_from_slice(&0x001Fu16.to_le_bytes());
+        data.extend_from_slice(&[0x00, 0x55, 0xAA, 0xFF]);
+
+        let buffer = decode_dds(data.as_slice()).unwrap().buffer;
+
+        assert_eq!((buffer.width(), buffer.height()), (4, 4));
+        assert_eq!(rgba(&buffer, 0, 0), [255, 0, 0, 255]);
+        assert_eq!(rgba(&buffer, 3, 1), [0, 0, 255, 255]);
+        assert_eq!(rgba(&buffer, 1, 2), [170, 0, 85, 255]);
+        assert_eq!(rgba(&buffer, 2, 3), [85, 0, 170, 255]);
+    }
+
+    #[test]
+    fn test_decode_bc1_transparent() {
+        let mut data = header(2, 2, DDPF_FOURCC, b"DXT1");
+        data.extend_from_slice(&0x001Fu16.to_le_bytes());
+        data.extend_from_slice(&0xF800u16.to_le_bytes());
+        data.extend_from_slice(&[0x03, 0x00, 0x00, 0x00]);
+
+        let buffer = decode_dds(data.as_slice()).unwrap().buffer;
+
+        // Only the visible part of the block is decoded
+        assert_eq!((buffer.width(), buffer.height()), (2, 2));
+        assert_eq!(rgba(&buffer, 0, 0)[3], 0);
+        assert_eq!(rgba(&buffer, 1, 0), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn test_decode_bc3() {
+        let mut data = header(4, 4, DDPF_FOURCC, b"DXT5");
+        // Alpha palette from 255 to 0 with the first pixel using a0 and the second a1
+        data.extend_from_slice(&[255, 0, 0b0000_1000, 0, 0, 0, 0, 0]);
+        data.extend_from_slice(&0x07E0u16.to_le_bytes());
+        data.extend_from_slice(&0x07E0u16.to_le_bytes());
+        data.extend_from_slice(&[0, 0, 0, 0]);
+
+        let buffer = decode_dds(data.as_slice()).unwrap().buffer;
+
+        assert_eq!(rgba(&buffer, 0, 0), [0, 255, 0, 255]);
+        assert_eq!(rgba(&buffer, 1, 0)[3], 0);
+        assert_eq!(rgba(&buffer, 2, 0)[3], 255);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let buffer = PixelBuffer::new_from_raw(
+            3,
+            2,
+            vec![
+                Rgb::RED,
+                Rgb::GREEN,
+                Rgb::BLUE,
+                Rgb::WHITE,
+                Rgb::BLACK,
+                Rgb::NONE,
+            ],
+        );
+
+        let mut data = vec![];
+        encode_dds(&mut data, &buffer).unwrap();
+
+        assert_eq!(data.len(), 4 + HEADER_SIZE + 3 * 2 * 4);
+
+        let result = decode_dds(data.as_slice()).unwrap().buffer;
+        assert_eq!((result.width(), result.height()), (3, 2));
+
+        for (x, y, _) in buffer.enumerate() {
+            let expected = rgba(&buffer, x, y);
+            let res = rgba(&result, x, y);
+
+            assert!(expected
+                .iter()
+                .zip(res.iter())
+                .all(|(a, b)| a.abs_diff(*b) <= 1));
+        }
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let mut data = header(4, 4, DDPF_FOURCC, b"DX10");
+        data.extend_from_slice(&[0; 16]);
+
+        assert!(matches!(
+            decode_dds(data.as_slice()),
+            Err(DecodingError::Unsupported { .. })
+        ));
+
+        let data = header(4, 4, DDPF_FOURCC, b"DXT1");
+        assert!(matches!(
+            decode_dds(data.as_slice()),
+            Err(DecodingError::InvalidData { .. })
+        ));
+    }
+}
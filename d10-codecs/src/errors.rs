@@ -1,75 +1,200 @@
 use std::error::Error;
-use std::fmt;
 use std::io::Error as IoError;
 
-#[derive(Debug)]
+use thiserror::Error;
+
+use crate::{Format, PaletteFormat};
+
+/// Boxed error of the underlying codec library
+pub type CodecError = Box<dyn Error + Send + Sync + 'static>;
+
+#[derive(Debug, Error)]
 pub enum DecodingError {
+    #[error("Bad file extension: {0}")]
     BadFileExtension(String),
+    #[error("Unknown format")]
     UnknownFormat,
+    #[error("Unsupported buffer size for image: {width}x{height}")]
     InvalidBufferSize { width: u32, height: u32 },
-    Decoding(String),
-    IoError(IoError),
+    #[error("Unsupported {format} image: {message}")]
+    Unsupported { format: Format, message: String },
+    #[error("Invalid {format} data: {message}")]
+    InvalidData { format: Format, message: String },
+    #[error("Error decoding {format} image: {source}")]
+    Codec {
+        format: Format,
+        #[source]
+        source: CodecError,
+    },
+    #[error(transparent)]
+    IoError(#[from] IoError),
 }
 
-impl fmt::Display for DecodingError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl DecodingError {
+    pub(crate) fn codec<E>(format: Format, err: E) -> DecodingError
+    where
+        E: Into<CodecError>,
+    {
+        DecodingError::Codec {
+            format,
+            source: err.into(),
+        }
+    }
+
+    /// Stable identifier of the kind of error
+    pub fn code(&self) -> &'static str {
         use DecodingError::*;
         match self {
-            BadFileExtension(path) => write!(f, "Bad file extension: {}", path),
-            UnknownFormat => write!(f, "Unknown format"),
-            InvalidBufferSize { width, height } => {
-                write!(f, "Unsupported buffer size for image: {}x{}", width, height)
-            }
-            Decoding(message) => write!(f, "{}", message),
-            IoError(err) => err.fmt(f),
+            BadFileExtension(_) => "bad_file_extension",
+            UnknownFormat => "unknown_format",
+            InvalidBufferSize { .. } => "invalid_buffer_size",
+            Unsupported { .. } => "unsupported",
+            InvalidData { .. } => "invalid_data",
+            Codec { .. } => "codec",
+            IoError(_) => "io",
         }
     }
-}
-
-impl Error for DecodingError {}
 
-impl From<IoError> for DecodingError {
-    fn from(err: IoError) -> DecodingError {
-        DecodingError::IoError(err)
+    /// The format of the image if the error happened inside of a decoder
+    pub fn format(&self) -> Option<Format> {
+        use DecodingError::*;
+        match self {
+            Unsupported { format, .. } | InvalidData { format, .. } | Codec { format, .. } => {
+                Some(*format)
+            }
+            _ => None,
+        }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum EncodingError {
+    #[error("Bad file extension: {0}")]
     BadFileExtension(String),
+    #[error("Image dimensions not supported by format {format}: {width}x{height}")]
     BadDimensions {
-        format: &'static str,
+        format: Format,
         width: u32,
         height: u32,
     },
-    Encoding(String),
-    IoError(IoError),
+    #[error("Invalid {format} encoder configuration: {message}")]
+    InvalidConfig { format: Format, message: String },
+    #[error("Error encoding {format} image: {source}")]
+    Codec {
+        format: Format,
+        #[source]
+        source: CodecError,
+    },
+    #[error("Smallest encoded {format} image has {size} bytes, limit is {max_bytes}")]
+    TargetSizeNotReached {
+        format: Format,
+        max_bytes: usize,
+        size: usize,
+    },
+    #[error("Wrong number of rows written to {format} stream: expected {expected}, got {written}")]
+    RowCount {
+        format: Format,
+        expected: u32,
+        written: u32,
+    },
+    #[error(transparent)]
+    IoError(#[from] IoError),
 }
 
-impl fmt::Display for EncodingError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl EncodingError {
+    pub(crate) fn codec<E>(format: Format, err: E) -> EncodingError
+    where
+        E: Into<CodecError>,
+    {
+        EncodingError::Codec {
+            format,
+            source: err.into(),
+        }
+    }
+
+    /// Stable identifier of the kind of error
+    pub fn code(&self) -> &'static str {
+        use EncodingError::*;
+        match self {
+            BadFileExtension(_) => "bad_file_extension",
+            BadDimensions { .. } => "bad_dimensions",
+            InvalidConfig { .. } => "invalid_config",
+            Codec { .. } => "codec",
+            TargetSizeNotReached { .. } => "target_size_not_reached",
+            RowCount { .. } => "row_count",
+            IoError(_) => "io",
+        }
+    }
+
+    /// The format of the image if the error happened inside of an encoder
+    pub fn format(&self) -> Option<Format> {
         use EncodingError::*;
         match self {
-            BadFileExtension(path) => write!(f, "Bad file extension: {}", path),
-            BadDimensions {
-                format,
-                width,
-                height,
-            } => write!(
-                f,
-                "Image dimensions not supported by format {}: {}x{}",
-                format, width, height
-            ),
-            Encoding(message) => write!(f, "{}", message),
-            IoError(err) => err.fmt(f),
+            BadDimensions { format, .. }
+            | InvalidConfig { format, .. }
+            | Codec { format, .. }
+            | TargetSizeNotReached { format, .. }
+            | RowCount { format, .. } => Some(*format),
+            _ => None,
         }
     }
 }
 
-impl Error for EncodingError {}
+#[derive(Debug, Error)]
+pub enum PaletteError {
+    #[error("Bad palette file extension: {0}")]
+    BadFileExtension(String),
+    #[error("Unsupported {format} palette: {message}")]
+    Unsupported {
+        format: PaletteFormat,
+        message: String,
+    },
+    #[error("Invalid {format} palette: {message}")]
+    InvalidData {
+        format: PaletteFormat,
+        message: String,
+    },
+    #[error(transparent)]
+    IoError(#[from] IoError),
+}
+
+impl PaletteError {
+    /// Stable identifier of the kind of error
+    pub fn code(&self) -> &'static str {
+        use PaletteError::*;
+        match self {
+            BadFileExtension(_) => "bad_file_extension",
+            Unsupported { .. } => "unsupported",
+            InvalidData { .. } => "invalid_data",
+            IoError(_) => "io",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source() {
+        let err = DecodingError::codec(Format::Png, "broken chunk");
+
+        assert_eq!(err.code(), "codec");
+        assert_eq!(err.format(), Some(Format::Png));
+        assert_eq!(err.to_string(), "Error decoding png image: broken chunk");
+        assert_eq!(err.source().unwrap().to_string(), "broken chunk");
+
+        let err = EncodingError::BadDimensions {
+            format: Format::Jpeg,
+            width: 70000,
+            height: 1,
+        };
 
-impl From<IoError> for EncodingError {
-    fn from(err: IoError) -> EncodingError {
-        EncodingError::IoError(err)
+        assert_eq!(err.code(), "bad_dimensions");
+        assert!(err.source().is_none());
+        assert_eq!(
+            err.to_string(),
+            "Image dimensions not supported by format jpeg: 70000x1"
+        );
     }
 }
@@ -0,0 +1,210 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+
+const TAG_THUMBNAIL_OFFSET: u16 = 0x0201;
+const TAG_THUMBNAIL_LENGTH: u16 = 0x0202;
+
+/// Read the embedded thumbnail from the exif data of a jpeg file
+///
+/// The reader is positioned somewhere inside the file afterwards.
+pub(crate) fn read_jpeg_exif_thumbnail<T>(reader: &mut T) -> io::Result<Option<Vec<u8>>>
+where
+    T: Read + Seek,
+{
+    let mut marker = [0u8; 2];
+
+    reader.read_exact(&mut marker)?;
+    if marker != [0xFF, 0xD8] {
+        return Ok(None);
+    }
+
+    loop {
+        reader.read_exact(&mut marker)?;
+
+        // Skip fill bytes
+        while marker[0] == 0xFF && marker[1] == 0xFF {
+            reader.read_exact(&mut marker[1..])?;
+        }
+
+        // The exif data is always stored before the image data starts
+        if marker[0] != 0xFF || marker[1] == 0xDA || marker[1] == 0xD9 {
+            return Ok(None);
+        }
+
+        let mut len = [0u8; 2];
+        reader.read_exact(&mut len)?;
+        let len = (u16::from_be_bytes(len) as usize).saturating_sub(2);
+
+        if marker[1] == 0xE1 {
+            let mut data = vec![0u8; len];
+            reader.read_exact(&mut data)?;
+
+            if let Some(tiff) = data.strip_prefix(EXIF_HEADER) {
+                return Ok(find_thumbnail(tiff).map(|thumbnail| thumbnail.to_vec()));
+            }
+        } else {
+            reader.seek(SeekFrom::Current(len as i64))?;
+        }
+    }
+}
+
+/// Find the jpeg thumbnail referenced by the second IFD of tiff structured data
+fn find_thumbnail(tiff: &[u8]) -> Option<&[u8]> {
+    let big_endian = match tiff.get(0..2)? {
+        b"II" => false,
+        b"MM" => true,
+        _ => return None,
+    };
+
+    let read_u16 = |pos: usize| -> Option<u16> {
+        let bytes = tiff.get(pos..pos + 2)?.try_into().ok()?;
+        Some(if big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    };
+
+    let read_u32 = |pos: usize| -> Option<u32> {
+        let bytes = tiff.get(pos..pos + 4)?.try_into().ok()?;
+        Some(if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    };
+
+    if read_u16(2)? != 42 {
+        return None;
+    }
+
+    let ifd0 = read_u32(4)? as usize;
+    let ifd0_entries = read_u16(ifd0)? as usize;
+
+    let ifd1 = read_u32(ifd0 + 2 + ifd0_entries * 12)? as usize;
+    if ifd1 == 0 {
+        return None;
+    }
+
+    let mut offset = None;
+    let mut length = None;
+
+    for i in 0..read_u16(ifd1)? as usize {
+        let entry = ifd1 + 2 + i * 12;
+
+        match read_u16(entry)? {
+            TAG_THUMBNAIL_OFFSET => offset = Some(read_u32(entry + 8)? as usize),
+            TAG_THUMBNAIL_LENGTH => length = Some(read_u32(entry + 8)? as usize),
+            _ => {}
+        }
+    }
+
+    let offset = offset?;
+
+    tiff.get(offset..offset.checked_add(length?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use d10_core::color::Rgb;
+    use d10_core::pixelbuffer::PixelBuffer;
+
+    use super::*;
+    use crate::{decode_thumbnail, encode, EncodingFormat};
+
+    /// Create a little endian tiff structure with an empty IFD0 and the thumbnail in IFD1
+    fn create_tiff(thumbnail: &[u8]) -> Vec<u8> {
+        let mut tiff = b"II".to_vec();
+        tiff.extend_from_slice(&42u16.to_le_bytes());
+        tiff.extend_from_slice(&8u32.to_le_bytes());
+
+        // IFD0 without entries
+        tiff.extend_from_slice(&0u16.to_le_bytes());
+        tiff.extend_from_slice(&14u32.to_le_bytes());
+
+        // IFD1 with offset and length of the thumbnail
+        let data_offset = 14 + 2 + 2 * 12 + 4;
+        tiff.extend_from_slice(&2u16.to_le_bytes());
+        for (tag, value) in [
+            (TAG_THUMBNAIL_OFFSET, data_offset),
+            (TAG_THUMBNAIL_LENGTH, thumbnail.len() as u32),
+        ] {
+            tiff.extend_from_slice(&tag.to_le_bytes());
+            tiff.extend_from_slice(&4u16.to_le_bytes());
+            tiff.extend_from_slice(&1u32.to_le_bytes());
+            tiff.extend_from_slice(&value.to_le_bytes());
+        }
+        tiff.extend_from_slice(&0u32.to_le_bytes());
+
+        tiff.extend_from_slice(thumbnail);
+        tiff
+    }
+
+    #[test]
+    fn test_find_thumbnail() {
+        let tiff = create_tiff(&[1, 2, 3, 4]);
+        assert_eq!(find_thumbnail(&tiff), Some(&[1u8, 2, 3, 4][..]));
+
+        assert_eq!(find_thumbnail(&tiff[..tiff.len() - 1]), None);
+        assert_eq!(find_thumbnail(b"MM\0\x2a"), None);
+        assert_eq!(find_thumbnail(&[]), None);
+    }
+
+    #[test]
+    fn test_read_jpeg_exif_thumbnail() {
+        let mut data = vec![0xFF, 0xD8];
+
+        // Unrelated APP0 segment
+        data.extend_from_slice(&[0xFF, 0xE0, 0, 4, 0, 0]);
+
+        let mut exif = EXIF_HEADER.to_vec();
+        exif.extend(create_tiff(&[5, 6, 7]));
+
+        data.extend_from_slice(&[0xFF, 0xE1]);
+        data.extend_from_slice(&(exif.len() as u16 + 2).to_be_bytes());
+        data.extend(exif);
+        data.extend_from_slice(&[0xFF, 0xDA]);
+
+        let thumbnail = read_jpeg_exif_thumbnail(&mut Cursor::new(data)).unwrap();
+        assert_eq!(thumbnail, Some(vec![5, 6, 7]));
+
+        let data = [0xFF, 0xD8, 0xFF, 0xDA];
+        let thumbnail = read_jpeg_exif_thumbnail(&mut Cursor::new(data)).unwrap();
+        assert_eq!(thumbnail, None);
+    }
+
+    #[test]
+    fn test_decode_thumbnail_from_exif() {
+        let encode_jpeg = |width, height, color| {
+            let mut data = vec![];
+            let buffer = PixelBuffer::new_with_color(width, height, color);
+            encode(&mut data, &buffer, EncodingFormat::jpeg_default()).unwrap();
+            data
+        };
+
+        let thumbnail = encode_jpeg(16, 12, Rgb::RED);
+
+        let mut exif = EXIF_HEADER.to_vec();
+        exif.extend(create_tiff(&thumbnail));
+
+        // Insert the exif segment right after the SOI marker
+        let main = encode_jpeg(64, 48, Rgb::BLUE);
+        let mut data = main[..2].to_vec();
+        data.extend_from_slice(&[0xFF, 0xE1]);
+        data.extend_from_slice(&(exif.len() as u16 + 2).to_be_bytes());
+        data.extend(exif);
+        data.extend_from_slice(&main[2..]);
+
+        let res = decode_thumbnail(Cursor::new(&data), 16).unwrap().buffer;
+        assert_eq!((res.width(), res.height()), (16, 12));
+        assert!(res.get_pixel(8, 6).red() > 0.9);
+
+        // The embedded thumbnail is too small
+        let res = decode_thumbnail(Cursor::new(&data), 20).unwrap().buffer;
+        assert_eq!((res.width(), res.height()), (32, 24));
+        assert!(res.get_pixel(8, 6).blue() > 0.9);
+    }
+}
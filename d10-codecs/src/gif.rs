@@ -1,44 +1,279 @@
 use d10_core::color::{Color, Rgb, Srgb};
+use d10_core::errors::ParseEnumError;
+use d10_core::palette::Palette;
 use d10_core::pixelbuffer::{is_valid_buffer_size, PixelBuffer};
+use d10_core::region::Region;
 
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::io::{BufRead, Read, Seek, Write};
+use std::str::FromStr;
+use std::time::Duration;
 
-use crate::utils::{from_u8, to_rgba8_vec};
-use crate::{DecodedImage, DecodingError, EncodingError};
+use crate::utils::{changed_rect, from_u8};
+use crate::{AnimationFrame, DecodedAnimation, DecodedImage, DecodingError, EncodingError, Format};
 
+use color_quant::NeuQuant;
 use gif::{
-    DecodeOptions, DecodingError as GIFDecodingError, Encoder, EncodingError as GIFEncodingError,
-    Frame,
+    DecodeOptions, DecodingError as GIFDecodingError, DisposalMethod, Encoder,
+    EncodingError as GIFEncodingError, Frame, Repeat,
 };
 
+/// Maximal number of colors in the palette of a gif
+const MAX_COLORS: usize = 256;
+
+/// Sampling factor of the color quantizer from 1 (best quality) to 30 (fastest)
+const QUANTIZER_SPEED: i32 = 10;
+
+const BAYER_8X8: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// Dithering used when mapping the colors of an image to the palette of a gif
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GifDither {
+    /// Use the nearest color, which results in banding of smooth gradients
+    None,
+    /// Error diffusion that gives the best looking results for photos
+    FloydSteinberg,
+    /// Regular pattern that compresses better and doesn't flicker in animations
+    Ordered,
+}
+
+impl FromStr for GifDither {
+    type Err = ParseEnumError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        use GifDither::*;
+        match value {
+            "none" => Ok(None),
+            "floyd_steinberg" | "default" => Ok(FloydSteinberg),
+            "ordered" => Ok(Ordered),
+            _ => Err(ParseEnumError::new(value, "GifDither")),
+        }
+    }
+}
+
 fn encode_error(err: GIFEncodingError) -> EncodingError {
     match err {
         GIFEncodingError::Io(err) => EncodingError::IoError(err),
-        err => EncodingError::Encoding(err.to_string()),
+        err => EncodingError::codec(Format::Gif, err),
     }
 }
 
-pub(crate) fn encode_gif<W>(w: W, buffer: &PixelBuffer<Rgb>) -> Result<(), EncodingError>
-where
-    W: Write,
-{
-    let width = buffer.width();
-    let height = buffer.height();
+fn to_srgb8(color: &Rgb) -> [u8; 3] {
+    let c = color.to_srgb();
+    [c.red(), c.green(), c.blue()].map(|v| (v * 255.0).round().clamp(0.0, 255.0) as u8)
+}
+
+/// Use the exact colors if possible and a NeuQuant based palette otherwise
+fn compute_palette(colors: &[[u8; 3]], max_colors: usize) -> Vec<[u8; 3]> {
+    let unique: HashSet<[u8; 3]> = colors.iter().copied().collect();
+
+    if unique.len() <= max_colors {
+        let mut palette: Vec<[u8; 3]> = unique.into_iter().collect();
+        palette.sort_unstable();
+        return palette;
+    }
+
+    let rgba: Vec<u8> = colors
+        .iter()
+        .flat_map(|[r, g, b]| [*r, *g, *b, 255])
+        .collect();
+
+    NeuQuant::new(QUANTIZER_SPEED, max_colors, &rgba)
+        .color_map_rgb()
+        .chunks_exact(3)
+        .map(|c| [c[0], c[1], c[2]])
+        .collect()
+}
+
+fn nearest(palette: &[[f32; 3]], color: [f32; 3]) -> usize {
+    let mut result = 0;
+    let mut min_distance = f32::MAX;
+
+    for (i, p) in palette.iter().enumerate() {
+        let distance: f32 = p
+            .iter()
+            .zip(color.iter())
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum();
+
+        if distance < min_distance {
+            min_distance = distance;
+            result = i;
+        }
+    }
+
+    result
+}
+
+/// Map the colors to the indices of the palette
+///
+/// `None` marks transparent pixels which don't take part in the error diffusion.
+fn map_to_palette(
+    colors: &[Option<[u8; 3]>],
+    width: usize,
+    palette: &[[u8; 3]],
+    dither: GifDither,
+    transparent: u8,
+) -> Vec<u8> {
+    let palette: Vec<[f32; 3]> = palette.iter().map(|c| c.map(|v| v as f32)).collect();
+
+    // Half the distance between neighboring colors of an evenly distributed palette
+    let spread = 255.0 / (palette.len() as f32).cbrt().max(1.0);
 
+    let mut errors = vec![[0.0f32; 3]; width + 2];
+    let mut next_errors = vec![[0.0f32; 3]; width + 2];
+    let mut out = Vec::with_capacity(colors.len());
+
+    for (y, row) in colors.chunks(width.max(1)).enumerate() {
+        for (x, color) in row.iter().enumerate() {
+            let Some(color) = color else {
+                out.push(transparent);
+                continue;
+            };
+
+            let mut value = color.map(|v| v as f32);
+
+            match dither {
+                GifDither::None => {}
+                GifDither::FloydSteinberg => {
+                    for (v, e) in value.iter_mut().zip(errors[x + 1]) {
+                        *v = (*v + e).clamp(0.0, 255.0);
+                    }
+                }
+                GifDither::Ordered => {
+                    let threshold = (BAYER_8X8[y % 8][x % 8] as f32 + 0.5) / 64.0 - 0.5;
+                    for v in value.iter_mut() {
+                        *v += threshold * spread;
+                    }
+                }
+            }
+
+            let index = nearest(&palette, value);
+            out.push(index as u8);
+
+            if dither == GifDither::FloydSteinberg {
+                for c in 0..3 {
+                    let error = value[c] - palette[index][c];
+                    errors[x + 2][c] += error * 7.0 / 16.0;
+                    next_errors[x][c] += error * 3.0 / 16.0;
+                    next_errors[x + 1][c] += error * 5.0 / 16.0;
+                    next_errors[x + 2][c] += error / 16.0;
+                }
+            }
+        }
+
+        std::mem::swap(&mut errors, &mut next_errors);
+        next_errors.fill([0.0; 3]);
+    }
+
+    out
+}
+
+fn check_dimensions(width: u32, height: u32) -> Result<(), EncodingError> {
     if width > u16::MAX as u32 || height > u16::MAX as u32 {
         return Err(EncodingError::BadDimensions {
-            format: "gif",
+            format: Format::Gif,
             width,
             height,
         });
     }
 
-    let width = width as u16;
-    let height = height as u16;
+    Ok(())
+}
+
+/// Colors of the pixels with `None` for pixels below the transparency threshold
+fn to_colors(buffer: &PixelBuffer<Rgb>, transparency_threshold: f32) -> Vec<Option<[u8; 3]>> {
+    buffer
+        .data()
+        .iter()
+        .map(|c| (c.alpha() >= transparency_threshold).then(|| to_srgb8(c)))
+        .collect()
+}
+
+/// Create a frame with its own palette where `None` marks transparent pixels
+fn create_frame(
+    colors: &[Option<[u8; 3]>],
+    width: u32,
+    height: u32,
+    palette: Option<&Palette>,
+    dither: GifDither,
+) -> Result<Frame<'static>, EncodingError> {
+    let has_transparency = colors.iter().any(|c| c.is_none());
+    let max_colors = if has_transparency {
+        MAX_COLORS - 1
+    } else {
+        MAX_COLORS
+    };
+
+    let mut palette: Vec<[u8; 3]> = match palette {
+        Some(palette) => {
+            if palette.is_empty() || palette.len() > max_colors {
+                return Err(EncodingError::InvalidConfig {
+                    format: Format::Gif,
+                    message: format!(
+                        "Palette must have between 1 and {} colors, got {}",
+                        max_colors,
+                        palette.len()
+                    ),
+                });
+            }
+
+            palette.colors().iter().map(to_srgb8).collect()
+        }
+        None => {
+            let opaque: Vec<[u8; 3]> = colors.iter().flatten().copied().collect();
+            compute_palette(&opaque, max_colors)
+        }
+    };
+
+    if palette.is_empty() {
+        palette.push([0, 0, 0]);
+    }
+
+    let transparent = palette.len() as u8;
+    let indices = map_to_palette(colors, width as usize, &palette, dither, transparent);
 
-    let mut raw = to_rgba8_vec(buffer);
+    if has_transparency {
+        palette.push([0, 0, 0]);
+    }
 
-    let frame = Frame::from_rgba_speed(width, height, &mut raw, 10);
+    Ok(Frame {
+        width: width as u16,
+        height: height as u16,
+        buffer: Cow::Owned(indices),
+        palette: Some(palette.into_iter().flatten().collect()),
+        transparent: has_transparency.then_some(transparent),
+        ..Frame::default()
+    })
+}
+
+pub(crate) fn encode_gif<W>(
+    w: W,
+    buffer: &PixelBuffer<Rgb>,
+    palette: Option<&Palette>,
+    dither: GifDither,
+    transparency_threshold: f32,
+) -> Result<(), EncodingError>
+where
+    W: Write,
+{
+    let width = buffer.width();
+    let height = buffer.height();
+
+    check_dimensions(width, height)?;
+
+    let colors = to_colors(buffer, transparency_threshold);
+    let frame = create_frame(&colors, width, height, palette, dither)?;
 
     let mut encoder = Encoder::new(w, frame.width, frame.height, &[]).map_err(encode_error)?;
 
@@ -47,10 +282,125 @@ where
     Ok(())
 }
 
+/// Encode fully composed frames of the same size as animated gif
+///
+/// Every frame after the first one only stores the area that changed since the previous frame with unchanged pixels
+/// marked as transparent. Because a kept pixel can't become transparent again, a frame before one
+/// that reveals transparent areas is stored completely and disposed to the background.
+pub(crate) fn encode_gif_animation<W>(
+    w: W,
+    frames: &[(&PixelBuffer<Rgb>, Duration)],
+    loop_count: u32,
+    palette: Option<&Palette>,
+    dither: GifDither,
+    transparency_threshold: f32,
+) -> Result<(), EncodingError>
+where
+    W: Write,
+{
+    let Some((first, _)) = frames.first() else {
+        return Err(EncodingError::InvalidConfig {
+            format: Format::Gif,
+            message: "Animation has no frames".to_owned(),
+        });
+    };
+
+    let width = first.width();
+    let height = first.height();
+
+    check_dimensions(width, height)?;
+
+    if let Some((buffer, _)) = frames
+        .iter()
+        .find(|(b, _)| b.width() != width || b.height() != height)
+    {
+        return Err(EncodingError::InvalidConfig {
+            format: Format::Gif,
+            message: format!(
+                "All frames must have a size of {}x{}, got {}x{}",
+                width,
+                height,
+                buffer.width(),
+                buffer.height()
+            ),
+        });
+    }
+
+    let colors: Vec<_> = frames
+        .iter()
+        .map(|(buffer, _)| to_colors(buffer, transparency_threshold))
+        .collect();
+
+    // Whether a frame turns visible pixels of the previous frame transparent
+    let reveals_transparency: Vec<bool> = colors
+        .iter()
+        .enumerate()
+        .map(|(i, current)| {
+            i > 0
+                && current
+                    .iter()
+                    .zip(&colors[i - 1])
+                    .any(|(c, p)| c.is_none() && p.is_some())
+        })
+        .collect();
+
+    let mut encoder = Encoder::new(w, width as u16, height as u16, &[]).map_err(encode_error)?;
+
+    match loop_count {
+        0 => encoder.set_repeat(Repeat::Infinite).map_err(encode_error)?,
+        1 => {}
+        count => encoder
+            .set_repeat(Repeat::Finite((count - 1).min(u16::MAX as u32) as u16))
+            .map_err(encode_error)?,
+    }
+
+    let cleared = vec![None; (width * height) as usize];
+
+    for (i, (current, (_, delay))) in colors.iter().zip(frames).enumerate() {
+        let previous = if i == 0 || reveals_transparency[i] {
+            &cleared
+        } else {
+            &colors[i - 1]
+        };
+
+        let dispose_to_background = reveals_transparency.get(i + 1) == Some(&true);
+
+        // The first frame is always complete because decoders of still images only read it
+        let rect = if i == 0 || dispose_to_background {
+            Region::new(0, 0, width, height)
+        } else {
+            changed_rect(width, height, |i| current[i] != previous[i])
+                .unwrap_or(Region::new(0, 0, 1, 1))
+        };
+
+        let mut changed = Vec::with_capacity((rect.width * rect.height) as usize);
+        for y in rect.y..rect.y + rect.height {
+            for pos in (y * width + rect.x)..(y * width + rect.x + rect.width) {
+                let pos = pos as usize;
+                changed.push(current[pos].filter(|_| current[pos] != previous[pos]));
+            }
+        }
+
+        let mut frame = create_frame(&changed, rect.width, rect.height, palette, dither)?;
+        frame.left = rect.x as u16;
+        frame.top = rect.y as u16;
+        frame.delay = (delay.as_millis() / 10).min(u16::MAX as u128) as u16;
+        frame.dispose = if dispose_to_background {
+            DisposalMethod::Background
+        } else {
+            DisposalMethod::Keep
+        };
+
+        encoder.write_frame(&frame).map_err(encode_error)?;
+    }
+
+    Ok(())
+}
+
 fn decode_error(err: GIFDecodingError) -> DecodingError {
     match err {
         GIFDecodingError::Io(err) => DecodingError::IoError(err),
-        err => DecodingError::Decoding(err.to_string()),
+        err => DecodingError::codec(Format::Gif, err),
     }
 }
 
@@ -90,6 +440,81 @@ where
 
         Ok(DecodedImage { buffer })
     } else {
-        Err(DecodingError::Decoding("No frame found".to_owned()))
+        Err(DecodingError::InvalidData {
+            format: Format::Gif,
+            message: "No frame found".to_owned(),
+        })
+    }
+}
+
+/// Decode all frames of a gif composed onto the canvas of the logical screen
+pub(crate) fn decode_gif_animation<T>(reader: T) -> Result<DecodedAnimation, DecodingError>
+where
+    T: Read + Seek + BufRead,
+{
+    let mut decoder = DecodeOptions::new();
+
+    decoder.set_color_output(gif::ColorOutput::RGBA);
+
+    let mut decoder = decoder.read_info(reader).map_err(decode_error)?;
+
+    let width = decoder.width() as u32;
+    let height = decoder.height() as u32;
+
+    if !is_valid_buffer_size(width, height) {
+        return Err(DecodingError::InvalidBufferSize { width, height });
+    }
+
+    let mut canvas = PixelBuffer::new_with_color(width, height, Rgb::NONE);
+    let mut frames = vec![];
+
+    while let Some(frame) = decoder.read_next_frame().map_err(decode_error)? {
+        let restore = (frame.dispose == DisposalMethod::Previous).then(|| canvas.clone());
+
+        let rect = Region::new(
+            frame.left as u32,
+            frame.top as u32,
+            frame.width as u32,
+            frame.height as u32,
+        );
+
+        for (i, chunk) in frame.buffer.chunks_exact(4).enumerate() {
+            let x = rect.x + i as u32 % rect.width;
+            let y = rect.y + i as u32 / rect.width;
+
+            if chunk[3] != 0 && x < width && y < height {
+                let color = Srgb::new(from_u8(chunk[0]), from_u8(chunk[1]), from_u8(chunk[2]));
+                canvas.put_pixel(x, y, color.to_rgb());
+            }
+        }
+
+        frames.push(AnimationFrame {
+            buffer: canvas.clone(),
+            delay: Duration::from_millis(frame.delay as u64 * 10),
+        });
+
+        match frame.dispose {
+            DisposalMethod::Background => canvas.fill_rect(rect, Rgb::NONE),
+            DisposalMethod::Previous => {
+                if let Some(restore) = restore {
+                    canvas = restore;
+                }
+            }
+            DisposalMethod::Any | DisposalMethod::Keep => {}
+        }
     }
+
+    if frames.is_empty() {
+        return Err(DecodingError::InvalidData {
+            format: Format::Gif,
+            message: "No frame found".to_owned(),
+        });
+    }
+
+    let loop_count = match decoder.repeat() {
+        Repeat::Infinite => 0,
+        Repeat::Finite(count) => count as u32 + 1,
+    };
+
+    Ok(DecodedAnimation { frames, loop_count })
 }
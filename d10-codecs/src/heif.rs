@@ -0,0 +1,72 @@
+use d10_core::color::{Color, Srgb};
+use d10_core::pixelbuffer::{is_valid_buffer_size, PixelBuffer};
+
+use std::io::Read;
+
+use libheif_rs::{ColorSpace, HeifContext, HeifError, LibHeif, RgbChroma};
+
+use crate::utils::from_u8;
+use crate::{DecodedImage, DecodingError, Format};
+
+fn decode_error(err: HeifError) -> DecodingError {
+    DecodingError::codec(Format::Heif, err)
+}
+
+/// Decode the primary image of a heif/heic file
+///
+/// Rotation, mirroring and cropping stored in the file are already applied by libheif.
+pub(crate) fn decode_heif<T>(mut reader: T) -> Result<DecodedImage, DecodingError>
+where
+    T: Read,
+{
+    let mut data = vec![];
+    reader.read_to_end(&mut data)?;
+
+    let context = HeifContext::read_from_bytes(&data).map_err(decode_error)?;
+    let handle = context.primary_image_handle().map_err(decode_error)?;
+
+    let width = handle.width();
+    let height = handle.height();
+
+    if !is_valid_buffer_size(width, height) {
+        return Err(DecodingError::InvalidBufferSize { width, height });
+    }
+
+    let (chroma, bytes_per_pixel) = if handle.has_alpha_channel() {
+        (RgbChroma::Rgba, 4)
+    } else {
+        (RgbChroma::Rgb, 3)
+    };
+
+    let image = LibHeif::new()
+        .decode(&handle, ColorSpace::Rgb(chroma), None)
+        .map_err(decode_error)?;
+
+    let planes = image.planes();
+    let plane = planes
+        .interleaved
+        .ok_or_else(|| DecodingError::InvalidData {
+            format: Format::Heif,
+            message: "Missing interleaved rgb plane".to_owned(),
+        })?;
+
+    let width = plane.width;
+    let height = plane.height;
+
+    let mut raw = Vec::with_capacity(width as usize * height as usize);
+
+    for row in plane.data.chunks(plane.stride).take(height as usize) {
+        for c in row[..width as usize * bytes_per_pixel].chunks_exact(bytes_per_pixel) {
+            let alpha = if bytes_per_pixel == 4 { c[3] } else { 255 };
+
+            raw.push(
+                Srgb::new_with_alpha(from_u8(c[0]), from_u8(c[1]), from_u8(c[2]), from_u8(alpha))
+                    .to_rgb(),
+            );
+        }
+    }
+
+    Ok(DecodedImage {
+        buffer: PixelBuffer::new_from_raw(width, height, raw),
+    })
+}
@@ -1,15 +1,15 @@
 use std::io::{BufRead, Read, Seek, Write};
 use std::str::FromStr;
 
-use image::codecs::ico::{IcoDecoder, IcoEncoder};
-use image::{ColorType, DynamicImage, ImageEncoder, ImageError};
+use image::codecs::ico::{IcoDecoder, IcoEncoder, IcoFrame};
+use image::{ColorType, DynamicImage, ImageError};
 
 use d10_core::color::Rgb;
 use d10_core::errors::ParseEnumError;
 use d10_core::pixelbuffer::PixelBuffer;
 
 use crate::utils::*;
-use crate::{DecodedImage, DecodingError, EncodingError};
+use crate::{DecodedImage, DecodingError, EncodingError, Format};
 
 #[derive(Copy, Clone, Debug)]
 pub enum IcoColorType {
@@ -34,6 +34,13 @@ impl FromStr for IcoColorType {
     }
 }
 
+fn encoding_error(err: ImageError) -> EncodingError {
+    match err {
+        ImageError::IoError(err) => EncodingError::IoError(err),
+        err => EncodingError::codec(Format::Ico, err),
+    }
+}
+
 pub(crate) fn encode_ico<W>(
     w: W,
     buffer: &PixelBuffer<Rgb>,
@@ -42,23 +49,36 @@ pub(crate) fn encode_ico<W>(
 where
     W: Write,
 {
-    let (out, color_type) = match color_type {
-        IcoColorType::L8 => (to_l8_vec(buffer), ColorType::L8),
-        IcoColorType::La8 => (to_la8_vec(buffer), ColorType::La8),
-        IcoColorType::Rgb8 => (to_rgb8_vec(buffer), ColorType::Rgb8),
-        IcoColorType::Rgba8 => (to_rgba8_vec(buffer), ColorType::Rgba8),
-    };
+    encode_ico_frames(w, &[buffer], color_type)
+}
+
+/// Encode an ico file with one png encoded entry per buffer
+pub(crate) fn encode_ico_frames<W>(
+    w: W,
+    buffers: &[&PixelBuffer<Rgb>],
+    color_type: IcoColorType,
+) -> Result<(), EncodingError>
+where
+    W: Write,
+{
+    let frames = buffers
+        .iter()
+        .map(|buffer| {
+            let (out, color_type) = match color_type {
+                IcoColorType::L8 => (to_l8_vec(buffer.data()), ColorType::L8),
+                IcoColorType::La8 => (to_la8_vec(buffer.data()), ColorType::La8),
+                IcoColorType::Rgb8 => (to_rgb8_vec(buffer.data()), ColorType::Rgb8),
+                IcoColorType::Rgba8 => (to_rgba8_vec(buffer.data()), ColorType::Rgba8),
+            };
 
-    if let Err(err) =
-        IcoEncoder::new(w).write_image(&out, buffer.width(), buffer.height(), color_type)
-    {
-        Err(match err {
-            ImageError::IoError(err) => EncodingError::IoError(err),
-            err => EncodingError::Encoding(err.to_string()),
+            IcoFrame::as_png(&out, buffer.width(), buffer.height(), color_type)
         })
-    } else {
-        Ok(())
-    }
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(encoding_error)?;
+
+    IcoEncoder::new(w)
+        .encode_images(&frames)
+        .map_err(encoding_error)
 }
 
 pub(crate) fn decode_ico<T>(reader: T) -> Result<DecodedImage, DecodingError>
@@ -67,13 +87,15 @@ where
 {
     let decoder = IcoDecoder::new(reader).map_err(|err| match err {
         ImageError::IoError(err) => DecodingError::IoError(err),
-        err => DecodingError::Decoding(err.to_string()),
+        err => DecodingError::codec(Format::Ico, err),
     })?;
 
+    check_decoder_size(&decoder)?;
+
     let img = DynamicImage::from_decoder(decoder).map_err(|err| match err {
         ImageError::IoError(err) => DecodingError::IoError(err),
-        err => DecodingError::Decoding(err.to_string()),
+        err => DecodingError::codec(Format::Ico, err),
     })?;
 
-    read_into_buffer(img).map(|buffer| DecodedImage { buffer })
+    read_into_buffer(img, Format::Ico).map(|buffer| DecodedImage { buffer })
 }
@@ -2,14 +2,21 @@ use d10_core::color::{Color, Rgb, Srgb};
 use d10_core::errors::ParseEnumError;
 use d10_core::pixelbuffer::PixelBuffer;
 
-use std::io::{BufRead, Read, Seek, Write};
+use std::cell::RefCell;
+use std::io::{BufRead, Cursor, Read, Seek, SeekFrom, Write};
 use std::str::FromStr;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
 
-use jpeg_decoder::{Decoder, Error as DecoderError, PixelFormat};
-use jpeg_encoder::{ColorType, Encoder, EncodingError as JpegEncodingError, SamplingFactor};
+use jpeg_decoder::{CodingProcess, Decoder, Error as DecoderError, PixelFormat};
+use jpeg_encoder::{
+    rgb_to_ycbcr, ColorType, Encoder, EncodingError as JpegEncodingError, ImageBuffer,
+    JpegColorType, SamplingFactor,
+};
 
-use crate::utils::{cmyk_to_rgb, from_u16_ne, from_u8, to_l8_vec, to_rgb8_vec};
-use crate::{DecodedImage, DecodingError, EncodingError};
+use crate::exif::read_jpeg_exif_thumbnail;
+use crate::utils::{check_rows, cmyk_to_rgb, from_u16_ne, from_u8, to_l8_vec, to_rgb8_vec};
+use crate::{DecodedImage, DecodingError, EncodingError, Format};
 
 #[allow(non_camel_case_types)]
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
@@ -130,16 +137,16 @@ where
 
     if width > u16::MAX as u32 || height > u16::MAX as u32 {
         return Err(EncodingError::BadDimensions {
-            format: "jpeg",
+            format: Format::Jpeg,
             width,
             height,
         });
     }
 
     let (out, color_type) = if grayscale {
-        (to_l8_vec(buffer), ColorType::Luma)
+        (to_l8_vec(buffer.data()), ColorType::Luma)
     } else {
-        (to_rgb8_vec(buffer), ColorType::Rgb)
+        (to_rgb8_vec(buffer.data()), ColorType::Rgb)
     };
 
     // Ensure quality is always in the valid range.
@@ -159,30 +166,312 @@ where
         encoder.set_optimized_huffman_tables(true);
     }
 
-    if let Err(err) = encoder.encode(&out, width as u16, height as u16, color_type) {
-        Err(match err {
-            JpegEncodingError::IoError(err) => EncodingError::IoError(err),
-            err => EncodingError::Encoding(err.to_string()),
+    encoder
+        .encode(&out, width as u16, height as u16, color_type)
+        .map_err(encode_error)
+}
+
+fn encode_error(err: JpegEncodingError) -> EncodingError {
+    match err {
+        JpegEncodingError::IoError(err) => EncodingError::IoError(err),
+        err => EncodingError::codec(Format::Jpeg, err),
+    }
+}
+
+/// Number of rows buffered between a `JpegStreamEncoder` and its encoder thread
+const STREAM_BUFFER_ROWS: usize = 64;
+
+/// Rows sent by a `JpegStreamEncoder` as the image for the encoder thread
+struct RowReceiver {
+    receiver: Receiver<Vec<u8>>,
+    /// Index and data of the last received row
+    current: RefCell<(Option<u16>, Vec<u8>)>,
+    width: u16,
+    height: u16,
+    grayscale: bool,
+}
+
+impl ImageBuffer for RowReceiver {
+    fn get_jpeg_color_type(&self) -> JpegColorType {
+        if self.grayscale {
+            JpegColorType::Luma
+        } else {
+            JpegColorType::Ycbcr
+        }
+    }
+
+    fn width(&self) -> u16 {
+        self.width
+    }
+
+    fn height(&self) -> u16 {
+        self.height
+    }
+
+    fn fill_buffers(&self, y: u16, buffers: &mut [Vec<u8>; 4]) {
+        let mut current = self.current.borrow_mut();
+
+        // Rows are requested in order with the last row being repeated to fill incomplete blocks
+        while current.0.is_none_or(|index| index < y) {
+            let index = current.0.map_or(0, |index| index + 1);
+
+            // The stream encoder was dropped without writing all rows
+            let row = self.receiver.recv().unwrap_or_else(|_| {
+                let channels = if self.grayscale { 1 } else { 3 };
+                vec![0; self.width as usize * channels]
+            });
+
+            *current = (Some(index), row);
+        }
+
+        if self.grayscale {
+            buffers[0].extend_from_slice(&current.1);
+        } else {
+            for pixel in current.1.chunks_exact(3) {
+                let (y, cb, cr) = rgb_to_ycbcr(pixel[0], pixel[1], pixel[2]);
+                buffers[0].push(y);
+                buffers[1].push(cb);
+                buffers[2].push(cr);
+            }
+        }
+    }
+}
+
+/// Jpeg encoder that accepts the image row by row
+///
+/// This allows writing images that never exist completely in memory, e.g. when they are rendered in tiles.
+/// The encoding runs on a separate thread which owns the writer until `finish()` returns it.
+/// Dropping the encoder before all rows were written leaves an incomplete image in the writer.
+pub struct JpegStreamEncoder<W> {
+    sender: Option<SyncSender<Vec<u8>>>,
+    handle: Option<JoinHandle<Result<W, EncodingError>>>,
+    width: u32,
+    height: u32,
+    rows_written: u32,
+    grayscale: bool,
+}
+
+impl<W: Write + Send + 'static> JpegStreamEncoder<W> {
+    /// Start a baseline jpeg encoder for `height` rows of `width` pixels
+    pub fn new(
+        w: W,
+        width: u32,
+        height: u32,
+        quality: u8,
+        grayscale: bool,
+    ) -> Result<JpegStreamEncoder<W>, EncodingError> {
+        if width == 0 || height == 0 || width > u16::MAX as u32 || height > u16::MAX as u32 {
+            return Err(EncodingError::BadDimensions {
+                format: Format::Jpeg,
+                width,
+                height,
+            });
+        }
+
+        let (sender, receiver) = sync_channel(STREAM_BUFFER_ROWS);
+
+        let image = RowReceiver {
+            receiver,
+            current: RefCell::new((None, vec![])),
+            width: width as u16,
+            height: height as u16,
+            grayscale,
+        };
+
+        let quality = quality.clamp(1, 100);
+
+        let handle = thread::spawn(move || {
+            let mut w = w;
+            Encoder::new(&mut w, quality)
+                .encode_image(image)
+                .map_err(encode_error)?;
+            Ok(w)
+        });
+
+        Ok(JpegStreamEncoder {
+            sender: Some(sender),
+            handle: Some(handle),
+            width,
+            height,
+            rows_written: 0,
+            grayscale,
         })
-    } else {
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Number of rows that were written so far
+    pub fn rows_written(&self) -> u32 {
+        self.rows_written
+    }
+
+    /// Write one or more complete rows
+    ///
+    /// The length of `rows` must be a multiple of the image width.
+    pub fn write_rows(&mut self, rows: &[Rgb]) -> Result<(), EncodingError> {
+        check_rows(
+            Format::Jpeg,
+            rows.len(),
+            self.width,
+            self.height,
+            self.rows_written,
+        )?;
+
+        let sender = match &self.sender {
+            Some(sender) => sender,
+            None => return self.join().map(|_| ()),
+        };
+
+        for row in rows.chunks(self.width as usize) {
+            let data = if self.grayscale {
+                to_l8_vec(row)
+            } else {
+                to_rgb8_vec(row)
+            };
+
+            // Sending only fails if the encoder thread stopped because of an error
+            if sender.send(data).is_err() {
+                self.sender = None;
+                return self.join().map(|_| ());
+            }
+
+            self.rows_written += 1;
+        }
+
         Ok(())
     }
+
+    /// Wait for the encoder to finish after all rows were written and return the writer
+    pub fn finish(mut self) -> Result<W, EncodingError> {
+        if self.rows_written != self.height {
+            return Err(EncodingError::RowCount {
+                format: Format::Jpeg,
+                expected: self.height,
+                written: self.rows_written,
+            });
+        }
+
+        self.sender = None;
+        self.join()
+    }
+
+    fn join(&mut self) -> Result<W, EncodingError> {
+        let handle = match self.handle.take() {
+            Some(handle) => handle,
+            None => {
+                return Err(EncodingError::InvalidConfig {
+                    format: Format::Jpeg,
+                    message: "Encoder already stopped".to_owned(),
+                })
+            }
+        };
+
+        match handle.join() {
+            Ok(result) => result,
+            Err(err) => std::panic::resume_unwind(err),
+        }
+    }
+}
+
+fn decode_error(err: DecoderError) -> DecodingError {
+    match err {
+        DecoderError::Io(err) => DecodingError::IoError(err),
+        err => DecodingError::codec(Format::Jpeg, err),
+    }
 }
 
 pub(crate) fn decode_jpeg<T>(reader: T) -> Result<DecodedImage, DecodingError>
 where
     T: Read + Seek + BufRead,
 {
+    decode_frame(Decoder::new(reader))
+}
+
+/// Decode a downscaled version of a jpeg with at least `max_edge` pixels on its longer side
+///
+/// The embedded exif thumbnail is used if it's large enough.
+/// Otherwise the image is decoded with a DCT scaling of 1/2, 1/4 or 1/8.
+pub(crate) fn decode_jpeg_thumbnail<T>(
+    mut reader: T,
+    max_edge: u32,
+) -> Result<DecodedImage, DecodingError>
+where
+    T: Read + Seek + BufRead,
+{
+    // Broken exif data is ignored because the image itself might still be valid
+    if let Ok(Some(data)) = read_jpeg_exif_thumbnail(&mut reader) {
+        if let Ok(thumbnail) = decode_jpeg(Cursor::new(data)) {
+            if thumbnail.buffer.width().max(thumbnail.buffer.height()) >= max_edge {
+                return Ok(thumbnail);
+            }
+        }
+    }
+
+    reader.seek(SeekFrom::Start(0))?;
+
     let mut decoder = Decoder::new(reader);
 
-    let data = decoder.decode().map_err(|err| match err {
-        DecoderError::Io(err) => DecodingError::IoError(err),
-        err => DecodingError::Decoding(err.to_string()),
-    })?;
+    let size = max_edge.min(u16::MAX as u32) as u16;
+    decoder.scale(size, size).map_err(decode_error)?;
+
+    decode_frame(decoder)
+}
 
-    let info = decoder
+/// Decode the stages of a progressive jpeg
+///
+/// The decoder doesn't expose the individual scans. Instead the first stage is built from the
+/// DC coefficients only (a DCT scaling of 1/8), which is what the first scan of a progressive jpeg contains.
+/// It's followed by the fully decoded image. Baseline jpegs only result in the full image.
+pub(crate) fn decode_jpeg_passes<T>(mut reader: T) -> Result<Vec<DecodedImage>, DecodingError>
+where
+    T: Read + Seek + BufRead,
+{
+    let mut decoder = Decoder::new(&mut reader);
+    decoder.read_info().map_err(decode_error)?;
+
+    let progressive = decoder
         .info()
-        .ok_or_else(|| DecodingError::Decoding("Missing jpeg info".to_owned()))?;
+        .is_some_and(|info| info.coding_process == CodingProcess::DctProgressive);
+
+    reader.seek(SeekFrom::Start(0))?;
+
+    if !progressive {
+        return Ok(vec![decode_jpeg(reader)?]);
+    }
+
+    let mut decoder = Decoder::new(&mut reader);
+    decoder.scale(1, 1).map_err(decode_error)?;
+    let dc = decode_frame(decoder)?.buffer;
+
+    reader.seek(SeekFrom::Start(0))?;
+    let full = decode_jpeg(reader)?;
+
+    let width = full.buffer.width();
+    let height = full.buffer.height();
+
+    let preview = PixelBuffer::new_from_func(width, height, |x, y| {
+        *dc.get_pixel((x / 8).min(dc.width() - 1), (y / 8).min(dc.height() - 1))
+    });
+
+    Ok(vec![DecodedImage { buffer: preview }, full])
+}
+
+fn decode_frame<T>(mut decoder: Decoder<T>) -> Result<DecodedImage, DecodingError>
+where
+    T: Read,
+{
+    let data = decoder.decode().map_err(decode_error)?;
+
+    let info = decoder.info().ok_or_else(|| DecodingError::InvalidData {
+        format: Format::Jpeg,
+        message: "Missing jpeg info".to_owned(),
+    })?;
 
     let width = info.width as u32;
     let height = info.height as u32;
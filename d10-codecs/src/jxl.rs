@@ -0,0 +1,133 @@
+use std::io::{Read, Write};
+
+use d10_core::color::{Color, Rgb, Srgb};
+use d10_core::pixelbuffer::{is_valid_buffer_size, PixelBuffer};
+
+use jxl_oxide::{EnumColourEncoding, JxlImage, PixelFormat, RenderingIntent};
+use zune_core::bit_depth::BitDepth;
+use zune_core::colorspace::ColorSpace;
+use zune_core::options::EncoderOptions;
+use zune_jpegxl::JxlSimpleEncoder;
+
+use crate::utils::to_rgba8_vec;
+use crate::{DecodedImage, DecodingError, EncodingError, Format};
+
+/// Decode the first frame of a jpeg xl file
+///
+/// Colors are converted to sRGB and the orientation stored in the file is already applied.
+pub(crate) fn decode_jxl<T>(reader: T) -> Result<DecodedImage, DecodingError>
+where
+    T: Read,
+{
+    let mut image = JxlImage::builder()
+        .read(reader)
+        .map_err(|err| DecodingError::codec(Format::Jxl, err))?;
+
+    let width = image.width();
+    let height = image.height();
+
+    if !is_valid_buffer_size(width, height) {
+        return Err(DecodingError::InvalidBufferSize { width, height });
+    }
+
+    if image.image_header().metadata.grayscale() {
+        image.request_color_encoding(EnumColourEncoding::gray_srgb(RenderingIntent::Relative));
+    } else {
+        image.request_color_encoding(EnumColourEncoding::srgb(RenderingIntent::Relative));
+    }
+
+    let pixel_format = image.pixel_format();
+    if pixel_format.has_black() {
+        return Err(DecodingError::Unsupported {
+            format: Format::Jxl,
+            message: "Cmyk images are not supported".to_owned(),
+        });
+    }
+
+    if image.num_loaded_keyframes() == 0 {
+        return Err(DecodingError::InvalidData {
+            format: Format::Jxl,
+            message: "Missing image frame".to_owned(),
+        });
+    }
+
+    let render = image
+        .render_frame(0)
+        .map_err(|err| DecodingError::codec(Format::Jxl, err))?;
+
+    let mut stream = render.stream();
+    let width = stream.width();
+    let height = stream.height();
+    let channels = stream.channels() as usize;
+
+    let mut samples = vec![0.0f32; width as usize * height as usize * channels];
+    stream.write_to_buffer(&mut samples);
+
+    let raw = samples
+        .chunks_exact(channels)
+        .map(|c| {
+            let color = match pixel_format {
+                PixelFormat::Gray => Srgb::new(c[0], c[0], c[0]),
+                PixelFormat::Graya => Srgb::new_with_alpha(c[0], c[0], c[0], c[1]),
+                PixelFormat::Rgba => Srgb::new_with_alpha(c[0], c[1], c[2], c[3]),
+                _ => Srgb::new(c[0], c[1], c[2]),
+            };
+            color.to_rgb()
+        })
+        .collect();
+
+    Ok(DecodedImage {
+        buffer: PixelBuffer::new_from_raw(width, height, raw),
+    })
+}
+
+/// Encode a lossless jpeg xl file with 8 bits per channel
+///
+/// `effort` uses the range of libjxl between 1 and 9 and is clamped into it.
+pub(crate) fn encode_jxl<W>(
+    w: W,
+    buffer: &PixelBuffer<Rgb>,
+    effort: u8,
+    lossless: bool,
+) -> Result<(), EncodingError>
+where
+    W: Write,
+{
+    if !lossless {
+        return Err(EncodingError::InvalidConfig {
+            format: Format::Jxl,
+            message: "Only lossless encoding is supported".to_owned(),
+        });
+    }
+
+    let width = buffer.width();
+    let height = buffer.height();
+
+    // The encoder rejects images that are only a single pixel wide or high
+    if width < 2 || height < 2 {
+        return Err(EncodingError::BadDimensions {
+            format: Format::Jxl,
+            width,
+            height,
+        });
+    }
+
+    let data = to_rgba8_vec(buffer.data());
+
+    // zune-jpegxl samples more rows for its histograms with efforts up to 127
+    let effort = (u16::from(effort.clamp(1, 9)) * 127 / 9) as u8;
+
+    let options = EncoderOptions::new(
+        width as usize,
+        height as usize,
+        ColorSpace::RGBA,
+        BitDepth::Eight,
+    )
+    .set_effort(effort);
+
+    JxlSimpleEncoder::new(&data, options)
+        .encode(w)
+        .map_err(|err| EncodingError::codec(Format::Jxl, err))?;
+
+    Ok(())
+}
@@ -1,29 +1,64 @@
+use std::fmt::{self, Display};
 use std::fs::File;
 use std::io::{BufRead, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
 use std::path::Path;
+use std::time::Duration;
 
 use d10_core::color::Rgb;
+use d10_core::palette::Palette;
 use d10_core::pixelbuffer::PixelBuffer;
 
+#[cfg(feature = "async")]
+pub use crate::async_io::{decode_async, encode_async};
 pub use crate::bmp::BmpColorType;
 use crate::bmp::{decode_bmp, encode_bmp};
+use crate::dds::{decode_dds, encode_dds};
 pub use crate::errors::*;
-use crate::gif::{decode_gif, encode_gif};
+pub use crate::gif::GifDither;
+use crate::gif::{decode_gif, decode_gif_animation, encode_gif, encode_gif_animation};
+#[cfg(feature = "heif")]
+use crate::heif::decode_heif;
 pub use crate::ico::IcoColorType;
-use crate::ico::{decode_ico, encode_ico};
-pub use crate::jpeg::JpegSamplingFactor;
-use crate::jpeg::{decode_jpeg, encode_jpeg};
-use crate::png::{decode_png, encode_png};
-pub use crate::png::{PngColorType, PngCompression, PngFilterType};
+use crate::ico::{decode_ico, encode_ico, encode_ico_frames};
+use crate::jpeg::{decode_jpeg, decode_jpeg_passes, decode_jpeg_thumbnail, encode_jpeg};
+pub use crate::jpeg::{JpegSamplingFactor, JpegStreamEncoder};
+#[cfg(feature = "jxl")]
+use crate::jxl::{decode_jxl, encode_jxl};
+#[cfg(feature = "mmap")]
+pub use crate::mmap::{decode_mmap, encode_mmap};
+use crate::ora::{decode_ora, decode_ora_layers};
+pub use crate::palette::{
+    decode_palette, encode_palette, load_palette, save_palette, PaletteFormat,
+};
+use crate::png::{
+    decode_png, decode_png_animation, decode_png_passes, decode_png_thumbnail, encode_png,
+    encode_png_animation, encode_png_indexed,
+};
+pub use crate::png::{PngColorType, PngCompression, PngFilterType, PngStreamEncoder};
+use crate::psd::{decode_psd, decode_psd_layers};
+use crate::utils::dither_for_export;
 pub use crate::webp::WebPPreset;
 use crate::webp::{decode_webp, encode_webp};
 
+#[cfg(feature = "async")]
+mod async_io;
 mod bmp;
+mod dds;
 mod errors;
+mod exif;
 mod gif;
+#[cfg(feature = "heif")]
+mod heif;
 mod ico;
 mod jpeg;
+#[cfg(feature = "jxl")]
+mod jxl;
+#[cfg(feature = "mmap")]
+mod mmap;
+mod ora;
+mod palette;
 mod png;
+mod psd;
 mod utils;
 mod webp;
 
@@ -35,19 +70,69 @@ pub enum Format {
     Bmp,
     Ico,
     WebP,
+    Dds,
+    /// Heif and heic images which can only be decoded with the `heif` feature
+    Heif,
+    /// Jpeg XL images which can only be decoded and encoded with the `jxl` feature
+    Jxl,
+    /// Photoshop documents which can only be decoded
+    Psd,
+    /// OpenRaster images which can only be encoded with their layers by `encode_ora()`
+    Ora,
 }
 
 impl Format {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Jpeg => "jpeg",
+            Format::Png => "png",
+            Format::Gif => "gif",
+            Format::Bmp => "bmp",
+            Format::Ico => "ico",
+            Format::WebP => "webp",
+            Format::Dds => "dds",
+            Format::Heif => "heif",
+            Format::Jxl => "jxl",
+            Format::Psd => "psd",
+            Format::Ora => "ora",
+        }
+    }
+
     pub fn from_path(path: &Path) -> Option<Format> {
         let ext = path.extension()?.to_string_lossy().to_ascii_lowercase();
 
         match ext.as_str() {
             "jpg" | "jpeg" => Some(Self::Jpeg),
-            "png" => Some(Self::Png),
+            "png" | "apng" => Some(Self::Png),
             "gif" => Some(Self::Gif),
             "bmp" => Some(Self::Bmp),
             "ico" => Some(Self::Ico),
             "webp" => Some(Self::WebP),
+            "dds" => Some(Self::Dds),
+            "heic" | "heif" => Some(Self::Heif),
+            "jxl" => Some(Self::Jxl),
+            "psd" => Some(Self::Psd),
+            "ora" => Some(Self::Ora),
+            _ => None,
+        }
+    }
+
+    /// Get the format from a mime type like the content type of a http response
+    pub fn from_mime_type(mime_type: &str) -> Option<Format> {
+        let mime_type = mime_type.split(';').next()?.trim().to_ascii_lowercase();
+
+        match mime_type.as_str() {
+            "image/jpeg" | "image/jpg" | "image/pjpeg" => Some(Self::Jpeg),
+            "image/png" | "image/apng" => Some(Self::Png),
+            "image/gif" => Some(Self::Gif),
+            "image/bmp" | "image/x-bmp" | "image/x-ms-bmp" => Some(Self::Bmp),
+            "image/x-icon" | "image/vnd.microsoft.icon" => Some(Self::Ico),
+            "image/webp" => Some(Self::WebP),
+            "image/vnd-ms.dds" | "image/x-dds" => Some(Self::Dds),
+            "image/heic" | "image/heif" => Some(Self::Heif),
+            "image/jxl" => Some(Self::Jxl),
+            "image/vnd.adobe.photoshop" | "application/x-photoshop" => Some(Self::Psd),
+            "image/openraster" => Some(Self::Ora),
             _ => None,
         }
     }
@@ -56,7 +141,8 @@ impl Format {
     where
         T: Read + Seek,
     {
-        let mut buf = [0u8; 12];
+        // OpenRaster needs the most bytes to find its mimetype inside of the zip archive
+        let mut buf = [0u8; 54];
 
         let len = reader.read(&mut buf)?;
 
@@ -69,13 +155,46 @@ impl Format {
             [0x47, 0x49, 0x46, 0x38, 0x39, 0x61, ..] => Ok(Format::Gif),
             [0x42, 0x4D, ..] => Ok(Format::Bmp),
             [0x00, 0x00, 0x01, 0x00, ..] => Ok(Format::Ico),
-            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P'] => Ok(Format::WebP),
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Ok(Format::WebP),
+            [b'D', b'D', b'S', b' ', ..] => Ok(Format::Dds),
+            [_, _, _, _, b'f', b't', b'y', b'p', b1, b2, b3, b4, ..]
+                if is_heif_brand([b1, b2, b3, b4]) =>
+            {
+                Ok(Format::Heif)
+            }
+            [0xFF, 0x0A, ..] => Ok(Format::Jxl),
+            [0x00, 0x00, 0x00, 0x0C, b'J', b'X', b'L', b' ', 0x0D, 0x0A, 0x87, 0x0A, ..] => {
+                Ok(Format::Jxl)
+            }
+            [b'8', b'B', b'P', b'S', ..] => Ok(Format::Psd),
+            [b'P', b'K', 0x03, 0x04, ref header @ ..] if is_ora_header(header) => Ok(Format::Ora),
 
             _ => Err(DecodingError::UnknownFormat),
         }
     }
 }
 
+/// Major brands of the ftyp box used by heif and heic files
+fn is_heif_brand(brand: [u8; 4]) -> bool {
+    matches!(
+        &brand,
+        b"heic" | b"heix" | b"hevc" | b"hevx" | b"heim" | b"heis" | b"mif1" | b"msf1"
+    )
+}
+
+/// Check for the uncompressed mimetype file at the start of OpenRaster archives
+fn is_ora_header(header: &[u8]) -> bool {
+    header.len() >= 50
+        && &header[26..34] == b"mimetype"
+        && header[34..].starts_with(ora::MIME_TYPE.as_bytes())
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum EncodingFormat {
     Jpeg {
@@ -89,8 +208,18 @@ pub enum EncodingFormat {
         color_type: PngColorType,
         compression: PngCompression,
         filter: PngFilterType,
+        /// Write the image with Adam7 interlacing
+        interlaced: bool,
+        /// Write an indexed image with these colors instead of using `color_type`
+        palette: Option<Palette>,
+    },
+    Gif {
+        /// Colors to use instead of a palette computed from the image
+        palette: Option<Palette>,
+        dither: GifDither,
+        /// Pixels with a lower alpha value become fully transparent
+        transparency_threshold: f32,
     },
-    Gif,
     Bmp {
         color_type: BmpColorType,
     },
@@ -101,6 +230,17 @@ pub enum EncodingFormat {
         quality: u8,
         preset: WebPPreset,
     },
+    /// Uncompressed 32 bit BGRA texture
+    Dds,
+    /// Jpeg XL which can only be encoded with the `jxl` feature
+    Jxl {
+        /// Quality of lossy encoding which isn't supported by the encoder yet
+        quality: u8,
+        /// Encoder effort between 1 and 9 where higher values compress better but slower
+        effort: u8,
+        /// Only lossless encoding is supported, lossy encoding returns an error
+        lossless: bool,
+    },
 }
 
 impl EncodingFormat {
@@ -108,13 +248,36 @@ impl EncodingFormat {
         match self {
             EncodingFormat::Jpeg { .. } => Format::Jpeg,
             EncodingFormat::Png { .. } => Format::Png,
-            EncodingFormat::Gif => Format::Gif,
+            EncodingFormat::Gif { .. } => Format::Gif,
             EncodingFormat::Bmp { .. } => Format::Bmp,
             EncodingFormat::Ico { .. } => Format::Ico,
             EncodingFormat::WebP { .. } => Format::WebP,
+            EncodingFormat::Dds => Format::Dds,
+            EncodingFormat::Jxl { .. } => Format::Jxl,
         }
     }
 
+    /// The quality setting of lossy formats
+    pub fn quality(&self) -> Option<u8> {
+        match self {
+            EncodingFormat::Jpeg { quality, .. }
+            | EncodingFormat::WebP { quality, .. }
+            | EncodingFormat::Jxl { quality, .. } => Some(*quality),
+            _ => None,
+        }
+    }
+
+    /// Returns the format with a changed quality setting. Formats without quality are returned unchanged.
+    pub fn with_quality(mut self, new_quality: u8) -> Self {
+        match &mut self {
+            EncodingFormat::Jpeg { quality, .. }
+            | EncodingFormat::WebP { quality, .. }
+            | EncodingFormat::Jxl { quality, .. } => *quality = new_quality,
+            _ => {}
+        }
+        self
+    }
+
     pub fn jpeg_default() -> Self {
         Self::Jpeg {
             quality: 85,
@@ -140,11 +303,25 @@ impl EncodingFormat {
             color_type: PngColorType::Rgba8,
             compression: PngCompression::Default,
             filter: PngFilterType::Sub,
+            interlaced: false,
+            palette: None,
         }
     }
 
     pub fn gif_default() -> Self {
-        Self::Gif
+        Self::Gif {
+            palette: None,
+            dither: GifDither::FloydSteinberg,
+            transparency_threshold: 0.5,
+        }
+    }
+
+    pub fn gif_with_palette(palette: Palette) -> Self {
+        Self::Gif {
+            palette: Some(palette),
+            dither: GifDither::FloydSteinberg,
+            transparency_threshold: 0.5,
+        }
     }
 
     pub fn bmp_default() -> Self {
@@ -177,6 +354,18 @@ impl EncodingFormat {
         Self::WebP { quality, preset }
     }
 
+    pub fn dds_default() -> Self {
+        Self::Dds
+    }
+
+    pub fn jxl_default() -> Self {
+        Self::Jxl {
+            quality: 90,
+            effort: 7,
+            lossless: true,
+        }
+    }
+
     pub fn from_path(path: &Path) -> Result<EncodingFormat, EncodingError> {
         match Format::from_path(path) {
             Some(Format::Jpeg) => Ok(EncodingFormat::jpeg_default()),
@@ -185,6 +374,26 @@ impl EncodingFormat {
             Some(Format::Bmp) => Ok(EncodingFormat::bmp_default()),
             Some(Format::Ico) => Ok(EncodingFormat::ico_default()),
             Some(Format::WebP) => Ok(EncodingFormat::webp_default()),
+            Some(Format::Dds) => Ok(EncodingFormat::dds_default()),
+            Some(Format::Heif) => Err(EncodingError::InvalidConfig {
+                format: Format::Heif,
+                message: "Encoding heif images is not supported".to_owned(),
+            }),
+            #[cfg(feature = "jxl")]
+            Some(Format::Jxl) => Ok(EncodingFormat::jxl_default()),
+            #[cfg(not(feature = "jxl"))]
+            Some(Format::Jxl) => Err(EncodingError::InvalidConfig {
+                format: Format::Jxl,
+                message: "Built without the jxl feature".to_owned(),
+            }),
+            Some(Format::Psd) => Err(EncodingError::InvalidConfig {
+                format: Format::Psd,
+                message: "Encoding psd images is not supported".to_owned(),
+            }),
+            Some(Format::Ora) => Err(EncodingError::InvalidConfig {
+                format: Format::Ora,
+                message: "Ora images can only be encoded with their layers".to_owned(),
+            }),
             None => Err(EncodingError::BadFileExtension(
                 path.to_string_lossy().to_string(),
             )),
@@ -192,10 +401,87 @@ impl EncodingFormat {
     }
 }
 
+/// Metadata handling and integer conversion of the encoders
+///
+/// None of the encoders writes metadata like exif or icc profiles yet, so every image is currently
+/// saved without any metadata. These options define what encoders may write once it's supported.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct EncodeOptions {
+    /// Remove all metadata that isn't explicitly kept, e.g. to get rid of gps locations
+    pub strip_metadata: bool,
+    /// Keep the icc profile even if `strip_metadata` is set
+    pub keep_icc: bool,
+    /// Keep exif data even if `strip_metadata` is set
+    pub keep_exif: bool,
+    /// Dither the conversion into integer channel values instead of rounding to the nearest value
+    ///
+    /// This avoids banding in smooth gradients at the cost of slight noise.
+    /// Gif uses its own dithering configured with `EncodingFormat::Gif`.
+    pub dither: bool,
+}
+
+impl EncodeOptions {
+    /// Options to remove all metadata
+    pub fn strip() -> Self {
+        Self {
+            strip_metadata: true,
+            keep_icc: false,
+            keep_exif: false,
+            dither: false,
+        }
+    }
+
+    /// Whether an icc profile may be written
+    pub fn write_icc(&self) -> bool {
+        !self.strip_metadata || self.keep_icc
+    }
+
+    /// Whether exif data may be written
+    pub fn write_exif(&self) -> bool {
+        !self.strip_metadata || self.keep_exif
+    }
+}
+
 pub struct DecodedImage {
     pub buffer: PixelBuffer<Rgb>,
 }
 
+/// A single raster layer of a layered image
+pub struct ImageLayer {
+    pub name: String,
+    pub buffer: PixelBuffer<Rgb>,
+    /// Position of the top left corner on the canvas which might be negative
+    pub x: i32,
+    pub y: i32,
+    /// Opacity between 0.0 and 1.0
+    pub opacity: f32,
+    pub visible: bool,
+    /// Four character blend mode key as used by psd, e.g. `norm`, `mul ` or `scrn`
+    pub blend_mode: String,
+}
+
+/// The layers of an image with the bottom layer first
+pub struct LayeredImage {
+    /// Size of the canvas
+    pub width: u32,
+    pub height: u32,
+    pub layers: Vec<ImageLayer>,
+}
+
+/// A single frame of an animation covering the whole canvas
+pub struct AnimationFrame {
+    pub buffer: PixelBuffer<Rgb>,
+    /// How long the frame is shown
+    pub delay: Duration,
+}
+
+/// The fully composed frames of an animation
+pub struct DecodedAnimation {
+    pub frames: Vec<AnimationFrame>,
+    /// Number of times the animation is played with 0 meaning forever
+    pub loop_count: u32,
+}
+
 pub fn decode_file<P>(path: P) -> Result<DecodedImage, DecodingError>
 where
     P: AsRef<Path>,
@@ -219,6 +505,129 @@ pub fn decode_buffer(buffer: &[u8]) -> Result<DecodedImage, DecodingError> {
     decode(reader, format)
 }
 
+/// Decode a buffer using `hint` as the format if it can't be detected from the content
+pub fn decode_buffer_with_hint(
+    buffer: &[u8],
+    hint: Option<Format>,
+) -> Result<DecodedImage, DecodingError> {
+    let mut reader = Cursor::new(buffer);
+
+    match (Format::from_reader(&mut reader), hint) {
+        (Ok(format), _) | (Err(DecodingError::UnknownFormat), Some(format)) => {
+            decode(reader, format)
+        }
+        (Err(err), _) => Err(err),
+    }
+}
+
+/// Decode a small preview of an image with at least `max_edge` pixels on its longer side
+///
+/// Depending on the format this is much faster than decoding the full image:
+/// - Jpeg: Uses the embedded exif thumbnail if it's large enough or decodes with DCT scaling
+/// - Png: Decodes only the first passes of interlaced images
+///
+/// All other formats and images smaller than `max_edge` are fully decoded.
+/// The result isn't resized to an exact size and will usually be larger than `max_edge`.
+pub fn decode_thumbnail<T>(reader: T, max_edge: u32) -> Result<DecodedImage, DecodingError>
+where
+    T: Read + Seek,
+{
+    let mut reader = BufReader::new(reader);
+    let format = Format::from_reader(&mut reader)?;
+
+    match format {
+        Format::Jpeg => decode_jpeg_thumbnail(reader, max_edge),
+        Format::Png => decode_png_thumbnail(reader, max_edge),
+        format => decode(reader, format),
+    }
+}
+
+/// Decode the intermediate stages of an interlaced png or progressive jpeg, e.g. for progressive previews
+///
+/// Every stage has the full image size with missing pixels filled from their neighbors:
+/// - Png: One stage per Adam7 pass
+/// - Jpeg: A blocky preview from the DC coefficients followed by the full image
+///
+/// The last stage is always the fully decoded image.
+/// All other formats, baseline jpegs and non interlaced pngs only result in the full image.
+pub fn decode_progressive<T>(reader: T) -> Result<Vec<DecodedImage>, DecodingError>
+where
+    T: Read + Seek,
+{
+    let mut reader = BufReader::new(reader);
+    let format = Format::from_reader(&mut reader)?;
+
+    match format {
+        Format::Jpeg => decode_jpeg_passes(reader),
+        Format::Png => decode_png_passes(reader),
+        format => Ok(vec![decode(reader, format)?]),
+    }
+}
+
+/// Decode the individual layers of a layered image like psd or ora
+///
+/// Images without layers, including all formats that don't support them, result in a single
+/// visible layer named `Background` containing the whole image.
+pub fn decode_layers<T>(reader: T) -> Result<LayeredImage, DecodingError>
+where
+    T: Read + Seek,
+{
+    let mut reader = BufReader::new(reader);
+    let format = Format::from_reader(&mut reader)?;
+
+    let layers = match format {
+        Format::Psd => Some(decode_psd_layers(&mut reader)?),
+        Format::Ora => Some(decode_ora_layers(&mut reader)?),
+        _ => None,
+    };
+
+    if let Some(layers) = layers.filter(|layers| !layers.layers.is_empty()) {
+        return Ok(layers);
+    }
+
+    reader.seek(SeekFrom::Start(0))?;
+
+    let buffer = decode(reader, format)?.buffer;
+
+    Ok(LayeredImage {
+        width: buffer.width(),
+        height: buffer.height(),
+        layers: vec![ImageLayer {
+            name: "Background".to_owned(),
+            buffer,
+            x: 0,
+            y: 0,
+            opacity: 1.0,
+            visible: true,
+            blend_mode: "norm".to_owned(),
+        }],
+    })
+}
+
+/// Decode all frames of an animated gif or APNG
+///
+/// Every frame is composed onto the frames before it, so all frames have the size of the canvas.
+/// All other formats result in a single frame without delay.
+pub fn decode_animation<T>(reader: T) -> Result<DecodedAnimation, DecodingError>
+where
+    T: Read + Seek,
+{
+    let mut reader = BufReader::new(reader);
+    let format = Format::from_reader(&mut reader)?;
+
+    match format {
+        Format::Gif => decode_gif_animation(reader),
+        Format::Png => decode_png_animation(reader),
+        format => Ok(DecodedAnimation {
+            frames: vec![AnimationFrame {
+                buffer: decode(reader, format)?.buffer,
+                delay: Duration::ZERO,
+            }],
+            loop_count: 0,
+        }),
+    }
+}
+
 fn decode<T>(reader: T, format: Format) -> Result<DecodedImage, DecodingError>
 where
     T: Read + Seek + BufRead,
@@ -230,6 +639,23 @@ where
         Format::Bmp => decode_bmp(reader),
         Format::Ico => decode_ico(reader),
         Format::WebP => decode_webp(reader),
+        Format::Dds => decode_dds(reader),
+        #[cfg(feature = "heif")]
+        Format::Heif => decode_heif(reader),
+        #[cfg(not(feature = "heif"))]
+        Format::Heif => Err(DecodingError::Unsupported {
+            format: Format::Heif,
+            message: "Built without the heif feature".to_owned(),
+        }),
+        #[cfg(feature = "jxl")]
+        Format::Jxl => decode_jxl(reader),
+        #[cfg(not(feature = "jxl"))]
+        Format::Jxl => Err(DecodingError::Unsupported {
+            format: Format::Jxl,
+            message: "Built without the jxl feature".to_owned(),
+        }),
+        Format::Psd => decode_psd(reader),
+        Format::Ora => decode_ora(reader),
     }
 }
 
@@ -238,6 +664,18 @@ pub fn encode_to_file<P>(
     buffer: &PixelBuffer<Rgb>,
     format: Option<EncodingFormat>,
 ) -> Result<(), EncodingError>
+where
+    P: AsRef<Path>,
+{
+    encode_to_file_with_options(path, buffer, format, &EncodeOptions::default())
+}
+
+pub fn encode_to_file_with_options<P>(
+    path: P,
+    buffer: &PixelBuffer<Rgb>,
+    format: Option<EncodingFormat>,
+    options: &EncodeOptions,
+) -> Result<(), EncodingError>
 where
     P: AsRef<Path>,
 {
@@ -248,7 +686,57 @@ where
 
     let mut w = BufWriter::new(File::create(path)?);
 
-    encode(&mut w, buffer, format)
+    encode_with_options(&mut w, buffer, format, options)
+}
+
+/// Encode with explicit metadata handling and dithering
+///
+/// See `EncodeOptions` for the current state of metadata support.
+pub fn encode_with_options<W>(
+    w: W,
+    buffer: &PixelBuffer<Rgb>,
+    format: EncodingFormat,
+    options: &EncodeOptions,
+) -> Result<(), EncodingError>
+where
+    W: Write,
+{
+    // No encoder writes metadata yet, so the output is always stripped
+    if options.dither {
+        if let Some((max_value, gray)) = dither_target(&format) {
+            return encode(w, &dither_for_export(buffer, max_value, gray), format);
+        }
+    }
+
+    encode(w, buffer, format)
+}
+
+/// Largest integer channel value and whether only the luma is written
+fn dither_target(format: &EncodingFormat) -> Option<(f32, bool)> {
+    match format {
+        EncodingFormat::Jpeg { grayscale, .. } => Some((255.0, *grayscale)),
+        EncodingFormat::Png {
+            palette: Some(_), ..
+        } => None,
+        EncodingFormat::Png { color_type, .. } => Some(match color_type {
+            PngColorType::L8 | PngColorType::La8 => (255.0, true),
+            PngColorType::L16 | PngColorType::La16 => (65535.0, true),
+            PngColorType::Rgb8 | PngColorType::Rgba8 => (255.0, false),
+            PngColorType::Rgb16 | PngColorType::Rgba16 => (65535.0, false),
+        }),
+        EncodingFormat::Bmp { color_type } => Some((
+            255.0,
+            matches!(color_type, BmpColorType::L8 | BmpColorType::La8),
+        )),
+        EncodingFormat::Ico { color_type } => Some((
+            255.0,
+            matches!(color_type, IcoColorType::L8 | IcoColorType::La8),
+        )),
+        EncodingFormat::WebP { .. } | EncodingFormat::Dds | EncodingFormat::Jxl { .. } => {
+            Some((255.0, false))
+        }
+        EncodingFormat::Gif { .. } => None,
+    }
 }
 
 pub fn encode<W>(
@@ -279,10 +767,168 @@ where
             color_type,
             compression,
             filter,
-        } => encode_png(w, buffer, color_type, compression, filter),
-        EncodingFormat::Gif => encode_gif(w, buffer),
+            interlaced,
+            palette,
+        } => match palette {
+            Some(palette) => {
+                encode_png_indexed(w, buffer, &palette, compression, filter, interlaced)
+            }
+            None => encode_png(w, buffer, color_type, compression, filter, interlaced),
+        },
+        EncodingFormat::Gif {
+            palette,
+            dither,
+            transparency_threshold,
+        } => encode_gif(w, buffer, palette.as_ref(), dither, transparency_threshold),
         EncodingFormat::Bmp { color_type } => encode_bmp(w, buffer, color_type),
         EncodingFormat::Ico { color_type } => encode_ico(w, buffer, color_type),
         EncodingFormat::WebP { quality, preset } => encode_webp(w, buffer, quality, preset),
+        EncodingFormat::Dds => encode_dds(w, buffer),
+        #[cfg(feature = "jxl")]
+        EncodingFormat::Jxl {
+            effort, lossless, ..
+        } => encode_jxl(w, buffer, effort, lossless),
+        #[cfg(not(feature = "jxl"))]
+        EncodingFormat::Jxl { .. } => Err(EncodingError::InvalidConfig {
+            format: Format::Jxl,
+            message: "Built without the jxl feature".to_owned(),
+        }),
+    }
+}
+
+/// Encode multiple buffers into one ico file, i.e. a favicon with several sizes
+///
+/// Width and height of all buffers must be between 1 and 256.
+pub fn encode_multi_size_ico<W>(
+    w: W,
+    buffers: &[&PixelBuffer<Rgb>],
+    color_type: IcoColorType,
+) -> Result<(), EncodingError>
+where
+    W: Write,
+{
+    encode_ico_frames(w, buffers, color_type)
+}
+
+/// Encode frames of the same size with their delays as animation
+///
+/// Only gif and png are supported, where png results in an APNG. A `loop_count` of 0 repeats the
+/// animation forever.
+pub fn encode_animation<W>(
+    w: W,
+    frames: &[(&PixelBuffer<Rgb>, Duration)],
+    loop_count: u32,
+    format: EncodingFormat,
+) -> Result<(), EncodingError>
+where
+    W: Write,
+{
+    match format {
+        EncodingFormat::Gif {
+            palette,
+            dither,
+            transparency_threshold,
+        } => encode_gif_animation(
+            w,
+            frames,
+            loop_count,
+            palette.as_ref(),
+            dither,
+            transparency_threshold,
+        ),
+        EncodingFormat::Png {
+            palette: Some(_), ..
+        } => Err(EncodingError::InvalidConfig {
+            format: Format::Png,
+            message: "Animations can't be encoded as indexed png".to_owned(),
+        }),
+        EncodingFormat::Png {
+            color_type,
+            compression,
+            filter,
+            ..
+        } => encode_png_animation(w, frames, loop_count, color_type, compression, filter),
+        format => Err(EncodingError::InvalidConfig {
+            format: format.format(),
+            message: "Animations can only be encoded as gif or png".to_owned(),
+        }),
+    }
+}
+
+/// Encode layers together with their composite image as OpenRaster file
+///
+/// Blend modes are mapped to the matching composite ops of OpenRaster and fall back to
+/// `svg:src-over`.
+pub fn encode_ora<W>(
+    w: W,
+    image: &LayeredImage,
+    composite: &PixelBuffer<Rgb>,
+) -> Result<(), EncodingError>
+where
+    W: Write,
+{
+    ora::encode_ora(w, image, composite)
+}
+
+/// Encode with the highest quality that results in at most `max_bytes`
+///
+/// The quality of jpeg and webp is lowered by a binary search starting at the quality of `format`.
+/// All other formats are encoded as is.
+/// Returns the format with the quality used for the written data.
+pub fn encode_with_target_size<W>(
+    mut w: W,
+    buffer: &PixelBuffer<Rgb>,
+    format: EncodingFormat,
+    max_bytes: usize,
+) -> Result<EncodingFormat, EncodingError>
+where
+    W: Write,
+{
+    let encode_to_vec = |format: &EncodingFormat| -> Result<Vec<u8>, EncodingError> {
+        let mut data = vec![];
+        encode(&mut data, buffer, format.clone())?;
+        Ok(data)
+    };
+
+    let data = encode_to_vec(&format)?;
+
+    if data.len() <= max_bytes {
+        w.write_all(&data)?;
+        return Ok(format);
+    }
+
+    let mut smallest = data.len();
+    let mut best = None;
+
+    if let Some(quality) = format.quality() {
+        let mut low = 1;
+        let mut high = quality as i32 - 1;
+
+        while low <= high {
+            let mid = (low + high) / 2;
+
+            let candidate = format.clone().with_quality(mid as u8);
+            let data = encode_to_vec(&candidate)?;
+
+            if data.len() <= max_bytes {
+                best = Some((candidate, data));
+                low = mid + 1;
+            } else {
+                smallest = smallest.min(data.len());
+                high = mid - 1;
+            }
+        }
+    }
+
+    match best {
+        Some((format, data)) => {
+            w.write_all(&data)?;
+            Ok(format)
+        }
+        None => Err(EncodingError::TargetSizeNotReached {
+            format: format.format(),
+            max_bytes,
+            size: smallest,
+        }),
     }
 }
@@ -0,0 +1,196 @@
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+
+use memmap2::{Mmap, MmapMut};
+
+use d10_core::color::Rgb;
+use d10_core::pixelbuffer::PixelBuffer;
+
+use crate::bmp::{bmp_encoded_size, encode_bmp_into};
+use crate::dds::{dds_encoded_size, encode_dds_into};
+use crate::{
+    decode_buffer_with_hint, encode_to_file, DecodedImage, DecodingError, EncodingError,
+    EncodingFormat, Format,
+};
+
+/// Decode an image file by mapping it into memory instead of reading it
+///
+/// This avoids copying the file into a buffer and the read calls needed for it, which reduces
+/// the peak memory usage when processing many or large files.
+/// The file must not be modified while it's decoded. Use `decode_file()` if this can't be
+/// guaranteed, e.g. for files on network shares.
+pub fn decode_mmap<P>(path: P) -> Result<DecodedImage, DecodingError>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let file = File::open(path)?;
+    let hint = Format::from_path(path);
+
+    // Mapping empty files fails on some platforms
+    if file.metadata()?.len() == 0 {
+        return decode_buffer_with_hint(&[], hint);
+    }
+
+    // SAFETY: The file is only read and callers are required to not modify it while decoding.
+    let data = unsafe { Mmap::map(&file)? };
+
+    decode_buffer_with_hint(&data, hint)
+}
+
+/// Size of the encoded file if the format can be written directly into the mapped memory
+///
+/// Images the encoders reject, e.g. bmp files larger than 4 GiB, return `None` so the error of
+/// the regular encoder is returned.
+fn encoded_size(buffer: &PixelBuffer<Rgb>, format: &EncodingFormat) -> Option<u64> {
+    let width = buffer.width();
+    let height = buffer.height();
+
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    match format {
+        EncodingFormat::Bmp { color_type } => Some(bmp_encoded_size(width, height, *color_type))
+            .filter(|size| *size <= u32::MAX as u64),
+        EncodingFormat::Dds => Some(dds_encoded_size(width, height)),
+        _ => None,
+    }
+}
+
+/// Encode an image directly into a memory mapped file
+///
+/// Uncompressed formats like BMP and DDS have a size known before encoding, so the file is
+/// created with its final size and the pixels are converted row by row straight into the mapped
+/// memory without an intermediate copy of the image. All other formats fall back to
+/// `encode_to_file()`.
+pub fn encode_mmap<P>(
+    path: P,
+    buffer: &PixelBuffer<Rgb>,
+    format: EncodingFormat,
+) -> Result<(), EncodingError>
+where
+    P: AsRef<Path>,
+{
+    let Some(size) = encoded_size(buffer, &format) else {
+        return encode_to_file(path, buffer, Some(format));
+    };
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?;
+    file.set_len(size)?;
+
+    // SAFETY: The file was just created with the expected size and is only written through
+    // this mapping.
+    let mut data = unsafe { MmapMut::map_mut(&file)? };
+
+    match format {
+        EncodingFormat::Bmp { color_type } => encode_bmp_into(&mut data, buffer, color_type),
+        EncodingFormat::Dds => encode_dds_into(&mut data, buffer)?,
+        // Only formats with a known size are mapped
+        _ => unreachable!(),
+    }
+
+    data.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode_file, encode, BmpColorType};
+
+    fn test_buffer(width: u32, height: u32) -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(width, height, |x, y| {
+            Rgb::new_with_alpha(x as f32 / width as f32, y as f32 / height as f32, 0.5, 0.75)
+        })
+    }
+
+    #[test]
+    fn test_encoded_size() {
+        for (width, height) in [(1, 1), (5, 3), (13, 7), (16, 2)] {
+            let buffer = test_buffer(width, height);
+
+            for format in [
+                EncodingFormat::Bmp {
+                    color_type: BmpColorType::L8,
+                },
+                EncodingFormat::Bmp {
+                    color_type: BmpColorType::La8,
+                },
+                EncodingFormat::Bmp {
+                    color_type: BmpColorType::Rgb8,
+                },
+                EncodingFormat::Bmp {
+                    color_type: BmpColorType::Rgba8,
+                },
+                EncodingFormat::Dds,
+            ] {
+                let mut data = vec![];
+                encode(&mut data, &buffer, format.clone()).unwrap();
+
+                assert_eq!(
+                    encoded_size(&buffer, &format),
+                    Some(data.len() as u64),
+                    "{:?} {}x{}",
+                    format,
+                    width,
+                    height
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_mmap_roundtrip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let buffer = test_buffer(13, 7);
+
+        for (name, format) in [
+            ("test.bmp", EncodingFormat::bmp_default()),
+            (
+                "test_l8.bmp",
+                EncodingFormat::Bmp {
+                    color_type: BmpColorType::L8,
+                },
+            ),
+            (
+                "test_la8.bmp",
+                EncodingFormat::Bmp {
+                    color_type: BmpColorType::La8,
+                },
+            ),
+            (
+                "test_rgb8.bmp",
+                EncodingFormat::Bmp {
+                    color_type: BmpColorType::Rgb8,
+                },
+            ),
+            ("test.dds", EncodingFormat::Dds),
+            ("test.png", EncodingFormat::png_default()),
+        ] {
+            let path = dir.join(name);
+
+            encode_mmap(&path, &buffer, format.clone()).unwrap();
+
+            let mut expected = vec![];
+            encode(&mut expected, &buffer, format).unwrap();
+            assert_eq!(std::fs::read(&path).unwrap(), expected);
+
+            let decoded = decode_mmap(&path).unwrap().buffer;
+            let expected = decode_file(&path).unwrap().buffer;
+            assert_eq!(decoded.data(), expected.data());
+        }
+
+        let empty = dir.join("empty.png");
+        std::fs::write(&empty, []).unwrap();
+        assert!(decode_mmap(&empty).is_err());
+    }
+}
@@ -0,0 +1,605 @@
+use std::io::{BufReader, Cursor, Read, Seek, Write};
+
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use d10_core::color::Rgb;
+use d10_core::pixelbuffer::{is_valid_buffer_size, PixelBuffer};
+
+use crate::png::{decode_png, encode_png};
+use crate::{
+    DecodedImage, DecodingError, EncodingError, Format, ImageLayer, LayeredImage, PngColorType,
+    PngCompression, PngFilterType,
+};
+
+/// Content of the mimetype file which must be the first file of the archive
+pub(crate) const MIME_TYPE: &str = "image/openraster";
+
+/// Largest uncompressed size of a file inside of the archive to protect against zip bombs
+const MAX_ENTRY_SIZE: u64 = 1 << 30;
+
+/// Longer side of the thumbnail required by the specification
+const THUMBNAIL_SIZE: u32 = 256;
+
+/// Composite ops of OpenRaster and the matching psd blend mode keys used by `ImageLayer`
+const COMPOSITE_OPS: [(&str, &str); 16] = [
+    ("svg:src-over", "norm"),
+    ("svg:multiply", "mul "),
+    ("svg:screen", "scrn"),
+    ("svg:overlay", "over"),
+    ("svg:darken", "dark"),
+    ("svg:lighten", "lite"),
+    ("svg:color-dodge", "div "),
+    ("svg:color-burn", "idiv"),
+    ("svg:hard-light", "hLit"),
+    ("svg:soft-light", "sLit"),
+    ("svg:difference", "diff"),
+    ("svg:color", "colr"),
+    ("svg:luminosity", "lum "),
+    ("svg:hue", "hue "),
+    ("svg:saturation", "sat "),
+    ("svg:plus", "lddg"),
+];
+
+fn invalid_data(message: &str) -> DecodingError {
+    DecodingError::InvalidData {
+        format: Format::Ora,
+        message: message.to_owned(),
+    }
+}
+
+fn decoding_error(err: ZipError) -> DecodingError {
+    DecodingError::codec(Format::Ora, err)
+}
+
+fn encoding_error(err: ZipError) -> EncodingError {
+    EncodingError::codec(Format::Ora, err)
+}
+
+/// Blend mode key for a composite op
+///
+/// Unknown ops are kept as they are, so they survive reading and writing a file.
+fn blend_mode(composite_op: &str) -> String {
+    COMPOSITE_OPS
+        .iter()
+        .find(|(op, _)| *op == composite_op)
+        .map_or(composite_op, |(_, key)| key)
+        .to_owned()
+}
+
+fn composite_op(blend_mode: &str) -> &str {
+    match COMPOSITE_OPS.iter().find(|(_, key)| *key == blend_mode) {
+        Some((op, _)) => op,
+        None if blend_mode.contains(':') => blend_mode,
+        None => "svg:src-over",
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum TagKind {
+    Open,
+    Close,
+    Empty,
+}
+
+#[derive(Debug)]
+struct Tag<'a> {
+    name: &'a str,
+    kind: TagKind,
+    attributes: Vec<(&'a str, String)>,
+}
+
+impl Tag<'_> {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Split xml into tags with their attributes
+///
+/// This is just enough xml to read stack.xml files. Text, comments, processing instructions and
+/// doctypes are skipped.
+fn tags(xml: &str) -> Result<Vec<Tag<'_>>, DecodingError> {
+    let mut tags = vec![];
+    let mut rest = xml;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+
+        if let Some(comment) = rest.strip_prefix("!--") {
+            let end = comment
+                .find("-->")
+                .ok_or_else(|| invalid_data("Unterminated comment in stack.xml"))?;
+            rest = &comment[end + 3..];
+            continue;
+        }
+
+        // The end of the tag is the first '>' outside of attribute values
+        let mut quote = None;
+        let end = rest
+            .char_indices()
+            .find(|(_, c)| match (quote, *c) {
+                (None, '"' | '\'') => {
+                    quote = Some(*c);
+                    false
+                }
+                (Some(q), c) if q == c => {
+                    quote = None;
+                    false
+                }
+                (quote, c) => quote.is_none() && c == '>',
+            })
+            .map(|(i, _)| i)
+            .ok_or_else(|| invalid_data("Unterminated tag in stack.xml"))?;
+
+        let content = &rest[..end];
+        rest = &rest[end + 1..];
+
+        if content.starts_with('?') || content.starts_with('!') {
+            continue;
+        }
+
+        let (content, kind) = if let Some(content) = content.strip_prefix('/') {
+            (content, TagKind::Close)
+        } else if let Some(content) = content.strip_suffix('/') {
+            (content, TagKind::Empty)
+        } else {
+            (content, TagKind::Open)
+        };
+
+        let content = content.trim();
+        let name_end = content.find(char::is_whitespace).unwrap_or(content.len());
+
+        tags.push(Tag {
+            name: &content[..name_end],
+            kind,
+            attributes: attributes(&content[name_end..])?,
+        });
+    }
+
+    Ok(tags)
+}
+
+fn attributes(mut rest: &str) -> Result<Vec<(&str, String)>, DecodingError> {
+    let bad_attribute = || invalid_data("Bad attribute in stack.xml");
+    let mut attributes = vec![];
+
+    rest = rest.trim_start();
+
+    while !rest.is_empty() {
+        let equals = rest.find('=').ok_or_else(bad_attribute)?;
+        let name = rest[..equals].trim();
+        let value = rest[equals + 1..].trim_start();
+
+        let quote = value
+            .chars()
+            .next()
+            .filter(|c| *c == '"' || *c == '\'')
+            .ok_or_else(bad_attribute)?;
+        let value = &value[1..];
+        let end = value.find(quote).ok_or_else(bad_attribute)?;
+
+        attributes.push((name, unescape(&value[..end])));
+        rest = value[end + 1..].trim_start();
+    }
+
+    Ok(attributes)
+}
+
+/// Replace the predefined entities and character references
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+
+        let c = match &rest[1..end] {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            entity => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+
+        match c {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A layer of the stack.xml
+struct StackLayer {
+    name: String,
+    src: String,
+    x: i32,
+    y: i32,
+    opacity: f32,
+    visible: bool,
+    composite_op: String,
+}
+
+/// Size of the image and its layers with the top layer first
+///
+/// Nested stacks are flattened into a single list with their opacity and visibility applied
+/// to all their layers.
+fn parse_stack(xml: &str) -> Result<(u32, u32, Vec<StackLayer>), DecodingError> {
+    let mut size = None;
+    let mut layers = vec![];
+
+    // Opacity and visibility of all enclosing stacks
+    let mut groups = vec![(1.0, true)];
+
+    let opacity = |tag: &Tag| {
+        tag.attribute("opacity")
+            .and_then(|v| v.trim().parse::<f32>().ok())
+            .map_or(1.0, |v| v.clamp(0.0, 1.0))
+    };
+    let visible = |tag: &Tag| tag.attribute("visibility") != Some("hidden");
+    let position = |tag: &Tag, name: &str| {
+        tag.attribute(name)
+            .and_then(|v| v.trim().parse::<f32>().ok())
+            .map_or(0, |v| v.round() as i32)
+    };
+
+    for tag in tags(xml)? {
+        let (group_opacity, group_visible) = groups[groups.len() - 1];
+
+        match (tag.name, tag.kind) {
+            ("image", TagKind::Open | TagKind::Empty) => {
+                let dimension = |name| {
+                    tag.attribute(name)
+                        .and_then(|v| v.trim().parse::<u32>().ok())
+                        .ok_or_else(|| invalid_data("Missing image size"))
+                };
+                size = Some((dimension("w")?, dimension("h")?));
+            }
+            ("stack", TagKind::Open) => groups.push((
+                group_opacity * opacity(&tag),
+                group_visible && visible(&tag),
+            )),
+            ("stack", TagKind::Close) if groups.len() > 1 => {
+                groups.pop();
+            }
+            ("layer", TagKind::Open | TagKind::Empty) => layers.push(StackLayer {
+                name: tag.attribute("name").unwrap_or_default().to_owned(),
+                src: tag
+                    .attribute("src")
+                    .ok_or_else(|| invalid_data("Layer without src"))?
+                    .to_owned(),
+                x: position(&tag, "x"),
+                y: position(&tag, "y"),
+                opacity: group_opacity * opacity(&tag),
+                visible: group_visible && visible(&tag),
+                composite_op: tag
+                    .attribute("composite-op")
+                    .unwrap_or("svg:src-over")
+                    .to_owned(),
+            }),
+            _ => {}
+        }
+    }
+
+    let (width, height) = size.ok_or_else(|| invalid_data("Missing image element"))?;
+
+    if !is_valid_buffer_size(width, height) {
+        return Err(DecodingError::InvalidBufferSize { width, height });
+    }
+
+    Ok((width, height, layers))
+}
+
+fn read_file<T>(archive: &mut ZipArchive<T>, name: &str) -> Result<Vec<u8>, DecodingError>
+where
+    T: Read + Seek,
+{
+    let file = archive.by_name(name).map_err(|err| match err {
+        ZipError::FileNotFound => invalid_data(&format!("Missing {}", name)),
+        err => decoding_error(err),
+    })?;
+
+    let mut data = vec![];
+    file.take(MAX_ENTRY_SIZE).read_to_end(&mut data)?;
+
+    Ok(data)
+}
+
+fn read_png<T>(archive: &mut ZipArchive<T>, name: &str) -> Result<DecodedImage, DecodingError>
+where
+    T: Read + Seek,
+{
+    decode_png(BufReader::new(Cursor::new(read_file(archive, name)?)))
+}
+
+fn open_archive<T>(reader: T) -> Result<ZipArchive<T>, DecodingError>
+where
+    T: Read + Seek,
+{
+    let mut archive = ZipArchive::new(reader).map_err(decoding_error)?;
+
+    if read_file(&mut archive, "mimetype")? != MIME_TYPE.as_bytes() {
+        return Err(invalid_data("Bad mimetype"));
+    }
+
+    Ok(archive)
+}
+
+/// Decode the merged image of an OpenRaster file
+pub(crate) fn decode_ora<T>(reader: T) -> Result<DecodedImage, DecodingError>
+where
+    T: Read + Seek,
+{
+    read_png(&mut open_archive(reader)?, "mergedimage.png")
+}
+
+/// Decode the layers of an OpenRaster file
+///
+/// Layers of nested stacks are returned as normal layers. Text and filter elements are ignored.
+pub(crate) fn decode_ora_layers<T>(reader: T) -> Result<LayeredImage, DecodingError>
+where
+    T: Read + Seek,
+{
+    let mut archive = open_archive(reader)?;
+
+    let xml = String::from_utf8(read_file(&mut archive, "stack.xml")?)
+        .map_err(|_| invalid_data("Bad encoding of stack.xml"))?;
+
+    let (width, height, stack) = parse_stack(&xml)?;
+
+    let layers = stack
+        .into_iter()
+        .rev()
+        .map(|layer| {
+            Ok(ImageLayer {
+                buffer: read_png(&mut archive, &layer.src)?.buffer,
+                name: layer.name,
+                x: layer.x,
+                y: layer.y,
+                opacity: layer.opacity,
+                visible: layer.visible,
+                blend_mode: blend_mode(&layer.composite_op),
+            })
+        })
+        .collect::<Result<_, DecodingError>>()?;
+
+    Ok(LayeredImage {
+        width,
+        height,
+        layers,
+    })
+}
+
+fn stack_xml(image: &LayeredImage) -> String {
+    let mut xml = format!(
+        "<?xml version='1.0' encoding='UTF-8'?>\n<image version=\"0.0.5\" w=\"{}\" h=\"{}\">\n  <stack>\n",
+        image.width, image.height
+    );
+
+    for (i, layer) in image.layers.iter().enumerate().rev() {
+        xml.push_str(&format!(
+            "    <layer name=\"{}\" src=\"data/layer{}.png\" x=\"{}\" y=\"{}\" opacity=\"{}\" visibility=\"{}\" composite-op=\"{}\"/>\n",
+            escape(&layer.name),
+            i,
+            layer.x,
+            layer.y,
+            layer.opacity.clamp(0.0, 1.0),
+            if layer.visible { "visible" } else { "hidden" },
+            escape(composite_op(&layer.blend_mode)),
+        ));
+    }
+
+    xml.push_str("  </stack>\n</image>\n");
+    xml
+}
+
+/// Downscale to at most `THUMBNAIL_SIZE` by averaging all pixels covered by a pixel of the result
+fn thumbnail(buffer: &PixelBuffer<Rgb>) -> PixelBuffer<Rgb> {
+    let width = buffer.width() as u64;
+    let height = buffer.height() as u64;
+    let max_edge = width.max(height);
+
+    if max_edge <= THUMBNAIL_SIZE as u64 {
+        return buffer.clone();
+    }
+
+    let thumb_width = (width * THUMBNAIL_SIZE as u64 / max_edge).max(1);
+    let thumb_height = (height * THUMBNAIL_SIZE as u64 / max_edge).max(1);
+
+    PixelBuffer::new_from_func(thumb_width as u32, thumb_height as u32, |x, y| {
+        let x_range = (x as u64 * width / thumb_width)..((x as u64 + 1) * width / thumb_width);
+        let y_range = (y as u64 * height / thumb_height)..((y as u64 + 1) * height / thumb_height);
+
+        let mut sum = [0.0; 4];
+        let mut count = 0.0;
+
+        for y in y_range {
+            for x in x_range.clone() {
+                let c = buffer.get_pixel(x as u32, y as u32);
+                let alpha = c.data[3];
+
+                for (total, value) in sum.iter_mut().zip(&c.data[..3]) {
+                    *total += value * alpha;
+                }
+                sum[3] += alpha;
+                count += 1.0;
+            }
+        }
+
+        if sum[3] <= 0.0 {
+            return Rgb::NONE;
+        }
+
+        Rgb {
+            data: [
+                sum[0] / sum[3],
+                sum[1] / sum[3],
+                sum[2] / sum[3],
+                sum[3] / count,
+            ],
+        }
+    })
+}
+
+fn write_png<W>(w: W, buffer: &PixelBuffer<Rgb>) -> Result<(), EncodingError>
+where
+    W: Write,
+{
+    encode_png(
+        w,
+        buffer,
+        PngColorType::Rgba8,
+        PngCompression::Default,
+        PngFilterType::Sub,
+        false,
+    )
+}
+
+/// Encode layers together with their composite image as OpenRaster file
+///
+/// Layers without any pixels are stored as a single transparent pixel because png doesn't
+/// support empty images.
+pub(crate) fn encode_ora<W>(
+    mut w: W,
+    image: &LayeredImage,
+    composite: &PixelBuffer<Rgb>,
+) -> Result<(), EncodingError>
+where
+    W: Write,
+{
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut zip = ZipWriter::new(Cursor::new(vec![]));
+
+    // The uncompressed mimetype file at the start of the archive identifies the format
+    zip.start_file("mimetype", stored).map_err(encoding_error)?;
+    zip.write_all(MIME_TYPE.as_bytes())?;
+
+    zip.start_file("stack.xml", deflated)
+        .map_err(encoding_error)?;
+    zip.write_all(stack_xml(image).as_bytes())?;
+
+    let empty = PixelBuffer::new_with_color(1, 1, Rgb::NONE);
+
+    // Png data is already compressed
+    for (i, layer) in image.layers.iter().enumerate() {
+        let buffer = if layer.buffer.width() == 0 || layer.buffer.height() == 0 {
+            &empty
+        } else {
+            &layer.buffer
+        };
+
+        zip.start_file(format!("data/layer{}.png", i), stored)
+            .map_err(encoding_error)?;
+        write_png(&mut zip, buffer)?;
+    }
+
+    zip.start_file("mergedimage.png", stored)
+        .map_err(encoding_error)?;
+    write_png(&mut zip, composite)?;
+
+    zip.start_file("Thumbnails/thumbnail.png", stored)
+        .map_err(encoding_error)?;
+    write_png(&mut zip, &thumbnail(composite))?;
+
+    let data = zip.finish().map_err(encoding_error)?.into_inner();
+    w.write_all(&data)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stack() {
+        let xml = r#"<?xml version='1.0' encoding='UTF-8'?>
+            <!-- Written by hand -->
+            <image version="0.0.5" w="20" h='10'>
+              <stack>
+                <stack name="group" opacity="0.5" visibility="hidden">
+                  <layer name="a &amp; &quot;b&quot; &#x263A;" src="data/1.png" x="-3" y="4"
+                         opacity="0.5" composite-op="svg:multiply"/>
+                </stack>
+                <text name="ignored"></text>
+                <layer src="data/2.png" opacity="2"></layer>
+              </stack>
+            </image>"#;
+
+        let (width, height, layers) = parse_stack(xml).unwrap();
+        assert_eq!((width, height), (20, 10));
+        assert_eq!(layers.len(), 2);
+
+        assert_eq!(layers[0].name, "a & \"b\" \u{263A}");
+        assert_eq!(layers[0].src, "data/1.png");
+        assert_eq!((layers[0].x, layers[0].y), (-3, 4));
+        assert_eq!(layers[0].opacity, 0.25);
+        assert!(!layers[0].visible);
+        assert_eq!(blend_mode(&layers[0].composite_op), "mul ");
+
+        assert_eq!(layers[1].name, "");
+        assert_eq!(layers[1].opacity, 1.0);
+        assert!(layers[1].visible);
+        assert_eq!(layers[1].composite_op, "svg:src-over");
+
+        assert!(parse_stack("<stack><layer src='a.png'/></stack>").is_err());
+        assert!(parse_stack("<image w='1' h='1'><layer name='a'/></image>").is_err());
+        assert!(parse_stack("<image w='1' h='1'><layer src='a.png'</image>").is_err());
+    }
+
+    #[test]
+    fn test_composite_op() {
+        assert_eq!(composite_op(&blend_mode("svg:screen")), "svg:screen");
+        assert_eq!(composite_op(&blend_mode("svg:dst-in")), "svg:dst-in");
+        assert_eq!(composite_op("fsub"), "svg:src-over");
+    }
+
+    #[test]
+    fn test_thumbnail() {
+        let buffer = PixelBuffer::new_from_func(600, 300, |x, _| {
+            if x.is_multiple_of(2) {
+                Rgb::RED
+            } else {
+                Rgb::NONE
+            }
+        });
+
+        let thumbnail = thumbnail(&buffer);
+        assert_eq!((thumbnail.width(), thumbnail.height()), (256, 128));
+        assert_eq!(thumbnail.get_pixel(0, 0).data, [1.0, 0.0, 0.0, 0.5]);
+    }
+}
@@ -0,0 +1,604 @@
+use std::fmt::{self, Display};
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+use d10_core::color::illuminant::D50;
+use d10_core::color::observer::O2;
+use d10_core::color::{Color, Hsv, Lab, Rgb, Srgb};
+use d10_core::palette::Palette;
+
+use crate::PaletteError;
+
+/// File formats for color palettes
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PaletteFormat {
+    /// GIMP palette
+    Gpl,
+    /// Adobe color swatch as used by Photoshop
+    Aco,
+    /// Adobe swatch exchange
+    Ase,
+}
+
+impl PaletteFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PaletteFormat::Gpl => "gpl",
+            PaletteFormat::Aco => "aco",
+            PaletteFormat::Ase => "ase",
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<PaletteFormat> {
+        let ext = path.extension()?.to_string_lossy().to_ascii_lowercase();
+
+        match ext.as_str() {
+            "gpl" => Some(PaletteFormat::Gpl),
+            "aco" => Some(PaletteFormat::Aco),
+            "ase" => Some(PaletteFormat::Ase),
+            _ => None,
+        }
+    }
+}
+
+impl Display for PaletteFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+fn invalid(format: PaletteFormat, message: &str) -> PaletteError {
+    PaletteError::InvalidData {
+        format,
+        message: message.to_owned(),
+    }
+}
+
+fn to_srgb_u8(color: &Rgb) -> [u8; 3] {
+    let c = color.to_srgb();
+    [c.red(), c.green(), c.blue()].map(|v| (v * 255.0).round().clamp(0.0, 255.0) as u8)
+}
+
+fn to_srgb_u16(color: &Rgb) -> [u16; 3] {
+    let c = color.to_srgb();
+    [c.red(), c.green(), c.blue()].map(|v| (v * 65535.0).round().clamp(0.0, 65535.0) as u16)
+}
+
+fn from_srgb(red: f32, green: f32, blue: f32) -> Rgb {
+    Srgb::new(red, green, blue).to_rgb()
+}
+
+fn from_cmyk(cyan: f32, magenta: f32, yellow: f32, black: f32) -> Rgb {
+    from_srgb(
+        (1.0 - cyan) * (1.0 - black),
+        (1.0 - magenta) * (1.0 - black),
+        (1.0 - yellow) * (1.0 - black),
+    )
+}
+
+/// Big endian reader for the binary formats
+struct Reader<'a> {
+    format: PaletteFormat,
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], PaletteError> {
+        if self.data.len() < len {
+            return Err(invalid(self.format, "Unexpected end of file"));
+        }
+
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn read_u16(&mut self) -> Result<u16, PaletteError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, PaletteError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_f32(&mut self) -> Result<f32, PaletteError> {
+        Ok(f32::from_bits(self.read_u32()?))
+    }
+
+    /// Read `len` UTF-16 code units and strip the terminating null character
+    fn read_utf16(&mut self, len: usize) -> Result<String, PaletteError> {
+        let units: Vec<u16> = self
+            .take(len * 2)?
+            .chunks_exact(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .take_while(|c| *c != 0)
+            .collect();
+
+        Ok(String::from_utf16_lossy(&units))
+    }
+}
+
+fn write_utf16(out: &mut Vec<u8>, value: &str) {
+    for unit in value.encode_utf16().chain(std::iter::once(0)) {
+        out.extend_from_slice(&unit.to_be_bytes());
+    }
+}
+
+fn non_empty(name: String) -> Option<String> {
+    let name = name.trim();
+    (!name.is_empty()).then(|| name.to_owned())
+}
+
+fn parse_channel(value: Option<&str>) -> Result<u8, PaletteError> {
+    value
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| invalid(PaletteFormat::Gpl, "Bad color value"))
+}
+
+fn decode_gpl(data: &[u8]) -> Result<Palette, PaletteError> {
+    let text = String::from_utf8_lossy(data);
+    let mut lines = text.lines();
+
+    if lines.next().map(|l| l.trim()) != Some("GIMP Palette") {
+        return Err(invalid(PaletteFormat::Gpl, "Missing GIMP Palette header"));
+    }
+
+    let mut palette = Palette::new();
+
+    for line in lines {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') || line.starts_with("Columns:") {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix("Name:") {
+            palette.name = non_empty(name.to_owned());
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        let red = parse_channel(parts.next())?;
+        let green = parse_channel(parts.next())?;
+        let blue = parse_channel(parts.next())?;
+
+        // The name is the rest of the line after the three values
+        let name = parts.collect::<Vec<_>>().join(" ");
+
+        palette.push(
+            from_srgb(
+                red as f32 / 255.0,
+                green as f32 / 255.0,
+                blue as f32 / 255.0,
+            ),
+            non_empty(name).as_deref(),
+        );
+    }
+
+    Ok(palette)
+}
+
+fn encode_gpl(palette: &Palette) -> Vec<u8> {
+    let mut out = String::from("GIMP Palette\n");
+
+    if let Some(name) = &palette.name {
+        out.push_str(&format!("Name: {}\n", name.replace('\n', " ")));
+    }
+
+    out.push_str("#\n");
+
+    for (color, name) in palette.iter() {
+        let [red, green, blue] = to_srgb_u8(color);
+        out.push_str(&format!("{:3} {:3} {:3}", red, green, blue));
+
+        if let Some(name) = name {
+            out.push('\t');
+            out.push_str(&name.replace('\n', " "));
+        }
+
+        out.push('\n');
+    }
+
+    out.into_bytes()
+}
+
+fn decode_aco_color(reader: &mut Reader) -> Result<Rgb, PaletteError> {
+    let space = reader.read_u16()?;
+    let w = reader.read_u16()?;
+    let x = reader.read_u16()?;
+    let y = reader.read_u16()?;
+    let z = reader.read_u16()?;
+
+    let unit = |v: u16| v as f32 / 65535.0;
+
+    match space {
+        0 => Ok(from_srgb(unit(w), unit(x), unit(y))),
+        1 => {
+            let c = Hsv::new(unit(w), unit(x), unit(y)).to_rgb();
+            Ok(from_srgb(c.red(), c.green(), c.blue()))
+        }
+        // Zero means full ink coverage
+        2 => Ok(from_cmyk(
+            1.0 - unit(w),
+            1.0 - unit(x),
+            1.0 - unit(y),
+            1.0 - unit(z),
+        )),
+        7 => Ok(Lab::<D50, O2>::new(
+            w as f32 / 100.0,
+            x as i16 as f32 / 100.0,
+            y as i16 as f32 / 100.0,
+        )
+        .to_rgb()),
+        // The gray value is the amount of black ink between 0 and 10000
+        8 => {
+            let v = 1.0 - (w as f32 / 10000.0).min(1.0);
+            Ok(from_srgb(v, v, v))
+        }
+        _ => Err(PaletteError::Unsupported {
+            format: PaletteFormat::Aco,
+            message: format!("Color space {}", space),
+        }),
+    }
+}
+
+fn decode_aco(data: &[u8]) -> Result<Palette, PaletteError> {
+    let mut reader = Reader {
+        format: PaletteFormat::Aco,
+        data,
+    };
+
+    let mut palette = Palette::new();
+
+    // Version 1 contains only colors and is usually followed by version 2 that adds names
+    let mut version = reader.read_u16()?;
+    let mut count = reader.read_u16()?;
+
+    if version == 1 {
+        let mut colors = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            colors.push(decode_aco_color(&mut reader)?);
+        }
+
+        if reader.data.len() < 4 {
+            return Ok(Palette::from_colors(colors));
+        }
+
+        version = reader.read_u16()?;
+        count = reader.read_u16()?;
+    }
+
+    if version != 2 {
+        return Err(PaletteError::Unsupported {
+            format: PaletteFormat::Aco,
+            message: format!("Version {}", version),
+        });
+    }
+
+    for _ in 0..count {
+        let color = decode_aco_color(&mut reader)?;
+        let len = reader.read_u32()? as usize;
+        let name = reader.read_utf16(len)?;
+
+        palette.push(color, non_empty(name).as_deref());
+    }
+
+    Ok(palette)
+}
+
+fn encode_aco(palette: &Palette) -> Vec<u8> {
+    let mut out = vec![];
+
+    for version in [1u16, 2] {
+        out.extend_from_slice(&version.to_be_bytes());
+        out.extend_from_slice(&(palette.len() as u16).to_be_bytes());
+
+        for (color, name) in palette.iter() {
+            let [red, green, blue] = to_srgb_u16(color);
+
+            for v in [0, red, green, blue, 0] {
+                out.extend_from_slice(&v.to_be_bytes());
+            }
+
+            if version == 2 {
+                let name = name.unwrap_or_default();
+                let len = name.encode_utf16().count() as u32 + 1;
+                out.extend_from_slice(&len.to_be_bytes());
+                write_utf16(&mut out, name);
+            }
+        }
+    }
+
+    out
+}
+
+const ASE_GROUP_START: u16 = 0xC001;
+const ASE_GROUP_END: u16 = 0xC002;
+const ASE_COLOR: u16 = 0x0001;
+
+fn decode_ase(data: &[u8]) -> Result<Palette, PaletteError> {
+    let mut reader = Reader {
+        format: PaletteFormat::Ase,
+        data,
+    };
+
+    if reader.take(4)? != b"ASEF" {
+        return Err(invalid(PaletteFormat::Ase, "Missing ASEF signature"));
+    }
+
+    let major = reader.read_u16()?;
+    let _minor = reader.read_u16()?;
+    if major != 1 {
+        return Err(PaletteError::Unsupported {
+            format: PaletteFormat::Ase,
+            message: format!("Version {}", major),
+        });
+    }
+
+    let blocks = reader.read_u32()?;
+    let mut palette = Palette::new();
+
+    for _ in 0..blocks {
+        let block_type = reader.read_u16()?;
+        let len = reader.read_u32()? as usize;
+
+        let mut block = Reader {
+            format: PaletteFormat::Ase,
+            data: reader.take(len)?,
+        };
+
+        match block_type {
+            ASE_GROUP_START => {
+                let len = block.read_u16()? as usize;
+                let name = block.read_utf16(len)?;
+
+                if palette.name.is_none() {
+                    palette.name = non_empty(name);
+                }
+            }
+            ASE_COLOR => {
+                let len = block.read_u16()? as usize;
+                let name = block.read_utf16(len)?;
+                let model = block.take(4)?;
+
+                let color = match model {
+                    b"RGB " => from_srgb(block.read_f32()?, block.read_f32()?, block.read_f32()?),
+                    b"CMYK" => from_cmyk(
+                        block.read_f32()?,
+                        block.read_f32()?,
+                        block.read_f32()?,
+                        block.read_f32()?,
+                    ),
+                    b"LAB " => Lab::<D50, O2>::new(
+                        block.read_f32()? * 100.0,
+                        block.read_f32()?,
+                        block.read_f32()?,
+                    )
+                    .to_rgb(),
+                    b"Gray" => {
+                        let v = block.read_f32()?;
+                        from_srgb(v, v, v)
+                    }
+                    _ => {
+                        return Err(PaletteError::Unsupported {
+                            format: PaletteFormat::Ase,
+                            message: format!(
+                                "Color model {}",
+                                String::from_utf8_lossy(model).trim()
+                            ),
+                        })
+                    }
+                };
+
+                palette.push(color, non_empty(name).as_deref());
+            }
+            _ => {}
+        }
+    }
+
+    Ok(palette)
+}
+
+fn ase_block(out: &mut Vec<u8>, block_type: u16, data: &[u8]) {
+    out.extend_from_slice(&block_type.to_be_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+fn ase_name(out: &mut Vec<u8>, name: &str) {
+    let len = name.encode_utf16().count() as u16 + 1;
+    out.extend_from_slice(&len.to_be_bytes());
+    write_utf16(out, name);
+}
+
+fn encode_ase(palette: &Palette) -> Vec<u8> {
+    let mut blocks = vec![];
+    let mut count = palette.len() as u32;
+
+    // The name of the palette is stored as a group around all colors
+    if let Some(name) = &palette.name {
+        let mut data = vec![];
+        ase_name(&mut data, name);
+        ase_block(&mut blocks, ASE_GROUP_START, &data);
+        count += 2;
+    }
+
+    for (color, name) in palette.iter() {
+        let c = color.to_srgb();
+
+        let mut data = vec![];
+        ase_name(&mut data, name.unwrap_or_default());
+        data.extend_from_slice(b"RGB ");
+        for v in [c.red(), c.green(), c.blue()] {
+            data.extend_from_slice(&v.clamp(0.0, 1.0).to_be_bytes());
+        }
+        // Normal color instead of a global or spot color
+        data.extend_from_slice(&2u16.to_be_bytes());
+
+        ase_block(&mut blocks, ASE_COLOR, &data);
+    }
+
+    if palette.name.is_some() {
+        ase_block(&mut blocks, ASE_GROUP_END, &[]);
+    }
+
+    let mut out = b"ASEF".to_vec();
+    out.extend_from_slice(&1u16.to_be_bytes());
+    out.extend_from_slice(&0u16.to_be_bytes());
+    out.extend_from_slice(&count.to_be_bytes());
+    out.extend(blocks);
+
+    out
+}
+
+pub fn decode_palette(data: &[u8], format: PaletteFormat) -> Result<Palette, PaletteError> {
+    match format {
+        PaletteFormat::Gpl => decode_gpl(data),
+        PaletteFormat::Aco => decode_aco(data),
+        PaletteFormat::Ase => decode_ase(data),
+    }
+}
+
+pub fn encode_palette(palette: &Palette, format: PaletteFormat) -> Vec<u8> {
+    match format {
+        PaletteFormat::Gpl => encode_gpl(palette),
+        PaletteFormat::Aco => encode_aco(palette),
+        PaletteFormat::Ase => encode_ase(palette),
+    }
+}
+
+fn format_from_path(path: &Path) -> Result<PaletteFormat, PaletteError> {
+    PaletteFormat::from_path(path)
+        .ok_or_else(|| PaletteError::BadFileExtension(path.to_string_lossy().to_string()))
+}
+
+/// Load a palette file with the format detected from the file extension
+pub fn load_palette<P>(path: P) -> Result<Palette, PaletteError>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let format = format_from_path(path)?;
+
+    let mut data = vec![];
+    File::open(path)?.read_to_end(&mut data)?;
+
+    decode_palette(&data, format)
+}
+
+/// Save a palette file with the format detected from the file extension
+pub fn save_palette<P>(path: P, palette: &Palette) -> Result<(), PaletteError>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let format = format_from_path(path)?;
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(&encode_palette(palette, format))?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_palette() -> Palette {
+        let mut palette = Palette::new();
+        palette.name = Some("Test Palette".to_owned());
+        palette.push(Rgb::RED, Some("Red"));
+        palette.push(from_srgb(0.0, 0.5, 1.0), None);
+        palette.push(Rgb::WHITE, Some("Weiß und Grün"));
+        palette
+    }
+
+    fn assert_similar(a: &Palette, b: &Palette) {
+        assert_eq!(a.len(), b.len());
+
+        for ((c1, n1), (c2, n2)) in a.iter().zip(b.iter()) {
+            assert_eq!(to_srgb_u8(c1), to_srgb_u8(c2));
+            assert_eq!(n1, n2);
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let palette = test_palette();
+
+        for format in [PaletteFormat::Gpl, PaletteFormat::Aco, PaletteFormat::Ase] {
+            let data = encode_palette(&palette, format);
+            let decoded = decode_palette(&data, format).unwrap();
+
+            assert_similar(&decoded, &palette);
+
+            if format != PaletteFormat::Aco {
+                assert_eq!(decoded.name.as_deref(), Some("Test Palette"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_gpl() {
+        let data = b"GIMP Palette\nName: Basic\nColumns: 2\n# comment\n255   0   0\tBright Red\n  0 0 255\n";
+        let palette = decode_palette(data, PaletteFormat::Gpl).unwrap();
+
+        assert_eq!(palette.name.as_deref(), Some("Basic"));
+        assert_eq!(palette.colors(), &[Rgb::RED, Rgb::BLUE]);
+        assert_eq!(palette.color_name(0), Some("Bright Red"));
+        assert_eq!(palette.color_name(1), None);
+
+        assert!(decode_palette(b"255 0 0\n", PaletteFormat::Gpl).is_err());
+        assert!(decode_palette(b"GIMP Palette\n300 0 0\n", PaletteFormat::Gpl).is_err());
+    }
+
+    #[test]
+    fn test_decode_aco_color_spaces() {
+        let mut data = vec![];
+        for v in [1u16, 3] {
+            data.extend_from_slice(&v.to_be_bytes());
+        }
+        // CMYK with full yellow and magenta ink, lab white and 100% black ink
+        for v in [
+            2u16, 65535, 0, 0, 65535, 7, 10000, 0, 0, 0, 8, 10000, 0, 0, 0,
+        ] {
+            data.extend_from_slice(&v.to_be_bytes());
+        }
+
+        let palette = decode_palette(&data, PaletteFormat::Aco).unwrap();
+        assert_eq!(to_srgb_u8(&palette.colors()[0]), [255, 0, 0]);
+        assert_eq!(to_srgb_u8(&palette.colors()[1]), [255, 255, 255]);
+        assert_eq!(to_srgb_u8(&palette.colors()[2]), [0, 0, 0]);
+
+        assert!(decode_palette(&data[..12], PaletteFormat::Aco).is_err());
+    }
+
+    #[test]
+    fn test_decode_ase_errors() {
+        assert!(matches!(
+            decode_palette(b"ASEX\0\x01\0\0\0\0\0\0", PaletteFormat::Ase),
+            Err(PaletteError::InvalidData { .. })
+        ));
+        assert!(matches!(
+            decode_palette(b"ASEF\0\x02\0\0\0\0\0\0", PaletteFormat::Ase),
+            Err(PaletteError::Unsupported { .. })
+        ));
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            PaletteFormat::from_path(Path::new("colors.GPL")),
+            Some(PaletteFormat::Gpl)
+        );
+        assert_eq!(PaletteFormat::from_path(Path::new("colors.png")), None);
+        assert!(matches!(
+            load_palette("colors.png"),
+            Err(PaletteError::BadFileExtension(_))
+        ));
+    }
+}
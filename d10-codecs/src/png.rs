@@ -1,18 +1,24 @@
+use std::collections::HashMap;
 use std::io::{BufRead, Read, Seek, Write};
 use std::str::FromStr;
+use std::time::Duration;
 
 use png::{
-    BitDepth, ColorType, Decoder, DecodingError as PngDecodingError, Encoder,
-    EncodingError as PngEncodingError,
+    BitDepth, BlendOp, ColorType, Decoder, DecodingError as PngDecodingError, DisposeOp, Encoder,
+    EncodingError as PngEncodingError, Reader, StreamWriter,
 };
 use png::{Compression, FilterType};
 
+use flate2::write::ZlibEncoder;
+
 use d10_core::color::{Color, Rgb, Srgb};
 use d10_core::errors::ParseEnumError;
+use d10_core::palette::Palette;
 use d10_core::pixelbuffer::{is_valid_buffer_size, PixelBuffer};
+use d10_core::region::Region;
 
 use crate::utils::*;
-use crate::{DecodedImage, DecodingError, EncodingError};
+use crate::{AnimationFrame, DecodedAnimation, DecodedImage, DecodingError, EncodingError, Format};
 
 #[derive(Copy, Clone, Debug)]
 pub enum PngColorType {
@@ -116,214 +122,997 @@ impl FromStr for PngCompression {
 fn encode_error(err: PngEncodingError) -> EncodingError {
     match err {
         PngEncodingError::IoError(err) => EncodingError::IoError(err),
-        err => EncodingError::Encoding(err.to_string()),
+        err => EncodingError::codec(Format::Png, err),
+    }
+}
+
+/// Convert pixels into the raw data of a color type
+fn png_data(data: &[Rgb], color_type: PngColorType) -> (Vec<u8>, ColorType, BitDepth) {
+    match color_type {
+        PngColorType::L8 => (to_l8_vec(data), ColorType::Grayscale, BitDepth::Eight),
+        PngColorType::La8 => (to_la8_vec(data), ColorType::GrayscaleAlpha, BitDepth::Eight),
+        PngColorType::L16 => (to_l16_be_vec(data), ColorType::Grayscale, BitDepth::Sixteen),
+        PngColorType::La16 => (
+            to_la16_be_vec(data),
+            ColorType::GrayscaleAlpha,
+            BitDepth::Sixteen,
+        ),
+        PngColorType::Rgb8 => (to_rgb8_vec(data), ColorType::Rgb, BitDepth::Eight),
+        PngColorType::Rgba8 => (to_rgba8_vec(data), ColorType::Rgba, BitDepth::Eight),
+        PngColorType::Rgb16 => (to_rgb16_be_vec(data), ColorType::Rgb, BitDepth::Sixteen),
+        PngColorType::Rgba16 => (to_rgba16_be_vec(data), ColorType::Rgba, BitDepth::Sixteen),
     }
 }
 
+/// Maximal number of colors in the palette of an indexed png
+const MAX_PALETTE_COLORS: usize = 256;
+
+/// Raw data of an indexed image together with the data of the PLTE and tRNS chunks
+struct IndexedData {
+    indices: Vec<u8>,
+    plte: Vec<u8>,
+    trns: Vec<u8>,
+}
+
+/// Map every pixel to the index of the nearest color of the palette
+fn indexed_data(data: &[Rgb], palette: &Palette) -> Result<IndexedData, EncodingError> {
+    if palette.is_empty() || palette.len() > MAX_PALETTE_COLORS {
+        return Err(EncodingError::InvalidConfig {
+            format: Format::Png,
+            message: format!(
+                "Palette must have between 1 and {} colors, got {}",
+                MAX_PALETTE_COLORS,
+                palette.len()
+            ),
+        });
+    }
+
+    let colors = to_rgba8_vec(palette.colors());
+    let colors: Vec<&[u8]> = colors.chunks_exact(4).collect();
+
+    let nearest = |pixel: &[u8]| {
+        colors
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, c)| {
+                c.iter()
+                    .zip(pixel)
+                    .map(|(a, b)| (*a as i32 - *b as i32).pow(2))
+                    .sum::<i32>()
+            })
+            .map_or(0, |(i, _)| i as u8)
+    };
+
+    // Images usually contain far less distinct colors than pixels
+    let mut cache: HashMap<&[u8], u8> = HashMap::new();
+
+    let pixels = to_rgba8_vec(data);
+    let indices = pixels
+        .chunks_exact(4)
+        .map(|pixel| *cache.entry(pixel).or_insert_with(|| nearest(pixel)))
+        .collect();
+
+    let plte = colors.iter().flat_map(|c| &c[..3]).copied().collect();
+
+    // Trailing opaque entries can be omitted from tRNS
+    let trns_len = colors
+        .iter()
+        .rposition(|c| c[3] != 255)
+        .map_or(0, |i| i + 1);
+    let trns = colors[..trns_len].iter().map(|c| c[3]).collect();
+
+    Ok(IndexedData {
+        indices,
+        plte,
+        trns,
+    })
+}
+
+fn create_encoder<W>(
+    w: W,
+    width: u32,
+    height: u32,
+    color_type: PngColorType,
+    compression: PngCompression,
+    filter: PngFilterType,
+) -> Encoder<'static, W>
+where
+    W: Write,
+{
+    let (_, png_color_type, bit_depth) = png_data(&[], color_type);
+
+    let mut encoder = Encoder::new(w, width, height);
+
+    encoder.set_color(png_color_type);
+    encoder.set_depth(bit_depth);
+    encoder.set_compression(compression.into());
+    encoder.set_filter(filter.into());
+
+    encoder
+}
+
 pub(crate) fn encode_png<W>(
     w: W,
     buffer: &PixelBuffer<Rgb>,
     color_type: PngColorType,
     compression: PngCompression,
     filter: PngFilterType,
+    interlaced: bool,
 ) -> Result<(), EncodingError>
 where
     W: Write,
 {
-    let (out, color_type, bit_depth) = match color_type {
-        PngColorType::L8 => (to_l8_vec(buffer), ColorType::Grayscale, BitDepth::Eight),
-        PngColorType::La8 => (
-            to_la8_vec(buffer),
-            ColorType::GrayscaleAlpha,
-            BitDepth::Eight,
-        ),
-        PngColorType::L16 => (
-            to_l16_be_vec(buffer),
-            ColorType::Grayscale,
-            BitDepth::Sixteen,
-        ),
-        PngColorType::La16 => (
-            to_la16_be_vec(buffer),
-            ColorType::GrayscaleAlpha,
-            BitDepth::Sixteen,
-        ),
-        PngColorType::Rgb8 => (to_rgb8_vec(buffer), ColorType::Rgb, BitDepth::Eight),
-        PngColorType::Rgba8 => (to_rgba8_vec(buffer), ColorType::Rgba, BitDepth::Eight),
-        PngColorType::Rgb16 => (to_rgb16_be_vec(buffer), ColorType::Rgb, BitDepth::Sixteen),
-        PngColorType::Rgba16 => (to_rgba16_be_vec(buffer), ColorType::Rgba, BitDepth::Sixteen),
-    };
+    if interlaced {
+        return encode_png_interlaced(w, buffer, color_type, compression, filter);
+    }
+
+    let (out, _, _) = png_data(buffer.data(), color_type);
+
+    let encoder = create_encoder(
+        w,
+        buffer.width(),
+        buffer.height(),
+        color_type,
+        compression,
+        filter,
+    );
+
+    let mut writer = encoder.write_header().map_err(encode_error)?;
+    writer.write_image_data(&out).map_err(encode_error)?;
+
+    Ok(())
+}
+
+/// Encode an indexed png with the colors of `palette`
+///
+/// Every pixel is mapped to the nearest color of the palette including its alpha value.
+pub(crate) fn encode_png_indexed<W>(
+    w: W,
+    buffer: &PixelBuffer<Rgb>,
+    palette: &Palette,
+    compression: PngCompression,
+    filter: PngFilterType,
+    interlaced: bool,
+) -> Result<(), EncodingError>
+where
+    W: Write,
+{
+    let data = indexed_data(buffer.data(), palette)?;
+
+    if interlaced {
+        return write_png_interlaced(
+            w,
+            buffer.width(),
+            buffer.height(),
+            (&data.indices, ColorType::Indexed, BitDepth::Eight),
+            Some(&data),
+            compression,
+            filter,
+        );
+    }
 
     let mut encoder = Encoder::new(w, buffer.width(), buffer.height());
 
-    encoder.set_color(color_type);
-    encoder.set_depth(bit_depth);
+    encoder.set_color(ColorType::Indexed);
+    encoder.set_depth(BitDepth::Eight);
+    encoder.set_palette(data.plte);
+    if !data.trns.is_empty() {
+        encoder.set_trns(data.trns);
+    }
     encoder.set_compression(compression.into());
     encoder.set_filter(filter.into());
 
     let mut writer = encoder.write_header().map_err(encode_error)?;
-    writer.write_image_data(&out).map_err(encode_error)?;
+    writer
+        .write_image_data(&data.indices)
+        .map_err(encode_error)?;
+
+    Ok(())
+}
+
+/// Encode fully composed frames of the same size as APNG
+///
+/// The first frame is the default image shown by decoders without APNG support. Every following
+/// frame only stores the area that changed since the previous frame. If all changed pixels are
+/// opaque, the unchanged pixels in that area are made transparent and the frame is blended over
+/// the previous one, which compresses better. Interlacing isn't supported for animations.
+pub(crate) fn encode_png_animation<W>(
+    w: W,
+    frames: &[(&PixelBuffer<Rgb>, Duration)],
+    loop_count: u32,
+    color_type: PngColorType,
+    compression: PngCompression,
+    filter: PngFilterType,
+) -> Result<(), EncodingError>
+where
+    W: Write,
+{
+    let Some((first, _)) = frames.first() else {
+        return Err(EncodingError::InvalidConfig {
+            format: Format::Png,
+            message: "Animation has no frames".to_owned(),
+        });
+    };
+
+    let width = first.width();
+    let height = first.height();
+
+    if let Some((buffer, _)) = frames
+        .iter()
+        .find(|(b, _)| b.width() != width || b.height() != height)
+    {
+        return Err(EncodingError::InvalidConfig {
+            format: Format::Png,
+            message: format!(
+                "All frames must have a size of {}x{}, got {}x{}",
+                width,
+                height,
+                buffer.width(),
+                buffer.height()
+            ),
+        });
+    }
+
+    let mut encoder = create_encoder(w, width, height, color_type, compression, filter);
+    encoder
+        .set_animated(frames.len() as u32, loop_count)
+        .map_err(encode_error)?;
+
+    let (_, png_color_type, bit_depth) = png_data(&[], color_type);
+    let bytes_per_pixel = png_color_type.samples() * (bit_depth as usize / 8);
+    let alpha_bytes = match png_color_type {
+        ColorType::Rgba | ColorType::GrayscaleAlpha => bit_depth as usize / 8,
+        _ => 0,
+    };
+
+    let mut writer = encoder.write_header().map_err(encode_error)?;
+    let mut previous: Option<Vec<u8>> = None;
+
+    for (buffer, delay) in frames {
+        let (data, _, _) = png_data(buffer.data(), color_type);
+
+        // Delays are stored in milliseconds, so longer ones are cut off
+        let delay = delay.as_millis().min(u16::MAX as u128) as u16;
+        writer.set_frame_delay(delay, 1000).map_err(encode_error)?;
+
+        let Some(prev) = &previous else {
+            writer.write_image_data(&data).map_err(encode_error)?;
+            previous = Some(data);
+            continue;
+        };
+
+        let pixels: Vec<_> = data.chunks_exact(bytes_per_pixel).collect();
+        let prev_pixels: Vec<_> = prev.chunks_exact(bytes_per_pixel).collect();
+        let changed = |i: usize| pixels[i] != prev_pixels[i];
+
+        let rect = changed_rect(width, height, changed).unwrap_or(Region::new(0, 0, 1, 1));
+
+        let blend_over = alpha_bytes > 0
+            && (0..pixels.len()).filter(|i| changed(*i)).all(|i| {
+                pixels[i][bytes_per_pixel - alpha_bytes..]
+                    .iter()
+                    .all(|v| *v == 255)
+            });
+
+        let mut out = Vec::with_capacity((rect.width * rect.height) as usize * bytes_per_pixel);
+        for y in rect.y..rect.y + rect.height {
+            for x in rect.x..rect.x + rect.width {
+                let i = (y * width + x) as usize;
+
+                if blend_over && !changed(i) {
+                    out.resize(out.len() + bytes_per_pixel, 0);
+                } else {
+                    out.extend_from_slice(pixels[i]);
+                }
+            }
+        }
+
+        // Reset the position first because the new size must fit at the old position
+        writer.set_frame_position(0, 0).map_err(encode_error)?;
+        writer
+            .set_frame_dimension(rect.width, rect.height)
+            .map_err(encode_error)?;
+        writer
+            .set_frame_position(rect.x, rect.y)
+            .map_err(encode_error)?;
+        writer
+            .set_blend_op(if blend_over {
+                BlendOp::Over
+            } else {
+                BlendOp::Source
+            })
+            .map_err(encode_error)?;
+        writer.write_image_data(&out).map_err(encode_error)?;
+
+        previous = Some(data);
+    }
+
+    writer.finish().map_err(encode_error)?;
+
+    Ok(())
+}
+
+/// Apply a png filter to a row with `prev` being the unfiltered previous row of the same pass
+fn filter_row(filter: PngFilterType, bpp: usize, row: &[u8], prev: &[u8], out: &mut Vec<u8>) {
+    out.push(match filter {
+        PngFilterType::NoFilter => 0,
+        PngFilterType::Sub => 1,
+        PngFilterType::Up => 2,
+        PngFilterType::Avg => 3,
+        PngFilterType::Paeth => 4,
+    });
+
+    for (i, (&value, &b)) in row.iter().zip(prev.iter()).enumerate() {
+        let a = if i >= bpp { row[i - bpp] } else { 0 };
+        let c = if i >= bpp { prev[i - bpp] } else { 0 };
+
+        let prediction = match filter {
+            PngFilterType::NoFilter => 0,
+            PngFilterType::Sub => a,
+            PngFilterType::Up => b,
+            PngFilterType::Avg => ((a as u16 + b as u16) / 2) as u8,
+            PngFilterType::Paeth => paeth(a, b, c),
+        };
+
+        out.push(value.wrapping_sub(prediction));
+    }
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+fn write_chunk<W: Write>(w: &mut W, name: &[u8; 4], data: &[u8]) -> Result<(), EncodingError> {
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(name);
+    crc.update(data);
+
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    w.write_all(name)?;
+    w.write_all(data)?;
+    w.write_all(&crc.finalize().to_be_bytes())?;
+
+    Ok(())
+}
+
+/// Maximal size of the data in a single IDAT chunk
+const MAX_IDAT_SIZE: usize = 1 << 18;
+
+/// Encode a png with Adam7 interlacing
+///
+/// The png crate can't write interlaced images so the chunks are written directly.
+fn encode_png_interlaced<W>(
+    w: W,
+    buffer: &PixelBuffer<Rgb>,
+    color_type: PngColorType,
+    compression: PngCompression,
+    filter: PngFilterType,
+) -> Result<(), EncodingError>
+where
+    W: Write,
+{
+    let (data, png_color_type, bit_depth) = png_data(buffer.data(), color_type);
+
+    write_png_interlaced(
+        w,
+        buffer.width(),
+        buffer.height(),
+        (&data, png_color_type, bit_depth),
+        None,
+        compression,
+        filter,
+    )
+}
+
+/// Write the chunks of an interlaced png from the raw data of all pixels
+fn write_png_interlaced<W>(
+    mut w: W,
+    width: u32,
+    height: u32,
+    (data, png_color_type, bit_depth): (&[u8], ColorType, BitDepth),
+    indexed: Option<&IndexedData>,
+    compression: PngCompression,
+    filter: PngFilterType,
+) -> Result<(), EncodingError>
+where
+    W: Write,
+{
+    if width == 0 || height == 0 {
+        return Err(EncodingError::BadDimensions {
+            format: Format::Png,
+            width,
+            height,
+        });
+    }
+
+    let bpp = png_color_type.samples() * bit_depth as usize / 8;
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[bit_depth as u8, png_color_type as u8, 0, 0, 1]);
+
+    let level = match compression {
+        PngCompression::Fast => flate2::Compression::fast(),
+        PngCompression::Default => flate2::Compression::default(),
+        PngCompression::Best => flate2::Compression::best(),
+    };
+
+    let mut zlib = ZlibEncoder::new(vec![], level);
+    let mut filtered = vec![];
+
+    for &(x0, y0, dx, dy) in &ADAM7_PASSES {
+        // Passes without any pixel are omitted completely
+        if x0 >= width || y0 >= height {
+            continue;
+        }
+
+        let pass_width = (width - x0).div_ceil(dx) as usize;
+        let mut prev = vec![0u8; pass_width * bpp];
+        let mut row = Vec::with_capacity(pass_width * bpp);
+
+        for y in (y0..height).step_by(dy as usize) {
+            row.clear();
+
+            for x in (x0..width).step_by(dx as usize) {
+                let start = (y * width + x) as usize * bpp;
+                row.extend_from_slice(&data[start..start + bpp]);
+            }
+
+            filtered.clear();
+            filter_row(filter, bpp, &row, &prev, &mut filtered);
+            zlib.write_all(&filtered)?;
+
+            std::mem::swap(&mut prev, &mut row);
+        }
+    }
+
+    let compressed = zlib.finish()?;
+
+    w.write_all(&[137, 80, 78, 71, 13, 10, 26, 10])?;
+    write_chunk(&mut w, b"IHDR", &header)?;
+
+    if let Some(indexed) = indexed {
+        write_chunk(&mut w, b"PLTE", &indexed.plte)?;
+        if !indexed.trns.is_empty() {
+            write_chunk(&mut w, b"tRNS", &indexed.trns)?;
+        }
+    }
+
+    for chunk in compressed.chunks(MAX_IDAT_SIZE) {
+        write_chunk(&mut w, b"IDAT", chunk)?;
+    }
+
+    write_chunk(&mut w, b"IEND", &[])?;
 
     Ok(())
 }
 
+/// Png encoder that accepts the image row by row
+///
+/// This allows writing images that never exist completely in memory, e.g. when they are rendered in tiles.
+/// The writer is owned by the encoder and flushed by `finish()`.
+pub struct PngStreamEncoder<W: Write + 'static> {
+    writer: StreamWriter<'static, W>,
+    width: u32,
+    height: u32,
+    rows_written: u32,
+    color_type: PngColorType,
+}
+
+impl<W: Write + 'static> PngStreamEncoder<W> {
+    /// Write the png header and prepare the encoder for `height` rows of `width` pixels
+    pub fn new(
+        w: W,
+        width: u32,
+        height: u32,
+        color_type: PngColorType,
+        compression: PngCompression,
+        filter: PngFilterType,
+    ) -> Result<PngStreamEncoder<W>, EncodingError> {
+        let encoder = create_encoder(w, width, height, color_type, compression, filter);
+
+        let writer = encoder
+            .write_header()
+            .and_then(|writer| writer.into_stream_writer())
+            .map_err(encode_error)?;
+
+        Ok(PngStreamEncoder {
+            writer,
+            width,
+            height,
+            rows_written: 0,
+            color_type,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Number of rows that were written so far
+    pub fn rows_written(&self) -> u32 {
+        self.rows_written
+    }
+
+    /// Write one or more complete rows
+    ///
+    /// The length of `rows` must be a multiple of the image width.
+    pub fn write_rows(&mut self, rows: &[Rgb]) -> Result<(), EncodingError> {
+        let count = check_rows(
+            Format::Png,
+            rows.len(),
+            self.width,
+            self.height,
+            self.rows_written,
+        )?;
+
+        let (data, _, _) = png_data(rows, self.color_type);
+        self.writer.write_all(&data)?;
+        self.rows_written += count;
+
+        Ok(())
+    }
+
+    /// Finish the image after all rows were written
+    pub fn finish(self) -> Result<(), EncodingError> {
+        if self.rows_written != self.height {
+            return Err(EncodingError::RowCount {
+                format: Format::Png,
+                expected: self.height,
+                written: self.rows_written,
+            });
+        }
+
+        self.writer.finish().map_err(encode_error)
+    }
+}
+
 fn decode_error(err: PngDecodingError) -> DecodingError {
     match err {
         PngDecodingError::IoError(err) => DecodingError::IoError(err),
-        err => DecodingError::Decoding(err.to_string()),
+        err => DecodingError::codec(Format::Png, err),
     }
 }
 
-pub(crate) fn decode_png<T>(reader: T) -> Result<DecodedImage, DecodingError>
+/// Number of bytes per pixel and a function to convert them
+type PixelReader = (usize, fn(&[u8]) -> Rgb);
+
+/// Get the pixel reader for the output format of the decoder
+fn pixel_reader(color_type: ColorType, bits: BitDepth) -> Result<PixelReader, DecodingError> {
+    Ok(match (color_type, bits) {
+        (ColorType::Rgba, BitDepth::Eight) => (4, |chunks| {
+            Srgb::new_with_alpha(
+                from_u8(chunks[0]),
+                from_u8(chunks[1]),
+                from_u8(chunks[2]),
+                from_u8(chunks[3]),
+            )
+            .to_rgb()
+        }),
+        (ColorType::Rgb, BitDepth::Eight) => (3, |chunks| {
+            Srgb::new(from_u8(chunks[0]), from_u8(chunks[1]), from_u8(chunks[2])).to_rgb()
+        }),
+        (ColorType::Grayscale, BitDepth::Eight) => (1, |chunks| {
+            Srgb::new(from_u8(chunks[0]), from_u8(chunks[0]), from_u8(chunks[0])).to_rgb()
+        }),
+        (ColorType::GrayscaleAlpha, BitDepth::Eight) => (2, |chunks| {
+            Srgb::new_with_alpha(
+                from_u8(chunks[0]),
+                from_u8(chunks[0]),
+                from_u8(chunks[0]),
+                from_u8(chunks[1]),
+            )
+            .to_rgb()
+        }),
+        (ColorType::Rgba, BitDepth::Sixteen) => (8, |chunks| {
+            Srgb::new_with_alpha(
+                from_u16_be([chunks[0], chunks[1]]),
+                from_u16_be([chunks[2], chunks[3]]),
+                from_u16_be([chunks[4], chunks[5]]),
+                from_u16_be([chunks[6], chunks[7]]),
+            )
+            .to_rgb()
+        }),
+        (ColorType::Rgb, BitDepth::Sixteen) => (6, |chunks| {
+            Srgb::new(
+                from_u16_be([chunks[0], chunks[1]]),
+                from_u16_be([chunks[2], chunks[3]]),
+                from_u16_be([chunks[4], chunks[5]]),
+            )
+            .to_rgb()
+        }),
+        (ColorType::Grayscale, BitDepth::Sixteen) => (2, |chunks| {
+            Srgb::new(
+                from_u16_be([chunks[0], chunks[1]]),
+                from_u16_be([chunks[0], chunks[1]]),
+                from_u16_be([chunks[0], chunks[1]]),
+            )
+            .to_rgb()
+        }),
+        (ColorType::GrayscaleAlpha, BitDepth::Sixteen) => (4, |chunks| {
+            Srgb::new_with_alpha(
+                from_u16_be([chunks[0], chunks[1]]),
+                from_u16_be([chunks[0], chunks[1]]),
+                from_u16_be([chunks[0], chunks[1]]),
+                from_u16_be([chunks[2], chunks[3]]),
+            )
+            .to_rgb()
+        }),
+        _ => {
+            return Err(DecodingError::Unsupported {
+                format: Format::Png,
+                message: format!("{:?}:{:?}", color_type, bits),
+            })
+        }
+    })
+}
+
+fn read_info<T>(reader: T) -> Result<Reader<T>, DecodingError>
 where
     T: Read + Seek + BufRead,
 {
     let mut decoder = Decoder::new(reader);
     decoder.set_transformations(png::Transformations::EXPAND);
 
-    let mut reader = decoder.read_info().map_err(decode_error)?;
+    let reader = decoder.read_info().map_err(decode_error)?;
 
-    let (color_type, bits) = reader.output_color_type();
     let info = reader.info();
 
-    let width = info.width;
-    let height = info.height;
+    if !is_valid_buffer_size(info.width, info.height) {
+        return Err(DecodingError::InvalidBufferSize {
+            width: info.width,
+            height: info.height,
+        });
+    }
+
+    Ok(reader)
+}
 
-    if !is_valid_buffer_size(width, height) {
-        return Err(DecodingError::InvalidBufferSize { width, height });
+fn decode_frame<T>(mut reader: Reader<T>) -> Result<DecodedImage, DecodingError>
+where
+    T: Read + Seek + BufRead,
+{
+    let (color_type, bits) = reader.output_color_type();
+    let (bytes_per_pixel, to_rgb) = pixel_reader(color_type, bits)?;
+
+    let width = reader.info().width;
+    let height = reader.info().height;
+
+    let mut buffer = vec![0u8; (width * height) as usize * bytes_per_pixel];
+    reader.next_frame(&mut buffer).map_err(decode_error)?;
+
+    let raw = buffer.chunks(bytes_per_pixel).map(to_rgb).collect();
+
+    Ok(DecodedImage {
+        buffer: PixelBuffer::new_from_raw(width, height, raw),
+    })
+}
+
+pub(crate) fn decode_png<T>(reader: T) -> Result<DecodedImage, DecodingError>
+where
+    T: Read + Seek + BufRead,
+{
+    decode_frame(read_info(reader)?)
+}
+
+/// Alpha compositing of `top` over `bottom`
+fn blend_over(bottom: Rgb, top: Rgb) -> Rgb {
+    let alpha = top.alpha() + bottom.alpha() * (1.0 - top.alpha());
+
+    if alpha <= 0.0 {
+        return Rgb::NONE;
     }
 
-    let num_pixel = (width * height) as usize;
+    let channel =
+        |t: f32, b: f32| (t * top.alpha() + b * bottom.alpha() * (1.0 - top.alpha())) / alpha;
 
-    let raw: Vec<Rgb> = match (color_type, bits) {
-        (ColorType::Rgba, BitDepth::Eight) => {
-            let mut buffer = vec![0u8; num_pixel * 4];
-            reader.next_frame(&mut buffer).map_err(decode_error)?;
+    Rgb::new_with_alpha(
+        channel(top.red(), bottom.red()),
+        channel(top.green(), bottom.green()),
+        channel(top.blue(), bottom.blue()),
+        alpha,
+    )
+}
 
-            buffer
-                .chunks(4)
-                .map(|chunks| {
-                    Srgb::new_with_alpha(
-                        from_u8(chunks[0]),
-                        from_u8(chunks[1]),
-                        from_u8(chunks[2]),
-                        from_u8(chunks[3]),
-                    )
-                    .to_rgb()
-                })
-                .collect()
-        }
-        (ColorType::Rgb, BitDepth::Eight) => {
-            let mut buffer = vec![0u8; num_pixel * 3];
-            reader.next_frame(&mut buffer).map_err(decode_error)?;
-
-            buffer
-                .chunks(3)
-                .map(|chunks| {
-                    Srgb::new(from_u8(chunks[0]), from_u8(chunks[1]), from_u8(chunks[2])).to_rgb()
-                })
-                .collect()
-        }
-        (ColorType::Grayscale, BitDepth::Eight) => {
-            let mut buffer = vec![0u8; num_pixel];
-            reader.next_frame(&mut buffer).map_err(decode_error)?;
-
-            buffer
-                .iter()
-                .map(|v| Srgb::new(from_u8(*v), from_u8(*v), from_u8(*v)).to_rgb())
-                .collect()
-        }
-        (ColorType::GrayscaleAlpha, BitDepth::Eight) => {
-            let mut buffer = vec![0u8; num_pixel * 2];
-            reader.next_frame(&mut buffer).map_err(decode_error)?;
-
-            buffer
-                .chunks(2)
-                .map(|chunks| {
-                    Srgb::new_with_alpha(
-                        from_u8(chunks[0]),
-                        from_u8(chunks[0]),
-                        from_u8(chunks[0]),
-                        from_u8(chunks[1]),
-                    )
-                    .to_rgb()
-                })
-                .collect()
+/// Decode all frames of an APNG composed onto the canvas
+///
+/// A default image that isn't part of the animation is skipped. Images without animation result
+/// in a single frame.
+pub(crate) fn decode_png_animation<T>(reader: T) -> Result<DecodedAnimation, DecodingError>
+where
+    T: Read + Seek + BufRead,
+{
+    let mut reader = read_info(reader)?;
+
+    let Some(animation) = reader.info().animation_control else {
+        return Ok(DecodedAnimation {
+            frames: vec![AnimationFrame {
+                buffer: decode_frame(reader)?.buffer,
+                delay: Duration::ZERO,
+            }],
+            loop_count: 0,
+        });
+    };
+
+    let (color_type, bits) = reader.output_color_type();
+    let (bytes_per_pixel, to_rgb) = pixel_reader(color_type, bits)?;
+
+    let width = reader.info().width;
+    let height = reader.info().height;
+
+    let mut buffer = vec![0u8; reader.output_buffer_size()];
+
+    if reader.info().frame_control.is_none() {
+        reader.next_frame(&mut buffer).map_err(decode_error)?;
+    }
+
+    let mut canvas = PixelBuffer::new_with_color(width, height, Rgb::NONE);
+    let mut frames = vec![];
+
+    for _ in 0..animation.num_frames {
+        let output = reader.next_frame(&mut buffer).map_err(decode_error)?;
+
+        let Some(control) = reader.info().frame_control else {
+            break;
+        };
+
+        let rect = Region::new(
+            control.x_offset,
+            control.y_offset,
+            output.width,
+            output.height,
+        );
+
+        // The first frame has no previous state to return to
+        let dispose = match control.dispose_op {
+            DisposeOp::Previous if frames.is_empty() => DisposeOp::Background,
+            dispose => dispose,
+        };
+
+        let restore = (dispose == DisposeOp::Previous).then(|| canvas.clone());
+
+        // Interlaced frames are expanded with the stride of the whole image
+        let stride = if reader.info().interlaced {
+            reader.output_line_size(width)
+        } else {
+            output.line_size
+        };
+
+        for (row, y) in buffer
+            .chunks(stride)
+            .take(output.height as usize)
+            .zip(rect.y..)
+        {
+            for (chunk, x) in row[..output.line_size]
+                .chunks_exact(bytes_per_pixel)
+                .zip(rect.x..)
+            {
+                if x >= width || y >= height {
+                    continue;
+                }
+
+                let color = to_rgb(chunk);
+                let color = match control.blend_op {
+                    BlendOp::Source => color,
+                    BlendOp::Over => blend_over(*canvas.get_pixel(x, y), color),
+                };
+
+                canvas.put_pixel(x, y, color);
+            }
         }
-        (ColorType::Rgba, BitDepth::Sixteen) => {
-            let mut buffer = vec![0u8; num_pixel * 8];
-            reader.next_frame(&mut buffer).map_err(decode_error)?;
-
-            buffer
-                .chunks(8)
-                .map(|chunks| {
-                    Srgb::new_with_alpha(
-                        from_u16_be([chunks[0], chunks[1]]),
-                        from_u16_be([chunks[2], chunks[3]]),
-                        from_u16_be([chunks[4], chunks[5]]),
-                        from_u16_be([chunks[6], chunks[7]]),
-                    )
-                    .to_rgb()
-                })
-                .collect()
+
+        let delay = match (control.delay_num, control.delay_den) {
+            (num, 0) => Duration::from_millis(num as u64 * 10),
+            (num, den) => Duration::from_secs_f64(num as f64 / den as f64),
+        };
+
+        frames.push(AnimationFrame {
+            buffer: canvas.clone(),
+            delay,
+        });
+
+        match dispose {
+            DisposeOp::None => {}
+            DisposeOp::Background => canvas.fill_rect(rect, Rgb::NONE),
+            DisposeOp::Previous => {
+                if let Some(restore) = restore {
+                    canvas = restore;
+                }
+            }
         }
-        (ColorType::Rgb, BitDepth::Sixteen) => {
-            let mut buffer = vec![0u8; num_pixel * 6];
-            reader.next_frame(&mut buffer).map_err(decode_error)?;
-
-            buffer
-                .chunks(6)
-                .map(|chunks| {
-                    Srgb::new(
-                        from_u16_be([chunks[0], chunks[1]]),
-                        from_u16_be([chunks[2], chunks[3]]),
-                        from_u16_be([chunks[4], chunks[5]]),
-                    )
-                    .to_rgb()
-                })
-                .collect()
+    }
+
+    if frames.is_empty() {
+        return Err(DecodingError::InvalidData {
+            format: Format::Png,
+            message: "No animation frame found".to_owned(),
+        });
+    }
+
+    Ok(DecodedAnimation {
+        frames,
+        loop_count: animation.num_plays,
+    })
+}
+
+/// Position and step size of the pixels in each pass of Adam7 interlacing
+const ADAM7_PASSES: [(u32, u32, u32, u32); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+/// Size of the area each pixel of an Adam7 pass covers until it's refined by the following passes
+const ADAM7_BLOCKS: [(u32, u32); 7] = [(8, 8), (4, 8), (4, 4), (2, 4), (2, 2), (1, 2), (1, 1)];
+
+/// Decode the image after every Adam7 pass of an interlaced png
+///
+/// Missing pixels are filled with the nearest pixel of a previous pass.
+/// Images that are not interlaced result in a single fully decoded pass.
+pub(crate) fn decode_png_passes<T>(reader: T) -> Result<Vec<DecodedImage>, DecodingError>
+where
+    T: Read + Seek + BufRead,
+{
+    let mut reader = read_info(reader)?;
+
+    if !reader.info().interlaced {
+        return Ok(vec![decode_frame(reader)?]);
+    }
+
+    let width = reader.info().width;
+    let height = reader.info().height;
+
+    let (color_type, bits) = reader.output_color_type();
+    let (bytes_per_pixel, to_rgb) = pixel_reader(color_type, bits)?;
+
+    let mut buffer = vec![0u8; (width * height) as usize * bytes_per_pixel];
+    let mut passes = vec![];
+
+    for (&(x0, y0, dx, dy), &(block_width, block_height)) in
+        ADAM7_PASSES.iter().zip(ADAM7_BLOCKS.iter())
+    {
+        // Empty passes are skipped by the decoder
+        if x0 >= width || y0 >= height {
+            continue;
         }
-        (ColorType::Grayscale, BitDepth::Sixteen) => {
-            let mut buffer = vec![0u8; num_pixel * 2];
-            reader.next_frame(&mut buffer).map_err(decode_error)?;
-
-            buffer
-                .chunks(2)
-                .map(|chunks| {
-                    Srgb::new(
-                        from_u16_be([chunks[0], chunks[1]]),
-                        from_u16_be([chunks[0], chunks[1]]),
-                        from_u16_be([chunks[0], chunks[1]]),
-                    )
-                    .to_rgb()
-                })
-                .collect()
+
+        let pass_width = (width - x0).div_ceil(dx);
+        let pass_height = (height - y0).div_ceil(dy);
+
+        for line in 0..pass_height {
+            let row = reader
+                .next_interlaced_row()
+                .map_err(decode_error)?
+                .ok_or_else(|| DecodingError::InvalidData {
+                    format: Format::Png,
+                    message: "Missing interlaced row".to_owned(),
+                })?;
+
+            let y = y0 + line * dy;
+
+            for (i, pixel) in row
+                .data()
+                .chunks(bytes_per_pixel)
+                .take(pass_width as usize)
+                .enumerate()
+            {
+                let x = x0 + i as u32 * dx;
+
+                for by in y..(y + block_height).min(height) {
+                    for bx in x..(x + block_width).min(width) {
+                        let start = (by * width + bx) as usize * bytes_per_pixel;
+                        buffer[start..start + bytes_per_pixel].copy_from_slice(pixel);
+                    }
+                }
+            }
         }
-        (ColorType::GrayscaleAlpha, BitDepth::Sixteen) => {
-            let mut buffer = vec![0u8; num_pixel * 4];
-            reader.next_frame(&mut buffer).map_err(decode_error)?;
-
-            buffer
-                .chunks(4)
-                .map(|chunks| {
-                    Srgb::new_with_alpha(
-                        from_u16_be([chunks[0], chunks[1]]),
-                        from_u16_be([chunks[0], chunks[1]]),
-                        from_u16_be([chunks[0], chunks[1]]),
-                        from_u16_be([chunks[2], chunks[3]]),
-                    )
-                    .to_rgb()
-                })
-                .collect()
+
+        let raw = buffer.chunks(bytes_per_pixel).map(to_rgb).collect();
+
+        passes.push(DecodedImage {
+            buffer: PixelBuffer::new_from_raw(width, height, raw),
+        });
+    }
+
+    Ok(passes)
+}
+
+/// Decode a subsampled version of an interlaced png with at least `max_edge` pixels on its longer side
+///
+/// Only the first Adam7 passes required for the scale are decoded.
+/// Images that are not interlaced are fully decoded.
+pub(crate) fn decode_png_thumbnail<T>(
+    reader: T,
+    max_edge: u32,
+) -> Result<DecodedImage, DecodingError>
+where
+    T: Read + Seek + BufRead,
+{
+    let mut reader = read_info(reader)?;
+
+    let width = reader.info().width;
+    let height = reader.info().height;
+
+    let scale = [8, 4, 2]
+        .into_iter()
+        .find(|scale| width.div_ceil(*scale).max(height.div_ceil(*scale)) >= max_edge);
+
+    let scale = match scale {
+        Some(scale) if reader.info().interlaced => scale,
+        _ => return decode_frame(reader),
+    };
+
+    let num_passes = match scale {
+        8 => 1,
+        4 => 3,
+        _ => 5,
+    };
+
+    let (color_type, bits) = reader.output_color_type();
+    let (bytes_per_pixel, to_rgb) = pixel_reader(color_type, bits)?;
+
+    let thumb_width = width.div_ceil(scale);
+    let thumb_height = height.div_ceil(scale);
+
+    let mut buffer = vec![0u8; (thumb_width * thumb_height) as usize * bytes_per_pixel];
+
+    for &(x0, y0, dx, dy) in &ADAM7_PASSES[..num_passes] {
+        // Empty passes are skipped by the decoder
+        if x0 >= width || y0 >= height {
+            continue;
         }
-        _ => {
-            return Err(DecodingError::Decoding(format!(
-                "Unsupported png: {:?}:{:?}",
-                color_type, bits
-            )))
+
+        let pass_width = (width - x0).div_ceil(dx);
+        let pass_height = (height - y0).div_ceil(dy);
+
+        for line in 0..pass_height {
+            let row = reader
+                .next_interlaced_row()
+                .map_err(decode_error)?
+                .ok_or_else(|| DecodingError::InvalidData {
+                    format: Format::Png,
+                    message: "Missing interlaced row".to_owned(),
+                })?;
+
+            let y = (y0 + line * dy) / scale;
+
+            for (i, pixel) in row
+                .data()
+                .chunks(bytes_per_pixel)
+                .take(pass_width as usize)
+                .enumerate()
+            {
+                let x = (x0 + i as u32 * dx) / scale;
+                let start = (y * thumb_width + x) as usize * bytes_per_pixel;
+
+                buffer[start..start + bytes_per_pixel].copy_from_slice(pixel);
+            }
         }
-    };
+    }
+
+    let raw = buffer.chunks(bytes_per_pixel).map(to_rgb).collect();
 
     Ok(DecodedImage {
-        buffer: PixelBuffer::new_from_raw(width, height, raw),
+        buffer: PixelBuffer::new_from_raw(thumb_width, thumb_height, raw),
     })
 }
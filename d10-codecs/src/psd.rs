@@ -0,0 +1,734 @@
+use d10_core::color::{Color, Rgb, Srgb};
+use d10_core::pixelbuffer::{is_valid_buffer_size, PixelBuffer};
+
+use std::io::Read;
+
+use flate2::read::ZlibDecoder;
+
+use crate::utils::{from_u16_be, from_u8};
+use crate::{DecodedImage, DecodingError, Format, ImageLayer, LayeredImage};
+
+/// Size of the file header following the magic bytes
+const HEADER_SIZE: usize = 22;
+
+const MODE_BITMAP: u16 = 0;
+const MODE_GRAYSCALE: u16 = 1;
+const MODE_INDEXED: u16 = 2;
+const MODE_RGB: u16 = 3;
+const MODE_CMYK: u16 = 4;
+const MODE_DUOTONE: u16 = 8;
+
+const COMPRESSION_RAW: u16 = 0;
+const COMPRESSION_RLE: u16 = 1;
+const COMPRESSION_ZIP: u16 = 2;
+const COMPRESSION_ZIP_PREDICTION: u16 = 3;
+
+/// Channel id of the transparency of a layer
+const CHANNEL_ALPHA: i16 = -1;
+
+/// Layer flag marking hidden layers
+const FLAG_HIDDEN: u8 = 0x2;
+
+fn invalid_data(message: &str) -> DecodingError {
+    DecodingError::InvalidData {
+        format: Format::Psd,
+        message: message.to_owned(),
+    }
+}
+
+fn unsupported(message: String) -> DecodingError {
+    DecodingError::Unsupported {
+        format: Format::Psd,
+        message,
+    }
+}
+
+/// Bounds checked reader of the big endian values used by psd files
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Reader<'a> {
+        Reader { data, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.data[self.pos..]
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DecodingError> {
+        if len > self.data.len() - self.pos {
+            return Err(invalid_data("Unexpected end of data"));
+        }
+
+        let data = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(data)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<(), DecodingError> {
+        self.take(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Result<u8, DecodingError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, DecodingError> {
+        let data = self.take(2)?;
+        Ok(u16::from_be_bytes([data[0], data[1]]))
+    }
+
+    fn i16(&mut self) -> Result<i16, DecodingError> {
+        Ok(self.u16()? as i16)
+    }
+
+    fn u32(&mut self) -> Result<u32, DecodingError> {
+        let data = self.take(4)?;
+        Ok(u32::from_be_bytes([data[0], data[1], data[2], data[3]]))
+    }
+
+    fn i32(&mut self) -> Result<i32, DecodingError> {
+        Ok(self.u32()? as i32)
+    }
+
+    /// Read a section prefixed with its length
+    fn section(&mut self) -> Result<&'a [u8], DecodingError> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Header {
+    channels: usize,
+    width: u32,
+    height: u32,
+    depth: u16,
+    color_mode: u16,
+}
+
+impl Header {
+    fn read(reader: &mut Reader) -> Result<Header, DecodingError> {
+        if reader.take(4)? != b"8BPS" {
+            return Err(invalid_data("Missing psd header"));
+        }
+
+        let version = reader.u16()?;
+        reader.skip(6)?;
+
+        let header = Header {
+            channels: reader.u16()? as usize,
+            height: reader.u32()?,
+            width: reader.u32()?,
+            depth: reader.u16()?,
+            color_mode: reader.u16()?,
+        };
+
+        match version {
+            1 => {}
+            2 => return Err(unsupported("Large document format (psb)".to_owned())),
+            _ => return Err(invalid_data("Bad version")),
+        }
+
+        if !is_valid_buffer_size(header.width, header.height) {
+            return Err(DecodingError::InvalidBufferSize {
+                width: header.width,
+                height: header.height,
+            });
+        }
+
+        let valid_depth = match header.color_mode {
+            MODE_BITMAP => header.depth == 1,
+            MODE_INDEXED => header.depth == 8,
+            MODE_GRAYSCALE | MODE_RGB | MODE_CMYK | MODE_DUOTONE => {
+                matches!(header.depth, 8 | 16 | 32)
+            }
+            mode => return Err(unsupported(format!("Color mode {}", mode))),
+        };
+
+        if !valid_depth {
+            return Err(unsupported(format!("Bit depth {}", header.depth)));
+        }
+
+        if header.channels < header.color_channels() {
+            return Err(invalid_data("Missing color channels"));
+        }
+
+        Ok(header)
+    }
+
+    /// Number of channels needed for the color without alpha
+    fn color_channels(&self) -> usize {
+        match self.color_mode {
+            MODE_RGB => 3,
+            MODE_CMYK => 4,
+            _ => 1,
+        }
+    }
+
+    fn row_bytes(&self, width: u32) -> usize {
+        let width = width as usize;
+        match self.depth {
+            1 => width.div_ceil(8),
+            8 => width,
+            16 => width * 2,
+            _ => width * 4,
+        }
+    }
+}
+
+/// Sections of a psd file that are needed to get the pixels
+struct Document<'a> {
+    header: Header,
+    /// Color table of indexed images
+    palette: &'a [u8],
+    /// Layer records followed by the pixel data of all layers
+    layer_info: Option<&'a [u8]>,
+    image_data: &'a [u8],
+}
+
+impl<'a> Document<'a> {
+    fn read(data: &'a [u8]) -> Result<Document<'a>, DecodingError> {
+        if data.len() < 4 + HEADER_SIZE {
+            return Err(invalid_data("Missing psd header"));
+        }
+
+        let mut reader = Reader::new(data);
+        let header = Header::read(&mut reader)?;
+
+        let palette = reader.section()?;
+
+        if header.color_mode == MODE_INDEXED && palette.len() < 768 {
+            return Err(invalid_data("Missing color table"));
+        }
+
+        // Image resources like thumbnails, guides or icc profiles
+        reader.section()?;
+
+        let layer_info = read_layer_info(reader.section()?)?;
+
+        Ok(Document {
+            header,
+            palette,
+            layer_info,
+            image_data: reader.rest(),
+        })
+    }
+
+    /// Number of layers which is negative if the composite image has transparency
+    fn layer_count(&self) -> i16 {
+        match self.layer_info {
+            Some([a, b, ..]) => i16::from_be_bytes([*a, *b]),
+            _ => 0,
+        }
+    }
+
+    fn composite(&self) -> Result<PixelBuffer<Rgb>, DecodingError> {
+        let header = &self.header;
+        let mut reader = Reader::new(self.image_data);
+        let compression = reader.u16()?;
+
+        let height = header.height as usize;
+        let raw = decompress(
+            reader.rest(),
+            compression,
+            header,
+            header.width,
+            header.channels * height,
+        )?;
+
+        let plane_size = header.row_bytes(header.width) * height;
+        let mut planes = raw
+            .chunks_exact(plane_size.max(1))
+            .map(|plane| samples(plane, header, header.width));
+
+        let colors: Vec<_> = planes.by_ref().take(header.color_channels()).collect();
+
+        // The first extra channel contains the transparency if there are transparent layers
+        // or no layers at all. All other extra channels are selections and spot colors.
+        let alpha = if self.layer_count() <= 0 {
+            planes.next()
+        } else {
+            None
+        };
+
+        let data = self.to_rgb(&colors, alpha.as_deref(), header.width * header.height);
+
+        Ok(PixelBuffer::new_from_raw(header.width, header.height, data))
+    }
+
+    fn layers(&self) -> Result<Vec<ImageLayer>, DecodingError> {
+        let Some(layer_info) = self.layer_info else {
+            return Ok(vec![]);
+        };
+
+        let mut reader = Reader::new(layer_info);
+        let count = reader.i16()?.unsigned_abs();
+
+        let records = (0..count)
+            .map(|_| LayerRecord::read(&mut reader))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut layers = vec![];
+
+        for record in records {
+            let width = record.width()?;
+            let height = record.height()?;
+
+            let mut colors = vec![None; self.header.color_channels()];
+            let mut alpha = None;
+
+            for (id, len) in &record.channels {
+                let data = reader.take(*len)?;
+
+                if *id == CHANNEL_ALPHA || (*id >= 0 && (*id as usize) < colors.len()) {
+                    let [c1, c2, data @ ..] = data else {
+                        return Err(invalid_data("Missing channel compression"));
+                    };
+
+                    let compression = u16::from_be_bytes([*c1, *c2]);
+                    let raw = decompress(data, compression, &self.header, width, height as usize)?;
+                    let values = samples(&raw, &self.header, width);
+
+                    match id {
+                        &CHANNEL_ALPHA => alpha = Some(values),
+                        id => colors[*id as usize] = Some(values),
+                    }
+                }
+
+                // All other channels are layer masks, which are not supported yet
+            }
+
+            if record.is_group_divider {
+                continue;
+            }
+
+            let len = width * height;
+            let colors: Vec<_> = colors
+                .into_iter()
+                .map(|c| c.unwrap_or_else(|| vec![0.0; len as usize]))
+                .collect();
+
+            layers.push(ImageLayer {
+                name: record.name,
+                buffer: PixelBuffer::new_from_raw(
+                    width,
+                    height,
+                    self.to_rgb(&colors, alpha.as_deref(), len),
+                ),
+                x: record.left,
+                y: record.top,
+                opacity: from_u8(record.opacity),
+                visible: record.flags & FLAG_HIDDEN == 0,
+                blend_mode: String::from_utf8_lossy(&record.blend_mode).into_owned(),
+            });
+        }
+
+        Ok(layers)
+    }
+
+    /// Combine the channel values into colors depending on the color mode
+    fn to_rgb(&self, colors: &[Vec<f32>], alpha: Option<&[f32]>, len: u32) -> Vec<Rgb> {
+        let palette = |index: f32, offset: usize| {
+            from_u8(self.palette[offset + (index * 255.0).round() as usize])
+        };
+
+        (0..len as usize)
+            .map(|i| {
+                let a = alpha.map_or(1.0, |alpha| alpha[i]);
+
+                let [r, g, b] = match self.header.color_mode {
+                    MODE_RGB => [colors[0][i], colors[1][i], colors[2][i]],
+                    MODE_CMYK => {
+                        // Psd stores the inverted ink values with 1.0 being no ink
+                        let k = colors[3][i];
+                        [colors[0][i] * k, colors[1][i] * k, colors[2][i] * k]
+                    }
+                    MODE_INDEXED => {
+                        let index = colors[0][i];
+                        [palette(index, 0), palette(index, 256), palette(index, 512)]
+                    }
+                    _ => [colors[0][i]; 3],
+                };
+
+                // 32 bit images use linear floating point values
+                if self.header.depth == 32 {
+                    Rgb::new_with_alpha(r, g, b, a)
+                } else {
+                    Srgb::new_with_alpha(r, g, b, a).to_rgb()
+                }
+            })
+            .collect()
+    }
+}
+
+/// Find the layer info inside of the layer and mask section
+///
+/// 16 and 32 bit documents store their layers in an additional info block at the end of the
+/// section instead.
+fn read_layer_info(section: &[u8]) -> Result<Option<&[u8]>, DecodingError> {
+    if section.is_empty() {
+        return Ok(None);
+    }
+
+    let mut reader = Reader::new(section);
+    let layer_info = reader.section()?;
+
+    if !layer_info.is_empty() {
+        return Ok(Some(layer_info));
+    }
+
+    // Global layer mask info
+    reader.section()?;
+
+    while reader.rest().len() >= 12 {
+        let signature = reader.take(4)?;
+        let key = reader.take(4)?;
+        let data = reader.section()?;
+
+        if signature != b"8BIM" && signature != b"8B64" {
+            break;
+        }
+
+        if matches!(key, b"Layr" | b"Lr16" | b"Lr32") && !data.is_empty() {
+            return Ok(Some(data));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Properties of a layer stored in front of the pixel data of all layers
+struct LayerRecord {
+    name: String,
+    top: i32,
+    left: i32,
+    bottom: i32,
+    right: i32,
+    /// Id and length of the data of every channel
+    channels: Vec<(i16, usize)>,
+    blend_mode: [u8; 4],
+    opacity: u8,
+    flags: u8,
+    /// Start or end of a layer group without any pixels
+    is_group_divider: bool,
+}
+
+impl LayerRecord {
+    fn read(reader: &mut Reader) -> Result<LayerRecord, DecodingError> {
+        let top = reader.i32()?;
+        let left = reader.i32()?;
+        let bottom = reader.i32()?;
+        let right = reader.i32()?;
+
+        let channel_count = reader.u16()?;
+        let channels = (0..channel_count)
+            .map(|_| Ok((reader.i16()?, reader.u32()? as usize)))
+            .collect::<Result<Vec<_>, DecodingError>>()?;
+
+        if reader.take(4)? != b"8BIM" {
+            return Err(invalid_data("Bad blend mode signature"));
+        }
+
+        let blend_mode = reader.take(4)?.try_into().unwrap();
+        let opacity = reader.u8()?;
+        let _clipping = reader.u8()?;
+        let flags = reader.u8()?;
+        reader.skip(1)?;
+
+        let mut extra = Reader::new(reader.section()?);
+
+        // Layer mask and blending ranges
+        extra.section()?;
+        extra.section()?;
+
+        // Pascal string padded to a multiple of 4 bytes
+        let name_len = extra.u8()? as usize;
+        let mut name = String::from_utf8_lossy(extra.take(name_len)?).into_owned();
+        extra.skip((4 - (name_len + 1) % 4) % 4)?;
+
+        let mut is_group_divider = false;
+
+        while extra.rest().len() >= 12 {
+            let signature = extra.take(4)?;
+            let key = extra.take(4)?;
+            let mut data = Reader::new(extra.section()?);
+
+            if signature != b"8BIM" && signature != b"8B64" {
+                break;
+            }
+
+            match key {
+                b"luni" => {
+                    let len = data.u32()? as usize;
+                    let chars = data
+                        .take(len.saturating_mul(2))?
+                        .chunks_exact(2)
+                        .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                        .collect::<Vec<_>>();
+
+                    name = String::from_utf16_lossy(&chars);
+                }
+                b"lsct" => is_group_divider = data.u32()? != 0,
+                _ => {}
+            }
+        }
+
+        Ok(LayerRecord {
+            name: name.trim_end_matches('\0').to_owned(),
+            top,
+            left,
+            bottom,
+            right,
+            channels,
+            blend_mode,
+            opacity,
+            flags,
+            is_group_divider,
+        })
+    }
+
+    fn width(&self) -> Result<u32, DecodingError> {
+        size(self.left, self.right)
+    }
+
+    fn height(&self) -> Result<u32, DecodingError> {
+        size(self.top, self.bottom)
+    }
+}
+
+fn size(start: i32, end: i32) -> Result<u32, DecodingError> {
+    let size = (end as i64 - start as i64).max(0);
+
+    // Photoshop limits documents to 300000 pixels on each side
+    if size > 300_000 {
+        return Err(invalid_data("Bad layer size"));
+    }
+
+    Ok(size as u32)
+}
+
+/// Decode the pixel data of `rows` rows into raw bytes
+fn decompress(
+    data: &[u8],
+    compression: u16,
+    header: &Header,
+    width: u32,
+    rows: usize,
+) -> Result<Vec<u8>, DecodingError> {
+    let row_bytes = header.row_bytes(width);
+    let len = row_bytes * rows;
+
+    match compression {
+        COMPRESSION_RAW => Ok(Reader::new(data).take(len)?.to_vec()),
+        COMPRESSION_RLE => {
+            let mut reader = Reader::new(data);
+
+            let counts = (0..rows)
+                .map(|_| reader.u16())
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let mut out = vec![];
+            for count in counts {
+                unpack_bits(reader.take(count as usize)?, row_bytes, &mut out)?;
+            }
+
+            Ok(out)
+        }
+        COMPRESSION_ZIP | COMPRESSION_ZIP_PREDICTION => {
+            let mut out = vec![];
+            ZlibDecoder::new(data)
+                .take(len as u64)
+                .read_to_end(&mut out)
+                .map_err(|_| invalid_data("Bad zip data"))?;
+
+            if out.len() != len {
+                return Err(invalid_data("Unexpected end of zip data"));
+            }
+
+            if compression == COMPRESSION_ZIP_PREDICTION && row_bytes > 0 {
+                for row in out.chunks_exact_mut(row_bytes) {
+                    unpredict(row, header.depth);
+                }
+            }
+
+            Ok(out)
+        }
+        _ => Err(invalid_data("Unknown compression")),
+    }
+}
+
+/// Decode a PackBits compressed row of `len` bytes
+fn unpack_bits(mut data: &[u8], len: usize, out: &mut Vec<u8>) -> Result<(), DecodingError> {
+    let end = out.len() + len;
+
+    while out.len() < end {
+        let [header, rest @ ..] = data else {
+            return Err(invalid_data("Unexpected end of rle data"));
+        };
+
+        let header = *header as i8;
+
+        if header >= 0 {
+            let count = header as usize + 1;
+            if rest.len() < count {
+                return Err(invalid_data("Unexpected end of rle data"));
+            }
+
+            out.extend_from_slice(&rest[..count]);
+            data = &rest[count..];
+        } else if header != -128 {
+            let [value, rest @ ..] = rest else {
+                return Err(invalid_data("Unexpected end of rle data"));
+            };
+
+            out.resize(out.len() + (1 - header as isize) as usize, *value);
+            data = rest;
+        } else {
+            data = rest;
+        }
+    }
+
+    // Some encoders write longer runs than needed at the end of a row
+    out.truncate(end);
+
+    Ok(())
+}
+
+/// Revert the delta encoding of a row with zip prediction
+fn unpredict(row: &mut [u8], depth: u16) {
+    match depth {
+        16 => {
+            for i in (2..row.len() - 1).step_by(2) {
+                let prev = u16::from_be_bytes([row[i - 2], row[i - 1]]);
+                let value = u16::from_be_bytes([row[i], row[i + 1]]).wrapping_add(prev);
+                row[i..i + 2].copy_from_slice(&value.to_be_bytes());
+            }
+        }
+        32 => {
+            for i in 1..row.len() {
+                row[i] = row[i].wrapping_add(row[i - 1]);
+            }
+
+            // The bytes of the floats are stored in separate planes for better compression
+            let planes = row.to_vec();
+            let width = row.len() / 4;
+            for (x, value) in row.chunks_exact_mut(4).enumerate() {
+                for (b, byte) in value.iter_mut().enumerate() {
+                    *byte = planes[b * width + x];
+                }
+            }
+        }
+        _ => {
+            for i in 1..row.len() {
+                row[i] = row[i].wrapping_add(row[i - 1]);
+            }
+        }
+    }
+}
+
+/// Convert the raw bytes of a channel into values between 0.0 and 1.0
+fn samples(raw: &[u8], header: &Header, width: u32) -> Vec<f32> {
+    let row_bytes = header.row_bytes(width);
+    let width = width as usize;
+
+    if row_bytes == 0 {
+        return vec![];
+    }
+
+    let mut values = Vec::with_capacity(raw.len() / row_bytes * width);
+
+    for row in raw.chunks_exact(row_bytes) {
+        match header.depth {
+            // Set bits of bitmap images are black
+            1 => values
+                .extend((0..width).map(|x| ((row[x / 8] >> (7 - x % 8)) & 1 == 0) as u8 as f32)),
+            8 => values.extend(row.iter().map(|v| from_u8(*v))),
+            16 => values.extend(row.chunks_exact(2).map(|v| from_u16_be([v[0], v[1]]))),
+            _ => values.extend(
+                row.chunks_exact(4)
+                    .map(|v| f32::from_be_bytes([v[0], v[1], v[2], v[3]])),
+            ),
+        }
+    }
+
+    values
+}
+
+fn read_document<T>(mut reader: T) -> Result<Vec<u8>, DecodingError>
+where
+    T: Read,
+{
+    let mut data = vec![];
+    reader.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Decode the composite image of a psd file
+///
+/// Supported are bitmap, grayscale, duotone (as grayscale), indexed, RGB and CMYK images with
+/// 8, 16 and 32 bit channels. CMYK is converted without color profile.
+/// The composite is only available if the file was saved with maximized compatibility.
+pub(crate) fn decode_psd<T>(reader: T) -> Result<DecodedImage, DecodingError>
+where
+    T: Read,
+{
+    let data = read_document(reader)?;
+    let document = Document::read(&data)?;
+
+    Ok(DecodedImage {
+        buffer: document.composite()?,
+    })
+}
+
+/// Decode the raster layers of a psd file
+///
+/// Group dividers are skipped, layers inside of groups are returned as normal layers.
+/// Masks, clipping, layer effects and the content of adjustment and text layers are ignored.
+pub(crate) fn decode_psd_layers<T>(reader: T) -> Result<LayeredImage, DecodingError>
+where
+    T: Read,
+{
+    let data = read_document(reader)?;
+    let document = Document::read(&data)?;
+
+    Ok(LayeredImage {
+        width: document.header.width,
+        height: document.header.height,
+        layers: document.layers()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unpack_bits() {
+        let mut out = vec![];
+        unpack_bits(&[0xFE, 0xAA, 0x02, 0x80, 0x00, 0x2A], 6, &mut out).unwrap();
+        assert_eq!(out, [0xAA, 0xAA, 0xAA, 0x80, 0x00, 0x2A]);
+
+        // Runs longer than the row are cut off
+        let mut out = vec![1];
+        unpack_bits(&[0xFD, 0x05], 2, &mut out).unwrap();
+        assert_eq!(out, [1, 5, 5]);
+
+        assert!(unpack_bits(&[0x04, 0x01], 5, &mut vec![]).is_err());
+        assert!(unpack_bits(&[0xFD], 4, &mut vec![]).is_err());
+    }
+
+    #[test]
+    fn test_unpredict() {
+        let mut row = [1, 1, 254, 3];
+        unpredict(&mut row, 8);
+        assert_eq!(row, [1, 2, 0, 3]);
+
+        let mut row = [0x01, 0x00, 0x00, 0x10, 0xFF, 0xFF];
+        unpredict(&mut row, 16);
+        assert_eq!(row, [0x01, 0x00, 0x01, 0x10, 0x01, 0x0F]);
+    }
+}
@@ -1,24 +1,143 @@
-use image::DynamicImage;
+use image::{DynamicImage, ImageDecoder};
 
 use d10_core::color::{Color, Rgb, Srgb};
 use d10_core::pixelbuffer::{is_valid_buffer_size, PixelBuffer};
+use d10_core::region::Region;
+
+use crate::{DecodingError, EncodingError, Format};
+
+/// Check that `len` pixels are complete rows that fit into the remaining rows of an image
+///
+/// Returns the number of rows.
+pub(crate) fn check_rows(
+    format: Format,
+    len: usize,
+    width: u32,
+    height: u32,
+    rows_written: u32,
+) -> Result<u32, EncodingError> {
+    let bad_row_count = || EncodingError::RowCount {
+        format,
+        expected: height,
+        written: rows_written.saturating_add((len / width.max(1) as usize) as u32),
+    };
+
+    if width == 0 || !len.is_multiple_of(width as usize) {
+        return Err(EncodingError::InvalidConfig {
+            format,
+            message: format!("{} pixels are no complete rows of width {}", len, width),
+        });
+    }
+
+    let count = u32::try_from(len / width as usize).map_err(|_| bad_row_count())?;
+
+    if rows_written + count > height {
+        return Err(bad_row_count());
+    }
+
+    Ok(count)
+}
+
+/// Bounding box of all pixels for which `changed` returns true
+pub(crate) fn changed_rect<F>(width: u32, height: u32, changed: F) -> Option<Region>
+where
+    F: Fn(usize) -> bool,
+{
+    let mut min_x = width;
+    let mut min_y = height;
+    let mut max_x = 0;
+    let mut max_y = 0;
+
+    for y in 0..height {
+        for x in 0..width {
+            if changed((y * width + x) as usize) {
+                min_x = min_x.min(x);
+                min_y = min_y.min(y);
+                max_x = max_x.max(x);
+                max_y = max_y.max(y);
+            }
+        }
+    }
 
-use crate::DecodingError;
+    (min_x <= max_x).then(|| Region::new(min_x, min_y, max_x - min_x + 1, max_y - min_y + 1))
+}
 
-/// Convert color channel value between 0.0 and 1.0 into an u8
+/// Convert color channel value between 0.0 and 1.0 into an u8 rounded to the nearest value
 pub(crate) fn as_u8(value: f32) -> u8 {
-    (value * 255.0).clamp(0.0, 255.0) as u8
+    (value * 255.0).round().clamp(0.0, 255.0) as u8
 }
 
-/// Convert color channel value between 0.0 and 1.0 into an u16
+/// Convert color channel value between 0.0 and 1.0 into an u16 rounded to the nearest value
 pub(crate) fn as_u16(value: f32) -> u16 {
-    (value * 65535.0).clamp(0.0, 65535.0) as u16
+    (value * 65535.0).round().clamp(0.0, 65535.0) as u16
 }
 
-pub(crate) fn to_l8_vec(buffer: &PixelBuffer<Rgb>) -> Vec<u8> {
-    let mut out: Vec<u8> = Vec::with_capacity(buffer.width() as usize * buffer.height() as usize);
+/// Spread the rounding errors of the conversion into integers onto the neighbor pixels
+///
+/// Uses Floyd-Steinberg error diffusion on the gamma encoded values with `max_value` being the
+/// largest integer value, e.g. 255 for 8 bit channels. The channels of the result are exactly on
+/// the integer values, so the encoders convert them without any further rounding.
+/// With `gray` the luma written by grayscale encoders gets dithered instead of the colors.
+pub(crate) fn dither_for_export(
+    buffer: &PixelBuffer<Rgb>,
+    max_value: f32,
+    gray: bool,
+) -> PixelBuffer<Rgb> {
+    let width = buffer.width() as usize;
+    let height = buffer.height() as usize;
+
+    let mut values: Vec<[f32; 3]> = buffer
+        .data()
+        .iter()
+        .map(|c| {
+            if gray {
+                let v = c.to_gray().to_srgb().red();
+                [v, v, v]
+            } else {
+                let c = c.to_srgb();
+                [c.data[0], c.data[1], c.data[2]]
+            }
+        })
+        .collect();
+
+    let mut result = Vec::with_capacity(width * height);
+
+    for y in 0..height {
+        for x in 0..width {
+            let c = values[y * width + x];
+
+            let quantized = c.map(|v| (v * max_value).round().clamp(0.0, max_value) / max_value);
+
+            let neighbors = [
+                (x + 1, y, 7.0 / 16.0),
+                (x.wrapping_sub(1), y + 1, 3.0 / 16.0),
+                (x, y + 1, 5.0 / 16.0),
+                (x + 1, y + 1, 1.0 / 16.0),
+            ];
+
+            for (nx, ny, factor) in neighbors {
+                if nx < width && ny < height {
+                    for i in 0..3 {
+                        values[ny * width + nx][i] += (c[i] - quantized[i]) * factor;
+                    }
+                }
+            }
+
+            let alpha = buffer.data()[y * width + x].alpha();
 
-    for color in buffer.data().iter() {
+            result.push(
+                Srgb::new_with_alpha(quantized[0], quantized[1], quantized[2], alpha).to_rgb(),
+            );
+        }
+    }
+
+    PixelBuffer::new_from_raw(buffer.width(), buffer.height(), result)
+}
+
+pub(crate) fn to_l8_vec(data: &[Rgb]) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::with_capacity(data.len());
+
+    for color in data.iter() {
         let color = color.to_gray().to_srgb();
         out.push(as_u8(color.red()));
     }
@@ -26,11 +145,10 @@ pub(crate) fn to_l8_vec(buffer: &PixelBuffer<Rgb>) -> Vec<u8> {
     out
 }
 
-pub(crate) fn to_la8_vec(buffer: &PixelBuffer<Rgb>) -> Vec<u8> {
-    let mut out: Vec<u8> =
-        Vec::with_capacity(buffer.width() as usize * buffer.height() as usize * 2);
+pub(crate) fn to_la8_vec(data: &[Rgb]) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::with_capacity(data.len() * 2);
 
-    for color in buffer.data().iter() {
+    for color in data.iter() {
         let color = color.to_gray().to_srgb();
         out.push(as_u8(color.red()));
         out.push(as_u8(color.alpha()));
@@ -39,11 +157,10 @@ pub(crate) fn to_la8_vec(buffer: &PixelBuffer<Rgb>) -> Vec<u8> {
     out
 }
 
-pub(crate) fn to_rgb8_vec(buffer: &PixelBuffer<Rgb>) -> Vec<u8> {
-    let mut out: Vec<u8> =
-        Vec::with_capacity(buffer.width() as usize * buffer.height() as usize * 3);
+pub(crate) fn to_rgb8_vec(data: &[Rgb]) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::with_capacity(data.len() * 3);
 
-    for color in buffer.data().iter() {
+    for color in data.iter() {
         let color = color.to_srgb();
 
         out.push(as_u8(color.red()));
@@ -54,11 +171,10 @@ pub(crate) fn to_rgb8_vec(buffer: &PixelBuffer<Rgb>) -> Vec<u8> {
     out
 }
 
-pub(crate) fn to_rgba8_vec(buffer: &PixelBuffer<Rgb>) -> Vec<u8> {
-    let mut out: Vec<u8> =
-        Vec::with_capacity(buffer.width() as usize * buffer.height() as usize * 4);
+pub(crate) fn to_rgba8_vec(data: &[Rgb]) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::with_capacity(data.len() * 4);
 
-    for color in buffer.data().iter() {
+    for color in data.iter() {
         let color = color.to_srgb();
 
         out.push(as_u8(color.red()));
@@ -70,10 +186,10 @@ pub(crate) fn to_rgba8_vec(buffer: &PixelBuffer<Rgb>) -> Vec<u8> {
     out
 }
 
-pub(crate) fn to_argb8_vec32(buffer: &PixelBuffer<Rgb>) -> Vec<u32> {
-    let mut out: Vec<u32> = Vec::with_capacity(buffer.width() as usize * buffer.height() as usize);
+pub(crate) fn to_argb8_vec32(data: &[Rgb]) -> Vec<u32> {
+    let mut out: Vec<u32> = Vec::with_capacity(data.len());
 
-    for color in buffer.data().iter() {
+    for color in data.iter() {
         let color = color.to_srgb();
 
         let v = (as_u8(color.alpha()) as u32) << 24
@@ -86,23 +202,21 @@ pub(crate) fn to_argb8_vec32(buffer: &PixelBuffer<Rgb>) -> Vec<u32> {
     out
 }
 
-pub(crate) fn to_l16_be_vec(buffer: &PixelBuffer<Rgb>) -> Vec<u8> {
-    let mut out: Vec<u8> =
-        Vec::with_capacity(buffer.width() as usize * buffer.height() as usize * 2);
+pub(crate) fn to_l16_be_vec(data: &[Rgb]) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::with_capacity(data.len() * 2);
 
-    for color in buffer.data().iter() {
+    for color in data.iter() {
         let color = color.to_gray().to_srgb();
-        out.extend_from_slice(&color.red().to_be_bytes());
+        out.extend_from_slice(&as_u16(color.red()).to_be_bytes());
     }
 
     out
 }
 
-pub(crate) fn to_la16_be_vec(buffer: &PixelBuffer<Rgb>) -> Vec<u8> {
-    let mut out: Vec<u8> =
-        Vec::with_capacity(buffer.width() as usize * buffer.height() as usize * 4);
+pub(crate) fn to_la16_be_vec(data: &[Rgb]) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::with_capacity(data.len() * 4);
 
-    for color in buffer.data().iter() {
+    for color in data.iter() {
         let color = color.to_gray().to_srgb();
 
         out.extend_from_slice(&as_u16(color.red()).to_be_bytes());
@@ -112,11 +226,10 @@ pub(crate) fn to_la16_be_vec(buffer: &PixelBuffer<Rgb>) -> Vec<u8> {
     out
 }
 
-pub(crate) fn to_rgb16_be_vec(buffer: &PixelBuffer<Rgb>) -> Vec<u8> {
-    let mut out: Vec<u8> =
-        Vec::with_capacity(buffer.width() as usize * buffer.height() as usize * 6);
+pub(crate) fn to_rgb16_be_vec(data: &[Rgb]) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::with_capacity(data.len() * 6);
 
-    for color in buffer.data().iter() {
+    for color in data.iter() {
         let color = color.to_srgb();
 
         out.extend_from_slice(&as_u16(color.red()).to_be_bytes());
@@ -127,11 +240,10 @@ pub(crate) fn to_rgb16_be_vec(buffer: &PixelBuffer<Rgb>) -> Vec<u8> {
     out
 }
 
-pub(crate) fn to_rgba16_be_vec(buffer: &PixelBuffer<Rgb>) -> Vec<u8> {
-    let mut out: Vec<u8> =
-        Vec::with_capacity(buffer.width() as usize * buffer.height() as usize * 8);
+pub(crate) fn to_rgba16_be_vec(data: &[Rgb]) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::with_capacity(data.len() * 8);
 
-    for color in buffer.data().iter() {
+    for color in data.iter() {
         let color = color.to_srgb();
 
         out.extend_from_slice(&as_u16(color.red()).to_be_bytes());
@@ -155,7 +267,27 @@ pub fn from_u16_ne(v: [u8; 2]) -> f32 {
     f32::from(u16::from_ne_bytes(v)) / 65535.0
 }
 
-pub fn read_into_buffer(img: DynamicImage) -> Result<PixelBuffer<Rgb>, DecodingError> {
+/// Check the dimensions of a decoder before it allocates the image
+///
+/// Decoders of the image crate allocate the whole image up front, so corrupted headers with huge
+/// dimensions would abort with an allocation failure before `read_into_buffer()` can check them.
+pub(crate) fn check_decoder_size<'a, D>(decoder: &D) -> Result<(), DecodingError>
+where
+    D: ImageDecoder<'a>,
+{
+    let (width, height) = decoder.dimensions();
+
+    if is_valid_buffer_size(width, height) {
+        Ok(())
+    } else {
+        Err(DecodingError::InvalidBufferSize { width, height })
+    }
+}
+
+pub fn read_into_buffer(
+    img: DynamicImage,
+    format: Format,
+) -> Result<PixelBuffer<Rgb>, DecodingError> {
     let width = img.width();
     let height = img.height();
 
@@ -297,10 +429,10 @@ pub fn read_into_buffer(img: DynamicImage) -> Result<PixelBuffer<Rgb>, DecodingE
             })
             .collect(),
         img => {
-            return Err(DecodingError::Decoding(format!(
-                "Unsupported image format: {:?}",
-                img.color()
-            )))
+            return Err(DecodingError::Unsupported {
+                format,
+                message: format!("Unsupported color type: {:?}", img.color()),
+            })
         }
     };
 
@@ -334,7 +466,9 @@ mod tests {
         assert_eq!(as_u8(1.0), 255);
         assert_eq!(as_u8(1.5), 255);
 
-        assert_eq!(as_u8(0.5), 127);
+        assert_eq!(as_u8(0.5), 128);
+        assert_eq!(as_u8(0.498), 127);
+        assert_eq!(as_u8(from_u8(77)), 77);
     }
 
     #[test]
@@ -345,6 +479,31 @@ mod tests {
         assert_eq!(as_u16(1.0), 65535);
         assert_eq!(as_u16(1.5), 65535);
 
-        assert_eq!(as_u16(0.5), 32767);
+        assert_eq!(as_u16(0.5), 32768);
+        assert_eq!(as_u16(from_u16_be(1234u16.to_be_bytes())), 1234);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        for v in 0..=255 {
+            let c = Srgb::new(from_u8(v), 0.0, 0.0).to_rgb();
+            assert_eq!(to_rgb8_vec(&[c])[0], v);
+        }
+    }
+
+    #[test]
+    fn test_dither_for_export() {
+        let buffer = PixelBuffer::new_with_color(16, 16, Srgb::new(0.5 / 255.0, 0.2, 0.2).to_rgb());
+
+        let result = dither_for_export(&buffer, 255.0, false);
+        let data = to_rgb8_vec(result.data());
+
+        // Half of the pixels are rounded up to keep the average
+        let ones = data.iter().step_by(3).filter(|v| **v == 1).count();
+        assert!((100..=156).contains(&ones), "{}", ones);
+        assert!(data.iter().step_by(3).all(|v| *v <= 1));
+
+        let result = dither_for_export(&buffer, 255.0, true);
+        assert!(result.data().iter().all(|c| c.is_grayscale()));
     }
 }
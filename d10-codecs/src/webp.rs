@@ -17,7 +17,7 @@ use d10_core::errors::ParseEnumError;
 use d10_core::pixelbuffer::PixelBuffer;
 
 use crate::utils::{from_u8, to_argb8_vec32};
-use crate::{DecodedImage, DecodingError, EncodingError};
+use crate::{DecodedImage, DecodingError, EncodingError, Format};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum WebPPreset {
@@ -72,13 +72,17 @@ where
         let len = data.len();
 
         if WebPGetInfo(data.as_ptr(), len, &mut width, &mut height) == 0 {
-            return Err(DecodingError::Decoding("Bad webp file".to_string()));
+            return Err(DecodingError::InvalidData {
+                format: Format::WebP,
+                message: "Bad webp file".to_owned(),
+            });
         }
         let out_buf = WebPDecodeRGBA(data.as_ptr(), len, &mut width, &mut height);
         if out_buf.is_null() {
-            return Err(DecodingError::Decoding(
-                "Error decoding webp file".to_string(),
-            ));
+            return Err(DecodingError::InvalidData {
+                format: Format::WebP,
+                message: "Error decoding webp file".to_owned(),
+            });
         }
 
         let image_data = std::slice::from_raw_parts(out_buf, width as usize * height as usize * 4);
@@ -154,10 +158,14 @@ where
                 config
             }
         }
-        .map_err(|_| EncodingError::Encoding("Unable to init webp encoder config".to_owned()))?;
+        .map_err(|_| EncodingError::InvalidConfig {
+            format: Format::WebP,
+            message: "Unable to init webp encoder config".to_owned(),
+        })?;
 
-        let mut picture = WebPPicture::new().map_err(|_| {
-            EncodingError::Encoding("Unable to init webp picture config".to_owned())
+        let mut picture = WebPPicture::new().map_err(|_| EncodingError::InvalidConfig {
+            format: Format::WebP,
+            message: "Unable to init webp picture config".to_owned(),
         })?;
 
         let mut write = Writer {
@@ -165,7 +173,7 @@ where
             err: None,
         };
 
-        let raw_data = to_argb8_vec32(buffer);
+        let raw_data = to_argb8_vec32(buffer.data());
 
         picture.use_argb = 1;
         picture.width = width;
@@ -181,8 +189,9 @@ where
         match write.err {
             None => {
                 if res == 0 {
-                    Err(EncodingError::Encoding(
-                        "Error encoding webp file".to_owned(),
+                    Err(EncodingError::codec(
+                        Format::WebP,
+                        "Error encoding webp file",
                     ))
                 } else {
                     Ok(())
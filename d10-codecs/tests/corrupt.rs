@@ -0,0 +1,59 @@
+use std::io::Cursor;
+
+use d10_codecs::{decode_buffer, decode_progressive, decode_thumbnail, encode, EncodingFormat};
+use d10_core::color::{Color, Rgb};
+use d10_core::pixelbuffer::PixelBuffer;
+
+/// Decoding any of the files in `tests/images/corrupt` must fail with an error instead of a panic
+#[test]
+pub fn test_corrupt_files() {
+    let mut paths: Vec<_> = std::fs::read_dir("tests/images/corrupt")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+
+    assert!(!paths.is_empty());
+
+    for path in paths {
+        let data = std::fs::read(&path).unwrap();
+
+        assert!(decode_buffer(&data).is_err(), "{:?}", path);
+        assert!(
+            decode_thumbnail(Cursor::new(&data), 4).is_err(),
+            "{:?}",
+            path
+        );
+        assert!(
+            decode_progressive(Cursor::new(&data)).is_err(),
+            "{:?}",
+            path
+        );
+    }
+}
+
+/// Every truncated version of a valid file must be decoded without a panic
+#[test]
+pub fn test_truncated() {
+    let buffer = PixelBuffer::new_from_func(13, 7, |x, y| {
+        Rgb::new(x as f32 / 13.0, y as f32 / 7.0, 0.5).with_alpha(0.7)
+    });
+
+    for format in [
+        EncodingFormat::jpeg_default(),
+        EncodingFormat::png_default(),
+        EncodingFormat::gif_default(),
+        EncodingFormat::bmp_default(),
+        EncodingFormat::ico_default(),
+        EncodingFormat::webp_default(),
+        EncodingFormat::dds_default(),
+    ] {
+        let mut data = vec![];
+        encode(&mut data, &buffer, format).unwrap();
+
+        for len in 0..data.len() {
+            let _ = decode_buffer(&data[..len]);
+            let _ = decode_thumbnail(Cursor::new(&data[..len]), 4);
+        }
+    }
+}
@@ -1,4 +1,19 @@
-use d10_codecs::{decode_buffer, decode_file, encode, EncodingFormat};
+use std::cell::RefCell;
+use std::io::{Cursor, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Duration;
+
+use d10_codecs::{
+    decode_animation, decode_buffer, decode_buffer_with_hint, decode_file, decode_layers,
+    decode_progressive, decode_thumbnail, encode, encode_animation, encode_multi_size_ico,
+    encode_ora, encode_with_options, encode_with_target_size, DecodingError, EncodeOptions,
+    EncodingError, EncodingFormat, Format, GifDither, IcoColorType, ImageLayer, JpegStreamEncoder,
+    LayeredImage, PngColorType, PngCompression, PngFilterType, PngStreamEncoder,
+};
+use d10_core::color::{Color, Rgb, Srgb};
+use d10_core::palette::Palette;
+use d10_core::pixelbuffer::PixelBuffer;
 
 // Because reference images are u8 based and there might be rounding
 // errors in all images tested, a delta of 2 should be save to not have
@@ -21,14 +36,14 @@ fn test_decode(path: &str) {
     }
 }
 
-fn test_encode(path: &str) {
+fn test_encode(expected_path: &str) {
     let orig = decode_file("tests/images/test.png").unwrap().buffer;
 
     let mut buffer = vec![];
     encode(&mut buffer, &orig, EncodingFormat::webp_with_quality(95)).unwrap();
 
     let result = decode_buffer(&buffer).unwrap().buffer;
-    let expected = decode_file(&format!("{}.png", path)).unwrap().buffer;
+    let expected = decode_file(expected_path).unwrap().buffer;
 
     assert_eq!(expected.width(), result.width());
     assert_eq!(expected.height(), result.height());
@@ -45,5 +60,1058 @@ fn test_encode(path: &str) {
 #[test]
 pub fn test_webp() {
     test_decode("tests/images/test.webp");
-    test_encode("tests/images/test.webp");
+    test_encode("tests/images/test.webp.encoded.png");
+}
+
+#[test]
+pub fn test_decode_with_hint() {
+    let data = std::fs::read("tests/images/test.webp").unwrap();
+
+    // The detected format takes precedence over the hint
+    let result = decode_buffer_with_hint(&data, Some(Format::Png))
+        .unwrap()
+        .buffer;
+    assert_eq!(result.width(), decode_buffer(&data).unwrap().buffer.width());
+
+    let result = decode_buffer_with_hint(&[1; 32], Some(Format::Png));
+    assert_eq!(result.err().and_then(|err| err.format()), Some(Format::Png));
+
+    let result = decode_buffer_with_hint(&[1, 2, 3], None);
+    assert!(matches!(result, Err(DecodingError::UnknownFormat)));
+}
+
+#[test]
+pub fn test_format_from_mime_type() {
+    assert_eq!(Format::from_mime_type("image/png"), Some(Format::Png));
+    assert_eq!(
+        Format::from_mime_type("Image/JPEG; charset=binary"),
+        Some(Format::Jpeg)
+    );
+    assert_eq!(Format::from_mime_type("image/webp"), Some(Format::WebP));
+    assert_eq!(
+        Format::from_mime_type("image/vnd-ms.dds"),
+        Some(Format::Dds)
+    );
+    assert_eq!(Format::from_mime_type("text/html"), None);
+}
+
+#[test]
+pub fn test_thumbnail_interlaced_png() {
+    let full = decode_file("tests/images/interlaced.png").unwrap().buffer;
+
+    for (max_edge, scale, width, height) in [(5, 8, 5, 4), (10, 4, 10, 8), (19, 2, 19, 15)] {
+        let file = std::fs::File::open("tests/images/interlaced.png").unwrap();
+        let thumbnail = decode_thumbnail(file, max_edge).unwrap().buffer;
+
+        assert_eq!(thumbnail.width(), width);
+        assert_eq!(thumbnail.height(), height);
+
+        for (x, y, c) in thumbnail.enumerate() {
+            assert_eq!(&c, full.get_pixel(x * scale, y * scale));
+        }
+    }
+
+    let file = std::fs::File::open("tests/images/interlaced.png").unwrap();
+    let thumbnail = decode_thumbnail(file, 30).unwrap().buffer;
+    assert_eq!(thumbnail.data(), full.data());
+}
+
+#[test]
+pub fn test_thumbnail_jpeg() {
+    let orig = decode_file("tests/images/test.png").unwrap().buffer;
+
+    let mut data = vec![];
+    encode(&mut data, &orig, EncodingFormat::jpeg_default()).unwrap();
+
+    let max_edge = orig.width().max(orig.height()) / 4;
+    let thumbnail = decode_thumbnail(std::io::Cursor::new(&data), max_edge)
+        .unwrap()
+        .buffer;
+
+    assert_eq!(thumbnail.width(), orig.width().div_ceil(4));
+    assert_eq!(thumbnail.height(), orig.height().div_ceil(4));
+
+    // Formats without a fast path are fully decoded
+    let file = std::fs::File::open("tests/images/test.webp").unwrap();
+    let thumbnail = decode_thumbnail(file, 4).unwrap().buffer;
+    assert_eq!(thumbnail.width(), orig.width());
+}
+
+#[test]
+pub fn test_encode_with_target_size() {
+    let orig = decode_file("tests/images/test.png").unwrap().buffer;
+
+    for format in [
+        EncodingFormat::jpeg_default(),
+        EncodingFormat::webp_default(),
+    ] {
+        let mut full = vec![];
+        encode(&mut full, &orig, format.clone()).unwrap();
+
+        // The original quality is kept if the size is already small enough
+        let mut out = vec![];
+        let used = encode_with_target_size(&mut out, &orig, format.clone(), full.len()).unwrap();
+        assert_eq!(used.quality(), format.quality());
+        assert_eq!(out, full);
+
+        let max_bytes = full.len() * 2 / 3;
+        let mut out = vec![];
+        let used = encode_with_target_size(&mut out, &orig, format.clone(), max_bytes).unwrap();
+
+        assert!(out.len() <= max_bytes);
+        assert!(used.quality().unwrap() < format.quality().unwrap());
+        assert_eq!(decode_buffer(&out).unwrap().buffer.width(), orig.width());
+
+        let mut out = vec![];
+        let err = encode_with_target_size(&mut out, &orig, format.clone(), 10).unwrap_err();
+        assert!(matches!(
+            err,
+            EncodingError::TargetSizeNotReached { max_bytes: 10, .. }
+        ));
+        assert!(out.is_empty());
+    }
+
+    let mut out = vec![];
+    let result = encode_with_target_size(&mut out, &orig, EncodingFormat::png_default(), 10);
+    assert_eq!(result.unwrap_err().code(), "target_size_not_reached");
+}
+
+#[test]
+pub fn test_multi_size_ico() {
+    let small = PixelBuffer::new_with_color(16, 16, Rgb::RED);
+    let large = PixelBuffer::new_with_color(48, 48, Rgb::RED);
+
+    let mut data = vec![];
+    encode_multi_size_ico(&mut data, &[&small, &large], IcoColorType::Rgba8).unwrap();
+
+    // Number of entries in the ico header
+    assert_eq!(u16::from_le_bytes([data[4], data[5]]), 2);
+
+    // The decoder picks the largest entry
+    let decoded = decode_buffer(&data).unwrap().buffer;
+    assert_eq!(decoded.width(), 48);
+
+    let too_large = PixelBuffer::new_with_color(300, 16, Rgb::RED);
+    let result = encode_multi_size_ico(&mut vec![], &[&too_large], IcoColorType::Rgba8);
+    assert_eq!(result.unwrap_err().code(), "codec");
+}
+
+/// Writer that keeps the data accessible after it was moved into an encoder
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+pub fn test_png_stream_encoder() {
+    let orig = decode_file("tests/images/test.png").unwrap().buffer;
+
+    let out = SharedBuffer::default();
+    let mut encoder = PngStreamEncoder::new(
+        out.clone(),
+        orig.width(),
+        orig.height(),
+        PngColorType::Rgba8,
+        PngCompression::Default,
+        PngFilterType::Sub,
+    )
+    .unwrap();
+
+    // Write single rows and blocks of multiple rows
+    for (i, rows) in orig.data().chunks(orig.width() as usize * 3).enumerate() {
+        if i % 2 == 0 {
+            encoder.write_rows(rows).unwrap();
+        } else {
+            for row in rows.chunks(orig.width() as usize) {
+                encoder.write_rows(row).unwrap();
+            }
+        }
+    }
+
+    assert_eq!(encoder.rows_written(), orig.height());
+    encoder.finish().unwrap();
+
+    let mut expected = vec![];
+    encode(
+        &mut expected,
+        &orig,
+        EncodingFormat::Png {
+            color_type: PngColorType::Rgba8,
+            compression: PngCompression::Default,
+            filter: PngFilterType::Sub,
+            interlaced: false,
+            palette: None,
+        },
+    )
+    .unwrap();
+
+    let result = decode_buffer(&out.0.borrow()).unwrap().buffer;
+    let expected = decode_buffer(&expected).unwrap().buffer;
+    assert_eq!(result.data(), expected.data());
+}
+
+#[test]
+pub fn test_png_stream_encoder_row_count() {
+    let row = vec![Rgb::RED; 4];
+
+    let mut encoder = PngStreamEncoder::new(
+        SharedBuffer::default(),
+        4,
+        2,
+        PngColorType::Rgb8,
+        PngCompression::Fast,
+        PngFilterType::NoFilter,
+    )
+    .unwrap();
+
+    assert_eq!(
+        encoder.write_rows(&row[..3]).unwrap_err().code(),
+        "invalid_config"
+    );
+
+    encoder.write_rows(&row).unwrap();
+    let err = encoder.finish().unwrap_err();
+    assert!(matches!(
+        err,
+        EncodingError::RowCount {
+            format: Format::Png,
+            expected: 2,
+            written: 1
+        }
+    ));
+
+    let mut encoder = PngStreamEncoder::new(
+        SharedBuffer::default(),
+        4,
+        1,
+        PngColorType::Rgb8,
+        PngCompression::Fast,
+        PngFilterType::NoFilter,
+    )
+    .unwrap();
+    let rows = vec![Rgb::RED; 8];
+    assert_eq!(encoder.write_rows(&rows).unwrap_err().code(), "row_count");
+}
+
+#[test]
+pub fn test_jpeg_stream_encoder() {
+    let orig = decode_file("tests/images/test.png").unwrap().buffer;
+
+    let mut expected = vec![];
+    encode(
+        &mut expected,
+        &orig,
+        EncodingFormat::Jpeg {
+            quality: 80,
+            progressive: false,
+            sampling_factor: None,
+            grayscale: false,
+            optimize_huffman_tables: false,
+        },
+    )
+    .unwrap();
+
+    let mut encoder =
+        JpegStreamEncoder::new(vec![], orig.width(), orig.height(), 80, false).unwrap();
+
+    for rows in orig.data().chunks(orig.width() as usize * 5) {
+        encoder.write_rows(rows).unwrap();
+    }
+
+    // Streaming produces the same file as encoding the whole buffer
+    let data = encoder.finish().unwrap();
+    assert_eq!(data, expected);
+
+    let mut encoder = JpegStreamEncoder::new(vec![], 3, 2, 80, true).unwrap();
+    encoder.write_rows(&[Rgb::WHITE; 3]).unwrap();
+    assert_eq!(encoder.finish().unwrap_err().code(), "row_count");
+
+    let err = JpegStreamEncoder::new(vec![], 70000, 1, 80, false)
+        .err()
+        .unwrap();
+    assert_eq!(err.code(), "bad_dimensions");
+}
+
+#[test]
+pub fn test_interlaced_png() {
+    let orig = decode_file("tests/images/test.png").unwrap().buffer;
+
+    for color_type in [PngColorType::Rgba8, PngColorType::La8, PngColorType::Rgb16] {
+        for filter in [
+            PngFilterType::NoFilter,
+            PngFilterType::Sub,
+            PngFilterType::Up,
+            PngFilterType::Avg,
+            PngFilterType::Paeth,
+        ] {
+            let mut expected = vec![];
+            let mut interlaced = vec![];
+
+            for (out, interlaced) in [(&mut expected, false), (&mut interlaced, true)] {
+                encode(
+                    out,
+                    &orig,
+                    EncodingFormat::Png {
+                        color_type,
+                        compression: PngCompression::Fast,
+                        filter,
+                        interlaced,
+                        palette: None,
+                    },
+                )
+                .unwrap();
+            }
+
+            let expected = decode_buffer(&expected).unwrap().buffer;
+            let result = decode_buffer(&interlaced).unwrap().buffer;
+            assert_eq!(result.data(), expected.data());
+        }
+    }
+}
+
+#[test]
+pub fn test_indexed_png() {
+    let transparent = Rgb::new_with_alpha(0.0, 1.0, 0.0, 0.0);
+    let palette = Palette::from_colors(vec![Rgb::RED, Rgb::BLUE, transparent]);
+
+    let orig = PixelBuffer::new_from_func(5, 3, |x, y| match (x + y) % 3 {
+        0 => Rgb::new(0.9, 0.1, 0.0),
+        1 => Rgb::new(0.0, 0.0, 0.8),
+        _ => Rgb::new_with_alpha(0.2, 0.8, 0.2, 0.1),
+    });
+    let expected = PixelBuffer::new_from_func(5, 3, |x, y| match (x + y) % 3 {
+        0 => Rgb::RED,
+        1 => Rgb::BLUE,
+        _ => transparent,
+    });
+
+    for interlaced in [false, true] {
+        let mut data = vec![];
+        encode(
+            &mut data,
+            &orig,
+            EncodingFormat::Png {
+                color_type: PngColorType::Rgba8,
+                compression: PngCompression::Default,
+                filter: PngFilterType::Sub,
+                interlaced,
+                palette: Some(palette.clone()),
+            },
+        )
+        .unwrap();
+
+        // Color type of the IHDR chunk
+        assert_eq!(data[25], 3);
+
+        let result = decode_buffer(&data).unwrap().buffer;
+        assert_eq!(result.data(), expected.data());
+    }
+
+    let err = encode(
+        &mut vec![],
+        &orig,
+        EncodingFormat::Png {
+            color_type: PngColorType::Rgba8,
+            compression: PngCompression::Default,
+            filter: PngFilterType::Sub,
+            interlaced: false,
+            palette: Some(Palette::new()),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err.code(), "invalid_config");
+}
+
+#[test]
+pub fn test_decode_progressive_png() {
+    let orig = decode_file("tests/images/test.png").unwrap().buffer;
+
+    let mut data = vec![];
+    encode(
+        &mut data,
+        &orig,
+        EncodingFormat::Png {
+            color_type: PngColorType::Rgba8,
+            compression: PngCompression::Default,
+            filter: PngFilterType::Paeth,
+            interlaced: true,
+            palette: None,
+        },
+    )
+    .unwrap();
+
+    let passes = decode_progressive(Cursor::new(&data)).unwrap();
+    assert_eq!(passes.len(), 7);
+
+    let full = decode_buffer(&data).unwrap().buffer;
+
+    for pass in &passes {
+        assert_eq!(pass.buffer.width(), orig.width());
+        assert_eq!(pass.buffer.height(), orig.height());
+    }
+
+    // The first pass only contains every 8th pixel in both directions
+    let first = &passes[0].buffer;
+    assert_eq!(first.get_pixel(7, 7), full.get_pixel(0, 0));
+    assert_eq!(first.get_pixel(8, 0), full.get_pixel(8, 0));
+
+    assert_eq!(passes[6].buffer.data(), full.data());
+
+    let mut data = vec![];
+    encode(&mut data, &orig, EncodingFormat::png_default()).unwrap();
+    assert_eq!(decode_progressive(Cursor::new(&data)).unwrap().len(), 1);
+}
+
+#[test]
+pub fn test_decode_progressive_jpeg() {
+    let orig = decode_file("tests/images/test.png").unwrap().buffer;
+
+    for progressive in [false, true] {
+        let mut data = vec![];
+        encode(
+            &mut data,
+            &orig,
+            EncodingFormat::Jpeg {
+                quality: 90,
+                progressive,
+                sampling_factor: None,
+                grayscale: false,
+                optimize_huffman_tables: true,
+            },
+        )
+        .unwrap();
+
+        let passes = decode_progressive(Cursor::new(&data)).unwrap();
+        let full = decode_buffer(&data).unwrap().buffer;
+
+        assert_eq!(passes.len(), if progressive { 2 } else { 1 });
+        assert_eq!(passes.last().unwrap().buffer.data(), full.data());
+
+        // The preview consists of 8x8 blocks
+        let preview = &passes[0].buffer;
+        assert_eq!(preview.width(), orig.width());
+        if progressive {
+            assert_eq!(preview.get_pixel(0, 0), preview.get_pixel(7, 7));
+        }
+    }
+}
+
+#[test]
+pub fn test_dds() {
+    let orig = decode_file("tests/images/test.png").unwrap().buffer;
+
+    let mut data = vec![];
+    encode(&mut data, &orig, EncodingFormat::dds_default()).unwrap();
+
+    assert_eq!(
+        Format::from_reader(&mut Cursor::new(&data)).unwrap(),
+        Format::Dds
+    );
+
+    let mut png = vec![];
+    encode(&mut png, &orig, EncodingFormat::png_default()).unwrap();
+
+    // Both formats store the same 8 bit values
+    let result = decode_buffer(&data).unwrap().buffer;
+    let expected = decode_buffer(&png).unwrap().buffer;
+    assert_eq!(result.data(), expected.data());
+}
+
+#[test]
+pub fn test_gif() {
+    let orig = decode_file("tests/images/test.png").unwrap().buffer;
+
+    let mut data = vec![];
+    encode(&mut data, &orig, EncodingFormat::gif_default()).unwrap();
+
+    let result = decode_buffer(&data).unwrap().buffer;
+    assert_eq!(result.width(), orig.width());
+    assert_eq!(result.height(), orig.height());
+
+    for (c1, c2) in orig.data().iter().zip(result.data()) {
+        assert_eq!(c1.alpha() < 0.5, c2.alpha() == 0.0);
+    }
+
+    let palette = Palette::from_colors(vec![Rgb::RED, Rgb::BLUE, Rgb::WHITE]);
+
+    let mut data = vec![];
+    encode(
+        &mut data,
+        &orig,
+        EncodingFormat::gif_with_palette(palette.clone()),
+    )
+    .unwrap();
+
+    let result = decode_buffer(&data).unwrap().buffer;
+    for c in result.data() {
+        assert!(c.alpha() == 0.0 || palette.colors().contains(c), "{}", c);
+    }
+
+    let too_large = Palette::from_colors(vec![Rgb::RED; 257]);
+    assert!(matches!(
+        encode(
+            &mut vec![],
+            &orig,
+            EncodingFormat::gif_with_palette(too_large)
+        ),
+        Err(EncodingError::InvalidConfig { .. })
+    ));
+}
+
+#[test]
+pub fn test_gif_dither() {
+    let gradient = PixelBuffer::new_from_func(64, 16, |x, _| {
+        let v = x as f32 / 63.0;
+        Srgb::new(v, v, v).to_rgb()
+    });
+
+    let palette = Palette::from_colors(vec![Rgb::BLACK, Rgb::WHITE]);
+
+    let encode_with_dither = |dither: GifDither| {
+        let mut data = vec![];
+        let format = EncodingFormat::Gif {
+            palette: Some(palette.clone()),
+            dither,
+            transparency_threshold: 0.5,
+        };
+        encode(&mut data, &gradient, format).unwrap();
+        decode_buffer(&data).unwrap().buffer
+    };
+
+    // Average sRGB value of a column
+    let column_mean = |buffer: &PixelBuffer<Rgb>, x: u32| {
+        (0..16)
+            .map(|y| buffer.get_pixel(x, y).to_srgb().red())
+            .sum::<f32>()
+            / 16.0
+    };
+
+    let result = encode_with_dither(GifDither::None);
+    assert!(column_mean(&result, 16) < 0.001);
+    assert!(column_mean(&result, 48) > 0.999);
+
+    for dither in [GifDither::FloydSteinberg, GifDither::Ordered] {
+        let result = encode_with_dither(dither);
+
+        let left = (8..24).map(|x| column_mean(&result, x)).sum::<f32>() / 16.0;
+        let right = (40..56).map(|x| column_mean(&result, x)).sum::<f32>() / 16.0;
+
+        assert!(left > 0.1 && left < 0.4, "{:?} {}", dither, left);
+        assert!(right > 0.6 && right < 0.9, "{:?} {}", dither, right);
+    }
+}
+
+#[test]
+pub fn test_gif_animation() {
+    let red = PixelBuffer::new_with_color(4, 4, Rgb::RED);
+    let mut dot = red.clone();
+    dot.put_pixel(1, 1, Rgb::BLUE);
+    let mut hole = dot.clone();
+    hole.put_pixel(0, 0, Rgb::NONE);
+    let green = PixelBuffer::new_from_func(4, 4, |x, y| {
+        if x >= 2 && y >= 2 {
+            Rgb::GREEN
+        } else {
+            *hole.get_pixel(x, y)
+        }
+    });
+
+    let buffers = [&red, &dot, &dot, &hole, &green];
+    let frames: Vec<_> = buffers
+        .iter()
+        .enumerate()
+        .map(|(i, b)| (*b, Duration::from_millis(100 * i as u64)))
+        .collect();
+
+    for loop_count in [0, 1, 3] {
+        let mut data = vec![];
+        encode_animation(
+            &mut data,
+            &frames,
+            loop_count,
+            EncodingFormat::gif_default(),
+        )
+        .unwrap();
+
+        let result = decode_animation(Cursor::new(&data)).unwrap();
+        assert_eq!(result.loop_count, loop_count);
+        assert_eq!(result.frames.len(), buffers.len());
+
+        for (i, (frame, expected)) in result.frames.iter().zip(buffers).enumerate() {
+            assert_eq!(frame.buffer.data(), expected.data(), "frame {}", i);
+            assert_eq!(frame.delay, Duration::from_millis(100 * i as u64));
+        }
+
+        // Still images only use the first frame
+        assert_eq!(decode_buffer(&data).unwrap().buffer.data(), red.data());
+    }
+
+    let small = PixelBuffer::new_with_color(2, 2, Rgb::RED);
+    for (frames, format) in [
+        (vec![], EncodingFormat::gif_default()),
+        (
+            vec![(&red, Duration::ZERO), (&small, Duration::ZERO)],
+            EncodingFormat::gif_default(),
+        ),
+        (vec![(&red, Duration::ZERO)], EncodingFormat::Dds),
+        (vec![(&red, Duration::ZERO)], EncodingFormat::jpeg_default()),
+    ] {
+        assert!(matches!(
+            encode_animation(&mut vec![], &frames, 0, format),
+            Err(EncodingError::InvalidConfig { .. })
+        ));
+    }
+
+    let mut data = vec![];
+    encode(&mut data, &red, EncodingFormat::jpeg_default()).unwrap();
+    let result = decode_animation(Cursor::new(&data)).unwrap();
+    assert_eq!(result.frames.len(), 1);
+    assert_eq!(result.frames[0].delay, Duration::ZERO);
+}
+
+#[test]
+pub fn test_apng() {
+    let frames: Vec<_> = (0..6)
+        .map(|i| {
+            PixelBuffer::new_from_func(8, 6, move |x, y| {
+                if x == i && y < 3 {
+                    Rgb::NONE
+                } else if y == i {
+                    Srgb::new_with_alpha(0.2, 0.6, 1.0, 0.6).to_rgb()
+                } else if x == 5 {
+                    Rgb::RED
+                } else {
+                    Rgb::BLUE
+                }
+            })
+        })
+        .collect();
+
+    let input: Vec<_> = frames
+        .iter()
+        .enumerate()
+        .map(|(i, b)| (b, Duration::from_millis(20 * i as u64 + 30)))
+        .collect();
+
+    for (loop_count, color_type) in [(0, PngColorType::Rgba8), (2, PngColorType::Rgba16)] {
+        let format = EncodingFormat::Png {
+            color_type,
+            compression: PngCompression::Default,
+            filter: PngFilterType::Paeth,
+            interlaced: false,
+            palette: None,
+        };
+
+        let mut data = vec![];
+        encode_animation(&mut data, &input, loop_count, format).unwrap();
+
+        let result = decode_animation(Cursor::new(&data)).unwrap();
+        assert_eq!(result.loop_count, loop_count);
+        assert_eq!(result.frames.len(), frames.len());
+
+        for (frame, (expected, delay)) in result.frames.iter().zip(&input) {
+            assert_eq!(frame.delay, *delay);
+
+            for (c1, c2) in frame.buffer.data().iter().zip(expected.data()) {
+                let (c1, c2) = (c1.to_srgb(), c2.to_srgb());
+                for i in 0..4 {
+                    assert!((c1.data[i] - c2.data[i]).abs() < ALLOWED_DELTA);
+                }
+            }
+        }
+
+        // The default image is the first frame
+        let still = decode_buffer(&data).unwrap().buffer;
+        assert_eq!(still.data(), result.frames[0].buffer.data());
+    }
+
+    let gray = PixelBuffer::new_with_color(8, 6, Rgb::WHITE);
+    let mut data = vec![];
+    encode_animation(
+        &mut data,
+        &[(&frames[0], Duration::ZERO), (&gray, Duration::ZERO)],
+        0,
+        EncodingFormat::Png {
+            color_type: PngColorType::L8,
+            compression: PngCompression::Fast,
+            filter: PngFilterType::NoFilter,
+            interlaced: false,
+            palette: None,
+        },
+    )
+    .unwrap();
+
+    let result = decode_animation(Cursor::new(&data)).unwrap();
+    assert_eq!(result.frames[1].buffer.data(), gray.data());
+
+    let small = PixelBuffer::new_with_color(2, 2, Rgb::RED);
+    let err = encode_animation(
+        &mut vec![],
+        &[(&gray, Duration::ZERO), (&small, Duration::ZERO)],
+        0,
+        EncodingFormat::png_default(),
+    );
+    assert!(matches!(err, Err(EncodingError::InvalidConfig { .. })));
+}
+
+#[test]
+pub fn test_encode_options() {
+    let options = EncodeOptions::default();
+    assert!(options.write_exif() && options.write_icc());
+
+    let options = EncodeOptions {
+        keep_icc: true,
+        ..EncodeOptions::strip()
+    };
+    assert!(!options.write_exif());
+    assert!(options.write_icc());
+
+    let buffer = PixelBuffer::new_with_color(3, 2, Rgb::RED);
+
+    let mut expected = vec![];
+    encode(&mut expected, &buffer, EncodingFormat::jpeg_default()).unwrap();
+
+    let mut data = vec![];
+    encode_with_options(&mut data, &buffer, EncodingFormat::jpeg_default(), &options).unwrap();
+    assert_eq!(data, expected);
+}
+
+#[test]
+pub fn test_encode_rounding_and_dither() {
+    let buffer = PixelBuffer::new_from_func(64, 8, |x, _| {
+        let v = (x as f32 * 2.0 + 100.4) / 255.0;
+        Srgb::new(v, v, v).to_rgb()
+    });
+
+    let mut data = vec![];
+    encode(&mut data, &buffer, EncodingFormat::png_default()).unwrap();
+    let rounded = decode_buffer(&data).unwrap().buffer;
+
+    for (c1, c2) in buffer.data().iter().zip(rounded.data()) {
+        let diff = (c1.to_srgb().red() - c2.to_srgb().red()).abs() * 255.0;
+        assert!(diff <= 0.5, "{}", diff);
+    }
+
+    let options = EncodeOptions {
+        dither: true,
+        ..EncodeOptions::default()
+    };
+
+    let mut data = vec![];
+    encode_with_options(&mut data, &buffer, EncodingFormat::png_default(), &options).unwrap();
+    let dithered = decode_buffer(&data).unwrap().buffer;
+
+    assert_ne!(dithered.data(), rounded.data());
+
+    // The dithered image keeps the average value that gets lost by rounding
+    let mean = |buffer: &PixelBuffer<Rgb>| {
+        buffer
+            .data()
+            .iter()
+            .map(|c| c.to_srgb().red() as f64)
+            .sum::<f64>()
+            / buffer.data().len() as f64
+    };
+
+    let expected = mean(&buffer);
+    assert!((mean(&dithered) - expected).abs() < (mean(&rounded) - expected).abs());
+}
+
+#[test]
+pub fn test_heif_detection() {
+    let header = [
+        0, 0, 0, 24, b'f', b't', b'y', b'p', b'h', b'e', b'i', b'c', 0, 0, 0, 0,
+    ];
+
+    assert_eq!(
+        Format::from_reader(&mut Cursor::new(&header)).unwrap(),
+        Format::Heif
+    );
+    assert_eq!(Format::from_mime_type("image/heic"), Some(Format::Heif));
+
+    let mp4 = [0, 0, 0, 24, b'f', b't', b'y', b'p', b'i', b's', b'o', b'm'];
+    assert!(Format::from_reader(&mut Cursor::new(&mp4)).is_err());
+
+    #[cfg(not(feature = "heif"))]
+    assert_eq!(decode_buffer(&header).err().unwrap().code(), "unsupported");
+}
+
+#[test]
+pub fn test_jxl_detection() {
+    let codestream = [0xFF, 0x0A, 0xFA, 0x1F];
+    let container = [
+        0, 0, 0, 0x0C, b'J', b'X', b'L', b' ', 0x0D, 0x0A, 0x87, 0x0A,
+    ];
+
+    for data in [&codestream[..], &container[..]] {
+        assert_eq!(
+            Format::from_reader(&mut Cursor::new(data)).unwrap(),
+            Format::Jxl
+        );
+        #[cfg(not(feature = "jxl"))]
+        assert_eq!(decode_buffer(data).err().unwrap().code(), "unsupported");
+        #[cfg(feature = "jxl")]
+        assert!(decode_buffer(data).is_err());
+    }
+
+    #[cfg(not(feature = "jxl"))]
+    {
+        let err = EncodingFormat::from_path(Path::new("test.jxl")).unwrap_err();
+        assert_eq!(err.format(), Some(Format::Jxl));
+    }
+    #[cfg(feature = "jxl")]
+    assert!(matches!(
+        EncodingFormat::from_path(Path::new("test.jxl")).unwrap(),
+        EncodingFormat::Jxl { lossless: true, .. }
+    ));
+}
+
+#[cfg(feature = "jxl")]
+#[test]
+pub fn test_jxl_decode() {
+    // Small codestream from the documentation of jxl-oxide
+    let data = [
+        0xff, 0x0a, 0x30, 0x54, 0x10, 0x09, 0x08, 0x06, 0x01, 0x00, 0x78, 0x00, 0x4b, 0x38, 0x41,
+        0x3c, 0xb6, 0x3a, 0x51, 0xfe, 0x00, 0x47, 0x1e, 0xa0, 0x85, 0xb8, 0x27, 0x1a, 0x48, 0x45,
+        0x84, 0x1b, 0x71, 0x4f, 0xa8, 0x3e, 0x8e, 0x30, 0x03, 0x92, 0x84, 0x01,
+    ];
+
+    let result = decode_buffer(&data).unwrap().buffer;
+    assert_eq!(result.width(), 240);
+    assert_eq!(result.height(), 135);
+    assert!(result.data().iter().all(|c| c.alpha() == 1.0));
+}
+
+#[cfg(feature = "jxl")]
+#[test]
+pub fn test_jxl_roundtrip() {
+    let orig = decode_file("tests/images/test.png").unwrap().buffer;
+
+    let transparent = PixelBuffer::new_from_func(16, 8, |x, y| {
+        Srgb::new_with_alpha(x as f32 / 15.0, y as f32 / 7.0, 0.5, (x + y) as f32 / 21.0).to_rgb()
+    });
+
+    for (buffer, effort) in [(&orig, 7), (&transparent, 1), (&transparent, 9)] {
+        let mut data = vec![];
+        let format = EncodingFormat::Jxl {
+            quality: 90,
+            effort,
+            lossless: true,
+        };
+        encode(&mut data, buffer, format).unwrap();
+
+        assert_eq!(
+            Format::from_reader(&mut Cursor::new(&data)).unwrap(),
+            Format::Jxl
+        );
+
+        let result = decode_buffer(&data).unwrap().buffer;
+
+        assert_eq!(buffer.width(), result.width());
+        assert_eq!(buffer.height(), result.height());
+
+        for ((x, y, c1), &c2) in buffer.enumerate().zip(result.data().iter()) {
+            let c1 = c1.to_srgb();
+            let c2 = c2.to_srgb();
+            for i in 0..4 {
+                if (c1.data[i] - c2.data[i]).abs() > 0.5 / 255.0 + 1e-4 {
+                    panic!("Expected {} got {} at position {}x{}", c1, c2, x, y);
+                }
+            }
+        }
+    }
+
+    let lossy = EncodingFormat::jxl_default().with_quality(80);
+    assert_eq!(lossy.quality(), Some(80));
+
+    let err = encode(
+        &mut vec![],
+        &orig,
+        EncodingFormat::Jxl {
+            quality: 80,
+            effort: 7,
+            lossless: false,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err.code(), "invalid_config");
+
+    let err = encode(
+        &mut vec![],
+        &PixelBuffer::new(1, 8),
+        EncodingFormat::jxl_default(),
+    )
+    .unwrap_err();
+    assert_eq!(err.code(), "bad_dimensions");
+}
+
+/// The psd files are test fixtures of the psd crate
+fn read_psd(name: &str) -> Vec<u8> {
+    std::fs::read(format!("tests/images/psd/{}", name)).unwrap()
+}
+
+#[test]
+pub fn test_psd() {
+    let data = read_psd("rle-3-layer-8x8.psd");
+
+    assert_eq!(
+        Format::from_reader(&mut Cursor::new(&data)).unwrap(),
+        Format::Psd
+    );
+
+    // The blue top layer covers all other layers
+    let composite = decode_buffer(&data).unwrap().buffer;
+    assert_eq!((composite.width(), composite.height()), (8, 8));
+    assert!(composite.data().iter().all(|c| *c == Rgb::BLUE));
+
+    let layers = decode_layers(Cursor::new(&data)).unwrap();
+    assert_eq!((layers.width, layers.height), (8, 8));
+
+    let names: Vec<_> = layers.layers.iter().map(|l| l.name.as_str()).collect();
+    assert_eq!(names, ["Red Layer", "Green Layer", "Blue Layer"]);
+
+    for (layer, color) in layers.layers.iter().zip([Rgb::RED, Rgb::GREEN, Rgb::BLUE]) {
+        assert!(layer.buffer.data().iter().all(|c| *c == color));
+        assert!(layer.visible);
+        assert_eq!(layer.opacity, 1.0);
+        assert_eq!(layer.blend_mode, "norm");
+    }
+}
+
+#[test]
+pub fn test_psd_layers() {
+    let layers = decode_layers(Cursor::new(read_psd("transparent-top-layer-2x1.psd"))).unwrap();
+    let top = &layers.layers[2];
+    assert_eq!((top.x, top.y), (1, 0));
+    assert_eq!((top.buffer.width(), top.buffer.height()), (1, 1));
+
+    let layers = decode_layers(Cursor::new(read_psd("layer-larger.psd"))).unwrap();
+    let layer = &layers.layers[0];
+    assert_eq!((layers.width, layers.height), (1, 1));
+    assert_eq!((layer.x, layer.y), (-6, -3));
+    assert_eq!((layer.buffer.width(), layer.buffer.height()), (13, 9));
+
+    let layers = decode_layers(Cursor::new(read_psd("green-chinese-layer-name-1x1.psd"))).unwrap();
+    assert_eq!(layers.layers[0].name, "圆角矩形");
+
+    let layers = decode_layers(Cursor::new(read_psd("blue-red-1x1-multiply.psd"))).unwrap();
+    let top = &layers.layers[1];
+    assert_eq!(top.blend_mode, "mul ");
+    assert!((top.opacity - 0.5).abs() < 0.01);
+
+    // Images without layers result in a single layer
+    let mut png = vec![];
+    let buffer = PixelBuffer::new_with_color(3, 2, Rgb::RED);
+    encode(&mut png, &buffer, EncodingFormat::png_default()).unwrap();
+
+    let layers = decode_layers(Cursor::new(&png)).unwrap();
+    assert_eq!(layers.layers.len(), 1);
+    assert_eq!(layers.layers[0].buffer.data(), buffer.data());
+}
+
+#[test]
+pub fn test_psd_16_bit() {
+    let composite = decode_buffer(&read_psd("one-channel-1x1.psd"))
+        .unwrap()
+        .buffer;
+    assert!(composite.get_pixel(0, 0).is_grayscale());
+
+    let c = composite.get_pixel(0, 0).to_srgb();
+    assert!((c.red() - 175.0 / 255.0).abs() < ALLOWED_DELTA);
+}
+
+#[test]
+pub fn test_psd_truncated() {
+    let data = read_psd("transparent-top-layer-2x1.psd");
+
+    for len in (0..data.len()).step_by(7) {
+        let _ = decode_buffer(&data[..len]);
+        let _ = decode_layers(Cursor::new(&data[..len]));
+    }
+
+    let err = EncodingFormat::from_path(Path::new("test.psd")).unwrap_err();
+    assert_eq!(err.format(), Some(Format::Psd));
+    assert_eq!(
+        Format::from_mime_type("image/vnd.adobe.photoshop"),
+        Some(Format::Psd)
+    );
+}
+
+#[test]
+pub fn test_ora() {
+    let red = PixelBuffer::new_with_color(3, 2, Rgb::RED);
+    let blue = PixelBuffer::new_with_color(1, 1, Rgb::BLUE);
+
+    let image = LayeredImage {
+        width: 4,
+        height: 3,
+        layers: vec![
+            ImageLayer {
+                name: "red".to_owned(),
+                buffer: red.clone(),
+                x: 1,
+                y: 1,
+                opacity: 0.5,
+                visible: true,
+                blend_mode: "mul ".to_owned(),
+            },
+            ImageLayer {
+                name: "blue".to_owned(),
+                buffer: blue.clone(),
+                x: -1,
+                y: 0,
+                opacity: 1.0,
+                visible: false,
+                blend_mode: "svg:dst-in".to_owned(),
+            },
+        ],
+    };
+
+    let composite = PixelBuffer::new_with_color(4, 3, Rgb::GREEN);
+
+    let mut data = vec![];
+    encode_ora(&mut data, &image, &composite).unwrap();
+
+    assert_eq!(
+        Format::from_reader(&mut Cursor::new(&data)).unwrap(),
+        Format::Ora
+    );
+    assert_eq!(
+        decode_buffer(&data).unwrap().buffer.data(),
+        composite.data()
+    );
+
+    let result = decode_layers(Cursor::new(&data)).unwrap();
+    assert_eq!((result.width, result.height), (4, 3));
+    assert_eq!(result.layers.len(), 2);
+
+    for (layer, expected) in result.layers.iter().zip(&image.layers) {
+        assert_eq!(layer.name, expected.name);
+        assert_eq!(layer.buffer.data(), expected.buffer.data());
+        assert_eq!((layer.x, layer.y), (expected.x, expected.y));
+        assert_eq!(layer.opacity, expected.opacity);
+        assert_eq!(layer.visible, expected.visible);
+        assert_eq!(layer.blend_mode, expected.blend_mode);
+    }
+
+    // Other zip files are not detected as ora
+    let mut zip = data.clone();
+    zip[38] = b'x';
+    assert!(Format::from_reader(&mut Cursor::new(&zip)).is_err());
+
+    for len in (0..data.len()).step_by(11) {
+        let _ = decode_buffer(&data[..len]);
+        let _ = decode_layers(Cursor::new(&data[..len]));
+    }
+
+    let err = EncodingFormat::from_path(Path::new("test.ora")).unwrap_err();
+    assert_eq!(err.format(), Some(Format::Ora));
 }
@@ -0,0 +1,412 @@
+//! Content addressed disk cache of processed images
+//!
+//! Entries are keyed by the hash of the source (the bytes of an opened file or the pixels of an
+//! image passed to `Queue::apply()`) combined with the hash of the chain of commands applied to
+//! it. Running the same queue again on unchanged files loads the results from the cache instead of
+//! decoding and processing the images again.
+//!
+//! Only commands that depend on nothing but the current image are cached. Commands with side
+//! effects like `Save`, commands reading other files like `Diff` and random noise are always
+//! executed.
+
+use std::fs::File;
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+
+use d10::{EncodingError, Image, Rgb};
+use sha2::{Digest, Sha256};
+
+use crate::commands::{execute_step, Cmd, Context};
+use crate::log::Log;
+use crate::{CommandError, CommandFailure};
+
+const MAGIC: &[u8; 4] = b"D10C";
+const EXTENSION: &str = "d10c";
+const HEADER_SIZE: usize = 12;
+const PIXEL_SIZE: usize = 16;
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Hash of the content of a file
+pub fn hash_file<P>(path: P) -> std::io::Result<String>
+where
+    P: AsRef<Path>,
+{
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+
+    loop {
+        match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(len) => hasher.update(&buf[..len]),
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// Hash of the exact pixel values of an image
+///
+/// Unlike `Image::content_hash()` this doesn't round the values, so images that differ only
+/// slightly get different hashes.
+pub fn hash_image(image: &Image) -> String {
+    let mut hasher = Sha256::new();
+
+    hasher.update(image.width().to_le_bytes());
+    hasher.update(image.height().to_le_bytes());
+
+    for c in image.data() {
+        for v in c.data {
+            hasher.update(v.to_le_bytes());
+        }
+    }
+
+    to_hex(&hasher.finalize())
+}
+
+/// Hash of a chain of commands including the version of this crate
+///
+/// The version is part of the hash because the implementation of the commands might change
+/// between releases.
+pub fn chain_hash(commands: &[Cmd]) -> String {
+    let mut hasher = Sha256::new();
+
+    hasher.update(env!("CARGO_PKG_VERSION"));
+
+    for cmd in commands {
+        hasher.update(format!("\n{:?}", cmd));
+    }
+
+    to_hex(&hasher.finalize())
+}
+
+/// Disk cache storing processed images in a directory
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+impl DiskCache {
+    /// Create a cache in `dir`, the directory is created with the first stored entry
+    pub fn new<P: Into<PathBuf>>(dir: P) -> DiskCache {
+        DiskCache { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Key of the result of running `commands` on a source with the given hash
+    pub fn key(source_hash: &str, commands: &[Cmd]) -> String {
+        let mut hasher = Sha256::new();
+
+        hasher.update(source_hash);
+        hasher.update(":");
+        hasher.update(chain_hash(commands));
+
+        to_hex(&hasher.finalize())
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        let prefix = key.get(..2).unwrap_or(key);
+        self.dir.join(prefix).join(format!("{}.{}", key, EXTENSION))
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.entry_path(key).is_file()
+    }
+
+    /// Load a cached image
+    ///
+    /// Missing, unreadable or corrupt entries are treated as not cached.
+    pub fn get(&self, key: &str) -> Option<Image> {
+        let data = std::fs::read(self.entry_path(key)).ok()?;
+
+        if data.len() < HEADER_SIZE || &data[..4] != MAGIC {
+            return None;
+        }
+
+        let width = u32::from_le_bytes(data[4..8].try_into().ok()?);
+        let height = u32::from_le_bytes(data[8..12].try_into().ok()?);
+        let pixels = &data[HEADER_SIZE..];
+
+        if pixels.len() != width as usize * height as usize * PIXEL_SIZE {
+            return None;
+        }
+
+        let data = pixels
+            .chunks_exact(PIXEL_SIZE)
+            .map(|p| {
+                let mut data = [0.0; 4];
+                for (v, bytes) in data.iter_mut().zip(p.chunks_exact(4)) {
+                    *v = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                }
+                Rgb { data }
+            })
+            .collect();
+
+        Some(Image::new_from_raw(width, height, data))
+    }
+
+    /// Store an image without any loss of precision
+    ///
+    /// The entry is written to a temporary file first, so concurrent runs never see partially
+    /// written entries.
+    pub fn put(&self, key: &str, image: &Image) -> std::io::Result<()> {
+        let path = self.entry_path(key);
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut data = Vec::with_capacity(HEADER_SIZE + image.data().len() * PIXEL_SIZE);
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&image.width().to_le_bytes());
+        data.extend_from_slice(&image.height().to_le_bytes());
+
+        for c in image.data() {
+            for v in c.data {
+                data.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+
+        let tmp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+        std::fs::write(&tmp_path, data)?;
+        std::fs::rename(tmp_path, path)
+    }
+
+    /// Remove all entries
+    pub fn clear(&self) -> std::io::Result<()> {
+        match std::fs::remove_dir_all(&self.dir) {
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+            res => res,
+        }
+    }
+}
+
+/// Execute an `Open` or the current image followed by a chain of cacheable commands
+///
+/// Returns the number of executed or skipped commands or `None` if the commands can't be cached.
+pub(crate) fn execute_cached(
+    ctx: &mut Context,
+    commands: &[Cmd],
+    log: &mut Log,
+) -> Result<Option<usize>, CommandFailure> {
+    let Some(cache) = ctx.cache.clone() else {
+        return Ok(None);
+    };
+
+    let (source_hash, open) = match commands.first() {
+        // Errors are reported by the regular execution of `Open`
+        Some(Cmd::Open(path)) => match hash_file(path) {
+            Ok(hash) => (hash, Some(path)),
+            Err(_) => return Ok(None),
+        },
+        Some(cmd) if cmd.is_pure() => match &ctx.image {
+            Some(image) => (hash_image(image), None),
+            None => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+
+    let start = usize::from(open.is_some());
+    let end = start
+        + commands[start..]
+            .iter()
+            .take_while(|cmd| cmd.is_pure())
+            .count();
+
+    if end == start {
+        return Ok(None);
+    }
+
+    let key = DiskCache::key(&source_hash, &commands[start..end]);
+
+    if let Some(image) = cache.get(&key) {
+        let image_bytes = std::mem::size_of_val(image.data());
+        for cmd in &commands[..end] {
+            log.log_cached(cmd, image_bytes);
+        }
+
+        ctx.image = Some(image);
+        if let Some(path) = open {
+            ctx.inputs.push(path.to_owned());
+        }
+
+        return Ok(Some(end));
+    }
+
+    for cmd in &commands[..end] {
+        execute_step(ctx, cmd, log)?;
+    }
+
+    if let Some(image) = &ctx.image {
+        cache.put(&key, image).map_err(|err| {
+            CommandFailure::new(CommandError::Encoding(EncodingError::IoError(err)))
+                .with_command(&commands[end - 1])
+        })?;
+    }
+
+    Ok(Some(end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Queue;
+
+    fn count_entries(cache: &DiskCache) -> usize {
+        std::fs::read_dir(cache.dir())
+            .map(|dirs| {
+                dirs.map(|dir| std::fs::read_dir(dir.unwrap().path()).unwrap().count())
+                    .sum()
+            })
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn test_put_get() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let cache = DiskCache::new(dir.join("cache"));
+
+        let image = Image::new_from_raw(
+            2,
+            1,
+            vec![
+                Rgb::new(0.1, 0.2, 0.3),
+                Rgb {
+                    data: [1.5, -0.25, 0.0, 0.5],
+                },
+            ],
+        );
+        let key = DiskCache::key(&hash_image(&image), &[Cmd::Invert]);
+
+        assert!(!cache.contains(&key));
+        assert!(cache.get(&key).is_none());
+
+        cache.put(&key, &image).unwrap();
+        assert!(cache.contains(&key));
+
+        let cached = cache.get(&key).unwrap();
+        assert_eq!((cached.width(), cached.height()), (2, 1));
+        assert_eq!(cached.data(), image.data());
+
+        std::fs::write(cache.entry_path(&key), b"D10C").unwrap();
+        assert!(cache.get(&key).is_none());
+
+        cache.clear().unwrap();
+        assert!(!cache.dir().exists());
+        cache.clear().unwrap();
+    }
+
+    #[test]
+    fn test_key() {
+        let key = DiskCache::key("source", &[Cmd::Invert, Cmd::Gamma(2.0)]);
+
+        assert_eq!(key.len(), 64);
+        assert_eq!(
+            key,
+            DiskCache::key("source", &[Cmd::Invert, Cmd::Gamma(2.0)])
+        );
+        assert_ne!(
+            key,
+            DiskCache::key("other", &[Cmd::Invert, Cmd::Gamma(2.0)])
+        );
+        assert_ne!(
+            key,
+            DiskCache::key("source", &[Cmd::Invert, Cmd::Gamma(2.1)])
+        );
+        assert_ne!(
+            key,
+            DiskCache::key("source", &[Cmd::Gamma(2.0), Cmd::Invert])
+        );
+    }
+
+    #[test]
+    fn test_queue_cache() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let cache = DiskCache::new(dir.join("cache"));
+
+        let input = dir.join("input.png");
+        let output = dir.join("output.png");
+
+        Image::new_with_color(4, 2, Rgb::BLACK)
+            .save(&input)
+            .unwrap();
+
+        let queue = || {
+            Queue::new()
+                .with_cache(cache.clone())
+                .silent()
+                .open(&input)
+                .invert()
+                .fit(2, 2)
+                .save(&output)
+        };
+
+        let report = queue().run_with_report().unwrap();
+        assert_eq!(report.commands.len(), 4);
+        assert_eq!(count_entries(&cache), 1);
+
+        // Replace the cached result to detect if it was used
+        let key = DiskCache::key(
+            &hash_file(&input).unwrap(),
+            &[
+                Cmd::Invert,
+                Cmd::Fit {
+                    width: 2,
+                    height: 2,
+                },
+            ],
+        );
+        cache
+            .put(&key, &Image::new_with_color(2, 1, Rgb::RED))
+            .unwrap();
+
+        queue().run().unwrap();
+        let cached = Image::open(&output).unwrap();
+        assert_eq!(cached.get_pixel(0, 0), &Rgb::RED);
+
+        // Changing the source invalidates the entry
+        Image::new_with_color(4, 2, Rgb::WHITE)
+            .save(&input)
+            .unwrap();
+
+        queue().run().unwrap();
+        let changed = Image::open(&output).unwrap();
+        assert_eq!(changed.get_pixel(0, 0), &Rgb::BLACK);
+        assert_eq!(count_entries(&cache), 2);
+    }
+
+    #[test]
+    fn test_apply_cache() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let cache = DiskCache::new(dir.join("cache"));
+
+        let queue = Queue::new().with_cache(cache.clone()).invert();
+        let image = Image::new_with_color(2, 2, Rgb::BLACK);
+
+        let first = queue.apply(image.clone()).unwrap();
+        let second = queue.apply(image).unwrap();
+
+        assert_eq!(first.data(), second.data());
+        assert_eq!(second.get_pixel(1, 1), &Rgb::WHITE);
+        assert_eq!(count_entries(&cache), 1);
+
+        // Random noise must never be cached
+        Queue::new()
+            .with_cache(cache.clone())
+            .with(Cmd::RandomNoise(0.5))
+            .apply(Image::new(2, 2))
+            .unwrap();
+        assert_eq!(count_entries(&cache), 1);
+    }
+}
@@ -1,14 +1,25 @@
-use d10::{FilterMode, Image, Intensity};
+use d10::ops::{text_size, DiffMode, LookPreset, Metric, ScanMode};
+use d10::ops::{BorderFill, BorderStyle, HalftoneShape};
+use d10::{
+    generate_icons, save_icons, Color, EncodeOptions, EncodingError, FilterMode, Format, IconSet,
+    Image, Intensity, Region, Rgb,
+};
+use std::fs::File;
 use std::path::{Path, PathBuf};
 
 use crate::log::Log;
-use crate::{CommandError, CommandResult};
+use crate::{CommandError, CommandFailure, CommandResult};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Cmd {
     Silent,
+    /// Record time and image size of all following commands and print a report after the run
+    Profile,
     Open(PathBuf),
     Save(PathBuf),
+    /// Remove all metadata when saving images
+    Strip,
+    SaveIcons(PathBuf),
     ToGray(Intensity),
     Invert,
     Gamma(f32),
@@ -31,73 +42,316 @@ pub enum Cmd {
         radians: f32,
         filter: FilterMode,
     },
+    Straighten(f32),
+    Offset {
+        dx: i32,
+        dy: i32,
+    },
+    MakeSeamless(u32),
+    UpscaleEnhanced(f32),
     RandomNoise(f32),
     SaltNPepperNoise(f32),
     RgbNoise(f32),
+    Halftone {
+        cell_size: u32,
+        angle: f32,
+        shape: HalftoneShape,
+    },
+    Duotone {
+        dark_color: Rgb,
+        light_color: Rgb,
+    },
+    Border {
+        width: u32,
+        fill: BorderFill,
+        style: BorderStyle,
+    },
+    /// Put the image on a matte like a polaroid photo
+    Polaroid,
+    /// Apply a named look like a film emulation
+    Look(LookPreset),
+    AutoEnhance(f32),
+    ScanEnhance(ScanMode),
+    ContactSheet {
+        columns: u32,
+        thumb_size: u32,
+    },
+    Diff {
+        other: PathBuf,
+        mode: DiffMode,
+        threshold: f32,
+    },
+    /// Compare the final image with a reference image after all other commands
+    Compare(PathBuf),
+    /// Metric used by `Compare`
+    Metric(Metric),
+    /// Minimal similarity required by `Compare`
+    Threshold(f32),
+    /// Print information about the current image
+    Identify {
+        json: bool,
+    },
+    /// Save the histogram of the current image as chart or as json if the path ends with `.json`
+    Histogram(PathBuf),
+    /// Shrink the image to fit into the given size while keeping the aspect ratio
+    Fit {
+        width: u32,
+        height: u32,
+    },
+    /// Scale and crop the image to exactly fill the given size
+    Cover {
+        width: u32,
+        height: u32,
+    },
+    /// Crop the most interesting part of the image and scale it to the given size
+    SmartCrop {
+        width: u32,
+        height: u32,
+    },
+    Unsharp {
+        radius: u32,
+        factor: f32,
+    },
+    /// Run the commands of a named preset, see `Presets`
+    Preset(String),
 }
 
 impl Cmd {
     pub(crate) fn ignore_in_log(&self) -> bool {
-        matches!(self, Cmd::Silent)
+        matches!(self, Cmd::Silent | Cmd::Profile)
+    }
+
+    /// Returns true if the command only changes the current image and the result only depends on
+    /// the image and the arguments
+    ///
+    /// Pure commands have no side effects like saving files and can be cached or replayed.
+    pub fn is_pure(&self) -> bool {
+        use Cmd::*;
+        matches!(
+            self,
+            ToGray(_)
+                | Invert
+                | Gamma(_)
+                | Level { .. }
+                | Brightness(_)
+                | Contrast(_)
+                | BrightnessContrast { .. }
+                | Saturation(_)
+                | StretchSaturation(_)
+                | Lightness(_)
+                | HueRotate(_)
+                | Rotate { .. }
+                | Straighten(_)
+                | Offset { .. }
+                | MakeSeamless(_)
+                | UpscaleEnhanced(_)
+                | Halftone { .. }
+                | Duotone { .. }
+                | Border { .. }
+                | Polaroid
+                | Look(_)
+                | AutoEnhance(_)
+                | ScanEnhance(_)
+                | Fit { .. }
+                | Cover { .. }
+                | SmartCrop { .. }
+                | Unsharp { .. }
+        )
+    }
+
+    /// Name of the command without its arguments, e.g. `Open` or `BrightnessContrast`
+    pub fn name(&self) -> String {
+        format!("{:?}", self)
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect()
+    }
+
+    /// The file or directory the command reads or writes
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Cmd::Open(path)
+            | Cmd::Save(path)
+            | Cmd::SaveIcons(path)
+            | Cmd::Compare(path)
+            | Cmd::Histogram(path)
+            | Cmd::Diff { other: path, .. } => Some(path),
+            _ => None,
+        }
     }
 }
 
+/// Pending comparison with a reference image
+pub(crate) struct Comparison {
+    pub reference: Option<PathBuf>,
+    pub metric: Metric,
+    pub threshold: Option<f32>,
+}
+
+#[derive(Default)]
 pub(crate) struct Context {
     pub image: Option<Image>,
+    /// Paths of all opened images
+    pub inputs: Vec<PathBuf>,
+    pub encode_options: EncodeOptions,
+    pub comparison: Option<Comparison>,
+    #[cfg(feature = "cache")]
+    pub cache: Option<crate::cache::DiskCache>,
 }
 
 impl Context {
     fn image(&mut self) -> CommandResult<&mut Image> {
         self.image.as_mut().ok_or(CommandError::MissingImage)
     }
+
+    /// Size of the pixel data of the current image
+    fn image_bytes(&self) -> usize {
+        self.image
+            .as_ref()
+            .map_or(0, |img| std::mem::size_of_val(img.data()))
+    }
+
+    fn comparison(&mut self) -> &mut Comparison {
+        self.comparison.get_or_insert(Comparison {
+            reference: None,
+            metric: Metric::Ssim,
+            threshold: None,
+        })
+    }
 }
 
-pub(crate) fn execute(ctx: &mut Context, commands: &[Cmd], log: &mut Log) -> CommandResult<()> {
-    for cmd in commands {
-        if !cmd.ignore_in_log() {
-            log.log_command_step(cmd);
+pub(crate) fn execute(
+    ctx: &mut Context,
+    commands: &[Cmd],
+    log: &mut Log,
+) -> Result<(), CommandFailure> {
+    let mut pos = 0;
+
+    while pos < commands.len() {
+        #[cfg(feature = "cache")]
+        if let Some(count) = crate::cache::execute_cached(ctx, &commands[pos..], log)? {
+            pos += count;
+            continue;
         }
 
-        use Cmd::*;
-        match cmd {
-            Silent => log.disable(),
-            Open(path) => execute_open(ctx, path)?,
-            Save(path) => execute_save(ctx, path)?,
-            ToGray(intensity) => execute_to_gray(ctx, *intensity)?,
-            Invert => execute_invert(ctx)?,
-            Gamma(gamma) => execute_gamma(ctx, *gamma)?,
-            Level {
-                black_point,
-                white_point,
-                gamma,
-            } => execute_level(ctx, *black_point, *white_point, *gamma)?,
-            Brightness(brightness) => execute_brightness(ctx, *brightness)?,
-            Contrast(contrast) => execute_contrast(ctx, *contrast)?,
-            BrightnessContrast {
-                brightness,
-                contrast,
-            } => execute_brightness_contrast(ctx, *brightness, *contrast)?,
-            Saturation(saturation) => execute_saturation(ctx, *saturation)?,
-            StretchSaturation(saturation) => execute_stretch_saturation(ctx, *saturation)?,
-            Lightness(lightness) => execute_lightness(ctx, *lightness)?,
-            HueRotate(rotation) => execute_hue_rotate(ctx, *rotation)?,
-            Rotate { radians, filter } => execute_rotate(ctx, *radians, *filter)?,
-            RandomNoise(alpha) => execute_random_noise(ctx, *alpha)?,
-            SaltNPepperNoise(threshold) => execute_salt_n_pepper_noise(ctx, *threshold)?,
-            RgbNoise(threshold) => execute_rgb_noise(ctx, *threshold)?,
-        };
+        execute_step(ctx, &commands[pos], log)?;
+        pos += 1;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn execute_step(
+    ctx: &mut Context,
+    cmd: &Cmd,
+    log: &mut Log,
+) -> Result<(), CommandFailure> {
+    if !cmd.ignore_in_log() {
+        log.log_command_step(cmd);
+    }
+
+    execute_cmd(ctx, cmd, log).map_err(|error| CommandFailure::new(error).with_command(cmd))?;
+
+    if !cmd.ignore_in_log() {
+        log.log_command_done(cmd, ctx.image_bytes());
     }
 
     Ok(())
 }
 
+fn execute_cmd(ctx: &mut Context, cmd: &Cmd, log: &mut Log) -> CommandResult<()> {
+    use Cmd::*;
+    match cmd {
+        Silent => log.disable(),
+        Profile => log.enable_profiling(),
+        Open(path) => execute_open(ctx, path)?,
+        Save(path) => execute_save(ctx, path)?,
+        Strip => ctx.encode_options = EncodeOptions::strip(),
+        SaveIcons(dir) => execute_save_icons(ctx, dir)?,
+        ToGray(intensity) => execute_to_gray(ctx, *intensity)?,
+        Invert => execute_invert(ctx)?,
+        Gamma(gamma) => execute_gamma(ctx, *gamma)?,
+        Level {
+            black_point,
+            white_point,
+            gamma,
+        } => execute_level(ctx, *black_point, *white_point, *gamma)?,
+        Brightness(brightness) => execute_brightness(ctx, *brightness)?,
+        Contrast(contrast) => execute_contrast(ctx, *contrast)?,
+        BrightnessContrast {
+            brightness,
+            contrast,
+        } => execute_brightness_contrast(ctx, *brightness, *contrast)?,
+        Saturation(saturation) => execute_saturation(ctx, *saturation)?,
+        StretchSaturation(saturation) => execute_stretch_saturation(ctx, *saturation)?,
+        Lightness(lightness) => execute_lightness(ctx, *lightness)?,
+        HueRotate(rotation) => execute_hue_rotate(ctx, *rotation)?,
+        Rotate { radians, filter } => execute_rotate(ctx, *radians, *filter)?,
+        Straighten(max_angle) => execute_straighten(ctx, *max_angle)?,
+        Offset { dx, dy } => execute_offset(ctx, *dx, *dy)?,
+        MakeSeamless(overlap) => execute_make_seamless(ctx, *overlap)?,
+        UpscaleEnhanced(factor) => execute_upscale_enhanced(ctx, *factor)?,
+        RandomNoise(alpha) => execute_random_noise(ctx, *alpha)?,
+        SaltNPepperNoise(threshold) => execute_salt_n_pepper_noise(ctx, *threshold)?,
+        RgbNoise(threshold) => execute_rgb_noise(ctx, *threshold)?,
+        Halftone {
+            cell_size,
+            angle,
+            shape,
+        } => execute_halftone(ctx, *cell_size, *angle, *shape)?,
+        Duotone {
+            dark_color,
+            light_color,
+        } => execute_duotone(ctx, *dark_color, *light_color)?,
+        Border { width, fill, style } => execute_border(ctx, *width, *fill, *style)?,
+        Polaroid => execute_polaroid(ctx)?,
+        Look(preset) => execute_look(ctx, *preset)?,
+        AutoEnhance(strength) => execute_auto_enhance(ctx, *strength)?,
+        ScanEnhance(mode) => execute_scan_enhance(ctx, *mode)?,
+        ContactSheet {
+            columns,
+            thumb_size,
+        } => execute_contact_sheet(ctx, *columns, *thumb_size)?,
+        Diff {
+            other,
+            mode,
+            threshold,
+        } => execute_diff(ctx, other, *mode, *threshold)?,
+        Compare(reference) => ctx.comparison().reference = Some(reference.clone()),
+        Metric(metric) => ctx.comparison().metric = *metric,
+        Threshold(threshold) => ctx.comparison().threshold = Some(*threshold),
+        Identify { json } => execute_identify(ctx, *json)?,
+        Histogram(path) => execute_histogram(ctx, path)?,
+        Fit { width, height } => execute_fit(ctx, *width, *height)?,
+        Cover { width, height } => execute_cover(ctx, *width, *height)?,
+        SmartCrop { width, height } => execute_smart_crop(ctx, *width, *height)?,
+        Unsharp { radius, factor } => execute_unsharp(ctx, *radius, *factor)?,
+        // Presets are expanded by the queue before any command is executed
+        Preset(name) => return Err(CommandError::UnknownPreset(name.clone())),
+    };
+
+    Ok(())
+}
+
 fn execute_open(ctx: &mut Context, path: &Path) -> CommandResult<()> {
     ctx.image = Some(Image::open(path)?);
+    ctx.inputs.push(path.to_owned());
     Ok(())
 }
 
 fn execute_save(ctx: &mut Context, path: &Path) -> CommandResult<()> {
-    ctx.image()?.save(path).map_err(|err| err.into())
+    let options = ctx.encode_options;
+
+    ctx.image()?
+        .save_with_options(path, None, &options)
+        .map_err(|err| err.into())
+}
+
+fn execute_save_icons(ctx: &mut Context, dir: &Path) -> CommandResult<()> {
+    let sets = [IconSet::Ico, IconSet::Png, IconSet::Android, IconSet::Ios];
+    let icons = generate_icons(ctx.image()?, &[], &sets)?;
+
+    save_icons(&icons, dir).map_err(|err| EncodingError::IoError(err).into())
 }
 
 fn execute_to_gray(ctx: &mut Context, intensity: Intensity) -> CommandResult<()> {
@@ -173,6 +427,26 @@ fn execute_rotate(ctx: &mut Context, radians: f32, filter: FilterMode) -> Comman
     Ok(())
 }
 
+fn execute_straighten(ctx: &mut Context, max_angle: f32) -> CommandResult<()> {
+    ctx.image = Some(ctx.image()?.straighten(max_angle));
+    Ok(())
+}
+
+fn execute_offset(ctx: &mut Context, dx: i32, dy: i32) -> CommandResult<()> {
+    ctx.image = Some(ctx.image()?.offset(dx, dy));
+    Ok(())
+}
+
+fn execute_make_seamless(ctx: &mut Context, overlap: u32) -> CommandResult<()> {
+    ctx.image = Some(ctx.image()?.make_seamless(overlap));
+    Ok(())
+}
+
+fn execute_upscale_enhanced(ctx: &mut Context, factor: f32) -> CommandResult<()> {
+    ctx.image = Some(ctx.image()?.upscale_enhanced(factor));
+    Ok(())
+}
+
 fn execute_random_noise(ctx: &mut Context, alpha: f32) -> CommandResult<()> {
     ctx.image = Some(ctx.image()?.random_noise(alpha));
     Ok(())
@@ -187,3 +461,337 @@ fn execute_rgb_noise(ctx: &mut Context, threshold: f32) -> CommandResult<()> {
     ctx.image = Some(ctx.image()?.rgb_noise(threshold));
     Ok(())
 }
+
+fn execute_halftone(
+    ctx: &mut Context,
+    cell_size: u32,
+    angle: f32,
+    shape: HalftoneShape,
+) -> CommandResult<()> {
+    ctx.image = Some(ctx.image()?.halftone(cell_size, angle, shape));
+    Ok(())
+}
+
+fn execute_duotone(ctx: &mut Context, dark_color: Rgb, light_color: Rgb) -> CommandResult<()> {
+    ctx.image = Some(ctx.image()?.duotone(dark_color, light_color));
+    Ok(())
+}
+
+fn execute_border(
+    ctx: &mut Context,
+    width: u32,
+    fill: BorderFill,
+    style: BorderStyle,
+) -> CommandResult<()> {
+    ctx.image = Some(ctx.image()?.add_border(width, fill, style));
+    Ok(())
+}
+
+fn execute_polaroid(ctx: &mut Context) -> CommandResult<()> {
+    ctx.image = Some(ctx.image()?.polaroid());
+    Ok(())
+}
+
+fn execute_look(ctx: &mut Context, preset: LookPreset) -> CommandResult<()> {
+    ctx.image = Some(ctx.image()?.apply_look(preset));
+    Ok(())
+}
+
+fn execute_auto_enhance(ctx: &mut Context, strength: f32) -> CommandResult<()> {
+    ctx.image = Some(ctx.image()?.auto_enhance(strength));
+    Ok(())
+}
+
+fn execute_scan_enhance(ctx: &mut Context, mode: ScanMode) -> CommandResult<()> {
+    ctx.image = Some(ctx.image()?.scan_enhance(mode));
+    Ok(())
+}
+
+/// Space between the cells of a contact sheet and around the labels
+const CONTACT_SHEET_SPACING: u32 = 8;
+
+/// Shorten a label with `...` to fit into `width` pixels
+fn fit_label(label: &str, width: u32) -> String {
+    if text_size(label, 1).0 <= width {
+        return label.to_owned();
+    }
+
+    let mut chars: Vec<char> = label.chars().collect();
+
+    while !chars.is_empty() {
+        chars.pop();
+
+        let shortened = format!("{}...", chars.iter().collect::<String>());
+        if text_size(&shortened, 1).0 <= width {
+            return shortened;
+        }
+    }
+
+    String::new()
+}
+
+fn execute_contact_sheet(ctx: &mut Context, columns: u32, thumb_size: u32) -> CommandResult<()> {
+    if ctx.inputs.is_empty() {
+        return Err(CommandError::MissingImage);
+    }
+
+    let thumb_size = thumb_size.max(1);
+    let label_height = text_size("", 1).1 + CONTACT_SHEET_SPACING;
+
+    let mut cells = Vec::with_capacity(ctx.inputs.len());
+
+    for path in &ctx.inputs {
+        let thumb = Image::open_thumbnail(path, thumb_size)?;
+
+        let mut cell = Image::new_with_color(thumb_size, thumb_size + label_height, Rgb::WHITE);
+        cell.buffer_mut().copy_from(
+            thumb.buffer(),
+            Region::new(0, 0, thumb.width(), thumb.height()),
+            (
+                (thumb_size - thumb.width()) / 2,
+                (thumb_size - thumb.height()) / 2,
+            ),
+        );
+
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let label = fit_label(&name, thumb_size);
+        let label_width = text_size(&label, 1).0;
+
+        cell.draw_text(
+            ((thumb_size - label_width) / 2) as i32,
+            (thumb_size + CONTACT_SHEET_SPACING / 2) as i32,
+            &label,
+            Rgb::BLACK,
+            1,
+        );
+
+        cells.push(cell);
+    }
+
+    let cells: Vec<&Image> = cells.iter().collect();
+    ctx.image = Some(Image::montage(
+        &cells,
+        columns,
+        CONTACT_SHEET_SPACING,
+        Rgb::WHITE,
+    ));
+    Ok(())
+}
+
+fn execute_diff(
+    ctx: &mut Context,
+    other: &Path,
+    mode: DiffMode,
+    threshold: f32,
+) -> CommandResult<()> {
+    let other = Image::open(other)?;
+    ctx.image = Some(ctx.image()?.diff_visualize(&other, mode, threshold));
+    Ok(())
+}
+
+/// Properties of an image printed by `Cmd::Identify`
+pub(crate) struct ImageInfo {
+    pub path: Option<PathBuf>,
+    pub format: Option<Format>,
+    pub width: u32,
+    pub height: u32,
+    pub transparency: bool,
+    pub grayscale: bool,
+    pub average_color: Rgb,
+}
+
+impl ImageInfo {
+    pub fn new(image: &Image, path: Option<&Path>) -> ImageInfo {
+        let format = path.and_then(|path| {
+            let mut file = File::open(path).ok()?;
+            Format::from_reader(&mut file).ok()
+        });
+
+        ImageInfo {
+            path: path.map(|path| path.to_owned()),
+            format,
+            width: image.width(),
+            height: image.height(),
+            transparency: image.has_transparency(),
+            grayscale: image.is_grayscale(),
+            average_color: image.average_color(),
+        }
+    }
+
+    fn fields(&self) -> Vec<(&'static str, Option<String>)> {
+        let c = self.average_color.to_srgb();
+        let channels = if self.transparency { 4 } else { 3 };
+        let color: String = std::iter::once("#".to_owned())
+            .chain(
+                c.data[..channels]
+                    .iter()
+                    .map(|v| format!("{:02x}", (v.clamp(0.0, 1.0) * 255.0).round() as u8)),
+            )
+            .collect();
+
+        vec![
+            ("path", self.path.as_ref().map(|p| p.display().to_string())),
+            ("format", self.format.map(|f| f.name().to_owned())),
+            ("width", Some(self.width.to_string())),
+            ("height", Some(self.height.to_string())),
+            ("transparency", Some(self.transparency.to_string())),
+            ("grayscale", Some(self.grayscale.to_string())),
+            ("average_color", Some(color)),
+        ]
+    }
+
+    /// One `key: value` pair per line
+    pub fn to_lines(&self) -> String {
+        self.fields()
+            .into_iter()
+            .map(|(key, value)| format!("{}: {}", key, value.as_deref().unwrap_or("unknown")))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Single line json object with missing values being `null`
+    pub fn to_json(&self) -> String {
+        let numeric = ["width", "height", "transparency", "grayscale"];
+
+        let fields: Vec<String> = self
+            .fields()
+            .into_iter()
+            .map(|(key, value)| match value {
+                Some(value) if numeric.contains(&key) => format!("\"{}\":{}", key, value),
+                Some(value) => format!("\"{}\":\"{}\"", key, json_escape(&value)),
+                None => format!("\"{}\":null", key),
+            })
+            .collect();
+
+        format!("{{{}}}", fields.join(","))
+    }
+}
+
+pub(crate) fn json_escape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+
+    result
+}
+
+fn execute_identify(ctx: &mut Context, json: bool) -> CommandResult<()> {
+    let path = ctx.inputs.last().cloned();
+    let info = ImageInfo::new(ctx.image()?, path.as_deref());
+
+    if json {
+        println!("{}", info.to_json());
+    } else {
+        println!("{}", info.to_lines());
+    }
+
+    Ok(())
+}
+
+fn execute_fit(ctx: &mut Context, width: u32, height: u32) -> CommandResult<()> {
+    ctx.image = Some(ctx.image()?.fit(width, height, FilterMode::Auto));
+    Ok(())
+}
+
+fn execute_cover(ctx: &mut Context, width: u32, height: u32) -> CommandResult<()> {
+    ctx.image = Some(ctx.image()?.cover(width, height, FilterMode::Auto));
+    Ok(())
+}
+
+fn execute_smart_crop(ctx: &mut Context, width: u32, height: u32) -> CommandResult<()> {
+    ctx.image = Some(ctx.image()?.smart_crop(width, height, FilterMode::Auto));
+    Ok(())
+}
+
+fn execute_unsharp(ctx: &mut Context, radius: u32, factor: f32) -> CommandResult<()> {
+    ctx.image = Some(ctx.image()?.unsharp(radius, factor, None));
+    Ok(())
+}
+
+/// Size of a bin and the height of rendered histogram charts
+const HISTOGRAM_BIN_WIDTH: u32 = 2;
+const HISTOGRAM_HEIGHT: u32 = 200;
+
+fn execute_histogram(ctx: &mut Context, path: &Path) -> CommandResult<()> {
+    let histogram = ctx.image()?.histogram();
+
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+
+    if is_json {
+        std::fs::write(path, histogram.to_json()).map_err(EncodingError::from)?;
+    } else {
+        Image::new_from_buffer(histogram.render(HISTOGRAM_BIN_WIDTH, HISTOGRAM_HEIGHT))
+            .save(path)?;
+    }
+
+    Ok(())
+}
+
+/// Run the comparison requested by `Cmd::Compare` and print the result
+pub(crate) fn execute_comparison(ctx: &mut Context) -> Result<(), CommandFailure> {
+    let Some(comparison) = ctx.comparison.take() else {
+        return Ok(());
+    };
+
+    let reference = comparison.reference.clone();
+
+    compare_with_reference(ctx, comparison).map_err(|error| CommandFailure {
+        error,
+        command: Some("Compare".to_owned()),
+        file: reference,
+    })
+}
+
+fn compare_with_reference(ctx: &mut Context, comparison: Comparison) -> CommandResult<()> {
+    let Some(reference) = comparison.reference else {
+        return Err(CommandError::MissingReference);
+    };
+
+    let metric = comparison.metric;
+    let reference = Image::open(reference)?;
+    let image = ctx.image()?;
+
+    let value = image
+        .compare(&reference, metric)
+        .ok_or(CommandError::SizeMismatch {
+            width: image.width(),
+            height: image.height(),
+            reference_width: reference.width(),
+            reference_height: reference.height(),
+        })?;
+
+    println!("{}: {}", metric.name(), value);
+
+    if let Some(threshold) = comparison.threshold {
+        let similar = if metric.higher_is_better() {
+            value >= threshold
+        } else {
+            value <= threshold
+        };
+
+        if !similar {
+            return Err(CommandError::NotSimilar {
+                metric: metric.name(),
+                value,
+                threshold,
+            });
+        }
+    }
+
+    Ok(())
+}
@@ -1,44 +1,114 @@
+use crate::commands::{json_escape, Cmd};
 use d10::{DecodingError, EncodingError};
-use std::error::Error;
-use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use thiserror::Error;
 
 pub type CommandResult<T> = Result<T, CommandError>;
 
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum CommandError {
+    #[error("Missing image")]
     MissingImage,
-    Decoding(DecodingError),
-    Encoding(EncodingError),
+    #[error("{0}")]
+    InvalidArgument(String),
+    #[error("Unknown preset: {0}")]
+    UnknownPreset(String),
+    #[error("Too deeply nested preset: {0}")]
+    PresetRecursion(String),
+    #[error("Missing reference image for comparison")]
+    MissingReference,
+    #[error("Image size {width}x{height} differs from reference size {reference_width}x{reference_height}")]
+    SizeMismatch {
+        width: u32,
+        height: u32,
+        reference_width: u32,
+        reference_height: u32,
+    },
+    #[error("Images are not similar: {metric} of {value} doesn't reach threshold {threshold}")]
+    NotSimilar {
+        metric: &'static str,
+        value: f32,
+        threshold: f32,
+    },
+    #[error(transparent)]
+    Decoding(#[from] DecodingError),
+    #[error(transparent)]
+    Encoding(#[from] EncodingError),
 }
 
-impl Display for CommandError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+impl CommandError {
+    /// Stable identifier of the kind of error
+    pub fn code(&self) -> &'static str {
         match self {
-            CommandError::MissingImage => write!(f, "Missing image"),
-            CommandError::Decoding(err) => err.fmt(f),
-            CommandError::Encoding(err) => err.fmt(f),
+            CommandError::MissingImage => "missing_image",
+            CommandError::InvalidArgument(_) => "invalid_argument",
+            CommandError::UnknownPreset(_) => "unknown_preset",
+            CommandError::PresetRecursion(_) => "preset_recursion",
+            CommandError::MissingReference => "missing_reference",
+            CommandError::SizeMismatch { .. } => "size_mismatch",
+            CommandError::NotSimilar { .. } => "not_similar",
+            CommandError::Decoding(err) => err.code(),
+            CommandError::Encoding(err) => err.code(),
         }
     }
+
+    /// Returns true if the error is the result of a failed image comparison
+    pub fn is_comparison_failure(&self) -> bool {
+        matches!(
+            self,
+            CommandError::SizeMismatch { .. } | CommandError::NotSimilar { .. }
+        )
+    }
 }
 
-impl Error for CommandError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
-        match self {
-            CommandError::Decoding(err) => Some(err),
-            CommandError::Encoding(err) => Some(err),
-            _ => None,
+/// An error together with the command and file that caused it
+#[derive(Debug, Error)]
+#[error("{error}")]
+pub struct CommandFailure {
+    pub error: CommandError,
+    /// Name of the failed command like `Open` or `Save`
+    pub command: Option<String>,
+    pub file: Option<PathBuf>,
+}
+
+impl CommandFailure {
+    pub fn new(error: CommandError) -> CommandFailure {
+        CommandFailure {
+            error,
+            command: None,
+            file: None,
         }
     }
-}
 
-impl From<DecodingError> for CommandError {
-    fn from(err: DecodingError) -> Self {
-        CommandError::Decoding(err)
+    pub(crate) fn with_command(mut self, cmd: &Cmd) -> CommandFailure {
+        self.command = Some(cmd.name());
+        self.file = cmd.path().map(|path| path.to_owned());
+        self
+    }
+
+    /// Single line json object with the error code, message, command and file
+    ///
+    /// Unknown commands or files are `null`.
+    pub fn to_json(&self) -> String {
+        let string = |value: Option<&str>| match value {
+            Some(value) => format!("\"{}\"", json_escape(value)),
+            None => "null".to_owned(),
+        };
+
+        let file = self.file.as_ref().map(|path| path.to_string_lossy());
+
+        format!(
+            "{{\"error\":\"{}\",\"message\":{},\"command\":{},\"file\":{}}}",
+            self.error.code(),
+            string(Some(&self.error.to_string())),
+            string(self.command.as_deref()),
+            string(file.as_deref()),
+        )
     }
 }
 
-impl From<EncodingError> for CommandError {
-    fn from(err: EncodingError) -> Self {
-        CommandError::Encoding(err)
+impl From<CommandError> for CommandFailure {
+    fn from(error: CommandError) -> Self {
+        CommandFailure::new(error)
     }
 }
@@ -0,0 +1,258 @@
+use d10::Image;
+
+use crate::commands::{execute, Cmd, Context};
+use crate::{CommandError, CommandResult, Log};
+
+/// Default number of commands between two snapshots of an `EditHistory`
+pub const DEFAULT_SNAPSHOT_INTERVAL: usize = 10;
+
+/// Undo and redo of the commands applied to an image, i.e. for interactive editors
+///
+/// Instead of storing the image after every command only a snapshot after every
+/// `snapshot_interval` commands is kept. Undo restores the nearest snapshot and replays the
+/// remaining commands, which trades some time for a lot less memory.
+///
+/// Only pure commands as reported by `Cmd::is_pure()` can be recorded because all others would
+/// repeat their side effects or produce different results on replay.
+pub struct EditHistory {
+    image: Image,
+    /// Applied commands in the order they were applied
+    commands: Vec<Cmd>,
+    /// Undone commands with the most recently undone one at the end
+    undone: Vec<Cmd>,
+    /// Images after the given number of commands, starting with the original image
+    snapshots: Vec<(usize, Image)>,
+    snapshot_interval: usize,
+}
+
+impl EditHistory {
+    pub fn new(image: Image) -> EditHistory {
+        EditHistory::with_snapshot_interval(image, DEFAULT_SNAPSHOT_INTERVAL)
+    }
+
+    /// Create a history taking a snapshot every `interval` commands
+    ///
+    /// An interval of 1 stores every state and makes undo instant.
+    pub fn with_snapshot_interval(image: Image, interval: usize) -> EditHistory {
+        EditHistory {
+            snapshots: vec![(0, image.clone())],
+            image,
+            commands: vec![],
+            undone: vec![],
+            snapshot_interval: interval.max(1),
+        }
+    }
+
+    /// The image with all applied commands
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    pub fn into_image(self) -> Image {
+        self.image
+    }
+
+    /// The image before the first command
+    pub fn original(&self) -> &Image {
+        &self.snapshots[0].1
+    }
+
+    /// All applied commands that weren't undone
+    pub fn commands(&self) -> &[Cmd] {
+        &self.commands
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.commands.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// Apply a command to the image and record it
+    ///
+    /// This discards all undone commands. On failure the image and history stay unchanged.
+    pub fn apply(&mut self, command: Cmd) -> CommandResult<&Image> {
+        if !command.is_pure() {
+            return Err(CommandError::InvalidArgument(format!(
+                "{} can't be recorded in the edit history",
+                command.name()
+            )));
+        }
+
+        self.push(command)?;
+        self.undone.clear();
+
+        Ok(&self.image)
+    }
+
+    /// Revert the last command
+    ///
+    /// Returns false if there is nothing to undo.
+    pub fn undo(&mut self) -> CommandResult<bool> {
+        let Some(command) = self.commands.pop() else {
+            return Ok(false);
+        };
+
+        let len = self.commands.len();
+        self.snapshots.retain(|(pos, _)| *pos <= len);
+
+        match self.replay() {
+            Ok(image) => {
+                self.image = image;
+                self.undone.push(command);
+                Ok(true)
+            }
+            Err(err) => {
+                self.commands.push(command);
+                Err(err)
+            }
+        }
+    }
+
+    /// Apply the last undone command again
+    ///
+    /// Returns false if there is nothing to redo.
+    pub fn redo(&mut self) -> CommandResult<bool> {
+        let Some(command) = self.undone.pop() else {
+            return Ok(false);
+        };
+
+        if let Err(err) = self.push(command.clone()) {
+            self.undone.push(command);
+            return Err(err);
+        }
+
+        Ok(true)
+    }
+
+    /// Forget all recorded commands and make the current image the new original
+    pub fn clear(&mut self) {
+        self.commands.clear();
+        self.undone.clear();
+        self.snapshots = vec![(0, self.image.clone())];
+    }
+
+    fn push(&mut self, command: Cmd) -> CommandResult<()> {
+        let image = run(self.image.clone(), std::slice::from_ref(&command))?;
+
+        self.image = image;
+        self.commands.push(command);
+
+        let len = self.commands.len();
+        if len.is_multiple_of(self.snapshot_interval) {
+            self.snapshots.push((len, self.image.clone()));
+        }
+
+        Ok(())
+    }
+
+    /// Recreate the image from the last snapshot and the commands recorded after it
+    fn replay(&self) -> CommandResult<Image> {
+        let (pos, snapshot) = &self.snapshots[self.snapshots.len() - 1];
+        run(snapshot.clone(), &self.commands[*pos..])
+    }
+}
+
+fn run(image: Image, commands: &[Cmd]) -> CommandResult<Image> {
+    let mut ctx = Context {
+        image: Some(image),
+        ..Context::default()
+    };
+
+    let mut log = Log::new(commands.len());
+    log.disable();
+
+    execute(&mut ctx, commands, &mut log).map_err(|failure| failure.error)?;
+
+    ctx.image.ok_or(CommandError::MissingImage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use d10::Rgb;
+    use std::path::PathBuf;
+
+    fn test_image() -> Image {
+        Image::new_from_raw(2, 1, vec![Rgb::new(0.2, 0.4, 0.6), Rgb::new(0.9, 0.1, 0.5)])
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        for interval in [1, 2, DEFAULT_SNAPSHOT_INTERVAL] {
+            let mut history = EditHistory::with_snapshot_interval(test_image(), interval);
+            assert!(!history.can_undo());
+            assert!(!history.undo().unwrap());
+
+            let mut states = vec![history.image().clone()];
+
+            for cmd in [
+                Cmd::Invert,
+                Cmd::Brightness(0.1),
+                Cmd::Gamma(1.5),
+                Cmd::Fit {
+                    width: 1,
+                    height: 1,
+                },
+                Cmd::Contrast(1.2),
+            ] {
+                history.apply(cmd).unwrap();
+                states.push(history.image().clone());
+            }
+
+            assert_eq!(history.commands().len(), 5);
+            assert_eq!(history.image().width(), 1);
+
+            for expected in states.iter().rev().skip(1) {
+                assert!(history.undo().unwrap());
+                assert_eq!(history.image().data(), expected.data());
+            }
+
+            assert!(!history.can_undo());
+            assert_eq!(history.image().data(), history.original().data());
+
+            assert!(history.redo().unwrap());
+            assert!(history.redo().unwrap());
+            assert_eq!(history.image().data(), states[2].data());
+
+            // A new command discards the undone commands
+            history.apply(Cmd::Saturation(0.5)).unwrap();
+            assert!(!history.can_redo());
+            assert!(!history.redo().unwrap());
+            assert_eq!(history.commands().len(), 3);
+        }
+    }
+
+    #[test]
+    fn test_impure_commands() {
+        let mut history = EditHistory::new(test_image());
+
+        for cmd in [
+            Cmd::Save(PathBuf::from("out.png")),
+            Cmd::RandomNoise(0.5),
+            Cmd::Preset("web-thumbnail".to_owned()),
+        ] {
+            let err = history.apply(cmd).unwrap_err();
+            assert_eq!(err.code(), "invalid_argument");
+        }
+
+        assert!(!history.can_undo());
+        assert_eq!(history.image().data(), test_image().data());
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut history = EditHistory::new(test_image());
+        history.apply(Cmd::Invert).unwrap();
+        history.clear();
+
+        assert!(!history.can_undo());
+        assert_eq!(history.original().data(), history.image().data());
+        assert_eq!(
+            history.into_image().get_pixel(0, 0),
+            &test_image().get_pixel(0, 0).invert()
+        );
+    }
+}
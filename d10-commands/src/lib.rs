@@ -1,9 +1,19 @@
+mod alloc;
+#[cfg(feature = "cache")]
+mod cache;
 mod commands;
 mod errors;
+mod history;
 mod log;
+mod presets;
 mod queue;
 
+pub use alloc::TrackingAllocator;
+#[cfg(feature = "cache")]
+pub use cache::{chain_hash, hash_file, hash_image, DiskCache};
 pub use commands::Cmd;
-pub use errors::{CommandError, CommandResult};
-pub use log::Log;
+pub use errors::{CommandError, CommandFailure, CommandResult};
+pub use history::{EditHistory, DEFAULT_SNAPSHOT_INTERVAL};
+pub use log::{CommandProfile, Log, Report};
+pub use presets::Presets;
 pub use queue::Queue;
@@ -1,9 +1,97 @@
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+
+use crate::alloc;
 use crate::commands::Cmd;
 
+/// Wall time and memory usage of a single executed command
+#[derive(Debug, Clone)]
+pub struct CommandProfile {
+    /// Debug representation of the command
+    pub command: String,
+    pub duration: Duration,
+    /// Size of the pixel data of the current image after the command in bytes
+    pub image_bytes: usize,
+    /// Most heap memory in use while the command ran in bytes, including temporary buffers
+    ///
+    /// Only measured if [`TrackingAllocator`](crate::TrackingAllocator) is the global allocator.
+    pub peak_bytes: Option<usize>,
+}
+
+/// Summary of a profiled run
+///
+/// The image size is always recorded. The peak heap memory including temporary allocations of the
+/// commands is only recorded if [`TrackingAllocator`](crate::TrackingAllocator) is installed as the
+/// global allocator.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub commands: Vec<CommandProfile>,
+}
+
+impl Report {
+    pub fn total_duration(&self) -> Duration {
+        self.commands.iter().map(|c| c.duration).sum()
+    }
+
+    /// Largest image size in bytes measured after any of the commands
+    ///
+    /// Temporary buffers allocated while a command runs aren't included, see `peak_bytes()`.
+    pub fn max_image_bytes(&self) -> usize {
+        self.commands
+            .iter()
+            .map(|c| c.image_bytes)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Most heap memory in use while any of the commands ran or `None` if it wasn't measured
+    pub fn peak_bytes(&self) -> Option<usize> {
+        self.commands.iter().filter_map(|c| c.peak_bytes).max()
+    }
+
+    /// The command that took the most time
+    pub fn slowest(&self) -> Option<&CommandProfile> {
+        self.commands.iter().max_by_key(|c| c.duration)
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, c) in self.commands.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>3} {:>10.3}ms {:>12} bytes {:>12} peak  {}",
+                i + 1,
+                c.duration.as_secs_f64() * 1000.0,
+                c.image_bytes,
+                c.peak_bytes
+                    .map(|peak| peak.to_string())
+                    .unwrap_or_else(|| "-".to_owned()),
+                c.command
+            )?;
+        }
+
+        write!(
+            f,
+            "Total: {:.3}ms, max image size: {} bytes",
+            self.total_duration().as_secs_f64() * 1000.0,
+            self.max_image_bytes()
+        )?;
+
+        if let Some(peak) = self.peak_bytes() {
+            write!(f, ", peak memory: {} bytes", peak)?;
+        }
+
+        Ok(())
+    }
+}
+
 pub struct Log {
     disabled: bool,
     total: usize,
     current: usize,
+    profile: Option<Report>,
+    step_start: Option<Instant>,
 }
 
 impl Log {
@@ -12,6 +100,8 @@ impl Log {
             disabled: false,
             total,
             current: 0,
+            profile: None,
+            step_start: None,
         }
     }
 
@@ -19,10 +109,65 @@ impl Log {
         self.disabled = true;
     }
 
+    /// Record the time and memory usage of all following commands
+    pub fn enable_profiling(&mut self) {
+        if self.profile.is_none() {
+            self.profile = Some(Report::default());
+        }
+    }
+
+    pub fn is_profiling(&self) -> bool {
+        self.profile.is_some()
+    }
+
     pub fn log_command_step(&mut self, cmd: &Cmd) {
         self.current += 1;
         if !self.disabled {
             println!("{}/{}: {:?}", self.current, self.total, cmd);
         }
+
+        if self.profile.is_some() {
+            alloc::reset_peak();
+            self.step_start = Some(Instant::now());
+        }
+    }
+
+    /// Log a command whose result was loaded from the cache
+    #[cfg(feature = "cache")]
+    pub(crate) fn log_cached(&mut self, cmd: &Cmd, image_bytes: usize) {
+        self.current += 1;
+        if !self.disabled {
+            println!("{}/{}: {:?} (cached)", self.current, self.total, cmd);
+        }
+
+        if let Some(report) = &mut self.profile {
+            report.commands.push(CommandProfile {
+                command: format!("{:?}", cmd),
+                duration: Duration::ZERO,
+                image_bytes,
+                peak_bytes: None,
+            });
+        }
+    }
+
+    /// Finish the profile entry of a command started with `log_command_step()`
+    pub fn log_command_done(&mut self, cmd: &Cmd, image_bytes: usize) {
+        if let (Some(report), Some(start)) = (&mut self.profile, self.step_start.take()) {
+            report.commands.push(CommandProfile {
+                command: format!("{:?}", cmd),
+                duration: start.elapsed(),
+                image_bytes,
+                peak_bytes: alloc::peak(),
+            });
+        }
+    }
+
+    /// Report of all profiled commands if profiling was enabled
+    pub fn report(&self) -> Option<&Report> {
+        self.profile.as_ref()
+    }
+
+    pub fn into_report(self) -> Option<Report> {
+        self.profile
     }
 }
@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+
+use crate::{Cmd, CommandError, CommandResult};
+
+/// Maximal depth of presets using other presets
+const MAX_PRESET_DEPTH: usize = 16;
+
+/// Named lists of commands that can be run with `Cmd::Preset`
+#[derive(Debug, Clone)]
+pub struct Presets {
+    presets: HashMap<String, Vec<Cmd>>,
+}
+
+impl Presets {
+    /// Create a registry without any presets
+    pub fn empty() -> Presets {
+        Presets {
+            presets: HashMap::new(),
+        }
+    }
+
+    /// Create a registry with all builtin presets
+    ///
+    /// - `web-thumbnail`: Fit into 320x320, sharpen and strip metadata
+    /// - `social-crop`: Crop to the 1200x630 size used by link previews and strip metadata
+    /// - `email-size`: Fit into 1280x1280 and strip metadata
+    /// - `print-ready`: Enhance contrast and sharpen stronger to compensate for ink spread
+    pub fn builtin() -> Presets {
+        Presets::empty()
+            .with(
+                "web-thumbnail",
+                vec![
+                    Cmd::Fit {
+                        width: 320,
+                        height: 320,
+                    },
+                    Cmd::Unsharp {
+                        radius: 1,
+                        factor: 0.5,
+                    },
+                    Cmd::Strip,
+                ],
+            )
+            .with(
+                "social-crop",
+                vec![
+                    Cmd::Cover {
+                        width: 1200,
+                        height: 630,
+                    },
+                    Cmd::Strip,
+                ],
+            )
+            .with(
+                "email-size",
+                vec![
+                    Cmd::Fit {
+                        width: 1280,
+                        height: 1280,
+                    },
+                    Cmd::Strip,
+                ],
+            )
+            .with(
+                "print-ready",
+                vec![
+                    Cmd::AutoEnhance(0.5),
+                    Cmd::Unsharp {
+                        radius: 2,
+                        factor: 1.0,
+                    },
+                ],
+            )
+    }
+
+    /// Add a preset or replace an existing one with the same name
+    pub fn register<S: Into<String>>(&mut self, name: S, commands: Vec<Cmd>) {
+        self.presets.insert(name.into(), commands);
+    }
+
+    pub fn with<S: Into<String>>(mut self, name: S, commands: Vec<Cmd>) -> Presets {
+        self.register(name, commands);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&[Cmd]> {
+        self.presets.get(name).map(|commands| commands.as_slice())
+    }
+
+    /// Names of all presets in alphabetical order
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.presets.keys().map(|name| name.as_str()).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Replace all `Cmd::Preset` with the commands of the preset
+    pub(crate) fn expand(&self, commands: &[Cmd]) -> CommandResult<Vec<Cmd>> {
+        let mut result = Vec::with_capacity(commands.len());
+        self.expand_into(commands, &mut result, 0)?;
+        Ok(result)
+    }
+
+    fn expand_into(
+        &self,
+        commands: &[Cmd],
+        result: &mut Vec<Cmd>,
+        depth: usize,
+    ) -> CommandResult<()> {
+        for cmd in commands {
+            match cmd {
+                Cmd::Preset(name) => {
+                    let preset = self
+                        .get(name)
+                        .ok_or_else(|| CommandError::UnknownPreset(name.clone()))?;
+
+                    if depth >= MAX_PRESET_DEPTH {
+                        return Err(CommandError::PresetRecursion(name.clone()));
+                    }
+
+                    self.expand_into(preset, result, depth + 1)?;
+                }
+                cmd => result.push(cmd.clone()),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Presets {
+    fn default() -> Self {
+        Presets::builtin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin() {
+        let presets = Presets::builtin();
+
+        assert_eq!(
+            presets.names(),
+            vec!["email-size", "print-ready", "social-crop", "web-thumbnail"]
+        );
+        assert!(presets.get("web-thumbnail").is_some());
+        assert!(Presets::empty().get("web-thumbnail").is_none());
+    }
+
+    #[test]
+    fn test_expand() {
+        let presets = Presets::empty()
+            .with("inner", vec![Cmd::Invert, Cmd::Strip])
+            .with(
+                "outer",
+                vec![Cmd::Preset("inner".to_owned()), Cmd::Gamma(2.0)],
+            );
+
+        let commands = presets
+            .expand(&[Cmd::Silent, Cmd::Preset("outer".to_owned())])
+            .unwrap();
+
+        assert!(matches!(
+            commands.as_slice(),
+            [Cmd::Silent, Cmd::Invert, Cmd::Strip, Cmd::Gamma(_)]
+        ));
+
+        let err = presets
+            .expand(&[Cmd::Preset("missing".to_owned())])
+            .unwrap_err();
+        assert_eq!(err.code(), "unknown_preset");
+    }
+
+    #[test]
+    fn test_expand_recursion() {
+        let presets = Presets::empty().with("loop", vec![Cmd::Preset("loop".to_owned())]);
+
+        let err = presets
+            .expand(&[Cmd::Preset("loop".to_owned())])
+            .unwrap_err();
+        assert_eq!(err.code(), "preset_recursion");
+    }
+}
@@ -1,31 +1,99 @@
-use crate::commands::{execute, Cmd, Context};
-use crate::{CommandResult, Log};
-use d10::{FilterMode, Intensity};
+use crate::commands::{execute, execute_comparison, Cmd, Context};
+#[cfg(feature = "cache")]
+use crate::DiskCache;
+use crate::{CommandError, CommandFailure, CommandResult, Log, Presets, Report};
+use d10::ops::{BorderFill, BorderStyle, DiffMode, HalftoneShape, LookPreset, Metric, ScanMode};
+use d10::{FilterMode, Image, Intensity, Rgb};
 use std::path::PathBuf;
 
 pub struct Queue {
     pub(crate) commands: Vec<Cmd>,
+    presets: Presets,
+    #[cfg(feature = "cache")]
+    cache: Option<DiskCache>,
 }
 
 impl Queue {
     pub fn new() -> Queue {
-        Queue { commands: vec![] }
+        Queue {
+            commands: vec![],
+            presets: Presets::builtin(),
+            #[cfg(feature = "cache")]
+            cache: None,
+        }
     }
 
     pub fn run(&self) -> CommandResult<()> {
-        let mut ctx = Context { image: None };
+        self.run_detailed().map_err(|failure| failure.error)
+    }
+
+    /// Run the queue and report the failed command and file together with the error
+    pub fn run_detailed(&self) -> Result<(), CommandFailure> {
+        let log = self.execute(false)?;
+
+        if let Some(report) = log.report() {
+            println!("{}", report);
+        }
+
+        Ok(())
+    }
+
+    /// Run the queue and return the time and memory usage of every command
+    pub fn run_with_report(&self) -> CommandResult<Report> {
+        let log = self.execute(true).map_err(|failure| failure.error)?;
+        Ok(log.into_report().unwrap_or_default())
+    }
+
+    fn context(&self, image: Option<Image>) -> Context {
+        Context {
+            image,
+            #[cfg(feature = "cache")]
+            cache: self.cache.clone(),
+            ..Context::default()
+        }
+    }
 
-        let total = self
-            .commands
-            .iter()
-            .filter(|cmd| !cmd.ignore_in_log())
-            .count();
+    fn execute(&self, profile: bool) -> Result<Log, CommandFailure> {
+        let mut ctx = self.context(None);
+        let commands = self.expand_presets()?;
+
+        let total = commands.iter().filter(|cmd| !cmd.ignore_in_log()).count();
 
         let mut log = Log::new(total);
+        if profile {
+            log.enable_profiling();
+        }
 
-        execute(&mut ctx, &self.commands, &mut log)?;
+        execute(&mut ctx, &commands, &mut log)?;
+        execute_comparison(&mut ctx)?;
 
-        Ok(())
+        Ok(log)
+    }
+
+    fn expand_presets(&self) -> Result<Vec<Cmd>, CommandFailure> {
+        self.presets
+            .expand(&self.commands)
+            .map_err(|error| CommandFailure {
+                error,
+                command: Some("Preset".to_owned()),
+                file: None,
+            })
+    }
+
+    /// Run the commands on an already opened image and return the resulting image
+    ///
+    /// Nothing is logged. This allows to use a queue in `d10::batch::process()`.
+    pub fn apply(&self, image: Image) -> CommandResult<Image> {
+        let mut ctx = self.context(Some(image));
+
+        let commands = self.expand_presets().map_err(|failure| failure.error)?;
+
+        let mut log = Log::new(commands.len());
+        log.disable();
+
+        execute(&mut ctx, &commands, &mut log).map_err(|failure| failure.error)?;
+
+        ctx.image.ok_or(CommandError::MissingImage)
     }
 
     pub fn is_empty(&self) -> bool {
@@ -36,6 +104,30 @@ impl Queue {
         self.commands.len()
     }
 
+    /// Presets available to `Cmd::Preset` with the builtin presets being registered by default
+    pub fn presets(&self) -> &Presets {
+        &self.presets
+    }
+
+    pub fn presets_mut(&mut self) -> &mut Presets {
+        &mut self.presets
+    }
+
+    pub fn with_presets(mut self, presets: Presets) -> Self {
+        self.presets = presets;
+        self
+    }
+
+    /// Skip chains of commands whose results are already stored in `cache`
+    ///
+    /// A chain starts with an `Open` or the image passed to `apply()` and ends with the first
+    /// command that can't be cached like `Save`.
+    #[cfg(feature = "cache")]
+    pub fn with_cache(mut self, cache: DiskCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
     pub fn push(&mut self, command: Cmd) {
         self.commands.push(command)
     }
@@ -49,6 +141,11 @@ impl Queue {
         self.with(Cmd::Silent)
     }
 
+    /// Print the time and image size of all following commands after the run
+    pub fn profile(self) -> Self {
+        self.with(Cmd::Profile)
+    }
+
     pub fn open<P: Into<PathBuf>>(self, path: P) -> Self {
         self.with(Cmd::Open(path.into()))
     }
@@ -57,6 +154,16 @@ impl Queue {
         self.with(Cmd::Save(path.into()))
     }
 
+    /// Remove all metadata like exif or icc profiles in all following saves
+    pub fn strip(self) -> Self {
+        self.with(Cmd::Strip)
+    }
+
+    /// Save favicon, png, android and iOS icons into a directory
+    pub fn save_icons<P: Into<PathBuf>>(self, dir: P) -> Self {
+        self.with(Cmd::SaveIcons(dir.into()))
+    }
+
     pub fn to_gray(self, intensity: Intensity) -> Self {
         self.with(Cmd::ToGray(intensity))
     }
@@ -111,6 +218,127 @@ impl Queue {
     pub fn rotate(self, radians: f32, filter: FilterMode) -> Self {
         self.with(Cmd::Rotate { radians, filter })
     }
+
+    pub fn straighten(self, max_angle: f32) -> Self {
+        self.with(Cmd::Straighten(max_angle))
+    }
+
+    pub fn offset(self, dx: i32, dy: i32) -> Self {
+        self.with(Cmd::Offset { dx, dy })
+    }
+
+    pub fn make_seamless(self, overlap: u32) -> Self {
+        self.with(Cmd::MakeSeamless(overlap))
+    }
+
+    pub fn upscale_enhanced(self, factor: f32) -> Self {
+        self.with(Cmd::UpscaleEnhanced(factor))
+    }
+
+    pub fn halftone(self, cell_size: u32, angle: f32, shape: HalftoneShape) -> Self {
+        self.with(Cmd::Halftone {
+            cell_size,
+            angle,
+            shape,
+        })
+    }
+
+    pub fn duotone(self, dark_color: Rgb, light_color: Rgb) -> Self {
+        self.with(Cmd::Duotone {
+            dark_color,
+            light_color,
+        })
+    }
+
+    pub fn border(self, width: u32, fill: BorderFill, style: BorderStyle) -> Self {
+        self.with(Cmd::Border { width, fill, style })
+    }
+
+    pub fn polaroid(self) -> Self {
+        self.with(Cmd::Polaroid)
+    }
+
+    pub fn look(self, preset: LookPreset) -> Self {
+        self.with(Cmd::Look(preset))
+    }
+
+    pub fn auto_enhance(self, strength: f32) -> Self {
+        self.with(Cmd::AutoEnhance(strength))
+    }
+
+    pub fn scan_enhance(self, mode: ScanMode) -> Self {
+        self.with(Cmd::ScanEnhance(mode))
+    }
+
+    /// Replace the current image with a visualization of the differences to another image
+    pub fn diff<P: Into<PathBuf>>(self, other: P, mode: DiffMode, threshold: f32) -> Self {
+        self.with(Cmd::Diff {
+            other: other.into(),
+            mode,
+            threshold,
+        })
+    }
+
+    /// Compare the final image with a reference image after all other commands
+    ///
+    /// The run fails with `CommandError::NotSimilar` if the result is worse than `threshold`.
+    pub fn compare<P: Into<PathBuf>>(
+        self,
+        reference: P,
+        metric: Metric,
+        threshold: Option<f32>,
+    ) -> Self {
+        let queue = self
+            .with(Cmd::Compare(reference.into()))
+            .with(Cmd::Metric(metric));
+
+        match threshold {
+            Some(threshold) => queue.with(Cmd::Threshold(threshold)),
+            None => queue,
+        }
+    }
+
+    /// Print size, format, transparency and average color of the current image
+    pub fn identify(self, json: bool) -> Self {
+        self.with(Cmd::Identify { json })
+    }
+
+    /// Save a histogram chart or the bin counts as json if the path ends with `.json`
+    pub fn histogram<P: Into<PathBuf>>(self, path: P) -> Self {
+        self.with(Cmd::Histogram(path.into()))
+    }
+
+    /// Shrink the image to fit into the given size while keeping the aspect ratio
+    pub fn fit(self, width: u32, height: u32) -> Self {
+        self.with(Cmd::Fit { width, height })
+    }
+
+    /// Scale and crop the image to exactly fill the given size
+    pub fn cover(self, width: u32, height: u32) -> Self {
+        self.with(Cmd::Cover { width, height })
+    }
+
+    /// Crop the most interesting part of the image and scale it to the given size
+    pub fn smart_crop(self, width: u32, height: u32) -> Self {
+        self.with(Cmd::SmartCrop { width, height })
+    }
+
+    pub fn unsharp(self, radius: u32, factor: f32) -> Self {
+        self.with(Cmd::Unsharp { radius, factor })
+    }
+
+    /// Run the commands registered under `name`
+    pub fn preset<S: Into<String>>(self, name: S) -> Self {
+        self.with(Cmd::Preset(name.into()))
+    }
+
+    /// Replace the current image with a labeled contact sheet of all opened images
+    pub fn contact_sheet(self, columns: u32, thumb_size: u32) -> Self {
+        self.with(Cmd::ContactSheet {
+            columns,
+            thumb_size,
+        })
+    }
 }
 
 impl Default for Queue {
@@ -121,7 +349,12 @@ impl Default for Queue {
 
 #[cfg(test)]
 mod tests {
-    use crate::{Cmd, Queue};
+    use d10::batch::{process, BatchOptions};
+    use d10::ops::{BorderFill, BorderStyle, DiffMode, LookPreset, Metric, DEFAULT_DIFF_THRESHOLD};
+    use d10::{Image, Rgb};
+
+    use crate::commands::ImageInfo;
+    use crate::{Cmd, CommandError, CommandFailure, Log, Presets, Queue};
 
     #[test]
     fn test_is_empty() {
@@ -151,6 +384,440 @@ mod tests {
         assert!(matches!(q.commands[0], Cmd::Silent));
     }
 
+    #[test]
+    fn test_apply() {
+        let queue = Queue::new().invert();
+
+        let img = queue
+            .apply(Image::new_with_color(2, 1, Rgb::BLACK))
+            .unwrap();
+        assert_eq!(img.get_pixel(1, 0), &Rgb::WHITE);
+    }
+
+    #[test]
+    fn test_look() {
+        let queue = Queue::new().look(LookPreset::BwRedFilter);
+
+        let img = queue
+            .apply(Image::new_with_color(2, 1, Rgb::GREEN))
+            .unwrap();
+        assert!(img.get_pixel(0, 0).is_grayscale());
+    }
+
+    #[test]
+    fn test_border() {
+        let queue = Queue::new()
+            .border(2, BorderFill::Color(Rgb::RED), BorderStyle::Solid)
+            .polaroid();
+
+        let img = queue
+            .apply(Image::new_with_color(10, 10, Rgb::BLACK))
+            .unwrap();
+        assert_eq!((img.width(), img.height()), (16, 19));
+        assert_eq!(img.get_pixel(1, 1), &Rgb::RED);
+    }
+
+    #[test]
+    fn test_batch_process() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let input = dir.join("input.png");
+        let output = dir.join("output.png");
+
+        Image::new_with_color(3, 2, Rgb::BLACK)
+            .save(&input)
+            .unwrap();
+
+        let queue = Queue::new().invert();
+        let report = process([(&input, &output)], &BatchOptions::new(), |img| {
+            queue.apply(img)
+        });
+
+        assert!(report.is_success());
+
+        let img = Image::open(&output).unwrap();
+
+        assert!(img.get_pixel(2, 1).red() > 0.9);
+    }
+
+    #[test]
+    fn test_compare() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let input = dir.join("input.png");
+        let reference = dir.join("reference.png");
+        let small = dir.join("small.png");
+
+        Image::new_with_color(8, 8, Rgb::BLUE).save(&input).unwrap();
+        Image::new_with_color(8, 8, Rgb::BLUE)
+            .save(&reference)
+            .unwrap();
+        Image::new_with_color(4, 4, Rgb::BLUE).save(&small).unwrap();
+
+        let similar = Queue::new()
+            .silent()
+            .open(&input)
+            .compare(&reference, Metric::Ssim, Some(0.98))
+            .run();
+
+        let inverted = Queue::new()
+            .silent()
+            .open(&input)
+            .invert()
+            .compare(&reference, Metric::Mae, Some(0.1))
+            .run();
+
+        let resized = Queue::new()
+            .silent()
+            .open(&input)
+            .compare(&small, Metric::Psnr, None)
+            .run();
+
+        let missing_reference = Queue::new()
+            .silent()
+            .open(&input)
+            .with(Cmd::Threshold(0.5))
+            .run();
+
+        assert!(similar.is_ok());
+
+        let err = inverted.unwrap_err();
+        assert_eq!(err.code(), "not_similar");
+        assert!(err.is_comparison_failure());
+
+        assert_eq!(resized.unwrap_err().code(), "size_mismatch");
+        assert_eq!(missing_reference.unwrap_err().code(), "missing_reference");
+    }
+
+    #[test]
+    fn test_run_detailed() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing \"file\".png");
+
+        let failure = Queue::new()
+            .silent()
+            .open(&missing)
+            .invert()
+            .run_detailed()
+            .unwrap_err();
+
+        assert_eq!(failure.error.code(), "io");
+        assert_eq!(failure.command.as_deref(), Some("Open"));
+        assert_eq!(failure.file.as_deref(), Some(missing.as_path()));
+
+        let json = failure.to_json();
+        assert!(json.starts_with("{\"error\":\"io\",\"message\":\""));
+        assert!(json.contains(",\"command\":\"Open\","));
+        assert!(json.ends_with("missing \\\"file\\\".png\"}"));
+
+        let failure = Queue::new().silent().invert().run_detailed().unwrap_err();
+        assert_eq!(failure.error.code(), "missing_image");
+        assert_eq!(failure.command.as_deref(), Some("Invert"));
+        assert!(failure
+            .to_json()
+            .ends_with(",\"command\":\"Invert\",\"file\":null}"));
+
+        let failure = Queue::new()
+            .silent()
+            .preset("missing")
+            .run_detailed()
+            .unwrap_err();
+        assert_eq!(failure.error.code(), "unknown_preset");
+        assert_eq!(failure.command.as_deref(), Some("Preset"));
+    }
+
+    #[test]
+    fn test_failure_to_json() {
+        let failure = CommandFailure::new(CommandError::InvalidArgument(
+            "Unknown argument: -x".to_owned(),
+        ));
+
+        assert_eq!(
+            failure.to_json(),
+            "{\"error\":\"invalid_argument\",\"message\":\"Unknown argument: -x\",\"command\":null,\"file\":null}"
+        );
+    }
+
+    #[test]
+    fn test_identify() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let input = dir.join("input \"1\".png");
+        let img = Image::new_with_color(3, 2, Rgb::RED);
+        img.save(&input).unwrap();
+
+        let res = Queue::new().silent().open(&input).identify(true).run();
+        let info = ImageInfo::new(&img, Some(&input));
+
+        assert!(res.is_ok());
+
+        assert_eq!(
+            info.to_lines(),
+            format!(
+                "path: {}\nformat: png\nwidth: 3\nheight: 2\ntransparency: false\n\
+                 grayscale: false\naverage_color: #ff0000",
+                input.display()
+            )
+        );
+
+        let json = info.to_json();
+        assert!(json.contains("input \\\"1\\\".png\""));
+        assert!(json.ends_with(
+            "\"format\":\"png\",\"width\":3,\"height\":2,\"transparency\":false,\
+             \"grayscale\":false,\"average_color\":\"#ff0000\"}"
+        ));
+
+        let mut img = Image::new_with_color(1, 1, Rgb::NONE);
+        img.buffer_mut()
+            .put_pixel(0, 0, Rgb::new_with_alpha(0.0, 0.0, 0.0, 0.5));
+        let info = ImageInfo::new(&img, None);
+
+        assert!(info
+            .to_lines()
+            .starts_with("path: unknown\nformat: unknown"));
+        assert!(info.to_json().starts_with("{\"path\":null,\"format\":null"));
+        assert!(info.to_json().contains("\"average_color\":\"#00000080\""));
+    }
+
+    #[test]
+    fn test_identify_without_image() {
+        let res = Queue::new().silent().identify(false).run();
+        assert_eq!(res.unwrap_err().code(), "missing_image");
+    }
+
+    #[test]
+    fn test_histogram() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let input = dir.join("input.png");
+        let chart = dir.join("chart.png");
+        let json = dir.join("histogram.JSON");
+
+        Image::new_with_color(4, 4, Rgb::BLACK)
+            .save(&input)
+            .unwrap();
+
+        Queue::new()
+            .silent()
+            .open(&input)
+            .histogram(&chart)
+            .histogram(&json)
+            .run()
+            .unwrap();
+
+        let chart = Image::open(&chart).unwrap();
+        let json = std::fs::read_to_string(&json).unwrap();
+
+        assert_eq!((chart.width(), chart.height()), (512, 200));
+        assert!(json.starts_with("{\"bins\":256,\"total\":16,\"red\":[16,0,"));
+    }
+
+    #[test]
+    fn test_preset() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let input = dir.join("input.png");
+        let thumbnail = dir.join("thumbnail.png");
+        let social = dir.join("social.png");
+
+        Image::new_with_color(800, 400, Rgb::RED)
+            .save(&input)
+            .unwrap();
+
+        Queue::new()
+            .silent()
+            .open(&input)
+            .preset("web-thumbnail")
+            .save(&thumbnail)
+            .open(&input)
+            .preset("social-crop")
+            .save(&social)
+            .run()
+            .unwrap();
+
+        let thumbnail = Image::open(&thumbnail).unwrap();
+        let social = Image::open(&social).unwrap();
+
+        assert_eq!((thumbnail.width(), thumbnail.height()), (320, 160));
+        assert_eq!((social.width(), social.height()), (1200, 630));
+    }
+
+    #[test]
+    fn test_custom_preset() {
+        let mut queue = Queue::new().preset("negative");
+        queue.presets_mut().register(
+            "negative",
+            vec![
+                Cmd::Invert,
+                Cmd::Fit {
+                    width: 1,
+                    height: 1,
+                },
+            ],
+        );
+
+        let img = queue
+            .apply(Image::new_with_color(2, 2, Rgb::BLACK))
+            .unwrap();
+
+        assert_eq!((img.width(), img.height()), (1, 1));
+        assert!(img.get_pixel(0, 0).red() > 0.9);
+
+        let queue = Queue::new()
+            .with_presets(Presets::empty())
+            .preset("web-thumbnail");
+        let res = queue.apply(Image::new(1, 1));
+        assert_eq!(res.unwrap_err().code(), "unknown_preset");
+    }
+
+    #[test]
+    fn test_run_with_report() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let input = dir.join("input.png");
+        Image::new_with_color(8, 4, Rgb::RED).save(&input).unwrap();
+
+        let report = Queue::new()
+            .silent()
+            .open(&input)
+            .fit(4, 4)
+            .invert()
+            .run_with_report()
+            .unwrap();
+
+        assert_eq!(report.commands.len(), 3);
+        assert!(report.commands[0].command.starts_with("Open("));
+        assert_eq!(report.commands[0].image_bytes, 8 * 4 * 16);
+        assert_eq!(report.commands[1].image_bytes, 4 * 2 * 16);
+        assert_eq!(report.max_image_bytes(), 8 * 4 * 16);
+        assert_eq!(
+            report.total_duration(),
+            report.commands.iter().map(|c| c.duration).sum()
+        );
+        assert!(report.slowest().is_some());
+        assert_eq!(report.peak_bytes(), None);
+        assert!(report.to_string().contains("max image size: 512 bytes"));
+    }
+
+    #[test]
+    fn test_profile() {
+        let mut log = Log::new(2);
+        log.disable();
+
+        let cmd = Cmd::Invert;
+        log.log_command_step(&cmd);
+        log.log_command_done(&cmd, 10);
+        assert!(log.report().is_none());
+
+        log.enable_profiling();
+        log.log_command_step(&cmd);
+        log.log_command_done(&cmd, 20);
+
+        let report = log.into_report().unwrap();
+        assert_eq!(report.commands.len(), 1);
+        assert_eq!(report.commands[0].command, "Invert");
+        assert_eq!(report.commands[0].image_bytes, 20);
+        assert_eq!(report.commands[0].peak_bytes, None);
+    }
+
+    #[test]
+    fn test_contact_sheet() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let input1 = dir.join("red.png");
+        let input2 = dir.join("blue.png");
+        let output = dir.join("sheet.png");
+
+        Image::new_with_color(40, 20, Rgb::RED)
+            .save(&input1)
+            .unwrap();
+        Image::new_with_color(20, 40, Rgb::BLUE)
+            .save(&input2)
+            .unwrap();
+
+        Queue::new()
+            .silent()
+            .open(&input1)
+            .open(&input2)
+            .contact_sheet(2, 32)
+            .save(&output)
+            .run()
+            .unwrap();
+
+        let sheet = Image::open(&output).unwrap();
+
+        // Two cells with 32px thumbnails and a 15px label area separated by 8px spacing
+        assert_eq!(sheet.width(), 8 + 2 * (32 + 8));
+        assert_eq!(sheet.height(), 8 + 32 + 15 + 8);
+
+        assert!(sheet.get_pixel(8 + 16, 8 + 16).red() > 0.9);
+        assert!(sheet.get_pixel(8 + 40 + 16, 8 + 16).blue() > 0.9);
+        assert!(sheet.get_pixel(8 + 40 + 16, 8 + 16).red() < 0.1);
+        assert!(sheet.get_pixel(8 + 40 + 2, 8 + 16).red() > 0.9);
+
+        // The labels are drawn below the thumbnails
+        assert!((0..32).any(|x| sheet.get_pixel(8 + x, 8 + 32 + 8).red() < 0.5));
+    }
+
+    #[test]
+    fn test_contact_sheet_without_images() {
+        let res = Queue::new().silent().contact_sheet(2, 32).run();
+        assert_eq!(res.unwrap_err().code(), "missing_image");
+    }
+
+    #[test]
+    fn test_diff() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let input = dir.join("input.png");
+        let other = dir.join("other.png");
+        let output = dir.join("diff.png");
+
+        Image::new_with_color(4, 4, Rgb::BLUE).save(&input).unwrap();
+        Image::new_with_color(4, 4, Rgb::RED).save(&other).unwrap();
+
+        Queue::new()
+            .silent()
+            .open(&input)
+            .diff(&other, DiffMode::Heatmap, DEFAULT_DIFF_THRESHOLD)
+            .save(&output)
+            .run()
+            .unwrap();
+
+        let diff = Image::open(&output).unwrap();
+
+        assert_eq!((diff.width(), diff.height()), (4, 4));
+        assert!(diff.get_pixel(2, 2).red() > 0.9);
+        assert!(diff.get_pixel(2, 2).blue() < 0.1);
+    }
+
+    #[test]
+    fn test_strip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let input = dir.join("input.png");
+        let output = dir.join("output.jpg");
+
+        Image::new_with_color(4, 4, Rgb::BLUE).save(&input).unwrap();
+
+        let q = Queue::new().silent().open(&input).strip().save(&output);
+        assert!(matches!(q.commands[2], Cmd::Strip));
+        q.run().unwrap();
+
+        let res = Image::open(&output).unwrap();
+
+        assert_eq!((res.width(), res.height()), (4, 4));
+    }
+
     #[test]
     fn test_with() {
         let q = Queue::new();
@@ -0,0 +1,34 @@
+use d10::{Image, Rgb};
+use d10_commands::{Queue, TrackingAllocator};
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+#[test]
+fn test_peak_bytes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let input = tmp.path().join("input.png");
+    Image::new_with_color(512, 512, Rgb::RED)
+        .save(&input)
+        .unwrap();
+
+    let report = Queue::new()
+        .silent()
+        .open(&input)
+        .fit(64, 64)
+        .run_with_report()
+        .unwrap();
+
+    assert_eq!(report.commands.len(), 2);
+    for command in &report.commands {
+        assert!(command.peak_bytes.unwrap() >= command.image_bytes);
+    }
+
+    // The input of fit() is freed before the image size gets measured and only shows up in the peak
+    let fit = &report.commands[1];
+    assert_eq!(fit.image_bytes, 64 * 64 * 16);
+    assert!(fit.peak_bytes.unwrap() >= 512 * 512 * 16);
+
+    assert!(report.peak_bytes().unwrap() >= 512 * 512 * 16);
+    assert!(report.to_string().contains("peak memory:"));
+}
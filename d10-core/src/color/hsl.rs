@@ -1,5 +1,5 @@
-use std::array::from_fn;
 use super::{format_color, Color, Rgb, EPSILON};
+use std::array::from_fn;
 use std::fmt::Display;
 
 #[derive(Debug, Copy, Clone)]
@@ -22,7 +22,7 @@ impl Hsl {
 
     pub fn new_from_fn<F: Fn(usize) -> f32>(func: F) -> Hsl {
         Hsl {
-            data: from_fn(func)
+            data: from_fn(func),
         }
     }
 
@@ -1,6 +1,6 @@
 use super::{format_color, Color, Rgb, EPSILON};
-use std::fmt::Display;
 use std::array::from_fn;
+use std::fmt::Display;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Hsv {
@@ -22,7 +22,7 @@ impl Hsv {
 
     pub fn new_from_fn<F: Fn(usize) -> f32>(func: F) -> Hsv {
         Hsv {
-            data: from_fn(func)
+            data: from_fn(func),
         }
     }
 
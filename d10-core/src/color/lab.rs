@@ -1,5 +1,5 @@
-use std::array::from_fn;
 use super::{format_color, Color, Rgb, Xyz, EPSILON};
+use std::array::from_fn;
 
 use crate::color::illuminant::D65;
 use crate::color::observer::O2;
@@ -133,7 +133,7 @@ impl<I: Illuminant, O: Observer> Lab<I, O> {
         }
     }
 
-    pub fn new_from_fn<F: Fn(usize) -> f32>(func: F) ->  Lab<I, O> {
+    pub fn new_from_fn<F: Fn(usize) -> f32>(func: F) -> Lab<I, O> {
         Lab {
             data: from_fn(func),
             _phantom: PhantomData,
@@ -293,7 +293,7 @@ impl<I: Illuminant, O: Observer> Lch<I, O> {
         }
     }
 
-    pub fn new_from_fn<F: Fn(usize) -> f32>(func: F) ->  Lch<I, O> {
+    pub fn new_from_fn<F: Fn(usize) -> f32>(func: F) -> Lch<I, O> {
         Lch {
             data: from_fn(func),
             _phantom: PhantomData,
@@ -4,6 +4,7 @@ mod iter;
 mod lab;
 mod rgb;
 mod srgb;
+mod transfer;
 mod xyz;
 mod yuv;
 
@@ -13,6 +14,7 @@ pub use iter::{ColorIter, ColorIterRef};
 pub use lab::{illuminant, observer, DefaultLab, Illuminant, Lab, Lch, Observer};
 pub use rgb::{Intensity, Rgb};
 pub use srgb::{gamma_to_linear, linear_to_gamma, Srgb};
+pub use transfer::TransferFunction;
 pub use xyz::Xyz;
 pub use yuv::Yuv;
 
@@ -201,6 +203,24 @@ pub trait Color:
         (1.0 - self.alpha()).abs() > EPSILON
     }
 
+    /// Relative luminance as defined by WCAG 2 from 0 for black to 1 for white
+    ///
+    /// The alpha channel is ignored.
+    fn relative_luminance(&self) -> f32 {
+        let rgb = self.to_rgb();
+        clamp(rgb.data[0]) * 0.2126 + clamp(rgb.data[1]) * 0.7152 + clamp(rgb.data[2]) * 0.0722
+    }
+
+    /// WCAG 2 contrast ratio from 1 for identical luminance to 21 for black on white
+    ///
+    /// WCAG AA requires at least 4.5 for normal and 3 for large text.
+    fn contrast_ratio<C: Color>(&self, other: &C) -> f32 {
+        let l1 = self.relative_luminance();
+        let l2 = other.relative_luminance();
+
+        (l1.max(l2) + 0.05) / (l1.min(l2) + 0.05)
+    }
+
     /// Map all color channels and return a new color with the same alpha value
     fn map_color_channels<F: FnMut(f32) -> f32>(&self, mut func: F) -> Self {
         self.try_map_color_channels::<(), _>(|f| Ok(func(f)))
@@ -291,7 +311,6 @@ color_from!(Yuv, Lch, to_lch);
 color_from!(Xyz, Lch, to_lch);
 color_from!(Lab, Lch, to_lch);
 
-
 // A generic implementation to format a color as a CSS alike string used to implement the Display trait
 //
 // TODO: Improve performance by directly writing parts to the formatter
@@ -130,7 +130,12 @@ impl Rgb {
 
     pub fn invert(&self) -> Rgb {
         Rgb {
-            data: [1.0 - self.data[0], 1.0 - self.data[1], 1.0 - self.data[2], self.alpha()],
+            data: [
+                1.0 - self.data[0],
+                1.0 - self.data[1],
+                1.0 - self.data[2],
+                self.alpha(),
+            ],
         }
     }
 
@@ -317,6 +322,15 @@ impl Rgb {
         }
     }
 
+    /// Black or white, whichever has the higher contrast ratio to the background
+    pub fn best_text_color(background: &Rgb) -> Rgb {
+        if Rgb::BLACK.contrast_ratio(background) >= Rgb::WHITE.contrast_ratio(background) {
+            Rgb::BLACK
+        } else {
+            Rgb::WHITE
+        }
+    }
+
     pub fn max(&self) -> f32 {
         self.data[0..=2].iter().cloned().fold(0.0, f32::max)
     }
@@ -433,7 +447,7 @@ impl Display for Rgb {
 #[cfg(test)]
 mod tests {
     use super::Rgb;
-    use crate::color::{Color, Intensity};
+    use crate::color::{Color, Intensity, Srgb, EPSILON};
     use std::str::FromStr;
 
     #[test]
@@ -589,6 +603,28 @@ mod tests {
         assert_eq!(res.enum_type, "Intensity");
     }
 
+    #[test]
+    fn test_contrast_ratio() {
+        assert_eq!(Rgb::BLACK.relative_luminance(), 0.0);
+        assert!((Rgb::WHITE.relative_luminance() - 1.0).abs() < EPSILON);
+        assert!((Rgb::BLACK.contrast_ratio(&Rgb::WHITE) - 21.0).abs() < 1e-4);
+        assert!((Rgb::WHITE.contrast_ratio(&Rgb::BLACK) - 21.0).abs() < 1e-4);
+        assert_eq!(Rgb::RED.contrast_ratio(&Rgb::RED), 1.0);
+
+        // #777777 on white is slightly below the AA threshold of 4.5
+        let v = 0x77 as f32 / 255.0;
+        let ratio = Srgb::new(v, v, v).contrast_ratio(&Rgb::WHITE);
+        assert!((ratio - 4.48).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_best_text_color() {
+        assert_eq!(Rgb::best_text_color(&Rgb::WHITE), Rgb::BLACK);
+        assert_eq!(Rgb::best_text_color(&Rgb::BLACK), Rgb::WHITE);
+        assert_eq!(Rgb::best_text_color(&Rgb::new(0.0, 0.0, 0.5)), Rgb::WHITE);
+        assert_eq!(Rgb::best_text_color(&Rgb::YELLOW), Rgb::BLACK);
+    }
+
     #[test]
     fn test_setters() {
         let mut color = Rgb::new_with_alpha(0.1, 0.3, 0.5, 0.7);
@@ -1,5 +1,5 @@
-use std::array::from_fn;
 use crate::color::{clamp, format_color, Color, Rgb, EPSILON};
+use std::array::from_fn;
 use std::fmt::Display;
 
 #[derive(Debug, Copy, Clone)]
@@ -22,7 +22,7 @@ impl Srgb {
 
     pub fn new_from_fn<F: Fn(usize) -> f32>(func: F) -> Srgb {
         Srgb {
-            data: from_fn(|i| clamp(func(i)))
+            data: from_fn(|i| clamp(func(i))),
         }
     }
 
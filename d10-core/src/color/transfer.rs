@@ -0,0 +1,170 @@
+use crate::color::{gamma_to_linear, linear_to_gamma};
+use crate::errors::ParseEnumError;
+use std::str::FromStr;
+
+/// Luminance in cd/m² of the reference white of HDR content as defined by ITU-R BT.2408
+const HDR_REFERENCE_WHITE: f32 = 203.0;
+
+/// Peak luminance in cd/m² of the PQ curve
+const PQ_PEAK: f32 = 10000.0;
+
+const PQ_M1: f32 = 2610.0 / 16384.0;
+const PQ_M2: f32 = 2523.0 / 4096.0 * 128.0;
+const PQ_C1: f32 = 3424.0 / 4096.0;
+const PQ_C2: f32 = 2413.0 / 4096.0 * 32.0;
+const PQ_C3: f32 = 2392.0 / 4096.0 * 32.0;
+
+const HLG_A: f32 = 0.178_832_77;
+const HLG_B: f32 = 0.284_668_92;
+const HLG_C: f32 = 0.559_910_7;
+
+/// Signal value of the reference white of HLG content as defined by ITU-R BT.2408
+const HLG_REFERENCE_SIGNAL: f32 = 0.75;
+
+/// Curves used to encode linear light values into the values stored in images and videos
+///
+/// The HDR curves `Pq` and `Hlg` are scaled to map their reference white to a linear value of
+/// 1.0, so brighter highlights end up above 1.0.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TransferFunction {
+    /// No encoding at all
+    Linear,
+    /// The sRGB curve used by almost all images
+    Srgb,
+    /// The curve of ITU-R BT.709 used by SDR video
+    Rec709,
+    /// Perceptual quantizer of SMPTE ST 2084 used by HDR10 and Dolby Vision
+    Pq,
+    /// Hybrid log-gamma of ITU-R BT.2100 used by HDR broadcasts
+    Hlg,
+}
+
+impl FromStr for TransferFunction {
+    type Err = ParseEnumError;
+
+    fn from_str(value: &str) -> Result<TransferFunction, Self::Err> {
+        use TransferFunction::*;
+        match value {
+            "linear" => Ok(Linear),
+            "srgb" | "default" => Ok(Srgb),
+            "rec709" => Ok(Rec709),
+            "pq" => Ok(Pq),
+            "hlg" => Ok(Hlg),
+            _ => Err(ParseEnumError::new(value, "TransferFunction")),
+        }
+    }
+}
+
+fn hlg_to_scene_light(value: f32) -> f32 {
+    if value <= 0.5 {
+        value * value / 3.0
+    } else {
+        (((value - HLG_C) / HLG_A).exp() + HLG_B) / 12.0
+    }
+}
+
+fn scene_light_to_hlg(value: f32) -> f32 {
+    if value <= 1.0 / 12.0 {
+        (3.0 * value).sqrt()
+    } else {
+        HLG_A * (12.0 * value - HLG_B).ln() + HLG_C
+    }
+}
+
+impl TransferFunction {
+    /// Convert an encoded value into a linear value
+    pub fn to_linear(&self, value: f32) -> f32 {
+        use TransferFunction::*;
+        match self {
+            Linear => value,
+            Srgb => gamma_to_linear(value),
+            Rec709 => {
+                if value < 0.081 {
+                    value / 4.5
+                } else {
+                    ((value + 0.099) / 1.099).powf(1.0 / 0.45)
+                }
+            }
+            Pq => {
+                let v = value.max(0.0).powf(1.0 / PQ_M2);
+                let y = ((v - PQ_C1).max(0.0) / (PQ_C2 - PQ_C3 * v)).powf(1.0 / PQ_M1);
+
+                y * PQ_PEAK / HDR_REFERENCE_WHITE
+            }
+            Hlg => hlg_to_scene_light(value.max(0.0)) / hlg_to_scene_light(HLG_REFERENCE_SIGNAL),
+        }
+    }
+
+    /// Convert a linear value into an encoded value
+    pub fn from_linear(&self, value: f32) -> f32 {
+        use TransferFunction::*;
+        match self {
+            Linear => value,
+            Srgb => linear_to_gamma(value),
+            Rec709 => {
+                if value < 0.018 {
+                    value * 4.5
+                } else {
+                    1.099 * value.powf(0.45) - 0.099
+                }
+            }
+            Pq => {
+                let y = (value.max(0.0) * HDR_REFERENCE_WHITE / PQ_PEAK).powf(PQ_M1);
+
+                ((PQ_C1 + PQ_C2 * y) / (1.0 + PQ_C3 * y)).powf(PQ_M2)
+            }
+            Hlg => scene_light_to_hlg(value.max(0.0) * hlg_to_scene_light(HLG_REFERENCE_SIGNAL)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [TransferFunction; 5] = [
+        TransferFunction::Linear,
+        TransferFunction::Srgb,
+        TransferFunction::Rec709,
+        TransferFunction::Pq,
+        TransferFunction::Hlg,
+    ];
+
+    #[test]
+    fn test_roundtrip() {
+        for transfer in ALL {
+            for i in 0..=20 {
+                let value = i as f32 / 20.0;
+                let result = transfer.from_linear(transfer.to_linear(value));
+
+                assert!((value - result).abs() < 0.001, "{:?} {}", transfer, value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_reference_points() {
+        assert_eq!(TransferFunction::Srgb.to_linear(0.5), gamma_to_linear(0.5));
+        assert!((TransferFunction::Rec709.to_linear(1.0) - 1.0).abs() < 0.0001);
+
+        // 203 cd/m² are encoded as 58% in PQ
+        assert!((TransferFunction::Pq.to_linear(0.58) - 1.0).abs() < 0.01);
+        assert!((TransferFunction::Pq.to_linear(1.0) - PQ_PEAK / HDR_REFERENCE_WHITE).abs() < 0.1);
+
+        assert!((TransferFunction::Hlg.to_linear(0.75) - 1.0).abs() < 0.0001);
+        assert!(TransferFunction::Hlg.to_linear(1.0) > 3.0);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "default".parse::<TransferFunction>().unwrap(),
+            TransferFunction::Srgb
+        );
+        assert_eq!(
+            "hlg".parse::<TransferFunction>().unwrap(),
+            TransferFunction::Hlg
+        );
+        assert!("foo".parse::<TransferFunction>().is_err());
+    }
+}
@@ -1,5 +1,5 @@
+use crate::color::{apply_matrix_clamped, format_color, Color, Rgb, EPSILON};
 use std::array::from_fn;
-use crate::color::{format_color, Color, Rgb, EPSILON, apply_matrix_clamped};
 use std::fmt::Display;
 
 pub(crate) const RGB_TO_XYZ: [[f32; 3]; 3] = [
@@ -35,7 +35,7 @@ impl Xyz {
 
     pub fn new_from_fn<F: Fn(usize) -> f32>(func: F) -> Xyz {
         Xyz {
-            data: from_fn(func)
+            data: from_fn(func),
         }
     }
 
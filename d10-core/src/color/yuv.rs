@@ -1,5 +1,5 @@
-use std::array::from_fn;
 use super::{apply_matrix, format_color, Color, Rgb, Srgb, EPSILON};
+use std::array::from_fn;
 use std::fmt::Display;
 
 pub(crate) const RGB_TO_YUV: [[f32; 3]; 3] = [
@@ -34,7 +34,7 @@ impl Yuv {
 
     pub fn new_from_fn<F: Fn(usize) -> f32>(func: F) -> Yuv {
         Yuv {
-            data: from_fn(func)
+            data: from_fn(func),
         }
     }
 
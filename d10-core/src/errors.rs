@@ -23,3 +23,29 @@ impl fmt::Display for ParseEnumError {
 }
 
 impl Error for ParseEnumError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BufferError {
+    InvalidSize { width: u32, height: u32 },
+    WrongDataLength { width: u32, height: u32, len: usize },
+}
+
+impl fmt::Display for BufferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BufferError::InvalidSize { width, height } => {
+                write!(f, "Invalid buffer size: {}x{}", width, height)
+            }
+            BufferError::WrongDataLength { width, height, len } => write!(
+                f,
+                "Data has wrong length: {}x{}={} data has {}",
+                width,
+                height,
+                *width as u64 * *height as u64,
+                len
+            ),
+        }
+    }
+}
+
+impl Error for BufferError {}
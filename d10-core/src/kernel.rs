@@ -59,4 +59,184 @@ impl<const N: usize> Kernel<N> {
 
         Self::new(data)
     }
+
+    /// Create a laplacian of gaussian kernel used to detect edges and blobs
+    ///
+    /// The values are shifted to sum up to zero so flat areas result in a value of zero.
+    pub fn new_laplacian_of_gaussian(sigma: f32) -> Kernel<N> {
+        let mut data = [[0.0; N]; N];
+        fill_laplacian_of_gaussian(data.as_flattened_mut(), N, sigma);
+        Self::new(data)
+    }
+
+    /// Create a kernel that blurs along a line with the given angle in degrees
+    pub fn new_motion_blur(angle: f32) -> Kernel<N> {
+        let mut data = [[0.0; N]; N];
+        fill_motion_blur(data.as_flattened_mut(), N, angle);
+        Self::new(data).normalized()
+    }
+
+    /// Sum of all kernel values
+    pub fn sum(&self) -> f32 {
+        self.data.iter().flatten().sum()
+    }
+
+    /// Scale all values to sum up to 1.0
+    ///
+    /// Kernels with a sum of zero (i.e. edge detection) are left unchanged.
+    pub fn normalize(&mut self) {
+        let sum = self.sum();
+
+        if sum.abs() > f32::EPSILON {
+            for v in self.data.iter_mut().flatten() {
+                *v /= sum;
+            }
+        }
+    }
+
+    pub fn normalized(mut self) -> Kernel<N> {
+        self.normalize();
+        self
+    }
+}
+
+impl Kernel<3> {
+    pub const fn new_sobel_x() -> Kernel<3> {
+        Kernel::new([[-1.0, 0.0, 1.0], [-2.0, 0.0, 2.0], [-1.0, 0.0, 1.0]])
+    }
+
+    pub const fn new_sobel_y() -> Kernel<3> {
+        Kernel::new([[1.0, 2.0, 1.0], [0.0, 0.0, 0.0], [-1.0, -2.0, -1.0]])
+    }
+
+    pub const fn new_scharr_x() -> Kernel<3> {
+        Kernel::new([[-3.0, 0.0, 3.0], [-10.0, 0.0, 10.0], [-3.0, 0.0, 3.0]])
+    }
+
+    pub const fn new_scharr_y() -> Kernel<3> {
+        Kernel::new([[3.0, 10.0, 3.0], [0.0, 0.0, 0.0], [-3.0, -10.0, -3.0]])
+    }
+
+    pub const fn new_prewitt_x() -> Kernel<3> {
+        Kernel::new([[-1.0, 0.0, 1.0], [-1.0, 0.0, 1.0], [-1.0, 0.0, 1.0]])
+    }
+
+    pub const fn new_prewitt_y() -> Kernel<3> {
+        Kernel::new([[1.0, 1.0, 1.0], [0.0, 0.0, 0.0], [-1.0, -1.0, -1.0]])
+    }
+
+    pub const fn new_emboss() -> Kernel<3> {
+        Kernel::new([[-2.0, -1.0, 0.0], [-1.0, 1.0, 1.0], [0.0, 1.0, 2.0]])
+    }
+
+    /// Create a sharpening kernel
+    ///
+    /// An amount of 1.0 results in the common kernel with a center of 5.0
+    pub fn new_sharpen(amount: f32) -> Kernel<3> {
+        Kernel::new([
+            [0.0, -amount, 0.0],
+            [-amount, 1.0 + 4.0 * amount, -amount],
+            [0.0, -amount, 0.0],
+        ])
+    }
+}
+
+/// Fill the row by row stored values of a `size` x `size` kernel with a laplacian of gaussian
+///
+/// Shared by `Kernel` and `KernelDyn` to keep both in sync.
+pub(crate) fn fill_laplacian_of_gaussian(data: &mut [f32], size: usize, sigma: f32) {
+    let offset = (size / 2) as f32;
+    let s = 2.0 * sigma * sigma;
+
+    for (i, v) in data.iter_mut().enumerate() {
+        let dx = (i % size) as f32 - offset;
+        let dy = (i / size) as f32 - offset;
+        let r2 = dx * dx + dy * dy;
+
+        *v = -(1.0 - r2 / s) * (-r2 / s).exp() / (PI * sigma.powi(4));
+    }
+
+    let mean = data.iter().sum::<f32>() / data.len() as f32;
+
+    for v in data {
+        *v -= mean;
+    }
+}
+
+/// Fill the row by row stored values of a `size` x `size` kernel with a line through the center
+///
+/// The values are not normalized.
+pub(crate) fn fill_motion_blur(data: &mut [f32], size: usize, angle: f32) {
+    let offset = (size / 2) as f32;
+    let (sin, cos) = angle.to_radians().sin_cos();
+
+    for (i, v) in data.iter_mut().enumerate() {
+        let dx = (i % size) as f32 - offset;
+        let dy = (i / size) as f32 - offset;
+
+        // Distance to the line through the center
+        let distance = (dy * cos - dx * sin).abs();
+        let length = (dx * cos + dy * sin).abs();
+
+        *v = if length <= offset + 0.5 {
+            (1.0 - distance).max(0.0)
+        } else {
+            0.0
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let kernel = Kernel::<3>::new([[1.0; 3]; 3]).normalized();
+        assert!((kernel.sum() - 1.0).abs() < 1e-6);
+        assert!((kernel.data[1][1] - 1.0 / 9.0).abs() < 1e-6);
+
+        // Zero sum kernels can't be normalized
+        let kernel = Kernel::<3>::new_sobel_x().normalized();
+        assert_eq!(kernel.data[1][2], 2.0);
+    }
+
+    #[test]
+    fn test_sums() {
+        assert!(Kernel::<3>::new_scharr_y().sum().abs() < 1e-6);
+        assert!(Kernel::<3>::new_prewitt_x().sum().abs() < 1e-6);
+        assert!((Kernel::<3>::new_emboss().sum() - 1.0).abs() < 1e-6);
+        assert!((Kernel::<3>::new_sharpen(0.7).sum() - 1.0).abs() < 1e-6);
+        assert!((Kernel::<5>::new_gaussian(1.0).sum() - 1.0).abs() < 1e-6);
+        assert!((Kernel::<7>::new_motion_blur(30.0).sum() - 1.0).abs() < 1e-6);
+        assert!(Kernel::<9>::new_laplacian_of_gaussian(1.4).sum().abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_laplacian_of_gaussian() {
+        let kernel = Kernel::<5>::new_laplacian_of_gaussian(1.0);
+
+        assert!(kernel.data[2][2] < 0.0);
+        assert!(kernel.data[0][0] > 0.0);
+        assert_eq!(kernel.data[0][2], kernel.data[2][0]);
+    }
+
+    #[test]
+    fn test_motion_blur() {
+        let kernel = Kernel::<5>::new_motion_blur(0.0);
+
+        for (y, row) in kernel.data.iter().enumerate() {
+            for v in row {
+                if y == 2 {
+                    assert!((v - 0.2).abs() < 1e-6);
+                } else {
+                    assert_eq!(*v, 0.0);
+                }
+            }
+        }
+
+        let kernel = Kernel::<5>::new_motion_blur(90.0);
+        assert!((kernel.data[0][2] - 0.2).abs() < 1e-6);
+        assert!(kernel.data[2][0].abs() < 1e-6);
+    }
 }
@@ -1,5 +1,7 @@
 use std::f32::consts::PI;
 
+use crate::kernel::{fill_laplacian_of_gaussian, fill_motion_blur, Kernel};
+
 pub struct KernelDyn {
     data: Vec<f32>,
     pub width: u32,
@@ -85,4 +87,114 @@ impl KernelDyn {
             height: 3,
         }
     }
+
+    pub fn new_scharr_x() -> KernelDyn {
+        (&Kernel::<3>::new_scharr_x()).into()
+    }
+
+    pub fn new_scharr_y() -> KernelDyn {
+        (&Kernel::<3>::new_scharr_y()).into()
+    }
+
+    pub fn new_prewitt_x() -> KernelDyn {
+        (&Kernel::<3>::new_prewitt_x()).into()
+    }
+
+    pub fn new_prewitt_y() -> KernelDyn {
+        (&Kernel::<3>::new_prewitt_y()).into()
+    }
+
+    pub fn new_emboss() -> KernelDyn {
+        (&Kernel::<3>::new_emboss()).into()
+    }
+
+    /// Create a sharpening kernel
+    ///
+    /// An amount of 1.0 results in the common kernel with a center of 5.0
+    pub fn new_sharpen(amount: f32) -> KernelDyn {
+        (&Kernel::<3>::new_sharpen(amount)).into()
+    }
+
+    /// Create a laplacian of gaussian kernel used to detect edges and blobs
+    ///
+    /// The values are shifted to sum up to zero so flat areas result in a value of zero.
+    pub fn new_laplacian_of_gaussian(size: u32, sigma: f32) -> KernelDyn {
+        let mut data = vec![0.0; (size * size) as usize];
+        fill_laplacian_of_gaussian(&mut data, size as usize, sigma);
+        Self::new(data, size, size)
+    }
+
+    /// Create a kernel that blurs along a line with the given angle in degrees
+    pub fn new_motion_blur(size: u32, angle: f32) -> KernelDyn {
+        let mut data = vec![0.0; (size * size) as usize];
+        fill_motion_blur(&mut data, size as usize, angle);
+        Self::new(data, size, size).normalized()
+    }
+
+    /// Sum of all kernel values
+    pub fn sum(&self) -> f32 {
+        self.data.iter().sum()
+    }
+
+    /// Scale all values to sum up to 1.0
+    ///
+    /// Kernels with a sum of zero (i.e. edge detection) are left unchanged.
+    pub fn normalize(&mut self) {
+        let sum = self.sum();
+
+        if sum.abs() > f32::EPSILON {
+            for v in &mut self.data {
+                *v /= sum;
+            }
+        }
+    }
+
+    pub fn normalized(mut self) -> KernelDyn {
+        self.normalize();
+        self
+    }
+}
+
+impl<const N: usize> From<&Kernel<N>> for KernelDyn {
+    fn from(kernel: &Kernel<N>) -> Self {
+        KernelDyn::new(
+            kernel.data.iter().flatten().copied().collect(),
+            N as u32,
+            N as u32,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_kernel() {
+        let kernel = KernelDyn::from(&Kernel::<3>::new_sobel_x());
+        let expected = KernelDyn::new_sobel_x();
+
+        assert_eq!(kernel.width, 3);
+        assert_eq!(kernel.height, 3);
+        assert_eq!(kernel.data, expected.data);
+    }
+
+    #[test]
+    fn test_same_as_kernel() {
+        let kernel = KernelDyn::new_laplacian_of_gaussian(7, 1.2);
+        let expected = KernelDyn::from(&Kernel::<7>::new_laplacian_of_gaussian(1.2));
+
+        for (a, b) in kernel.data.iter().zip(&expected.data) {
+            assert!((a - b).abs() < 1e-6);
+        }
+
+        let kernel = KernelDyn::new_motion_blur(9, 45.0);
+        let expected = KernelDyn::from(&Kernel::<9>::new_motion_blur(45.0));
+
+        for (a, b) in kernel.data.iter().zip(&expected.data) {
+            assert!((a - b).abs() < 1e-6);
+        }
+
+        assert!((kernel.sum() - 1.0).abs() < 1e-6);
+    }
 }
@@ -2,4 +2,7 @@ pub mod color;
 pub mod errors;
 pub mod kernel;
 pub mod kernel_dyn;
+pub mod palette;
 pub mod pixelbuffer;
+pub mod region;
+pub mod tile;
@@ -0,0 +1,98 @@
+use crate::color::Rgb;
+use crate::pixelbuffer::PixelBuffer;
+
+/// Ordered list of colors with optional names
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Palette {
+    /// Name of the palette itself
+    pub name: Option<String>,
+    colors: Vec<Rgb>,
+    names: Vec<Option<String>>,
+}
+
+impl Palette {
+    pub fn new() -> Palette {
+        Palette::default()
+    }
+
+    pub fn from_colors(colors: Vec<Rgb>) -> Palette {
+        let names = vec![None; colors.len()];
+
+        Palette {
+            name: None,
+            colors,
+            names,
+        }
+    }
+
+    /// Create a palette from all pixels of the buffer in row major order
+    pub fn from_buffer(buffer: &PixelBuffer<Rgb>) -> Palette {
+        Palette::from_colors(buffer.data().to_vec())
+    }
+
+    /// Create a buffer with a single row containing all colors
+    pub fn to_buffer(&self) -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_raw(self.colors.len() as u32, 1, self.colors.clone())
+    }
+
+    pub fn push(&mut self, color: Rgb, name: Option<&str>) {
+        self.colors.push(color);
+        self.names.push(name.map(|n| n.to_owned()));
+    }
+
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    pub fn colors(&self) -> &[Rgb] {
+        &self.colors
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Rgb> {
+        self.colors.get(index)
+    }
+
+    /// Name of the color at the given index
+    pub fn color_name(&self, index: usize) -> Option<&str> {
+        self.names.get(index)?.as_deref()
+    }
+
+    /// Iterate over all colors together with their names
+    pub fn iter(&self) -> impl Iterator<Item = (&Rgb, Option<&str>)> {
+        self.colors
+            .iter()
+            .zip(self.names.iter().map(|n| n.as_deref()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette() {
+        let mut palette = Palette::from_colors(vec![Rgb::RED, Rgb::BLUE]);
+        palette.push(Rgb::WHITE, Some("White"));
+
+        assert_eq!(palette.len(), 3);
+        assert_eq!(palette.get(1), Some(&Rgb::BLUE));
+        assert_eq!(palette.color_name(0), None);
+        assert_eq!(palette.color_name(2), Some("White"));
+        assert_eq!(palette.color_name(3), None);
+
+        let entries: Vec<_> = palette.iter().collect();
+        assert_eq!(entries[2], (&Rgb::WHITE, Some("White")));
+
+        let buffer = palette.to_buffer();
+        assert_eq!(buffer.width(), 3);
+        assert_eq!(buffer.height(), 1);
+        assert_eq!(
+            Palette::from_buffer(&buffer).colors(),
+            &[Rgb::RED, Rgb::BLUE, Rgb::WHITE]
+        );
+    }
+}
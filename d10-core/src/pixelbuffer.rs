@@ -1,7 +1,11 @@
 use crate::color::*;
+use crate::errors::BufferError;
 use crate::kernel::Kernel;
 use crate::kernel_dyn::KernelDyn;
+use crate::region::Region;
+use crate::tile::{Tile, TileMut};
 use std::any::type_name;
+use std::borrow::Cow;
 use std::fmt::{Debug, Formatter};
 
 pub const MAX_BUFFER_SIZE: u64 = (i32::MAX as u64) / 2;
@@ -16,9 +20,17 @@ pub fn is_valid_buffer_size(width: u32, height: u32) -> bool {
     }
 }
 
+fn check_size(width: u32, height: u32) -> Result<(), BufferError> {
+    if is_valid_buffer_size(width, height) {
+        Ok(())
+    } else {
+        Err(BufferError::InvalidSize { width, height })
+    }
+}
+
 fn validate_size(width: u32, height: u32) {
-    if !is_valid_buffer_size(width, height) {
-        panic!("Invalid buffer size: {}x{}", width, height)
+    if let Err(err) = check_size(width, height) {
+        panic!("{}", err)
     }
 }
 
@@ -53,25 +65,54 @@ impl<T: Color> PixelBuffer<T> {
         }
     }
 
+    /// Creates a new buffer with the default color or returns an error if the size is invalid
+    pub fn try_new(width: u32, height: u32) -> Result<PixelBuffer<T>, BufferError> {
+        Self::try_new_with_color(width, height, T::default())
+    }
+
+    pub fn try_new_with_color(
+        width: u32,
+        height: u32,
+        color: T,
+    ) -> Result<PixelBuffer<T>, BufferError> {
+        check_size(width, height)?;
+
+        Ok(Self::new_with_color(width, height, color))
+    }
+
+    /// Creates a new buffer from raw data
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size is invalid or the length of `data` doesn't match the size
     pub fn new_from_raw(width: u32, height: u32, data: Vec<T>) -> PixelBuffer<T> {
+        match Self::try_new_from_raw(width, height, data) {
+            Ok(buffer) => buffer,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    pub fn try_new_from_raw(
+        width: u32,
+        height: u32,
+        data: Vec<T>,
+    ) -> Result<PixelBuffer<T>, BufferError> {
         let required_len = width as u64 * height as u64;
 
         if required_len > usize::MAX as u64 || required_len as usize != data.len() {
-            panic!(
-                "Data has wrong length: {}x{}={} data has {}",
+            Err(BufferError::WrongDataLength {
                 width,
                 height,
-                required_len,
-                data.len()
-            )
+                len: data.len(),
+            })
         } else {
-            validate_size(width, height);
+            check_size(width, height)?;
 
-            Self {
+            Ok(Self {
                 width,
                 height,
                 data,
-            }
+            })
         }
     }
 
@@ -151,6 +192,153 @@ impl<T: Color> PixelBuffer<T> {
             .map(move |(i, v)| (i as u32 % width, i as u32 / width, v))
     }
 
+    /// Iterate over all rows of the buffer
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> + '_ {
+        // chunks() panics with a size of 0 so use 1 for empty buffers without any data
+        self.data.chunks(self.width.max(1) as usize)
+    }
+
+    /// Iterate mutably over all rows of the buffer
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [T]> + '_ {
+        self.data.chunks_mut(self.width.max(1) as usize)
+    }
+
+    /// Iterate over tiles of the given size from left to right and top to bottom
+    ///
+    /// Tiles at the right and bottom border are smaller if the buffer size is not a multiple of the tile size.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tile_width` or `tile_height` is zero
+    pub fn tiles(&self, tile_width: u32, tile_height: u32) -> impl Iterator<Item = Tile<'_, T>> {
+        assert!(
+            tile_width > 0 && tile_height > 0,
+            "Invalid tile size: {}x{}",
+            tile_width,
+            tile_height
+        );
+
+        let width = self.width;
+        let height = self.height;
+        let cols = width.div_ceil(tile_width);
+
+        (0..height.div_ceil(tile_height)).flat_map(move |ty| {
+            (0..cols).map(move |tx| {
+                let region = Region::new(
+                    tx * tile_width,
+                    ty * tile_height,
+                    tile_width.min(width - tx * tile_width),
+                    tile_height.min(height - ty * tile_height),
+                );
+
+                let rows = (region.y..region.y + region.height)
+                    .map(|y| {
+                        let offset = (y * width + region.x) as usize;
+                        &self.data[offset..offset + region.width as usize]
+                    })
+                    .collect();
+
+                Tile::new(region, rows)
+            })
+        })
+    }
+
+    /// Iterate mutably over non overlapping tiles of the given size
+    ///
+    /// See `tiles()` for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tile_width` or `tile_height` is zero
+    pub fn tiles_mut(
+        &mut self,
+        tile_width: u32,
+        tile_height: u32,
+    ) -> impl Iterator<Item = TileMut<'_, T>> {
+        assert!(
+            tile_width > 0 && tile_height > 0,
+            "Invalid tile size: {}x{}",
+            tile_width,
+            tile_height
+        );
+
+        let width = self.width;
+        let cols = width.div_ceil(tile_width);
+
+        let mut tiles = Vec::new();
+
+        if self.data.is_empty() {
+            return tiles.into_iter();
+        }
+
+        let band_size = (width * tile_height) as usize;
+
+        for (band_index, band) in self.data.chunks_mut(band_size).enumerate() {
+            let y = band_index as u32 * tile_height;
+            let band_height = band.len() as u32 / width;
+
+            let mut band_tiles: Vec<TileMut<T>> = (0..cols)
+                .map(|tx| {
+                    let x = tx * tile_width;
+                    let region = Region::new(x, y, tile_width.min(width - x), band_height);
+                    TileMut::new(region, Vec::with_capacity(band_height as usize))
+                })
+                .collect();
+
+            for row in band.chunks_mut(width as usize) {
+                let mut rest = row;
+
+                for tile in band_tiles.iter_mut() {
+                    let (head, tail) =
+                        std::mem::take(&mut rest).split_at_mut(tile.width() as usize);
+                    tile.push_row(head);
+                    rest = tail;
+                }
+            }
+
+            tiles.extend(band_tiles);
+        }
+
+        tiles.into_iter()
+    }
+
+    /// Copy the region `src_rect` of `other` into this buffer with its top left corner at `dst_pos`
+    ///
+    /// Parts outside of one of the buffers are ignored.
+    pub fn copy_from(&mut self, other: &PixelBuffer<T>, src_rect: Region, dst_pos: (u32, u32)) {
+        let src_rect = match src_rect.clamp(other.width, other.height) {
+            Some(rect) => rect,
+            None => return,
+        };
+
+        let (dst_x, dst_y) = dst_pos;
+
+        if dst_x >= self.width || dst_y >= self.height {
+            return;
+        }
+
+        let width = src_rect.width.min(self.width - dst_x) as usize;
+        let height = src_rect.height.min(self.height - dst_y);
+
+        for y in 0..height {
+            let src_offset = ((src_rect.y + y) * other.width + src_rect.x) as usize;
+            let dst_offset = ((dst_y + y) * self.width + dst_x) as usize;
+
+            self.data[dst_offset..dst_offset + width]
+                .copy_from_slice(&other.data[src_offset..src_offset + width]);
+        }
+    }
+
+    /// Set all pixels inside of `rect` to the given color
+    pub fn fill_rect(&mut self, rect: Region, color: T) {
+        if let Some(rect) = rect.clamp(self.width, self.height) {
+            for y in rect.y..rect.y + rect.height {
+                let offset = (y * self.width + rect.x) as usize;
+                self.data[offset..offset + rect.width as usize].fill(color);
+            }
+        }
+    }
+
     pub fn mod_colors<F: FnMut(&T) -> T>(&mut self, mut func: F) {
         for pixel in self.data.iter_mut() {
             let new_color = func(pixel);
@@ -298,7 +486,6 @@ impl<T: Color> PixelBuffer<T> {
     }
 
     pub fn get_kernel_dyn(&self, x: i32, y: i32, size: usize) -> Vec<Vec<T>> {
-
         let mut values = vec![vec![T::default(); size]; size];
 
         let offset = size as i32 / 2;
@@ -329,6 +516,66 @@ impl<T: Color> PixelBuffer<T> {
         self.data.iter().any(Color::has_transparency)
     }
 
+    /// Check if any channel value is NaN or infinite
+    pub fn has_non_finite(&self) -> bool {
+        self.data
+            .iter()
+            .any(|c| c.data().iter().any(|v| !v.is_finite()))
+    }
+
+    /// Replace all NaN and infinite channel values
+    ///
+    /// NaN and negative infinity become `0.0` while positive infinity becomes `1.0`.
+    /// Ops that mix neighbor pixels like resizing or blurring would otherwise spread a single
+    /// invalid value over whole regions of the image.
+    pub fn sanitize(&mut self) {
+        for c in self.data.iter_mut() {
+            for v in c.data_mut() {
+                if v.is_nan() {
+                    *v = 0.0;
+                } else if v.is_infinite() {
+                    *v = if *v > 0.0 { 1.0 } else { 0.0 };
+                }
+            }
+        }
+    }
+
+    /// Borrow the buffer if all values are finite or return a copy with `sanitize()` applied
+    pub fn sanitized(&self) -> Cow<'_, PixelBuffer<T>> {
+        if self.has_non_finite() {
+            let mut buffer = self.clone();
+            buffer.sanitize();
+            Cow::Owned(buffer)
+        } else {
+            Cow::Borrowed(self)
+        }
+    }
+
+    /// Largest absolute difference of any channel value between the two buffers
+    ///
+    /// Buffers with different dimensions are infinitely different. Two NaN values are treated as
+    /// equal, while NaN compared to any other value is an infinite difference.
+    pub fn max_channel_difference(&self, other: &PixelBuffer<T>) -> f32 {
+        if self.width != other.width || self.height != other.height {
+            return f32::INFINITY;
+        }
+
+        let mut max = 0.0f32;
+
+        for (c1, c2) in self.data.iter().zip(other.data.iter()) {
+            for (v1, v2) in c1.data().iter().zip(c2.data()) {
+                if v1 == v2 || (v1.is_nan() && v2.is_nan()) {
+                    continue;
+                }
+
+                let diff = (v1 - v2).abs();
+                max = max.max(if diff.is_nan() { f32::INFINITY } else { diff });
+            }
+        }
+
+        max
+    }
+
     pub fn to_rgb(&self) -> PixelBuffer<Rgb> {
         PixelBuffer {
             width: self.width,
@@ -399,6 +646,30 @@ impl PixelBuffer<Rgb> {
         self.data.iter().all(Rgb::is_grayscale)
     }
 
+    pub fn invert_in_place(&mut self) {
+        self.mod_colors(Rgb::invert);
+    }
+
+    pub fn brightness_in_place(&mut self, factor: f32) {
+        self.mod_colors(|c| c.with_brightness(factor));
+    }
+
+    pub fn contrast_in_place(&mut self, factor: f32) {
+        self.mod_colors(|c| c.with_contrast(factor));
+    }
+
+    pub fn brightness_contrast_in_place(&mut self, brightness: f32, contrast: f32) {
+        self.mod_colors(|c| c.with_brightness_contrast(brightness, contrast));
+    }
+
+    pub fn level_in_place(&mut self, black_point: f32, white_point: f32, gamma: f32) {
+        self.mod_colors(|c| c.with_level(black_point, white_point, gamma));
+    }
+
+    pub fn sepia_in_place(&mut self) {
+        self.mod_colors(Rgb::with_sepia);
+    }
+
     pub fn apply_kernel<const N: usize>(&self, kernel: &Kernel<N>) -> PixelBuffer<Rgb> {
         self.map_colors_enumerated(|x, y, _| {
             let buffer_k = self.get_kernel::<N>(x as i32, y as i32);
@@ -449,7 +720,10 @@ impl<C: Color> Debug for PixelBuffer<C> {
 #[cfg(test)]
 mod tests {
     use crate::color::Rgb;
+    use crate::errors::BufferError;
     use crate::pixelbuffer::PixelBuffer;
+    use crate::region::Region;
+    use std::borrow::Cow;
 
     #[test]
     fn new() {
@@ -598,6 +872,84 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_in_place() {
+        let buffer =
+            PixelBuffer::new_from_func(3, 2, |x, y| Rgb::new(x as f32 / 2.0, y as f32, 0.3));
+
+        let check = |func: &dyn Fn(&mut PixelBuffer<Rgb>), map: &dyn Fn(&Rgb) -> Rgb| {
+            let mut result = buffer.clone();
+            func(&mut result);
+            assert_eq!(result.data(), buffer.map_colors(map).data());
+        };
+
+        check(&|b| b.invert_in_place(), &|c| c.invert());
+        check(&|b| b.brightness_in_place(0.2), &|c| c.with_brightness(0.2));
+        check(&|b| b.contrast_in_place(1.3), &|c| c.with_contrast(1.3));
+        check(&|b| b.brightness_contrast_in_place(-0.1, 0.8), &|c| {
+            c.with_brightness_contrast(-0.1, 0.8)
+        });
+        check(&|b| b.level_in_place(0.1, 0.9, 1.2), &|c| {
+            c.with_level(0.1, 0.9, 1.2)
+        });
+        check(&|b| b.sepia_in_place(), &|c| c.with_sepia());
+    }
+
+    #[test]
+    fn test_sanitize() {
+        let mut buffer = PixelBuffer::new_with_color(3, 2, Rgb::new(0.5, 0.5, 0.5));
+        assert!(!buffer.has_non_finite());
+        assert!(matches!(buffer.sanitized(), Cow::Borrowed(_)));
+
+        buffer.put_pixel(
+            0,
+            0,
+            Rgb {
+                data: [f32::NAN, 0.5, 0.5, f32::NAN],
+            },
+        );
+        buffer.put_pixel(
+            1,
+            0,
+            Rgb {
+                data: [f32::INFINITY, f32::NEG_INFINITY, 0.5, 1.0],
+            },
+        );
+        assert!(buffer.has_non_finite());
+
+        let sanitized = buffer.sanitized().into_owned();
+        assert!(matches!(buffer.sanitized(), Cow::Owned(_)));
+        assert!(!sanitized.has_non_finite());
+
+        buffer.sanitize();
+        assert_eq!(buffer.data(), sanitized.data());
+        assert_eq!(
+            buffer.get_pixel(0, 0),
+            &Rgb::new_with_alpha(0.0, 0.5, 0.5, 0.0)
+        );
+        assert_eq!(buffer.get_pixel(1, 0), &Rgb::new(1.0, 0.0, 0.5));
+        assert_eq!(buffer.get_pixel(2, 0), &Rgb::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn test_max_channel_difference() {
+        let buffer1 = PixelBuffer::new_with_color(3, 2, Rgb::new(0.5, 0.5, 0.5));
+        let mut buffer2 = buffer1.clone();
+
+        assert_eq!(buffer1.max_channel_difference(&buffer2), 0.0);
+
+        buffer2.put_pixel(1, 1, Rgb::new(0.5, 0.75, 0.25));
+        assert_eq!(buffer1.max_channel_difference(&buffer2), 0.25);
+        assert_eq!(buffer2.max_channel_difference(&buffer1), 0.25);
+
+        buffer2.data_mut()[0].data[3] = f32::NAN;
+        assert_eq!(buffer1.max_channel_difference(&buffer2), f32::INFINITY);
+        assert_eq!(buffer2.max_channel_difference(&buffer2.clone()), 0.0);
+
+        let buffer3 = PixelBuffer::new_with_color(2, 3, Rgb::new(0.5, 0.5, 0.5));
+        assert_eq!(buffer1.max_channel_difference(&buffer3), f32::INFINITY);
+    }
+
     #[test]
     fn test_is_grayscale() {
         let mut buffer = PixelBuffer::new_with_color(13, 7, Rgb::new(0.5, 0.5, 0.5));
@@ -655,4 +1007,92 @@ mod tests {
 
         assert!(res.is_err());
     }
+
+    #[test]
+    fn try_new() {
+        let buffer: PixelBuffer<Rgb> = PixelBuffer::try_new(13, 7).unwrap();
+        assert_eq!(buffer.width(), 13);
+        assert_eq!(buffer.height(), 7);
+
+        assert_eq!(
+            PixelBuffer::<Rgb>::try_new(u32::MAX, u32::MAX).err(),
+            Some(BufferError::InvalidSize {
+                width: u32::MAX,
+                height: u32::MAX
+            })
+        );
+
+        assert!(PixelBuffer::<Rgb>::try_new(0, 7).is_err());
+        assert!(PixelBuffer::try_new_with_color(0, 0, Rgb::RED).is_ok());
+    }
+
+    #[test]
+    fn try_new_from_raw() {
+        let buffer = PixelBuffer::try_new_from_raw(7, 13, vec![Rgb::BLUE; 7 * 13]).unwrap();
+        assert_eq!(buffer.get_pixel(6, 12), &Rgb::BLUE);
+
+        assert_eq!(
+            PixelBuffer::try_new_from_raw(7, 13, vec![Rgb::BLUE; 7 * 12]).err(),
+            Some(BufferError::WrongDataLength {
+                width: 7,
+                height: 13,
+                len: 7 * 12
+            })
+        );
+    }
+
+    #[test]
+    fn rows() {
+        let mut buffer: PixelBuffer<Rgb> =
+            PixelBuffer::new_from_func(3, 2, |x, y| Rgb::new(x as f32 / 2.0, y as f32, 0.0));
+
+        let rows: Vec<&[Rgb]> = buffer.rows().collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].len(), 3);
+        assert_eq!(rows[1][2], *buffer.get_pixel(2, 1));
+
+        for row in buffer.rows_mut() {
+            row[0] = Rgb::RED;
+        }
+
+        assert_eq!(buffer.get_pixel(0, 1), &Rgb::RED);
+
+        let empty: PixelBuffer<Rgb> = PixelBuffer::new(0, 0);
+        assert_eq!(empty.rows().count(), 0);
+    }
+
+    #[test]
+    fn copy_from() {
+        let src = PixelBuffer::new_with_color(4, 4, Rgb::RED);
+        let mut dst = PixelBuffer::new_with_color(5, 3, Rgb::BLUE);
+
+        dst.copy_from(&src, Region::new(1, 1, 10, 2), (3, 2));
+
+        for (x, y, c) in dst.enumerate() {
+            if x >= 3 && y >= 2 {
+                assert_eq!(c, Rgb::RED);
+            } else {
+                assert_eq!(c, Rgb::BLUE);
+            }
+        }
+
+        dst.copy_from(&src, Region::new(0, 0, 4, 4), (5, 0));
+        dst.copy_from(&src, Region::new(4, 0, 4, 4), (0, 0));
+        assert_eq!(dst.get_pixel(0, 0), &Rgb::BLUE);
+    }
+
+    #[test]
+    fn fill_rect() {
+        let mut buffer = PixelBuffer::new_with_color(5, 3, Rgb::BLUE);
+
+        buffer.fill_rect(Region::new(1, 1, 10, 1), Rgb::GREEN);
+
+        for (x, y, c) in buffer.enumerate() {
+            if x >= 1 && y == 1 {
+                assert_eq!(c, Rgb::GREEN);
+            } else {
+                assert_eq!(c, Rgb::BLUE);
+            }
+        }
+    }
 }
@@ -0,0 +1,65 @@
+/// Rectangular area of an image
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Region {
+        Region {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns the part of the region that lies inside an image of the given size
+    pub fn clamp(&self, width: u32, height: u32) -> Option<Region> {
+        let x = self.x.min(width);
+        let y = self.y.min(height);
+        let right = self.x.saturating_add(self.width).min(width);
+        let bottom = self.y.saturating_add(self.height).min(height);
+
+        if right > x && bottom > y {
+            Some(Region::new(x, y, right - x, bottom - y))
+        } else {
+            None
+        }
+    }
+
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        x >= self.x && y >= self.y && x - self.x < self.width && y - self.y < self.height
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clamp() {
+        assert_eq!(
+            Region::new(10, 20, 100, 100).clamp(50, 40),
+            Some(Region::new(10, 20, 40, 20))
+        );
+        assert_eq!(Region::new(60, 0, 10, 10).clamp(50, 40), None);
+        assert_eq!(
+            Region::new(0, 0, u32::MAX, u32::MAX).clamp(5, 6),
+            Some(Region::new(0, 0, 5, 6))
+        );
+    }
+
+    #[test]
+    fn test_contains() {
+        let region = Region::new(1, 2, 3, 4);
+
+        assert!(region.contains(1, 2));
+        assert!(region.contains(3, 5));
+        assert!(!region.contains(4, 5));
+        assert!(!region.contains(0, 2));
+    }
+}
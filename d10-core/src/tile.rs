@@ -0,0 +1,194 @@
+use crate::color::Color;
+use crate::region::Region;
+
+/// Read only view into a rectangular part of a PixelBuffer
+pub struct Tile<'a, T: Color> {
+    region: Region,
+    rows: Vec<&'a [T]>,
+}
+
+/// Mutable view into a rectangular part of a PixelBuffer
+///
+/// Tiles returned by `PixelBuffer::tiles_mut()` don't overlap and can be processed in parallel.
+pub struct TileMut<'a, T: Color> {
+    region: Region,
+    rows: Vec<&'a mut [T]>,
+}
+
+impl<'a, T: Color> Tile<'a, T> {
+    pub(crate) fn new(region: Region, rows: Vec<&'a [T]>) -> Tile<'a, T> {
+        Tile { region, rows }
+    }
+
+    /// Horizontal position of the tile in the buffer
+    pub fn x(&self) -> u32 {
+        self.region.x
+    }
+
+    /// Vertical position of the tile in the buffer
+    pub fn y(&self) -> u32 {
+        self.region.y
+    }
+
+    pub fn width(&self) -> u32 {
+        self.region.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.region.height
+    }
+
+    /// The area of the buffer covered by this tile
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    /// Get a pixel with coordinates relative to the tile
+    pub fn get_pixel(&self, x: u32, y: u32) -> &T {
+        &self.rows[y as usize][x as usize]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> + '_ {
+        self.rows.iter().map(|row| &row[..])
+    }
+
+    /// Iterate over all pixels with coordinates relative to the tile
+    pub fn enumerate(&self) -> impl Iterator<Item = (u32, u32, T)> + '_ {
+        self.rows.iter().enumerate().flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .map(move |(x, c)| (x as u32, y as u32, *c))
+        })
+    }
+}
+
+impl<'a, T: Color> TileMut<'a, T> {
+    pub(crate) fn new(region: Region, rows: Vec<&'a mut [T]>) -> TileMut<'a, T> {
+        TileMut { region, rows }
+    }
+
+    pub(crate) fn push_row(&mut self, row: &'a mut [T]) {
+        self.rows.push(row);
+    }
+
+    /// Horizontal position of the tile in the buffer
+    pub fn x(&self) -> u32 {
+        self.region.x
+    }
+
+    /// Vertical position of the tile in the buffer
+    pub fn y(&self) -> u32 {
+        self.region.y
+    }
+
+    pub fn width(&self) -> u32 {
+        self.region.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.region.height
+    }
+
+    /// The area of the buffer covered by this tile
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    /// Get a pixel with coordinates relative to the tile
+    pub fn get_pixel(&self, x: u32, y: u32) -> &T {
+        &self.rows[y as usize][x as usize]
+    }
+
+    /// Set a pixel with coordinates relative to the tile
+    pub fn put_pixel(&mut self, x: u32, y: u32, color: T) {
+        self.rows[y as usize][x as usize] = color;
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> + '_ {
+        self.rows.iter().map(|row| &row[..])
+    }
+
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [T]> + use<'_, 'a, T> {
+        self.rows.iter_mut().map(|row| &mut row[..])
+    }
+
+    /// Iterate mutably over all pixels with coordinates relative to the tile
+    pub fn enumerate_mut(&mut self) -> impl Iterator<Item = (u32, u32, &mut T)> + use<'_, 'a, T> {
+        self.rows.iter_mut().enumerate().flat_map(|(y, row)| {
+            row.iter_mut()
+                .enumerate()
+                .map(move |(x, c)| (x as u32, y as u32, c))
+        })
+    }
+
+    pub fn fill(&mut self, color: T) {
+        for row in self.rows.iter_mut() {
+            row.fill(color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::Rgb;
+    use crate::pixelbuffer::PixelBuffer;
+    use crate::region::Region;
+
+    #[test]
+    fn tiles() {
+        let buffer: PixelBuffer<Rgb> =
+            PixelBuffer::new_from_func(5, 3, |x, y| Rgb::new(x as f32 / 4.0, y as f32 / 2.0, 0.0));
+
+        let tiles: Vec<_> = buffer.tiles(2, 2).collect();
+
+        assert_eq!(tiles.len(), 6);
+
+        let regions: Vec<Region> = tiles.iter().map(|t| t.region()).collect();
+        assert_eq!(
+            regions,
+            vec![
+                Region::new(0, 0, 2, 2),
+                Region::new(2, 0, 2, 2),
+                Region::new(4, 0, 1, 2),
+                Region::new(0, 2, 2, 1),
+                Region::new(2, 2, 2, 1),
+                Region::new(4, 2, 1, 1),
+            ]
+        );
+
+        for tile in &tiles {
+            for (x, y, c) in tile.enumerate() {
+                assert_eq!(&c, buffer.get_pixel(tile.x() + x, tile.y() + y));
+            }
+        }
+
+        let pixels: usize = tiles
+            .iter()
+            .map(|t| t.rows().map(|r| r.len()).sum::<usize>())
+            .sum();
+        assert_eq!(pixels, 15);
+    }
+
+    #[test]
+    fn tiles_mut() {
+        let mut buffer: PixelBuffer<Rgb> = PixelBuffer::new(7, 5);
+
+        for mut tile in buffer.tiles_mut(3, 2) {
+            let color = Rgb::new(tile.x() as f32 / 6.0, tile.y() as f32 / 4.0, 1.0);
+            tile.fill(color);
+        }
+
+        for (x, y, c) in buffer.enumerate() {
+            let expected = Rgb::new((x / 3 * 3) as f32 / 6.0, (y / 2 * 2) as f32 / 4.0, 1.0);
+            assert_eq!(c, expected, "Bad color at {}x{}", x, y);
+        }
+    }
+
+    #[test]
+    fn tiles_empty() {
+        let mut buffer: PixelBuffer<Rgb> = PixelBuffer::new(0, 0);
+
+        assert_eq!(buffer.tiles(3, 3).count(), 0);
+        assert_eq!(buffer.tiles_mut(3, 3).count(), 0);
+    }
+}
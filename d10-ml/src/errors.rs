@@ -0,0 +1,31 @@
+use std::error::Error;
+
+use thiserror::Error;
+
+/// Boxed error of the underlying inference library
+pub type BackendError = Box<dyn Error + Send + Sync + 'static>;
+
+#[derive(Debug, Error)]
+pub enum ModelError {
+    #[error("Error running model: {0}")]
+    Backend(#[source] BackendError),
+    #[error("Unsupported tensor shape: {0:?}")]
+    UnsupportedShape(Vec<usize>),
+    #[error("Model output of {width}x{height} is no multiple of the input size {input_width}x{input_height}")]
+    InvalidOutputSize {
+        width: u32,
+        height: u32,
+        input_width: u32,
+        input_height: u32,
+    },
+}
+
+impl ModelError {
+    #[cfg(any(feature = "tract", feature = "onnxruntime"))]
+    pub(crate) fn backend<E>(err: E) -> ModelError
+    where
+        E: Into<BackendError>,
+    {
+        ModelError::Backend(err.into())
+    }
+}
@@ -0,0 +1,42 @@
+//! Hooks to run machine learning models like matting networks or super resolution upscalers
+//! on images
+//!
+//! Models are wrapped by a [`ModelBackend`] that maps an input image to an output image.
+//! Backends for ONNX models are available with the `tract` (pure rust) and `onnxruntime`
+//! features. Large images can be processed in overlapping tiles with [`run_tiled`].
+
+mod errors;
+#[cfg(feature = "onnxruntime")]
+mod onnxruntime;
+mod tensor;
+mod tiling;
+#[cfg(feature = "tract")]
+mod tract;
+
+use d10_core::color::Rgb;
+use d10_core::pixelbuffer::PixelBuffer;
+
+pub use errors::{BackendError, ModelError};
+#[cfg(feature = "onnxruntime")]
+pub use onnxruntime::OnnxRuntimeBackend;
+pub use tensor::{buffer_to_tensor, tensor_to_buffer, Normalization, TensorFormat, TensorLayout};
+pub use tiling::run_tiled;
+#[cfg(feature = "tract")]
+pub use tract::TractBackend;
+
+/// A model that transforms an image into another image
+///
+/// The output can have a different size than the input, i.e. for upscalers, or be a grayscale
+/// mask for segmentation models.
+pub trait ModelBackend {
+    fn run(&self, input: &PixelBuffer<Rgb>) -> Result<PixelBuffer<Rgb>, ModelError>;
+}
+
+impl<F> ModelBackend for F
+where
+    F: Fn(&PixelBuffer<Rgb>) -> Result<PixelBuffer<Rgb>, ModelError>,
+{
+    fn run(&self, input: &PixelBuffer<Rgb>) -> Result<PixelBuffer<Rgb>, ModelError> {
+        self(input)
+    }
+}
@@ -0,0 +1,54 @@
+use std::path::Path;
+use std::sync::Mutex;
+
+use d10_core::color::Rgb;
+use d10_core::pixelbuffer::PixelBuffer;
+use ort::session::Session;
+use ort::value::Tensor;
+
+use crate::{buffer_to_tensor, tensor_to_buffer, ModelBackend, ModelError, TensorFormat};
+
+/// Backend for ONNX models using ONNX Runtime
+///
+/// The ONNX Runtime library gets loaded dynamically, so it must be installed on the system
+/// or its location must be set with the `ORT_DYLIB_PATH` environment variable.
+pub struct OnnxRuntimeBackend {
+    session: Mutex<Session>,
+    format: TensorFormat,
+}
+
+impl OnnxRuntimeBackend {
+    pub fn from_file<P: AsRef<Path>>(path: P, format: TensorFormat) -> Result<Self, ModelError> {
+        let session = Session::builder()
+            .and_then(|builder| builder.commit_from_file(path))
+            .map_err(ModelError::backend)?;
+
+        Ok(OnnxRuntimeBackend {
+            session: Mutex::new(session),
+            format,
+        })
+    }
+}
+
+impl ModelBackend for OnnxRuntimeBackend {
+    fn run(&self, input: &PixelBuffer<Rgb>) -> Result<PixelBuffer<Rgb>, ModelError> {
+        let (data, shape) = buffer_to_tensor(input, self.format.layout, &self.format.input);
+
+        let tensor = Tensor::from_array((shape.map(|v| v as i64).to_vec(), data))
+            .map_err(ModelError::backend)?;
+
+        let mut session = self.session.lock().unwrap_or_else(|err| err.into_inner());
+
+        let outputs = session
+            .run(ort::inputs![tensor])
+            .map_err(ModelError::backend)?;
+
+        let (shape, data) = outputs[0]
+            .try_extract_tensor::<f32>()
+            .map_err(ModelError::backend)?;
+
+        let shape: Vec<usize> = shape.iter().map(|v| *v as usize).collect();
+
+        tensor_to_buffer(data, &shape, self.format.layout, &self.format.output)
+    }
+}
@@ -0,0 +1,220 @@
+use d10_core::color::{Color, Rgb, Srgb};
+use d10_core::pixelbuffer::PixelBuffer;
+
+use crate::ModelError;
+
+/// Order of the dimensions of an image tensor with a batch size of 1
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TensorLayout {
+    /// Batch, channels, height, width as used by most PyTorch based models
+    Nchw,
+    /// Batch, height, width, channels as used by most TensorFlow based models
+    Nhwc,
+}
+
+/// Per channel normalization of the sRGB values between 0.0 and 1.0
+///
+/// The values passed to a model are `(value - mean) / std`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Normalization {
+    pub mean: [f32; 3],
+    pub std: [f32; 3],
+}
+
+impl Normalization {
+    /// Pass the values unchanged
+    pub const NONE: Normalization = Normalization {
+        mean: [0.0, 0.0, 0.0],
+        std: [1.0, 1.0, 1.0],
+    };
+
+    /// Statistics of the ImageNet dataset used by many pretrained models
+    pub const IMAGENET: Normalization = Normalization {
+        mean: [0.485, 0.456, 0.406],
+        std: [0.229, 0.224, 0.225],
+    };
+}
+
+impl Default for Normalization {
+    fn default() -> Normalization {
+        Normalization::NONE
+    }
+}
+
+/// Layout and value ranges of the input and output tensors of a model
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TensorFormat {
+    pub layout: TensorLayout,
+    pub input: Normalization,
+    pub output: Normalization,
+}
+
+impl Default for TensorFormat {
+    fn default() -> TensorFormat {
+        TensorFormat {
+            layout: TensorLayout::Nchw,
+            input: Normalization::NONE,
+            output: Normalization::NONE,
+        }
+    }
+}
+
+/// Convert an image into a tensor with three channels
+///
+/// Returns the data and the shape of the tensor.
+pub fn buffer_to_tensor(
+    buffer: &PixelBuffer<Rgb>,
+    layout: TensorLayout,
+    normalization: &Normalization,
+) -> (Vec<f32>, [usize; 4]) {
+    let width = buffer.width() as usize;
+    let height = buffer.height() as usize;
+
+    let srgb: Vec<Srgb> = buffer.data().iter().map(|c| c.to_srgb()).collect();
+    let value = |c: &Srgb, channel: usize| {
+        (c.data[channel] - normalization.mean[channel]) / normalization.std[channel]
+    };
+
+    match layout {
+        TensorLayout::Nchw => {
+            let data = (0..3)
+                .flat_map(|channel| srgb.iter().map(move |c| value(c, channel)))
+                .collect();
+            (data, [1, 3, height, width])
+        }
+        TensorLayout::Nhwc => {
+            let data = srgb
+                .iter()
+                .flat_map(|c| (0..3).map(move |channel| value(c, channel)))
+                .collect();
+            (data, [1, height, width, 3])
+        }
+    }
+}
+
+/// Convert a tensor with one or three channels into an image
+///
+/// The batch dimension can be omitted. A single channel results in a grayscale image.
+pub fn tensor_to_buffer(
+    data: &[f32],
+    shape: &[usize],
+    layout: TensorLayout,
+    normalization: &Normalization,
+) -> Result<PixelBuffer<Rgb>, ModelError> {
+    let unsupported = || ModelError::UnsupportedShape(shape.to_vec());
+
+    let dims = match shape {
+        [1, a, b, c] | [a, b, c] => [*a, *b, *c],
+        _ => return Err(unsupported()),
+    };
+
+    let (channels, height, width) = match layout {
+        TensorLayout::Nchw => (dims[0], dims[1], dims[2]),
+        TensorLayout::Nhwc => (dims[2], dims[0], dims[1]),
+    };
+
+    if !(channels == 1 || channels == 3) || data.len() != channels * height * width {
+        return Err(unsupported());
+    }
+
+    let size = width * height;
+
+    let color = |i: usize| {
+        let mut values = [0.0; 3];
+
+        for (channel, v) in values.iter_mut().enumerate() {
+            let index = match (layout, channels) {
+                (_, 1) => i,
+                (TensorLayout::Nchw, _) => channel * size + i,
+                (TensorLayout::Nhwc, _) => i * 3 + channel,
+            };
+
+            let norm = if channels == 1 { 0 } else { channel };
+            *v = data[index] * normalization.std[norm] + normalization.mean[norm];
+        }
+
+        Srgb::new(values[0], values[1], values[2]).to_rgb()
+    };
+
+    Ok(PixelBuffer::new_from_raw(
+        width as u32,
+        height as u32,
+        (0..size).map(color).collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_buffer() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(3, 2, |x, y| {
+            Srgb::new(x as f32 / 4.0, y as f32 / 2.0, 0.75).to_rgb()
+        })
+    }
+
+    #[test]
+    fn test_nchw() {
+        let buffer = test_buffer();
+
+        let (data, shape) = buffer_to_tensor(&buffer, TensorLayout::Nchw, &Normalization::NONE);
+        assert_eq!(shape, [1, 3, 2, 3]);
+        assert!((data[1] - 0.25).abs() < 1e-6);
+        assert!((data[6 + 3] - 0.5).abs() < 1e-6);
+        assert!((data[12] - 0.75).abs() < 1e-6);
+
+        let res = tensor_to_buffer(&data, &shape, TensorLayout::Nchw, &Normalization::NONE);
+        for (c1, c2) in res.unwrap().data().iter().zip(buffer.data()) {
+            assert!((c1.red() - c2.red()).abs() < 1e-5);
+            assert!((c1.green() - c2.green()).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_nhwc() {
+        let buffer = test_buffer();
+        let norm = Normalization::IMAGENET;
+
+        let (data, shape) = buffer_to_tensor(&buffer, TensorLayout::Nhwc, &norm);
+        assert_eq!(shape, [1, 2, 3, 3]);
+        assert!((data[3] - (0.25 - 0.485) / 0.229).abs() < 1e-5);
+
+        let res = tensor_to_buffer(&data, &shape[1..], TensorLayout::Nhwc, &norm).unwrap();
+        for (c1, c2) in res.data().iter().zip(buffer.data()) {
+            assert!((c1.blue() - c2.blue()).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_single_channel() {
+        let data = [0.0, 0.5, 1.0, 1.0];
+
+        let res = tensor_to_buffer(
+            &data,
+            &[1, 1, 2, 2],
+            TensorLayout::Nchw,
+            &Normalization::NONE,
+        )
+        .unwrap();
+
+        assert_eq!(res.get_pixel(0, 0), &Rgb::BLACK);
+        assert_eq!(res.get_pixel(1, 1), &Rgb::WHITE);
+        assert_eq!(res.get_pixel(1, 0).red(), res.get_pixel(1, 0).blue());
+    }
+
+    #[test]
+    fn test_unsupported_shape() {
+        let data = [0.0; 8];
+
+        let res = tensor_to_buffer(
+            &data,
+            &[1, 2, 2, 2],
+            TensorLayout::Nchw,
+            &Normalization::NONE,
+        );
+        assert!(matches!(res, Err(ModelError::UnsupportedShape(_))));
+
+        let res = tensor_to_buffer(&data, &[8], TensorLayout::Nchw, &Normalization::NONE);
+        assert!(matches!(res, Err(ModelError::UnsupportedShape(_))));
+    }
+}
@@ -0,0 +1,217 @@
+use d10_core::color::Rgb;
+use d10_core::pixelbuffer::PixelBuffer;
+
+use crate::{ModelBackend, ModelError};
+
+/// Start positions of tiles covering `size` pixels
+fn tile_positions(size: u32, tile_size: u32, overlap: u32) -> Vec<u32> {
+    if size <= tile_size {
+        return vec![0];
+    }
+
+    let step = tile_size - overlap;
+
+    let mut positions: Vec<u32> = (0..)
+        .map(|i| i * step)
+        .take_while(|p| p + tile_size < size)
+        .collect();
+
+    // The last tile ends at the border of the image
+    positions.push(size - tile_size);
+
+    positions
+}
+
+/// Weight of an output pixel that fades in over the overlap at inner tile borders
+fn blend_weight(pos: u32, size: u32, overlap: u32, fade_start: bool, fade_end: bool) -> f32 {
+    if overlap == 0 {
+        return 1.0;
+    }
+
+    let mut weight = 1.0f32;
+
+    if fade_start {
+        weight = weight.min((pos as f32 + 0.5) / overlap as f32);
+    }
+
+    if fade_end {
+        weight = weight.min((size as f32 - pos as f32 - 0.5) / overlap as f32);
+    }
+
+    weight
+}
+
+/// Run a model over an image in overlapping tiles
+///
+/// This limits the memory usage of models on large images and allows to use models that
+/// require a fixed input size. The outputs of the tiles are blended in the overlapping areas to
+/// hide the seams. The output of every tile must have the same integer scale factor relative to
+/// its input, i.e. 4 for a 4x upscaler.
+pub fn run_tiled<B>(
+    backend: &B,
+    buffer: &PixelBuffer<Rgb>,
+    tile_size: u32,
+    overlap: u32,
+) -> Result<PixelBuffer<Rgb>, ModelError>
+where
+    B: ModelBackend + ?Sized,
+{
+    let tile_size = tile_size.max(1);
+
+    if buffer.width() <= tile_size && buffer.height() <= tile_size {
+        return backend.run(buffer);
+    }
+
+    let overlap = overlap.min(tile_size / 2);
+
+    let xs = tile_positions(buffer.width(), tile_size, overlap);
+    let ys = tile_positions(buffer.height(), tile_size, overlap);
+
+    let mut scale = None;
+    let mut sums: Vec<[f32; 4]> = vec![];
+    let mut weights: Vec<f32> = vec![];
+
+    for y in &ys {
+        for x in &xs {
+            let tile_width = tile_size.min(buffer.width());
+            let tile_height = tile_size.min(buffer.height());
+
+            let input = PixelBuffer::new_from_func(tile_width, tile_height, |tx, ty| {
+                *buffer.get_pixel(x + tx, y + ty)
+            });
+
+            let output = backend.run(&input)?;
+
+            let tile_scale = output.width() / tile_width;
+            let invalid = output.width() % tile_width != 0
+                || output.height() != tile_height * tile_scale
+                || tile_scale == 0
+                || scale.is_some_and(|s| s != tile_scale);
+
+            if invalid {
+                return Err(ModelError::InvalidOutputSize {
+                    width: output.width(),
+                    height: output.height(),
+                    input_width: tile_width,
+                    input_height: tile_height,
+                });
+            }
+
+            if scale.is_none() {
+                scale = Some(tile_scale);
+                let len = (buffer.width() * buffer.height() * tile_scale * tile_scale) as usize;
+                sums = vec![[0.0; 4]; len];
+                weights = vec![0.0; len];
+            }
+
+            let result_width = buffer.width() * tile_scale;
+            let scaled_overlap = overlap * tile_scale;
+
+            for (ox, oy, c) in output.enumerate() {
+                let weight = blend_weight(
+                    ox,
+                    output.width(),
+                    scaled_overlap,
+                    *x > 0,
+                    x + tile_width < buffer.width(),
+                ) * blend_weight(
+                    oy,
+                    output.height(),
+                    scaled_overlap,
+                    *y > 0,
+                    y + tile_height < buffer.height(),
+                );
+
+                let i = ((y * tile_scale + oy) * result_width + x * tile_scale + ox) as usize;
+
+                for (s, v) in sums[i].iter_mut().zip(c.data.iter()) {
+                    *s += v * weight;
+                }
+                weights[i] += weight;
+            }
+        }
+    }
+
+    let scale = scale.unwrap_or(1);
+
+    let data = sums
+        .iter()
+        .zip(weights.iter())
+        .map(|(s, w)| Rgb {
+            data: s.map(|v| v / w),
+        })
+        .collect();
+
+    Ok(PixelBuffer::new_from_raw(
+        buffer.width() * scale,
+        buffer.height() * scale,
+        data,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_buffer() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(37, 23, |x, y| {
+            Rgb::new(x as f32 / 37.0, y as f32 / 23.0, ((x * y) % 7) as f32 / 7.0)
+        })
+    }
+
+    fn upscale_2x(input: &PixelBuffer<Rgb>) -> Result<PixelBuffer<Rgb>, ModelError> {
+        Ok(PixelBuffer::new_from_func(
+            input.width() * 2,
+            input.height() * 2,
+            |x, y| *input.get_pixel(x / 2, y / 2),
+        ))
+    }
+
+    fn assert_similar(a: &PixelBuffer<Rgb>, b: &PixelBuffer<Rgb>) {
+        assert_eq!(a.width(), b.width());
+        assert_eq!(a.height(), b.height());
+
+        for (c1, c2) in a.data().iter().zip(b.data()) {
+            for (v1, v2) in c1.data.iter().zip(c2.data.iter()) {
+                assert!((v1 - v2).abs() < 1e-5, "{:?} {:?}", c1, c2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tile_positions() {
+        assert_eq!(tile_positions(10, 16, 4), vec![0]);
+        assert_eq!(tile_positions(16, 16, 4), vec![0]);
+        assert_eq!(tile_positions(30, 16, 4), vec![0, 12, 14]);
+        assert_eq!(tile_positions(28, 16, 4), vec![0, 12]);
+    }
+
+    #[test]
+    fn test_run_tiled() {
+        let buffer = test_buffer();
+
+        let identity = |input: &PixelBuffer<Rgb>| -> Result<PixelBuffer<Rgb>, ModelError> {
+            Ok(input.clone())
+        };
+        let res = run_tiled(&identity, &buffer, 10, 3).unwrap();
+        assert_similar(&res, &buffer);
+
+        let res = run_tiled(&upscale_2x, &buffer, 16, 4).unwrap();
+        assert_similar(&res, &upscale_2x(&buffer).unwrap());
+
+        let res = run_tiled(&upscale_2x, &buffer, 100, 4).unwrap();
+        assert_similar(&res, &upscale_2x(&buffer).unwrap());
+    }
+
+    #[test]
+    fn test_invalid_output() {
+        let buffer = test_buffer();
+
+        let wrong_size = |input: &PixelBuffer<Rgb>| -> Result<PixelBuffer<Rgb>, ModelError> {
+            Ok(PixelBuffer::new(input.width() * 2 + 1, input.height()))
+        };
+
+        let res = run_tiled(&wrong_size, &buffer, 16, 4);
+        assert!(matches!(res, Err(ModelError::InvalidOutputSize { .. })));
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use d10_core::color::Rgb;
+use d10_core::pixelbuffer::PixelBuffer;
+use tract_onnx::prelude::*;
+
+use crate::{buffer_to_tensor, tensor_to_buffer, ModelBackend, ModelError, TensorFormat};
+
+type Plan = TypedRunnableModel<TypedModel>;
+
+/// Backend for ONNX models using the pure rust inference engine tract
+///
+/// The model gets optimized for every input size on its first use, so running it in tiles
+/// of a fixed size with [`run_tiled`](crate::run_tiled) avoids repeated optimizations.
+pub struct TractBackend {
+    model: InferenceModel,
+    format: TensorFormat,
+    plans: Mutex<HashMap<[usize; 4], Arc<Plan>>>,
+}
+
+impl TractBackend {
+    pub fn from_file<P: AsRef<Path>>(path: P, format: TensorFormat) -> Result<Self, ModelError> {
+        let model = tract_onnx::onnx()
+            .model_for_path(path)
+            .map_err(ModelError::backend)?;
+
+        Ok(TractBackend {
+            model,
+            format,
+            plans: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn plan(&self, shape: [usize; 4]) -> Result<Arc<Plan>, ModelError> {
+        let mut plans = self.plans.lock().unwrap_or_else(|err| err.into_inner());
+
+        if let Some(plan) = plans.get(&shape) {
+            return Ok(plan.clone());
+        }
+
+        let fact = InferenceFact::dt_shape(f32::datum_type(), shape);
+
+        let plan = self
+            .model
+            .clone()
+            .with_input_fact(0, fact)
+            .and_then(|model| model.into_optimized())
+            .and_then(|model| model.into_runnable())
+            .map_err(ModelError::backend)?;
+
+        let plan = Arc::new(plan);
+        plans.insert(shape, plan.clone());
+
+        Ok(plan)
+    }
+}
+
+impl ModelBackend for TractBackend {
+    fn run(&self, input: &PixelBuffer<Rgb>) -> Result<PixelBuffer<Rgb>, ModelError> {
+        let (data, shape) = buffer_to_tensor(input, self.format.layout, &self.format.input);
+
+        let tensor = Tensor::from_shape(&shape, &data).map_err(ModelError::backend)?;
+
+        let outputs = self
+            .plan(shape)?
+            .run(tvec!(tensor.into()))
+            .map_err(ModelError::backend)?;
+
+        let output = outputs[0]
+            .to_array_view::<f32>()
+            .map_err(ModelError::backend)?;
+
+        let data: Vec<f32> = output.iter().copied().collect();
+
+        tensor_to_buffer(
+            &data,
+            output.shape(),
+            self.format.layout,
+            &self.format.output,
+        )
+    }
+}
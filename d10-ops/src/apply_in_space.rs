@@ -0,0 +1,224 @@
+use d10_core::color::illuminant::D65;
+use d10_core::color::observer::O2;
+use d10_core::color::{Color, Hsl, Hsv, Rgb, Yuv};
+use d10_core::errors::ParseEnumError;
+use d10_core::pixelbuffer::PixelBuffer;
+use std::str::FromStr;
+
+/// Single channel of a color space an operation can be restricted to
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColorSpaceChannel {
+    /// Linear red channel
+    Red,
+    /// Linear green channel
+    Green,
+    /// Linear blue channel
+    Blue,
+    /// Lightness of the HSL color space
+    HslLightness,
+    /// Value of the HSV color space
+    HsvValue,
+    /// Lightness of the Lab color space
+    LabLightness,
+    /// Luma of the YUV color space
+    YuvLuma,
+}
+
+impl FromStr for ColorSpaceChannel {
+    type Err = ParseEnumError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        use ColorSpaceChannel::*;
+        match value {
+            "red" => Ok(Red),
+            "green" => Ok(Green),
+            "blue" => Ok(Blue),
+            "hsl_lightness" => Ok(HslLightness),
+            "hsv_value" => Ok(HsvValue),
+            "lab_lightness" | "default" => Ok(LabLightness),
+            "yuv_luma" => Ok(YuvLuma),
+            _ => Err(ParseEnumError::new(value, "ColorSpaceChannel")),
+        }
+    }
+}
+
+fn channel_value(c: &Rgb, channel: ColorSpaceChannel) -> f32 {
+    use ColorSpaceChannel::*;
+    match channel {
+        Red => c.red(),
+        Green => c.green(),
+        Blue => c.blue(),
+        HslLightness => c.to_hsl().data[2],
+        HsvValue => c.to_hsv().data[2],
+        LabLightness => c.to_lab::<D65, O2>().l(),
+        YuvLuma => c.to_yuv().data[0],
+    }
+}
+
+fn with_channel_value(c: &Rgb, channel: ColorSpaceChannel, value: f32) -> Rgb {
+    use ColorSpaceChannel::*;
+
+    let value = value.clamp(0.0, 1.0);
+
+    match channel {
+        Red => c.with_red(value),
+        Green => c.with_green(value),
+        Blue => c.with_blue(value),
+        HslLightness => {
+            let mut hsl: Hsl = c.to_hsl();
+            hsl.data[2] = value;
+            hsl.to_rgb()
+        }
+        HsvValue => {
+            let mut hsv: Hsv = c.to_hsv();
+            hsv.data[2] = value;
+            hsv.to_rgb()
+        }
+        LabLightness => c.to_lab::<D65, O2>().with_l(value).to_rgb(),
+        YuvLuma => {
+            let mut yuv: Yuv = c.to_yuv();
+            yuv.data[0] = value;
+            yuv.to_rgb()
+        }
+    }
+}
+
+/// Create a gray image from a single channel
+///
+/// The values of the channel are stored in all three color channels of the result.
+/// The alpha channel is kept.
+pub fn extract_channel(buffer: &PixelBuffer<Rgb>, channel: ColorSpaceChannel) -> PixelBuffer<Rgb> {
+    buffer.map_colors(|c| {
+        let v = channel_value(c, channel);
+        Rgb::new_with_alpha(v, v, v, c.alpha())
+    })
+}
+
+/// Replace a single channel with the values of a gray image
+///
+/// The average of the red, green and blue channel of `gray` is used as the new value, so this
+/// also works if an operation slightly tinted the gray image.
+///
+/// # Panics
+///
+/// If `gray` differs in size from `buffer`.
+pub fn replace_channel(
+    buffer: &PixelBuffer<Rgb>,
+    channel: ColorSpaceChannel,
+    gray: &PixelBuffer<Rgb>,
+) -> PixelBuffer<Rgb> {
+    assert_eq!(
+        (buffer.width(), buffer.height()),
+        (gray.width(), gray.height()),
+        "Size of the channel doesn't match the image"
+    );
+
+    buffer.map_colors_enumerated(|x, y, c| {
+        let g = gray.get_pixel(x, y);
+        let value = (g.red() + g.green() + g.blue()) / 3.0;
+
+        with_channel_value(c, channel, value)
+    })
+}
+
+/// Run an operation on a single channel of the image
+///
+/// The channel gets extracted as a gray image, passed to `op` and the result gets merged back
+/// into the image. This allows to e.g. blur, sharpen or equalize only the lightness of an image
+/// without having a dedicated variant of every operation.
+///
+/// # Panics
+///
+/// If `op` returns an image with a different size.
+pub fn apply_in_space<F>(
+    buffer: &PixelBuffer<Rgb>,
+    channel: ColorSpaceChannel,
+    op: F,
+) -> PixelBuffer<Rgb>
+where
+    F: FnOnce(&PixelBuffer<Rgb>) -> PixelBuffer<Rgb>,
+{
+    let gray = op(&extract_channel(buffer, channel));
+    replace_channel(buffer, channel, &gray)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gaussian_blur;
+
+    fn test_buffer() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(8, 8, |x, y| {
+            Rgb::new(x as f32 / 8.0, y as f32 / 8.0, ((x + y) % 3) as f32 / 3.0)
+        })
+    }
+
+    #[test]
+    fn test_identity() {
+        let buffer = test_buffer();
+
+        for channel in [
+            ColorSpaceChannel::Red,
+            ColorSpaceChannel::HslLightness,
+            ColorSpaceChannel::HsvValue,
+            ColorSpaceChannel::LabLightness,
+            ColorSpaceChannel::YuvLuma,
+        ] {
+            let result = apply_in_space(&buffer, channel, |b| b.clone());
+
+            for (c1, c2) in buffer.data().iter().zip(result.data()) {
+                for i in 0..4 {
+                    assert!((c1.data[i] - c2.data[i]).abs() < 0.001, "{:?}", channel);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_single_channel() {
+        let buffer = test_buffer();
+
+        let result = apply_in_space(&buffer, ColorSpaceChannel::Green, |b| {
+            b.map_colors(|c| c.invert())
+        });
+
+        for (c1, c2) in buffer.data().iter().zip(result.data()) {
+            assert_eq!(c1.red(), c2.red());
+            assert!((1.0 - c1.green() - c2.green()).abs() < 0.0001);
+            assert_eq!(c1.blue(), c2.blue());
+        }
+    }
+
+    #[test]
+    fn test_blur_lightness() {
+        let buffer = test_buffer();
+
+        let result = apply_in_space(&buffer, ColorSpaceChannel::LabLightness, |b| {
+            gaussian_blur(b, 2, None)
+        });
+
+        let lightness = extract_channel(&result, ColorSpaceChannel::LabLightness);
+        let expected = gaussian_blur(
+            &extract_channel(&buffer, ColorSpaceChannel::LabLightness),
+            2,
+            None,
+        );
+
+        for (c1, c2) in lightness.data().iter().zip(expected.data()) {
+            assert!((c1.red() - c2.red()).abs() < 0.02);
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "default".parse::<ColorSpaceChannel>().unwrap(),
+            ColorSpaceChannel::LabLightness
+        );
+        assert_eq!(
+            "yuv_luma".parse::<ColorSpaceChannel>().unwrap(),
+            ColorSpaceChannel::YuvLuma
+        );
+        assert!("foo".parse::<ColorSpaceChannel>().is_err());
+    }
+}
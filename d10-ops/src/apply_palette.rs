@@ -1,29 +1,32 @@
 use d10_core::color::illuminant::D65;
 use d10_core::color::observer::O2;
 use d10_core::color::{Color, Lab, Rgb};
+use d10_core::palette::Palette;
 use d10_core::pixelbuffer::PixelBuffer;
 
-pub fn apply_palette<C: Color>(
-    buffer: &PixelBuffer<Rgb>,
-    palette: &PixelBuffer<C>,
-) -> PixelBuffer<Rgb> {
-    let palette = palette.to_lab();
+fn palette_to_lab(palette: &Palette) -> Vec<(Lab<D65, O2>, Rgb)> {
+    palette.colors().iter().map(|c| (c.to_lab(), *c)).collect()
+}
+
+/// Replace every color with the most similar color of the palette
+pub fn apply_palette(buffer: &PixelBuffer<Rgb>, palette: &Palette) -> PixelBuffer<Rgb> {
+    let palette = palette_to_lab(palette);
     buffer.map_colors(|c| get_color_from_palette(&palette, c))
 }
 
-pub fn apply_palette_in_place<C: Color>(buffer: &mut PixelBuffer<Rgb>, palette: &PixelBuffer<C>) {
-    let palette = palette.to_lab();
+pub fn apply_palette_in_place(buffer: &mut PixelBuffer<Rgb>, palette: &Palette) {
+    let palette = palette_to_lab(palette);
     buffer.mod_colors(|c| get_color_from_palette(&palette, c));
 }
 
-fn get_color_from_palette(palette: &PixelBuffer<Lab<D65, O2>>, c: &Rgb) -> Rgb {
+fn get_color_from_palette(palette: &[(Lab<D65, O2>, Rgb)], c: &Rgb) -> Rgb {
     let mut result = None;
     let mut min_diff = f32::MAX;
 
     let test_c = c.to_lab();
 
-    for pal_c in palette.data() {
-        let diff = get_delta_e(&test_c, pal_c);
+    for (pal_lab, pal_c) in palette {
+        let diff = get_delta_e(&test_c, pal_lab);
 
         if diff < min_diff {
             result = Some(pal_c);
@@ -31,7 +34,7 @@ fn get_color_from_palette(palette: &PixelBuffer<Lab<D65, O2>>, c: &Rgb) -> Rgb {
         }
     }
 
-    result.cloned().unwrap_or_default().to_rgb()
+    result.cloned().unwrap_or_default()
 }
 
 fn get_delta_e(c1: &Lab<D65, O2>, c2: &Lab<D65, O2>) -> f32 {
@@ -41,3 +44,30 @@ fn get_delta_e(c1: &Lab<D65, O2>, c2: &Lab<D65, O2>) -> f32 {
 
     (dl * dl + da * da + db * db).sqrt()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_palette() {
+        let buffer = PixelBuffer::new_from_raw(
+            3,
+            1,
+            vec![
+                Rgb::new(0.9, 0.1, 0.0),
+                Rgb::new(0.1, 0.0, 0.8),
+                Rgb::new(0.8, 0.8, 0.8),
+            ],
+        );
+
+        let palette = Palette::from_colors(vec![Rgb::RED, Rgb::BLUE, Rgb::WHITE]);
+
+        let res = apply_palette(&buffer, &palette);
+        assert_eq!(res.data(), &[Rgb::RED, Rgb::BLUE, Rgb::WHITE]);
+
+        let mut buffer = buffer;
+        apply_palette_in_place(&mut buffer, &Palette::new());
+        assert!(buffer.data().iter().all(|c| *c == Rgb::NONE));
+    }
+}
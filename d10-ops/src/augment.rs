@@ -0,0 +1,337 @@
+use d10_core::color::{Color, Rgb};
+use d10_core::pixelbuffer::PixelBuffer;
+use d10_core::region::Region;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::StandardNormal;
+
+use crate::{crop, flip_horizontal, flip_vertical, gaussian_blur, resize, rotate, FilterMode};
+
+/// A single random transformation of an augmentation pipeline
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Augmentation {
+    /// Crop a random area covering at least `min_scale` of each side and resize it back to the original size
+    RandomCrop { min_scale: f32 },
+    /// Mirror the image horizontally with the given probability
+    FlipHorizontal { probability: f32 },
+    /// Mirror the image vertically with the given probability
+    FlipVertical { probability: f32 },
+    /// Rotate by a random angle between `-max_degrees` and `max_degrees`
+    Rotation { max_degrees: f32 },
+    /// Randomly change brightness, contrast and saturation by up to the given amounts
+    ColorJitter {
+        brightness: f32,
+        contrast: f32,
+        saturation: f32,
+    },
+    /// Gaussian blur with a random radius between 0 and `max_radius`
+    Blur { max_radius: u32 },
+    /// Gaussian noise with a random standard deviation between 0 and `max_sigma`
+    Noise { max_sigma: f32 },
+    /// Fill up to `count` random rectangles of at most `max_size` of each side with black
+    Cutout { count: u32, max_size: f32 },
+}
+
+impl Augmentation {
+    fn apply(&self, buffer: PixelBuffer<Rgb>, rng: &mut StdRng) -> PixelBuffer<Rgb> {
+        match *self {
+            Augmentation::RandomCrop { min_scale } => random_crop(buffer, min_scale, rng),
+            Augmentation::FlipHorizontal { probability } => {
+                if rng.gen::<f32>() < probability {
+                    flip_horizontal(&buffer)
+                } else {
+                    buffer
+                }
+            }
+            Augmentation::FlipVertical { probability } => {
+                if rng.gen::<f32>() < probability {
+                    flip_vertical(&buffer)
+                } else {
+                    buffer
+                }
+            }
+            Augmentation::Rotation { max_degrees } => {
+                let degrees = random_range(rng, max_degrees);
+                if degrees == 0.0 {
+                    buffer
+                } else {
+                    rotate(&buffer, degrees, Rgb::BLACK, FilterMode::Bilinear)
+                }
+            }
+            Augmentation::ColorJitter {
+                brightness,
+                contrast,
+                saturation,
+            } => {
+                let brightness = random_range(rng, brightness);
+                let contrast = 1.0 + random_range(rng, contrast);
+                let saturation = 1.0 + random_range(rng, saturation);
+
+                buffer.map_colors(|c| {
+                    c.with_brightness_contrast(brightness, contrast)
+                        .with_saturation(saturation)
+                })
+            }
+            Augmentation::Blur { max_radius } => {
+                let radius = rng.gen_range(0..=max_radius);
+                if radius == 0 {
+                    buffer
+                } else {
+                    gaussian_blur(&buffer, radius, None)
+                }
+            }
+            Augmentation::Noise { max_sigma } => {
+                let sigma = rng.gen::<f32>() * max_sigma.max(0.0);
+                buffer.map_colors(|c| {
+                    c.map_color_channels(|v| {
+                        let noise: f32 = rng.sample(StandardNormal);
+                        v + noise * sigma
+                    })
+                })
+            }
+            Augmentation::Cutout { count, max_size } => cutout(buffer, count, max_size, rng),
+        }
+    }
+}
+
+/// Random value between `-max` and `max`
+fn random_range(rng: &mut StdRng, max: f32) -> f32 {
+    let max = max.abs();
+    if max > 0.0 {
+        rng.gen_range(-max..=max)
+    } else {
+        0.0
+    }
+}
+
+/// Random length between `min_scale` and 1 times `size` with at least one pixel
+fn random_length(rng: &mut StdRng, size: u32, min_scale: f32) -> u32 {
+    let min = ((size as f32 * min_scale.clamp(0.0, 1.0)) as u32).clamp(1, size);
+    rng.gen_range(min..=size)
+}
+
+fn random_crop(buffer: PixelBuffer<Rgb>, min_scale: f32, rng: &mut StdRng) -> PixelBuffer<Rgb> {
+    if buffer.is_empty() {
+        return buffer;
+    }
+
+    let width = random_length(rng, buffer.width(), min_scale);
+    let height = random_length(rng, buffer.height(), min_scale);
+    let x = rng.gen_range(0..=buffer.width() - width);
+    let y = rng.gen_range(0..=buffer.height() - height);
+
+    let cropped = crop(&buffer, x, y, width, height);
+    resize(
+        &cropped,
+        buffer.width(),
+        buffer.height(),
+        FilterMode::Bilinear,
+    )
+}
+
+fn cutout(
+    mut buffer: PixelBuffer<Rgb>,
+    count: u32,
+    max_size: f32,
+    rng: &mut StdRng,
+) -> PixelBuffer<Rgb> {
+    if buffer.is_empty() || count == 0 {
+        return buffer;
+    }
+
+    let max_size = max_size.clamp(0.0, 1.0);
+    let max_width = (buffer.width() as f32 * max_size) as u32;
+    let max_height = (buffer.height() as f32 * max_size) as u32;
+
+    if max_width == 0 || max_height == 0 {
+        return buffer;
+    }
+
+    let count = rng.gen_range(1..=count);
+
+    for _ in 0..count {
+        let width = random_length(rng, max_width, 0.0);
+        let height = random_length(rng, max_height, 0.0);
+
+        let x = rng.gen_range(0..=buffer.width() - width);
+        let y = rng.gen_range(0..=buffer.height() - height);
+
+        buffer.fill_rect(Region::new(x, y, width, height), Rgb::BLACK);
+    }
+
+    buffer
+}
+
+/// Composable pipeline of random transformations to create variants of an image
+///
+/// The steps are applied in the order they were added. All random decisions are derived from the seed,
+/// so the same pipeline creates the same variants for the same input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Augment {
+    seed: u64,
+    steps: Vec<Augmentation>,
+}
+
+impl Augment {
+    pub fn new(seed: u64) -> Augment {
+        Augment {
+            seed,
+            steps: vec![],
+        }
+    }
+
+    /// Create an empty pipeline with a random seed
+    pub fn from_entropy() -> Augment {
+        Augment::new(rand::random())
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn steps(&self) -> &[Augmentation] {
+        &self.steps
+    }
+
+    pub fn with(mut self, step: Augmentation) -> Augment {
+        self.steps.push(step);
+        self
+    }
+
+    pub fn random_crop(self, min_scale: f32) -> Augment {
+        self.with(Augmentation::RandomCrop { min_scale })
+    }
+
+    pub fn flip_horizontal(self, probability: f32) -> Augment {
+        self.with(Augmentation::FlipHorizontal { probability })
+    }
+
+    pub fn flip_vertical(self, probability: f32) -> Augment {
+        self.with(Augmentation::FlipVertical { probability })
+    }
+
+    pub fn rotation(self, max_degrees: f32) -> Augment {
+        self.with(Augmentation::Rotation { max_degrees })
+    }
+
+    pub fn color_jitter(self, brightness: f32, contrast: f32, saturation: f32) -> Augment {
+        self.with(Augmentation::ColorJitter {
+            brightness,
+            contrast,
+            saturation,
+        })
+    }
+
+    pub fn blur(self, max_radius: u32) -> Augment {
+        self.with(Augmentation::Blur { max_radius })
+    }
+
+    pub fn noise(self, max_sigma: f32) -> Augment {
+        self.with(Augmentation::Noise { max_sigma })
+    }
+
+    pub fn cutout(self, count: u32, max_size: f32) -> Augment {
+        self.with(Augmentation::Cutout { count, max_size })
+    }
+
+    /// Create `count` augmented variants of `buffer`
+    pub fn apply(&self, buffer: &PixelBuffer<Rgb>, count: usize) -> Vec<PixelBuffer<Rgb>> {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+
+        (0..count)
+            .map(|_| {
+                self.steps
+                    .iter()
+                    .fold(buffer.clone(), |buffer, step| step.apply(buffer, &mut rng))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_buffer() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(16, 12, |x, y| {
+            Rgb::new(x as f32 / 16.0, y as f32 / 12.0, 0.5)
+        })
+    }
+
+    fn full_pipeline(seed: u64) -> Augment {
+        Augment::new(seed)
+            .random_crop(0.5)
+            .flip_horizontal(0.5)
+            .flip_vertical(0.5)
+            .rotation(15.0)
+            .color_jitter(0.1, 0.2, 0.3)
+            .blur(2)
+            .noise(0.05)
+            .cutout(2, 0.25)
+    }
+
+    fn data(buffers: &[PixelBuffer<Rgb>]) -> Vec<&[Rgb]> {
+        buffers.iter().map(|b| b.data()).collect()
+    }
+
+    #[test]
+    fn test_augment() {
+        let buffer = test_buffer();
+
+        let variants = full_pipeline(42).apply(&buffer, 5);
+
+        assert_eq!(variants.len(), 5);
+        for variant in &variants {
+            assert_eq!(variant.width(), buffer.width());
+            assert_eq!(variant.height(), buffer.height());
+        }
+
+        assert_ne!(variants[0].data(), variants[1].data());
+    }
+
+    #[test]
+    fn test_augment_seed() {
+        let buffer = test_buffer();
+
+        let a = full_pipeline(1).apply(&buffer, 3);
+        let b = full_pipeline(1).apply(&buffer, 3);
+        let c = full_pipeline(2).apply(&buffer, 3);
+
+        assert_eq!(data(&a), data(&b));
+        assert_ne!(data(&a), data(&c));
+    }
+
+    #[test]
+    fn test_augment_empty_pipeline() {
+        let buffer = test_buffer();
+
+        let variants = Augment::new(0).apply(&buffer, 2);
+
+        assert_eq!(data(&variants), vec![buffer.data(), buffer.data()]);
+    }
+
+    #[test]
+    fn test_flip_probability() {
+        let buffer = test_buffer();
+
+        let flipped = Augment::new(0).flip_horizontal(1.0).apply(&buffer, 1);
+        assert_eq!(flipped[0].data(), flip_horizontal(&buffer).data());
+
+        let unchanged = Augment::new(0).flip_horizontal(0.0).apply(&buffer, 1);
+        assert_eq!(unchanged[0].data(), buffer.data());
+    }
+
+    #[test]
+    fn test_cutout() {
+        let buffer = PixelBuffer::new_with_color(10, 10, Rgb::WHITE);
+
+        let res = Augment::new(3).cutout(1, 0.5).apply(&buffer, 1);
+
+        let black = res[0].data().iter().filter(|c| **c == Rgb::BLACK).count();
+        assert!(black > 0);
+        assert!(black <= 25);
+
+        let tiny = Augment::new(3).cutout(1, 0.01).apply(&buffer, 1);
+        assert_eq!(tiny[0].data(), buffer.data());
+    }
+}
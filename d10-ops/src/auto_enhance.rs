@@ -0,0 +1,33 @@
+use d10_core::color::{Color, Rgb};
+use d10_core::pixelbuffer::PixelBuffer;
+
+use crate::{
+    balance, compose, optimize_lightness, optimize_saturation, stretch_contrast, BalanceMode,
+    SaturationMode,
+};
+
+/// Automatically improve white balance, contrast, lightness and saturation in a single step
+///
+/// # Arguments
+/// strength: Value between 0.0 (no change) and 1.0 (full enhancement)
+pub fn auto_enhance(buffer: &PixelBuffer<Rgb>, strength: f32) -> PixelBuffer<Rgb> {
+    let strength = strength.clamp(0.0, 1.0);
+
+    if strength <= f32::EPSILON || buffer.is_empty() {
+        return buffer.clone();
+    }
+
+    let enhanced = balance(buffer, BalanceMode::Rgb, 0.5);
+    let enhanced = stretch_contrast(&enhanced, 0.5);
+    let enhanced = optimize_lightness(&enhanced, 0.5);
+    let enhanced = optimize_saturation(&enhanced, 0.75, SaturationMode::Hsl);
+
+    compose([buffer, &enhanced], Rgb::NONE, |_, _, [c1, c2]| {
+        Rgb::new_with_alpha(
+            c1.red() + (c2.red() - c1.red()) * strength,
+            c1.green() + (c2.green() - c1.green()) * strength,
+            c1.blue() + (c2.blue() - c1.blue()) * strength,
+            c1.alpha(),
+        )
+    })
+}
@@ -0,0 +1,128 @@
+use d10_core::color::{Color, Rgb, Srgb};
+use d10_core::pixelbuffer::PixelBuffer;
+
+/// Black and white point of the values or `None` if there is nothing to stretch
+fn levels(mut values: Vec<f32>, clip_low: f32, clip_high: f32) -> Option<(f32, f32)> {
+    let last = values.len() - 1;
+
+    let low_index = ((values.len() as f32 * clip_low) as usize).min(last);
+    let high_index = last - ((values.len() as f32 * clip_high) as usize).min(last);
+
+    let black = *values.select_nth_unstable_by(low_index, f32::total_cmp).1;
+    let white = *values.select_nth_unstable_by(high_index, f32::total_cmp).1;
+
+    if black < white && (black > 0.0 || white < 1.0) {
+        Some((black, white))
+    } else {
+        None
+    }
+}
+
+/// Stretch the tones to use the full range from black to white
+///
+/// The black and white points are percentiles of the gamma encoded values, so that
+/// `clip_low_pct` percent of the values end up black and `clip_high_pct` percent white.
+/// Clipping a small amount like 0.1% keeps single outliers from preventing the stretch.
+///
+/// With `per_channel` the red, green and blue channel are stretched independently, which also
+/// removes color casts. Otherwise all channels use the same points and the colors are kept.
+/// Fully transparent pixels are ignored when computing the points.
+pub fn auto_levels(
+    buffer: &PixelBuffer<Rgb>,
+    clip_low_pct: f32,
+    clip_high_pct: f32,
+    per_channel: bool,
+) -> PixelBuffer<Rgb> {
+    let colors: Vec<Srgb> = buffer
+        .data()
+        .iter()
+        .filter(|c| c.alpha() > 0.0)
+        .map(|c| c.to_srgb())
+        .collect();
+
+    if colors.is_empty() {
+        return buffer.clone();
+    }
+
+    let clip_low = clip_low_pct.clamp(0.0, 100.0) / 100.0;
+    let clip_high = clip_high_pct.clamp(0.0, 100.0) / 100.0;
+
+    let points = if per_channel {
+        [0, 1, 2].map(|i| {
+            let values = colors.iter().map(|c| c.data[i]).collect();
+            levels(values, clip_low, clip_high)
+        })
+    } else {
+        let values = colors
+            .iter()
+            .flat_map(|c| [c.data[0], c.data[1], c.data[2]])
+            .collect();
+        [levels(values, clip_low, clip_high); 3]
+    };
+
+    if points.iter().all(|p| p.is_none()) {
+        return buffer.clone();
+    }
+
+    buffer.map_colors(|c| {
+        let mut c = c.to_srgb();
+
+        for (v, p) in c.data.iter_mut().zip(points.iter()) {
+            if let Some((black, white)) = p {
+                *v = (*v - black) / (white - black);
+            }
+        }
+
+        Srgb::new_with_alpha(c.data[0], c.data[1], c.data[2], c.data[3]).to_rgb()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(100, 1, |x, _| {
+            let v = 0.2 + x as f32 / 99.0 * 0.6;
+            Srgb::new(v, v * 0.9, v * 0.8).to_rgb()
+        })
+    }
+
+    #[test]
+    fn test_auto_levels() {
+        let result = auto_levels(&gradient(), 0.0, 0.0, false);
+
+        let first = result.get_pixel(0, 0).to_srgb();
+        let last = result.get_pixel(99, 0).to_srgb();
+
+        assert!(first.blue() < 0.01);
+        assert!(last.red() > 0.99);
+        assert!(last.blue() < 0.9);
+    }
+
+    #[test]
+    fn test_per_channel() {
+        let result = auto_levels(&gradient(), 0.0, 0.0, true);
+
+        let first = result.get_pixel(0, 0).to_srgb();
+        let last = result.get_pixel(99, 0).to_srgb();
+
+        for i in 0..3 {
+            assert!(first.data[i] < 0.01);
+            assert!(last.data[i] > 0.99);
+        }
+    }
+
+    #[test]
+    fn test_clipping() {
+        let mut buffer = gradient();
+        buffer.put_pixel(0, 0, Rgb::BLACK);
+        buffer.put_pixel(99, 0, Rgb::WHITE);
+
+        assert_eq!(auto_levels(&buffer, 0.0, 0.0, false).data(), buffer.data());
+
+        let result = auto_levels(&buffer, 1.5, 1.5, false);
+        assert!(result.get_pixel(1, 0).to_srgb().blue() < 0.01);
+        assert!(result.get_pixel(98, 0).to_srgb().red() > 0.99);
+    }
+}
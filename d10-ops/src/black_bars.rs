@@ -0,0 +1,69 @@
+use d10_core::color::Rgb;
+use d10_core::pixelbuffer::PixelBuffer;
+use d10_core::region::Region;
+
+use crate::threshold::srgb_luma;
+
+/// Fraction of bright pixels a line of a bar may contain, i.e. noise of lossy video codecs
+const MAX_BRIGHT_FRACTION: f32 = 0.02;
+
+/// Find the content of an image surrounded by black bars like letterboxed or pillarboxed videos
+///
+/// Rows and columns at the borders belong to a bar if nearly all their pixels have a luma of at
+/// most `threshold`. Images without any content return the full image.
+pub fn detect_black_bars(buffer: &PixelBuffer<Rgb>, threshold: f32) -> Region {
+    let width = buffer.width() as usize;
+    let height = buffer.height() as usize;
+
+    let mut rows = vec![0; height];
+    let mut columns = vec![0; width];
+
+    for (x, y, c) in buffer.enumerate() {
+        if srgb_luma(&c) > threshold {
+            rows[y as usize] += 1;
+            columns[x as usize] += 1;
+        }
+    }
+
+    let is_content = |count: usize, len: usize| count as f32 > len as f32 * MAX_BRIGHT_FRACTION;
+
+    let top = rows.iter().position(|c| is_content(*c, width));
+    let bottom = rows.iter().rposition(|c| is_content(*c, width));
+    let left = columns.iter().position(|c| is_content(*c, height));
+    let right = columns.iter().rposition(|c| is_content(*c, height));
+
+    match (top, bottom, left, right) {
+        (Some(top), Some(bottom), Some(left), Some(right)) => Region::new(
+            left as u32,
+            top as u32,
+            (right - left + 1) as u32,
+            (bottom - top + 1) as u32,
+        ),
+        _ => Region::new(0, 0, buffer.width(), buffer.height()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_black_bars() {
+        let buffer = PixelBuffer::new_from_func(100, 60, |x, y| {
+            if (10..90).contains(&x) && (5..50).contains(&y) {
+                Rgb::new(0.1, 0.6, 0.3)
+            } else if x == 3 && y == 30 {
+                // Compression noise inside of the bar
+                Rgb::WHITE
+            } else {
+                Rgb::new(0.01, 0.01, 0.01)
+            }
+        });
+
+        assert_eq!(detect_black_bars(&buffer, 0.1), Region::new(10, 5, 80, 45));
+
+        let black = PixelBuffer::new_with_color(20, 10, Rgb::BLACK);
+        assert_eq!(detect_black_bars(&black, 0.1), Region::new(0, 0, 20, 10));
+        assert_eq!(detect_black_bars(&buffer, 1.0), Region::new(0, 0, 100, 60));
+    }
+}
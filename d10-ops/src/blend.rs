@@ -12,8 +12,8 @@ fn apply_intensity(v1: f32, v2: f32, intensity: f32) -> f32 {
 }
 
 fn blend_color<F>(c1: Rgb, c2: Rgb, intensity: f32, func: F) -> Rgb
-    where
-        F: Fn(f32, f32) -> f32,
+where
+    F: Fn(f32, f32) -> f32,
 {
     let intensity = intensity * c2.alpha();
 
@@ -33,8 +33,8 @@ fn blend_image_with_func<F>(
     intensity: f32,
     func: F,
 ) -> PixelBuffer<Rgb>
-    where
-        F: Fn(Rgb, Rgb, f32) -> Rgb,
+where
+    F: Fn(Rgb, Rgb, f32) -> Rgb,
 {
     let width = img1.width().max(img2.width());
     let height = img1.height().max(img2.height());
@@ -52,7 +52,7 @@ fn blend_image_with_func<F>(
     })
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum BlendOp {
     Normal,
     Addition,
@@ -91,28 +91,35 @@ impl FromStr for BlendOp {
     }
 }
 
+impl BlendOp {
+    /// Blend a single color `c2` onto `c1`
+    pub fn blend(&self, c1: Rgb, c2: Rgb, intensity: f32) -> Rgb {
+        match self {
+            BlendOp::Normal => blend_normal(c1, c2, intensity),
+            BlendOp::Addition => blend_addition(c1, c2, intensity),
+            BlendOp::Subtract => blend_subtract(c1, c2, intensity),
+            BlendOp::Darken => blend_darken(c1, c2, intensity),
+            BlendOp::Lighten => blend_lighten(c1, c2, intensity),
+            BlendOp::HslDarken => blend_hsl_darken(c1, c2, intensity),
+            BlendOp::HslLighten => blend_hsl_lighten(c1, c2, intensity),
+            BlendOp::LchDarken => blend_lch_darken(c1, c2, intensity),
+            BlendOp::LchLighten => blend_lch_lighten(c1, c2, intensity),
+            BlendOp::LchHue => blend_lch_hue(c1, c2, intensity),
+            BlendOp::LchSaturation => blend_lch_saturation(c1, c2, intensity),
+            BlendOp::LchColor => blend_lch_color(c1, c2, intensity),
+        }
+    }
+}
+
 pub fn blend_image(
     img1: &PixelBuffer<Rgb>,
     img2: &PixelBuffer<Rgb>,
     blend_op: BlendOp,
     intensity: f32,
 ) -> PixelBuffer<Rgb> {
-    match blend_op {
-        BlendOp::Normal => blend_image_with_func(img1, img2, intensity, blend_normal),
-        BlendOp::Addition => blend_image_with_func(img1, img2, intensity, blend_addition),
-        BlendOp::Subtract => blend_image_with_func(img1, img2, intensity, blend_subtract),
-        BlendOp::Darken => blend_image_with_func(img1, img2, intensity, blend_darken),
-        BlendOp::Lighten => blend_image_with_func(img1, img2, intensity, blend_lighten),
-        BlendOp::HslDarken => blend_image_with_func(img1, img2, intensity, blend_hsl_darken),
-        BlendOp::HslLighten => blend_image_with_func(img1, img2, intensity, blend_hsl_lighten),
-        BlendOp::LchDarken => blend_image_with_func(img1, img2, intensity, blend_lch_darken),
-        BlendOp::LchLighten => blend_image_with_func(img1, img2, intensity, blend_lch_lighten),
-        BlendOp::LchHue => blend_image_with_func(img1, img2, intensity, blend_lch_hue),
-        BlendOp::LchSaturation => {
-            blend_image_with_func(img1, img2, intensity, blend_lch_saturation)
-        }
-        BlendOp::LchColor => blend_image_with_func(img1, img2, intensity, blend_lch_color),
-    }
+    blend_image_with_func(img1, img2, intensity, |c1, c2, intensity| {
+        blend_op.blend(c1, c2, intensity)
+    })
 }
 
 pub fn blend_normal(c1: Rgb, c2: Rgb, intensity: f32) -> Rgb {
@@ -208,8 +215,7 @@ pub fn blend_lch_hue(c1: Rgb, c2: Rgb, intensity: f32) -> Rgb {
         apply_intensity(h1, h2, intensity)
     };
 
-    c1.with_h(h)
-        .to_rgb()
+    c1.with_h(h).to_rgb()
 }
 
 pub fn blend_lch_saturation(c1: Rgb, c2: Rgb, intensity: f32) -> Rgb {
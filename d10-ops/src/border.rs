@@ -0,0 +1,232 @@
+use d10_core::color::Rgb;
+use d10_core::errors::ParseEnumError;
+use d10_core::pixelbuffer::PixelBuffer;
+use d10_core::region::Region;
+
+use std::str::FromStr;
+
+/// Amount the sides of bevels get lightened or darkened
+const BEVEL_SHADE: f32 = 0.4;
+
+/// Off-white color of the polaroid matte
+const MATTE_COLOR: Rgb = Rgb {
+    data: [0.93, 0.92, 0.89, 1.0],
+};
+
+/// Color of a border
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BorderFill {
+    Color(Rgb),
+    /// Linear gradient from the edge of the image to the outer edge of the border
+    Gradient {
+        inner: Rgb,
+        outer: Rgb,
+    },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BorderStyle {
+    Solid,
+    /// Two lines with a transparent gap between them, each a third of the border width
+    Double,
+    /// Bevel with a dark top and left side that makes the image look sunken
+    Inset,
+    /// Bevel with a light top and left side that makes the image look raised
+    Outset,
+}
+
+impl FromStr for BorderStyle {
+    type Err = ParseEnumError;
+
+    fn from_str(value: &str) -> Result<BorderStyle, Self::Err> {
+        match value {
+            "solid" | "default" => Ok(BorderStyle::Solid),
+            "double" => Ok(BorderStyle::Double),
+            "inset" => Ok(BorderStyle::Inset),
+            "outset" => Ok(BorderStyle::Outset),
+            _ => Err(ParseEnumError::new(value, "BorderStyle")),
+        }
+    }
+}
+
+fn mix(a: &Rgb, b: &Rgb, t: f32) -> Rgb {
+    Rgb {
+        data: [
+            a.data[0] * t + b.data[0] * (1.0 - t),
+            a.data[1] * t + b.data[1] * (1.0 - t),
+            a.data[2] * t + b.data[2] * (1.0 - t),
+            a.data[3] * t + b.data[3] * (1.0 - t),
+        ],
+    }
+}
+
+/// Mix the color with white or black while keeping its alpha
+fn shade(c: &Rgb, lighten: bool) -> Rgb {
+    let target = if lighten { 1.0 } else { 0.0 };
+
+    Rgb {
+        data: [
+            c.data[0] + (target - c.data[0]) * BEVEL_SHADE,
+            c.data[1] + (target - c.data[1]) * BEVEL_SHADE,
+            c.data[2] + (target - c.data[2]) * BEVEL_SHADE,
+            c.data[3],
+        ],
+    }
+}
+
+/// Surround the image with a border of `width` pixels on every side
+pub fn add_border(
+    buffer: &PixelBuffer<Rgb>,
+    width: u32,
+    fill: BorderFill,
+    style: BorderStyle,
+) -> PixelBuffer<Rgb> {
+    let new_width = buffer.width() + 2 * width;
+    let new_height = buffer.height() + 2 * width;
+
+    PixelBuffer::new_from_func(new_width, new_height, |x, y| {
+        let (left, top) = (x, y);
+        let (right, bottom) = (new_width - 1 - x, new_height - 1 - y);
+
+        // Distance to the outer edge of the border
+        let distance = left.min(top).min(right).min(bottom);
+
+        if distance >= width {
+            return *buffer.get_pixel(x - width, y - width);
+        }
+
+        let color = match fill {
+            BorderFill::Color(c) => c,
+            BorderFill::Gradient { inner, outer } => {
+                mix(&inner, &outer, (distance as f32 + 0.5) / width as f32)
+            }
+        };
+
+        match style {
+            BorderStyle::Solid => color,
+            BorderStyle::Double if width >= 3 && distance * 3 / width == 1 => Rgb::NONE,
+            BorderStyle::Double => color,
+            BorderStyle::Inset | BorderStyle::Outset => {
+                // The corners are split diagonally between the sides
+                let top_left = top.min(left) <= bottom.min(right);
+                shade(&color, top_left == (style == BorderStyle::Outset))
+            }
+        }
+    })
+}
+
+/// Put the image on an off-white matte like a polaroid photo
+///
+/// The matte is 6% of the shorter side of the image wide with a four times wider bottom.
+pub fn polaroid(buffer: &PixelBuffer<Rgb>) -> PixelBuffer<Rgb> {
+    let margin = ((buffer.width().min(buffer.height()) as f32 * 0.06).round() as u32).max(1);
+
+    let mut result = PixelBuffer::new_with_color(
+        buffer.width() + 2 * margin,
+        buffer.height() + 5 * margin,
+        MATTE_COLOR,
+    );
+
+    result.copy_from(
+        buffer,
+        Region::new(0, 0, buffer.width(), buffer.height()),
+        (margin, margin),
+    );
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solid_border() {
+        let buffer = PixelBuffer::new_with_color(4, 3, Rgb::RED);
+
+        let result = add_border(&buffer, 2, BorderFill::Color(Rgb::BLUE), BorderStyle::Solid);
+
+        assert_eq!((result.width(), result.height()), (8, 7));
+        assert_eq!(result.get_pixel(0, 0), &Rgb::BLUE);
+        assert_eq!(result.get_pixel(1, 4), &Rgb::BLUE);
+        assert_eq!(result.get_pixel(2, 2), &Rgb::RED);
+        assert_eq!(result.get_pixel(5, 4), &Rgb::RED);
+        assert_eq!(result.get_pixel(6, 4), &Rgb::BLUE);
+
+        let unchanged = add_border(&buffer, 0, BorderFill::Color(Rgb::BLUE), BorderStyle::Solid);
+        assert_eq!(unchanged.data(), buffer.data());
+    }
+
+    #[test]
+    fn test_gradient_border() {
+        let buffer = PixelBuffer::new_with_color(2, 2, Rgb::RED);
+        let fill = BorderFill::Gradient {
+            inner: Rgb::WHITE,
+            outer: Rgb::BLACK,
+        };
+
+        let result = add_border(&buffer, 2, fill, BorderStyle::Solid);
+
+        assert_eq!(result.get_pixel(0, 3).red(), 0.25);
+        assert_eq!(result.get_pixel(1, 3).red(), 0.75);
+    }
+
+    #[test]
+    fn test_double_border() {
+        let buffer = PixelBuffer::new_with_color(2, 2, Rgb::RED);
+
+        let result = add_border(
+            &buffer,
+            3,
+            BorderFill::Color(Rgb::BLUE),
+            BorderStyle::Double,
+        );
+
+        let row: Vec<_> = (0..4).map(|x| *result.get_pixel(x, 3)).collect();
+        assert_eq!(row, [Rgb::BLUE, Rgb::NONE, Rgb::BLUE, Rgb::RED]);
+    }
+
+    #[test]
+    fn test_bevel_border() {
+        let buffer = PixelBuffer::new_with_color(2, 2, Rgb::RED);
+        let gray = Rgb::new(0.5, 0.5, 0.5);
+
+        let outset = add_border(&buffer, 2, BorderFill::Color(gray), BorderStyle::Outset);
+        let inset = add_border(&buffer, 2, BorderFill::Color(gray), BorderStyle::Inset);
+
+        for (x, y) in [(2, 0), (0, 2), (5, 0)] {
+            assert!(outset.get_pixel(x, y).red() > 0.5, "{}x{}", x, y);
+            assert!(inset.get_pixel(x, y).red() < 0.5, "{}x{}", x, y);
+        }
+
+        for (x, y) in [(2, 5), (5, 2), (1, 5)] {
+            assert!(outset.get_pixel(x, y).red() < 0.5, "{}x{}", x, y);
+            assert!(inset.get_pixel(x, y).red() > 0.5, "{}x{}", x, y);
+        }
+    }
+
+    #[test]
+    fn test_polaroid() {
+        let buffer = PixelBuffer::new_with_color(100, 50, Rgb::RED);
+
+        let result = polaroid(&buffer);
+
+        assert_eq!((result.width(), result.height()), (106, 65));
+        assert_eq!(result.get_pixel(3, 3), &Rgb::RED);
+        assert_eq!(result.get_pixel(2, 3), &MATTE_COLOR);
+        assert_eq!(result.get_pixel(50, 60), &MATTE_COLOR);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "default".parse::<BorderStyle>().unwrap(),
+            BorderStyle::Solid
+        );
+        assert_eq!(
+            "outset".parse::<BorderStyle>().unwrap(),
+            BorderStyle::Outset
+        );
+        assert!("groove".parse::<BorderStyle>().is_err());
+    }
+}
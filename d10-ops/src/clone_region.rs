@@ -0,0 +1,110 @@
+use d10_core::color::Rgb;
+use d10_core::pixelbuffer::PixelBuffer;
+use d10_core::region::Region;
+
+/// Weight of a pixel at `pos` inside of a span of `size` pixels with a soft border of `feather`
+fn edge_weight(pos: u32, size: u32, feather: u32) -> f32 {
+    if feather == 0 {
+        return 1.0;
+    }
+
+    let distance = pos.min(size - 1 - pos) as f32 + 0.5;
+    (distance / feather as f32).min(1.0)
+}
+
+/// Copy a region of the image to another position like a clone stamp
+///
+/// The copied pixels are blended into the destination with a soft border of `feather` pixels,
+/// so that the patch doesn't leave visible edges. Parts of the source region outside of the image
+/// are ignored, as well as pixels that would be placed outside of the image.
+pub fn clone_region(
+    buffer: &PixelBuffer<Rgb>,
+    src_rect: Region,
+    dst_pos: (u32, u32),
+    feather: u32,
+) -> PixelBuffer<Rgb> {
+    let src_rect = match src_rect.clamp(buffer.width(), buffer.height()) {
+        Some(src_rect) => src_rect,
+        None => return buffer.clone(),
+    };
+
+    let mut result = buffer.clone();
+
+    for y in 0..src_rect.height {
+        let dst_y = dst_pos.1.saturating_add(y);
+        if dst_y >= buffer.height() {
+            break;
+        }
+
+        let weight_y = edge_weight(y, src_rect.height, feather);
+
+        for x in 0..src_rect.width {
+            let dst_x = dst_pos.0.saturating_add(x);
+            if dst_x >= buffer.width() {
+                break;
+            }
+
+            let weight = weight_y * edge_weight(x, src_rect.width, feather);
+
+            let src = buffer.get_pixel(src_rect.x + x, src_rect.y + y);
+            let dst = buffer.get_pixel(dst_x, dst_y);
+
+            let mut data = dst.data;
+            for (d, s) in data.iter_mut().zip(src.data.iter()) {
+                *d += (s - *d) * weight;
+            }
+
+            result.put_pixel(dst_x, dst_y, Rgb { data });
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_buffer() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(20, 10, |x, _| if x < 10 { Rgb::RED } else { Rgb::BLUE })
+    }
+
+    #[test]
+    fn test_clone_region() {
+        let buffer = test_buffer();
+
+        let res = clone_region(&buffer, Region::new(0, 0, 5, 5), (12, 2), 0);
+
+        assert_eq!(res.get_pixel(12, 2), &Rgb::RED);
+        assert_eq!(res.get_pixel(16, 6), &Rgb::RED);
+        assert_eq!(res.get_pixel(17, 6), &Rgb::BLUE);
+        assert_eq!(res.get_pixel(12, 7), &Rgb::BLUE);
+        assert_eq!(res.get_pixel(11, 2), &Rgb::BLUE);
+    }
+
+    #[test]
+    fn test_clone_region_feather() {
+        let buffer = test_buffer();
+
+        let res = clone_region(&buffer, Region::new(0, 0, 8, 8), (12, 1), 3);
+
+        // Fully replaced in the center and blended at the border
+        assert_eq!(res.get_pixel(16, 5), &Rgb::RED);
+        let border = res.get_pixel(12, 5);
+        assert!(border.red() > 0.0 && border.red() < 0.5);
+        assert!(border.blue() > 0.5 && border.blue() < 1.0);
+    }
+
+    #[test]
+    fn test_clone_region_outside() {
+        let buffer = test_buffer();
+
+        let res = clone_region(&buffer, Region::new(5, 5, 10, 10), (15, 8), 0);
+
+        assert_eq!(res.get_pixel(15, 8), buffer.get_pixel(5, 5));
+        assert_eq!(res.get_pixel(19, 9), buffer.get_pixel(9, 6));
+
+        let res = clone_region(&buffer, Region::new(30, 0, 5, 5), (0, 0), 0);
+        assert_eq!(res.data(), buffer.data());
+    }
+}
@@ -0,0 +1,321 @@
+use d10_core::color::Rgb;
+use d10_core::errors::ParseEnumError;
+use d10_core::pixelbuffer::PixelBuffer;
+use d10_core::region::Region;
+use std::str::FromStr;
+
+use crate::{cover, FilterMode, Gravity};
+
+/// Golden ratio used to split the canvas of `CollageLayout::GoldenRatio`
+const PHI: f32 = 1.618_034;
+
+/// Arrangement of the cells of a collage
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CollageLayout {
+    /// Cells of equal size with the given number of columns
+    ///
+    /// Zero columns result in a roughly square number of columns and rows.
+    Grid { columns: u32 },
+    /// A large first image with the other images around it in a three column grid
+    Mosaic,
+    /// Every image takes the golden section of the remaining space in a spiral
+    GoldenRatio,
+}
+
+impl FromStr for CollageLayout {
+    type Err = ParseEnumError;
+
+    fn from_str(value: &str) -> Result<CollageLayout, Self::Err> {
+        use CollageLayout::*;
+        match value {
+            "grid" | "default" => Ok(Grid { columns: 0 }),
+            "mosaic" => Ok(Mosaic),
+            "golden_ratio" => Ok(GoldenRatio),
+            _ => Err(ParseEnumError::new(value, "CollageLayout")),
+        }
+    }
+}
+
+/// Size and layout of a collage
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CollageTemplate {
+    pub layout: CollageLayout,
+    pub width: u32,
+    pub height: u32,
+    /// Part of every image that is kept when it gets cropped to the aspect ratio of its cell
+    pub gravity: Gravity,
+}
+
+impl CollageTemplate {
+    pub fn new(layout: CollageLayout, width: u32, height: u32) -> CollageTemplate {
+        CollageTemplate {
+            layout,
+            width,
+            height,
+            gravity: Gravity::Center,
+        }
+    }
+
+    pub fn grid(columns: u32, width: u32, height: u32) -> CollageTemplate {
+        Self::new(CollageLayout::Grid { columns }, width, height)
+    }
+
+    pub fn mosaic(width: u32, height: u32) -> CollageTemplate {
+        Self::new(CollageLayout::Mosaic, width, height)
+    }
+
+    pub fn golden_ratio(width: u32, height: u32) -> CollageTemplate {
+        Self::new(CollageLayout::GoldenRatio, width, height)
+    }
+
+    pub fn with_gravity(self, gravity: Gravity) -> CollageTemplate {
+        CollageTemplate { gravity, ..self }
+    }
+
+    /// Cells for `count` images relative to a canvas of `width` x `height`
+    fn layout_cells(&self, count: usize, width: f32, height: f32) -> Vec<(f32, f32, f32, f32)> {
+        let count = count as u32;
+
+        match self.layout {
+            CollageLayout::Grid { columns } => {
+                let columns = match columns {
+                    0 => (count as f32).sqrt().ceil() as u32,
+                    columns => columns.min(count),
+                };
+                let rows = count.div_ceil(columns);
+
+                let cell_width = width / columns as f32;
+                let cell_height = height / rows as f32;
+
+                (0..count)
+                    .map(|i| {
+                        let x = (i % columns) as f32 * cell_width;
+                        let y = (i / columns) as f32 * cell_height;
+                        (x, y, cell_width, cell_height)
+                    })
+                    .collect()
+            }
+            CollageLayout::Mosaic => {
+                if count == 1 {
+                    return vec![(0.0, 0.0, width, height)];
+                }
+
+                // The first image takes 2x2 cells and the next two the column next to it
+                let rows = 2 + (count.saturating_sub(3)).div_ceil(3);
+                let cell_width = width / 3.0;
+                let cell_height = height / rows as f32;
+
+                let mut cells = vec![(0.0, 0.0, cell_width * 2.0, cell_height * 2.0)];
+
+                if count == 2 {
+                    cells.push((cell_width * 2.0, 0.0, cell_width, cell_height * 2.0));
+                    return cells;
+                }
+
+                cells.push((cell_width * 2.0, 0.0, cell_width, cell_height));
+                cells.push((cell_width * 2.0, cell_height, cell_width, cell_height));
+
+                // Images in an incomplete last row share its width
+                let rest = count - 3;
+                for i in 0..rest {
+                    let row = i / 3;
+                    let in_row = (rest - row * 3).min(3);
+                    let w = width / in_row as f32;
+
+                    cells.push((
+                        (i % 3) as f32 * w,
+                        (row + 2) as f32 * cell_height,
+                        w,
+                        cell_height,
+                    ));
+                }
+
+                cells
+            }
+            CollageLayout::GoldenRatio => {
+                let (mut x, mut y, mut w, mut h) = (0.0, 0.0, width, height);
+                let mut cells = Vec::with_capacity(count as usize);
+
+                for i in 0..count {
+                    if i == count - 1 {
+                        cells.push((x, y, w, h));
+                        break;
+                    }
+
+                    // Take the golden section from the left, top, right and bottom in turn
+                    match i % 4 {
+                        0 => {
+                            let part = w / PHI;
+                            cells.push((x, y, part, h));
+                            x += part;
+                            w -= part;
+                        }
+                        1 => {
+                            let part = h / PHI;
+                            cells.push((x, y, w, part));
+                            y += part;
+                            h -= part;
+                        }
+                        2 => {
+                            let part = w / PHI;
+                            cells.push((x + w - part, y, part, h));
+                            w -= part;
+                        }
+                        _ => {
+                            let part = h / PHI;
+                            cells.push((x, y + h - part, w, part));
+                            h -= part;
+                        }
+                    }
+                }
+
+                cells
+            }
+        }
+    }
+
+    /// Regions of the cells for `count` images with `gap` pixels between and around them
+    ///
+    /// Cells that get too small for the gap have a size of zero.
+    pub fn cells(&self, count: usize, gap: u32) -> Vec<Region> {
+        if count == 0 {
+            return vec![];
+        }
+
+        // The gap before each cell is part of the cell, so only one gap remains at the end
+        let inner_width = self.width.saturating_sub(gap) as f32;
+        let inner_height = self.height.saturating_sub(gap) as f32;
+
+        self.layout_cells(count, inner_width, inner_height)
+            .into_iter()
+            .map(|(x, y, w, h)| {
+                let x0 = x.round() as u32;
+                let y0 = y.round() as u32;
+                let x1 = (x + w).round() as u32;
+                let y1 = (y + h).round() as u32;
+
+                Region::new(
+                    x0 + gap,
+                    y0 + gap,
+                    (x1 - x0).saturating_sub(gap),
+                    (y1 - y0).saturating_sub(gap),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Arrange images on a canvas using the given template
+///
+/// Every image is scaled to cover its cell and cropped using the gravity of the template.
+/// `gap` is the number of pixels between the cells and around the border of the canvas.
+pub fn collage(
+    buffers: &[&PixelBuffer<Rgb>],
+    template: &CollageTemplate,
+    gap: u32,
+    background: Rgb,
+) -> PixelBuffer<Rgb> {
+    let mut result = PixelBuffer::new_with_color(template.width, template.height, background);
+
+    for (buffer, cell) in buffers.iter().zip(template.cells(buffers.len(), gap)) {
+        if cell.width == 0 || cell.height == 0 || buffer.is_empty() {
+            continue;
+        }
+
+        let tile = cover(
+            buffer,
+            cell.width,
+            cell.height,
+            template.gravity,
+            FilterMode::Auto,
+        );
+
+        result.copy_from(
+            &tile,
+            Region::new(0, 0, cell.width, cell.height),
+            (cell.x, cell.y),
+        );
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn covered_area(cells: &[Region]) -> u32 {
+        cells.iter().map(|c| c.width * c.height).sum()
+    }
+
+    #[test]
+    fn test_grid_cells() {
+        let cells = CollageTemplate::grid(2, 100, 60).cells(4, 0);
+        assert_eq!(
+            cells,
+            [
+                Region::new(0, 0, 50, 30),
+                Region::new(50, 0, 50, 30),
+                Region::new(0, 30, 50, 30),
+                Region::new(50, 30, 50, 30),
+            ]
+        );
+
+        let cells = CollageTemplate::grid(2, 100, 60).cells(4, 4);
+        assert_eq!(cells[0], Region::new(4, 4, 44, 24));
+        assert_eq!(cells[3], Region::new(52, 32, 44, 24));
+
+        // Automatic number of columns
+        assert_eq!(
+            CollageTemplate::grid(0, 90, 90).cells(9, 0)[4],
+            Region::new(30, 30, 30, 30)
+        );
+    }
+
+    #[test]
+    fn test_mosaic_cells() {
+        let cells = CollageTemplate::mosaic(90, 90).cells(5, 0);
+        assert_eq!(cells[0], Region::new(0, 0, 60, 60));
+        assert_eq!(cells[1], Region::new(60, 0, 30, 30));
+        assert_eq!(cells[3], Region::new(0, 60, 45, 30));
+        assert_eq!(covered_area(&cells), 90 * 90);
+
+        let cells = CollageTemplate::mosaic(90, 60).cells(2, 0);
+        assert_eq!(cells[1], Region::new(60, 0, 30, 60));
+    }
+
+    #[test]
+    fn test_golden_ratio_cells() {
+        let cells = CollageTemplate::golden_ratio(160, 100).cells(4, 0);
+
+        assert_eq!(cells[0].width, 99);
+        assert!(cells
+            .windows(2)
+            .all(|c| c[0].width * c[0].height >= c[1].width * c[1].height));
+        assert_eq!(covered_area(&cells), 160 * 100);
+    }
+
+    #[test]
+    fn test_collage() {
+        let red = PixelBuffer::new_with_color(20, 10, Rgb::RED);
+        let blue = PixelBuffer::new_with_color(5, 30, Rgb::BLUE);
+
+        let res = collage(
+            &[&red, &blue],
+            &CollageTemplate::grid(2, 42, 22),
+            2,
+            Rgb::WHITE,
+        );
+
+        assert_eq!((res.width(), res.height()), (42, 22));
+        assert_eq!(res.get_pixel(0, 0), &Rgb::WHITE);
+        assert_eq!(res.get_pixel(2, 2), &Rgb::RED);
+        assert_eq!(res.get_pixel(20, 20), &Rgb::WHITE);
+        assert_eq!(res.get_pixel(22, 2), &Rgb::BLUE);
+        assert_eq!(res.get_pixel(39, 19), &Rgb::BLUE);
+        assert_eq!(res.get_pixel(41, 21), &Rgb::WHITE);
+
+        let res = collage(&[], &CollageTemplate::mosaic(10, 10), 0, Rgb::BLACK);
+        assert!(res.data().iter().all(|c| *c == Rgb::BLACK));
+    }
+}
@@ -0,0 +1,137 @@
+use d10_core::color::{Color, Rgb};
+use d10_core::errors::ParseEnumError;
+use d10_core::pixelbuffer::PixelBuffer;
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    /// Fast 32 bit checksum to detect accidental changes
+    Crc32,
+    /// Cryptographic hash suitable for deduplication and verification
+    Sha256,
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = ParseEnumError;
+
+    fn from_str(value: &str) -> Result<HashAlgorithm, Self::Err> {
+        match value {
+            "sha256" | "default" => Ok(HashAlgorithm::Sha256),
+            "crc32" => Ok(HashAlgorithm::Crc32),
+            _ => Err(ParseEnumError::new(value, "HashAlgorithm")),
+        }
+    }
+}
+
+/// Canonical byte representation of the image
+///
+/// Starts with the width and height as big endian u32 followed by all pixels as
+/// big endian 16 bit sRGBA values. The color channels of fully transparent pixels are zeroed
+/// because many encoders don't preserve them.
+fn canonical_bytes(buffer: &PixelBuffer<Rgb>) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(8 + buffer.data().len() * 8);
+
+    bytes.extend_from_slice(&buffer.width().to_be_bytes());
+    bytes.extend_from_slice(&buffer.height().to_be_bytes());
+
+    let to_u16 = |v: f32| (v.clamp(0.0, 1.0) * 65535.0).round() as u16;
+
+    for c in buffer.data() {
+        let c = c.to_srgb();
+        let alpha = to_u16(c.alpha());
+
+        let channels = if alpha == 0 {
+            [0, 0, 0, 0]
+        } else {
+            [
+                to_u16(c.data[0]),
+                to_u16(c.data[1]),
+                to_u16(c.data[2]),
+                alpha,
+            ]
+        };
+
+        for v in channels {
+            bytes.extend_from_slice(&v.to_be_bytes());
+        }
+    }
+
+    bytes
+}
+
+/// Hash the pixel data of an image as lowercase hex string
+///
+/// The hash only depends on the size and the 16 bit sRGB pixel values, so the same image
+/// has the same hash regardless of the file format or metadata it was stored with.
+/// Lossy formats or 8 bit formats will still change the hash if the pixel values change.
+pub fn content_hash(buffer: &PixelBuffer<Rgb>, algorithm: HashAlgorithm) -> String {
+    let bytes = canonical_bytes(buffer);
+
+    match algorithm {
+        HashAlgorithm::Crc32 => format!("{:08x}", crc32fast::hash(&bytes)),
+        HashAlgorithm::Sha256 => Sha256::digest(&bytes)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_hash() {
+        let buffer = PixelBuffer::new_with_color(3, 2, Rgb::RED);
+
+        let sha = content_hash(&buffer, HashAlgorithm::Sha256);
+        let crc = content_hash(&buffer, HashAlgorithm::Crc32);
+
+        assert_eq!(sha.len(), 64);
+        assert_eq!(crc.len(), 8);
+        assert_eq!(sha, content_hash(&buffer.clone(), HashAlgorithm::Sha256));
+
+        let other_size = PixelBuffer::new_with_color(2, 3, Rgb::RED);
+        assert_ne!(sha, content_hash(&other_size, HashAlgorithm::Sha256));
+
+        let mut changed = buffer.clone();
+        changed.put_pixel(1, 1, Rgb::BLUE);
+        assert_ne!(sha, content_hash(&changed, HashAlgorithm::Sha256));
+        assert_ne!(crc, content_hash(&changed, HashAlgorithm::Crc32));
+    }
+
+    #[test]
+    fn test_content_hash_transparent() {
+        let a = PixelBuffer::new_with_color(2, 2, Rgb::new_with_alpha(1.0, 0.0, 0.0, 0.0));
+        let b = PixelBuffer::new_with_color(2, 2, Rgb::new_with_alpha(0.0, 1.0, 0.0, 0.0));
+
+        assert_eq!(
+            content_hash(&a, HashAlgorithm::Sha256),
+            content_hash(&b, HashAlgorithm::Sha256)
+        );
+    }
+
+    #[test]
+    fn test_content_hash_empty() {
+        let buffer = PixelBuffer::<Rgb>::new(0, 0);
+
+        assert_eq!(
+            content_hash(&buffer, HashAlgorithm::Sha256),
+            "af5570f5a1810b7af78caf4bc70a660f0df51e42baf91d4de5b2328de0e83dfc"
+        );
+    }
+
+    #[test]
+    fn test_hash_algorithm_from_str() {
+        assert_eq!(
+            "default".parse::<HashAlgorithm>().unwrap(),
+            HashAlgorithm::Sha256
+        );
+        assert_eq!(
+            "crc32".parse::<HashAlgorithm>().unwrap(),
+            HashAlgorithm::Crc32
+        );
+        assert!("md5".parse::<HashAlgorithm>().is_err());
+    }
+}
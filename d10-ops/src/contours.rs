@@ -0,0 +1,323 @@
+use d10_core::color::Rgb;
+use d10_core::pixelbuffer::PixelBuffer;
+
+use crate::threshold::srgb_luma;
+
+/// Directions as offsets in the order east, south, west and north
+const DIRECTIONS: [(i64, i64); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+/// Closed outline of a region in a binary image
+///
+/// The points are corners of pixels, so a single pixel at (0, 0) results in the points
+/// (0, 0), (1, 0), (1, 1) and (0, 1).
+/// Outer borders run clockwise and borders of holes run counterclockwise.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contour {
+    pub points: Vec<(f32, f32)>,
+}
+
+impl Contour {
+    /// Signed area that is positive for outer borders and negative for holes
+    pub fn area(&self) -> f32 {
+        let sum: f32 = self
+            .points
+            .iter()
+            .zip(self.points.iter().cycle().skip(1))
+            .map(|((x1, y1), (x2, y2))| x1 * y2 - x2 * y1)
+            .sum();
+
+        sum / 2.0
+    }
+
+    /// Returns true if this is the border of a hole inside a region
+    pub fn is_hole(&self) -> bool {
+        self.area() < 0.0
+    }
+
+    /// Check if the point is inside of the contour
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        let mut inside = false;
+
+        for ((x1, y1), (x2, y2)) in self.points.iter().zip(self.points.iter().cycle().skip(1)) {
+            if (*y1 > y) != (*y2 > y) && x < x1 + (y - y1) * (x2 - x1) / (y2 - y1) {
+                inside = !inside;
+            }
+        }
+
+        inside
+    }
+
+    /// Reduce the number of points with the Douglas-Peucker algorithm
+    ///
+    /// No removed point is farther away from the simplified outline than `epsilon`.
+    pub fn simplify(&self, epsilon: f32) -> Contour {
+        if self.points.len() < 4 {
+            return self.clone();
+        }
+
+        // Split the closed outline at the point farthest from the first point
+        let first = self.points[0];
+        let (split, _) = self
+            .points
+            .iter()
+            .enumerate()
+            .map(|(i, (x, y))| (i, (x - first.0).hypot(y - first.1)))
+            .fold((0, 0.0), |a, b| if b.1 > a.1 { b } else { a });
+
+        let mut points = douglas_peucker(&self.points[..=split], epsilon);
+        points.pop();
+
+        let mut second = self.points[split..].to_vec();
+        second.push(first);
+        points.extend(douglas_peucker(&second, epsilon));
+        points.pop();
+
+        Contour { points }
+    }
+}
+
+fn line_distance(p: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len = dx.hypot(dy);
+
+    if len == 0.0 {
+        (p.0 - a.0).hypot(p.1 - a.1)
+    } else {
+        (dy * (p.0 - a.0) - dx * (p.1 - a.1)).abs() / len
+    }
+}
+
+/// Simplify an open chain of points with the Douglas-Peucker algorithm
+///
+/// The first and the last point are always kept.
+pub fn douglas_peucker(points: &[(f32, f32)], epsilon: f32) -> Vec<(f32, f32)> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    let mut stack = vec![(0, points.len() - 1)];
+
+    while let Some((start, end)) = stack.pop() {
+        let (index, distance) = (start + 1..end)
+            .map(|i| (i, line_distance(points[i], points[start], points[end])))
+            .fold((start, 0.0), |a, b| if b.1 > a.1 { b } else { a });
+
+        if distance > epsilon {
+            keep[index] = true;
+            stack.push((start, index));
+            stack.push((index, end));
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .map(|(p, _)| *p)
+        .collect()
+}
+
+/// Find the outlines of all regions of bright pixels in a binary image
+///
+/// Pixels with a luma above 0.5 belong to the regions. Diagonally touching pixels are
+/// treated as separate regions. The contours follow the pixel borders exactly and contain only
+/// the corners where the direction changes.
+pub fn find_contours(buffer: &PixelBuffer<Rgb>) -> Vec<Contour> {
+    let width = buffer.width() as i64;
+    let height = buffer.height() as i64;
+
+    let inside = |x: i64, y: i64| {
+        x >= 0
+            && y >= 0
+            && x < width
+            && y < height
+            && srgb_luma(buffer.get_pixel(x as u32, y as u32)) > 0.5
+    };
+
+    // Bit mask of the border edges leaving every pixel corner with the region on the right side
+    let stride = width + 1;
+    let mut edges = vec![0u8; (stride * (height + 1)) as usize];
+    let index = |x: i64, y: i64| (y * stride + x) as usize;
+
+    for y in 0..height {
+        for x in 0..width {
+            if !inside(x, y) {
+                continue;
+            }
+
+            if !inside(x, y - 1) {
+                edges[index(x, y)] |= 1 << 0;
+            }
+            if !inside(x + 1, y) {
+                edges[index(x + 1, y)] |= 1 << 1;
+            }
+            if !inside(x, y + 1) {
+                edges[index(x + 1, y + 1)] |= 1 << 2;
+            }
+            if !inside(x - 1, y) {
+                edges[index(x, y + 1)] |= 1 << 3;
+            }
+        }
+    }
+
+    let mut contours = vec![];
+
+    for start_y in 0..=height {
+        for start_x in 0..=width {
+            while edges[index(start_x, start_y)] != 0 {
+                let start_dir = edges[index(start_x, start_y)].trailing_zeros() as usize;
+
+                let mut points = vec![];
+                let (mut x, mut y) = (start_x, start_y);
+                let mut dir = start_dir;
+
+                loop {
+                    edges[index(x, y)] &= !(1 << dir);
+                    x += DIRECTIONS[dir].0;
+                    y += DIRECTIONS[dir].1;
+
+                    if x == start_x && y == start_y {
+                        if dir != start_dir {
+                            // Start with the first corner to keep the order of the scan
+                            points.insert(0, (x as f32, y as f32));
+                        }
+                        break;
+                    }
+
+                    // Turning right first keeps diagonally touching pixels apart
+                    let mask = edges[index(x, y)];
+                    let next = [(dir + 1) % 4, dir, (dir + 3) % 4]
+                        .into_iter()
+                        .find(|d| mask & (1 << d) != 0);
+
+                    let Some(next) = next else {
+                        break;
+                    };
+
+                    if next != dir {
+                        points.push((x as f32, y as f32));
+                    }
+                    dir = next;
+                }
+
+                contours.push(Contour { points });
+            }
+        }
+    }
+
+    contours
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binary(width: u32, height: u32, pixels: &[(u32, u32)]) -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(width, height, |x, y| {
+            if pixels.contains(&(x, y)) {
+                Rgb::WHITE
+            } else {
+                Rgb::BLACK
+            }
+        })
+    }
+
+    #[test]
+    fn test_single_pixel() {
+        let contours = find_contours(&binary(3, 3, &[(1, 1)]));
+
+        assert_eq!(contours.len(), 1);
+        assert_eq!(
+            contours[0].points,
+            vec![(1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0)]
+        );
+        assert_eq!(contours[0].area(), 1.0);
+        assert!(contours[0].contains(1.5, 1.5));
+        assert!(!contours[0].contains(0.5, 1.5));
+    }
+
+    #[test]
+    fn test_hole() {
+        let buffer = PixelBuffer::new_from_func(5, 5, |x, y| {
+            if x == 2 && y == 2 {
+                Rgb::BLACK
+            } else {
+                Rgb::WHITE
+            }
+        });
+
+        let contours = find_contours(&buffer);
+        assert_eq!(contours.len(), 2);
+
+        assert_eq!(contours[0].area(), 25.0);
+        assert!(!contours[0].is_hole());
+        assert_eq!(contours[0].points.len(), 4);
+
+        assert_eq!(contours[1].area(), -1.0);
+        assert!(contours[1].is_hole());
+    }
+
+    #[test]
+    fn test_diagonal_pixels() {
+        let contours = find_contours(&binary(2, 2, &[(0, 0), (1, 1)]));
+
+        assert_eq!(contours.len(), 2);
+        assert!(contours.iter().all(|c| c.area() == 1.0));
+    }
+
+    #[test]
+    fn test_l_shape() {
+        let contours = find_contours(&binary(3, 3, &[(0, 0), (0, 1), (1, 1)]));
+
+        assert_eq!(contours.len(), 1);
+        assert_eq!(contours[0].points.len(), 6);
+        assert_eq!(contours[0].area(), 3.0);
+    }
+
+    #[test]
+    fn test_douglas_peucker() {
+        let points = [
+            (0.0, 0.0),
+            (1.0, 0.1),
+            (2.0, -0.1),
+            (3.0, 5.0),
+            (4.0, 6.0),
+            (5.0, 7.0),
+        ];
+
+        assert_eq!(
+            douglas_peucker(&points, 0.5),
+            vec![(0.0, 0.0), (2.0, -0.1), (3.0, 5.0), (5.0, 7.0)]
+        );
+        assert_eq!(
+            douglas_peucker(&points, 100.0),
+            vec![(0.0, 0.0), (5.0, 7.0)]
+        );
+        assert_eq!(douglas_peucker(&points[..2], 0.5), points[..2].to_vec());
+    }
+
+    #[test]
+    fn test_simplify_contour() {
+        // A disk has a lot of stair steps that get removed
+        let buffer = PixelBuffer::new_from_func(40, 40, |x, y| {
+            if (x as f32 - 19.5).hypot(y as f32 - 19.5) < 15.0 {
+                Rgb::WHITE
+            } else {
+                Rgb::BLACK
+            }
+        });
+
+        let contours = find_contours(&buffer);
+        assert_eq!(contours.len(), 1);
+
+        let simplified = contours[0].simplify(1.0);
+        assert!(simplified.points.len() < contours[0].points.len() / 2);
+        assert!(simplified.points.len() >= 8);
+        assert!((simplified.area() - contours[0].area()).abs() < contours[0].area() * 0.05);
+        assert!(simplified.contains(20.0, 20.0));
+    }
+}
@@ -0,0 +1,244 @@
+use d10_core::color::Rgb;
+use d10_core::errors::ParseEnumError;
+use d10_core::pixelbuffer::PixelBuffer;
+use d10_core::region::Region;
+use std::str::FromStr;
+
+use crate::{crop, resize, FilterMode};
+
+/// Part of an image that is kept when cropping it to a different aspect ratio
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Gravity {
+    Center,
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    /// Keep the crop centered on a region of interest like a face
+    ///
+    /// The region is given in coordinates of the uncropped image.
+    Focus(Region),
+}
+
+impl FromStr for Gravity {
+    type Err = ParseEnumError;
+
+    fn from_str(value: &str) -> Result<Gravity, Self::Err> {
+        use Gravity::*;
+        match value {
+            "center" | "default" => Ok(Center),
+            "top" => Ok(Top),
+            "bottom" => Ok(Bottom),
+            "left" => Ok(Left),
+            "right" => Ok(Right),
+            "top_left" => Ok(TopLeft),
+            "top_right" => Ok(TopRight),
+            "bottom_left" => Ok(BottomLeft),
+            "bottom_right" => Ok(BottomRight),
+            _ => Err(ParseEnumError::new(value, "Gravity")),
+        }
+    }
+}
+
+/// Start of a crop of `size` centered on `center` and clamped to `0..=max_start`
+fn centered_start(center: f32, size: u32, max_start: u32) -> u32 {
+    ((center - size as f32 / 2.0).round().max(0.0) as u32).min(max_start)
+}
+
+impl Gravity {
+    /// Focus on the bounding box of the given regions, i.e. the result of a `RegionDetector`
+    ///
+    /// Returns `Gravity::Center` if there are no regions.
+    pub fn focus(regions: &[Region]) -> Gravity {
+        let Some(first) = regions.first() else {
+            return Gravity::Center;
+        };
+
+        let (x0, y0, x1, y1) = regions.iter().fold(
+            (
+                first.x,
+                first.y,
+                first.x + first.width,
+                first.y + first.height,
+            ),
+            |(x0, y0, x1, y1), r| {
+                (
+                    x0.min(r.x),
+                    y0.min(r.y),
+                    x1.max(r.x + r.width),
+                    y1.max(r.y + r.height),
+                )
+            },
+        );
+
+        Gravity::Focus(Region::new(x0, y0, x1 - x0, y1 - y0))
+    }
+
+    /// Position of a crop with `crop_width` x `crop_height` in an image of `width` x `height`
+    pub fn crop_region(
+        &self,
+        width: u32,
+        height: u32,
+        crop_width: u32,
+        crop_height: u32,
+    ) -> Region {
+        use Gravity::*;
+
+        let crop_width = crop_width.min(width);
+        let crop_height = crop_height.min(height);
+        let excess_x = width - crop_width;
+        let excess_y = height - crop_height;
+
+        let (x, y) = match self {
+            Focus(region) => (
+                centered_start(
+                    region.x as f32 + region.width as f32 / 2.0,
+                    crop_width,
+                    excess_x,
+                ),
+                centered_start(
+                    region.y as f32 + region.height as f32 / 2.0,
+                    crop_height,
+                    excess_y,
+                ),
+            ),
+            _ => {
+                let x = match self {
+                    Left | TopLeft | BottomLeft => 0,
+                    Right | TopRight | BottomRight => excess_x,
+                    _ => excess_x / 2,
+                };
+
+                let y = match self {
+                    Top | TopLeft | TopRight => 0,
+                    Bottom | BottomLeft | BottomRight => excess_y,
+                    _ => excess_y / 2,
+                };
+
+                (x, y)
+            }
+        };
+
+        Region::new(x, y, crop_width, crop_height)
+    }
+}
+
+/// Size of the largest crop of a `width` x `height` image with the aspect ratio of
+/// `target_width` x `target_height`
+pub(crate) fn aspect_crop_size(
+    width: u32,
+    height: u32,
+    target_width: u32,
+    target_height: u32,
+) -> (u32, u32) {
+    if width == 0 || height == 0 || target_width == 0 || target_height == 0 {
+        return (width, height);
+    }
+
+    if width as u64 * target_height as u64 > height as u64 * target_width as u64 {
+        let crop_width = (height as f32 * target_width as f32 / target_height as f32).round();
+        ((crop_width as u32).clamp(1, width), height)
+    } else {
+        let crop_height = (width as f32 * target_height as f32 / target_width as f32).round();
+        (width, (crop_height as u32).clamp(1, height))
+    }
+}
+
+/// Region of the buffer that is kept by `cover()`
+pub fn cover_region(
+    buffer: &PixelBuffer<Rgb>,
+    width: u32,
+    height: u32,
+    gravity: Gravity,
+) -> Region {
+    let (crop_width, crop_height) =
+        aspect_crop_size(buffer.width(), buffer.height(), width, height);
+    gravity.crop_region(buffer.width(), buffer.height(), crop_width, crop_height)
+}
+
+/// Crop the buffer to the aspect ratio of `width` x `height` and scale it to this size
+///
+/// The `gravity` selects which part of the buffer is kept.
+pub fn cover(
+    buffer: &PixelBuffer<Rgb>,
+    width: u32,
+    height: u32,
+    gravity: Gravity,
+    filter: FilterMode,
+) -> PixelBuffer<Rgb> {
+    if buffer.is_empty() {
+        return buffer.clone();
+    }
+
+    let region = cover_region(buffer, width, height, gravity);
+
+    resize(
+        &crop(buffer, region.x, region.y, region.width, region.height),
+        width,
+        height,
+        filter,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cover() {
+        let buffer = PixelBuffer::new_from_func(4, 2, |x, _| match x {
+            0 => Rgb::RED,
+            3 => Rgb::BLUE,
+            _ => Rgb::GREEN,
+        });
+
+        let res = cover(&buffer, 2, 2, Gravity::Left, FilterMode::Nearest);
+        assert_eq!((res.width(), res.height()), (2, 2));
+        assert_eq!(res.get_pixel(0, 0), &Rgb::RED);
+
+        let res = cover(&buffer, 2, 2, Gravity::BottomRight, FilterMode::Nearest);
+        assert_eq!(res.get_pixel(1, 1), &Rgb::BLUE);
+
+        let res = cover(&buffer, 2, 2, Gravity::Center, FilterMode::Nearest);
+        assert!(res.data().iter().all(|c| *c == Rgb::GREEN));
+
+        let res = cover(&buffer, 8, 2, Gravity::Top, FilterMode::Nearest);
+        assert_eq!((res.width(), res.height()), (8, 2));
+    }
+
+    #[test]
+    fn test_cover_empty() {
+        let buffer = PixelBuffer::new(0, 0);
+
+        assert!(cover(&buffer, 3, 5, Gravity::Center, FilterMode::Nearest).is_empty());
+        assert_eq!(
+            cover_region(&buffer, 3, 5, Gravity::Right),
+            Region::new(0, 0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_focus() {
+        let buffer = PixelBuffer::new_with_color(300, 100, Rgb::RED);
+
+        let focus = Gravity::focus(&[Region::new(200, 10, 20, 20), Region::new(250, 60, 10, 10)]);
+        assert_eq!(focus, Gravity::Focus(Region::new(200, 10, 60, 60)));
+        assert_eq!(
+            cover_region(&buffer, 50, 50, focus),
+            Region::new(180, 0, 100, 100)
+        );
+
+        // The crop stays inside of the image
+        let focus = Gravity::focus(&[Region::new(290, 0, 10, 10)]);
+        assert_eq!(
+            cover_region(&buffer, 50, 50, focus),
+            Region::new(200, 0, 100, 100)
+        );
+
+        assert_eq!(Gravity::focus(&[]), Gravity::Center);
+    }
+}
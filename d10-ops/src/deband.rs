@@ -0,0 +1,207 @@
+use d10_core::color::{Color, Rgb, Srgb};
+use d10_core::pixelbuffer::PixelBuffer;
+
+/// Maximal distance of the pixels used to smooth a band
+const DEBAND_RADIUS: i32 = 16;
+
+/// Largest difference between gamma encoded values that is treated as a band at full strength
+const MAX_THRESHOLD: f32 = 6.0 / 255.0;
+
+/// Largest difference between 8 bit luma levels that counts as a band edge
+const MAX_BAND_STEP: i32 = 2;
+
+const BAYER_8X8: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// Cheap deterministic hash to pick the sample offsets of a pixel
+fn hash(x: u32, y: u32) -> u32 {
+    let mut h = x.wrapping_mul(0x8da6_b343) ^ y.wrapping_mul(0xd816_3841);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 12;
+    h
+}
+
+fn sample_offset(x: u32, y: u32, radius: i32) -> (i32, i32) {
+    let h = hash(x, y);
+    let range = (radius * 2 + 1) as u32;
+
+    let dx = (h & 0xffff) % range;
+    let dy = (h >> 16) % range;
+
+    (dx as i32 - radius, dy as i32 - radius)
+}
+
+/// Smooth posterization banding in gradients
+///
+/// Every pixel is compared to four pixels at random offsets that are mirrored around it.
+/// If all of them differ by less than a threshold from the pixel it lies in a flat area with
+/// bands and gets replaced by their average, while edges and details stay untouched.
+///
+/// `strength` ranges from 0 to 1 and controls the threshold.
+/// With `dither` an ordered dither pattern of one 8 bit step gets added to the smoothed pixels,
+/// so that the gradients don't band again when they get saved with 8 bits per channel.
+pub fn deband(buffer: &PixelBuffer<Rgb>, strength: f32, dither: bool) -> PixelBuffer<Rgb> {
+    let threshold = strength.clamp(0.0, 1.0) * MAX_THRESHOLD;
+
+    if threshold <= 0.0 || buffer.is_empty() {
+        return buffer.clone();
+    }
+
+    let srgb = buffer.map_colors(|c| c.to_srgb());
+
+    let radius = DEBAND_RADIUS
+        .min(buffer.width() as i32 - 1)
+        .min(buffer.height() as i32 - 1)
+        .max(1);
+
+    PixelBuffer::new_from_func(buffer.width(), buffer.height(), |x, y| {
+        let c = srgb.get_pixel(x, y);
+        let (dx, dy) = sample_offset(x, y, radius);
+
+        let samples = [
+            srgb.get_pixel_clamped(x as i32 + dx, y as i32 + dy),
+            srgb.get_pixel_clamped(x as i32 - dx, y as i32 - dy),
+            srgb.get_pixel_clamped(x as i32 - dy, y as i32 + dx),
+            srgb.get_pixel_clamped(x as i32 + dy, y as i32 - dx),
+        ];
+
+        let flat = samples
+            .iter()
+            .all(|s| (0..3).all(|i| (s.data[i] - c.data[i]).abs() < threshold));
+
+        let mut data = c.data;
+
+        if flat {
+            for (i, value) in data.iter_mut().enumerate().take(3) {
+                *value = samples.iter().map(|s| s.data[i]).sum::<f32>() / 4.0;
+            }
+
+            if dither {
+                let offset = (BAYER_8X8[y as usize % 8][x as usize % 8] as f32 + 0.5) / 64.0 - 0.5;
+
+                for value in data.iter_mut().take(3) {
+                    *value += offset / 255.0;
+                }
+            }
+        }
+
+        Srgb::new_with_alpha(data[0], data[1], data[2], data[3]).to_rgb()
+    })
+}
+
+/// Estimate how strong an image suffers from banding
+///
+/// Counts the steps between 8 bit luma levels of neighboring pixels. A step that is small and
+/// separates two flat areas is a band edge, while all other changes come from details or noise.
+/// The result is the fraction of band edges from 0 (no banding) to 1 (all changes are bands).
+pub fn banding_score(buffer: &PixelBuffer<Rgb>) -> f32 {
+    let width = buffer.width() as usize;
+    let height = buffer.height() as usize;
+
+    let levels: Vec<i32> = buffer
+        .data()
+        .iter()
+        .map(|c| {
+            let c = c.to_srgb();
+            let luma = c.data[0] * 0.212_656 + c.data[1] * 0.715_158 + c.data[2] * 0.072_186;
+            (luma.clamp(0.0, 1.0) * 255.0).round() as i32
+        })
+        .collect();
+
+    let mut bands = 0u64;
+    let mut changes = 0u64;
+
+    let mut count = |before: i32, a: i32, b: i32, after: i32| {
+        if a == b {
+            return;
+        }
+
+        changes += 1;
+
+        if (a - b).abs() <= MAX_BAND_STEP && before == a && b == after {
+            bands += 1;
+        }
+    };
+
+    for y in 0..height {
+        let row = &levels[y * width..(y + 1) * width];
+
+        for x in 1..width.saturating_sub(2) {
+            count(row[x - 1], row[x], row[x + 1], row[x + 2]);
+        }
+    }
+
+    for y in 1..height.saturating_sub(2) {
+        for x in 0..width {
+            let level = |y: usize| levels[y * width + x];
+            count(level(y - 1), level(y), level(y + 1), level(y + 2));
+        }
+    }
+
+    if changes == 0 {
+        0.0
+    } else {
+        bands as f32 / changes as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Horizontal gray gradient with only 8 levels
+    fn banded_gradient() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(128, 32, |x, _| {
+            let v = 0.4 + (x / 16) as f32 / 255.0;
+            Srgb::new(v, v, v).to_rgb()
+        })
+    }
+
+    #[test]
+    fn test_banding_score() {
+        let banded = banded_gradient();
+        assert!(banding_score(&banded) > 0.9);
+
+        let smooth = PixelBuffer::new_from_func(128, 32, |x, y| {
+            let v = (x as f32 / 127.0 + (x * 7 + y * 13) as f32 % 5.0 / 100.0).min(1.0);
+            Srgb::new(v, v, v).to_rgb()
+        });
+        assert!(banding_score(&smooth) < 0.1);
+
+        assert_eq!(banding_score(&PixelBuffer::new(4, 4)), 0.0);
+    }
+
+    #[test]
+    fn test_deband() {
+        let banded = banded_gradient();
+
+        let result = deband(&banded, 1.0, false);
+        assert!(banding_score(&result) < banding_score(&banded));
+
+        // The values between the bands are interpolated
+        let center = result.get_pixel(16, 16).to_srgb();
+        assert!(center.data[0] > 0.4 + 0.1 / 255.0 && center.data[0] < 0.4 + 0.9 / 255.0);
+
+        let dithered = deband(&banded, 1.0, true);
+        assert_ne!(dithered.data(), result.data());
+
+        assert_eq!(deband(&banded, 0.0, true).data(), banded.data());
+    }
+
+    #[test]
+    fn test_deband_keeps_edges() {
+        let buffer =
+            PixelBuffer::new_from_func(32, 32, |x, _| if x < 16 { Rgb::BLACK } else { Rgb::WHITE });
+
+        assert_eq!(deband(&buffer, 1.0, false).data(), buffer.data());
+    }
+}
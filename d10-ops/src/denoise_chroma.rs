@@ -0,0 +1,124 @@
+use d10_core::color::illuminant::D65;
+use d10_core::color::observer::O2;
+use d10_core::color::{Color, Lab, Rgb};
+use d10_core::pixelbuffer::PixelBuffer;
+
+fn gaussian_weights(sigma: f32) -> Vec<f32> {
+    let radius = (sigma * 3.0).ceil() as i32;
+
+    let weights: Vec<f32> = (-radius..=radius)
+        .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+
+    let sum: f32 = weights.iter().sum();
+    weights.into_iter().map(|w| w / sum).collect()
+}
+
+/// Blur a single channel with a separable kernel and clamped borders
+fn blur_channel(values: &[f32], width: usize, height: usize, weights: &[f32]) -> Vec<f32> {
+    let radius = (weights.len() / 2) as i64;
+    let mut tmp = vec![0.0; values.len()];
+
+    for y in 0..height {
+        for x in 0..width {
+            tmp[y * width + x] = weights
+                .iter()
+                .enumerate()
+                .map(|(i, w)| {
+                    let sx = (x as i64 + i as i64 - radius).clamp(0, width as i64 - 1) as usize;
+                    values[y * width + sx] * w
+                })
+                .sum();
+        }
+    }
+
+    let mut result = vec![0.0; values.len()];
+
+    for y in 0..height {
+        for x in 0..width {
+            result[y * width + x] = weights
+                .iter()
+                .enumerate()
+                .map(|(i, w)| {
+                    let sy = (y as i64 + i as i64 - radius).clamp(0, height as i64 - 1) as usize;
+                    tmp[sy * width + x] * w
+                })
+                .sum();
+        }
+    }
+
+    result
+}
+
+/// Reduce color noise without touching the luminance
+///
+/// The image gets converted into the Lab color space and only the a and b channels are blurred
+/// with a gaussian kernel. This removes the color blotches of high ISO images while the
+/// lightness, which holds most of the visible detail, stays as sharp as before.
+///
+/// `strength` is the sigma of the blur in pixels.
+pub fn denoise_chroma(buffer: &PixelBuffer<Rgb>, strength: f32) -> PixelBuffer<Rgb> {
+    if strength <= 0.0 || buffer.is_empty() {
+        return buffer.clone();
+    }
+
+    let width = buffer.width() as usize;
+    let height = buffer.height() as usize;
+
+    let lab: Vec<Lab<D65, O2>> = buffer.data().iter().map(|c| c.to_lab()).collect();
+
+    let weights = gaussian_weights(strength);
+
+    let a: Vec<f32> = lab.iter().map(|c| c.a()).collect();
+    let b: Vec<f32> = lab.iter().map(|c| c.b()).collect();
+
+    let a = blur_channel(&a, width, height, &weights);
+    let b = blur_channel(&b, width, height, &weights);
+
+    let data = lab
+        .iter()
+        .zip(a.iter().zip(b.iter()))
+        .map(|(c, (a, b))| Lab::<D65, O2>::new_with_alpha(c.l(), *a, *b, c.alpha()).to_rgb())
+        .collect();
+
+    PixelBuffer::new_from_raw(buffer.width(), buffer.height(), data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_denoise_chroma() {
+        let buffer = PixelBuffer::new_from_func(16, 16, |x, y| {
+            if (x * 7 + y * 3) % 5 == 0 {
+                Rgb::new(0.5, 0.2, 0.2)
+            } else {
+                Rgb::new(0.2, 0.3, 0.5)
+            }
+        });
+
+        let result = denoise_chroma(&buffer, 2.0);
+
+        let a_range = |buffer: &PixelBuffer<Rgb>| {
+            let a: Vec<f32> = buffer
+                .data()
+                .iter()
+                .map(|c| c.to_lab::<D65, O2>().a())
+                .collect();
+
+            a.iter().cloned().fold(f32::MIN, f32::max) - a.iter().cloned().fold(f32::MAX, f32::min)
+        };
+
+        assert!(a_range(&result) < a_range(&buffer) / 4.0);
+
+        // The lightness is kept
+        for (c1, c2) in buffer.data().iter().zip(result.data()) {
+            let l1 = c1.to_lab::<D65, O2>().l();
+            let l2 = c2.to_lab::<D65, O2>().l();
+            assert!((l1 - l2).abs() < 0.5);
+        }
+
+        assert_eq!(denoise_chroma(&buffer, 0.0).data(), buffer.data());
+    }
+}
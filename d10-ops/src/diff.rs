@@ -0,0 +1,266 @@
+use d10_core::color::illuminant::D65;
+use d10_core::color::observer::O2;
+use d10_core::color::{Color, Lab, Rgb};
+use d10_core::errors::ParseEnumError;
+use d10_core::pixelbuffer::PixelBuffer;
+use d10_core::region::Region;
+use std::str::FromStr;
+
+use crate::{compose, draw_text, montage, text_size};
+
+/// Threshold of a just noticeable color difference
+pub const DEFAULT_DIFF_THRESHOLD: f32 = 2.3;
+
+/// Difference at which the heatmap reaches its strongest color
+const MAX_DELTA_E: f32 = 50.0;
+
+/// Space around the panels and labels of the side by side view
+const SPACING: u32 = 4;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DiffMode {
+    /// Faded version of the first image with differing pixels colored from yellow to red
+    Heatmap,
+    /// Absolute difference of the color channels stretched to the full range
+    Channels,
+    /// Both images and the heatmap next to each other with labels
+    SideBySide,
+}
+
+impl FromStr for DiffMode {
+    type Err = ParseEnumError;
+
+    fn from_str(value: &str) -> Result<DiffMode, Self::Err> {
+        match value {
+            "heatmap" | "default" => Ok(DiffMode::Heatmap),
+            "channels" => Ok(DiffMode::Channels),
+            "side_by_side" => Ok(DiffMode::SideBySide),
+            _ => Err(ParseEnumError::new(value, "DiffMode")),
+        }
+    }
+}
+
+/// CIE76 color difference in the usual 0 to 100 scale
+///
+/// Differences in the alpha channel are added as a fourth dimension.
+pub fn delta_e(c1: &Rgb, c2: &Rgb) -> f32 {
+    let lab1: Lab<D65, O2> = c1.to_lab();
+    let lab2: Lab<D65, O2> = c2.to_lab();
+
+    let dl = (lab1.l() - lab2.l()) * 100.0;
+    let da = (lab1.a() - lab2.a()) * 128.0;
+    let db = (lab1.b() - lab2.b()) * 128.0;
+    let dalpha = (c1.alpha() - c2.alpha()) * 100.0;
+
+    (dl * dl + da * da + db * db + dalpha * dalpha).sqrt()
+}
+
+/// Per pixel color difference of two buffers
+///
+/// Pixels outside of one of the buffers are treated as transparent.
+struct DeltaMap {
+    width: u32,
+    height: u32,
+    data: Vec<f32>,
+}
+
+impl DeltaMap {
+    fn new(a: &PixelBuffer<Rgb>, b: &PixelBuffer<Rgb>) -> DeltaMap {
+        let width = a.width().max(b.width());
+        let height = a.height().max(b.height());
+
+        let mut data = Vec::with_capacity(width as usize * height as usize);
+
+        for y in 0..height as i32 {
+            for x in 0..width as i32 {
+                let c1 = a.get_pixel_optional(x, y).unwrap_or(&Rgb::NONE);
+                let c2 = b.get_pixel_optional(x, y).unwrap_or(&Rgb::NONE);
+                data.push(delta_e(c1, c2));
+            }
+        }
+
+        DeltaMap {
+            width,
+            height,
+            data,
+        }
+    }
+
+    fn get(&self, x: u32, y: u32) -> f32 {
+        self.data[(y * self.width + x) as usize]
+    }
+}
+
+fn heatmap(a: &PixelBuffer<Rgb>, diff: &DeltaMap, threshold: f32) -> PixelBuffer<Rgb> {
+    PixelBuffer::new_from_func(diff.width, diff.height, |x, y| {
+        let de = diff.get(x, y);
+
+        if de <= threshold {
+            let c = a
+                .get_pixel_optional(x as i32, y as i32)
+                .unwrap_or(&Rgb::NONE);
+            let v = 0.7 + c.to_gray().red() * c.alpha() * 0.3;
+            Rgb::new(v, v, v)
+        } else {
+            let t =
+                ((de - threshold) / (MAX_DELTA_E - threshold).max(f32::EPSILON)).clamp(0.0, 1.0);
+            Rgb::new(1.0, 1.0 - t, 0.0)
+        }
+    })
+}
+
+fn channels(
+    a: &PixelBuffer<Rgb>,
+    b: &PixelBuffer<Rgb>,
+    diff: &DeltaMap,
+    threshold: f32,
+) -> PixelBuffer<Rgb> {
+    let mut out = compose([a, b], Rgb::NONE, |x, y, [c1, c2]| {
+        if diff.get(x, y) <= threshold {
+            Rgb::BLACK
+        } else {
+            Rgb::new(
+                (c1.red() - c2.red()).abs(),
+                (c1.green() - c2.green()).abs(),
+                (c1.blue() - c2.blue()).abs(),
+            )
+        }
+    });
+
+    let max = out
+        .data()
+        .iter()
+        .map(|c| c.red().max(c.green()).max(c.blue()))
+        .fold(0.0, f32::max);
+
+    if max > 0.0 {
+        out.mod_colors(|c| Rgb::new(c.red() / max, c.green() / max, c.blue() / max));
+    }
+
+    out
+}
+
+/// Add a label above a panel
+fn labeled(panel: &PixelBuffer<Rgb>, label: &str) -> PixelBuffer<Rgb> {
+    let label_height = text_size(label, 1).1 + SPACING;
+
+    let mut out =
+        PixelBuffer::new_with_color(panel.width(), panel.height() + label_height, Rgb::WHITE);
+
+    out.copy_from(
+        panel,
+        Region::new(0, 0, panel.width(), panel.height()),
+        (0, label_height),
+    );
+    draw_text(&mut out, 0, 0, label, Rgb::BLACK, 1);
+
+    out
+}
+
+/// Visualize the differences between two images
+///
+/// `threshold` is the minimal CIE76 color difference (0 to 100) for a pixel to count as changed.
+/// `DEFAULT_DIFF_THRESHOLD` corresponds to a just noticeable difference.
+/// Images of different sizes are compared on the larger size with missing pixels being transparent.
+pub fn diff_visualize(
+    a: &PixelBuffer<Rgb>,
+    b: &PixelBuffer<Rgb>,
+    mode: DiffMode,
+    threshold: f32,
+) -> PixelBuffer<Rgb> {
+    let diff = DeltaMap::new(a, b);
+
+    match mode {
+        DiffMode::Heatmap => heatmap(a, &diff, threshold),
+        DiffMode::Channels => channels(a, b, &diff, threshold),
+        DiffMode::SideBySide => {
+            let changed = diff.data.iter().filter(|de| **de > threshold).count();
+
+            let panels = [
+                labeled(a, "A"),
+                labeled(b, "B"),
+                labeled(
+                    &heatmap(a, &diff, threshold),
+                    &format!("Diff: {} px", changed),
+                ),
+            ];
+
+            let panels: Vec<&PixelBuffer<Rgb>> = panels.iter().collect();
+            montage(&panels, 3, SPACING, Rgb::WHITE)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delta_e() {
+        assert_eq!(delta_e(&Rgb::RED, &Rgb::RED), 0.0);
+        assert!((delta_e(&Rgb::BLACK, &Rgb::WHITE) - 100.0).abs() < 0.1);
+        assert!(
+            (delta_e(&Rgb::NONE, &Rgb::new_with_alpha(0.0, 0.0, 0.0, 1.0)) - 100.0).abs() < 0.1
+        );
+    }
+
+    #[test]
+    fn test_heatmap() {
+        let a = PixelBuffer::new_with_color(3, 2, Rgb::BLUE);
+        let mut b = a.clone();
+        b.put_pixel(1, 1, Rgb::new(0.0, 0.0, 0.99));
+        b.put_pixel(2, 0, Rgb::RED);
+
+        let res = diff_visualize(&a, &b, DiffMode::Heatmap, DEFAULT_DIFF_THRESHOLD);
+
+        assert_eq!((res.width(), res.height()), (3, 2));
+
+        let unchanged = res.get_pixel(0, 0);
+        assert_eq!(unchanged.red(), unchanged.blue());
+        assert_eq!(res.get_pixel(1, 1), unchanged);
+
+        let changed = res.get_pixel(2, 0);
+        assert_eq!(changed.red(), 1.0);
+        assert_eq!(changed.blue(), 0.0);
+    }
+
+    #[test]
+    fn test_channels() {
+        let a = PixelBuffer::new_with_color(2, 1, Rgb::new(0.5, 0.5, 0.5));
+        let mut b = a.clone();
+        b.put_pixel(1, 0, Rgb::new(0.5, 0.75, 0.5));
+
+        let res = diff_visualize(&a, &b, DiffMode::Channels, DEFAULT_DIFF_THRESHOLD);
+
+        assert_eq!(res.get_pixel(0, 0), &Rgb::BLACK);
+        assert_eq!(res.get_pixel(1, 0), &Rgb::GREEN);
+    }
+
+    #[test]
+    fn test_side_by_side() {
+        let a = PixelBuffer::new_with_color(20, 10, Rgb::BLUE);
+        let b = PixelBuffer::new_with_color(10, 10, Rgb::BLUE);
+
+        let res = diff_visualize(&a, &b, DiffMode::SideBySide, DEFAULT_DIFF_THRESHOLD);
+
+        let label_height = 7 + SPACING;
+        assert_eq!(res.width(), 3 * 20 + 4 * SPACING);
+        assert_eq!(res.height(), 10 + label_height + 2 * SPACING);
+
+        // The missing half of the second image shows up in the heatmap
+        let heatmap_x = 2 * 20 + 3 * SPACING;
+        let y = SPACING + label_height;
+        assert_eq!(res.get_pixel(heatmap_x + 15, y).green(), 0.0);
+        assert!(res.get_pixel(heatmap_x + 5, y).green() > 0.5);
+    }
+
+    #[test]
+    fn test_diff_mode_from_str() {
+        assert_eq!("default".parse::<DiffMode>().unwrap(), DiffMode::Heatmap);
+        assert_eq!(
+            "side_by_side".parse::<DiffMode>().unwrap(),
+            DiffMode::SideBySide
+        );
+        assert!("other".parse::<DiffMode>().is_err());
+    }
+}
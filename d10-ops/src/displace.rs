@@ -0,0 +1,92 @@
+use d10_core::color::{Color, Rgb};
+use d10_core::pixelbuffer::PixelBuffer;
+
+use crate::filters::{get_pixel_bilinear, get_pixel_filtered};
+use crate::FilterMode;
+
+/// Read a gamma encoded channel of a displacement map that gets stretched to the image size
+fn map_value(
+    map: &PixelBuffer<Rgb>,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    channel: usize,
+) -> f32 {
+    let c = if map.width() == width && map.height() == height {
+        *map.get_pixel(x, y)
+    } else {
+        let mx = (x as f32 + 0.5) * map.width() as f32 / width as f32 - 0.5;
+        let my = (y as f32 + 0.5) * map.height() as f32 / height as f32 - 0.5;
+        get_pixel_bilinear(map, mx, my)
+    };
+
+    c.to_srgb().data[channel]
+}
+
+/// Warp an image by moving every pixel by the amount read from displacement maps
+///
+/// The horizontal offset is taken from the red channel of `dx_map` and the vertical offset from
+/// the green channel of `dy_map`, so a gray map can be used for both directions as well as a
+/// single colored map. Like in ImageMagick a value of 0.5 (50% gray) doesn't move the pixel,
+/// while white moves it by `scale` pixels and black by `-scale` pixels.
+///
+/// Maps with a different size than the image get stretched to cover the whole image.
+pub fn displace(
+    buffer: &PixelBuffer<Rgb>,
+    dx_map: &PixelBuffer<Rgb>,
+    dy_map: &PixelBuffer<Rgb>,
+    scale: f32,
+    filter: FilterMode,
+) -> PixelBuffer<Rgb> {
+    if buffer.is_empty() || dx_map.is_empty() || dy_map.is_empty() {
+        return buffer.clone();
+    }
+
+    let width = buffer.width();
+    let height = buffer.height();
+
+    PixelBuffer::new_from_func(width, height, |x, y| {
+        let dx = (map_value(dx_map, x, y, width, height, 0) * 2.0 - 1.0) * scale;
+        let dy = (map_value(dy_map, x, y, width, height, 1) * 2.0 - 1.0) * scale;
+
+        get_pixel_filtered(buffer, x as f32 + dx, y as f32 + dy, filter)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use d10_core::color::Srgb;
+
+    fn gradient() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(8, 4, |x, y| Rgb::new(x as f32 / 7.0, y as f32 / 3.0, 0.0))
+    }
+
+    #[test]
+    fn test_neutral_map() {
+        let buffer = gradient();
+        let neutral = PixelBuffer::new_with_color(1, 1, Srgb::new(0.5, 0.5, 0.5).to_rgb());
+
+        let result = displace(&buffer, &neutral, &neutral, 10.0, FilterMode::Nearest);
+
+        assert_eq!(result.data(), buffer.data());
+    }
+
+    #[test]
+    fn test_displace() {
+        let buffer = gradient();
+
+        let right = PixelBuffer::new_with_color(8, 4, Rgb::WHITE);
+        let neutral = PixelBuffer::new_with_color(8, 4, Srgb::new(0.5, 0.5, 0.5).to_rgb());
+
+        let result = displace(&buffer, &right, &neutral, 2.0, FilterMode::Nearest);
+        assert_eq!(result.get_pixel(1, 2), buffer.get_pixel(3, 2));
+        assert_eq!(result.get_pixel(7, 1), buffer.get_pixel(7, 1));
+
+        let up = PixelBuffer::new_with_color(8, 4, Rgb::BLACK);
+
+        let result = displace(&buffer, &neutral, &up, 1.0, FilterMode::Bilinear);
+        assert_eq!(result.get_pixel(3, 2), buffer.get_pixel(3, 1));
+    }
+}
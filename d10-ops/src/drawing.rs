@@ -61,9 +61,7 @@ pub fn drawing(buffer: &PixelBuffer<Rgb>, radius: u32, mode: DrawingMode) -> Pix
             }
         }
 
-        let c = |v1: f32, v2: f32| {
-            (v1 / (1.0 - v2 + 0.01).min(1.0)).abs()
-        };
+        let c = |v1: f32, v2: f32| (v1 / (1.0 - v2 + 0.01).min(1.0)).abs();
 
         let diff = v1
             .iter()
@@ -0,0 +1,20 @@
+use d10_core::color::{Color, Rgb, Srgb};
+use d10_core::pixelbuffer::PixelBuffer;
+
+/// Map the tones of the image to a gradient between two colors
+pub fn duotone(buffer: &PixelBuffer<Rgb>, dark_color: Rgb, light_color: Rgb) -> PixelBuffer<Rgb> {
+    let dark = dark_color.to_srgb();
+    let light = light_color.to_srgb();
+
+    buffer.map_colors(|c| {
+        let t = c.to_gray().to_srgb().red();
+
+        Srgb::new_with_alpha(
+            dark.red() + (light.red() - dark.red()) * t,
+            dark.green() + (light.green() - dark.green()) * t,
+            dark.blue() + (light.blue() - dark.blue()) * t,
+            c.alpha(),
+        )
+        .to_rgb()
+    })
+}
@@ -23,7 +23,10 @@ impl FromStr for EdgeDetection {
     }
 }
 
-fn edge_detection_sobel(buffer: &PixelBuffer<Rgb>) -> PixelBuffer<Rgb> {
+/// Horizontal and vertical sobel gradients of every channel
+///
+/// The values are positive if the channel decreases to the right or to the bottom.
+pub(crate) fn sobel_gradients(buffer: &PixelBuffer<Rgb>) -> (PixelBuffer<Rgb>, PixelBuffer<Rgb>) {
     let buffer_x = buffer.apply_kernel(&Kernel::new([
         [1.0, 0.0, -1.0],
         [2.0, 0.0, -2.0],
@@ -36,6 +39,12 @@ fn edge_detection_sobel(buffer: &PixelBuffer<Rgb>) -> PixelBuffer<Rgb> {
         [-1.0, -2.0, -1.0],
     ]));
 
+    (buffer_x, buffer_y)
+}
+
+fn edge_detection_sobel(buffer: &PixelBuffer<Rgb>) -> PixelBuffer<Rgb> {
+    let (buffer_x, buffer_y) = sobel_gradients(buffer);
+
     compose([&buffer_x, &buffer_y], Rgb::BLACK, |_, _, [img1, img2]| {
         let r = (img1.red() * img1.red() + img2.red() * img2.red()).sqrt();
         let g = (img1.green() * img1.green() + img2.green() * img2.green()).sqrt();
@@ -55,9 +64,14 @@ fn edge_detection_laplace(buffer: &PixelBuffer<Rgb>) -> PixelBuffer<Rgb> {
         .map_colors(|c| c.with_alpha(1.0))
 }
 
+/// Detect edges with a sobel or laplace kernel
+///
+/// NaN and infinite values are replaced with `PixelBuffer::sanitize()` before detecting edges.
 pub fn edge_detection(buffer: &PixelBuffer<Rgb>, mode: EdgeDetection) -> PixelBuffer<Rgb> {
+    let buffer = buffer.sanitized();
+
     match mode {
-        EdgeDetection::Sobel => edge_detection_sobel(buffer),
-        EdgeDetection::Laplace => edge_detection_laplace(buffer),
+        EdgeDetection::Sobel => edge_detection_sobel(&buffer),
+        EdgeDetection::Laplace => edge_detection_laplace(&buffer),
     }
 }
@@ -5,13 +5,19 @@ use d10_core::pixelbuffer::PixelBuffer;
 use std::f32::consts::PI;
 use std::str::FromStr;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum FilterMode {
     Nearest,
     Bilinear,
     Bicubic,
     Lanczos3,
     Auto,
+    /// Nearest neighbor that repeats every pixel equally often for integer scale factors
+    PixelArt,
+    /// Integer upscaling with nearest neighbor followed by bilinear scaling of the remainder
+    ///
+    /// Keeps text and pixel art crisp at non integer scale factors with a one pixel wide blend.
+    SharpBilinear,
 }
 
 impl FromStr for FilterMode {
@@ -25,6 +31,8 @@ impl FromStr for FilterMode {
             "bicubic" => Ok(Bicubic),
             "lanczos3" | "Lanczos" => Ok(Lanczos3),
             "default" | "auto" => Ok(Auto),
+            "pixel_art" => Ok(PixelArt),
+            "sharp_bilinear" => Ok(SharpBilinear),
             _ => Err(ParseEnumError::new(value, "FilterMode")),
         }
     }
@@ -147,6 +155,23 @@ pub fn get_pixel_lanczos3(buffer: &PixelBuffer<Rgb>, x: f32, y: f32) -> Rgb {
     get_pixel_lanczos::<7>(buffer, x, y)
 }
 
+/// Get the pixel at the given position applying the given filter
+pub(crate) fn get_pixel_filtered(
+    buffer: &PixelBuffer<Rgb>,
+    x: f32,
+    y: f32,
+    filter: FilterMode,
+) -> Rgb {
+    match filter {
+        FilterMode::Nearest | FilterMode::PixelArt => {
+            *buffer.get_pixel_clamped(x.round() as i32, y.round() as i32)
+        }
+        FilterMode::Bilinear | FilterMode::SharpBilinear => get_pixel_bilinear(buffer, x, y),
+        FilterMode::Bicubic | FilterMode::Auto => get_pixel_bicubic(buffer, x, y),
+        FilterMode::Lanczos3 => get_pixel_lanczos3(buffer, x, y),
+    }
+}
+
 /// Get the pixel at the given position applying a lanczos filter with a window of N
 // Silence clippy because this would result in a mixture of range and non range loops...
 #[allow(clippy::needless_range_loop)]
@@ -3,6 +3,9 @@ use d10_core::kernel::Kernel;
 use d10_core::kernel_dyn::KernelDyn;
 use d10_core::pixelbuffer::PixelBuffer;
 
+/// Blur with a gaussian kernel
+///
+/// NaN and infinite values are replaced with `PixelBuffer::sanitize()` before blurring.
 pub fn gaussian_blur(
     buffer: &PixelBuffer<Rgb>,
     radius: u32,
@@ -14,6 +17,8 @@ pub fn gaussian_blur(
      * this only implements 1 to 3 as the most common radii that are expected
      */
 
+    let buffer = buffer.sanitized();
+
     let kernel_size = radius * 2 + 1;
     let sigma = sigma.unwrap_or_else(|| get_default_sigma(kernel_size));
 
@@ -25,6 +30,124 @@ pub fn gaussian_blur(
     }
 }
 
+/// Blur with a gaussian kernel without allocating a second buffer
+///
+/// The kernel is applied to the rows and columns separately, so only a copy of a single row or
+/// column is needed. The result matches `gaussian_blur()` except for rounding errors.
+pub fn gaussian_blur_in_place(buffer: &mut PixelBuffer<Rgb>, radius: u32, sigma: Option<f32>) {
+    if radius == 0 || buffer.is_empty() {
+        return;
+    }
+
+    buffer.sanitize();
+
+    let sigma = sigma.unwrap_or_else(|| get_default_sigma(radius * 2 + 1));
+    let weights = get_weights(radius, sigma);
+
+    let width = buffer.width() as usize;
+    let height = buffer.height() as usize;
+    let mut line = Vec::with_capacity(width.max(height));
+
+    for row in buffer.rows_mut() {
+        line.clear();
+        line.extend_from_slice(row);
+
+        for (x, c) in row.iter_mut().enumerate() {
+            *c = convolve_line(&line, x, &weights);
+        }
+    }
+
+    let data = buffer.data_mut();
+
+    for x in 0..width {
+        line.clear();
+        line.extend((0..height).map(|y| data[y * width + x]));
+
+        for y in 0..height {
+            data[y * width + x] = convolve_line(&line, y, &weights);
+        }
+    }
+}
+
+/// Normalized weights of a one dimensional gaussian kernel
+fn get_weights(radius: u32, sigma: f32) -> Vec<f32> {
+    let s = 2.0 * sigma * sigma;
+    let radius = radius as i32;
+
+    let weights: Vec<f32> = (-radius..=radius)
+        .map(|x| (-((x * x) as f32) / s).exp())
+        .collect();
+    let sum: f32 = weights.iter().sum();
+
+    weights.into_iter().map(|w| w / sum).collect()
+}
+
+fn convolve_line(line: &[Rgb], pos: usize, weights: &[f32]) -> Rgb {
+    let radius = (weights.len() / 2) as isize;
+    let last = line.len() as isize - 1;
+
+    let mut data = [0.0; 4];
+
+    for (i, weight) in weights.iter().enumerate() {
+        let index = (pos as isize + i as isize - radius).clamp(0, last) as usize;
+
+        for (value, color_value) in data.iter_mut().zip(line[index].data.iter()) {
+            *value += color_value * weight;
+        }
+    }
+
+    Rgb { data }
+}
+
 pub(crate) fn get_default_sigma(kernel_size: u32) -> f32 {
     (kernel_size as f32 - 1.0) / 4.0
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_non_finite() {
+        let mut buffer = PixelBuffer::new_with_color(21, 21, Rgb::new(0.5, 0.5, 0.5));
+        buffer.data_mut()[220].data = [f32::NAN, f32::NEG_INFINITY, f32::INFINITY, 1.0];
+
+        for radius in [1, 2, 3, 5] {
+            let result = gaussian_blur(&buffer, radius, None);
+
+            assert!(!result.has_non_finite());
+
+            // Pixels out of reach of the kernel stay untouched
+            let c = result.get_pixel(0, 0);
+            assert!((c.red() - 0.5).abs() < 0.001 && (c.blue() - 0.5).abs() < 0.001);
+        }
+    }
+
+    #[test]
+    fn test_in_place() {
+        let buffer = PixelBuffer::new_from_func(13, 9, |x, y| {
+            Rgb::new_with_alpha(
+                (x % 3) as f32 / 2.0,
+                y as f32 / 8.0,
+                ((x + y) % 2) as f32,
+                1.0 - x as f32 / 26.0,
+            )
+        });
+
+        for (radius, sigma) in [(1, None), (2, Some(1.5)), (3, None), (6, None)] {
+            let expected = gaussian_blur(&buffer, radius, sigma);
+
+            let mut result = buffer.clone();
+            gaussian_blur_in_place(&mut result, radius, sigma);
+
+            assert!(result.max_channel_difference(&expected) < 1e-5);
+        }
+
+        let mut result = buffer.clone();
+        gaussian_blur_in_place(&mut result, 0, None);
+        assert_eq!(result.data(), buffer.data());
+
+        let mut empty = PixelBuffer::<Rgb>::new(0, 0);
+        gaussian_blur_in_place(&mut empty, 2, None);
+    }
+}
@@ -0,0 +1,220 @@
+use d10_core::color::{Color, Rgb};
+use d10_core::pixelbuffer::PixelBuffer;
+
+use crate::temperature::calculate_factors;
+
+/// Reference temperature used to convert temperature shifts into channel factors
+const NEUTRAL_TEMPERATURE: f32 = 6500.0;
+
+/// Color adjustments applied by a graduated filter
+///
+/// All values of the default adjustment leave the image unchanged.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Adjustment {
+    /// Exposure change in stops. Negative values darken the image.
+    pub exposure: f32,
+    /// Relative saturation change. `-1.0` removes all colors, `0.5` increases it by 50%.
+    pub saturation: f32,
+    /// Color temperature shift in kelvin. Positive values warm up the image.
+    pub temperature: f32,
+}
+
+impl Adjustment {
+    fn apply(&self, color: &Rgb, temperature_factors: &[f32; 3], weight: f32) -> Rgb {
+        let exposure = 2f32.powf(self.exposure * weight);
+
+        let mut data = color.data;
+        for (v, f) in data.iter_mut().zip(temperature_factors) {
+            *v *= exposure * (1.0 + (f - 1.0) * weight);
+        }
+
+        let color = Rgb::new_with_alpha(data[0], data[1], data[2], color.alpha());
+
+        if self.saturation != 0.0 {
+            color.with_saturation((1.0 + self.saturation * weight).max(0.0))
+        } else {
+            color
+        }
+    }
+}
+
+fn smoothstep(value: f32) -> f32 {
+    let value = value.clamp(0.0, 1.0);
+    value * value * (3.0 - 2.0 * value)
+}
+
+/// Apply an adjustment with a linear gradient mask and an additional per pixel mask
+fn apply_gradient(
+    buffer: &PixelBuffer<Rgb>,
+    angle: f32,
+    start: f32,
+    end: f32,
+    adjustment: &Adjustment,
+    mask: impl Fn(&Rgb) -> f32,
+) -> PixelBuffer<Rgb> {
+    if buffer.is_empty() || *adjustment == Adjustment::default() {
+        return buffer.clone();
+    }
+
+    let (dx, dy) = angle.to_radians().sin_cos();
+
+    let width = buffer.width() as f32;
+    let height = buffer.height() as f32;
+
+    // Positions along the gradient are relative to the extent of the image in that direction
+    let projections =
+        [(0.0, 0.0), (width, 0.0), (0.0, height), (width, height)].map(|(x, y)| x * dx + y * dy);
+    let min = projections.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = projections
+        .iter()
+        .copied()
+        .fold(f32::NEG_INFINITY, f32::max);
+    let extent = (max - min).max(f32::EPSILON);
+
+    let temperature_factors = calculate_factors(
+        NEUTRAL_TEMPERATURE,
+        NEUTRAL_TEMPERATURE + adjustment.temperature,
+    );
+
+    let mut result = buffer.clone();
+
+    for (x, y, c) in result.enumerate_mut() {
+        let position = ((x as f32 + 0.5) * dx + (y as f32 + 0.5) * dy - min) / extent;
+
+        let gradient = if (end - start).abs() < f32::EPSILON {
+            if position < start {
+                1.0
+            } else {
+                0.0
+            }
+        } else {
+            1.0 - smoothstep((position - start) / (end - start))
+        };
+
+        let weight = gradient * mask(c);
+        if weight > 0.0 {
+            *c = adjustment.apply(c, &temperature_factors, weight);
+        }
+    }
+
+    result
+}
+
+/// Apply an adjustment that fades out along a linear gradient like a graduated ND filter
+///
+/// The angle in degrees defines the direction of the gradient. With an angle of 0 the
+/// adjustment is applied to the top and fades out towards the bottom, with 90 it fades from
+/// left to right. `start` and `end` are positions between 0.0 and 1.0 along that direction:
+/// the adjustment is fully applied before `start` and not at all after `end`.
+pub fn graduated_filter(
+    buffer: &PixelBuffer<Rgb>,
+    angle: f32,
+    start: f32,
+    end: f32,
+    adjustment: &Adjustment,
+) -> PixelBuffer<Rgb> {
+    apply_gradient(buffer, angle, start, end, adjustment, |_| 1.0)
+}
+
+/// Darken and saturate a bright sky in the upper part of an image
+///
+/// This is a graduated filter from the top to the middle of the image that is restricted to
+/// bright areas, so that foreground objects reaching into the sky keep their exposure.
+/// The strength should be between 0 and 1.
+pub fn enhance_sky(buffer: &PixelBuffer<Rgb>, strength: f32) -> PixelBuffer<Rgb> {
+    let strength = strength.clamp(0.0, 1.0);
+
+    let adjustment = Adjustment {
+        exposure: -strength,
+        saturation: 0.4 * strength,
+        temperature: -500.0 * strength,
+    };
+
+    apply_gradient(buffer, 0.0, 0.1, 0.6, &adjustment, |c| {
+        let luma = c.to_gray().to_srgb().red();
+        smoothstep((luma - 0.35) / 0.4)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use d10_core::color::Srgb;
+
+    fn test_buffer() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_with_color(20, 20, Srgb::new(0.6, 0.7, 0.9).to_rgb())
+    }
+
+    #[test]
+    fn test_graduated_filter() {
+        let buffer = test_buffer();
+
+        let adjustment = Adjustment {
+            exposure: -1.0,
+            ..Adjustment::default()
+        };
+
+        let res = graduated_filter(&buffer, 0.0, 0.2, 0.8, &adjustment);
+
+        let orig = buffer.get_pixel(10, 0);
+        let top = res.get_pixel(10, 0);
+        assert!((top.red() - orig.red() / 2.0).abs() < 1e-6);
+        assert_eq!(res.get_pixel(10, 19), orig);
+
+        let middle = res.get_pixel(10, 10).red();
+        assert!(middle < orig.red() && middle > top.red());
+
+        for x in 0..20 {
+            assert_eq!(res.get_pixel(x, 5), res.get_pixel(0, 5));
+        }
+
+        // Fading from left to right
+        let res = graduated_filter(&buffer, 90.0, 0.2, 0.8, &adjustment);
+        assert!(res.get_pixel(0, 10).red() < orig.red());
+        assert_eq!(res.get_pixel(19, 10), orig);
+    }
+
+    #[test]
+    fn test_graduated_filter_temperature() {
+        let buffer = test_buffer();
+
+        let adjustment = Adjustment {
+            temperature: 1000.0,
+            ..Adjustment::default()
+        };
+
+        let res = graduated_filter(&buffer, 0.0, 0.5, 0.5, &adjustment);
+
+        let orig = buffer.get_pixel(0, 0);
+        let warm = res.get_pixel(0, 0);
+        assert!(warm.red() / warm.blue() > orig.red() / orig.blue());
+        assert_eq!(res.get_pixel(0, 15), orig);
+    }
+
+    #[test]
+    fn test_graduated_filter_default() {
+        let buffer = test_buffer();
+
+        let res = graduated_filter(&buffer, 30.0, 0.0, 1.0, &Adjustment::default());
+        assert_eq!(res.data(), buffer.data());
+    }
+
+    #[test]
+    fn test_enhance_sky() {
+        let buffer = PixelBuffer::new_from_func(20, 20, |x, _| {
+            if x < 10 {
+                Srgb::new(0.6, 0.7, 0.9).to_rgb()
+            } else {
+                Srgb::new(0.1, 0.15, 0.1).to_rgb()
+            }
+        });
+
+        let res = enhance_sky(&buffer, 1.0);
+
+        assert!(res.get_pixel(5, 0).to_gray().red() < buffer.get_pixel(5, 0).to_gray().red());
+        assert_eq!(res.get_pixel(15, 0), buffer.get_pixel(15, 0));
+        assert_eq!(res.get_pixel(5, 19), buffer.get_pixel(5, 19));
+
+        assert_eq!(enhance_sky(&buffer, 0.0).data(), buffer.data());
+    }
+}
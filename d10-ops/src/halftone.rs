@@ -0,0 +1,86 @@
+use d10_core::color::{Color, Rgb};
+use d10_core::errors::ParseEnumError;
+use d10_core::pixelbuffer::PixelBuffer;
+use std::f32::consts::PI;
+use std::str::FromStr;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HalftoneShape {
+    Dot,
+    Square,
+    Line,
+}
+
+impl FromStr for HalftoneShape {
+    type Err = ParseEnumError;
+
+    fn from_str(value: &str) -> Result<HalftoneShape, Self::Err> {
+        match value {
+            "dot" | "default" => Ok(HalftoneShape::Dot),
+            "square" => Ok(HalftoneShape::Square),
+            "line" => Ok(HalftoneShape::Line),
+            _ => Err(ParseEnumError::new(value, "HalftoneShape")),
+        }
+    }
+}
+
+/// Distance of the position to the edge of the shape in relative cell units
+///
+/// Negative values are inside the shape
+fn get_shape_distance(shape: HalftoneShape, du: f32, dv: f32, coverage: f32) -> f32 {
+    match shape {
+        HalftoneShape::Dot => (du * du + dv * dv).sqrt() - (coverage / PI).sqrt(),
+        HalftoneShape::Square => du.abs().max(dv.abs()) - coverage.sqrt() / 2.0,
+        HalftoneShape::Line => dv.abs() - coverage / 2.0,
+    }
+}
+
+/// Convert the image into a black and white halftone screen
+///
+/// # Arguments
+/// cell_size: Size of a single screen cell in pixels
+/// angle: Rotation of the screen in degrees
+pub fn halftone(
+    buffer: &PixelBuffer<Rgb>,
+    cell_size: u32,
+    angle: f32,
+    shape: HalftoneShape,
+) -> PixelBuffer<Rgb> {
+    let cell_size = cell_size.max(1) as f32;
+
+    let radians = angle / 180.0 * PI;
+    let sinf = radians.sin();
+    let cosf = radians.cos();
+
+    buffer.map_colors_enumerated(|x, y, c| {
+        let x = x as f32 + 0.5;
+        let y = y as f32 + 0.5;
+
+        // Position in the rotated screen
+        let u = (x * cosf + y * sinf) / cell_size;
+        let v = (y * cosf - x * sinf) / cell_size;
+
+        let cell_u = u.floor() + 0.5;
+        let cell_v = v.floor() + 0.5;
+
+        // Position of the cell center in the image
+        let center_x = (cell_u * cosf - cell_v * sinf) * cell_size;
+        let center_y = (cell_u * sinf + cell_v * cosf) * cell_size;
+
+        let sample = buffer.get_pixel_clamped(center_x.floor() as i32, center_y.floor() as i32);
+        let coverage = 1.0 - sample.to_gray().to_srgb().red();
+
+        if coverage <= f32::EPSILON {
+            return Rgb::WHITE.with_alpha(c.alpha());
+        } else if coverage >= 1.0 - f32::EPSILON {
+            return Rgb::BLACK.with_alpha(c.alpha());
+        }
+
+        let distance = get_shape_distance(shape, u - cell_u, v - cell_v, coverage);
+
+        // Smooth the edges of the shape over a single pixel
+        let value = (distance * cell_size + 0.5).clamp(0.0, 1.0);
+
+        Rgb::new_with_alpha(value, value, value, c.alpha())
+    })
+}
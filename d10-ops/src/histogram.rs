@@ -0,0 +1,171 @@
+use d10_core::color::{Color, Rgb};
+use d10_core::pixelbuffer::PixelBuffer;
+use d10_core::region::Region;
+
+/// Number of bins of every channel
+pub const HISTOGRAM_BINS: usize = 256;
+
+/// Pixel counts of the gamma encoded red, green, blue and luma values
+///
+/// Fully transparent pixels are not counted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    pub red: [u32; HISTOGRAM_BINS],
+    pub green: [u32; HISTOGRAM_BINS],
+    pub blue: [u32; HISTOGRAM_BINS],
+    pub luma: [u32; HISTOGRAM_BINS],
+}
+
+fn bin(value: f32) -> usize {
+    (value.clamp(0.0, 1.0) * (HISTOGRAM_BINS - 1) as f32).round() as usize
+}
+
+impl Histogram {
+    pub fn new(buffer: &PixelBuffer<Rgb>) -> Histogram {
+        let mut histogram = Histogram {
+            red: [0; HISTOGRAM_BINS],
+            green: [0; HISTOGRAM_BINS],
+            blue: [0; HISTOGRAM_BINS],
+            luma: [0; HISTOGRAM_BINS],
+        };
+
+        for c in buffer.data() {
+            if c.alpha() <= 0.0 {
+                continue;
+            }
+
+            let c = c.to_srgb();
+            let luma = c.data[0] * 0.212_656 + c.data[1] * 0.715_158 + c.data[2] * 0.072_186;
+
+            histogram.red[bin(c.data[0])] += 1;
+            histogram.green[bin(c.data[1])] += 1;
+            histogram.blue[bin(c.data[2])] += 1;
+            histogram.luma[bin(luma)] += 1;
+        }
+
+        histogram
+    }
+
+    /// Number of counted pixels
+    pub fn total(&self) -> u32 {
+        self.luma.iter().sum()
+    }
+
+    /// Largest bin of all channels
+    pub fn max(&self) -> u32 {
+        [&self.red, &self.green, &self.blue, &self.luma]
+            .iter()
+            .flat_map(|channel| channel.iter())
+            .copied()
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Json object with the bin counts of every channel
+    pub fn to_json(&self) -> String {
+        let channel = |name: &str, bins: &[u32; HISTOGRAM_BINS]| {
+            let bins: Vec<String> = bins.iter().map(|v| v.to_string()).collect();
+            format!("\"{}\":[{}]", name, bins.join(","))
+        };
+
+        format!(
+            "{{\"bins\":{},\"total\":{},{},{},{},{}}}",
+            HISTOGRAM_BINS,
+            self.total(),
+            channel("red", &self.red),
+            channel("green", &self.green),
+            channel("blue", &self.blue),
+            channel("luma", &self.luma),
+        )
+    }
+
+    /// Render a chart with the luma as gray area and the color channels blended on top of it
+    ///
+    /// Every bin is `bin_width` pixels wide and the largest bin fills the full `height`.
+    pub fn render(&self, bin_width: u32, height: u32) -> PixelBuffer<Rgb> {
+        let bin_width = bin_width.max(1);
+        let width = HISTOGRAM_BINS as u32 * bin_width;
+
+        let mut buffer = PixelBuffer::new_with_color(width, height, Rgb::WHITE);
+
+        let max = self.max();
+        if max == 0 || height == 0 {
+            return buffer;
+        }
+
+        let bar_height = |count: u32| (count as f32 / max as f32 * height as f32).round() as u32;
+
+        let mut draw_bars = |bins: &[u32; HISTOGRAM_BINS], color: Rgb| {
+            for (i, count) in bins.iter().enumerate() {
+                let h = bar_height(*count);
+                if h == 0 {
+                    continue;
+                }
+
+                let region = Region::new(i as u32 * bin_width, height - h, bin_width, h);
+
+                for y in region.y..region.y + region.height {
+                    for x in region.x..region.x + region.width {
+                        let c = buffer.get_pixel(x, y).alpha_blend(color);
+                        buffer.put_pixel(x, y, c);
+                    }
+                }
+            }
+        };
+
+        draw_bars(&self.luma, Rgb::new(0.6, 0.6, 0.6));
+        draw_bars(&self.red, Rgb::new_with_alpha(1.0, 0.0, 0.0, 0.35));
+        draw_bars(&self.green, Rgb::new_with_alpha(0.0, 1.0, 0.0, 0.35));
+        draw_bars(&self.blue, Rgb::new_with_alpha(0.0, 0.0, 1.0, 0.35));
+
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram() {
+        let buffer =
+            PixelBuffer::new_from_raw(4, 1, vec![Rgb::BLACK, Rgb::WHITE, Rgb::RED, Rgb::NONE]);
+
+        let histogram = Histogram::new(&buffer);
+
+        assert_eq!(histogram.total(), 3);
+        assert_eq!(histogram.red[0], 1);
+        assert_eq!(histogram.red[255], 2);
+        assert_eq!(histogram.green[0], 2);
+        assert_eq!(histogram.blue[255], 1);
+        assert_eq!(histogram.luma[0], 1);
+        assert_eq!(histogram.luma[54], 1);
+        assert_eq!(histogram.max(), 2);
+    }
+
+    #[test]
+    fn test_to_json() {
+        let buffer = PixelBuffer::new_with_color(2, 1, Rgb::BLACK);
+
+        let json = Histogram::new(&buffer).to_json();
+
+        assert!(json.starts_with("{\"bins\":256,\"total\":2,\"red\":[2,0,0,"));
+        assert!(json.ends_with(",0,0]}"));
+        assert!(json.contains("\"luma\":[2,0,"));
+    }
+
+    #[test]
+    fn test_render() {
+        let buffer = PixelBuffer::new_with_color(2, 1, Rgb::WHITE);
+
+        let chart = Histogram::new(&buffer).render(2, 50);
+
+        assert_eq!((chart.width(), chart.height()), (512, 50));
+        assert_eq!(chart.get_pixel(0, 49), &Rgb::WHITE);
+        assert_ne!(chart.get_pixel(511, 0), &Rgb::WHITE);
+        assert_ne!(chart.get_pixel(510, 49), &Rgb::WHITE);
+
+        let empty = Histogram::new(&PixelBuffer::new(0, 0)).render(1, 10);
+        assert!(empty.data().iter().all(|c| *c == Rgb::WHITE));
+    }
+}
@@ -0,0 +1,155 @@
+use d10_core::color::{Color, Rgb, Srgb};
+use d10_core::pixelbuffer::PixelBuffer;
+
+fn neighbors(buffer: &PixelBuffer<Srgb>, x: u32, y: u32) -> Vec<&Srgb> {
+    let mut result = Vec::with_capacity(8);
+
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            if dx == 0 && dy == 0 {
+                continue;
+            }
+
+            if let Some(c) = buffer.get_pixel_optional(x as i32 + dx, y as i32 + dy) {
+                result.push(c);
+            }
+        }
+    }
+
+    result
+}
+
+fn median(values: &mut [f32]) -> f32 {
+    values.sort_unstable_by(|a, b| a.total_cmp(b));
+
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+/// Per channel median of the neighbors if the pixel is a hot or dead pixel
+fn replacement(buffer: &PixelBuffer<Srgb>, x: u32, y: u32, threshold: f32) -> Option<Srgb> {
+    let c = buffer.get_pixel(x, y);
+    let neighbors = neighbors(buffer, x, y);
+
+    if neighbors.len() < 3 {
+        return None;
+    }
+
+    let mut outlier = false;
+    let mut medians = [0.0; 3];
+
+    for (i, m) in medians.iter_mut().enumerate() {
+        let mut values: Vec<f32> = neighbors.iter().map(|n| n.data[i]).collect();
+        *m = median(&mut values);
+
+        let min = values[0];
+        let max = values[values.len() - 1];
+        let value = c.data[i];
+
+        // Only pixels outside of the range of all neighbors are isolated, which keeps lines
+        // and edges intact
+        if (value - *m).abs() > threshold && (value > max || value < min) {
+            outlier = true;
+        }
+    }
+
+    if outlier {
+        Some(Srgb::new_with_alpha(
+            medians[0],
+            medians[1],
+            medians[2],
+            c.alpha(),
+        ))
+    } else {
+        None
+    }
+}
+
+/// Find single pixels that are much brighter (hot) or darker (dead) than all of their neighbors
+///
+/// A pixel is reported if at least one of its gamma encoded channels differs by more than
+/// `threshold` from the median of the surrounding pixels and lies outside of their range.
+pub fn detect_hot_pixels(buffer: &PixelBuffer<Rgb>, threshold: f32) -> Vec<(u32, u32)> {
+    let srgb = buffer.map_colors(|c| c.to_srgb());
+
+    srgb.enumerate()
+        .filter(|(x, y, _)| replacement(&srgb, *x, *y, threshold).is_some())
+        .map(|(x, y, _)| (x, y))
+        .collect()
+}
+
+/// Replace hot and dead pixels with the median of their neighbors
+///
+/// Returns the repaired image and the locations of all replaced pixels.
+/// See `detect_hot_pixels()` for how the pixels are found.
+pub fn repair_hot_pixels(
+    buffer: &PixelBuffer<Rgb>,
+    threshold: f32,
+) -> (PixelBuffer<Rgb>, Vec<(u32, u32)>) {
+    let srgb = buffer.map_colors(|c| c.to_srgb());
+
+    let mut result = buffer.clone();
+    let mut locations = vec![];
+
+    for (x, y, _) in srgb.enumerate() {
+        if let Some(c) = replacement(&srgb, x, y, threshold) {
+            result.put_pixel(x, y, c.to_rgb());
+            locations.push((x, y));
+        }
+    }
+
+    (result, locations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noisy_gray() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(8, 8, |x, y| {
+            let v = 0.3 + ((x * 5 + y * 3) % 7) as f32 / 100.0;
+            Rgb::new(v, v, v)
+        })
+    }
+
+    #[test]
+    fn test_repair_hot_pixels() {
+        let mut buffer = noisy_gray();
+        buffer.put_pixel(3, 4, Rgb::new(1.0, 0.3, 0.3));
+        buffer.put_pixel(6, 1, Rgb::BLACK);
+        buffer.put_pixel(0, 0, Rgb::WHITE);
+
+        assert_eq!(
+            detect_hot_pixels(&buffer, 0.2),
+            vec![(0, 0), (6, 1), (3, 4)]
+        );
+
+        let (repaired, locations) = repair_hot_pixels(&buffer, 0.2);
+        assert_eq!(locations, vec![(0, 0), (6, 1), (3, 4)]);
+
+        for (x, y) in locations {
+            let c = repaired.get_pixel(x, y);
+            assert!(c.red() > 0.3 && c.red() < 0.37);
+            assert!(c.green() > 0.3 && c.green() < 0.37);
+        }
+
+        assert!(detect_hot_pixels(&repaired, 0.2).is_empty());
+    }
+
+    #[test]
+    fn test_keeps_lines() {
+        let mut buffer = noisy_gray();
+        for y in 0..8 {
+            buffer.put_pixel(4, y, Rgb::WHITE);
+        }
+
+        let (repaired, locations) = repair_hot_pixels(&buffer, 0.2);
+
+        assert!(locations.is_empty());
+        assert_eq!(repaired.data(), buffer.data());
+    }
+}
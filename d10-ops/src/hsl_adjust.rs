@@ -0,0 +1,180 @@
+use d10_core::color::{Color, Hsl, Rgb};
+use d10_core::pixelbuffer::PixelBuffer;
+
+/// Hue, saturation and lightness change for all colors within a range of hues
+///
+/// All adjustment values of `HueRangeAdjustment::new()` leave the image unchanged.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HueRangeAdjustment {
+    /// Center of the hue range in degrees, e.g. `0.0` for red or `240.0` for blue
+    pub hue: f32,
+    /// Width of the range in degrees that gets fully adjusted.
+    /// The adjustment fades out over the same distance on both sides of the range.
+    pub width: f32,
+    /// Hue rotation in degrees
+    pub hue_shift: f32,
+    /// Relative saturation change. `-1.0` removes all colors, `0.5` increases it by 50%.
+    pub saturation: f32,
+    /// Lightness change. `-1.0` turns the colors black and `1.0` white.
+    pub lightness: f32,
+}
+
+impl HueRangeAdjustment {
+    pub fn new(hue: f32, width: f32) -> HueRangeAdjustment {
+        HueRangeAdjustment {
+            hue,
+            width,
+            hue_shift: 0.0,
+            saturation: 0.0,
+            lightness: 0.0,
+        }
+    }
+
+    /// Strength of the adjustment for a hue in degrees
+    fn weight(&self, hue: f32) -> f32 {
+        let distance = (hue - self.hue).rem_euclid(360.0);
+        let distance = distance.min(360.0 - distance);
+
+        let half_width = self.width.max(0.0) / 2.0;
+
+        if distance <= half_width {
+            1.0
+        } else if half_width > 0.0 {
+            1.0 - smoothstep((distance - half_width) / (half_width * 2.0))
+        } else {
+            0.0
+        }
+    }
+}
+
+fn smoothstep(value: f32) -> f32 {
+    let value = value.clamp(0.0, 1.0);
+    value * value * (3.0 - 2.0 * value)
+}
+
+/// Colors below this saturation are treated as gray and only partially adjusted
+const GRAY_SATURATION: f32 = 0.1;
+
+/// Change the hue, saturation and lightness of specific color ranges
+///
+/// This allows e.g. to make the sky a deeper blue without touching skin tones, like the HSL
+/// panel of photo editors. The ranges are selected by the hue of the original color, so
+/// overlapping ranges add up instead of affecting the result of each other. Colors with almost
+/// no saturation don't have a meaningful hue and are faded out of the adjustment.
+pub fn hsl_adjust(buffer: &PixelBuffer<Rgb>, ranges: &[HueRangeAdjustment]) -> PixelBuffer<Rgb> {
+    if ranges.is_empty() {
+        return buffer.clone();
+    }
+
+    buffer.map_colors(|c| {
+        let hsl = c.to_hsl();
+        let chroma_weight = smoothstep(hsl.saturation() / GRAY_SATURATION);
+
+        if chroma_weight == 0.0 {
+            return *c;
+        }
+
+        let original_hue = hsl.hue() * 360.0;
+
+        let mut hue = original_hue;
+        let mut saturation = hsl.saturation();
+        let mut lightness = hsl.lightness();
+
+        for range in ranges {
+            let weight = range.weight(original_hue) * chroma_weight;
+            if weight == 0.0 {
+                continue;
+            }
+
+            hue += range.hue_shift * weight;
+            saturation *= (1.0 + range.saturation * weight).max(0.0);
+
+            let amount = (range.lightness * weight).clamp(-1.0, 1.0);
+            if amount > 0.0 {
+                lightness += (1.0 - lightness) * amount;
+            } else {
+                lightness *= 1.0 + amount;
+            }
+        }
+
+        Hsl::new_with_alpha(
+            hue.rem_euclid(360.0) / 360.0,
+            saturation.clamp(0.0, 1.0),
+            lightness.clamp(0.0, 1.0),
+            c.alpha(),
+        )
+        .to_rgb()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn colors() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_raw(
+            4,
+            1,
+            vec![
+                Rgb::new(0.8, 0.4, 0.3),
+                Rgb::new(0.2, 0.4, 0.8),
+                Rgb::new(0.2, 0.8, 0.2),
+                Rgb::new(0.5, 0.5, 0.5),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_weight() {
+        let range = HueRangeAdjustment::new(0.0, 60.0);
+
+        assert_eq!(range.weight(20.0), 1.0);
+        assert_eq!(range.weight(340.0), 1.0);
+        assert!(range.weight(60.0) > 0.0 && range.weight(60.0) < 1.0);
+        assert_eq!(range.weight(90.0), 0.0);
+        assert_eq!(range.weight(180.0), 0.0);
+    }
+
+    #[test]
+    fn test_hsl_adjust() {
+        let buffer = colors();
+
+        let result = hsl_adjust(
+            &buffer,
+            &[HueRangeAdjustment {
+                saturation: 0.5,
+                lightness: -0.2,
+                ..HueRangeAdjustment::new(220.0, 40.0)
+            }],
+        );
+
+        // Only the blue color is changed
+        assert_eq!(result.get_pixel(0, 0), buffer.get_pixel(0, 0));
+        assert_eq!(result.get_pixel(2, 0), buffer.get_pixel(2, 0));
+        assert_eq!(result.get_pixel(3, 0), buffer.get_pixel(3, 0));
+
+        let before = buffer.get_pixel(1, 0).to_hsl();
+        let after = result.get_pixel(1, 0).to_hsl();
+        assert!(after.saturation() > before.saturation());
+        assert!(after.lightness() < before.lightness());
+        assert!((after.hue() - before.hue()).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_hue_shift() {
+        let buffer = colors();
+
+        let result = hsl_adjust(
+            &buffer,
+            &[HueRangeAdjustment {
+                hue_shift: 120.0,
+                ..HueRangeAdjustment::new(120.0, 30.0)
+            }],
+        );
+
+        let hue = result.get_pixel(2, 0).to_hsl().hue() * 360.0;
+        assert!((hue - 240.0).abs() < 0.1);
+
+        assert_eq!(hsl_adjust(&buffer, &[]).data(), buffer.data());
+    }
+}
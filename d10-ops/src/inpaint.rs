@@ -0,0 +1,387 @@
+use d10_core::color::Rgb;
+use d10_core::pixelbuffer::PixelBuffer;
+
+use crate::threshold::srgb_luma;
+
+/// Maximal number of diffusion steps after the initial fill
+const MAX_ITERATIONS: usize = 200;
+
+/// Diffusion stops early if no channel changes more than this
+const MIN_CHANGE: f32 = 1e-4;
+
+/// Pixels of the image that are marked by bright pixels in the mask
+///
+/// Pixels outside of the mask are never marked.
+pub(crate) fn masked_pixels(buffer: &PixelBuffer<Rgb>, mask: &PixelBuffer<Rgb>) -> Vec<bool> {
+    buffer
+        .enumerate()
+        .map(|(x, y, _)| {
+            mask.get_pixel_optional(x as i32, y as i32)
+                .is_some_and(|c| srgb_luma(c) > 0.5)
+        })
+        .collect()
+}
+
+fn neighbors(i: usize, width: usize, height: usize) -> impl Iterator<Item = (usize, f32)> {
+    let x = (i % width) as i32;
+    let y = (i / width) as i32;
+
+    [
+        (-1, -1),
+        (0, -1),
+        (1, -1),
+        (-1, 0),
+        (1, 0),
+        (-1, 1),
+        (0, 1),
+        (1, 1),
+    ]
+    .into_iter()
+    .filter(move |(dx, dy)| {
+        (0..width as i32).contains(&(x + dx)) && (0..height as i32).contains(&(y + dy))
+    })
+    .map(move |(dx, dy)| {
+        let weight = if dx == 0 || dy == 0 {
+            1.0
+        } else {
+            std::f32::consts::FRAC_1_SQRT_2
+        };
+        ((y + dy) as usize * width + (x + dx) as usize, weight)
+    })
+}
+
+fn weighted_average(items: impl Iterator<Item = (Rgb, f32)>) -> Option<Rgb> {
+    let mut sum = [0.0; 4];
+    let mut total = 0.0;
+
+    for (c, weight) in items {
+        for (s, v) in sum.iter_mut().zip(c.data.iter()) {
+            *s += v * weight;
+        }
+        total += weight;
+    }
+
+    (total > 0.0).then(|| Rgb {
+        data: sum.map(|s| s / total),
+    })
+}
+
+/// Fill the masked area of the image by diffusing the surrounding colors into it
+///
+/// Bright pixels in the mask mark the area to fill. The area gets filled from its border to
+/// the inside with the average of the already known neighbors and gets smoothed afterwards.
+/// This works best for small blemishes, scratches and thin objects.
+pub fn inpaint(buffer: &PixelBuffer<Rgb>, mask: &PixelBuffer<Rgb>) -> PixelBuffer<Rgb> {
+    let width = buffer.width() as usize;
+    let height = buffer.height() as usize;
+
+    let masked = masked_pixels(buffer, mask);
+    if masked.iter().all(|m| *m) {
+        return buffer.clone();
+    }
+
+    let mut data = buffer.data().to_vec();
+    let mut known: Vec<bool> = masked.iter().map(|m| !m).collect();
+
+    // Fill the area layer by layer starting at its border
+    let mut remaining: Vec<usize> = (0..data.len()).filter(|i| masked[*i]).collect();
+
+    while !remaining.is_empty() {
+        let layer: Vec<(usize, Rgb)> = remaining
+            .iter()
+            .filter_map(|i| {
+                let known_neighbors = neighbors(*i, width, height)
+                    .filter(|(n, _)| known[*n])
+                    .map(|(n, weight)| (data[n], weight));
+                weighted_average(known_neighbors).map(|c| (*i, c))
+            })
+            .collect();
+
+        for (i, c) in &layer {
+            data[*i] = *c;
+            known[*i] = true;
+        }
+
+        remaining.retain(|i| !known[*i]);
+    }
+
+    // Smooth the filled area to remove the artifacts of the layered fill
+    let filled: Vec<usize> = (0..data.len()).filter(|i| masked[*i]).collect();
+
+    for _ in 0..MAX_ITERATIONS {
+        let mut max_change = 0.0f32;
+
+        for i in &filled {
+            let c = weighted_average(neighbors(*i, width, height).map(|(n, w)| (data[n], w)))
+                .unwrap_or(data[*i]);
+
+            for (old, new) in data[*i].data.iter().zip(c.data.iter()) {
+                max_change = max_change.max((old - new).abs());
+            }
+
+            data[*i] = c;
+        }
+
+        if max_change < MIN_CHANGE {
+            break;
+        }
+    }
+
+    PixelBuffer::new_from_raw(buffer.width(), buffer.height(), data)
+}
+
+/// Gradient of a value in a grid with central differences, ignoring positions that are not known
+fn gradient(values: &[f32], known: &[bool], i: usize, width: usize, height: usize) -> (f32, f32) {
+    let (x, y) = (i % width, i / width);
+
+    let diff = |prev: Option<usize>, next: Option<usize>| {
+        let prev = prev.filter(|p| known[*p]);
+        let next = next.filter(|n| known[*n]);
+
+        match (prev, next) {
+            (Some(p), Some(n)) => (values[n] - values[p]) / 2.0,
+            (Some(p), None) if known[i] => values[i] - values[p],
+            (None, Some(n)) if known[i] => values[n] - values[i],
+            _ => 0.0,
+        }
+    };
+
+    let dx = diff((x > 0).then(|| i - 1), (x + 1 < width).then(|| i + 1));
+    let dy = diff(
+        (y > 0).then(|| i - width),
+        (y + 1 < height).then(|| i + width),
+    );
+
+    (dx, dy)
+}
+
+/// Remove larger objects by filling the masked area with patches from the rest of the image
+///
+/// This is the exemplar based inpainting algorithm by Criminisi, Pérez and Toyama. The border of
+/// the area gets filled patch by patch, starting with patches that continue strong edges into
+/// the area and that already contain many known pixels. Each patch gets filled with the most
+/// similar completely known patch of the image, so that textures and structures are continued.
+///
+/// Bright pixels in the mask mark the area to fill. The patch size should be slightly larger than
+/// the largest texture element of the image and gets rounded up to an odd number.
+pub fn inpaint_exemplar(
+    buffer: &PixelBuffer<Rgb>,
+    mask: &PixelBuffer<Rgb>,
+    patch_size: u32,
+) -> PixelBuffer<Rgb> {
+    let width = buffer.width() as usize;
+    let height = buffer.height() as usize;
+    let half = (patch_size.max(3) / 2) as usize;
+
+    let masked = masked_pixels(buffer, mask);
+    if !masked.contains(&true) {
+        return buffer.clone();
+    }
+
+    let patch = |i: usize| {
+        let (x, y) = (i % width, i / width);
+        let (x0, x1) = (x.saturating_sub(half), (x + half).min(width - 1));
+        let (y0, y1) = (y.saturating_sub(half), (y + half).min(height - 1));
+        (y0..=y1).flat_map(move |py| (x0..=x1).map(move |px| py * width + px))
+    };
+
+    // Patches that are completely inside of the image and don't contain masked pixels
+    let sources: Vec<usize> = (half..height.saturating_sub(half))
+        .flat_map(|y| (half..width.saturating_sub(half)).map(move |x| y * width + x))
+        .filter(|i| patch(*i).all(|p| !masked[p]))
+        .collect();
+
+    if sources.is_empty() {
+        return inpaint(buffer, mask);
+    }
+
+    let mut data = buffer.data().to_vec();
+    let mut known: Vec<bool> = masked.iter().map(|m| !m).collect();
+    let mut confidence: Vec<f32> = known.iter().map(|k| if *k { 1.0 } else { 0.0 }).collect();
+    let mut gray: Vec<f32> = data.iter().map(srgb_luma).collect();
+
+    let patch_area = ((half * 2 + 1) * (half * 2 + 1)) as f32;
+
+    let all_known = vec![true; known.len()];
+
+    loop {
+        let known_values: Vec<f32> = known.iter().map(|k| if *k { 1.0 } else { 0.0 }).collect();
+
+        // Pixels of the fill front are unknown pixels next to known pixels
+        let front = (0..data.len()).filter(|i| {
+            !known[*i] && neighbors(*i, width, height).any(|(n, w)| w == 1.0 && known[n])
+        });
+
+        let best = front
+            .map(|i| {
+                let c = patch(i).map(|p| confidence[p]).sum::<f32>() / patch_area;
+
+                // Strongest isophote in the known part of the patch, which is perpendicular to
+                // the gradient and keeps its length
+                let isophote = patch(i)
+                    .filter(|p| known[*p])
+                    .map(|p| gradient(&gray, &known, p, width, height))
+                    .max_by(|a, b| a.0.hypot(a.1).total_cmp(&b.0.hypot(b.1)))
+                    .map(|(dx, dy)| (-dy, dx))
+                    .unwrap_or((0.0, 0.0));
+
+                let (nx, ny) = gradient(&known_values, &all_known, i, width, height);
+                let length = nx.hypot(ny);
+                let normal = if length > 0.0 {
+                    (nx / length, ny / length)
+                } else {
+                    (0.0, 0.0)
+                };
+
+                // Small offset to not stall on areas without any structure
+                let d = (isophote.0 * normal.0 + isophote.1 * normal.1).abs() + 0.001;
+
+                (i, c, c * d)
+            })
+            .max_by(|a, b| a.2.total_cmp(&b.2));
+
+        let Some((target, target_confidence, _)) = best else {
+            break;
+        };
+
+        let (tx, ty) = ((target % width) as isize, (target / width) as isize);
+
+        let offsets: Vec<(usize, isize, isize)> = patch(target)
+            .filter(|p| known[*p])
+            .map(|p| (p, (p % width) as isize - tx, (p / width) as isize - ty))
+            .collect();
+
+        let source = sources
+            .iter()
+            .map(|s| {
+                let (sx, sy) = ((s % width) as isize, (s / width) as isize);
+
+                let distance: f32 = offsets
+                    .iter()
+                    .map(|(p, dx, dy)| {
+                        let c = &data[((sy + dy) as usize) * width + (sx + dx) as usize];
+                        c.data
+                            .iter()
+                            .zip(data[*p].data.iter())
+                            .map(|(a, b)| (a - b).powi(2))
+                            .sum::<f32>()
+                    })
+                    .sum();
+
+                (*s, distance)
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(s, _)| s)
+            .unwrap();
+
+        let (sx, sy) = ((source % width) as isize, (source / width) as isize);
+
+        for p in patch(target).filter(|p| !known[*p]).collect::<Vec<_>>() {
+            let dx = (p % width) as isize - tx;
+            let dy = (p / width) as isize - ty;
+
+            data[p] = data[((sy + dy) as usize) * width + (sx + dx) as usize];
+            gray[p] = srgb_luma(&data[p]);
+            confidence[p] = target_confidence;
+            known[p] = true;
+        }
+    }
+
+    PixelBuffer::new_from_raw(buffer.width(), buffer.height(), data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inpaint() {
+        let buffer = PixelBuffer::new_from_func(20, 20, |x, y| {
+            if (8..12).contains(&x) && (8..12).contains(&y) {
+                Rgb::RED
+            } else {
+                Rgb::new(x as f32 / 20.0, 0.5, 0.5)
+            }
+        });
+
+        let mask = PixelBuffer::new_from_func(20, 20, |x, y| {
+            if (8..12).contains(&x) && (8..12).contains(&y) {
+                Rgb::WHITE
+            } else {
+                Rgb::BLACK
+            }
+        });
+
+        let res = inpaint(&buffer, &mask);
+
+        for (x, y, c) in res.enumerate() {
+            if (8..12).contains(&x) && (8..12).contains(&y) {
+                assert!(
+                    (c.red() - x as f32 / 20.0).abs() < 0.05,
+                    "{} {} {:?}",
+                    x,
+                    y,
+                    c
+                );
+                assert!((c.green() - 0.5).abs() < 1e-4);
+            } else {
+                assert_eq!(&c, buffer.get_pixel(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn test_inpaint_without_mask() {
+        let buffer = PixelBuffer::new_from_func(5, 5, |x, _| Rgb::new(x as f32 / 5.0, 0.0, 0.0));
+
+        let res = inpaint(&buffer, &PixelBuffer::new(2, 2));
+        assert_eq!(res.data(), buffer.data());
+
+        let res = inpaint(&buffer, &PixelBuffer::new_with_color(5, 5, Rgb::WHITE));
+        assert_eq!(res.data(), buffer.data());
+    }
+
+    #[test]
+    fn test_inpaint_exemplar() {
+        let buffer = PixelBuffer::new_from_func(40, 30, |x, y| {
+            if x % 6 < 2 {
+                Rgb::new(0.8, 0.2, 0.1)
+            } else if y < 15 {
+                Rgb::new(0.1, 0.3, 0.9)
+            } else {
+                Rgb::new(0.2, 0.7, 0.2)
+            }
+        });
+
+        let in_hole = |x: u32, y: u32| (15..25).contains(&x) && (10..20).contains(&y);
+
+        let damaged =
+            buffer.map_colors_enumerated(|x, y, c| if in_hole(x, y) { Rgb::WHITE } else { *c });
+        let mask = PixelBuffer::new_from_func(40, 30, |x, y| {
+            if in_hole(x, y) {
+                Rgb::WHITE
+            } else {
+                Rgb::BLACK
+            }
+        });
+
+        let res = inpaint_exemplar(&damaged, &mask, 7);
+
+        // The stripes and the horizontal edge are continued into the hole
+        assert_eq!(res.data(), buffer.data());
+    }
+
+    #[test]
+    fn test_inpaint_exemplar_without_mask() {
+        let buffer = PixelBuffer::new_from_func(5, 5, |x, _| Rgb::new(x as f32 / 5.0, 0.0, 0.0));
+
+        let res = inpaint_exemplar(&buffer, &PixelBuffer::new(5, 5), 9);
+        assert_eq!(res.data(), buffer.data());
+
+        // Without complete source patches the diffusion fill gets used
+        let mut mask = PixelBuffer::new(5, 5);
+        mask.put_pixel(2, 2, Rgb::WHITE);
+        let res = inpaint_exemplar(&buffer, &mask, 9);
+        assert_eq!(res.width(), 5);
+    }
+}
@@ -0,0 +1,367 @@
+use d10_core::color::{Color, Rgb};
+use d10_core::errors::ParseEnumError;
+use d10_core::pixelbuffer::PixelBuffer;
+
+use std::str::FromStr;
+
+use crate::filters::get_pixel_bilinear;
+
+/// Size of the blocks that get a motion vector of their own
+const BLOCK_SIZE: usize = 8;
+
+/// Search range in pixels on every level of the pyramid
+const SEARCH_RADIUS: i32 = 4;
+
+/// Number of pyramid levels, which allows motions of up to 60 pixels
+const LEVELS: usize = 4;
+
+/// Method to compute the frames between two frames of an animation
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FrameInterpolation {
+    /// Blend both frames which is fast but shows moving objects twice
+    CrossFade,
+    /// Move the pixels along the motion estimated by block matching in both directions
+    ///
+    /// Works best for objects moving over a textured background. Areas where no motion matches
+    /// both frames fall back to cross-fading.
+    OpticalFlow,
+}
+
+impl FromStr for FrameInterpolation {
+    type Err = ParseEnumError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        use FrameInterpolation::*;
+        match value {
+            "cross_fade" | "default" => Ok(CrossFade),
+            "optical_flow" => Ok(OpticalFlow),
+            _ => Err(ParseEnumError::new(value, "FrameInterpolation")),
+        }
+    }
+}
+
+fn mix(a: &Rgb, b: &Rgb, t: f32) -> Rgb {
+    Rgb {
+        data: [
+            a.data[0] * (1.0 - t) + b.data[0] * t,
+            a.data[1] * (1.0 - t) + b.data[1] * t,
+            a.data[2] * (1.0 - t) + b.data[2] * t,
+            a.data[3] * (1.0 - t) + b.data[3] * t,
+        ],
+    }
+}
+
+fn difference(a: &Rgb, b: &Rgb) -> f32 {
+    a.data.iter().zip(b.data).map(|(a, b)| (a - b).abs()).sum()
+}
+
+/// Gray values of an image used to match blocks
+struct Plane {
+    width: usize,
+    height: usize,
+    data: Vec<f32>,
+}
+
+impl Plane {
+    fn new(buffer: &PixelBuffer<Rgb>) -> Plane {
+        Plane {
+            width: buffer.width() as usize,
+            height: buffer.height() as usize,
+            data: buffer
+                .data()
+                .iter()
+                .map(|c| {
+                    let s = c.to_srgb();
+                    (s.red() + s.green() + s.blue()) / 3.0 * c.alpha()
+                })
+                .collect(),
+        }
+    }
+
+    fn get(&self, x: i32, y: i32) -> f32 {
+        let x = x.clamp(0, self.width as i32 - 1) as usize;
+        let y = y.clamp(0, self.height as i32 - 1) as usize;
+        self.data[y * self.width + x]
+    }
+
+    /// Half the size by averaging 2x2 pixels
+    fn downscale(&self) -> Plane {
+        let width = self.width.div_ceil(2);
+        let height = self.height.div_ceil(2);
+
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height as i32 {
+            for x in 0..width as i32 {
+                let sum = self.get(x * 2, y * 2)
+                    + self.get(x * 2 + 1, y * 2)
+                    + self.get(x * 2, y * 2 + 1)
+                    + self.get(x * 2 + 1, y * 2 + 1);
+                data.push(sum / 4.0);
+            }
+        }
+
+        Plane {
+            width,
+            height,
+            data,
+        }
+    }
+}
+
+/// Motion vectors of all blocks from the first to the second frame
+struct Flow {
+    blocks_x: usize,
+    blocks_y: usize,
+    vectors: Vec<(f32, f32)>,
+}
+
+impl Flow {
+    /// Estimate the motion by block matching from the coarsest to the finest level of a pyramid
+    fn estimate(a: &PixelBuffer<Rgb>, b: &PixelBuffer<Rgb>) -> Flow {
+        let mut levels = vec![(Plane::new(a), Plane::new(b))];
+        for i in 1..LEVELS {
+            let (a, b) = &levels[i - 1];
+            let next = (a.downscale(), b.downscale());
+            levels.push(next);
+        }
+
+        let blocks_x = (a.width() as usize).div_ceil(BLOCK_SIZE);
+        let blocks_y = (a.height() as usize).div_ceil(BLOCK_SIZE);
+        let mut vectors = vec![(0i32, 0i32); blocks_x * blocks_y];
+
+        for (level, (a, b)) in levels.iter().enumerate().rev() {
+            let size = (BLOCK_SIZE >> level).max(2) as i32;
+
+            for (i, vector) in vectors.iter_mut().enumerate() {
+                let x = ((i % blocks_x * BLOCK_SIZE) >> level) as i32;
+                let y = ((i / blocks_x * BLOCK_SIZE) >> level) as i32;
+
+                *vector = best_match(a, b, x, y, size, *vector);
+
+                if level > 0 {
+                    *vector = (vector.0 * 2, vector.1 * 2);
+                }
+            }
+        }
+
+        Flow {
+            blocks_x,
+            blocks_y,
+            vectors: median_filter(&vectors, blocks_x, blocks_y),
+        }
+    }
+
+    fn vector(&self, x: i32, y: i32) -> (f32, f32) {
+        let x = x.clamp(0, self.blocks_x as i32 - 1) as usize;
+        let y = y.clamp(0, self.blocks_y as i32 - 1) as usize;
+        self.vectors[y * self.blocks_x + x]
+    }
+
+    /// Motion at a pixel interpolated between the centers of the surrounding blocks
+    fn at(&self, x: u32, y: u32) -> (f32, f32) {
+        let center = BLOCK_SIZE as f32 / 2.0 - 0.5;
+        let bx = (x as f32 - center) / BLOCK_SIZE as f32;
+        let by = (y as f32 - center) / BLOCK_SIZE as f32;
+
+        let (x0, y0) = (bx.floor(), by.floor());
+        let (tx, ty) = (bx - x0, by - y0);
+        let (x0, y0) = (x0 as i32, y0 as i32);
+
+        let v00 = self.vector(x0, y0);
+        let v10 = self.vector(x0 + 1, y0);
+        let v01 = self.vector(x0, y0 + 1);
+        let v11 = self.vector(x0 + 1, y0 + 1);
+
+        let calc = |a: f32, b: f32, c: f32, d: f32| {
+            (a * (1.0 - tx) + b * tx) * (1.0 - ty) + (c * (1.0 - tx) + d * tx) * ty
+        };
+
+        (
+            calc(v00.0, v10.0, v01.0, v11.0),
+            calc(v00.1, v10.1, v01.1, v11.1),
+        )
+    }
+}
+
+/// Find the offset around `start` where the block of `a` at `x`, `y` matches `b` best
+///
+/// Ties keep the vector closest to the start, so blocks without details don't move.
+fn best_match(a: &Plane, b: &Plane, x: i32, y: i32, size: i32, start: (i32, i32)) -> (i32, i32) {
+    let difference = |dx: i32, dy: i32| {
+        let mut sum = 0.0;
+        for by in y..y + size {
+            for bx in x..x + size {
+                sum += (a.get(bx, by) - b.get(bx + dx, by + dy)).abs();
+            }
+        }
+        sum
+    };
+
+    let mut best = start;
+    let mut best_difference = difference(start.0, start.1);
+    let mut best_distance = 0;
+
+    for dy in -SEARCH_RADIUS..=SEARCH_RADIUS {
+        for dx in -SEARCH_RADIUS..=SEARCH_RADIUS {
+            let value = difference(start.0 + dx, start.1 + dy);
+            let distance = dx * dx + dy * dy;
+
+            if value < best_difference - 1e-4
+                || (value <= best_difference + 1e-4 && distance < best_distance)
+            {
+                best = (start.0 + dx, start.1 + dy);
+                best_difference = value;
+                best_distance = distance;
+            }
+        }
+    }
+
+    best
+}
+
+/// Remove outliers of the vectors with a 3x3 median filter of each component
+fn median_filter(vectors: &[(i32, i32)], width: usize, height: usize) -> Vec<(f32, f32)> {
+    let mut result = Vec::with_capacity(vectors.len());
+
+    for y in 0..height as i32 {
+        for x in 0..width as i32 {
+            let mut xs = Vec::with_capacity(9);
+            let mut ys = Vec::with_capacity(9);
+
+            for ny in (y - 1).max(0)..(y + 2).min(height as i32) {
+                for nx in (x - 1).max(0)..(x + 2).min(width as i32) {
+                    let v = vectors[ny as usize * width + nx as usize];
+                    xs.push(v.0);
+                    ys.push(v.1);
+                }
+            }
+
+            xs.sort_unstable();
+            ys.sort_unstable();
+
+            result.push((xs[xs.len() / 2] as f32, ys[ys.len() / 2] as f32));
+        }
+    }
+
+    result
+}
+
+/// Compute the frame at `t` between the frames `a` (0.0) and `b` (1.0)
+///
+/// Returns `None` if the frames differ in size.
+pub fn interpolate_frames(
+    a: &PixelBuffer<Rgb>,
+    b: &PixelBuffer<Rgb>,
+    t: f32,
+    method: FrameInterpolation,
+) -> Option<PixelBuffer<Rgb>> {
+    if a.width() != b.width() || a.height() != b.height() {
+        return None;
+    }
+
+    let t = t.clamp(0.0, 1.0);
+
+    if a.is_empty() {
+        return Some(a.clone());
+    }
+
+    Some(match method {
+        FrameInterpolation::CrossFade => {
+            PixelBuffer::new_from_func(a.width(), a.height(), |x, y| {
+                mix(a.get_pixel(x, y), b.get_pixel(x, y), t)
+            })
+        }
+        FrameInterpolation::OpticalFlow => {
+            let forward = Flow::estimate(a, b);
+            let backward = Flow::estimate(b, a);
+
+            PixelBuffer::new_from_func(a.width(), a.height(), |x, y| {
+                let (bx, by) = backward.at(x, y);
+                let candidates = [(0.0, 0.0), forward.at(x, y), (-bx, -by)];
+
+                let (x, y) = (x as f32, y as f32);
+
+                // Use the motion for which both frames agree the most
+                let (c1, c2) = candidates
+                    .iter()
+                    .map(|(dx, dy)| {
+                        let c1 = get_pixel_bilinear(a, x - dx * t, y - dy * t);
+                        let c2 = get_pixel_bilinear(b, x + dx * (1.0 - t), y + dy * (1.0 - t));
+                        (c1, c2)
+                    })
+                    .min_by(|(a1, a2), (b1, b2)| difference(a1, a2).total_cmp(&difference(b1, b2)))
+                    .unwrap_or_default();
+
+                mix(&c1, &c2, t)
+            })
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Textured background with a white square at `offset`
+    fn frame(offset: u32) -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(64, 48, |x, y| {
+            if (offset..offset + 12).contains(&x) && (16..28).contains(&y) {
+                Rgb::WHITE
+            } else {
+                let v = ((x * 7 + y * 13) % 10) as f32 / 20.0;
+                Rgb::new(v, v, v)
+            }
+        })
+    }
+
+    #[test]
+    fn test_cross_fade() {
+        let a = frame(10);
+        let b = frame(20);
+
+        let result = interpolate_frames(&a, &b, 0.5, FrameInterpolation::CrossFade).unwrap();
+        assert_eq!(
+            result.get_pixel(12, 20),
+            &mix(&Rgb::WHITE, b.get_pixel(12, 20), 0.5)
+        );
+
+        for (t, expected) in [(0.0, &a), (1.0, &b), (-1.0, &a)] {
+            let result = interpolate_frames(&a, &b, t, FrameInterpolation::CrossFade).unwrap();
+            assert_eq!(result.data(), expected.data());
+        }
+
+        let small = PixelBuffer::new(2, 2);
+        assert!(interpolate_frames(&a, &small, 0.5, FrameInterpolation::CrossFade).is_none());
+    }
+
+    #[test]
+    fn test_optical_flow() {
+        let a = frame(10);
+        let b = frame(22);
+
+        let result = interpolate_frames(&a, &b, 0.5, FrameInterpolation::OpticalFlow).unwrap();
+
+        // The square moved halfway instead of being shown twice
+        for x in 17..27 {
+            assert_eq!(result.get_pixel(x, 22), &Rgb::WHITE, "{}", x);
+        }
+        assert_ne!(result.get_pixel(12, 22), &Rgb::WHITE);
+        assert_ne!(result.get_pixel(31, 22), &Rgb::WHITE);
+
+        let result = interpolate_frames(&a, &b, 0.0, FrameInterpolation::OpticalFlow).unwrap();
+        assert_eq!(result.data(), a.data());
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "optical_flow".parse::<FrameInterpolation>().unwrap(),
+            FrameInterpolation::OpticalFlow
+        );
+        assert_eq!(
+            "default".parse::<FrameInterpolation>().unwrap(),
+            FrameInterpolation::CrossFade
+        );
+        assert!("flow".parse::<FrameInterpolation>().is_err());
+    }
+}
@@ -0,0 +1,285 @@
+use d10_core::color::{Color, Rgb, Srgb};
+use d10_core::pixelbuffer::PixelBuffer;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Default strength of embedded watermarks
+pub const DEFAULT_WATERMARK_STRENGTH: f32 = 0.02;
+
+/// Detection scores above this value indicate an embedded watermark
+///
+/// Scores of images without the watermark are normally distributed around 0 with a standard
+/// deviation of 1, so false positives are extremely unlikely.
+pub const DEFAULT_WATERMARK_THRESHOLD: f32 = 6.0;
+
+const BLOCK_SIZE: usize = 8;
+
+type Block = [[f32; BLOCK_SIZE]; BLOCK_SIZE];
+
+/// Coefficients with `u + v` in this range carry the watermark
+///
+/// Low frequencies would cause visible changes while high frequencies get removed by jpeg.
+const MIN_FREQUENCY: usize = 3;
+const MAX_FREQUENCY: usize = 5;
+
+/// Orthonormal 8x8 DCT-II
+struct Dct {
+    cos: Block,
+}
+
+impl Dct {
+    fn new() -> Dct {
+        let mut cos = [[0.0; BLOCK_SIZE]; BLOCK_SIZE];
+
+        for (u, row) in cos.iter_mut().enumerate() {
+            let scale = if u == 0 {
+                (1.0 / BLOCK_SIZE as f32).sqrt()
+            } else {
+                (2.0 / BLOCK_SIZE as f32).sqrt()
+            };
+
+            for (x, v) in row.iter_mut().enumerate() {
+                *v = scale
+                    * ((2 * x + 1) as f32 * u as f32 * std::f32::consts::PI
+                        / (2 * BLOCK_SIZE) as f32)
+                        .cos();
+            }
+        }
+
+        Dct { cos }
+    }
+
+    fn forward(&self, block: &Block) -> Block {
+        let mut out = [[0.0; BLOCK_SIZE]; BLOCK_SIZE];
+
+        for (v, out_row) in out.iter_mut().enumerate() {
+            for (u, out) in out_row.iter_mut().enumerate() {
+                let mut sum = 0.0;
+                for (y, row) in block.iter().enumerate() {
+                    for (x, value) in row.iter().enumerate() {
+                        sum += value * self.cos[u][x] * self.cos[v][y];
+                    }
+                }
+                *out = sum;
+            }
+        }
+
+        out
+    }
+
+    fn inverse(&self, coeffs: &Block) -> Block {
+        let mut out = [[0.0; BLOCK_SIZE]; BLOCK_SIZE];
+
+        for (y, out_row) in out.iter_mut().enumerate() {
+            for (x, out) in out_row.iter_mut().enumerate() {
+                let mut sum = 0.0;
+                for (v, row) in coeffs.iter().enumerate() {
+                    for (u, value) in row.iter().enumerate() {
+                        sum += value * self.cos[u][x] * self.cos[v][y];
+                    }
+                }
+                *out = sum;
+            }
+        }
+
+        out
+    }
+}
+
+fn is_mid_frequency(u: usize, v: usize) -> bool {
+    (MIN_FREQUENCY..=MAX_FREQUENCY).contains(&(u + v))
+}
+
+/// Random signs for the mid frequency coefficients of the next block
+fn next_pattern(rng: &mut StdRng) -> Block {
+    let mut pattern = [[0.0; BLOCK_SIZE]; BLOCK_SIZE];
+
+    for (v, row) in pattern.iter_mut().enumerate() {
+        for (u, value) in row.iter_mut().enumerate() {
+            if is_mid_frequency(u, v) {
+                *value = if rng.gen::<bool>() { 1.0 } else { -1.0 };
+            }
+        }
+    }
+
+    pattern
+}
+
+/// Number of complete 8x8 blocks in both directions
+fn block_count(buffer: &PixelBuffer<Rgb>) -> (usize, usize) {
+    (
+        buffer.width() as usize / BLOCK_SIZE,
+        buffer.height() as usize / BLOCK_SIZE,
+    )
+}
+
+/// Embed an invisible watermark identified by `key`
+///
+/// A pseudo random pattern derived from the key is added to the mid frequency DCT coefficients
+/// of the luma of all complete 8x8 blocks. The watermark survives mild jpeg compression and color
+/// changes but not cropping, resizing or rotation. Higher strengths are more robust but may cause
+/// visible noise in flat areas.
+pub fn embed_invisible_watermark(
+    buffer: &PixelBuffer<Rgb>,
+    key: u64,
+    strength: f32,
+) -> PixelBuffer<Rgb> {
+    let dct = Dct::new();
+    let mut rng = StdRng::seed_from_u64(key);
+    let (blocks_x, blocks_y) = block_count(buffer);
+
+    let mut result = buffer.clone();
+
+    for by in 0..blocks_y {
+        for bx in 0..blocks_x {
+            let mut coeffs = next_pattern(&mut rng);
+            for value in coeffs.iter_mut().flatten() {
+                *value *= strength;
+            }
+
+            // The DCT is linear, so only the change of the luma needs to be transformed
+            let delta = dct.inverse(&coeffs);
+
+            for (y, row) in delta.iter().enumerate() {
+                for (x, d) in row.iter().enumerate() {
+                    let px = (bx * BLOCK_SIZE + x) as u32;
+                    let py = (by * BLOCK_SIZE + y) as u32;
+
+                    let c = result.get_pixel(px, py).to_srgb();
+                    let c = Srgb::new_with_alpha(
+                        c.data[0] + d,
+                        c.data[1] + d,
+                        c.data[2] + d,
+                        c.alpha(),
+                    );
+                    result.put_pixel(px, py, c.to_rgb());
+                }
+            }
+        }
+    }
+
+    result
+}
+
+/// Detect an invisible watermark embedded with `embed_invisible_watermark()`
+///
+/// Returns the normalized correlation of the mid frequency coefficients with the pattern of
+/// the key. Compare it against `DEFAULT_WATERMARK_THRESHOLD` or a custom threshold.
+pub fn detect_invisible_watermark(buffer: &PixelBuffer<Rgb>, key: u64) -> f32 {
+    let dct = Dct::new();
+    let mut rng = StdRng::seed_from_u64(key);
+    let (blocks_x, blocks_y) = block_count(buffer);
+
+    let width = buffer.width() as usize;
+    let luma: Vec<f32> = buffer
+        .data()
+        .iter()
+        .map(|c| {
+            let c = c.to_srgb();
+            c.data[0] * 0.212_656 + c.data[1] * 0.715_158 + c.data[2] * 0.072_186
+        })
+        .collect();
+
+    let mut correlation = 0.0f64;
+    let mut energy = 0.0f64;
+
+    for by in 0..blocks_y {
+        for bx in 0..blocks_x {
+            let pattern = next_pattern(&mut rng);
+
+            let mut block = [[0.0; BLOCK_SIZE]; BLOCK_SIZE];
+            for (y, row) in block.iter_mut().enumerate() {
+                let offset = (by * BLOCK_SIZE + y) * width + bx * BLOCK_SIZE;
+                row.copy_from_slice(&luma[offset..offset + BLOCK_SIZE]);
+            }
+
+            let coeffs = dct.forward(&block);
+
+            for (c, p) in coeffs.iter().flatten().zip(pattern.iter().flatten()) {
+                if *p != 0.0 {
+                    correlation += (*c * *p) as f64;
+                    energy += (*c * *c) as f64;
+                }
+            }
+        }
+    }
+
+    if energy > 0.0 {
+        (correlation / energy.sqrt()) as f32
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_buffer() -> PixelBuffer<Rgb> {
+        let mut rng = StdRng::seed_from_u64(1);
+        PixelBuffer::new_from_func(64, 48, |x, y| {
+            let v = (x as f32 / 64.0 + y as f32 / 96.0) * 0.6 + rng.gen::<f32>() * 0.2;
+            Rgb::new(v, v * 0.8, 0.3)
+        })
+    }
+
+    #[test]
+    fn test_dct_roundtrip() {
+        let dct = Dct::new();
+
+        let mut block = [[0.0; BLOCK_SIZE]; BLOCK_SIZE];
+        for (y, row) in block.iter_mut().enumerate() {
+            for (x, value) in row.iter_mut().enumerate() {
+                *value = (x * 3 + y * 5 % 7) as f32 / 40.0;
+            }
+        }
+
+        let res = dct.inverse(&dct.forward(&block));
+
+        for (a, b) in block.iter().flatten().zip(res.iter().flatten()) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_embed_detect() {
+        let buffer = test_buffer();
+
+        let marked = embed_invisible_watermark(&buffer, 42, DEFAULT_WATERMARK_STRENGTH);
+
+        let score = detect_invisible_watermark(&marked, 42);
+        assert!(score > DEFAULT_WATERMARK_THRESHOLD, "score {}", score);
+        assert!(detect_invisible_watermark(&marked, 43).abs() < DEFAULT_WATERMARK_THRESHOLD);
+        assert!(detect_invisible_watermark(&buffer, 42).abs() < DEFAULT_WATERMARK_THRESHOLD);
+    }
+
+    #[test]
+    fn test_embed_is_invisible() {
+        let buffer = test_buffer();
+
+        let marked = embed_invisible_watermark(&buffer, 7, DEFAULT_WATERMARK_STRENGTH);
+
+        let diffs: Vec<f32> = buffer
+            .data()
+            .iter()
+            .zip(marked.data())
+            .map(|(c1, c2)| (c1.to_srgb().data[0] - c2.to_srgb().data[0]).abs())
+            .collect();
+
+        let mean = diffs.iter().sum::<f32>() / diffs.len() as f32;
+        let max = diffs.iter().copied().fold(0.0, f32::max);
+
+        assert!(mean < 0.01, "mean {}", mean);
+        assert!(max < 0.08, "max {}", max);
+    }
+
+    #[test]
+    fn test_small_image() {
+        let buffer = PixelBuffer::new_with_color(5, 5, Rgb::RED);
+
+        let marked = embed_invisible_watermark(&buffer, 1, DEFAULT_WATERMARK_STRENGTH);
+
+        assert_eq!(marked.data(), buffer.data());
+        assert_eq!(detect_invisible_watermark(&marked, 1), 0.0);
+    }
+}
@@ -1,6 +1,15 @@
 use d10_codecs::{decode_buffer, encode, EncodingFormat};
 use d10_core::color::Rgb;
 use d10_core::pixelbuffer::PixelBuffer;
+use d10_core::region::Region;
+
+use crate::delta_e;
+
+/// Size of the blocks the jpeg encoder works on
+const BLOCK_SIZE: u32 = 8;
+
+/// Mean color difference of a block that is shown with the hottest color
+const MAX_BLOCK_ERROR: f32 = 10.0;
 
 /// Returns a new buffer with a simulated jpeg quality
 ///
@@ -29,3 +38,80 @@ pub fn jpeg_quality(
 
     out
 }
+
+/// Black over red and yellow to white for values between 0.0 and 1.0
+fn heat_color(t: f32) -> Rgb {
+    let t = t.clamp(0.0, 1.0) * 3.0;
+    Rgb::new(
+        t.min(1.0),
+        (t - 1.0).clamp(0.0, 1.0),
+        (t - 2.0).clamp(0.0, 1.0),
+    )
+}
+
+/// Returns a heatmap of the errors introduced by encoding the image as jpeg with the given quality
+///
+/// Every 8x8 block is colored by its mean CIE76 color difference to the original,
+/// going from black (no visible loss) over red and yellow to white for a mean difference of 10 or more.
+/// The alpha channel is ignored.
+pub fn jpeg_artifact_map(buffer: &PixelBuffer<Rgb>, quality: u8) -> PixelBuffer<Rgb> {
+    let encoded = jpeg_quality(buffer, quality, true);
+
+    let mut out = PixelBuffer::new_with_color(buffer.width(), buffer.height(), Rgb::BLACK);
+
+    for tile in buffer.tiles(BLOCK_SIZE, BLOCK_SIZE) {
+        let sum: f32 = tile
+            .enumerate()
+            .map(|(x, y, c)| delta_e(&c, encoded.get_pixel(tile.x() + x, tile.y() + y)))
+            .sum();
+
+        let mean = sum / (tile.width() * tile.height()) as f32;
+
+        out.fill_rect(
+            Region::new(tile.x(), tile.y(), tile.width(), tile.height()),
+            heat_color(mean / MAX_BLOCK_ERROR),
+        );
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heat_color() {
+        assert_eq!(heat_color(0.0), Rgb::BLACK);
+        assert_eq!(heat_color(1.0 / 3.0), Rgb::RED);
+        assert_eq!(heat_color(2.0), Rgb::WHITE);
+    }
+
+    #[test]
+    fn test_jpeg_artifact_map() {
+        // Flat left half and a fine checkerboard on the right
+        let buffer = PixelBuffer::new_from_func(32, 16, |x, y| {
+            if x < 16 || (x + y) % 2 == 0 {
+                Rgb::new(0.5, 0.5, 0.5)
+            } else {
+                Rgb::new(1.0, 0.0, 1.0)
+            }
+        });
+
+        let map = jpeg_artifact_map(&buffer, 20);
+
+        assert_eq!((map.width(), map.height()), (32, 16));
+
+        let flat = map.get_pixel(4, 4);
+        let detailed = map.get_pixel(28, 4);
+
+        assert!(flat.red() < 0.2);
+        assert!(detailed.red() > flat.red());
+
+        // Every block has a single color
+        assert_eq!(map.get_pixel(24, 0), map.get_pixel(31, 7));
+
+        let high = jpeg_artifact_map(&buffer, 100);
+        assert!(high.get_pixel(28, 4).red() < detailed.red());
+    }
+}
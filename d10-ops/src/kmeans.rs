@@ -0,0 +1,320 @@
+use d10_core::color::illuminant::D65;
+use d10_core::color::observer::O2;
+use d10_core::color::{Color, Lab, Rgb};
+use d10_core::errors::ParseEnumError;
+use d10_core::pixelbuffer::PixelBuffer;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::str::FromStr;
+
+/// The cluster centers are calculated from a random sample of the pixels of larger images
+const MAX_SAMPLES: usize = 20000;
+
+const MAX_ITERATIONS: usize = 30;
+
+/// Fixed seed to get the same clusters for the same image
+const SEED: u64 = 0x6b6d_6561_6e73;
+
+/// Color space used to compare colors while clustering
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ClusterSpace {
+    /// Linear RGB
+    Rgb,
+    /// Gamma encoded sRGB
+    Srgb,
+    /// CIE Lab which matches the perceived color differences best
+    Lab,
+}
+
+impl FromStr for ClusterSpace {
+    type Err = ParseEnumError;
+
+    fn from_str(value: &str) -> Result<ClusterSpace, Self::Err> {
+        match value {
+            "rgb" => Ok(ClusterSpace::Rgb),
+            "srgb" => Ok(ClusterSpace::Srgb),
+            "lab" | "default" => Ok(ClusterSpace::Lab),
+            _ => Err(ParseEnumError::new(value, "ClusterSpace")),
+        }
+    }
+}
+
+impl ClusterSpace {
+    fn to_point(self, color: &Rgb) -> [f32; 3] {
+        match self {
+            ClusterSpace::Rgb => [color.red(), color.green(), color.blue()],
+            ClusterSpace::Srgb => {
+                let c = color.to_srgb();
+                [c.red(), c.green(), c.blue()]
+            }
+            ClusterSpace::Lab => {
+                let c: Lab<D65, O2> = color.to_lab();
+                [c.data[0], c.data[1], c.data[2]]
+            }
+        }
+    }
+
+    fn to_color(self, point: &[f32; 3]) -> Rgb {
+        let [a, b, c] = *point;
+
+        match self {
+            ClusterSpace::Rgb => Rgb::new(a, b, c),
+            ClusterSpace::Srgb => d10_core::color::Srgb::new(a, b, c).to_rgb(),
+            ClusterSpace::Lab => Lab::<D65, O2>::new(a, b, c).to_rgb(),
+        }
+    }
+}
+
+/// Result of a k-means clustering of the colors of an image
+#[derive(Debug, Clone)]
+pub struct KMeansSegmentation {
+    pub width: u32,
+    pub height: u32,
+    /// Index of the cluster of every pixel in row major order
+    pub labels: Vec<usize>,
+    /// Mean color of every cluster
+    pub colors: Vec<Rgb>,
+}
+
+impl KMeansSegmentation {
+    /// Replace every pixel with the mean color of its cluster while keeping the alpha channel
+    pub fn render(&self, buffer: &PixelBuffer<Rgb>) -> PixelBuffer<Rgb> {
+        let data = buffer
+            .data()
+            .iter()
+            .zip(&self.labels)
+            .map(|(c, label)| self.colors[*label].with_alpha(c.alpha()))
+            .collect();
+
+        PixelBuffer::new_from_raw(self.width, self.height, data)
+    }
+
+    /// Mask that is white for all pixels of the cluster and black for all others
+    pub fn mask(&self, cluster: usize) -> PixelBuffer<Rgb> {
+        let data = self
+            .labels
+            .iter()
+            .map(|label| {
+                if *label == cluster {
+                    Rgb::WHITE
+                } else {
+                    Rgb::BLACK
+                }
+            })
+            .collect();
+
+        PixelBuffer::new_from_raw(self.width, self.height, data)
+    }
+}
+
+fn distance_squared(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    a.iter().zip(b.iter()).map(|(a, b)| (a - b).powi(2)).sum()
+}
+
+fn nearest(centers: &[[f32; 3]], point: &[f32; 3]) -> usize {
+    centers
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| distance_squared(a, point).total_cmp(&distance_squared(b, point)))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+/// Choose initial centers with the k-means++ algorithm
+fn initial_centers(points: &[[f32; 3]], k: usize, rng: &mut StdRng) -> Vec<[f32; 3]> {
+    let mut centers = vec![points[rng.gen_range(0..points.len())]];
+    let mut distances: Vec<f32> = points
+        .iter()
+        .map(|p| distance_squared(p, &centers[0]))
+        .collect();
+
+    while centers.len() < k {
+        let total: f32 = distances.iter().sum();
+        if total <= 0.0 {
+            // Less distinct colors than clusters
+            break;
+        }
+
+        let mut target = rng.gen::<f32>() * total;
+        let index = distances
+            .iter()
+            .position(|d| {
+                target -= d;
+                target <= 0.0
+            })
+            .unwrap_or(points.len() - 1);
+
+        let center = points[index];
+        for (d, p) in distances.iter_mut().zip(points) {
+            *d = d.min(distance_squared(p, &center));
+        }
+
+        centers.push(center);
+    }
+
+    centers
+}
+
+/// Cluster the colors of the image into `k` groups with the k-means algorithm
+///
+/// The result contains the cluster of every pixel and the mean colors of the clusters.
+/// Less than `k` clusters are returned if the image has less distinct colors.
+/// Rendering the clusters with their mean color results in a flat, poster like look.
+pub fn segment_kmeans(
+    buffer: &PixelBuffer<Rgb>,
+    k: usize,
+    space: ClusterSpace,
+) -> KMeansSegmentation {
+    if buffer.is_empty() || k == 0 {
+        return KMeansSegmentation {
+            width: buffer.width(),
+            height: buffer.height(),
+            labels: vec![0; buffer.data().len()],
+            colors: vec![],
+        };
+    }
+
+    let points: Vec<[f32; 3]> = buffer.data().iter().map(|c| space.to_point(c)).collect();
+
+    let mut rng = StdRng::seed_from_u64(SEED);
+
+    let samples: Vec<[f32; 3]> = if points.len() > MAX_SAMPLES {
+        (0..MAX_SAMPLES)
+            .map(|_| points[rng.gen_range(0..points.len())])
+            .collect()
+    } else {
+        points.clone()
+    };
+
+    let mut centers = initial_centers(&samples, k, &mut rng);
+    let mut assignments = vec![usize::MAX; samples.len()];
+
+    for _ in 0..MAX_ITERATIONS {
+        let mut changed = false;
+        for (a, p) in assignments.iter_mut().zip(&samples) {
+            let n = nearest(&centers, p);
+            if *a != n {
+                *a = n;
+                changed = true;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+
+        let mut sums = vec![([0.0f64; 3], 0usize); centers.len()];
+        for (a, p) in assignments.iter().zip(&samples) {
+            let (sum, count) = &mut sums[*a];
+            for (s, v) in sum.iter_mut().zip(p) {
+                *s += *v as f64;
+            }
+            *count += 1;
+        }
+
+        for (center, (sum, count)) in centers.iter_mut().zip(sums) {
+            if count > 0 {
+                *center = sum.map(|s| (s / count as f64) as f32);
+            }
+        }
+    }
+
+    let labels: Vec<usize> = points.iter().map(|p| nearest(&centers, p)).collect();
+
+    // Drop clusters without pixels and renumber the remaining ones
+    let mut used = vec![false; centers.len()];
+    for label in &labels {
+        used[*label] = true;
+    }
+
+    let mut mapping = vec![0; centers.len()];
+    let mut colors = vec![];
+    for (i, center) in centers.iter().enumerate() {
+        if used[i] {
+            mapping[i] = colors.len();
+            colors.push(space.to_color(center));
+        }
+    }
+
+    KMeansSegmentation {
+        width: buffer.width(),
+        height: buffer.height(),
+        labels: labels.iter().map(|l| mapping[*l]).collect(),
+        colors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_buffer() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(30, 20, |x, y| {
+            let n = ((x * 3 + y * 7) % 5) as f32 * 0.01;
+            match (x / 10, y / 10) {
+                (0, _) => Rgb::new(0.8 + n, 0.1, 0.1),
+                (1, 0) => Rgb::new(0.1, 0.7 + n, 0.1),
+                _ => Rgb::new(0.1, 0.1 + n, 0.9),
+            }
+        })
+    }
+
+    #[test]
+    fn test_segment_kmeans() {
+        let buffer = test_buffer();
+
+        for space in [ClusterSpace::Rgb, ClusterSpace::Srgb, ClusterSpace::Lab] {
+            let res = segment_kmeans(&buffer, 3, space);
+
+            assert_eq!(res.colors.len(), 3);
+            assert_eq!(res.labels.len(), 600);
+
+            let label = |x: u32, y: u32| res.labels[(y * 30 + x) as usize];
+            assert_eq!(label(0, 0), label(9, 19));
+            assert_eq!(label(10, 0), label(19, 9));
+            assert_eq!(label(10, 10), label(29, 19));
+            assert_ne!(label(0, 0), label(10, 0));
+            assert_ne!(label(0, 0), label(10, 10));
+            assert_ne!(label(10, 0), label(10, 10));
+
+            let red = res.colors[label(0, 0)];
+            assert!((red.red() - 0.82).abs() < 0.01, "{:?} {:?}", space, red);
+        }
+    }
+
+    #[test]
+    fn test_render_and_mask() {
+        let buffer = test_buffer();
+        let res = segment_kmeans(&buffer, 3, ClusterSpace::Lab);
+
+        let flat = res.render(&buffer);
+        assert_eq!(flat.get_pixel(0, 0), flat.get_pixel(9, 19));
+        assert_ne!(flat.get_pixel(0, 0), flat.get_pixel(10, 0));
+
+        let mask = res.mask(res.labels[0]);
+        assert_eq!(mask.get_pixel(5, 5), &Rgb::WHITE);
+        assert_eq!(mask.get_pixel(15, 5), &Rgb::BLACK);
+    }
+
+    #[test]
+    fn test_less_colors_than_clusters() {
+        let buffer = PixelBuffer::new_with_color(5, 5, Rgb::RED);
+
+        let res = segment_kmeans(&buffer, 4, ClusterSpace::Lab);
+        assert_eq!(res.colors.len(), 1);
+        assert!(res.labels.iter().all(|l| *l == 0));
+
+        let res = segment_kmeans(&buffer, 0, ClusterSpace::Lab);
+        assert!(res.colors.is_empty());
+    }
+
+    #[test]
+    fn test_parse_cluster_space() {
+        assert_eq!(
+            "default".parse::<ClusterSpace>().unwrap(),
+            ClusterSpace::Lab
+        );
+        assert_eq!("srgb".parse::<ClusterSpace>().unwrap(), ClusterSpace::Srgb);
+        assert!("hsl".parse::<ClusterSpace>().is_err());
+    }
+}
@@ -0,0 +1,289 @@
+use d10_core::color::{Color, Rgb};
+use d10_core::pixelbuffer::PixelBuffer;
+
+use crate::{BlendOp, Pipeline};
+
+/// What a layer contributes to the layers below it
+#[derive(Debug, Clone)]
+pub enum LayerContent {
+    /// Pixels placed at the position of the layer
+    Pixels(PixelBuffer<Rgb>),
+    /// Non destructive adjustment of everything below the layer
+    ///
+    /// The pipeline should keep the size of the image. Pixels outside of its result are left
+    /// unchanged.
+    Adjustment(Pipeline),
+}
+
+/// A single layer of a `LayerStack`
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub name: String,
+    pub content: LayerContent,
+    /// Position of the top left corner of the pixels which is ignored by adjustment layers
+    pub x: i32,
+    pub y: i32,
+    /// Gray mask limiting the layer to the bright areas of the mask
+    ///
+    /// The average of the color channels multiplied by the alpha value is used as intensity.
+    /// Areas outside of the mask are hidden.
+    pub mask: Option<PixelBuffer<Rgb>>,
+    pub blend_op: BlendOp,
+    /// Opacity between 0.0 and 1.0
+    pub opacity: f32,
+    pub visible: bool,
+}
+
+impl Layer {
+    fn new(content: LayerContent) -> Layer {
+        Layer {
+            name: String::new(),
+            content,
+            x: 0,
+            y: 0,
+            mask: None,
+            blend_op: BlendOp::Normal,
+            opacity: 1.0,
+            visible: true,
+        }
+    }
+
+    pub fn pixels(buffer: PixelBuffer<Rgb>) -> Layer {
+        Layer::new(LayerContent::Pixels(buffer))
+    }
+
+    pub fn adjustment(pipeline: Pipeline) -> Layer {
+        Layer::new(LayerContent::Adjustment(pipeline))
+    }
+
+    pub fn with_name(mut self, name: &str) -> Layer {
+        self.name = name.to_owned();
+        self
+    }
+
+    pub fn with_position(mut self, x: i32, y: i32) -> Layer {
+        self.x = x;
+        self.y = y;
+        self
+    }
+
+    pub fn with_mask(mut self, mask: PixelBuffer<Rgb>) -> Layer {
+        self.mask = Some(mask);
+        self
+    }
+
+    pub fn with_blend_op(mut self, blend_op: BlendOp) -> Layer {
+        self.blend_op = blend_op;
+        self
+    }
+
+    pub fn with_opacity(mut self, opacity: f32) -> Layer {
+        self.opacity = opacity;
+        self
+    }
+
+    pub fn with_visible(mut self, visible: bool) -> Layer {
+        self.visible = visible;
+        self
+    }
+
+    /// Pixels of the layer with the mask applied to their alpha values
+    ///
+    /// Returns `None` for adjustment layers.
+    pub fn masked_pixels(&self) -> Option<PixelBuffer<Rgb>> {
+        let LayerContent::Pixels(pixels) = &self.content else {
+            return None;
+        };
+
+        let mut pixels = pixels.clone();
+
+        if self.mask.is_some() {
+            for (x, y, c) in pixels.enumerate_mut() {
+                let mask = self.mask_value(x as i32 + self.x, y as i32 + self.y);
+                *c = c.with_alpha(c.alpha() * mask);
+            }
+        }
+
+        Some(pixels)
+    }
+
+    fn mask_value(&self, x: i32, y: i32) -> f32 {
+        match &self.mask {
+            Some(mask) => mask
+                .get_pixel_optional(x, y)
+                .map_or(0.0, |c| (c.red() + c.green() + c.blue()) / 3.0 * c.alpha()),
+            None => 1.0,
+        }
+    }
+
+    fn blend_onto(&self, buffer: &mut PixelBuffer<Rgb>) {
+        let opacity = self.opacity.clamp(0.0, 1.0);
+
+        if !self.visible || opacity <= 0.0 {
+            return;
+        }
+
+        let adjusted;
+        let (source, x_offset, y_offset) = match &self.content {
+            LayerContent::Pixels(pixels) => (pixels, self.x, self.y),
+            LayerContent::Adjustment(pipeline) => {
+                adjusted = pipeline.apply(buffer);
+                (&adjusted, 0, 0)
+            }
+        };
+
+        for (x, y, c) in buffer.enumerate_mut() {
+            let Some(c2) = source.get_pixel_optional(x as i32 - x_offset, y as i32 - y_offset)
+            else {
+                continue;
+            };
+
+            let intensity = opacity * self.mask_value(x as i32, y as i32);
+            if intensity > 0.0 {
+                *c = self.blend_op.blend(*c, *c2, intensity);
+            }
+        }
+    }
+}
+
+/// Minimal document model of layers that are composed from bottom to top
+///
+/// Nothing is modified until `flatten()` renders the visible layers, so layers can be changed,
+/// hidden or reordered at any time.
+#[derive(Debug, Clone)]
+pub struct LayerStack {
+    width: u32,
+    height: u32,
+    /// Color of the canvas below the first layer
+    pub background: Rgb,
+    /// All layers with the bottom layer first
+    pub layers: Vec<Layer>,
+}
+
+impl LayerStack {
+    pub fn new(width: u32, height: u32) -> LayerStack {
+        LayerStack {
+            width,
+            height,
+            background: Rgb::NONE,
+            layers: vec![],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn push(&mut self, layer: Layer) {
+        self.layers.push(layer);
+    }
+
+    pub fn with(mut self, layer: Layer) -> LayerStack {
+        self.layers.push(layer);
+        self
+    }
+
+    pub fn with_background(mut self, background: Rgb) -> LayerStack {
+        self.background = background;
+        self
+    }
+
+    /// Render all visible layers into a single buffer with the size of the stack
+    pub fn flatten(&self) -> PixelBuffer<Rgb> {
+        let mut buffer = PixelBuffer::new_with_color(self.width, self.height, self.background);
+
+        for layer in &self.layers {
+            layer.blend_onto(&mut buffer);
+        }
+
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_color(c: &Rgb, expected: [f32; 4]) {
+        for (v, e) in c.data.iter().zip(expected) {
+            assert!((v - e).abs() < 0.0001, "{:?} != {:?}", c, expected);
+        }
+    }
+
+    #[test]
+    fn test_flatten() {
+        let mask =
+            PixelBuffer::new_from_func(4, 2, |x, _| if x < 2 { Rgb::WHITE } else { Rgb::BLACK });
+
+        let stack = LayerStack::new(4, 2)
+            .with(Layer::pixels(PixelBuffer::new_with_color(
+                4,
+                2,
+                Rgb::new(0.2, 0.4, 0.6),
+            )))
+            .with(Layer::adjustment(Pipeline::new().invert()).with_mask(mask))
+            .with(
+                Layer::pixels(PixelBuffer::new_with_color(2, 1, Rgb::RED))
+                    .with_opacity(0.5)
+                    .with_blend_op(BlendOp::Normal),
+            )
+            .with(Layer::pixels(PixelBuffer::new_with_color(4, 2, Rgb::GREEN)).with_visible(false));
+
+        let result = stack.flatten();
+
+        assert_eq!((result.width(), result.height()), (4, 2));
+        assert_color(result.get_pixel(0, 1), [0.8, 0.6, 0.4, 1.0]);
+        assert_color(result.get_pixel(3, 1), [0.2, 0.4, 0.6, 1.0]);
+        assert_color(result.get_pixel(0, 0), [0.9, 0.3, 0.2, 1.0]);
+        assert_color(result.get_pixel(2, 0), [0.2, 0.4, 0.6, 1.0]);
+    }
+
+    #[test]
+    fn test_background() {
+        let stack = LayerStack::new(2, 2).with_background(Rgb::BLUE);
+        assert_eq!(stack.flatten().get_pixel(1, 1), &Rgb::BLUE);
+
+        let stack = stack.with(
+            Layer::pixels(PixelBuffer::new_with_color(2, 2, Rgb::RED))
+                .with_blend_op(BlendOp::Addition)
+                .with_opacity(2.0),
+        );
+        assert_color(stack.flatten().get_pixel(0, 0), [1.0, 0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_position() {
+        let stack = LayerStack::new(3, 3).with(
+            Layer::pixels(PixelBuffer::new_with_color(2, 2, Rgb::RED))
+                .with_name("red")
+                .with_position(2, -1),
+        );
+
+        assert_eq!(stack.layers[0].name, "red");
+
+        let result = stack.flatten();
+        assert_eq!(result.get_pixel(2, 0), &Rgb::RED);
+        assert_eq!(result.get_pixel(1, 0), &Rgb::NONE);
+        assert_eq!(result.get_pixel(2, 1), &Rgb::NONE);
+    }
+
+    #[test]
+    fn test_masked_pixels() {
+        let mask =
+            PixelBuffer::new_from_func(4, 1, |x, _| if x < 2 { Rgb::WHITE } else { Rgb::BLACK });
+
+        let layer = Layer::pixels(PixelBuffer::new_with_color(2, 1, Rgb::RED))
+            .with_position(1, 0)
+            .with_mask(mask);
+
+        let pixels = layer.masked_pixels().unwrap();
+        assert_eq!(pixels.get_pixel(0, 0), &Rgb::RED);
+        assert_eq!(pixels.get_pixel(1, 0), &Rgb::RED.with_alpha(0.0));
+
+        assert!(Layer::adjustment(Pipeline::new()).masked_pixels().is_none());
+    }
+}
@@ -0,0 +1,70 @@
+use d10_core::color::{Color, Rgb};
+use d10_core::pixelbuffer::PixelBuffer;
+
+use crate::filters::get_pixel_filtered;
+use crate::FilterMode;
+
+/// Correct radial lens distortion, transverse chromatic aberration and vignetting
+///
+/// # Arguments
+/// k1, k2: Radial distortion coefficients. Positive values correct barrel, negative values pincushion distortion
+/// center: Relative position of the optical center. (0.5, 0.5) is the center of the image
+/// ca_red, ca_blue: Scale of the red and blue channel relative to green. 1.0 means no correction
+/// vignette: Amount of brightening applied towards the corners. 0.0 means no correction
+/// filter: Interpolation used to sample the distorted positions
+#[allow(clippy::too_many_arguments)]
+pub fn lens_correct(
+    buffer: &PixelBuffer<Rgb>,
+    k1: f32,
+    k2: f32,
+    center: (f32, f32),
+    ca_red: f32,
+    ca_blue: f32,
+    vignette: f32,
+    filter: FilterMode,
+) -> PixelBuffer<Rgb> {
+    if buffer.is_empty() {
+        return buffer.clone();
+    }
+
+    let center_x = center.0 * (buffer.width() - 1) as f32;
+    let center_y = center.1 * (buffer.height() - 1) as f32;
+
+    // Normalize the radius by the half diagonal to make the coefficients independent of the image size
+    let norm = (buffer.width() as f32).hypot(buffer.height() as f32) / 2.0;
+
+    buffer.map_colors_enumerated(|x, y, _| {
+        let dx = (x as f32 - center_x) / norm;
+        let dy = (y as f32 - center_y) / norm;
+
+        let r2 = dx * dx + dy * dy;
+        let scale = 1.0 + k1 * r2 + k2 * r2 * r2;
+
+        let sample = |channel_scale: f32| {
+            get_pixel_filtered(
+                buffer,
+                center_x + dx * scale * channel_scale * norm,
+                center_y + dy * scale * channel_scale * norm,
+                filter,
+            )
+        };
+
+        let green = sample(1.0);
+
+        let red = if (ca_red - 1.0).abs() < f32::EPSILON {
+            green.red()
+        } else {
+            sample(ca_red).red()
+        };
+
+        let blue = if (ca_blue - 1.0).abs() < f32::EPSILON {
+            green.blue()
+        } else {
+            sample(ca_blue).blue()
+        };
+
+        let gain = 1.0 + vignette * r2;
+
+        Rgb::new_with_alpha(red * gain, green.green() * gain, blue * gain, green.alpha())
+    })
+}
@@ -1,55 +1,182 @@
+mod apply_in_space;
 mod apply_palette;
+mod augment;
+mod auto_enhance;
+mod auto_levels;
 mod balance_channels;
+mod black_bars;
 mod blend;
+mod border;
+mod clone_region;
+mod collage;
 mod compose;
+mod content_hash;
+mod contours;
+mod cover;
 mod crop;
+mod deband;
+mod denoise_chroma;
 mod despeckle;
+mod diff;
+mod displace;
 mod drawing;
+mod duotone;
 mod edge_detection;
 mod equalize;
 mod filters;
 mod flip;
 mod gaussian_blur;
 mod gaussian_noise;
+mod graduated_filter;
+mod halftone;
+mod histogram;
+mod hot_pixels;
+mod hsl_adjust;
+mod inpaint;
 mod interlace;
+mod interpolate_frames;
+mod invisible_watermark;
 mod jpeg_quality;
+mod kmeans;
+mod layers;
+mod lens_correction;
+mod lighting;
+mod lightness;
+mod look;
+mod metrics;
+mod monochrome;
+mod montage;
+mod noise;
+mod offset;
+mod panorama;
+mod perspective;
+mod pipeline;
+mod poisson_noise;
+mod polar;
+mod portrait;
+mod qr;
 mod random_noise;
+mod regions;
+mod relief;
 mod resize;
 mod rgb_noise;
 mod rotate;
 mod rotate_90;
 mod salt_n_pepper_noise;
 mod saturation;
+mod scan_enhance;
+mod seamless;
+mod segmentation;
+mod smart_crop;
+mod speckle_noise;
+mod split;
+mod sprites;
+mod steganography;
+mod straighten;
 mod stretch_contrast;
+mod symmetric_nearest_neighbor;
 mod temperature;
+mod text;
+mod threshold;
+mod trace;
+mod transfer_function;
 mod unsharp;
-mod symmetric_nearest_neighbor;
-mod lightness;
+mod upscale;
+mod watermark;
+mod xbr;
+mod zone_statistics;
 
+pub use apply_in_space::{apply_in_space, extract_channel, replace_channel, ColorSpaceChannel};
 pub use apply_palette::{apply_palette, apply_palette_in_place};
+pub use augment::{Augment, Augmentation};
+pub use auto_enhance::auto_enhance;
+pub use auto_levels::auto_levels;
 pub use balance_channels::{balance, BalanceMode};
+pub use black_bars::detect_black_bars;
 pub use blend::*;
+pub use border::{add_border, polaroid, BorderFill, BorderStyle};
+pub use clone_region::clone_region;
+pub use collage::{collage, CollageLayout, CollageTemplate};
 pub use compose::{compose, compose_slice, try_compose, try_compose_slice};
+pub use content_hash::{content_hash, HashAlgorithm};
+pub use contours::{douglas_peucker, find_contours, Contour};
+pub use cover::{cover, cover_region, Gravity};
 pub use crop::crop;
+pub use deband::{banding_score, deband};
+pub use denoise_chroma::denoise_chroma;
 pub use despeckle::despeckle;
+pub use diff::{delta_e, diff_visualize, DiffMode, DEFAULT_DIFF_THRESHOLD};
+pub use displace::displace;
 pub use drawing::{drawing, DrawingMode};
+pub use duotone::duotone;
 pub use edge_detection::{edge_detection, EdgeDetection};
 pub use equalize::{equalize, EqualizeMode};
 pub use filters::FilterMode;
 pub use flip::{flip_horizontal, flip_vertical};
-pub use gaussian_blur::gaussian_blur;
+pub use gaussian_blur::{gaussian_blur, gaussian_blur_in_place};
 pub use gaussian_noise::{add_gaussian_noise, gaussian_noise};
+pub use graduated_filter::{enhance_sky, graduated_filter, Adjustment};
+pub use halftone::{halftone, HalftoneShape};
+pub use histogram::{Histogram, HISTOGRAM_BINS};
+pub use hot_pixels::{detect_hot_pixels, repair_hot_pixels};
+pub use hsl_adjust::{hsl_adjust, HueRangeAdjustment};
+pub use inpaint::{inpaint, inpaint_exemplar};
 pub use interlace::interlace;
-pub use jpeg_quality::jpeg_quality;
+pub use interpolate_frames::{interpolate_frames, FrameInterpolation};
+pub use invisible_watermark::{
+    detect_invisible_watermark, embed_invisible_watermark, DEFAULT_WATERMARK_STRENGTH,
+    DEFAULT_WATERMARK_THRESHOLD,
+};
+pub use jpeg_quality::{jpeg_artifact_map, jpeg_quality};
+pub use kmeans::{segment_kmeans, ClusterSpace, KMeansSegmentation};
+pub use layers::{Layer, LayerContent, LayerStack};
+pub use lens_correction::lens_correct;
+pub use lighting::{apply_lighting, LightSpec};
+pub use lightness::optimize_lightness;
+pub use look::{apply_look, LookPreset};
+pub use metrics::{compare, Metric};
+pub use monochrome::{to_monochrome, to_monochrome_with_filter, MonochromeFilter};
+pub use montage::montage;
+pub use noise::{generate_noise, NoiseKind, NoiseOptions};
+pub use offset::offset;
+pub use panorama::{
+    cubemap_to_equirect, equirect_to_cubemap, rectilinear_project, stereographic_project,
+};
+pub use perspective::{perspective_warp, Homography};
+pub use pipeline::{Pipeline, PipelineStep};
+pub use poisson_noise::{add_poisson_noise, poisson_noise};
+pub use polar::{fisheye, from_polar, swirl, to_polar};
+pub use portrait::portrait_smooth;
+pub use qr::{detect_qr_codes, QrCode};
 pub use random_noise::{add_random_noise, random_noise};
-pub use resize::resize;
+#[cfg(feature = "skin-detector")]
+pub use regions::SkinToneDetector;
+pub use regions::{blur_regions, pixelate_regions, RegionDetector};
+pub use relief::{emboss, height_to_ao, height_to_normal_map};
+pub use resize::{resize, resize_with_options, ResizeOptions};
 pub use rgb_noise::{add_rgb_noise, rgb_noise};
 pub use rotate::rotate;
 pub use rotate_90::{rotate180, rotate270, rotate90};
 pub use salt_n_pepper_noise::{add_salt_n_pepper_noise, salt_n_pepper_noise};
 pub use saturation::{optimize_saturation, SaturationMode};
+pub use scan_enhance::{detect_page, scan_enhance, ScanMode};
+pub use seamless::make_seamless;
+pub use segmentation::{segment_foreground, SegmentationHint};
+pub use smart_crop::{smart_crop, smart_crop_region};
+pub use speckle_noise::{add_speckle_noise, speckle_noise};
+pub use split::{join_vertical, split_vertical};
+pub use sprites::{pack_sprites, SpriteSheet};
+pub use steganography::{data_capacity, embed_data, extract_data, StegoError};
+pub use straighten::{detect_horizon_angle, straighten};
 pub use stretch_contrast::stretch_contrast;
+pub use symmetric_nearest_neighbor::symmetric_nearest_neighbor;
 pub use temperature::{change_color_temperature, optimize_color_temperature};
+pub use text::{draw_text, text_size};
+pub use threshold::{adaptive_threshold, otsu_threshold, threshold};
+pub use trace::{trace_bitmap, PathSegment, SvgDocument, SvgPath, TraceOptions};
+pub use transfer_function::{decode_transfer, encode_transfer};
 pub use unsharp::unsharp;
-pub use symmetric_nearest_neighbor::symmetric_nearest_neighbor;
-pub use lightness::optimize_lightness;
\ No newline at end of file
+pub use upscale::upscale_enhanced;
+pub use watermark::{watermark, WatermarkPosition};
+pub use xbr::{xbr, XbrScale};
+pub use zone_statistics::{zone_statistics, ZoneStatistics};
@@ -0,0 +1,269 @@
+use d10_core::color::{Color, Rgb};
+use d10_core::pixelbuffer::PixelBuffer;
+
+/// Factor applied to the slopes of the pseudo height map
+///
+/// Larger values make the relief of the image more visible in the light.
+const RELIEF: f32 = 4.0;
+
+/// A light source illuminating the image
+///
+/// Positions and distances are relative to the larger side of the image. Angles are in degrees.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LightSpec {
+    /// Uniform light from all directions
+    Ambient { color: Rgb, intensity: f32 },
+    /// Spotlight at `x`, `y` and `height` above the image
+    ///
+    /// The intensity falls off with the distance to the point below the light and reaches half
+    /// of its value at `radius`.
+    Point {
+        x: f32,
+        y: f32,
+        height: f32,
+        radius: f32,
+        color: Rgb,
+        intensity: f32,
+    },
+    /// Parallel light like sunlight coming from `azimuth` at `elevation` above the horizon
+    ///
+    /// An azimuth of 0 lights the image from the top, 90 from the left. With a `falloff` above 0
+    /// the intensity decreases linearly by this amount towards the opposite side of the image.
+    Directional {
+        azimuth: f32,
+        elevation: f32,
+        falloff: f32,
+        color: Rgb,
+        intensity: f32,
+    },
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let length = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+
+    if length > 0.0 {
+        [v[0] / length, v[1] / length, v[2] / length]
+    } else {
+        [0.0, 0.0, 1.0]
+    }
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+/// Surface normals of a height map derived from the gamma encoded luma of the pixels
+fn luma_normals(buffer: &PixelBuffer<Rgb>) -> Vec<[f32; 3]> {
+    let width = buffer.width() as i32;
+    let height = buffer.height() as i32;
+
+    let luma: Vec<f32> = buffer
+        .data()
+        .iter()
+        .map(|c| c.to_gray().to_srgb().red())
+        .collect();
+
+    let get =
+        |x: i32, y: i32| luma[(y.clamp(0, height - 1) * width + x.clamp(0, width - 1)) as usize];
+
+    let mut normals = Vec::with_capacity(luma.len());
+
+    for y in 0..height {
+        for x in 0..width {
+            let dx = (get(x + 1, y) - get(x - 1, y)) / 2.0;
+            let dy = (get(x, y + 1) - get(x, y - 1)) / 2.0;
+
+            normals.push(normalize([-dx * RELIEF, -dy * RELIEF, 1.0]));
+        }
+    }
+
+    normals
+}
+
+/// Illuminate the image with the given lights
+///
+/// The colors of the image are multiplied with the sum of all lights. The diffuse reflection
+/// of point and directional lights uses a pseudo height map from the luma of the image, so
+/// bright structures get a slight relief. Without any lights the image turns black, so most
+/// setups need an ambient light for the shadows.
+pub fn apply_lighting(buffer: &PixelBuffer<Rgb>, lights: &[LightSpec]) -> PixelBuffer<Rgb> {
+    if buffer.is_empty() {
+        return buffer.clone();
+    }
+
+    let normals = luma_normals(buffer);
+
+    let size = buffer.width().max(buffer.height()) as f32;
+    let width = buffer.width() as f32 / size;
+    let height = buffer.height() as f32 / size;
+
+    let mut result = buffer.clone();
+
+    for ((x, y, c), normal) in result.enumerate_mut().zip(normals) {
+        // Position of the center of the pixel relative to the larger side
+        let px = (x as f32 + 0.5) / size;
+        let py = (y as f32 + 0.5) / size;
+
+        let mut light = [0.0; 3];
+
+        for spec in lights {
+            let (color, value) = match *spec {
+                LightSpec::Ambient { color, intensity } => (color, intensity),
+                LightSpec::Point {
+                    x,
+                    y,
+                    height,
+                    radius,
+                    color,
+                    intensity,
+                } => {
+                    let direction = normalize([x - px, y - py, height.max(0.0)]);
+                    let distance2 = (x - px) * (x - px) + (y - py) * (y - py);
+                    let radius2 = (radius * radius).max(f32::EPSILON);
+
+                    let attenuation = 1.0 / (1.0 + distance2 / radius2);
+                    let diffuse = dot(normal, direction).max(0.0);
+
+                    (color, intensity * attenuation * diffuse)
+                }
+                LightSpec::Directional {
+                    azimuth,
+                    elevation,
+                    falloff,
+                    color,
+                    intensity,
+                } => {
+                    let (sin_a, cos_a) = azimuth.to_radians().sin_cos();
+                    let (sin_e, cos_e) = elevation.to_radians().sin_cos();
+
+                    let direction = [-sin_a * cos_e, -cos_a * cos_e, sin_e];
+                    let diffuse = dot(normal, direction).max(0.0);
+
+                    // Position between the lit side (0.0) and the opposite side (1.0)
+                    let projections = [(0.0, 0.0), (width, 0.0), (0.0, height), (width, height)]
+                        .map(|(x, y)| x * sin_a + y * cos_a);
+                    let min = projections.iter().copied().fold(f32::INFINITY, f32::min);
+                    let max = projections
+                        .iter()
+                        .copied()
+                        .fold(f32::NEG_INFINITY, f32::max);
+                    let position = (px * sin_a + py * cos_a - min) / (max - min).max(f32::EPSILON);
+
+                    let gradient = (1.0 - falloff * position).max(0.0);
+
+                    (color, intensity * diffuse * gradient)
+                }
+            };
+
+            for (l, v) in light.iter_mut().zip(&color.data) {
+                *l += v * value;
+            }
+        }
+
+        *c = Rgb::new_with_alpha(
+            c.red() * light[0],
+            c.green() * light[1],
+            c.blue() * light[2],
+            c.alpha(),
+        );
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(width: u32, height: u32) -> PixelBuffer<Rgb> {
+        PixelBuffer::new_with_color(width, height, Rgb::new(0.5, 0.5, 0.5))
+    }
+
+    #[test]
+    fn test_ambient() {
+        let buffer = gray(4, 4);
+
+        let res = apply_lighting(
+            &buffer,
+            &[LightSpec::Ambient {
+                color: Rgb::WHITE,
+                intensity: 1.0,
+            }],
+        );
+        assert_eq!(res.data(), buffer.data());
+
+        let res = apply_lighting(&buffer, &[]);
+        assert!(res.data().iter().all(|c| *c == Rgb::BLACK));
+    }
+
+    #[test]
+    fn test_spotlight() {
+        let buffer = gray(100, 100);
+
+        let res = apply_lighting(
+            &buffer,
+            &[LightSpec::Point {
+                x: 0.5,
+                y: 0.5,
+                height: 0.5,
+                radius: 0.25,
+                color: Rgb::WHITE,
+                intensity: 1.0,
+            }],
+        );
+
+        let center = res.get_pixel(50, 50).red();
+        let edge = res.get_pixel(50, 0).red();
+        let corner = res.get_pixel(0, 0).red();
+
+        assert!(center > 0.45, "{}", center);
+        assert!(
+            center > edge && edge > corner,
+            "{} {} {}",
+            center,
+            edge,
+            corner
+        );
+    }
+
+    #[test]
+    fn test_directional_gradient() {
+        let buffer = gray(10, 20);
+
+        let light = LightSpec::Directional {
+            azimuth: 0.0,
+            elevation: 90.0,
+            falloff: 1.0,
+            color: Rgb::new(1.0, 0.5, 1.0),
+            intensity: 1.0,
+        };
+        let res = apply_lighting(&buffer, &[light]);
+
+        let top = res.get_pixel(5, 0);
+        let bottom = res.get_pixel(5, 19);
+
+        assert!(top.red() > 0.45 && bottom.red() < 0.05);
+        assert!((top.green() - top.red() / 2.0).abs() < 1e-6);
+        assert_eq!(res.get_pixel(0, 0), res.get_pixel(9, 0));
+    }
+
+    #[test]
+    fn test_relief() {
+        // A bright line is lit on the side facing the light
+        let buffer = PixelBuffer::new_from_func(9, 3, |x, _| match x {
+            4 => Rgb::WHITE,
+            _ => Rgb::new(0.5, 0.5, 0.5),
+        });
+
+        let light = LightSpec::Directional {
+            azimuth: 90.0,
+            elevation: 30.0,
+            falloff: 0.0,
+            color: Rgb::WHITE,
+            intensity: 1.0,
+        };
+        let res = apply_lighting(&buffer, &[light]);
+
+        assert!(res.get_pixel(3, 1).red() > res.get_pixel(5, 1).red());
+    }
+}
@@ -0,0 +1,218 @@
+use d10_core::color::{Color, Rgb, Srgb};
+use d10_core::errors::ParseEnumError;
+use d10_core::pixelbuffer::PixelBuffer;
+use std::str::FromStr;
+
+/// Named looks emulating popular films and darkroom techniques
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LookPreset {
+    /// Muted colors with soft highlights and slightly lifted shadows
+    ClassicChrome,
+    /// Desaturated colors with harsh contrast like skipping the bleaching of a color film
+    BleachBypass,
+    /// Black and white with darkened blue skies and bright skin tones
+    BwRedFilter,
+    /// Black and white with slightly darkened skies, the classic landscape filter
+    BwYellowFilter,
+    /// Teal shadows and orange highlights as used in many movies
+    TealOrange,
+    /// Low contrast with lifted blacks and dimmed whites
+    Faded,
+    /// Shifted colors of developing a slide film in negative chemicals
+    CrossProcess,
+    /// Warm, faded colors of old prints
+    Vintage,
+}
+
+impl FromStr for LookPreset {
+    type Err = ParseEnumError;
+
+    fn from_str(value: &str) -> Result<LookPreset, Self::Err> {
+        use LookPreset::*;
+        match value {
+            "classic_chrome" | "default" => Ok(ClassicChrome),
+            "bleach_bypass" => Ok(BleachBypass),
+            "bw_red_filter" => Ok(BwRedFilter),
+            "bw_yellow_filter" => Ok(BwYellowFilter),
+            "teal_orange" => Ok(TealOrange),
+            "faded" => Ok(Faded),
+            "cross_process" => Ok(CrossProcess),
+            "vintage" => Ok(Vintage),
+            _ => Err(ParseEnumError::new(value, "LookPreset")),
+        }
+    }
+}
+
+/// Gentle S-curve to increase the contrast in the midtones
+const S_CURVE: &[(f32, f32)] = &[(0.0, 0.0), (0.25, 0.2), (0.75, 0.8), (1.0, 1.0)];
+
+/// Strong S-curve with crushed shadows and highlights
+const HARD_CURVE: &[(f32, f32)] = &[(0.0, 0.0), (0.2, 0.1), (0.8, 0.9), (1.0, 1.0)];
+
+/// Evaluate a tone curve made of straight segments between points sorted by their input
+fn curve(points: &[(f32, f32)], v: f32) -> f32 {
+    let v = v.clamp(0.0, 1.0);
+
+    for w in points.windows(2) {
+        let (x1, y1) = w[0];
+        let (x2, y2) = w[1];
+
+        if v <= x2 {
+            return y1 + (y2 - y1) * (v - x1) / (x2 - x1);
+        }
+    }
+
+    points.last().map_or(v, |p| p.1)
+}
+
+fn mix(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn luma(r: f32, g: f32, b: f32) -> f32 {
+    r * 0.212_656 + g * 0.715_158 + b * 0.072_186
+}
+
+/// Set the saturation by mixing with the luma, values above 1.0 increase the saturation
+fn saturate([r, g, b]: [f32; 3], factor: f32) -> [f32; 3] {
+    let l = luma(r, g, b);
+    [mix(l, r, factor), mix(l, g, factor), mix(l, b, factor)]
+}
+
+/// Black and white conversion with the weights of the red, green and blue channel
+fn mono([r, g, b]: [f32; 3], weights: [f32; 3]) -> [f32; 3] {
+    let v = curve(S_CURVE, r * weights[0] + g * weights[1] + b * weights[2]);
+    [v, v, v]
+}
+
+/// Apply the look to gamma encoded color channels
+fn look_color(c: [f32; 3], preset: LookPreset) -> [f32; 3] {
+    use LookPreset::*;
+
+    match preset {
+        ClassicChrome => {
+            let curve_points = &[(0.0, 0.04), (0.25, 0.22), (0.75, 0.76), (1.0, 0.95)];
+            saturate(c, 0.7).map(|v| curve(curve_points, v))
+        }
+        BleachBypass => saturate(c, 0.4).map(|v| curve(HARD_CURVE, v)),
+        BwRedFilter => mono(c, [0.9, 0.1, 0.0]),
+        BwYellowFilter => mono(c, [0.5, 0.45, 0.05]),
+        TealOrange => {
+            let [r, g, b] = c.map(|v| curve(S_CURVE, v));
+
+            // Tint the shadows towards teal and the highlights towards orange
+            let l = luma(r, g, b);
+            let shadows = (1.0 - l) * 0.1;
+            let highlights = l * 0.1;
+
+            saturate(
+                [
+                    r - shadows + highlights,
+                    g + shadows * 0.2 + highlights * 0.3,
+                    b + shadows - highlights,
+                ],
+                1.1,
+            )
+        }
+        Faded => {
+            let curve_points = &[(0.0, 0.12), (0.5, 0.5), (1.0, 0.9)];
+            saturate(c, 0.8).map(|v| curve(curve_points, v))
+        }
+        CrossProcess => {
+            let [r, g, b] = c;
+            [
+                curve(HARD_CURVE, r),
+                curve(S_CURVE, g),
+                curve(&[(0.0, 0.2), (1.0, 0.8)], b),
+            ]
+        }
+        Vintage => {
+            let [r, g, b] = saturate(c, 0.6);
+            let curve_points = &[(0.0, 0.1), (0.5, 0.52), (1.0, 0.92)];
+            [
+                curve(curve_points, r * 1.05),
+                curve(curve_points, g),
+                curve(curve_points, b * 0.85),
+            ]
+        }
+    }
+}
+
+/// Apply a named look
+///
+/// Looks are fixed combinations of tone curves, saturation changes and color tints on the gamma
+/// encoded values. They are meant as good starting points that only need small adjustments.
+pub fn apply_look(buffer: &PixelBuffer<Rgb>, preset: LookPreset) -> PixelBuffer<Rgb> {
+    buffer.map_colors(|c| {
+        let s = c.to_srgb();
+        let [r, g, b] = look_color([s.data[0], s.data[1], s.data[2]], preset);
+
+        Srgb::new_with_alpha(r, g, b, c.alpha()).to_rgb()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(16, 4, |x, y| {
+            Srgb::new(x as f32 / 15.0, y as f32 / 3.0, 1.0 - x as f32 / 15.0).to_rgb()
+        })
+    }
+
+    #[test]
+    fn test_curve() {
+        assert_eq!(curve(S_CURVE, 0.0), 0.0);
+        assert_eq!(curve(S_CURVE, 0.5), 0.5);
+        assert!((curve(S_CURVE, 0.125) - 0.1).abs() < 0.0001);
+        assert_eq!(curve(S_CURVE, 2.0), 1.0);
+    }
+
+    #[test]
+    fn test_black_and_white() {
+        let result = apply_look(&gradient(), LookPreset::BwRedFilter);
+        assert!(result.data().iter().all(|c| c.is_grayscale()));
+
+        // The red filter renders blue darker than the yellow filter
+        let blue = PixelBuffer::new_with_color(1, 1, Rgb::BLUE);
+        let red_filter = apply_look(&blue, LookPreset::BwRedFilter);
+        let yellow_filter = apply_look(&blue, LookPreset::BwYellowFilter);
+        assert!(red_filter.get_pixel(0, 0).red() < yellow_filter.get_pixel(0, 0).red());
+    }
+
+    #[test]
+    fn test_looks() {
+        let buffer = gradient();
+
+        for preset in [
+            LookPreset::ClassicChrome,
+            LookPreset::BleachBypass,
+            LookPreset::TealOrange,
+            LookPreset::Faded,
+            LookPreset::CrossProcess,
+            LookPreset::Vintage,
+        ] {
+            let result = apply_look(&buffer, preset);
+            assert_ne!(result.data(), buffer.data(), "{:?}", preset);
+        }
+
+        // Faded looks don't have real blacks
+        let black = PixelBuffer::new_with_color(1, 1, Rgb::BLACK);
+        let result = apply_look(&black, LookPreset::Faded);
+        assert!(result.get_pixel(0, 0).to_srgb().red() > 0.1);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "default".parse::<LookPreset>().unwrap(),
+            LookPreset::ClassicChrome
+        );
+        assert_eq!(
+            "bw_red_filter".parse::<LookPreset>().unwrap(),
+            LookPreset::BwRedFilter
+        );
+        assert!("foo".parse::<LookPreset>().is_err());
+    }
+}
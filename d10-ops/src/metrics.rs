@@ -0,0 +1,260 @@
+use d10_core::color::{Color, Rgb};
+use d10_core::errors::ParseEnumError;
+use d10_core::pixelbuffer::PixelBuffer;
+use std::str::FromStr;
+
+/// Stabilizing constants of SSIM for a dynamic range of 1
+const SSIM_C1: f32 = 0.01 * 0.01;
+const SSIM_C2: f32 = 0.03 * 0.03;
+
+/// Radius and standard deviation of the gaussian window used by SSIM
+const SSIM_RADIUS: i32 = 5;
+const SSIM_SIGMA: f32 = 1.5;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Metric {
+    /// Structural similarity of the luma from -1 to 1 with 1 meaning identical
+    Ssim,
+    /// Peak signal to noise ratio in decibel, infinite for identical images
+    Psnr,
+    /// Mean squared error of all channels
+    Mse,
+    /// Mean absolute error of all channels
+    Mae,
+}
+
+impl Metric {
+    /// Returns true if larger values mean more similar images
+    pub fn higher_is_better(&self) -> bool {
+        matches!(self, Metric::Ssim | Metric::Psnr)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::Ssim => "ssim",
+            Metric::Psnr => "psnr",
+            Metric::Mse => "mse",
+            Metric::Mae => "mae",
+        }
+    }
+}
+
+impl FromStr for Metric {
+    type Err = ParseEnumError;
+
+    fn from_str(value: &str) -> Result<Metric, Self::Err> {
+        match value {
+            "ssim" | "default" => Ok(Metric::Ssim),
+            "psnr" => Ok(Metric::Psnr),
+            "mse" => Ok(Metric::Mse),
+            "mae" => Ok(Metric::Mae),
+            _ => Err(ParseEnumError::new(value, "Metric")),
+        }
+    }
+}
+
+/// Gamma encoded channels with the color premultiplied by alpha
+fn channels(c: &Rgb) -> [f32; 4] {
+    let c = c.to_srgb();
+    let alpha = c.alpha();
+    [
+        c.data[0] * alpha,
+        c.data[1] * alpha,
+        c.data[2] * alpha,
+        alpha,
+    ]
+}
+
+fn mean_error(a: &PixelBuffer<Rgb>, b: &PixelBuffer<Rgb>, func: fn(f32) -> f32) -> f32 {
+    if a.is_empty() {
+        return 0.0;
+    }
+
+    let sum: f64 = a
+        .data()
+        .iter()
+        .zip(b.data())
+        .map(|(c1, c2)| {
+            let c1 = channels(c1);
+            let c2 = channels(c2);
+            (0..4).map(|i| func(c1[i] - c2[i]) as f64).sum::<f64>()
+        })
+        .sum();
+
+    (sum / (a.data().len() * 4) as f64) as f32
+}
+
+fn gaussian_weights() -> Vec<f32> {
+    let weights: Vec<f32> = (-SSIM_RADIUS..=SSIM_RADIUS)
+        .map(|i| (-((i * i) as f32) / (2.0 * SSIM_SIGMA * SSIM_SIGMA)).exp())
+        .collect();
+    let sum: f32 = weights.iter().sum();
+    weights.into_iter().map(|w| w / sum).collect()
+}
+
+/// Separable gaussian blur of a single channel plane with clamped edges
+fn blur_plane(plane: &[f32], width: usize, height: usize, weights: &[f32]) -> Vec<f32> {
+    let sample = |data: &[f32], x: i32, y: i32, horizontal: bool| {
+        let mut sum = 0.0;
+        for (i, w) in weights.iter().enumerate() {
+            let offset = i as i32 - SSIM_RADIUS;
+            let (sx, sy) = if horizontal {
+                ((x + offset).clamp(0, width as i32 - 1), y)
+            } else {
+                (x, (y + offset).clamp(0, height as i32 - 1))
+            };
+            sum += data[sy as usize * width + sx as usize] * w;
+        }
+        sum
+    };
+
+    let mut tmp = vec![0.0; plane.len()];
+    for y in 0..height {
+        for x in 0..width {
+            tmp[y * width + x] = sample(plane, x as i32, y as i32, true);
+        }
+    }
+
+    let mut out = vec![0.0; plane.len()];
+    for y in 0..height {
+        for x in 0..width {
+            out[y * width + x] = sample(&tmp, x as i32, y as i32, false);
+        }
+    }
+
+    out
+}
+
+fn ssim(a: &PixelBuffer<Rgb>, b: &PixelBuffer<Rgb>) -> f32 {
+    if a.is_empty() {
+        return 1.0;
+    }
+
+    let width = a.width() as usize;
+    let height = a.height() as usize;
+
+    let luma = |buffer: &PixelBuffer<Rgb>| -> Vec<f32> {
+        buffer
+            .data()
+            .iter()
+            .map(|c| {
+                let [r, g, b, _] = channels(c);
+                r * 0.212_656 + g * 0.715_158 + b * 0.072_186
+            })
+            .collect()
+    };
+
+    let la = luma(a);
+    let lb = luma(b);
+
+    let product = |p1: &[f32], p2: &[f32]| -> Vec<f32> {
+        p1.iter().zip(p2).map(|(v1, v2)| v1 * v2).collect()
+    };
+
+    let weights = gaussian_weights();
+    let blur = |plane: &[f32]| blur_plane(plane, width, height, &weights);
+
+    let mu_a = blur(&la);
+    let mu_b = blur(&lb);
+    let aa = blur(&product(&la, &la));
+    let bb = blur(&product(&lb, &lb));
+    let ab = blur(&product(&la, &lb));
+
+    let sum: f64 = (0..la.len())
+        .map(|i| {
+            let var_a = aa[i] - mu_a[i] * mu_a[i];
+            let var_b = bb[i] - mu_b[i] * mu_b[i];
+            let covar = ab[i] - mu_a[i] * mu_b[i];
+
+            let numerator = (2.0 * mu_a[i] * mu_b[i] + SSIM_C1) * (2.0 * covar + SSIM_C2);
+            let denominator =
+                (mu_a[i] * mu_a[i] + mu_b[i] * mu_b[i] + SSIM_C1) * (var_a + var_b + SSIM_C2);
+
+            (numerator / denominator) as f64
+        })
+        .sum();
+
+    (sum / la.len() as f64) as f32
+}
+
+/// Compute how similar two images are
+///
+/// All metrics are computed on gamma encoded values with premultiplied alpha.
+/// Returns `None` if the images differ in size.
+pub fn compare(a: &PixelBuffer<Rgb>, b: &PixelBuffer<Rgb>, metric: Metric) -> Option<f32> {
+    if a.width() != b.width() || a.height() != b.height() {
+        return None;
+    }
+
+    Some(match metric {
+        Metric::Ssim => ssim(a, b),
+        Metric::Psnr => {
+            let mse = mean_error(a, b, |d| d * d);
+            if mse > 0.0 {
+                -10.0 * mse.log10()
+            } else {
+                f32::INFINITY
+            }
+        }
+        Metric::Mse => mean_error(a, b, |d| d * d),
+        Metric::Mae => mean_error(a, b, f32::abs),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(20, 16, |x, y| {
+            Rgb::new(x as f32 / 20.0, y as f32 / 16.0, ((x + y) % 3) as f32 / 2.0)
+        })
+    }
+
+    #[test]
+    fn test_identical() {
+        let a = gradient();
+
+        assert!((compare(&a, &a, Metric::Ssim).unwrap() - 1.0).abs() < 1e-4);
+        assert_eq!(compare(&a, &a, Metric::Psnr).unwrap(), f32::INFINITY);
+        assert_eq!(compare(&a, &a, Metric::Mse).unwrap(), 0.0);
+        assert_eq!(compare(&a, &a, Metric::Mae).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_different() {
+        let a = gradient();
+        let slightly = a.map_colors(|c| c.with_brightness(0.01));
+        let inverted = a.map_colors(|c| c.invert());
+
+        let ssim_slightly = compare(&a, &slightly, Metric::Ssim).unwrap();
+        let ssim_inverted = compare(&a, &inverted, Metric::Ssim).unwrap();
+        assert!(ssim_slightly > 0.95);
+        assert!(ssim_inverted < 0.5);
+
+        let psnr_slightly = compare(&a, &slightly, Metric::Psnr).unwrap();
+        let psnr_inverted = compare(&a, &inverted, Metric::Psnr).unwrap();
+        assert!(psnr_slightly > psnr_inverted);
+
+        let black = PixelBuffer::new_with_color(2, 2, Rgb::BLACK);
+        let white = PixelBuffer::new_with_color(2, 2, Rgb::WHITE);
+        assert!((compare(&black, &white, Metric::Mae).unwrap() - 0.75).abs() < 1e-4);
+        assert!((compare(&black, &white, Metric::Mse).unwrap() - 0.75).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_size_mismatch() {
+        let a = PixelBuffer::new_with_color(2, 2, Rgb::BLACK);
+        let b = PixelBuffer::new_with_color(2, 3, Rgb::BLACK);
+
+        assert!(compare(&a, &b, Metric::Ssim).is_none());
+    }
+
+    #[test]
+    fn test_metric_from_str() {
+        assert_eq!("default".parse::<Metric>().unwrap(), Metric::Ssim);
+        assert_eq!("psnr".parse::<Metric>().unwrap(), Metric::Psnr);
+        assert!("other".parse::<Metric>().is_err());
+        assert!(!Metric::Mse.higher_is_better());
+    }
+}
@@ -0,0 +1,195 @@
+use d10_core::color::{Color, Rgb, Srgb};
+use d10_core::errors::ParseEnumError;
+use d10_core::pixelbuffer::PixelBuffer;
+use std::str::FromStr;
+
+/// Colored lens filters used in black and white photography
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MonochromeFilter {
+    /// No filter, the luminance of the image
+    Neutral,
+    /// Dramatic dark skies and bright skin with reduced blemishes
+    Red,
+    /// Darker skies and more contrast in landscapes without the drama of a red filter
+    Orange,
+    /// Slightly darkened skies for natural looking landscapes
+    Yellow,
+    /// Bright foliage and darker skin tones
+    Green,
+}
+
+impl MonochromeFilter {
+    /// Weights of the red, green and blue channel used to simulate the filter
+    pub fn weights(&self) -> (f32, f32, f32) {
+        use MonochromeFilter::*;
+        match self {
+            Neutral => (0.212_656, 0.715_158, 0.072_186),
+            Red => (0.8, 0.2, 0.0),
+            Orange => (0.6, 0.4, 0.0),
+            Yellow => (0.45, 0.5, 0.05),
+            Green => (0.1, 0.85, 0.05),
+        }
+    }
+}
+
+impl FromStr for MonochromeFilter {
+    type Err = ParseEnumError;
+
+    fn from_str(value: &str) -> Result<MonochromeFilter, Self::Err> {
+        use MonochromeFilter::*;
+        match value {
+            "neutral" | "default" => Ok(Neutral),
+            "red" => Ok(Red),
+            "orange" => Ok(Orange),
+            "yellow" => Ok(Yellow),
+            "green" => Ok(Green),
+            _ => Err(ParseEnumError::new(value, "MonochromeFilter")),
+        }
+    }
+}
+
+fn mix(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Color a gray value with a gradient from black over the tint color to white
+fn apply_tint(v: f32, tint: &Srgb) -> [f32; 3] {
+    let t = Rgb::new(v, v, v).to_srgb().red();
+
+    let channel = |c: f32| {
+        if t < 0.5 {
+            mix(0.0, c, t * 2.0)
+        } else {
+            mix(c, 1.0, t * 2.0 - 1.0)
+        }
+    };
+
+    [
+        channel(tint.red()),
+        channel(tint.green()),
+        channel(tint.blue()),
+    ]
+}
+
+/// Convert into a black and white image with a channel mixer
+///
+/// The gray value is the weighted sum of the red, green and blue channel. The weights get
+/// normalized to keep white as white, so only their ratio matters. Negative weights are allowed
+/// to darken a color even more.
+///
+/// If `tint` is set the gray values are mapped to a gradient from black over the tint color at
+/// middle gray to white, like a sepia or selenium toned print.
+pub fn to_monochrome(
+    buffer: &PixelBuffer<Rgb>,
+    red_weight: f32,
+    green_weight: f32,
+    blue_weight: f32,
+    tint: Option<Rgb>,
+) -> PixelBuffer<Rgb> {
+    let sum = red_weight + green_weight + blue_weight;
+    let (red_weight, green_weight, blue_weight) = if sum.abs() < f32::EPSILON {
+        MonochromeFilter::Neutral.weights()
+    } else {
+        (red_weight / sum, green_weight / sum, blue_weight / sum)
+    };
+
+    let tint = tint.map(|c| c.to_srgb());
+
+    buffer.map_colors(|c| {
+        let v = (c.red() * red_weight + c.green() * green_weight + c.blue() * blue_weight)
+            .clamp(0.0, 1.0);
+
+        match &tint {
+            Some(tint) => {
+                let [r, g, b] = apply_tint(v, tint);
+                Srgb::new_with_alpha(r, g, b, c.alpha()).to_rgb()
+            }
+            None => Rgb::new_with_alpha(v, v, v, c.alpha()),
+        }
+    })
+}
+
+/// Convert into a black and white image simulating a colored lens filter
+///
+/// See `to_monochrome()` for the meaning of `tint`.
+pub fn to_monochrome_with_filter(
+    buffer: &PixelBuffer<Rgb>,
+    filter: MonochromeFilter,
+    tint: Option<Rgb>,
+) -> PixelBuffer<Rgb> {
+    let (red, green, blue) = filter.weights();
+    to_monochrome(buffer, red, green, blue, tint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn colors() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_raw(
+            5,
+            1,
+            vec![Rgb::WHITE, Rgb::BLACK, Rgb::RED, Rgb::GREEN, Rgb::BLUE],
+        )
+    }
+
+    #[test]
+    fn test_to_monochrome() {
+        let result = to_monochrome(&colors(), 2.0, 1.0, 1.0, None);
+
+        assert!(result.data().iter().all(|c| c.is_grayscale()));
+        assert_eq!(result.get_pixel(0, 0), &Rgb::WHITE);
+        assert_eq!(result.get_pixel(1, 0), &Rgb::BLACK);
+        assert_eq!(result.get_pixel(2, 0).red(), 0.5);
+        assert_eq!(result.get_pixel(3, 0).red(), 0.25);
+
+        // Falls back to neutral weights
+        let result = to_monochrome(&colors(), 0.0, 0.0, 0.0, None);
+        assert_eq!(result.get_pixel(0, 0), &Rgb::WHITE);
+    }
+
+    #[test]
+    fn test_filters() {
+        let red = to_monochrome_with_filter(&colors(), MonochromeFilter::Red, None);
+        let neutral = to_monochrome_with_filter(&colors(), MonochromeFilter::Neutral, None);
+        let green = to_monochrome_with_filter(&colors(), MonochromeFilter::Green, None);
+
+        // Blue skies get darker with a red filter
+        assert!(red.get_pixel(4, 0).red() < neutral.get_pixel(4, 0).red());
+        assert!(red.get_pixel(2, 0).red() > neutral.get_pixel(2, 0).red());
+        assert!(green.get_pixel(3, 0).red() > neutral.get_pixel(3, 0).red());
+    }
+
+    #[test]
+    fn test_tint() {
+        let tint = Srgb::new(0.6, 0.4, 0.2).to_rgb();
+
+        let buffer = PixelBuffer::new_from_raw(
+            3,
+            1,
+            vec![Rgb::WHITE, Rgb::BLACK, Srgb::new(0.5, 0.5, 0.5).to_rgb()],
+        );
+
+        let result = to_monochrome_with_filter(&buffer, MonochromeFilter::Neutral, Some(tint));
+
+        assert_eq!(result.get_pixel(0, 0), &Rgb::WHITE);
+        assert_eq!(result.get_pixel(1, 0), &Rgb::BLACK);
+
+        let mid = result.get_pixel(2, 0).to_srgb();
+        assert!((mid.red() - 0.6).abs() < 0.01);
+        assert!((mid.blue() - 0.2).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "default".parse::<MonochromeFilter>().unwrap(),
+            MonochromeFilter::Neutral
+        );
+        assert_eq!(
+            "orange".parse::<MonochromeFilter>().unwrap(),
+            MonochromeFilter::Orange
+        );
+        assert!("foo".parse::<MonochromeFilter>().is_err());
+    }
+}
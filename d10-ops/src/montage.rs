@@ -0,0 +1,87 @@
+use d10_core::color::Color;
+use d10_core::pixelbuffer::PixelBuffer;
+use d10_core::region::Region;
+
+/// Arrange images in a grid with `columns` columns
+///
+/// All cells have the size of the largest image and every image is centered inside its cell.
+/// `spacing` is the number of pixels between the cells and around the border of the grid.
+pub fn montage<C: Color>(
+    buffers: &[&PixelBuffer<C>],
+    columns: u32,
+    spacing: u32,
+    background: C,
+) -> PixelBuffer<C> {
+    if buffers.is_empty() {
+        return PixelBuffer::new_with_color(0, 0, background);
+    }
+
+    let columns = columns.clamp(1, buffers.len() as u32);
+    let rows = (buffers.len() as u32).div_ceil(columns);
+
+    let cell_width = buffers.iter().map(|b| b.width()).max().unwrap_or(0);
+    let cell_height = buffers.iter().map(|b| b.height()).max().unwrap_or(0);
+
+    let width = columns * (cell_width + spacing) + spacing;
+    let height = rows * (cell_height + spacing) + spacing;
+
+    let mut buffer = PixelBuffer::new_with_color(width, height, background);
+
+    for (i, tile) in buffers.iter().enumerate() {
+        let col = i as u32 % columns;
+        let row = i as u32 / columns;
+
+        let x = spacing + col * (cell_width + spacing) + (cell_width - tile.width()) / 2;
+        let y = spacing + row * (cell_height + spacing) + (cell_height - tile.height()) / 2;
+
+        buffer.copy_from(tile, Region::new(0, 0, tile.width(), tile.height()), (x, y));
+    }
+
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use d10_core::color::Rgb;
+
+    use super::*;
+
+    #[test]
+    fn test_montage() {
+        let a = PixelBuffer::new_with_color(4, 4, Rgb::RED);
+        let b = PixelBuffer::new_with_color(2, 2, Rgb::GREEN);
+        let c = PixelBuffer::new_with_color(4, 2, Rgb::BLUE);
+
+        let res = montage(&[&a, &b, &c], 2, 1, Rgb::WHITE);
+
+        assert_eq!(res.width(), 11);
+        assert_eq!(res.height(), 11);
+
+        assert_eq!(res.get_pixel(0, 0), &Rgb::WHITE);
+        assert_eq!(res.get_pixel(1, 1), &Rgb::RED);
+        assert_eq!(res.get_pixel(4, 4), &Rgb::RED);
+
+        // Smaller images are centered in their cell
+        assert_eq!(res.get_pixel(6, 1), &Rgb::WHITE);
+        assert_eq!(res.get_pixel(7, 2), &Rgb::GREEN);
+        assert_eq!(res.get_pixel(8, 3), &Rgb::GREEN);
+        assert_eq!(res.get_pixel(9, 3), &Rgb::WHITE);
+
+        assert_eq!(res.get_pixel(1, 7), &Rgb::BLUE);
+        assert_eq!(res.get_pixel(1, 6), &Rgb::WHITE);
+        assert_eq!(res.get_pixel(7, 7), &Rgb::WHITE);
+    }
+
+    #[test]
+    fn test_montage_single_row() {
+        let a = PixelBuffer::new_with_color(3, 2, Rgb::RED);
+
+        let res = montage(&[&a, &a, &a], 10, 0, Rgb::WHITE);
+
+        assert_eq!(res.width(), 9);
+        assert_eq!(res.height(), 2);
+        assert!(res.data().iter().all(|c| *c == Rgb::RED));
+
+        assert!(montage::<Rgb>(&[], 3, 2, Rgb::WHITE).is_empty());
+    }
+}
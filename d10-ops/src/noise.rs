@@ -0,0 +1,357 @@
+use d10_core::color::Rgb;
+use d10_core::errors::ParseEnumError;
+use d10_core::pixelbuffer::PixelBuffer;
+use rand::prelude::*;
+use std::str::FromStr;
+
+/// Skew factors of the 2D simplex grid
+const SIMPLEX_F2: f32 = 0.366_025_4;
+const SIMPLEX_G2: f32 = 0.211_324_87;
+
+/// Gradients of the perlin and simplex noise
+const GRADIENTS: [(f32, f32); 8] = [
+    (1.0, 1.0),
+    (-1.0, 1.0),
+    (1.0, -1.0),
+    (-1.0, -1.0),
+    (1.0, 0.0),
+    (-1.0, 0.0),
+    (0.0, 1.0),
+    (0.0, -1.0),
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NoiseKind {
+    /// Smooth gradient noise on a square lattice
+    Perlin,
+    /// Gradient noise on a triangular lattice with less directional artifacts
+    Simplex,
+    /// Cellular noise with the distance to the nearest of randomly placed feature points
+    Worley,
+}
+
+impl FromStr for NoiseKind {
+    type Err = ParseEnumError;
+
+    fn from_str(value: &str) -> Result<NoiseKind, Self::Err> {
+        match value {
+            "perlin" | "default" => Ok(NoiseKind::Perlin),
+            "simplex" => Ok(NoiseKind::Simplex),
+            "worley" => Ok(NoiseKind::Worley),
+            _ => Err(ParseEnumError::new(value, "NoiseKind")),
+        }
+    }
+}
+
+/// Options to control the generated noise
+#[derive(Copy, Clone, Debug)]
+pub struct NoiseOptions {
+    pub kind: NoiseKind,
+    /// Number of lattice cells across the width of the image
+    pub scale: f32,
+    /// Number of layers with doubled frequency added to the base noise
+    pub octaves: u32,
+    /// Factor applied to the amplitude of every following octave
+    pub persistence: f32,
+    pub seed: u64,
+    /// Generate noise that wraps around seamlessly at the image borders
+    pub tileable: bool,
+}
+
+impl Default for NoiseOptions {
+    fn default() -> Self {
+        NoiseOptions {
+            kind: NoiseKind::Perlin,
+            scale: 4.0,
+            octaves: 1,
+            persistence: 0.5,
+            seed: 0,
+            tileable: false,
+        }
+    }
+}
+
+impl NoiseOptions {
+    pub fn new(kind: NoiseKind) -> NoiseOptions {
+        NoiseOptions {
+            kind,
+            ..Default::default()
+        }
+    }
+}
+
+/// Random permutation of the lattice coordinates
+struct Permutation {
+    table: [u8; 256],
+}
+
+impl Permutation {
+    fn new(seed: u64) -> Permutation {
+        let mut table = [0u8; 256];
+        for (i, v) in table.iter_mut().enumerate() {
+            *v = i as u8;
+        }
+
+        table.shuffle(&mut StdRng::seed_from_u64(seed));
+
+        Permutation { table }
+    }
+
+    fn hash(&self, x: i32, y: i32) -> usize {
+        let h = self.table[(x & 255) as usize] as i32;
+        self.table[((h + y) & 255) as usize] as usize
+    }
+}
+
+/// Lattice coordinates wrapped at `period` if the noise is tileable
+fn wrap(value: i32, period: Option<i32>) -> i32 {
+    match period {
+        Some(period) => value.rem_euclid(period),
+        None => value,
+    }
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Perlin noise between -1 and 1
+fn perlin(perm: &Permutation, x: f32, y: f32, period: Option<(i32, i32)>) -> f32 {
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let fx = x - x0;
+    let fy = y - y0;
+
+    let corner = |dx: i32, dy: i32| {
+        let ix = wrap(x0 as i32 + dx, period.map(|p| p.0));
+        let iy = wrap(y0 as i32 + dy, period.map(|p| p.1));
+        let (gx, gy) = GRADIENTS[perm.hash(ix, iy) & 7];
+        gx * (fx - dx as f32) + gy * (fy - dy as f32)
+    };
+
+    let u = fade(fx);
+    let v = fade(fy);
+
+    lerp(
+        lerp(corner(0, 0), corner(1, 0), u),
+        lerp(corner(0, 1), corner(1, 1), u),
+        v,
+    )
+}
+
+/// Simplex noise between -1 and 1
+fn simplex(perm: &Permutation, x: f32, y: f32) -> f32 {
+    let s = (x + y) * SIMPLEX_F2;
+    let i = (x + s).floor();
+    let j = (y + s).floor();
+
+    let t = (i + j) * SIMPLEX_G2;
+    let x0 = x - (i - t);
+    let y0 = y - (j - t);
+
+    // Second corner of the triangle containing the point
+    let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+
+    let corners = [
+        (0, 0, x0, y0),
+        (
+            i1,
+            j1,
+            x0 - i1 as f32 + SIMPLEX_G2,
+            y0 - j1 as f32 + SIMPLEX_G2,
+        ),
+        (
+            1,
+            1,
+            x0 - 1.0 + 2.0 * SIMPLEX_G2,
+            y0 - 1.0 + 2.0 * SIMPLEX_G2,
+        ),
+    ];
+
+    let sum: f32 = corners
+        .iter()
+        .map(|(di, dj, cx, cy)| {
+            let t = 0.5 - cx * cx - cy * cy;
+            if t < 0.0 {
+                0.0
+            } else {
+                let (gx, gy) = GRADIENTS[perm.hash(i as i32 + di, j as i32 + dj) & 7];
+                t * t * t * t * (gx * cx + gy * cy)
+            }
+        })
+        .sum();
+
+    70.0 * sum
+}
+
+/// Distance to the nearest feature point in units of the cell size
+fn worley(perm: &Permutation, x: f32, y: f32, period: Option<(i32, i32)>) -> f32 {
+    let cx = x.floor() as i32;
+    let cy = y.floor() as i32;
+
+    let mut nearest = f32::INFINITY;
+
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            let ix = wrap(cx + dx, period.map(|p| p.0));
+            let iy = wrap(cy + dy, period.map(|p| p.1));
+
+            // Two independent hashes for the position of the feature point inside the cell
+            let hash = perm.hash(ix, iy);
+            let px = (cx + dx) as f32 + perm.table[hash] as f32 / 255.0;
+            let py = (cy + dy) as f32 + perm.table[(hash + 101) & 255] as f32 / 255.0;
+
+            nearest = nearest.min(((px - x) * (px - x) + (py - y) * (py - y)).sqrt());
+        }
+    }
+
+    nearest
+}
+
+/// Noise of a single octave between 0 and 1 at the position given in lattice cells
+fn sample(perm: &Permutation, kind: NoiseKind, x: f32, y: f32, period: Option<(i32, i32)>) -> f32 {
+    match kind {
+        NoiseKind::Perlin => 0.5 + 0.5 * perlin(perm, x, y, period),
+        NoiseKind::Simplex => match period {
+            // The triangular lattice can't wrap, so the noise gets blended with its shifted
+            // copies instead
+            Some((px, py)) => {
+                let (px, py) = (px as f32, py as f32);
+                let tx = x.rem_euclid(px) / px;
+                let ty = y.rem_euclid(py) / py;
+
+                let a = simplex(perm, x, y);
+                let b = simplex(perm, x - px, y);
+                let c = simplex(perm, x, y - py);
+                let d = simplex(perm, x - px, y - py);
+
+                0.5 + 0.5 * lerp(lerp(a, b, tx), lerp(c, d, tx), ty)
+            }
+            None => 0.5 + 0.5 * simplex(perm, x, y),
+        },
+        NoiseKind::Worley => worley(perm, x, y, period),
+    }
+}
+
+/// Generate a grayscale noise texture
+///
+/// The values are between 0 and 1 and can be used as masks, displacement maps or as base for
+/// textures. If `options.tileable` is set, the scale is rounded to whole lattice cells so the
+/// texture wraps around seamlessly at the borders.
+pub fn generate_noise(width: u32, height: u32, options: &NoiseOptions) -> PixelBuffer<Rgb> {
+    let perm = Permutation::new(options.seed);
+
+    let scale = options.scale.max(f32::EPSILON);
+    let (cells_x, cells_y) = if options.tileable {
+        let cells_x = scale.round().max(1.0);
+        let cells_y = (scale * height as f32 / width.max(1) as f32)
+            .round()
+            .max(1.0);
+        (cells_x, cells_y)
+    } else {
+        (scale, scale * height as f32 / width.max(1) as f32)
+    };
+
+    let octaves = options.octaves.max(1);
+
+    PixelBuffer::new_from_func(width, height, |x, y| {
+        let u = x as f32 / width as f32;
+        let v = y as f32 / height as f32;
+
+        let mut value = 0.0;
+        let mut amplitude = 1.0;
+        let mut total = 0.0;
+
+        for octave in 0..octaves {
+            let frequency = (1 << octave.min(16)) as f32;
+
+            let period = options
+                .tileable
+                .then_some(((cells_x * frequency) as i32, (cells_y * frequency) as i32));
+
+            value += amplitude
+                * sample(
+                    &perm,
+                    options.kind,
+                    u * cells_x * frequency,
+                    v * cells_y * frequency,
+                    period,
+                );
+            total += amplitude;
+            amplitude *= options.persistence;
+        }
+
+        let value = (value / total).clamp(0.0, 1.0);
+
+        Rgb::new(value, value, value)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_seamless(buffer: &PixelBuffer<Rgb>) -> bool {
+        let max_step = |a: &Rgb, b: &Rgb| (a.red() - b.red()).abs();
+
+        let inner = (0..buffer.height())
+            .map(|y| max_step(buffer.get_pixel(0, y), buffer.get_pixel(1, y)))
+            .fold(0.0, f32::max);
+        let wrapped = (0..buffer.height())
+            .map(|y| {
+                max_step(
+                    buffer.get_pixel(buffer.width() - 1, y),
+                    buffer.get_pixel(0, y),
+                )
+            })
+            .fold(0.0, f32::max);
+
+        wrapped <= inner * 2.0 + 0.02
+    }
+
+    #[test]
+    fn test_generate_noise() {
+        for kind in [NoiseKind::Perlin, NoiseKind::Simplex, NoiseKind::Worley] {
+            let options = NoiseOptions {
+                octaves: 3,
+                ..NoiseOptions::new(kind)
+            };
+
+            let res = generate_noise(64, 32, &options);
+            assert_eq!((res.width(), res.height()), (64, 32));
+
+            let min = res.data().iter().map(|c| c.red()).fold(1.0, f32::min);
+            let max = res.data().iter().map(|c| c.red()).fold(0.0, f32::max);
+            assert!(max - min > 0.2, "{:?}: {} {}", kind, min, max);
+
+            // Same seed, same noise
+            assert_eq!(res.data(), generate_noise(64, 32, &options).data());
+
+            let other = generate_noise(64, 32, &NoiseOptions { seed: 1, ..options });
+            assert_ne!(res.data(), other.data());
+        }
+    }
+
+    #[test]
+    fn test_tileable() {
+        for kind in [NoiseKind::Perlin, NoiseKind::Simplex, NoiseKind::Worley] {
+            let options = NoiseOptions {
+                scale: 3.4,
+                octaves: 2,
+                tileable: true,
+                ..NoiseOptions::new(kind)
+            };
+
+            let res = generate_noise(60, 60, &options);
+            assert!(is_seamless(&res), "{:?}", kind);
+
+            // Rotating the image by 90 degrees swaps the borders that need to match
+            let rotated = PixelBuffer::new_from_func(60, 60, |x, y| *res.get_pixel(y, x));
+            assert!(is_seamless(&rotated), "{:?}", kind);
+        }
+    }
+}
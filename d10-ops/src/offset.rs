@@ -0,0 +1,45 @@
+use d10_core::color::Color;
+use d10_core::pixelbuffer::PixelBuffer;
+
+/// Shift the image by the given amount of pixels and wrap pixels around the borders
+pub fn offset<C>(buffer: &PixelBuffer<C>, dx: i32, dy: i32) -> PixelBuffer<C>
+where
+    C: Color,
+{
+    if buffer.is_empty() {
+        return buffer.clone();
+    }
+
+    let width = buffer.width() as i64;
+    let height = buffer.height() as i64;
+
+    let dx = (dx as i64).rem_euclid(width);
+    let dy = (dy as i64).rem_euclid(height);
+
+    PixelBuffer::new_from_func(buffer.width(), buffer.height(), |x, y| {
+        let src_x = (x as i64 - dx).rem_euclid(width) as u32;
+        let src_y = (y as i64 - dy).rem_euclid(height) as u32;
+
+        *buffer.get_pixel(src_x, src_y)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use d10_core::color::Rgb;
+
+    #[test]
+    fn test_offset() {
+        let buffer =
+            PixelBuffer::new_from_func(3, 2, |x, y| Rgb::new(x as f32 / 2.0, y as f32, 0.0));
+
+        let res = offset(&buffer, 1, -3);
+
+        assert_eq!(res.get_pixel(1, 1), buffer.get_pixel(0, 0));
+        assert_eq!(res.get_pixel(0, 0), buffer.get_pixel(2, 1));
+
+        assert_eq!(offset(&buffer, 3, 4).data(), buffer.data());
+    }
+}
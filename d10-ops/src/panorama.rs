@@ -0,0 +1,262 @@
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use d10_core::color::Rgb;
+use d10_core::pixelbuffer::PixelBuffer;
+
+use crate::filters::get_pixel_bilinear;
+
+type Vector = (f32, f32, f32);
+
+/// View direction, right and up vector of the cube faces in the order +X, -X, +Y, -Y, +Z, -Z
+///
+/// The coordinate system has x to the right, y up and z forward, where forward is the center of
+/// the panorama. The side faces are upright, the top face has the front at the bottom edge and
+/// the bottom face at the top edge, like in the common cross layout of cube maps.
+const CUBE_FACES: [(Vector, Vector, Vector); 6] = [
+    ((1.0, 0.0, 0.0), (0.0, 0.0, -1.0), (0.0, 1.0, 0.0)),
+    ((-1.0, 0.0, 0.0), (0.0, 0.0, 1.0), (0.0, 1.0, 0.0)),
+    ((0.0, 1.0, 0.0), (1.0, 0.0, 0.0), (0.0, 0.0, -1.0)),
+    ((0.0, -1.0, 0.0), (1.0, 0.0, 0.0), (0.0, 0.0, 1.0)),
+    ((0.0, 0.0, 1.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)),
+    ((0.0, 0.0, -1.0), (-1.0, 0.0, 0.0), (0.0, 1.0, 0.0)),
+];
+
+fn dot(a: Vector, b: Vector) -> f32 {
+    a.0 * b.0 + a.1 * b.1 + a.2 * b.2
+}
+
+/// Sample an equirectangular panorama in the given view direction
+fn sample_direction(buffer: &PixelBuffer<Rgb>, x: f32, y: f32, z: f32) -> Rgb {
+    let longitude = x.atan2(z);
+    let latitude = y.clamp(-1.0, 1.0).asin();
+
+    let width = buffer.width() as f32;
+    let height = buffer.height() as f32;
+
+    // Wrap around horizontally to avoid a seam at the back of the panorama
+    let sx = ((longitude / TAU + 0.5) * width).rem_euclid(width) - 0.5;
+    let sy = (0.5 - latitude / PI) * height - 0.5;
+
+    get_pixel_bilinear(buffer, sx, sy)
+}
+
+/// Render a square view of the panorama with the side length of the panorama height
+///
+/// `func` maps the distance from the center, relative to half the side length, to the angle
+/// between the view direction and the ray through the pixel.
+fn project<F>(buffer: &PixelBuffer<Rgb>, yaw: f32, pitch: f32, func: F) -> PixelBuffer<Rgb>
+where
+    F: Fn(f32) -> f32,
+{
+    let size = buffer.height();
+
+    if buffer.is_empty() {
+        return PixelBuffer::new(size, size);
+    }
+
+    let (yaw_sin, yaw_cos) = yaw.to_radians().sin_cos();
+    let (pitch_sin, pitch_cos) = pitch.to_radians().clamp(-FRAC_PI_2, FRAC_PI_2).sin_cos();
+
+    let half = size as f32 / 2.0;
+
+    PixelBuffer::new_from_func(size, size, |x, y| {
+        let u = (x as f32 + 0.5 - half) / half;
+        let v = (half - y as f32 - 0.5) / half;
+
+        let r = u.hypot(v);
+        let theta = func(r);
+
+        // Ray in camera space with x to the right, y up and z forward
+        let (dx, dy, dz) = if r == 0.0 {
+            (0.0, 0.0, 1.0)
+        } else {
+            let s = theta.sin() / r;
+            (u * s, v * s, theta.cos())
+        };
+
+        // Look up or down
+        let (dy, dz) = (
+            dy * pitch_cos + dz * pitch_sin,
+            dz * pitch_cos - dy * pitch_sin,
+        );
+
+        // Turn left or right
+        let (dx, dz) = (dx * yaw_cos + dz * yaw_sin, dz * yaw_cos - dx * yaw_sin);
+
+        sample_direction(buffer, dx, dy, dz)
+    })
+}
+
+/// Create a stereographic view of an equirectangular panorama
+///
+/// Looking straight down (`pitch` of -90 degrees) with a wide field of view of around
+/// 270 degrees results in the popular "little planet" effect, while looking up creates a
+/// "tunnel" view.
+///
+/// `fov` is the angle in degrees covered by the width of the result and has to be below 360.
+/// `yaw` turns the view to the right and `pitch` up, both in degrees.
+/// The result is a square with the height of the panorama as its size.
+pub fn stereographic_project(
+    buffer: &PixelBuffer<Rgb>,
+    fov: f32,
+    yaw: f32,
+    pitch: f32,
+) -> PixelBuffer<Rgb> {
+    let fov = fov.clamp(1.0, 359.0).to_radians();
+
+    // A point at a distance of r from the center is 2 * atan(r / (2 * f)) away from the view
+    // direction and the border has to be at half the field of view
+    let f = 1.0 / (2.0 * (fov / 4.0).tan());
+
+    project(buffer, yaw, pitch, |r| 2.0 * (r / (2.0 * f)).atan())
+}
+
+/// Create a perspective view of an equirectangular panorama like taken with a normal camera
+///
+/// `fov` is the angle in degrees covered by the width of the result and has to be below 180.
+/// `yaw` turns the view to the right and `pitch` up, both in degrees.
+/// The result is a square with the height of the panorama as its size.
+pub fn rectilinear_project(
+    buffer: &PixelBuffer<Rgb>,
+    fov: f32,
+    yaw: f32,
+    pitch: f32,
+) -> PixelBuffer<Rgb> {
+    let fov = fov.clamp(1.0, 179.0).to_radians();
+    let f = 1.0 / (fov / 2.0).tan();
+
+    project(buffer, yaw, pitch, |r| (r / f).atan())
+}
+
+/// Convert an equirectangular panorama into the six faces of a cube map
+///
+/// The faces are returned in the order right, left, top, bottom, front and back (+X, -X, +Y,
+/// -Y, +Z, -Z) as used by most skybox formats, where the front is the center of the panorama.
+/// The side faces are upright, the top face has the front at its bottom edge and the bottom
+/// face at its top edge.
+pub fn equirect_to_cubemap(buffer: &PixelBuffer<Rgb>, face_size: u32) -> [PixelBuffer<Rgb>; 6] {
+    CUBE_FACES.map(|(forward, right, up)| {
+        if buffer.is_empty() {
+            return PixelBuffer::new(face_size, face_size);
+        }
+
+        let half = face_size as f32 / 2.0;
+
+        PixelBuffer::new_from_func(face_size, face_size, |x, y| {
+            let u = (x as f32 + 0.5 - half) / half;
+            let v = (half - y as f32 - 0.5) / half;
+
+            let dx = forward.0 + u * right.0 + v * up.0;
+            let dy = forward.1 + u * right.1 + v * up.1;
+            let dz = forward.2 + u * right.2 + v * up.2;
+
+            let len = (dx * dx + dy * dy + dz * dz).sqrt();
+
+            sample_direction(buffer, dx / len, dy / len, dz / len)
+        })
+    })
+}
+
+/// Convert the six faces of a cube map into an equirectangular panorama
+///
+/// The faces are expected in the same order and orientation as returned by
+/// `equirect_to_cubemap()`. They don't need to have the same size.
+/// The result has the given width and half of it as its height.
+pub fn cubemap_to_equirect(faces: &[&PixelBuffer<Rgb>; 6], width: u32) -> PixelBuffer<Rgb> {
+    let height = width / 2;
+
+    if faces.iter().any(|face| face.is_empty()) {
+        return PixelBuffer::new(width, height);
+    }
+
+    PixelBuffer::new_from_func(width, height, |x, y| {
+        let longitude = ((x as f32 + 0.5) / width as f32 - 0.5) * TAU;
+        let latitude = (0.5 - (y as f32 + 0.5) / height as f32) * PI;
+
+        let (lon_sin, lon_cos) = longitude.sin_cos();
+        let (lat_sin, lat_cos) = latitude.sin_cos();
+        let direction = (lat_cos * lon_sin, lat_sin, lat_cos * lon_cos);
+
+        // The face the ray hits is the one it is most aligned with
+        let (face, (forward, right, up)) = faces
+            .iter()
+            .zip(CUBE_FACES.iter())
+            .max_by(|(_, a), (_, b)| dot(direction, a.0).total_cmp(&dot(direction, b.0)))
+            .unwrap();
+
+        let depth = dot(direction, *forward);
+        let u = dot(direction, *right) / depth;
+        let v = dot(direction, *up) / depth;
+
+        let sx = (u + 1.0) / 2.0 * face.width() as f32 - 0.5;
+        let sy = (1.0 - v) / 2.0 * face.height() as f32 - 0.5;
+
+        get_pixel_bilinear(face, sx, sy)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Panorama with a blue sky, green ground and a red marker straight ahead at the horizon
+    fn panorama() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(80, 40, |x, y| {
+            if (38..42).contains(&x) && (18..22).contains(&y) {
+                Rgb::RED
+            } else if y < 20 {
+                Rgb::BLUE
+            } else {
+                Rgb::GREEN
+            }
+        })
+    }
+
+    #[test]
+    fn test_little_planet() {
+        let result = stereographic_project(&panorama(), 270.0, 0.0, -90.0);
+
+        assert_eq!((result.width(), result.height()), (40, 40));
+        assert_eq!(result.get_pixel(20, 20), &Rgb::GREEN);
+        assert_eq!(result.get_pixel(0, 0), &Rgb::BLUE);
+        assert_eq!(result.get_pixel(39, 39), &Rgb::BLUE);
+    }
+
+    #[test]
+    fn test_rectilinear() {
+        let buffer = panorama();
+
+        let result = rectilinear_project(&buffer, 90.0, 0.0, 0.0);
+        assert_eq!(result.get_pixel(20, 20), &Rgb::RED);
+        assert_eq!(result.get_pixel(20, 5), &Rgb::BLUE);
+        assert_eq!(result.get_pixel(20, 35), &Rgb::GREEN);
+
+        let result = rectilinear_project(&buffer, 90.0, 180.0, 0.0);
+        assert_ne!(result.get_pixel(20, 20), &Rgb::RED);
+
+        let result = rectilinear_project(&buffer, 60.0, 0.0, 90.0);
+        assert_eq!(result.get_pixel(20, 20), &Rgb::BLUE);
+    }
+
+    #[test]
+    fn test_cubemap() {
+        let faces = equirect_to_cubemap(&panorama(), 16);
+
+        assert_eq!((faces[0].width(), faces[0].height()), (16, 16));
+
+        // The marker is in the center of the front face
+        assert_eq!(faces[4].get_pixel(8, 8), &Rgb::RED);
+        assert_eq!(faces[5].get_pixel(8, 8), &Rgb::GREEN);
+        assert!(faces[2].data().iter().all(|c| c == &Rgb::BLUE));
+        assert!(faces[3].data().iter().all(|c| c == &Rgb::GREEN));
+        assert_eq!(faces[0].get_pixel(8, 2), &Rgb::BLUE);
+        assert_eq!(faces[1].get_pixel(8, 13), &Rgb::GREEN);
+
+        let result = cubemap_to_equirect(&faces.each_ref(), 80);
+
+        assert_eq!((result.width(), result.height()), (80, 40));
+        assert_eq!(result.get_pixel(40, 20), &Rgb::RED);
+        assert_eq!(result.get_pixel(10, 5), &Rgb::BLUE);
+        assert_eq!(result.get_pixel(70, 35), &Rgb::GREEN);
+    }
+}
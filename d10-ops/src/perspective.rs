@@ -0,0 +1,197 @@
+use d10_core::color::Rgb;
+use d10_core::pixelbuffer::PixelBuffer;
+
+use crate::filters::get_pixel_filtered;
+use crate::FilterMode;
+
+/// Projective transformation between two planes
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Homography {
+    matrix: [f64; 9],
+}
+
+impl Homography {
+    /// Transformation mapping the four `src` points onto the four `dst` points
+    ///
+    /// Returns `None` if three of the points are collinear.
+    pub fn from_points(src: [(f32, f32); 4], dst: [(f32, f32); 4]) -> Option<Homography> {
+        // Solve the 8 equations of the 8 unknown matrix entries with h33 being fixed to 1
+        let mut rows = [[0.0f64; 9]; 8];
+
+        for (i, (&(x, y), &(u, v))) in src.iter().zip(dst.iter()).enumerate() {
+            let (x, y, u, v) = (x as f64, y as f64, u as f64, v as f64);
+            rows[i * 2] = [x, y, 1.0, 0.0, 0.0, 0.0, -u * x, -u * y, u];
+            rows[i * 2 + 1] = [0.0, 0.0, 0.0, x, y, 1.0, -v * x, -v * y, v];
+        }
+
+        let solution = solve(rows)?;
+
+        let mut matrix = [1.0; 9];
+        matrix[..8].copy_from_slice(&solution);
+
+        Some(Homography { matrix })
+    }
+
+    /// Transform a point
+    pub fn map(&self, x: f32, y: f32) -> (f32, f32) {
+        let m = &self.matrix;
+        let (x, y) = (x as f64, y as f64);
+
+        let w = m[6] * x + m[7] * y + m[8];
+        let u = (m[0] * x + m[1] * y + m[2]) / w;
+        let v = (m[3] * x + m[4] * y + m[5]) / w;
+
+        (u as f32, v as f32)
+    }
+
+    /// Transformation in the opposite direction
+    pub fn inverse(&self) -> Option<Homography> {
+        let m = &self.matrix;
+
+        let inv = [
+            m[4] * m[8] - m[5] * m[7],
+            m[2] * m[7] - m[1] * m[8],
+            m[1] * m[5] - m[2] * m[4],
+            m[5] * m[6] - m[3] * m[8],
+            m[0] * m[8] - m[2] * m[6],
+            m[2] * m[3] - m[0] * m[5],
+            m[3] * m[7] - m[4] * m[6],
+            m[1] * m[6] - m[0] * m[7],
+            m[0] * m[4] - m[1] * m[3],
+        ];
+
+        let det = m[0] * inv[0] + m[1] * inv[3] + m[2] * inv[6];
+        if det.abs() < 1e-12 {
+            return None;
+        }
+
+        Some(Homography {
+            matrix: inv.map(|v| v / det),
+        })
+    }
+}
+
+/// Gaussian elimination with partial pivoting of an augmented 8x9 matrix
+fn solve(mut rows: [[f64; 9]; 8]) -> Option<[f64; 8]> {
+    for col in 0..8 {
+        let pivot = (col..8).max_by(|a, b| rows[*a][col].abs().total_cmp(&rows[*b][col].abs()))?;
+        if rows[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        rows.swap(col, pivot);
+
+        for row in 0..8 {
+            if row != col {
+                let factor = rows[row][col] / rows[col][col];
+                let pivot_row = rows[col];
+                for (v, p) in rows[row][col..].iter_mut().zip(&pivot_row[col..]) {
+                    *v -= factor * p;
+                }
+            }
+        }
+    }
+
+    let mut result = [0.0; 8];
+    for (i, v) in result.iter_mut().enumerate() {
+        *v = rows[i][8] / rows[i][i];
+    }
+
+    Some(result)
+}
+
+/// Map the quadrilateral given by its corners onto a rectangle of the given size
+///
+/// The corners are expected in the order top left, top right, bottom right and bottom left.
+/// This corrects the perspective distortion of photographed documents, screens or signs.
+/// If three corners are collinear the quad can't be mapped and a transparent image is returned.
+pub fn perspective_warp(
+    buffer: &PixelBuffer<Rgb>,
+    corners: [(f32, f32); 4],
+    width: u32,
+    height: u32,
+    filter: FilterMode,
+) -> PixelBuffer<Rgb> {
+    let target = [
+        (0.0, 0.0),
+        (width as f32, 0.0),
+        (width as f32, height as f32),
+        (0.0, height as f32),
+    ];
+
+    // Pixel centers are at half positions while the corners describe the outer edges
+    let corners = corners.map(|(x, y)| (x - 0.5, y - 0.5));
+
+    let Some(homography) = Homography::from_points(target, corners) else {
+        return PixelBuffer::new(width, height);
+    };
+
+    if buffer.is_empty() {
+        return PixelBuffer::new(width, height);
+    }
+
+    PixelBuffer::new_from_func(width, height, |x, y| {
+        let (sx, sy) = homography.map(x as f32 + 0.5, y as f32 + 0.5);
+        get_pixel_filtered(buffer, sx, sy, filter)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_homography() {
+        let src = [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+        let dst = [(2.0, 1.0), (30.0, 4.0), (25.0, 20.0), (1.0, 18.0)];
+
+        let h = Homography::from_points(src, dst).unwrap();
+
+        for (s, d) in src.iter().zip(dst.iter()) {
+            let (x, y) = h.map(s.0, s.1);
+            assert!((x - d.0).abs() < 1e-3 && (y - d.1).abs() < 1e-3);
+        }
+
+        let inv = h.inverse().unwrap();
+        let (x, y) = inv.map(25.0, 20.0);
+        assert!((x - 10.0).abs() < 1e-3 && (y - 10.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_homography_collinear() {
+        let src = [(0.0, 0.0), (1.0, 1.0), (2.0, 2.0), (0.0, 5.0)];
+        let dst = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+
+        assert!(Homography::from_points(src, dst).is_none());
+    }
+
+    #[test]
+    fn test_perspective_warp() {
+        let buffer = PixelBuffer::new_from_func(20, 20, |x, y| {
+            if (5..15).contains(&x) && (5..15).contains(&y) {
+                Rgb::RED
+            } else {
+                Rgb::BLUE
+            }
+        });
+
+        let res = perspective_warp(
+            &buffer,
+            [(5.0, 5.0), (15.0, 5.0), (15.0, 15.0), (5.0, 15.0)],
+            4,
+            4,
+            FilterMode::Nearest,
+        );
+
+        assert_eq!((res.width(), res.height()), (4, 4));
+        assert!(res.data().iter().all(|c| *c == Rgb::RED));
+
+        let identity = perspective_warp(
+            &buffer,
+            [(0.0, 0.0), (20.0, 0.0), (20.0, 20.0), (0.0, 20.0)],
+            20,
+            20,
+            FilterMode::Bilinear,
+        );
+        assert_eq!(identity.data(), buffer.data());
+    }
+}
@@ -0,0 +1,389 @@
+use std::borrow::Cow;
+
+use d10_core::color::{Intensity, Rgb};
+use d10_core::pixelbuffer::PixelBuffer;
+
+use crate::{
+    crop, flip_horizontal, flip_vertical, gaussian_blur, resize, rotate180, rotate270, rotate90,
+    unsharp, FilterMode,
+};
+
+/// A single operation of a `Pipeline`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PipelineStep {
+    Brightness(f32),
+    Contrast(f32),
+    Saturation(f32),
+    Vibrance(f32),
+    Lightness(f32),
+    HueRotate(f32),
+    Gamma(f32),
+    Level {
+        black_point: f32,
+        white_point: f32,
+        gamma: f32,
+    },
+    Invert,
+    Sepia,
+    ToGray(Intensity),
+    Resize {
+        width: u32,
+        height: u32,
+        filter: FilterMode,
+    },
+    Crop {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    GaussianBlur {
+        radius: u32,
+        sigma: Option<f32>,
+    },
+    Unsharp {
+        radius: u32,
+        factor: f32,
+        sigma: Option<f32>,
+    },
+    FlipHorizontal,
+    FlipVertical,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+impl PipelineStep {
+    /// Returns true if every pixel only depends on the same pixel of the input
+    ///
+    /// Consecutive per pixel steps are fused into a single pass over the buffer.
+    pub fn is_per_pixel(&self) -> bool {
+        self.map_color(&Rgb::NONE).is_some()
+    }
+
+    /// Returns true if every pixel stays at its position and the size doesn't change
+    pub fn keeps_geometry(&self) -> bool {
+        use PipelineStep::*;
+        !matches!(
+            self,
+            Resize { .. }
+                | Crop { .. }
+                | FlipHorizontal
+                | FlipVertical
+                | Rotate90
+                | Rotate180
+                | Rotate270
+        )
+    }
+
+    fn map_color(&self, c: &Rgb) -> Option<Rgb> {
+        use PipelineStep::*;
+        Some(match *self {
+            Brightness(factor) => c.with_brightness(factor),
+            Contrast(factor) => c.with_contrast(factor),
+            Saturation(factor) => c.with_saturation(factor),
+            Vibrance(factor) => c.with_vibrance(factor),
+            Lightness(factor) => c.with_lightness(factor),
+            HueRotate(radians) => c.with_hue_rotate(radians),
+            Gamma(gamma) => c.with_gamma(gamma),
+            Level {
+                black_point,
+                white_point,
+                gamma,
+            } => c.with_level(black_point, white_point, gamma),
+            Invert => c.invert(),
+            Sepia => c.with_sepia(),
+            ToGray(intensity) => c.to_gray_with_intensity(intensity),
+            _ => return None,
+        })
+    }
+
+    fn apply(&self, buffer: &PixelBuffer<Rgb>) -> PixelBuffer<Rgb> {
+        use PipelineStep::*;
+        match *self {
+            Resize {
+                width,
+                height,
+                filter,
+            } => resize(buffer, width, height, filter),
+            Crop {
+                x,
+                y,
+                width,
+                height,
+            } => crop(buffer, x, y, width, height),
+            GaussianBlur { radius, sigma } => gaussian_blur(buffer, radius, sigma),
+            Unsharp {
+                radius,
+                factor,
+                sigma,
+            } => unsharp(buffer, radius, factor, sigma),
+            FlipHorizontal => flip_horizontal(buffer),
+            FlipVertical => flip_vertical(buffer),
+            Rotate90 => rotate90(buffer),
+            Rotate180 => rotate180(buffer),
+            Rotate270 => rotate270(buffer),
+            _ => buffer.map_colors(|c| self.map_color(c).unwrap_or(*c)),
+        }
+    }
+}
+
+/// Lazily executed chain of operations
+///
+/// The steps are only recorded until `apply()` is called. Consecutive per pixel steps like
+/// brightness, contrast and saturation are fused and executed in a single pass without creating
+/// intermediate buffers.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Pipeline {
+    steps: Vec<PipelineStep>,
+}
+
+impl Pipeline {
+    pub fn new() -> Pipeline {
+        Pipeline { steps: vec![] }
+    }
+
+    pub fn steps(&self) -> &[PipelineStep] {
+        &self.steps
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn with(mut self, step: PipelineStep) -> Pipeline {
+        self.steps.push(step);
+        self
+    }
+
+    pub fn brightness(self, factor: f32) -> Pipeline {
+        self.with(PipelineStep::Brightness(factor))
+    }
+
+    pub fn contrast(self, factor: f32) -> Pipeline {
+        self.with(PipelineStep::Contrast(factor))
+    }
+
+    pub fn saturation(self, factor: f32) -> Pipeline {
+        self.with(PipelineStep::Saturation(factor))
+    }
+
+    pub fn vibrance(self, factor: f32) -> Pipeline {
+        self.with(PipelineStep::Vibrance(factor))
+    }
+
+    pub fn lightness(self, factor: f32) -> Pipeline {
+        self.with(PipelineStep::Lightness(factor))
+    }
+
+    pub fn hue_rotate(self, radians: f32) -> Pipeline {
+        self.with(PipelineStep::HueRotate(radians))
+    }
+
+    pub fn gamma(self, gamma: f32) -> Pipeline {
+        self.with(PipelineStep::Gamma(gamma))
+    }
+
+    pub fn level(self, black_point: f32, white_point: f32, gamma: f32) -> Pipeline {
+        self.with(PipelineStep::Level {
+            black_point,
+            white_point,
+            gamma,
+        })
+    }
+
+    pub fn invert(self) -> Pipeline {
+        self.with(PipelineStep::Invert)
+    }
+
+    pub fn sepia(self) -> Pipeline {
+        self.with(PipelineStep::Sepia)
+    }
+
+    pub fn to_gray(self, intensity: Intensity) -> Pipeline {
+        self.with(PipelineStep::ToGray(intensity))
+    }
+
+    pub fn resize(self, width: u32, height: u32, filter: FilterMode) -> Pipeline {
+        self.with(PipelineStep::Resize {
+            width,
+            height,
+            filter,
+        })
+    }
+
+    pub fn crop(self, x: u32, y: u32, width: u32, height: u32) -> Pipeline {
+        self.with(PipelineStep::Crop {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+
+    pub fn gaussian_blur(self, radius: u32, sigma: Option<f32>) -> Pipeline {
+        self.with(PipelineStep::GaussianBlur { radius, sigma })
+    }
+
+    pub fn unsharp(self, radius: u32, factor: f32, sigma: Option<f32>) -> Pipeline {
+        self.with(PipelineStep::Unsharp {
+            radius,
+            factor,
+            sigma,
+        })
+    }
+
+    pub fn flip_horizontal(self) -> Pipeline {
+        self.with(PipelineStep::FlipHorizontal)
+    }
+
+    pub fn flip_vertical(self) -> Pipeline {
+        self.with(PipelineStep::FlipVertical)
+    }
+
+    pub fn rotate90(self) -> Pipeline {
+        self.with(PipelineStep::Rotate90)
+    }
+
+    pub fn rotate180(self) -> Pipeline {
+        self.with(PipelineStep::Rotate180)
+    }
+
+    pub fn rotate270(self) -> Pipeline {
+        self.with(PipelineStep::Rotate270)
+    }
+
+    /// Steps grouped into the passes executed over the buffer
+    ///
+    /// Every group of per pixel steps is fused into one pass, all other steps need a pass of
+    /// their own.
+    pub fn passes(&self) -> Vec<&[PipelineStep]> {
+        let mut passes = vec![];
+        let mut start = 0;
+
+        while start < self.steps.len() {
+            let len = self.steps[start..]
+                .iter()
+                .take_while(|step| step.is_per_pixel())
+                .count()
+                .max(1);
+
+            passes.push(&self.steps[start..start + len]);
+            start += len;
+        }
+
+        passes
+    }
+
+    /// Execute all steps on `buffer`
+    pub fn apply(&self, buffer: &PixelBuffer<Rgb>) -> PixelBuffer<Rgb> {
+        self.execute(Cow::Borrowed(buffer))
+    }
+
+    /// Execute all steps reusing the memory of `buffer` where possible
+    ///
+    /// Pipelines without any steps that change the size don't allocate a new buffer.
+    pub fn apply_owned(&self, buffer: PixelBuffer<Rgb>) -> PixelBuffer<Rgb> {
+        self.execute(Cow::Owned(buffer))
+    }
+
+    fn execute(&self, mut buffer: Cow<PixelBuffer<Rgb>>) -> PixelBuffer<Rgb> {
+        for pass in self.passes() {
+            let map = |c: &Rgb| {
+                pass.iter()
+                    .fold(*c, |c, step| step.map_color(&c).unwrap_or(c))
+            };
+
+            buffer = match buffer {
+                Cow::Owned(mut buffer) if pass[0].is_per_pixel() => {
+                    buffer.mod_colors(map);
+                    Cow::Owned(buffer)
+                }
+                buffer if pass[0].is_per_pixel() => Cow::Owned(buffer.map_colors(map)),
+                buffer => Cow::Owned(pass[0].apply(&buffer)),
+            };
+        }
+
+        buffer.into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use d10_core::color::Color;
+
+    fn test_buffer() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(8, 6, |x, y| {
+            Rgb::new_with_alpha(x as f32 / 8.0, y as f32 / 6.0, 0.3, 0.9)
+        })
+    }
+
+    #[test]
+    fn test_fusion() {
+        let pipeline = Pipeline::new()
+            .brightness(0.1)
+            .contrast(1.2)
+            .saturation(0.8)
+            .resize(4, 3, FilterMode::Bilinear)
+            .invert()
+            .gamma(1.5)
+            .flip_horizontal()
+            .flip_vertical();
+
+        let passes = pipeline.passes();
+        assert_eq!(passes.len(), 5);
+        assert_eq!(passes[0].len(), 3);
+        assert_eq!(passes[2], &[PipelineStep::Invert, PipelineStep::Gamma(1.5)]);
+        assert!(!passes[3][0].is_per_pixel());
+
+        assert!(Pipeline::new().passes().is_empty());
+    }
+
+    #[test]
+    fn test_apply() {
+        let buffer = test_buffer();
+
+        let pipeline = Pipeline::new()
+            .brightness(0.1)
+            .contrast(1.2)
+            .saturation(0.8)
+            .resize(4, 3, FilterMode::Bilinear)
+            .invert()
+            .flip_horizontal();
+
+        let expected = buffer.map_colors(|c| {
+            c.with_brightness(0.1)
+                .with_contrast(1.2)
+                .with_saturation(0.8)
+        });
+        let expected = resize(&expected, 4, 3, FilterMode::Bilinear);
+        let expected = flip_horizontal(&expected.map_colors(|c| c.invert()));
+
+        let res = pipeline.apply(&buffer);
+        assert_eq!(res.width(), 4);
+        assert_eq!(res.height(), 3);
+        assert_eq!(res.data(), expected.data());
+
+        let owned = pipeline.apply_owned(buffer.clone());
+        assert_eq!(owned.data(), expected.data());
+
+        assert_eq!(Pipeline::new().apply(&buffer).data(), buffer.data());
+    }
+
+    #[test]
+    fn test_apply_owned_in_place() {
+        let buffer = test_buffer();
+        let ptr = buffer.data().as_ptr();
+
+        let res = Pipeline::new()
+            .sepia()
+            .level(0.1, 0.9, 1.0)
+            .to_gray(Intensity::Average)
+            .apply_owned(buffer);
+
+        assert_eq!(res.data().as_ptr(), ptr);
+        assert!(res.data().iter().all(|c| c.is_grayscale()));
+        assert_eq!(res.get_pixel(0, 0).alpha(), 0.9);
+    }
+}
@@ -0,0 +1,47 @@
+use d10_core::color::Color;
+use d10_core::pixelbuffer::PixelBuffer;
+use rand::Rng;
+use rand_distr::Poisson;
+
+/// Sample the number of photons for a channel value and scale it back
+///
+/// `photons` is the expected photon count of a channel with the value 1.0.
+fn poisson_value<R>(v: f32, photons: f32, rng: &mut R) -> f32
+where
+    R: Rng,
+{
+    let lambda = v * photons;
+
+    if lambda <= 0.0 || !lambda.is_finite() {
+        return v.max(0.0);
+    }
+
+    match Poisson::new(lambda) {
+        Ok(poisson) => rng.sample(poisson) / photons,
+        Err(_) => v,
+    }
+}
+
+/// Simulate photon shot noise that grows with the intensity of a channel
+///
+/// `photons` is the number of photons a fully saturated channel receives.
+/// Lower values result in stronger noise, like a sensor at high ISO.
+pub fn poisson_noise<C>(buffer: &PixelBuffer<C>, photons: f32) -> PixelBuffer<C>
+where
+    C: Color,
+{
+    let mut rng = rand::thread_rng();
+    let photons = photons.max(f32::EPSILON);
+
+    buffer.map_colors(|c| c.map_color_channels(|v| poisson_value(v, photons, &mut rng)))
+}
+
+pub fn add_poisson_noise<C>(buffer: &mut PixelBuffer<C>, photons: f32)
+where
+    C: Color,
+{
+    let mut rng = rand::thread_rng();
+    let photons = photons.max(f32::EPSILON);
+
+    buffer.mod_colors(|c| c.map_color_channels(|v| poisson_value(v, photons, &mut rng)));
+}
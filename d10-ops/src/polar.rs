@@ -0,0 +1,221 @@
+use std::f32::consts::TAU;
+
+use d10_core::color::Rgb;
+use d10_core::pixelbuffer::PixelBuffer;
+
+use crate::filters::get_pixel_filtered;
+use crate::FilterMode;
+
+fn center(buffer: &PixelBuffer<Rgb>) -> (f32, f32) {
+    (buffer.width() as f32 / 2.0, buffer.height() as f32 / 2.0)
+}
+
+fn max_radius(buffer: &PixelBuffer<Rgb>) -> f32 {
+    buffer.width().min(buffer.height()) as f32 / 2.0
+}
+
+/// Wrap a horizontal position around the image to avoid a seam where the angle jumps
+fn wrap_x(x: f32, width: u32) -> f32 {
+    let width = width as f32;
+    (x + 0.5).rem_euclid(width) - 0.5
+}
+
+/// Wrap an image with the angle in the x direction and the radius in the y direction into
+/// a circle around the center
+///
+/// The top row ends up in the center, the bottom row at the border of the largest circle
+/// that fits into the image. The angle starts at the top and runs clockwise.
+/// This turns e.g. a panorama into a round "tiny planet" like view. Pixels outside of the
+/// circle are transparent.
+pub fn to_polar(buffer: &PixelBuffer<Rgb>, filter: FilterMode) -> PixelBuffer<Rgb> {
+    if buffer.is_empty() {
+        return buffer.clone();
+    }
+
+    let (cx, cy) = center(buffer);
+    let max_radius = max_radius(buffer);
+    let width = buffer.width() as f32;
+    let height = buffer.height() as f32;
+
+    PixelBuffer::new_from_func(buffer.width(), buffer.height(), |x, y| {
+        let dx = x as f32 + 0.5 - cx;
+        let dy = y as f32 + 0.5 - cy;
+
+        let radius = dx.hypot(dy);
+        if radius > max_radius {
+            return Rgb::NONE;
+        }
+
+        let angle = dx.atan2(-dy).rem_euclid(TAU);
+
+        let sx = wrap_x(angle / TAU * width - 0.5, buffer.width());
+        let sy = radius / max_radius * height - 0.5;
+
+        get_pixel_filtered(buffer, sx, sy, filter)
+    })
+}
+
+/// Unwrap the circle around the center into an image with the angle in the x direction and
+/// the radius in the y direction
+///
+/// The center ends up in the top row, the border of the largest circle that fits into the
+/// image in the bottom row. The angle starts at the top and runs clockwise.
+/// This is the inverse of `to_polar()`.
+pub fn from_polar(buffer: &PixelBuffer<Rgb>, filter: FilterMode) -> PixelBuffer<Rgb> {
+    if buffer.is_empty() {
+        return buffer.clone();
+    }
+
+    let (cx, cy) = center(buffer);
+    let max_radius = max_radius(buffer);
+    let width = buffer.width() as f32;
+    let height = buffer.height() as f32;
+
+    PixelBuffer::new_from_func(buffer.width(), buffer.height(), |x, y| {
+        let angle = (x as f32 + 0.5) / width * TAU;
+        let radius = (y as f32 + 0.5) / height * max_radius;
+
+        let sx = cx + radius * angle.sin() - 0.5;
+        let sy = cy - radius * angle.cos() - 0.5;
+
+        get_pixel_filtered(buffer, sx, sy, filter)
+    })
+}
+
+/// Move every pixel to the position given by `func` in polar coordinates around the center
+///
+/// `func` gets the distance to the center relative to the radius of the largest circle that fits
+/// into the image and returns the new relative distance and the rotation in radians.
+fn warp_radial<F>(buffer: &PixelBuffer<Rgb>, filter: FilterMode, func: F) -> PixelBuffer<Rgb>
+where
+    F: Fn(f32) -> (f32, f32),
+{
+    if buffer.is_empty() {
+        return buffer.clone();
+    }
+
+    let (cx, cy) = center(buffer);
+    let max_radius = max_radius(buffer);
+
+    buffer.map_colors_enumerated(|x, y, c| {
+        let dx = x as f32 + 0.5 - cx;
+        let dy = y as f32 + 0.5 - cy;
+
+        let radius = dx.hypot(dy) / max_radius;
+        if radius >= 1.0 || radius == 0.0 {
+            return *c;
+        }
+
+        let (new_radius, rotation) = func(radius);
+        let scale = new_radius / radius;
+
+        let (sin, cos) = rotation.sin_cos();
+        let sx = (dx * cos - dy * sin) * scale + cx - 0.5;
+        let sy = (dx * sin + dy * cos) * scale + cy - 0.5;
+
+        get_pixel_filtered(buffer, sx, sy, filter)
+    })
+}
+
+/// Rotate the pixels around the center with a rotation decreasing to zero at the border of the
+/// largest circle that fits into the image
+///
+/// `degrees` is the rotation in the center. Positive values swirl clockwise.
+pub fn swirl(buffer: &PixelBuffer<Rgb>, degrees: f32, filter: FilterMode) -> PixelBuffer<Rgb> {
+    let radians = degrees.to_radians();
+
+    warp_radial(buffer, filter, |radius| {
+        let factor = 1.0 - radius;
+        (radius, -radians * factor * factor)
+    })
+}
+
+/// Bulge out the center of the image like a fisheye lens
+///
+/// Positive values of `strength` magnify the center, negative values pinch it.
+/// Only the largest circle that fits into the image is changed.
+pub fn fisheye(buffer: &PixelBuffer<Rgb>, strength: f32, filter: FilterMode) -> PixelBuffer<Rgb> {
+    // Keep the exponent positive to not flip the image
+    let exponent = (1.0 + strength).max(0.1);
+
+    warp_radial(buffer, filter, |radius| (radius.powf(exponent), 0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stripes() -> PixelBuffer<Rgb> {
+        // Left half red, right half blue with a white bottom row
+        PixelBuffer::new_from_func(20, 20, |x, y| {
+            if y == 19 {
+                Rgb::WHITE
+            } else if x < 10 {
+                Rgb::RED
+            } else {
+                Rgb::BLUE
+            }
+        })
+    }
+
+    #[test]
+    fn test_to_polar() {
+        let result = to_polar(&stripes(), FilterMode::Nearest);
+
+        // The first half of the angles is on the right side
+        assert_eq!(result.get_pixel(14, 10), &Rgb::RED);
+        assert_eq!(result.get_pixel(5, 10), &Rgb::BLUE);
+        assert_eq!(result.get_pixel(0, 0), &Rgb::NONE);
+        assert_eq!(result.get_pixel(10, 0), &Rgb::WHITE);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let buffer = PixelBuffer::new_from_func(40, 40, |x, y| {
+            Rgb::new(x as f32 / 39.0, y as f32 / 39.0, 0.5)
+        });
+
+        let result = to_polar(
+            &from_polar(&buffer, FilterMode::Bilinear),
+            FilterMode::Bilinear,
+        );
+
+        // Away from the center and the border the image is restored
+        for (x, y) in [(20, 8), (30, 20), (14, 26)] {
+            let c1 = buffer.get_pixel(x, y);
+            let c2 = result.get_pixel(x, y);
+            assert!((c1.red() - c2.red()).abs() < 0.05, "{} {}", x, y);
+            assert!((c1.green() - c2.green()).abs() < 0.05, "{} {}", x, y);
+        }
+    }
+
+    #[test]
+    fn test_swirl() {
+        let buffer = stripes();
+
+        let result = swirl(&buffer, 0.0, FilterMode::Bilinear);
+        assert_eq!(result.data(), buffer.data());
+
+        let result = swirl(&buffer, 180.0, FilterMode::Nearest);
+        assert_eq!(result.get_pixel(0, 0), buffer.get_pixel(0, 0));
+        assert_eq!(result.get_pixel(11, 10), &Rgb::RED);
+    }
+
+    #[test]
+    fn test_fisheye() {
+        let buffer = PixelBuffer::new_from_func(21, 21, |x, y| {
+            if (x as i32 - 10).abs() <= 1 && (y as i32 - 10).abs() <= 1 {
+                Rgb::RED
+            } else {
+                Rgb::BLUE
+            }
+        });
+
+        let result = fisheye(&buffer, 1.0, FilterMode::Nearest);
+        assert_eq!(result.get_pixel(10, 13), &Rgb::RED);
+        assert_eq!(result.get_pixel(0, 0), buffer.get_pixel(0, 0));
+
+        let result = fisheye(&buffer, -0.5, FilterMode::Nearest);
+        assert_eq!(result.get_pixel(10, 11), &Rgb::BLUE);
+    }
+}
@@ -0,0 +1,148 @@
+use d10_core::color::{Color, Rgb};
+use d10_core::pixelbuffer::PixelBuffer;
+
+use crate::{gaussian_blur, symmetric_nearest_neighbor};
+
+/// Skin tones in the Cb and Cr channels of 8 bit YCbCr as proposed by Chai and Ngan
+const CB_RANGE: (f32, f32) = (77.0, 127.0);
+const CR_RANGE: (f32, f32) = (133.0, 173.0);
+
+/// Width of the soft transition at the borders of the skin tone ranges
+const RANGE_FEATHER: f32 = 6.0;
+
+/// Reduction of the fine texture at full strength
+///
+/// Removing all details would result in an unnatural plastic look.
+const MAX_DETAIL_REDUCTION: f32 = 0.6;
+
+/// Weight between 0 and 1 of a value being inside of a range with soft borders
+fn range_weight(value: f32, range: (f32, f32)) -> f32 {
+    let below = (value - range.0) / RANGE_FEATHER + 0.5;
+    let above = (range.1 - value) / RANGE_FEATHER + 0.5;
+    below.min(above).clamp(0.0, 1.0)
+}
+
+/// Likelihood of a color being a skin tone based on its chroma in YCbCr
+pub(crate) fn skin_weight(color: &Rgb) -> f32 {
+    let c = color.to_srgb();
+    let r = c.red() * 255.0;
+    let g = c.green() * 255.0;
+    let b = c.blue() * 255.0;
+
+    let cb = 128.0 - 0.168_736 * r - 0.331_264 * g + 0.5 * b;
+    let cr = 128.0 + 0.5 * r - 0.418_688 * g - 0.081_312 * b;
+
+    range_weight(cb, CB_RANGE) * range_weight(cr, CR_RANGE) * c.alpha()
+}
+
+/// Smooth skin while keeping edges and the structure of the rest of the image
+///
+/// The image is split into a low frequency layer with the colors and shading and a high
+/// frequency layer with the fine texture. Blotches in the low frequencies get removed with an
+/// edge preserving blur and the texture gets reduced. The result is only applied to skin tones
+/// using a feathered mask. The strength should be between 0 and 1.
+pub fn portrait_smooth(buffer: &PixelBuffer<Rgb>, strength: f32) -> PixelBuffer<Rgb> {
+    let strength = strength.clamp(0.0, 1.0);
+
+    if buffer.is_empty() || strength == 0.0 {
+        return buffer.clone();
+    }
+
+    // Blemishes and pores are small relative to a face, which is usually a large part of a portrait
+    let radius = ((buffer.width().min(buffer.height()) as f32 * 0.01).round() as u32).max(2);
+
+    let low = gaussian_blur(buffer, radius, None);
+    let smoothed = symmetric_nearest_neighbor(&low, radius as usize * 2, true);
+
+    let mask = buffer.map_colors(|c| {
+        let v = skin_weight(c);
+        Rgb::new(v, v, v)
+    });
+    let mask = gaussian_blur(&mask, radius, None);
+
+    let detail = 1.0 - MAX_DETAIL_REDUCTION * strength;
+
+    let data = buffer
+        .data()
+        .iter()
+        .zip(low.data())
+        .zip(smoothed.data())
+        .zip(mask.data())
+        .map(|(((c, low), smoothed), mask)| {
+            let weight = mask.red() * strength;
+
+            let mut result = *c;
+            for i in 0..3 {
+                let retouched = smoothed.data[i] + (c.data[i] - low.data[i]) * detail;
+                result.data[i] = c.data[i] + (retouched - c.data[i]) * weight;
+            }
+
+            Rgb::new_with_alpha(result.data[0], result.data[1], result.data[2], c.alpha())
+        })
+        .collect();
+
+    PixelBuffer::new_from_raw(buffer.width(), buffer.height(), data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use d10_core::color::Srgb;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    /// Noisy skin on the left and a noisy blue background on the right
+    fn test_buffer() -> PixelBuffer<Rgb> {
+        let mut rng = StdRng::seed_from_u64(3);
+
+        PixelBuffer::new_from_func(60, 40, |x, _| {
+            let n = rng.gen::<f32>() * 0.08 - 0.04;
+            if x < 30 {
+                Srgb::new(0.85 + n, 0.65 + n, 0.55 + n).to_rgb()
+            } else {
+                Srgb::new(0.2 + n, 0.3 + n, 0.7 + n).to_rgb()
+            }
+        })
+    }
+
+    fn variance(buffer: &PixelBuffer<Rgb>, x_range: std::ops::Range<u32>) -> f32 {
+        let values: Vec<f32> = buffer
+            .enumerate()
+            .filter(|(x, y, _)| x_range.contains(x) && (5..35).contains(y))
+            .map(|(_, _, c)| c.to_srgb().green())
+            .collect();
+
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / values.len() as f32
+    }
+
+    #[test]
+    fn test_skin_weight() {
+        assert_eq!(skin_weight(&Srgb::new(0.88, 0.67, 0.55).to_rgb()), 1.0);
+        assert_eq!(skin_weight(&Srgb::new(0.55, 0.36, 0.25).to_rgb()), 1.0);
+        assert_eq!(skin_weight(&Srgb::new(0.2, 0.3, 0.7).to_rgb()), 0.0);
+        assert_eq!(skin_weight(&Rgb::WHITE), 0.0);
+    }
+
+    #[test]
+    fn test_portrait_smooth() {
+        let buffer = test_buffer();
+
+        let res = portrait_smooth(&buffer, 1.0);
+
+        assert!(variance(&res, 5..25) < variance(&buffer, 5..25) * 0.5);
+
+        for x in 40..60 {
+            for y in 0..40 {
+                assert_eq!(res.get_pixel(x, y), buffer.get_pixel(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn test_zero_strength() {
+        let buffer = test_buffer();
+
+        assert_eq!(portrait_smooth(&buffer, 0.0).data(), buffer.data());
+    }
+}
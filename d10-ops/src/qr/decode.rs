@@ -0,0 +1,595 @@
+//! Reading the data of a sampled QR code symbol
+
+use super::reed_solomon;
+
+/// Error correction level of a symbol
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(super) enum EcLevel {
+    L,
+    M,
+    Q,
+    H,
+}
+
+impl EcLevel {
+    fn from_format_bits(bits: u32) -> EcLevel {
+        match bits & 3 {
+            1 => EcLevel::L,
+            0 => EcLevel::M,
+            3 => EcLevel::Q,
+            _ => EcLevel::H,
+        }
+    }
+
+    pub(super) fn format_bits(self) -> u32 {
+        match self {
+            EcLevel::L => 1,
+            EcLevel::M => 0,
+            EcLevel::Q => 3,
+            EcLevel::H => 2,
+        }
+    }
+}
+
+/// Number of error correction codewords per block indexed by level and version
+const ECC_CODEWORDS_PER_BLOCK: [[u8; 41]; 4] = [
+    [
+        0, 7, 10, 15, 20, 26, 18, 20, 24, 30, 18, 20, 24, 26, 30, 22, 24, 28, 30, 28, 28, 28, 28,
+        30, 30, 26, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30,
+    ],
+    [
+        0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26, 26, 28,
+        28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28,
+    ],
+    [
+        0, 13, 22, 18, 26, 18, 24, 18, 22, 20, 24, 28, 26, 24, 20, 30, 24, 28, 28, 26, 30, 28, 30,
+        30, 30, 30, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30,
+    ],
+    [
+        0, 17, 28, 22, 16, 22, 28, 26, 26, 24, 28, 24, 28, 22, 24, 24, 30, 28, 28, 26, 28, 30, 24,
+        30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30,
+    ],
+];
+
+/// Number of error correction blocks indexed by level and version
+const NUM_ERROR_CORRECTION_BLOCKS: [[u8; 41]; 4] = [
+    [
+        0, 1, 1, 1, 1, 1, 2, 2, 2, 2, 4, 4, 4, 4, 4, 6, 6, 6, 6, 7, 8, 8, 9, 9, 10, 12, 12, 12, 13,
+        14, 15, 16, 17, 18, 19, 19, 20, 21, 22, 24, 25,
+    ],
+    [
+        0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16, 17, 17, 18, 20, 21,
+        23, 25, 26, 28, 29, 31, 33, 35, 37, 38, 40, 43, 45, 47, 49,
+    ],
+    [
+        0, 1, 1, 2, 2, 4, 4, 6, 6, 8, 8, 8, 10, 12, 16, 12, 17, 16, 18, 21, 20, 23, 23, 25, 27, 29,
+        34, 34, 35, 38, 40, 43, 45, 48, 51, 53, 56, 59, 62, 65, 68,
+    ],
+    [
+        0, 1, 1, 2, 4, 4, 4, 5, 6, 8, 8, 11, 11, 16, 16, 18, 16, 19, 21, 25, 25, 25, 34, 30, 32,
+        35, 37, 40, 42, 45, 48, 51, 54, 57, 60, 63, 66, 70, 74, 77, 81,
+    ],
+];
+
+const ALPHANUMERIC_CHARS: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// Number of modules in both directions
+pub(super) fn symbol_size(version: u32) -> usize {
+    version as usize * 4 + 17
+}
+
+/// Centers of the alignment patterns in both directions
+pub(super) fn alignment_positions(version: u32) -> Vec<usize> {
+    if version == 1 {
+        return vec![];
+    }
+
+    let version = version as usize;
+    let count = version / 7 + 2;
+    let step = (version * 8 + count * 3 + 5) / (count * 4 - 4) * 2;
+    let last = symbol_size(version as u32) - 7;
+
+    let mut positions = vec![6];
+    positions.extend((0..count - 1).rev().map(|i| last - i * step));
+    positions
+}
+
+/// Check if an alignment pattern is placed at the given position
+///
+/// Positions overlapping with finder patterns are skipped.
+pub(super) fn has_alignment_pattern(positions: &[usize], i: usize, j: usize) -> bool {
+    let last = positions.len() - 1;
+    ![(0, 0), (0, last), (last, 0)].contains(&(i, j))
+}
+
+/// Number of modules available for data and error correction codewords
+fn raw_data_modules(version: u32) -> usize {
+    let v = version as usize;
+    let mut result = (16 * v + 128) * v + 64;
+
+    if v >= 2 {
+        let count = v / 7 + 2;
+        result -= (25 * count - 10) * count - 55;
+        if v >= 7 {
+            result -= 36;
+        }
+    }
+
+    result
+}
+
+/// Sizes of the error correction blocks of a symbol
+pub(super) struct BlockLayout {
+    pub data_lens: Vec<usize>,
+    pub ec_len: usize,
+}
+
+impl BlockLayout {
+    pub(super) fn new(version: u32, ec_level: EcLevel) -> BlockLayout {
+        let level = ec_level as usize;
+        let blocks = NUM_ERROR_CORRECTION_BLOCKS[level][version as usize] as usize;
+        let ec_len = ECC_CODEWORDS_PER_BLOCK[level][version as usize] as usize;
+
+        let raw_codewords = raw_data_modules(version) / 8;
+        let short_blocks = blocks - raw_codewords % blocks;
+        let short_len = raw_codewords / blocks - ec_len;
+
+        let data_lens = (0..blocks)
+            .map(|i| {
+                if i < short_blocks {
+                    short_len
+                } else {
+                    short_len + 1
+                }
+            })
+            .collect();
+
+        BlockLayout { data_lens, ec_len }
+    }
+
+    /// Number of data codewords of all blocks
+    pub(super) fn data_capacity(&self) -> usize {
+        self.data_lens.iter().sum()
+    }
+
+    /// Block index of every codeword in the order they are placed in the symbol
+    pub(super) fn order(&self) -> Vec<usize> {
+        let max_len = self.data_lens.iter().copied().max().unwrap_or(0);
+        let blocks = self.data_lens.len();
+
+        let data = (0..max_len).flat_map(|i| {
+            self.data_lens
+                .iter()
+                .enumerate()
+                .filter(move |(_, len)| i < **len)
+                .map(|(j, _)| j)
+        });
+        let ec = (0..self.ec_len).flat_map(|_| 0..blocks);
+
+        data.chain(ec).collect()
+    }
+}
+
+/// The modules reserved for finder, timing, alignment, format and version patterns
+fn function_modules(version: u32) -> Vec<bool> {
+    let size = symbol_size(version);
+    let mut modules = vec![false; size * size];
+
+    let mut fill = |x1: usize, y1: usize, x2: usize, y2: usize| {
+        for y in y1..y2 {
+            for x in x1..x2 {
+                modules[y * size + x] = true;
+            }
+        }
+    };
+
+    // Finder patterns with separators and format information
+    fill(0, 0, 9, 9);
+    fill(size - 8, 0, size, 9);
+    fill(0, size - 8, 9, size);
+
+    // Timing patterns
+    fill(6, 0, 7, size);
+    fill(0, 6, size, 7);
+
+    let positions = alignment_positions(version);
+    for (i, y) in positions.iter().enumerate() {
+        for (j, x) in positions.iter().enumerate() {
+            if has_alignment_pattern(&positions, i, j) {
+                fill(x - 2, y - 2, x + 3, y + 3);
+            }
+        }
+    }
+
+    if version >= 7 {
+        fill(size - 11, 0, size - 8, 6);
+        fill(0, size - 11, 6, size - 8);
+    }
+
+    modules
+}
+
+/// Positions of all data modules in the order they are filled
+pub(super) fn codeword_positions(version: u32) -> Vec<(usize, usize)> {
+    let size = symbol_size(version);
+    let function = function_modules(version);
+
+    let mut positions = Vec::with_capacity(raw_data_modules(version));
+
+    // Pairs of columns are filled from right to left alternating upwards and downwards
+    let mut right = size as isize - 1;
+    while right >= 1 {
+        if right == 6 {
+            right = 5;
+        }
+
+        let upward = (right + 1) & 2 == 0;
+        for vert in 0..size {
+            let y = if upward { size - 1 - vert } else { vert };
+            for x in [right as usize, right as usize - 1] {
+                if !function[y * size + x] {
+                    positions.push((x, y));
+                }
+            }
+        }
+
+        right -= 2;
+    }
+
+    positions
+}
+
+/// Check if the module at the given position gets inverted by a mask pattern
+pub(super) fn mask_bit(mask: u8, x: usize, y: usize) -> bool {
+    match mask {
+        0 => (x + y).is_multiple_of(2),
+        1 => y.is_multiple_of(2),
+        2 => x.is_multiple_of(3),
+        3 => (x + y).is_multiple_of(3),
+        4 => (x / 3 + y / 2).is_multiple_of(2),
+        5 => x * y % 2 + x * y % 3 == 0,
+        6 => (x * y % 2 + x * y % 3).is_multiple_of(2),
+        _ => ((x + y) % 2 + x * y % 3).is_multiple_of(2),
+    }
+}
+
+/// The 15 bit format information including error correction
+pub(super) fn format_bits(ec_level: EcLevel, mask: u8) -> u32 {
+    let data = ec_level.format_bits() << 3 | mask as u32;
+
+    let mut rem = data;
+    for _ in 0..10 {
+        rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+    }
+
+    (data << 10 | rem) ^ 0x5412
+}
+
+/// Positions of the bits of both copies of the format information starting with the lowest bit
+pub(super) fn format_positions(size: usize) -> [[(usize, usize); 15]; 2] {
+    let mut first = [(0, 0); 15];
+    let mut second = [(0, 0); 15];
+
+    for (i, p) in first.iter_mut().enumerate() {
+        *p = match i {
+            0..=5 => (8, i),
+            6 => (8, 7),
+            7 => (8, 8),
+            8 => (7, 8),
+            _ => (14 - i, 8),
+        };
+    }
+
+    for (i, p) in second.iter_mut().enumerate() {
+        *p = if i < 8 {
+            (size - 1 - i, 8)
+        } else {
+            (8, size - 15 + i)
+        };
+    }
+
+    [first, second]
+}
+
+/// The 18 bit version information including error correction
+pub(super) fn version_bits(version: u32) -> u32 {
+    let mut rem = version;
+    for _ in 0..12 {
+        rem = (rem << 1) ^ ((rem >> 11) * 0x1f25);
+    }
+
+    version << 12 | rem
+}
+
+/// Positions of the bits of both copies of the version information starting with the lowest bit
+pub(super) fn version_positions(size: usize) -> [[(usize, usize); 18]; 2] {
+    let mut first = [(0, 0); 18];
+    let mut second = [(0, 0); 18];
+
+    for i in 0..18 {
+        let a = size - 11 + i % 3;
+        let b = i / 3;
+        first[i] = (a, b);
+        second[i] = (b, a);
+    }
+
+    [first, second]
+}
+
+/// Sampled modules of a symbol with `true` for dark modules
+pub(super) struct Grid {
+    pub size: usize,
+    pub modules: Vec<bool>,
+}
+
+impl Grid {
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    fn read_bits(&self, positions: &[(usize, usize)]) -> u32 {
+        positions
+            .iter()
+            .enumerate()
+            .fold(0, |bits, (i, (x, y))| bits | (self.get(*x, *y) as u32) << i)
+    }
+}
+
+/// Find the format information with the fewest differences to one of the copies
+fn read_format(grid: &Grid) -> Option<(EcLevel, u8)> {
+    let copies = format_positions(grid.size).map(|positions| grid.read_bits(&positions));
+
+    (0..32u32)
+        .map(|data| {
+            let ec_level = EcLevel::from_format_bits(data >> 3);
+            let mask = (data & 7) as u8;
+            let bits = format_bits(ec_level, mask);
+            let distance = copies
+                .iter()
+                .map(|c| (c ^ bits).count_ones())
+                .min()
+                .unwrap_or(u32::MAX);
+            (distance, ec_level, mask)
+        })
+        .min_by_key(|(distance, _, _)| *distance)
+        .filter(|(distance, _, _)| *distance <= 3)
+        .map(|(_, ec_level, mask)| (ec_level, mask))
+}
+
+/// Version stored in the version information of symbols with version 7 or higher
+fn read_version(grid: &Grid) -> Option<u32> {
+    let copies = version_positions(grid.size).map(|positions| grid.read_bits(&positions));
+
+    (7..=40)
+        .map(|version| {
+            let bits = version_bits(version);
+            let distance = copies.iter().map(|c| (c ^ bits).count_ones()).min();
+            (distance.unwrap_or(u32::MAX), version)
+        })
+        .min_by_key(|(distance, _)| *distance)
+        .filter(|(distance, _)| *distance <= 3)
+        .map(|(_, version)| version)
+}
+
+/// Decode the payload of a symbol
+///
+/// Returns `None` if the symbol is unreadable or contains too many errors.
+pub(super) fn decode_grid(grid: &Grid, version: u32) -> Option<Vec<u8>> {
+    if version >= 7 && read_version(grid) != Some(version) {
+        return None;
+    }
+
+    let (ec_level, mask) = read_format(grid)?;
+
+    let codeword_count = raw_data_modules(version) / 8;
+    let positions = codeword_positions(version);
+
+    let codewords = positions[..codeword_count * 8]
+        .chunks_exact(8)
+        .map(|chunk| {
+            chunk.iter().fold(0u8, |byte, (x, y)| {
+                byte << 1 | (grid.get(*x, *y) ^ mask_bit(mask, *x, *y)) as u8
+            })
+        });
+
+    let layout = BlockLayout::new(version, ec_level);
+    let mut blocks = vec![vec![]; layout.data_lens.len()];
+    for (codeword, block) in codewords.zip(layout.order()) {
+        blocks[block].push(codeword);
+    }
+
+    let mut data = Vec::with_capacity(layout.data_capacity());
+    for (block, len) in blocks.iter_mut().zip(&layout.data_lens) {
+        if !reed_solomon::correct(block, layout.ec_len) {
+            return None;
+        }
+        data.extend_from_slice(&block[..*len]);
+    }
+
+    parse_segments(&data, version)
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn remaining(&self) -> usize {
+        self.data.len() * 8 - self.pos
+    }
+
+    fn read(&mut self, bits: usize) -> Option<u32> {
+        if bits > self.remaining() {
+            return None;
+        }
+
+        let mut value = 0;
+        for _ in 0..bits {
+            let bit = (self.data[self.pos / 8] >> (7 - self.pos % 8)) & 1;
+            value = value << 1 | bit as u32;
+            self.pos += 1;
+        }
+
+        Some(value)
+    }
+}
+
+/// Concatenate the content of all segments
+///
+/// Kanji segments are returned as Shift JIS bytes and ECI designators are skipped.
+fn parse_segments(data: &[u8], version: u32) -> Option<Vec<u8>> {
+    let group = match version {
+        1..=9 => 0,
+        10..=26 => 1,
+        _ => 2,
+    };
+
+    let mut reader = BitReader { data, pos: 0 };
+    let mut result = vec![];
+
+    while reader.remaining() >= 4 {
+        match reader.read(4)? {
+            0 => break,
+            // Numeric
+            1 => {
+                let mut count = reader.read([10, 12, 14][group])?;
+                while count > 0 {
+                    let digits = count.min(3);
+                    let value = reader.read([4, 7, 10][digits as usize - 1])?;
+                    if value >= 10u32.pow(digits) {
+                        return None;
+                    }
+                    result.extend(format!("{:01$}", value, digits as usize).bytes());
+                    count -= digits;
+                }
+            }
+            // Alphanumeric
+            2 => {
+                let mut count = reader.read([9, 11, 13][group])?;
+                while count > 0 {
+                    if count >= 2 {
+                        let value = reader.read(11)? as usize;
+                        if value >= 45 * 45 {
+                            return None;
+                        }
+                        result.push(ALPHANUMERIC_CHARS[value / 45]);
+                        result.push(ALPHANUMERIC_CHARS[value % 45]);
+                        count -= 2;
+                    } else {
+                        let value = reader.read(6)? as usize;
+                        result.push(*ALPHANUMERIC_CHARS.get(value)?);
+                        count -= 1;
+                    }
+                }
+            }
+            // Structured append
+            3 => {
+                reader.read(16)?;
+            }
+            // Byte
+            4 => {
+                let count = reader.read([8, 16, 16][group])?;
+                for _ in 0..count {
+                    result.push(reader.read(8)? as u8);
+                }
+            }
+            // FNC1 in first position
+            5 => {}
+            // ECI
+            7 => {
+                let first = reader.read(8)?;
+                if first & 0x80 == 0x80 {
+                    if first & 0xc0 == 0x80 {
+                        reader.read(8)?;
+                    } else if first & 0xe0 == 0xc0 {
+                        reader.read(16)?;
+                    } else {
+                        return None;
+                    }
+                }
+            }
+            // Kanji
+            8 => {
+                let count = reader.read([8, 10, 12][group])?;
+                for _ in 0..count {
+                    let value = reader.read(13)?;
+                    let value = ((value / 0xc0) << 8) | (value % 0xc0);
+                    let value = if value < 0x1f00 {
+                        value + 0x8140
+                    } else {
+                        value + 0xc140
+                    };
+                    result.push((value >> 8) as u8);
+                    result.push(value as u8);
+                }
+            }
+            // FNC1 in second position
+            9 => {
+                reader.read(8)?;
+            }
+            _ => return None,
+        }
+    }
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_bits() {
+        assert_eq!(format_bits(EcLevel::L, 0), 0b111011111000100);
+        assert_eq!(format_bits(EcLevel::M, 0), 0b101010000010010);
+        assert_eq!(format_bits(EcLevel::H, 1), 0b001001110111110);
+        assert_eq!(format_bits(EcLevel::H, 7), 0b000100000111011);
+        assert_eq!(version_bits(7), 0x07c94);
+        assert_eq!(version_bits(40), 0x28c69);
+    }
+
+    #[test]
+    fn test_alignment_positions() {
+        assert_eq!(alignment_positions(1), Vec::<usize>::new());
+        assert_eq!(alignment_positions(2), vec![6, 18]);
+        assert_eq!(alignment_positions(7), vec![6, 22, 38]);
+        assert_eq!(alignment_positions(32), vec![6, 34, 60, 86, 112, 138]);
+        assert_eq!(alignment_positions(40), vec![6, 30, 58, 86, 114, 142, 170]);
+    }
+
+    #[test]
+    fn test_data_capacity() {
+        let capacity = |version, ec_level| BlockLayout::new(version, ec_level).data_capacity();
+
+        assert_eq!(capacity(1, EcLevel::L), 19);
+        assert_eq!(capacity(1, EcLevel::M), 16);
+        assert_eq!(capacity(1, EcLevel::Q), 13);
+        assert_eq!(capacity(1, EcLevel::H), 9);
+        assert_eq!(capacity(10, EcLevel::M), 216);
+        assert_eq!(capacity(40, EcLevel::L), 2956);
+        assert_eq!(capacity(40, EcLevel::H), 1276);
+
+        for version in 1..=40 {
+            assert_eq!(codeword_positions(version).len(), raw_data_modules(version),);
+        }
+    }
+
+    #[test]
+    fn test_parse_segments() {
+        // "01234567" as numeric segment of a version 1 symbol
+        let data = [0x10, 0x20, 0x0c, 0x56, 0x61, 0x80, 0xec, 0x11];
+        assert_eq!(parse_segments(&data, 1).unwrap(), b"01234567");
+
+        // "AC-42" as alphanumeric segment followed by the terminator
+        let data = [0x20, 0x29, 0xce, 0xe7, 0x21, 0x00];
+        assert_eq!(parse_segments(&data, 1).unwrap(), b"AC-42");
+
+        // Byte segment
+        let data = [0x40, 0x26, 0x86, 0x90, 0xec];
+        assert_eq!(parse_segments(&data, 1).unwrap(), b"hi");
+
+        // Unknown mode
+        assert_eq!(parse_segments(&[0xf0], 1), None);
+    }
+}
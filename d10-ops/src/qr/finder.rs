@@ -0,0 +1,247 @@
+//! Locating finder and alignment patterns in a binarized image
+
+/// Binarized image with `true` for dark pixels
+pub(super) struct Bitmap {
+    pub width: u32,
+    pub height: u32,
+    pub dark: Vec<bool>,
+}
+
+impl Bitmap {
+    pub(super) fn get(&self, x: i32, y: i32) -> Option<bool> {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            None
+        } else {
+            Some(self.dark[y as usize * self.width as usize + x as usize])
+        }
+    }
+}
+
+/// Center of one of the three square finder patterns in the corners of a symbol
+#[derive(Debug, Copy, Clone)]
+pub(super) struct FinderPattern {
+    pub x: f32,
+    pub y: f32,
+    pub module_size: f32,
+    /// Number of scan lines the pattern was found on
+    pub count: u32,
+}
+
+/// Check if the run lengths match the 1:1:3:1:1 ratio of a finder pattern
+fn is_finder_ratio(counts: &[u32; 5]) -> bool {
+    if counts.contains(&0) {
+        return false;
+    }
+
+    let total: u32 = counts.iter().sum();
+    if total < 7 {
+        return false;
+    }
+
+    let module = total as f32 / 7.0;
+    let max_variance = module / 2.0;
+
+    counts.iter().enumerate().all(|(i, c)| {
+        let modules = if i == 2 { 3.0 } else { 1.0 };
+        (module * modules - *c as f32).abs() < max_variance * modules
+    })
+}
+
+/// Measure the runs of a finder pattern along a line through the dark position `start`
+///
+/// Returns the center of the pattern on the line and its total size.
+fn cross_check(is_dark: impl Fn(i32) -> Option<bool>, start: i32) -> Option<(f32, f32)> {
+    if is_dark(start) != Some(true) {
+        return None;
+    }
+
+    let mut counts = [0u32; 5];
+
+    let mut i = start;
+    for (state, dark) in [(2, true), (1, false), (0, true)] {
+        while is_dark(i) == Some(dark) {
+            counts[state] += 1;
+            i -= 1;
+        }
+    }
+
+    let mut i = start + 1;
+    for (state, dark) in [(2, true), (3, false), (4, true)] {
+        while is_dark(i) == Some(dark) {
+            counts[state] += 1;
+            i += 1;
+        }
+    }
+
+    if !is_finder_ratio(&counts) {
+        return None;
+    }
+
+    let center = i as f32 - counts[4] as f32 - counts[3] as f32 - counts[2] as f32 / 2.0;
+    let total = counts.iter().sum::<u32>() as f32;
+
+    Some((center, total))
+}
+
+/// Confirm a pattern found on a row by checking the column and the row through its center
+fn check_candidate(bitmap: &Bitmap, counts: &[u32; 5], end: i32, y: i32) -> Option<FinderPattern> {
+    let x = end as f32 - counts[4] as f32 - counts[3] as f32 - counts[2] as f32 / 2.0;
+
+    let (y, vertical) = cross_check(|i| bitmap.get(x as i32, i), y)?;
+    let (x, horizontal) = cross_check(|i| bitmap.get(i, y as i32), x as i32)?;
+
+    // Distorted but not completely different sizes in both directions
+    if vertical > horizontal * 2.0 || horizontal > vertical * 2.0 {
+        return None;
+    }
+
+    Some(FinderPattern {
+        x,
+        y,
+        module_size: (horizontal + vertical) / 14.0,
+        count: 1,
+    })
+}
+
+fn add_pattern(patterns: &mut Vec<FinderPattern>, pattern: FinderPattern) {
+    let existing = patterns.iter_mut().find(|p| {
+        let max_size = p.module_size.max(pattern.module_size);
+        (p.x - pattern.x).abs() <= max_size
+            && (p.y - pattern.y).abs() <= max_size
+            && (p.module_size - pattern.module_size).abs() <= max_size.max(1.0)
+    });
+
+    match existing {
+        Some(p) => {
+            let n = p.count as f32;
+            p.x = (p.x * n + pattern.x) / (n + 1.0);
+            p.y = (p.y * n + pattern.y) / (n + 1.0);
+            p.module_size = (p.module_size * n + pattern.module_size) / (n + 1.0);
+            p.count += 1;
+        }
+        None => patterns.push(pattern),
+    }
+}
+
+/// Find all finder patterns by scanning every row for dark and light runs with a 1:1:3:1:1 ratio
+pub(super) fn find_finder_patterns(bitmap: &Bitmap) -> Vec<FinderPattern> {
+    let mut patterns = vec![];
+
+    for y in 0..bitmap.height as i32 {
+        let mut counts = [0u32; 5];
+        let mut state = 0;
+
+        for x in 0..=bitmap.width as i32 {
+            match bitmap.get(x, y) {
+                Some(true) => {
+                    if state % 2 == 1 {
+                        state += 1;
+                    }
+                    counts[state] += 1;
+                }
+                Some(false) if state % 2 == 1 => counts[state] += 1,
+                Some(false) if state == 0 && counts[0] == 0 => {}
+                Some(false) if state < 4 => {
+                    state += 1;
+                    counts[state] += 1;
+                }
+                light_or_end => {
+                    if state == 4 && is_finder_ratio(&counts) {
+                        if let Some(pattern) = check_candidate(bitmap, &counts, x, y) {
+                            add_pattern(&mut patterns, pattern);
+                        }
+                    }
+
+                    if light_or_end.is_none() {
+                        break;
+                    }
+
+                    // Continue with the last dark and light run as the start of the next pattern
+                    counts = [counts[2], counts[3], counts[4], 1, 0];
+                    state = 3;
+                }
+            }
+        }
+    }
+
+    patterns
+}
+
+/// Measure a dark run through `start` surrounded by one module wide light and dark runs
+///
+/// Returns the center of the dark run.
+fn alignment_check(
+    is_dark: impl Fn(i32) -> Option<bool>,
+    start: i32,
+    module_size: f32,
+) -> Option<f32> {
+    if is_dark(start) != Some(true) {
+        return None;
+    }
+
+    let max_count = (module_size * 2.0).ceil() as u32;
+    let mut counts = [0u32; 5];
+
+    let mut i = start;
+    for (state, dark) in [(2, true), (1, false), (0, true)] {
+        while is_dark(i) == Some(dark) && counts[state] <= max_count {
+            counts[state] += 1;
+            i -= 1;
+        }
+    }
+
+    let mut i = start + 1;
+    let mut end = i;
+    for (state, dark) in [(2, true), (3, false), (4, true)] {
+        while is_dark(i) == Some(dark) && counts[state] <= max_count {
+            counts[state] += 1;
+            i += 1;
+        }
+        if state == 2 {
+            end = i;
+        }
+    }
+
+    let max_variance = module_size / 2.0;
+    let matches = counts[1..4]
+        .iter()
+        .all(|c| (module_size - *c as f32).abs() < max_variance)
+        && counts[0] as f32 >= max_variance
+        && counts[4] as f32 >= max_variance;
+
+    matches.then(|| end as f32 - counts[2] as f32 / 2.0)
+}
+
+/// Find the alignment pattern closest to the estimated position
+pub(super) fn find_alignment_pattern(
+    bitmap: &Bitmap,
+    x: f32,
+    y: f32,
+    module_size: f32,
+) -> Option<(f32, f32)> {
+    let radius = (module_size * 4.0).ceil() as i32;
+
+    let mut best: Option<(f32, (f32, f32))> = None;
+
+    for py in y as i32 - radius..=y as i32 + radius {
+        for px in x as i32 - radius..=x as i32 + radius {
+            let Some(cx) = alignment_check(|i| bitmap.get(i, py), px, module_size) else {
+                continue;
+            };
+            let Some(cy) = alignment_check(|i| bitmap.get(cx as i32, i), py, module_size) else {
+                continue;
+            };
+            let Some(cx) = alignment_check(|i| bitmap.get(i, cy as i32), cx as i32, module_size)
+            else {
+                continue;
+            };
+
+            let distance = (cx - x).powi(2) + (cy - y).powi(2);
+            if best.is_none_or(|(d, _)| distance < d) {
+                best = Some((distance, (cx, cy)));
+            }
+        }
+    }
+
+    best.map(|(_, p)| p)
+}
@@ -0,0 +1,550 @@
+mod decode;
+mod finder;
+mod reed_solomon;
+
+use d10_core::color::Rgb;
+use d10_core::pixelbuffer::PixelBuffer;
+
+use crate::perspective::Homography;
+use crate::threshold::{adaptive_threshold_mask, otsu_threshold, srgb_luma};
+use decode::Grid;
+use finder::{find_alignment_pattern, find_finder_patterns, Bitmap, FinderPattern};
+
+/// Only the most often found finder patterns are combined into symbols
+const MAX_FINDER_PATTERNS: usize = 16;
+
+/// A decoded QR code
+#[derive(Debug, Clone, PartialEq)]
+pub struct QrCode {
+    /// The raw content of the code
+    pub payload: Vec<u8>,
+    /// Version between 1 and 40 defining the number of modules
+    pub version: u32,
+    /// Outer corners of the code in the order top left, top right, bottom right and bottom left
+    pub corners: [(f32, f32); 4],
+}
+
+impl QrCode {
+    /// The payload if it's valid UTF-8
+    pub fn text(&self) -> Option<&str> {
+        std::str::from_utf8(&self.payload).ok()
+    }
+}
+
+/// Locate and decode all QR codes in an image
+///
+/// The image gets binarized with a global threshold and with an adaptive threshold as fallback
+/// for uneven lighting. Codes may be rotated and perspective distorted but not mirrored.
+/// The corners are in the same coordinates as used by `perspective_warp()`.
+pub fn detect_qr_codes(buffer: &PixelBuffer<Rgb>) -> Vec<QrCode> {
+    if buffer.is_empty() {
+        return vec![];
+    }
+
+    let value = otsu_threshold(buffer);
+    let bitmap = Bitmap {
+        width: buffer.width(),
+        height: buffer.height(),
+        dark: buffer
+            .data()
+            .iter()
+            .map(|c| srgb_luma(c) <= value)
+            .collect(),
+    };
+
+    let codes = detect_in_bitmap(&bitmap);
+    if !codes.is_empty() {
+        return codes;
+    }
+
+    let radius = (buffer.width().min(buffer.height()) / 8).max(8);
+    let bitmap = Bitmap {
+        width: buffer.width(),
+        height: buffer.height(),
+        dark: adaptive_threshold_mask(buffer, radius, 0.02)
+            .into_iter()
+            .map(|white| !white)
+            .collect(),
+    };
+
+    detect_in_bitmap(&bitmap)
+}
+
+fn detect_in_bitmap(bitmap: &Bitmap) -> Vec<QrCode> {
+    let mut patterns = find_finder_patterns(bitmap);
+    patterns.sort_by_key(|p| std::cmp::Reverse(p.count));
+    patterns.truncate(MAX_FINDER_PATTERNS);
+
+    let mut triples = vec![];
+    for i in 0..patterns.len() {
+        for j in i + 1..patterns.len() {
+            for k in j + 1..patterns.len() {
+                if let Some(triple) = orient(&patterns, [i, j, k]) {
+                    triples.push(triple);
+                }
+            }
+        }
+    }
+    triples.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut used = vec![false; patterns.len()];
+    let mut codes = vec![];
+
+    for (_, triple) in triples {
+        if triple.iter().any(|i| used[*i]) {
+            continue;
+        }
+
+        let [top_left, top_right, bottom_left] = triple.map(|i| &patterns[i]);
+        if let Some(code) = decode_symbol(bitmap, top_left, top_right, bottom_left) {
+            for i in triple {
+                used[i] = true;
+            }
+            codes.push(code);
+        }
+    }
+
+    codes
+}
+
+/// Order three finder patterns as top left, top right and bottom left
+///
+/// Returns `None` if they can't belong to the same symbol or a score with lower values for
+/// combinations closer to a square.
+fn orient(patterns: &[FinderPattern], indices: [usize; 3]) -> Option<(f32, [usize; 3])> {
+    let [a, b, c] = indices.map(|i| &patterns[i]);
+
+    let sizes = [a.module_size, b.module_size, c.module_size];
+    let min_size = sizes.iter().copied().fold(f32::MAX, f32::min);
+    let max_size = sizes.iter().copied().fold(0.0, f32::max);
+    if max_size > min_size * 2.0 {
+        return None;
+    }
+
+    let distance = |p: &FinderPattern, q: &FinderPattern| (p.x - q.x).hypot(p.y - q.y);
+
+    // The top left pattern is opposite to the longest side
+    let ab = distance(a, b);
+    let bc = distance(b, c);
+    let ac = distance(a, c);
+    let [top_left, mut top_right, mut bottom_left] = if bc >= ab && bc >= ac {
+        indices
+    } else if ac >= ab {
+        [indices[1], indices[0], indices[2]]
+    } else {
+        [indices[2], indices[0], indices[1]]
+    };
+
+    let p = &patterns[top_left];
+    let (ux, uy) = (patterns[top_right].x - p.x, patterns[top_right].y - p.y);
+    let (vx, vy) = (patterns[bottom_left].x - p.x, patterns[bottom_left].y - p.y);
+
+    let u = ux.hypot(uy);
+    let v = vx.hypot(vy);
+    if u.min(v) < max_size * 10.0 || u > v * 2.0 || v > u * 2.0 {
+        return None;
+    }
+
+    let cos = (ux * vx + uy * vy) / (u * v);
+    if cos.abs() > 0.5 {
+        return None;
+    }
+
+    // Top right is clockwise from bottom left
+    if ux * vy - uy * vx < 0.0 {
+        std::mem::swap(&mut top_right, &mut bottom_left);
+    }
+
+    let score = cos.abs() + (u / v - 1.0).abs();
+
+    Some((score, [top_left, top_right, bottom_left]))
+}
+
+/// Decode the symbol defined by its finder patterns trying versions close to the estimated one
+fn decode_symbol(
+    bitmap: &Bitmap,
+    top_left: &FinderPattern,
+    top_right: &FinderPattern,
+    bottom_left: &FinderPattern,
+) -> Option<QrCode> {
+    let module_size =
+        (top_left.module_size + top_right.module_size + bottom_left.module_size) / 3.0;
+
+    let distance = |p: &FinderPattern| (p.x - top_left.x).hypot(p.y - top_left.y);
+    let dimension = (distance(top_right) + distance(bottom_left)) / 2.0 / module_size + 7.0;
+    let estimated = ((dimension - 17.0) / 4.0).round() as i32;
+
+    [0, -1, 1, -2, 2]
+        .iter()
+        .map(|offset| estimated + offset)
+        .filter(|version| (1..=40).contains(version))
+        .find_map(|version| {
+            decode_version(
+                bitmap,
+                [top_left, top_right, bottom_left],
+                module_size,
+                version as u32,
+            )
+        })
+}
+
+fn decode_version(
+    bitmap: &Bitmap,
+    finders: [&FinderPattern; 3],
+    module_size: f32,
+    version: u32,
+) -> Option<QrCode> {
+    let [top_left, top_right, bottom_left] = finders;
+    let dim = decode::symbol_size(version) as f32;
+
+    let src = [(3.5, 3.5), (dim - 3.5, 3.5), (3.5, dim - 3.5)];
+    let dst = finders.map(|p| (p.x, p.y));
+
+    // The fourth point is either the bottom right alignment pattern or the estimated position of
+    // a fourth finder pattern if the symbol was a parallelogram
+    let mut fourth = vec![];
+
+    if version >= 2 {
+        let k = (dim - 10.0) / (dim - 7.0);
+        let x = top_left.x + (top_right.x - top_left.x + bottom_left.x - top_left.x) * k;
+        let y = top_left.y + (top_right.y - top_left.y + bottom_left.y - top_left.y) * k;
+
+        if let Some(p) = find_alignment_pattern(bitmap, x, y, module_size) {
+            fourth.push(((dim - 6.5, dim - 6.5), p));
+        }
+    }
+
+    fourth.push((
+        (dim - 3.5, dim - 3.5),
+        (
+            top_right.x + bottom_left.x - top_left.x,
+            top_right.y + bottom_left.y - top_left.y,
+        ),
+    ));
+
+    fourth.into_iter().find_map(|(s, d)| {
+        let homography =
+            Homography::from_points([src[0], src[1], src[2], s], [dst[0], dst[1], dst[2], d])?;
+
+        let size = dim as usize;
+        let modules = (0..size * size)
+            .map(|i| {
+                let (x, y) = homography.map((i % size) as f32 + 0.5, (i / size) as f32 + 0.5);
+                bitmap.get(x.floor() as i32, y.floor() as i32) == Some(true)
+            })
+            .collect();
+
+        let payload = decode::decode_grid(&Grid { size, modules }, version)?;
+
+        let corners =
+            [(0.0, 0.0), (dim, 0.0), (dim, dim), (0.0, dim)].map(|(x, y)| homography.map(x, y));
+
+        Some(QrCode {
+            payload,
+            version,
+            corners,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode::*;
+    use super::*;
+    use crate::{perspective_warp, FilterMode};
+
+    /// Create the modules of a symbol containing `payload` as a single byte segment
+    fn encode(payload: &[u8], version: u32, ec_level: EcLevel, mask: u8) -> Grid {
+        let size = symbol_size(version);
+        let layout = BlockLayout::new(version, ec_level);
+        let capacity = layout.data_capacity();
+
+        let mut bits = vec![];
+        let mut push = |value: u32, len: usize| {
+            for i in (0..len).rev() {
+                bits.push((value >> i) & 1 == 1);
+            }
+        };
+
+        push(0b0100, 4);
+        push(payload.len() as u32, if version <= 9 { 8 } else { 16 });
+        for b in payload {
+            push(*b as u32, 8);
+        }
+        push(0, 4);
+
+        let mut data: Vec<u8> = bits
+            .chunks(8)
+            .map(|chunk| {
+                (0..8).fold(0, |byte, i| {
+                    byte << 1 | *chunk.get(i).unwrap_or(&false) as u8
+                })
+            })
+            .collect();
+        assert!(data.len() <= capacity);
+
+        for pad in [0xec, 0x11].iter().cycle().take(capacity - data.len()) {
+            data.push(*pad);
+        }
+
+        let mut blocks = vec![];
+        let mut offset = 0;
+        for len in &layout.data_lens {
+            let mut block = data[offset..offset + len].to_vec();
+            block.extend(super::reed_solomon::encode(&block, layout.ec_len));
+            blocks.push(block.into_iter());
+            offset += len;
+        }
+
+        let codewords: Vec<u8> = layout
+            .order()
+            .into_iter()
+            .map(|block| blocks[block].next().unwrap())
+            .collect();
+
+        let mut modules = vec![false; size * size];
+        let mut set = |x: usize, y: usize, dark: bool| modules[y * size + x] = dark;
+
+        for (fx, fy) in [(0, 0), (size - 7, 0), (0, size - 7)] {
+            for dy in 0..7 {
+                for dx in 0..7 {
+                    let ring = (dx as i32 - 3).abs().max((dy as i32 - 3).abs());
+                    set(fx + dx, fy + dy, ring != 2);
+                }
+            }
+        }
+
+        for i in 8..size - 8 {
+            set(6, i, i % 2 == 0);
+            set(i, 6, i % 2 == 0);
+        }
+
+        let positions = alignment_positions(version);
+        for (i, y) in positions.iter().enumerate() {
+            for (j, x) in positions.iter().enumerate() {
+                if has_alignment_pattern(&positions, i, j) {
+                    for dy in 0..5 {
+                        for dx in 0..5 {
+                            let ring = (dx as i32 - 2).abs().max((dy as i32 - 2).abs());
+                            set(x - 2 + dx, y - 2 + dy, ring != 1);
+                        }
+                    }
+                }
+            }
+        }
+
+        let format = format_bits(ec_level, mask);
+        for copy in format_positions(size) {
+            for (i, (x, y)) in copy.iter().enumerate() {
+                set(*x, *y, (format >> i) & 1 == 1);
+            }
+        }
+        set(8, size - 8, true);
+
+        if version >= 7 {
+            let bits = version_bits(version);
+            for copy in version_positions(size) {
+                for (i, (x, y)) in copy.iter().enumerate() {
+                    set(*x, *y, (bits >> i) & 1 == 1);
+                }
+            }
+        }
+
+        for (i, (x, y)) in codeword_positions(version).into_iter().enumerate() {
+            let bit = codewords
+                .get(i / 8)
+                .is_some_and(|c| (c >> (7 - i % 8)) & 1 == 1);
+            set(x, y, bit ^ mask_bit(mask, x, y));
+        }
+
+        Grid { size, modules }
+    }
+
+    /// Render a symbol with a quiet zone of 4 modules
+    fn render(grid: &Grid, module_size: u32) -> PixelBuffer<Rgb> {
+        let size = (grid.size as u32 + 8) * module_size;
+
+        PixelBuffer::new_from_func(size, size, |x, y| {
+            let mx = (x / module_size) as i32 - 4;
+            let my = (y / module_size) as i32 - 4;
+            let inside = (0..grid.size as i32).contains(&mx) && (0..grid.size as i32).contains(&my);
+
+            if inside && grid.modules[my as usize * grid.size + mx as usize] {
+                Rgb::new(0.05, 0.05, 0.05)
+            } else {
+                Rgb::new(0.9, 0.9, 0.9)
+            }
+        })
+    }
+
+    fn assert_corners(code: &QrCode, expected: [(f32, f32); 4], tolerance: f32) {
+        for (c, e) in code.corners.iter().zip(expected.iter()) {
+            assert!(
+                (c.0 - e.0).abs() <= tolerance && (c.1 - e.1).abs() <= tolerance,
+                "corners {:?} expected {:?}",
+                code.corners,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_grid() {
+        for mask in 0..8 {
+            for ec_level in [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H] {
+                let grid = encode(b"d10", 1, ec_level, mask);
+                assert_eq!(decode_grid(&grid, 1).unwrap(), b"d10");
+            }
+        }
+
+        let payload = vec![b'x'; 150];
+        let grid = encode(&payload, 10, EcLevel::Q, 5);
+        assert_eq!(decode_grid(&grid, 10).unwrap(), payload);
+        assert_eq!(decode_grid(&grid, 11), None);
+    }
+
+    #[test]
+    fn test_detect() {
+        let grid = encode(b"https://example.com", 2, EcLevel::M, 3);
+        let buffer = render(&grid, 4);
+
+        let codes = detect_qr_codes(&buffer);
+
+        assert_eq!(codes.len(), 1);
+        assert_eq!(codes[0].text(), Some("https://example.com"));
+        assert_eq!(codes[0].version, 2);
+        assert_corners(
+            &codes[0],
+            [(16.0, 16.0), (116.0, 16.0), (116.0, 116.0), (16.0, 116.0)],
+            1.0,
+        );
+    }
+
+    #[test]
+    fn test_detect_versions() {
+        for (version, ec_level, len) in [
+            (1, EcLevel::H, 5),
+            (4, EcLevel::L, 70),
+            (7, EcLevel::Q, 60),
+            (12, EcLevel::M, 200),
+        ] {
+            let payload: Vec<u8> = (0..len).map(|i| (i * 7 % 256) as u8).collect();
+            let grid = encode(&payload, version, ec_level, (version % 8) as u8);
+
+            let codes = detect_qr_codes(&render(&grid, 3));
+
+            assert_eq!(codes.len(), 1, "version {}", version);
+            assert_eq!(codes[0].payload, payload);
+            assert_eq!(codes[0].version, version);
+        }
+    }
+
+    #[test]
+    fn test_detect_damaged() {
+        let mut grid = encode(b"robust", 3, EcLevel::H, 1);
+        for i in 0..6 {
+            let p = (12 + i * 3) * grid.size + 20 + i;
+            grid.modules[p] = !grid.modules[p];
+        }
+
+        let codes = detect_qr_codes(&render(&grid, 4));
+
+        assert_eq!(codes.len(), 1);
+        assert_eq!(codes[0].payload, b"robust");
+    }
+
+    #[test]
+    fn test_detect_rotated() {
+        let grid = encode(b"rotated", 2, EcLevel::M, 0);
+        let buffer = render(&grid, 5);
+        let size = buffer.width() as f32;
+
+        // Rotated by 90 degrees clockwise
+        let rotated = crate::rotate90(&buffer);
+        let codes = detect_qr_codes(&rotated);
+        assert_eq!(codes.len(), 1);
+        assert_eq!(codes[0].payload, b"rotated");
+        assert_corners(
+            &codes[0],
+            [
+                (size - 20.0, 20.0),
+                (size - 20.0, size - 20.0),
+                (20.0, size - 20.0),
+                (20.0, 20.0),
+            ],
+            1.0,
+        );
+
+        // Perspective distortion
+        let warped = perspective_warp(
+            &buffer,
+            [
+                (-30.0, 10.0),
+                (size + 5.0, -20.0),
+                (size + 20.0, size + 10.0),
+                (0.0, size - 5.0),
+            ],
+            220,
+            200,
+            FilterMode::Bilinear,
+        );
+        let codes = detect_qr_codes(&warped);
+        assert_eq!(codes.len(), 1);
+        assert_eq!(codes[0].payload, b"rotated");
+    }
+
+    #[test]
+    fn test_detect_multiple() {
+        let first = render(&encode(b"first", 1, EcLevel::M, 2), 4);
+        let second = render(&encode(b"second", 3, EcLevel::L, 6), 3);
+
+        let buffer = PixelBuffer::new_from_func(first.width() + second.width(), 150, |x, y| {
+            if x < first.width() && y < first.height() {
+                *first.get_pixel(x, y)
+            } else if x >= first.width() && y < second.height() {
+                *second.get_pixel(x - first.width(), y)
+            } else {
+                Rgb::new(0.9, 0.9, 0.9)
+            }
+        });
+
+        let mut payloads: Vec<Vec<u8>> = detect_qr_codes(&buffer)
+            .into_iter()
+            .map(|c| c.payload)
+            .collect();
+        payloads.sort();
+
+        assert_eq!(payloads, vec![b"first".to_vec(), b"second".to_vec()]);
+    }
+
+    #[test]
+    fn test_uneven_lighting() {
+        let grid = encode(b"shadow", 2, EcLevel::M, 4);
+        let buffer = render(&grid, 4);
+        let width = buffer.width() as f32;
+
+        let shaded = PixelBuffer::new_from_func(buffer.width(), buffer.height(), |x, y| {
+            let c = buffer.get_pixel(x, y);
+            let light = 0.15 + 0.85 * x as f32 / width;
+            Rgb::new(c.red() * light, c.green() * light, c.blue() * light)
+        });
+
+        let codes = detect_qr_codes(&shaded);
+        assert_eq!(codes.len(), 1);
+        assert_eq!(codes[0].payload, b"shadow");
+    }
+
+    #[test]
+    fn test_no_codes() {
+        let buffer = PixelBuffer::new_from_func(100, 80, |x, y| {
+            if (x / 10 + y / 10) % 2 == 0 {
+                Rgb::BLACK
+            } else {
+                Rgb::WHITE
+            }
+        });
+
+        assert!(detect_qr_codes(&buffer).is_empty());
+        assert!(detect_qr_codes(&PixelBuffer::new(0, 0)).is_empty());
+    }
+}
@@ -0,0 +1,244 @@
+//! Reed-Solomon error correction over GF(256) as used by QR codes
+
+/// Arithmetic in GF(256) with the primitive polynomial x^8 + x^4 + x^3 + x^2 + 1
+struct Galois {
+    exp: [u8; 512],
+    log: [u8; 256],
+}
+
+impl Galois {
+    fn new() -> Galois {
+        let mut exp = [0; 512];
+        let mut log = [0; 256];
+
+        let mut x: u16 = 1;
+        for (i, e) in exp.iter_mut().take(255).enumerate() {
+            *e = x as u8;
+            log[x as usize] = i as u8;
+
+            x <<= 1;
+            if x & 0x100 != 0 {
+                x ^= 0x11d;
+            }
+        }
+
+        // Repeat the table to avoid the modulo when adding logarithms
+        exp.copy_within(0..255, 255);
+
+        Galois { exp, log }
+    }
+
+    fn mul(&self, a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 {
+            0
+        } else {
+            self.exp[self.log[a as usize] as usize + self.log[b as usize] as usize]
+        }
+    }
+
+    fn div(&self, a: u8, b: u8) -> u8 {
+        if a == 0 {
+            0
+        } else {
+            self.exp[self.log[a as usize] as usize + 255 - self.log[b as usize] as usize]
+        }
+    }
+
+    /// `2` raised to the power of `n`
+    fn pow2(&self, n: usize) -> u8 {
+        self.exp[n % 255]
+    }
+
+    fn inverse(&self, a: u8) -> u8 {
+        self.div(1, a)
+    }
+
+    /// Evaluate a polynomial with the lowest degree coefficient first
+    fn eval(&self, poly: &[u8], x: u8) -> u8 {
+        poly.iter()
+            .rev()
+            .fold(0, |acc, coeff| self.mul(acc, x) ^ coeff)
+    }
+}
+
+/// Compute the error correction codewords for `data`
+#[cfg(test)]
+pub(crate) fn encode(data: &[u8], ec_len: usize) -> Vec<u8> {
+    let gf = Galois::new();
+
+    // Generator polynomial with the highest degree coefficient first and the leading 1 omitted
+    let mut generator = vec![0u8; ec_len];
+    generator[ec_len - 1] = 1;
+    let mut root = 1;
+    for _ in 0..ec_len {
+        for j in 0..ec_len {
+            generator[j] = gf.mul(generator[j], root);
+            if j + 1 < ec_len {
+                generator[j] ^= generator[j + 1];
+            }
+        }
+        root = gf.mul(root, 2);
+    }
+
+    let mut remainder = vec![0u8; ec_len];
+    for b in data {
+        let factor = b ^ remainder.remove(0);
+        remainder.push(0);
+        for (r, g) in remainder.iter_mut().zip(&generator) {
+            *r ^= gf.mul(*g, factor);
+        }
+    }
+
+    remainder
+}
+
+/// Correct errors of a block of data followed by `ec_len` error correction codewords in place
+///
+/// Returns false if the block contains more errors than can be corrected.
+pub(crate) fn correct(block: &mut [u8], ec_len: usize) -> bool {
+    let gf = Galois::new();
+    let n = block.len();
+
+    // The codewords are the coefficients of a polynomial with the highest degree first
+    let syndromes: Vec<u8> = (0..ec_len)
+        .map(|i| {
+            let x = gf.pow2(i);
+            block.iter().fold(0, |acc, c| gf.mul(acc, x) ^ c)
+        })
+        .collect();
+
+    if syndromes.iter().all(|s| *s == 0) {
+        return true;
+    }
+
+    // Berlekamp-Massey to find the error locator polynomial
+    let mut locator = vec![1u8];
+    let mut previous = vec![1u8];
+    let mut errors = 0;
+    let mut shift = 1;
+    let mut previous_discrepancy = 1;
+
+    for i in 0..ec_len {
+        let mut discrepancy = syndromes[i];
+        for j in 1..=errors {
+            discrepancy ^= gf.mul(locator[j], syndromes[i - j]);
+        }
+
+        if discrepancy == 0 {
+            shift += 1;
+            continue;
+        }
+
+        let factor = gf.div(discrepancy, previous_discrepancy);
+        let mut updated = locator.clone();
+        updated.resize(updated.len().max(previous.len() + shift), 0);
+        for (j, p) in previous.iter().enumerate() {
+            updated[j + shift] ^= gf.mul(factor, *p);
+        }
+
+        if 2 * errors <= i {
+            previous = locator;
+            errors = i + 1 - errors;
+            previous_discrepancy = discrepancy;
+            shift = 1;
+        } else {
+            shift += 1;
+        }
+
+        locator = updated;
+    }
+
+    if 2 * errors > ec_len {
+        return false;
+    }
+
+    // Chien search for the error positions
+    let positions: Vec<usize> = (0..n)
+        .filter(|p| {
+            let x_inv = gf.inverse(gf.pow2(n - 1 - p));
+            gf.eval(&locator, x_inv) == 0
+        })
+        .collect();
+
+    if positions.len() != errors {
+        return false;
+    }
+
+    // Forney's algorithm for the error values
+    let mut evaluator: Vec<u8> = vec![0; ec_len];
+    for (i, s) in syndromes.iter().enumerate() {
+        for (j, l) in locator.iter().enumerate() {
+            if i + j < ec_len {
+                evaluator[i + j] ^= gf.mul(*s, *l);
+            }
+        }
+    }
+
+    let derivative: Vec<u8> = locator
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, l)| if i % 2 == 1 { *l } else { 0 })
+        .collect();
+
+    for p in positions {
+        let x = gf.pow2(n - 1 - p);
+        let x_inv = gf.inverse(x);
+
+        let denominator = gf.eval(&derivative, x_inv);
+        if denominator == 0 {
+            return false;
+        }
+
+        let value = gf.mul(x, gf.div(gf.eval(&evaluator, x_inv), denominator));
+        block[p] ^= value;
+    }
+
+    (0..ec_len).all(|i| {
+        let x = gf.pow2(i);
+        block.iter().fold(0, |acc, c| gf.mul(acc, x) ^ c) == 0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Data and error correction codewords of "HELLO WORLD" as version 1-M code
+    const HELLO_WORLD_DATA: [u8; 16] = [
+        32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17,
+    ];
+    const HELLO_WORLD_EC: [u8; 10] = [196, 35, 39, 119, 235, 215, 231, 226, 93, 23];
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode(&HELLO_WORLD_DATA, 10), HELLO_WORLD_EC);
+    }
+
+    #[test]
+    fn test_correct() {
+        let mut block = [HELLO_WORLD_DATA.as_slice(), HELLO_WORLD_EC.as_slice()].concat();
+        let original = block.clone();
+
+        assert!(correct(&mut block, 10));
+        assert_eq!(block, original);
+
+        for (i, p) in [0, 3, 9, 17, 25].iter().enumerate() {
+            block[*p] ^= 0x5a + i as u8;
+        }
+
+        assert!(correct(&mut block, 10));
+        assert_eq!(block, original);
+    }
+
+    #[test]
+    fn test_too_many_errors() {
+        let mut block = [HELLO_WORLD_DATA.as_slice(), HELLO_WORLD_EC.as_slice()].concat();
+
+        for p in 0..6 {
+            block[p * 4] ^= 0xff;
+        }
+
+        assert!(!correct(&mut block, 10));
+    }
+}
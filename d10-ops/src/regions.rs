@@ -0,0 +1,326 @@
+use d10_core::color::{Color, Rgb};
+use d10_core::pixelbuffer::PixelBuffer;
+use d10_core::region::Region;
+
+use crate::{crop, gaussian_blur};
+
+/// Detector for regions of interest in an image, i.e. faces that need to be redacted
+pub trait RegionDetector {
+    fn detect(&self, buffer: &PixelBuffer<Rgb>) -> Vec<Region>;
+}
+
+impl<F> RegionDetector for F
+where
+    F: Fn(&PixelBuffer<Rgb>) -> Vec<Region>,
+{
+    fn detect(&self, buffer: &PixelBuffer<Rgb>) -> Vec<Region> {
+        self(buffer)
+    }
+}
+
+/// Apply a gaussian blur to the given regions and leave the rest of the image unchanged
+pub fn blur_regions(
+    buffer: &PixelBuffer<Rgb>,
+    regions: &[Region],
+    radius: u32,
+) -> PixelBuffer<Rgb> {
+    let mut result = buffer.clone();
+
+    for region in regions
+        .iter()
+        .filter_map(|r| r.clamp(buffer.width(), buffer.height()))
+    {
+        // Blur an area larger than the region to avoid clamped borders inside the region
+        let x = region.x.saturating_sub(radius);
+        let y = region.y.saturating_sub(radius);
+        let width = region.x + region.width + radius - x;
+        let height = region.y + region.height + radius - y;
+
+        let blurred = gaussian_blur(&crop(buffer, x, y, width, height), radius, None);
+
+        result.copy_from(
+            &blurred,
+            Region::new(region.x - x, region.y - y, region.width, region.height),
+            (region.x, region.y),
+        );
+    }
+
+    result
+}
+
+/// Replace the given regions by blocks of their average color
+///
+/// The blocks are aligned to the top left corner of each region.
+pub fn pixelate_regions(
+    buffer: &PixelBuffer<Rgb>,
+    regions: &[Region],
+    block_size: u32,
+) -> PixelBuffer<Rgb> {
+    let block_size = block_size.max(1);
+    let mut result = buffer.clone();
+
+    for region in regions
+        .iter()
+        .filter_map(|r| r.clamp(buffer.width(), buffer.height()))
+    {
+        for by in (region.y..region.y + region.height).step_by(block_size as usize) {
+            for bx in (region.x..region.x + region.width).step_by(block_size as usize) {
+                let block = Region::new(
+                    bx,
+                    by,
+                    block_size.min(region.x + region.width - bx),
+                    block_size.min(region.y + region.height - by),
+                );
+
+                result.fill_rect(block, average_color(buffer, &block));
+            }
+        }
+    }
+
+    result
+}
+
+fn average_color(buffer: &PixelBuffer<Rgb>, region: &Region) -> Rgb {
+    let mut sum = [0.0f32; 4];
+
+    for y in region.y..region.y + region.height {
+        for x in region.x..region.x + region.width {
+            let c = buffer.get_pixel(x, y);
+            sum[0] += c.red();
+            sum[1] += c.green();
+            sum[2] += c.blue();
+            sum[3] += c.alpha();
+        }
+    }
+
+    let count = (region.width * region.height) as f32;
+
+    Rgb::new_with_alpha(
+        sum[0] / count,
+        sum[1] / count,
+        sum[2] / count,
+        sum[3] / count,
+    )
+}
+
+/// Simple detector for areas with skin tones
+///
+/// This is based on an explicit rule in sRGB space and works on a grid of cells.
+/// Neighboring cells with enough skin colored pixels are combined into one region.
+/// It's meant as a fast default for redaction pipelines and will miss faces
+/// under unusual lighting and report other skin colored areas as well.
+#[cfg(feature = "skin-detector")]
+#[derive(Debug, Copy, Clone)]
+pub struct SkinToneDetector {
+    /// Size of the cells in pixels
+    pub cell_size: u32,
+    /// Fraction of skin colored pixels required to mark a cell
+    pub min_coverage: f32,
+    /// Regions smaller than this in width or height are ignored
+    pub min_size: u32,
+}
+
+#[cfg(feature = "skin-detector")]
+impl Default for SkinToneDetector {
+    fn default() -> Self {
+        SkinToneDetector {
+            cell_size: 8,
+            min_coverage: 0.5,
+            min_size: 16,
+        }
+    }
+}
+
+#[cfg(feature = "skin-detector")]
+fn is_skin(color: &Rgb) -> bool {
+    let c = color.to_srgb();
+
+    let r = c.red() * 255.0;
+    let g = c.green() * 255.0;
+    let b = c.blue() * 255.0;
+
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+
+    c.alpha() > 0.5
+        && r > 95.0
+        && g > 40.0
+        && b > 20.0
+        && max - min > 15.0
+        && (r - g).abs() > 15.0
+        && r > g
+        && r > b
+}
+
+#[cfg(feature = "skin-detector")]
+impl RegionDetector for SkinToneDetector {
+    fn detect(&self, buffer: &PixelBuffer<Rgb>) -> Vec<Region> {
+        let cell_size = self.cell_size.max(1);
+
+        let cols = buffer.width().div_ceil(cell_size);
+        let rows = buffer.height().div_ceil(cell_size);
+
+        let mut counts = vec![0u32; (cols * rows) as usize];
+
+        for (x, y, c) in buffer.enumerate() {
+            if is_skin(&c) {
+                counts[((y / cell_size) * cols + x / cell_size) as usize] += 1;
+            }
+        }
+
+        let mut marked: Vec<bool> = counts
+            .iter()
+            .enumerate()
+            .map(|(i, count)| {
+                let cell = Region::new(
+                    (i as u32 % cols) * cell_size,
+                    (i as u32 / cols) * cell_size,
+                    cell_size,
+                    cell_size,
+                )
+                .clamp(buffer.width(), buffer.height())
+                .unwrap();
+
+                *count as f32 >= (cell.width * cell.height) as f32 * self.min_coverage
+            })
+            .collect();
+
+        let mut regions = vec![];
+
+        for start in 0..marked.len() {
+            if !marked[start] {
+                continue;
+            }
+
+            // Flood fill the connected cells and track their bounding box
+            marked[start] = false;
+            let mut stack = vec![start];
+
+            let (mut min_x, mut min_y) = (u32::MAX, u32::MAX);
+            let (mut max_x, mut max_y) = (0, 0);
+
+            while let Some(i) = stack.pop() {
+                let cx = i as u32 % cols;
+                let cy = i as u32 / cols;
+
+                min_x = min_x.min(cx);
+                min_y = min_y.min(cy);
+                max_x = max_x.max(cx);
+                max_y = max_y.max(cy);
+
+                let mut neighbors = Vec::with_capacity(4);
+
+                if cx > 0 {
+                    neighbors.push(i - 1);
+                }
+                if cx + 1 < cols {
+                    neighbors.push(i + 1);
+                }
+                if cy > 0 {
+                    neighbors.push(i - cols as usize);
+                }
+                if cy + 1 < rows {
+                    neighbors.push(i + cols as usize);
+                }
+
+                for n in neighbors {
+                    if marked[n] {
+                        marked[n] = false;
+                        stack.push(n);
+                    }
+                }
+            }
+
+            let region = Region::new(
+                min_x * cell_size,
+                min_y * cell_size,
+                (max_x - min_x + 1) * cell_size,
+                (max_y - min_y + 1) * cell_size,
+            )
+            .clamp(buffer.width(), buffer.height())
+            .unwrap();
+
+            if region.width >= self.min_size && region.height >= self.min_size {
+                regions.push(region);
+            }
+        }
+
+        regions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_buffer() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(32, 32, |x, y| {
+            if (x + y) % 2 == 0 {
+                Rgb::WHITE
+            } else {
+                Rgb::BLACK
+            }
+        })
+    }
+
+    #[test]
+    fn test_blur_regions() {
+        let buffer = test_buffer();
+        let region = Region::new(8, 8, 10, 10);
+
+        let res = blur_regions(&buffer, &[region], 2);
+
+        for (x, y, c) in res.enumerate() {
+            if region.contains(x, y) {
+                assert!(c.red() > 0.1 && c.red() < 0.9);
+            } else {
+                assert_eq!(&c, buffer.get_pixel(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn test_pixelate_regions() {
+        let buffer = test_buffer();
+        let region = Region::new(4, 4, 8, 8);
+
+        let res = pixelate_regions(&buffer, &[region, Region::new(30, 30, 10, 10)], 4);
+
+        for (x, y, c) in res.enumerate() {
+            if region.contains(x, y) {
+                assert_eq!(c, Rgb::new(0.5, 0.5, 0.5));
+            } else if x < 30 || y < 30 {
+                assert_eq!(&c, buffer.get_pixel(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn test_closure_detector() {
+        let detector = |buffer: &PixelBuffer<Rgb>| vec![Region::new(0, 0, buffer.width(), 1)];
+
+        assert_eq!(
+            detector.detect(&test_buffer()),
+            vec![Region::new(0, 0, 32, 1)]
+        );
+    }
+
+    #[cfg(feature = "skin-detector")]
+    #[test]
+    fn test_skin_tone_detector() {
+        let skin = d10_core::color::Srgb::new(0.88, 0.67, 0.55).to_rgb();
+
+        let buffer = PixelBuffer::new_from_func(100, 80, |x, y| {
+            if (20..60).contains(&x) && (16..56).contains(&y) {
+                skin
+            } else {
+                Rgb::BLUE
+            }
+        });
+
+        assert_eq!(
+            SkinToneDetector::default().detect(&buffer),
+            vec![Region::new(16, 16, 48, 40)]
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use d10_core::color::{Color, Rgb, Srgb};
+use d10_core::pixelbuffer::PixelBuffer;
+
+use crate::edge_detection::sobel_gradients;
+
+/// Maximal number of height samples along every direction of the ambient occlusion
+const MAX_AO_STEPS: u32 = 16;
+
+/// Gamma encoded luma of every pixel used as height
+fn luma_heights(buffer: &PixelBuffer<Rgb>) -> Vec<f32> {
+    buffer
+        .data()
+        .iter()
+        .map(|c| c.to_gray().to_srgb().red())
+        .collect()
+}
+
+/// Slopes of the gamma encoded luma to the right and to the bottom of every pixel
+fn luma_slopes(buffer: &PixelBuffer<Rgb>) -> Vec<(f32, f32)> {
+    let height_map = PixelBuffer::new_from_raw(
+        buffer.width(),
+        buffer.height(),
+        luma_heights(buffer)
+            .into_iter()
+            .map(|v| Rgb::new(v, v, v))
+            .collect(),
+    );
+
+    let (gradient_x, gradient_y) = sobel_gradients(&height_map);
+
+    // The sobel kernels sum up the differences of four neighbor pairs, each two pixels apart
+    gradient_x
+        .data()
+        .iter()
+        .zip(gradient_y.data())
+        .map(|(gx, gy)| (-gx.red() / 8.0, -gy.red() / 8.0))
+        .collect()
+}
+
+/// Unit normal of a surface with the given slopes scaled by `strength`
+fn surface_normal(slope_x: f32, slope_y: f32, strength: f32) -> [f32; 3] {
+    let nx = -slope_x * strength;
+    let ny = -slope_y * strength;
+    let length = (nx * nx + ny * ny + 1.0).sqrt();
+
+    [nx / length, ny / length, 1.0 / length]
+}
+
+/// Render the luma of the image as a gray relief lit from the given direction
+///
+/// The azimuth in degrees is the direction the light comes from with 0 being the top and 90
+/// the left. The elevation is the angle above the image in degrees and `depth` scales the
+/// height of the relief. Flat areas get the brightness `sin(elevation)`.
+pub fn emboss(
+    buffer: &PixelBuffer<Rgb>,
+    azimuth: f32,
+    elevation: f32,
+    depth: f32,
+) -> PixelBuffer<Rgb> {
+    let (sin_a, cos_a) = azimuth.to_radians().sin_cos();
+    let (sin_e, cos_e) = elevation.to_radians().sin_cos();
+    let light = [-sin_a * cos_e, -cos_a * cos_e, sin_e];
+
+    let slopes = luma_slopes(buffer);
+
+    let data = buffer
+        .data()
+        .iter()
+        .zip(slopes)
+        .map(|(c, (slope_x, slope_y))| {
+            let n = surface_normal(slope_x, slope_y, depth);
+            let shade = (n[0] * light[0] + n[1] * light[1] + n[2] * light[2]).clamp(0.0, 1.0);
+
+            Srgb::new_with_alpha(shade, shade, shade, c.alpha()).to_rgb()
+        })
+        .collect();
+
+    PixelBuffer::new_from_raw(buffer.width(), buffer.height(), data)
+}
+
+/// Convert a height map into a tangent space normal map
+///
+/// The height is taken from the luma of the image and `strength` scales its slopes. The
+/// normals are encoded in the usual way as `0.5 + 0.5 * n` with the green channel pointing up
+/// (OpenGL convention). The values are stored gamma encoded, so they end up unchanged in 8 bit
+/// image files.
+pub fn height_to_normal_map(buffer: &PixelBuffer<Rgb>, strength: f32) -> PixelBuffer<Rgb> {
+    let slopes = luma_slopes(buffer);
+
+    let data = buffer
+        .data()
+        .iter()
+        .zip(slopes)
+        .map(|(c, (slope_x, slope_y))| {
+            let n = surface_normal(slope_x, slope_y, strength);
+
+            Srgb::new_with_alpha(
+                0.5 + 0.5 * n[0],
+                0.5 - 0.5 * n[1],
+                0.5 + 0.5 * n[2],
+                c.alpha(),
+            )
+            .to_rgb()
+        })
+        .collect();
+
+    PixelBuffer::new_from_raw(buffer.width(), buffer.height(), data)
+}
+
+/// Bake an ambient occlusion map from a height map
+///
+/// The height is taken from the luma of the image with the full range of luma values being as
+/// high as `radius` is wide. For every pixel the horizon is searched in `samples` directions up
+/// to `radius` pixels away. The occlusion of the sky above the pixel is multiplied by `strength`
+/// and the result is stored as gray value, i.e. white for unoccluded areas.
+pub fn height_to_ao(
+    buffer: &PixelBuffer<Rgb>,
+    samples: u32,
+    radius: f32,
+    strength: f32,
+) -> PixelBuffer<Rgb> {
+    let width = buffer.width() as i32;
+    let height = buffer.height() as i32;
+    let heights = luma_heights(buffer);
+
+    let get =
+        |x: i32, y: i32| heights[(y.clamp(0, height - 1) * width + x.clamp(0, width - 1)) as usize];
+
+    let samples = samples.max(1);
+    let radius = radius.max(1.0);
+    let steps = (radius.ceil() as u32).min(MAX_AO_STEPS);
+
+    let directions: Vec<(f32, f32)> = (0..samples)
+        .map(|i| (i as f32 * std::f32::consts::TAU / samples as f32).sin_cos())
+        .collect();
+
+    PixelBuffer::new_from_func(buffer.width(), buffer.height(), |x, y| {
+        let x = x as i32;
+        let y = y as i32;
+        let h = get(x, y) * radius;
+
+        let occlusion: f32 = directions
+            .iter()
+            .map(|(dx, dy)| {
+                // Sine of the highest elevation of the horizon in this direction
+                let mut horizon = 0.0f32;
+
+                for step in 1..=steps {
+                    let distance = radius * step as f32 / steps as f32;
+                    let sx = (x as f32 + dx * distance).round() as i32;
+                    let sy = (y as f32 + dy * distance).round() as i32;
+
+                    let rise = get(sx, sy) * radius - h;
+                    if rise > 0.0 {
+                        horizon = horizon.max(rise / (rise * rise + distance * distance).sqrt());
+                    }
+                }
+
+                horizon
+            })
+            .sum::<f32>()
+            / samples as f32;
+
+        let ao = (1.0 - strength * occlusion).clamp(0.0, 1.0);
+        let alpha = buffer.get_pixel(x as u32, y as u32).alpha();
+
+        Srgb::new_with_alpha(ao, ao, ao, alpha).to_rgb()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Height map rising from left to right
+    fn ramp() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(10, 10, |x, _| {
+            Srgb::new(x as f32 / 10.0, x as f32 / 10.0, x as f32 / 10.0).to_rgb()
+        })
+    }
+
+    #[test]
+    fn test_slopes() {
+        let (slope_x, slope_y) = luma_slopes(&ramp())[55];
+        assert!((slope_x - 0.1).abs() < 1e-3, "{}", slope_x);
+        assert!(slope_y.abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_emboss() {
+        let flat = PixelBuffer::new_with_color(3, 3, Rgb::new(0.2, 0.4, 0.6));
+
+        let res = emboss(&flat, 0.0, 90.0, 1.0);
+        assert!(res
+            .data()
+            .iter()
+            .all(|c| (c.to_srgb().red() - 1.0).abs() < 1e-5));
+
+        let res = emboss(&flat, 0.0, 30.0, 1.0);
+        assert!((res.get_pixel(1, 1).to_srgb().red() - 0.5).abs() < 1e-5);
+
+        // The ramp faces the light from the left and turns away from the light on the right
+        let lit = emboss(&ramp(), 90.0, 45.0, 10.0)
+            .get_pixel(5, 5)
+            .to_srgb()
+            .red();
+        let unlit = emboss(&ramp(), 270.0, 45.0, 10.0)
+            .get_pixel(5, 5)
+            .to_srgb()
+            .red();
+        assert!(lit > 0.9 && unlit < 0.3, "{} {}", lit, unlit);
+    }
+
+    #[test]
+    fn test_height_to_normal_map() {
+        let flat = PixelBuffer::new_with_color(3, 3, Rgb::WHITE);
+        let res = height_to_normal_map(&flat, 1.0);
+        let c = res.get_pixel(1, 1).to_srgb();
+        assert!((c.red() - 0.5).abs() < 1e-5);
+        assert!((c.green() - 0.5).abs() < 1e-5);
+        assert!((c.blue() - 1.0).abs() < 1e-5);
+
+        // Surfaces rising to the right face to the left
+        let c = height_to_normal_map(&ramp(), 10.0)
+            .get_pixel(5, 5)
+            .to_srgb();
+        assert!(c.red() < 0.2, "{:?}", c);
+        assert!((c.green() - 0.5).abs() < 1e-5);
+
+        // Surfaces rising to the bottom face up
+        let ramp_y = PixelBuffer::new_from_raw(10, 10, {
+            let ramp = ramp();
+            (0..100).map(|i| *ramp.get_pixel(i / 10, i % 10)).collect()
+        });
+        let c = height_to_normal_map(&ramp_y, 10.0)
+            .get_pixel(5, 5)
+            .to_srgb();
+        assert!(c.green() > 0.8, "{:?}", c);
+    }
+
+    #[test]
+    fn test_height_to_ao() {
+        let flat = PixelBuffer::new_with_color(5, 5, Rgb::new(0.3, 0.3, 0.3));
+        let res = height_to_ao(&flat, 8, 3.0, 1.0);
+        assert!(res.data().iter().all(|c| *c == Rgb::WHITE));
+
+        // A pit surrounded by walls is occluded, the top of the walls isn't
+        let pit = PixelBuffer::new_from_func(9, 9, |x, y| {
+            if (3..6).contains(&x) && (3..6).contains(&y) {
+                Rgb::BLACK
+            } else {
+                Rgb::WHITE
+            }
+        });
+
+        let res = height_to_ao(&pit, 8, 4.0, 1.0);
+        let center = res.get_pixel(4, 4).to_srgb().red();
+        let wall = res.get_pixel(0, 0).to_srgb().red();
+
+        assert!(center < 0.5, "{}", center);
+        assert!((wall - 1.0).abs() < 1e-5, "{}", wall);
+
+        let weak = height_to_ao(&pit, 8, 4.0, 0.5)
+            .get_pixel(4, 4)
+            .to_srgb()
+            .red();
+        assert!(weak > center);
+    }
+}
@@ -1,7 +1,39 @@
-use d10_core::color::Rgb;
+use d10_core::color::{Color, Rgb, Srgb};
 use d10_core::pixelbuffer::PixelBuffer;
 
-use crate::FilterMode;
+use crate::{unsharp, FilterMode};
+
+/// Options to control how an image gets resized
+#[derive(Copy, Clone, Debug)]
+pub struct ResizeOptions {
+    pub filter: FilterMode,
+    /// Interpolate in linear light instead of gamma encoded sRGB values
+    pub gamma_correct: bool,
+    /// Interpolate colors premultiplied with their alpha value to avoid dark fringes on transparent edges
+    pub premultiply: bool,
+    /// Factor of an unsharp mask applied after resizing
+    pub sharpen_after: Option<f32>,
+}
+
+impl Default for ResizeOptions {
+    fn default() -> Self {
+        ResizeOptions {
+            filter: FilterMode::Auto,
+            gamma_correct: true,
+            premultiply: true,
+            sharpen_after: None,
+        }
+    }
+}
+
+impl ResizeOptions {
+    pub fn new(filter: FilterMode) -> ResizeOptions {
+        ResizeOptions {
+            filter,
+            ..Default::default()
+        }
+    }
+}
 
 /// Resize buffer
 fn resize_with_fn<F>(
@@ -33,6 +65,13 @@ fn resize_pixel_nearest(
     *buffer.get_pixel_clamped(x2, y2)
 }
 
+/// Nearest neighbor sampling at the pixel centers
+fn resize_pixel_art(buffer: &PixelBuffer<Rgb>, x: u32, y: u32, scale_x: f32, scale_y: f32) -> Rgb {
+    let x2 = ((x as f32 + 0.5) / scale_x).floor() as i32;
+    let y2 = ((y as f32 + 0.5) / scale_y).floor() as i32;
+    *buffer.get_pixel_clamped(x2, y2)
+}
+
 fn resize_pixel_bilinear(
     buffer: &PixelBuffer<Rgb>,
     x: u32,
@@ -88,16 +127,29 @@ fn resize_auto(buffer: &PixelBuffer<Rgb>, new_width: u32, new_height: u32) -> Pi
     }
 }
 
-pub fn resize(
+fn resize_sharp_bilinear(
     buffer: &PixelBuffer<Rgb>,
     new_width: u32,
     new_height: u32,
-    filter: FilterMode,
 ) -> PixelBuffer<Rgb> {
-    if buffer.width() == new_width && buffer.height() == new_height {
-        return buffer.clone();
+    let width = buffer.width() * new_width.checked_div(buffer.width()).unwrap_or(1).max(1);
+    let height = buffer.height() * new_height.checked_div(buffer.height()).unwrap_or(1).max(1);
+
+    let prescaled = resize_with_fn(buffer, width, height, resize_pixel_art);
+
+    if width == new_width && height == new_height {
+        prescaled
+    } else {
+        resize_with_fn(&prescaled, new_width, new_height, resize_pixel_bilinear)
     }
+}
 
+fn resize_with_filter(
+    buffer: &PixelBuffer<Rgb>,
+    new_width: u32,
+    new_height: u32,
+    filter: FilterMode,
+) -> PixelBuffer<Rgb> {
     match filter {
         FilterMode::Nearest => resize_with_fn(buffer, new_width, new_height, resize_pixel_nearest),
         FilterMode::Bilinear => {
@@ -114,6 +166,84 @@ pub fn resize(
                 resize_auto(buffer, new_width, new_height)
             }
         }
+        FilterMode::PixelArt => resize_with_fn(buffer, new_width, new_height, resize_pixel_art),
+        FilterMode::SharpBilinear => resize_sharp_bilinear(buffer, new_width, new_height),
+    }
+}
+
+/// Resize buffer with linear light and premultiplied alpha
+///
+/// NaN and infinite values are replaced with `PixelBuffer::sanitize()` before resizing.
+pub fn resize(
+    buffer: &PixelBuffer<Rgb>,
+    new_width: u32,
+    new_height: u32,
+    filter: FilterMode,
+) -> PixelBuffer<Rgb> {
+    resize_with_options(buffer, new_width, new_height, &ResizeOptions::new(filter))
+}
+
+pub fn resize_with_options(
+    buffer: &PixelBuffer<Rgb>,
+    new_width: u32,
+    new_height: u32,
+    options: &ResizeOptions,
+) -> PixelBuffer<Rgb> {
+    if buffer.width() == new_width && buffer.height() == new_height {
+        return buffer.clone();
+    }
+
+    let buffer = buffer.sanitized();
+
+    // Nearest neighbor doesn't mix colors, so there is nothing to correct
+    let is_nearest = matches!(options.filter, FilterMode::Nearest | FilterMode::PixelArt);
+    let gamma_encode = !options.gamma_correct && !is_nearest;
+    let premultiply = options.premultiply && !is_nearest && buffer.has_transparency();
+
+    let result = if gamma_encode || premultiply {
+        // Values are stored in Rgb buffers regardless of the encoding to reuse the filters
+        let prepared = buffer.map_colors(|c| {
+            let mut data = if gamma_encode {
+                c.to_srgb().data
+            } else {
+                c.data
+            };
+
+            if premultiply {
+                for v in data.iter_mut().take(3) {
+                    *v *= c.alpha();
+                }
+            }
+
+            Rgb { data }
+        });
+
+        let resized = resize_with_filter(&prepared, new_width, new_height, options.filter);
+
+        resized.map_colors(|c| {
+            let alpha = c.alpha();
+
+            let mut data = c.data;
+
+            if premultiply {
+                for v in data.iter_mut().take(3) {
+                    *v = if alpha > 0.0 { *v / alpha } else { 0.0 };
+                }
+            }
+
+            if gamma_encode {
+                Srgb::new_with_alpha(data[0], data[1], data[2], data[3]).to_rgb()
+            } else {
+                Rgb::new_with_alpha(data[0], data[1], data[2], data[3])
+            }
+        })
+    } else {
+        resize_with_filter(&buffer, new_width, new_height, options.filter)
+    };
+
+    match options.sharpen_after {
+        Some(factor) if factor > 0.0 => unsharp(&result, 1, factor, None),
+        _ => result,
     }
 }
 
@@ -182,4 +312,110 @@ mod tests {
     fn test_bicubic() {
         check_resize_colors(FilterMode::Bicubic);
     }
+
+    #[test]
+    fn test_pixel_art() {
+        check_resize_colors(FilterMode::PixelArt);
+
+        let img_in = PixelBuffer::new_from_func(3, 2, |x, y| {
+            if (x + y).is_multiple_of(2) {
+                Rgb::BLACK
+            } else {
+                Rgb::WHITE
+            }
+        });
+
+        let img_out = resize(&img_in, 9, 6, FilterMode::PixelArt);
+        for (x, y, c) in img_out.enumerate() {
+            assert_eq!(&c, img_in.get_pixel(x / 3, y / 3), "{}x{}", x, y);
+        }
+    }
+
+    #[test]
+    fn test_sharp_bilinear() {
+        check_resize_colors(FilterMode::SharpBilinear);
+
+        let img_in =
+            PixelBuffer::new_from_func(2, 1, |x, _| if x == 0 { Rgb::BLACK } else { Rgb::WHITE });
+
+        let blended = |filter| {
+            resize(&img_in, 7, 1, filter)
+                .data()
+                .iter()
+                .filter(|c| c.red() > 0.0 && c.red() < 1.0)
+                .count()
+        };
+
+        assert_eq!(blended(FilterMode::SharpBilinear), 1);
+        assert_eq!(blended(FilterMode::Bilinear), 3);
+    }
+
+    #[test]
+    fn test_non_finite() {
+        let mut img_in = PixelBuffer::new_with_color(20, 20, Rgb::new(0.5, 0.5, 0.5));
+        img_in.data_mut()[210].data = [f32::NAN, f32::INFINITY, 0.5, f32::NAN];
+
+        for filter in [
+            FilterMode::Nearest,
+            FilterMode::Bilinear,
+            FilterMode::Bicubic,
+            FilterMode::Lanczos3,
+            FilterMode::Auto,
+            FilterMode::PixelArt,
+            FilterMode::SharpBilinear,
+        ] {
+            let img_out = resize(&img_in, 13, 27, filter);
+            assert!(!img_out.has_non_finite(), "{:?}", filter);
+        }
+    }
+
+    #[test]
+    fn test_gamma() {
+        let options = ResizeOptions {
+            gamma_correct: false,
+            ..ResizeOptions::new(FilterMode::Bilinear)
+        };
+
+        let img_in = PixelBuffer::new_with_color(10, 10, Rgb::new(0.2, 0.5, 0.8));
+        let img_out = resize_with_options(&img_in, 23, 7, &options);
+        check_color(&img_out, Rgb::new(0.2, 0.5, 0.8));
+
+        // Averaging black and white in sRGB results in a darker color than in linear light
+        let img_in =
+            PixelBuffer::new_from_func(2, 1, |x, _| if x == 0 { Rgb::BLACK } else { Rgb::WHITE });
+        let linear = resize(&img_in, 1, 1, FilterMode::Bilinear);
+        let encoded = resize_with_options(&img_in, 1, 1, &options);
+
+        assert_eq!(linear.get_pixel(0, 0), &Rgb::new(0.5, 0.5, 0.5));
+        assert!(encoded.get_pixel(0, 0).red() < 0.25);
+    }
+
+    #[test]
+    fn test_premultiplied_alpha() {
+        let img_in =
+            PixelBuffer::new_from_func(10, 10, |x, _| if x < 5 { Rgb::RED } else { Rgb::NONE });
+
+        let img_out = resize(&img_in, 5, 5, FilterMode::Bilinear);
+
+        for (x, y, c) in img_out.enumerate() {
+            if c.alpha() > 0.0 {
+                assert_eq!(
+                    c.with_alpha(1.0),
+                    Rgb::RED,
+                    "Dark fringe at position {}x{}: {:?}",
+                    x,
+                    y,
+                    c
+                );
+            }
+        }
+
+        let options = ResizeOptions {
+            premultiply: false,
+            ..ResizeOptions::new(FilterMode::Bilinear)
+        };
+
+        let img_out = resize_with_options(&img_in, 5, 5, &options);
+        assert!(img_out.get_pixel(2, 0).red() < 1.0);
+    }
 }
@@ -0,0 +1,339 @@
+use d10_core::color::{Color, Rgb};
+use d10_core::errors::ParseEnumError;
+use d10_core::pixelbuffer::PixelBuffer;
+use std::str::FromStr;
+
+use crate::threshold::{otsu_threshold, srgb_luma};
+use crate::{
+    adaptive_threshold, perspective_warp, resize, straighten, stretch_contrast, FilterMode,
+};
+
+/// Pages are detected on a downscaled copy of the image
+const MAX_DETECTION_SIZE: u32 = 512;
+
+/// Minimal part of the image covered by a detected page
+const MIN_PAGE_AREA: f32 = 0.2;
+
+/// Pages covering almost the complete image are already cropped
+const MAX_PAGE_AREA: f32 = 0.95;
+
+/// Minimal part of the page quad covered by bright pixels
+const MIN_PAGE_FILL: f32 = 0.85;
+
+/// Maximal rotation in degrees corrected for images without a detected page outline
+const MAX_DESKEW_ANGLE: f32 = 10.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ScanMode {
+    /// Keep the colors and boost the contrast
+    Color,
+    /// Convert to grayscale and boost the contrast
+    Gray,
+    /// Black text on white paper using an adaptive threshold
+    BlackWhite,
+}
+
+impl FromStr for ScanMode {
+    type Err = ParseEnumError;
+
+    fn from_str(value: &str) -> Result<ScanMode, Self::Err> {
+        match value {
+            "color" | "default" => Ok(ScanMode::Color),
+            "gray" => Ok(ScanMode::Gray),
+            "bw" | "black_white" => Ok(ScanMode::BlackWhite),
+            _ => Err(ParseEnumError::new(value, "ScanMode")),
+        }
+    }
+}
+
+/// Indices of all pixels where `mask` is true that are 4-connected to one of the seeds
+fn flood_fill(
+    mask: &[bool],
+    width: usize,
+    height: usize,
+    seeds: impl IntoIterator<Item = usize>,
+    visited: &mut [bool],
+) -> Vec<usize> {
+    let mut stack: Vec<usize> = seeds
+        .into_iter()
+        .filter(|i| mask[*i] && !visited[*i])
+        .collect();
+    for i in &stack {
+        visited[*i] = true;
+    }
+
+    let mut result = vec![];
+
+    while let Some(i) = stack.pop() {
+        result.push(i);
+
+        let (x, y) = (i % width, i / width);
+        let neighbors = [
+            (x > 0).then(|| i - 1),
+            (x + 1 < width).then(|| i + 1),
+            (y > 0).then(|| i - width),
+            (y + 1 < height).then(|| i + width),
+        ];
+
+        for n in neighbors.into_iter().flatten() {
+            if mask[n] && !visited[n] {
+                visited[n] = true;
+                stack.push(n);
+            }
+        }
+    }
+
+    result
+}
+
+/// Indices of the largest 4-connected area of pixels where `mask` is true
+fn largest_component(mask: &[bool], width: usize, height: usize) -> Vec<usize> {
+    let mut visited = vec![false; mask.len()];
+    let mut largest = vec![];
+
+    for start in 0..mask.len() {
+        let component = flood_fill(mask, width, height, [start], &mut visited);
+        if component.len() > largest.len() {
+            largest = component;
+        }
+    }
+
+    largest
+}
+
+/// Number of pixels of an area including the holes enclosed by it
+fn filled_area(area: &[usize], width: usize, height: usize) -> usize {
+    let mut outside = vec![true; width * height];
+    for i in area {
+        outside[*i] = false;
+    }
+
+    let border = (0..width)
+        .flat_map(|x| [x, (height - 1) * width + x])
+        .chain((0..height).flat_map(|y| [y * width, y * width + width - 1]));
+
+    let mut visited = vec![false; width * height];
+    let reached = flood_fill(&outside, width, height, border, &mut visited);
+
+    width * height - reached.len()
+}
+
+/// Area of a polygon with the shoelace formula
+fn polygon_area(points: &[(f32, f32)]) -> f32 {
+    let sum: f32 = points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(p1, p2)| p1.0 * p2.1 - p2.0 * p1.1)
+        .sum();
+
+    sum.abs() / 2.0
+}
+
+/// Detect the outline of a bright page on a darker background
+///
+/// Returns the corners in the order top left, top right, bottom right and bottom left
+/// or `None` if no page covering a reasonable part of the image was found. Images that are
+/// already cropped to the page don't have a detectable outline.
+pub fn detect_page(buffer: &PixelBuffer<Rgb>) -> Option<[(f32, f32); 4]> {
+    if buffer.width() < 3 || buffer.height() < 3 {
+        return None;
+    }
+
+    let size = buffer.width().max(buffer.height());
+    let small = if size > MAX_DETECTION_SIZE {
+        let scale = MAX_DETECTION_SIZE as f32 / size as f32;
+        let width = ((buffer.width() as f32 * scale).round() as u32).max(1);
+        let height = ((buffer.height() as f32 * scale).round() as u32).max(1);
+        resize(buffer, width, height, FilterMode::Bilinear)
+    } else {
+        buffer.clone()
+    };
+
+    let width = small.width() as usize;
+    let height = small.height() as usize;
+
+    let value = otsu_threshold(&small);
+    let mask: Vec<bool> = small.data().iter().map(|c| srgb_luma(c) > value).collect();
+
+    let page = largest_component(&mask, width, height);
+    let relative_size = page.len() as f32 / (width * height) as f32;
+    if !(MIN_PAGE_AREA..=MAX_PAGE_AREA).contains(&relative_size) {
+        return None;
+    }
+
+    // The corners are the outermost pixels in diagonal directions
+    let corner = |score: fn(f32, f32) -> f32, dx: f32, dy: f32| {
+        page.iter()
+            .map(|i| ((i % width) as f32 + dx, (i / width) as f32 + dy))
+            .max_by(|a, b| score(a.0, a.1).total_cmp(&score(b.0, b.1)))
+            .unwrap()
+    };
+
+    let corners = [
+        corner(|x, y| -x - y, 0.0, 0.0),
+        corner(|x, y| x - y, 1.0, 0.0),
+        corner(|x, y| x + y, 1.0, 1.0),
+        corner(|x, y| y - x, 0.0, 1.0),
+    ];
+
+    // Text and images on the page are holes in the bright area
+    let area = filled_area(&page, width, height);
+    if (area as f32) < polygon_area(&corners) * MIN_PAGE_FILL {
+        return None;
+    }
+
+    let scale_x = buffer.width() as f32 / width as f32;
+    let scale_y = buffer.height() as f32 / height as f32;
+
+    Some(corners.map(|(x, y)| (x * scale_x, y * scale_y)))
+}
+
+fn distance(p1: (f32, f32), p2: (f32, f32)) -> f32 {
+    (p1.0 - p2.0).hypot(p1.1 - p2.1)
+}
+
+/// Scale the channels so that the brightest quarter of the pixels becomes white
+fn whiten_paper(buffer: &PixelBuffer<Rgb>) -> PixelBuffer<Rgb> {
+    let mut luma: Vec<f32> = buffer.data().iter().map(srgb_luma).collect();
+    luma.sort_by(|a, b| a.total_cmp(b));
+    let limit = luma[luma.len() * 3 / 4];
+
+    let mut sum = [0.0f64; 3];
+    let mut count = 0;
+    for c in buffer.data() {
+        if srgb_luma(c) >= limit {
+            for (s, v) in sum.iter_mut().zip(c.data.iter()) {
+                *s += *v as f64;
+            }
+            count += 1;
+        }
+    }
+
+    // Very dark paper is most likely no paper at all, so the gain is limited
+    let gain = sum.map(|s| 1.0 / (s / count as f64).max(0.2) as f32);
+
+    buffer.map_colors(|c| {
+        Rgb::new_with_alpha(
+            c.red() * gain[0],
+            c.green() * gain[1],
+            c.blue() * gain[2],
+            c.alpha(),
+        )
+    })
+}
+
+/// Turn a photo of a document into a clean scan
+///
+/// The page outline gets detected and perspective corrected. Without a detectable outline
+/// the image is deskewed instead. Afterwards the paper color is balanced to white and
+/// depending on the mode the contrast is boosted or the page is converted to black and white
+/// with an adaptive threshold.
+pub fn scan_enhance(buffer: &PixelBuffer<Rgb>, mode: ScanMode) -> PixelBuffer<Rgb> {
+    if buffer.is_empty() {
+        return buffer.clone();
+    }
+
+    let page = match detect_page(buffer) {
+        Some([tl, tr, br, bl]) => {
+            let width = distance(tl, tr).max(distance(bl, br)).round().max(1.0) as u32;
+            let height = distance(tl, bl).max(distance(tr, br)).round().max(1.0) as u32;
+            perspective_warp(buffer, [tl, tr, br, bl], width, height, FilterMode::Bicubic)
+        }
+        None => straighten(buffer, MAX_DESKEW_ANGLE),
+    };
+
+    let page = whiten_paper(&page);
+
+    match mode {
+        ScanMode::Color => stretch_contrast(&page, 0.5),
+        ScanMode::Gray => stretch_contrast(&page.map_colors(|c| c.to_gray()), 0.5),
+        ScanMode::BlackWhite => {
+            let radius = (page.width().min(page.height()) / 40).max(4);
+            adaptive_threshold(&page, radius, 0.08)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A slightly yellow page with text lines on a dark table
+    fn photo() -> PixelBuffer<Rgb> {
+        let corners = [(30.0, 20.0), (170.0, 35.0), (160.0, 190.0), (20.0, 175.0)];
+
+        let page = PixelBuffer::new_from_func(140, 160, |x, y| {
+            if x > 15 && x < 125 && y % 20 > 14 && y > 10 && y < 150 {
+                Rgb::new(0.05, 0.05, 0.05)
+            } else {
+                Rgb::new(0.7, 0.65, 0.45)
+            }
+        });
+
+        // Inverse of the page to photo mapping
+        let homography = crate::Homography::from_points(
+            corners,
+            [(0.0, 0.0), (140.0, 0.0), (140.0, 160.0), (0.0, 160.0)],
+        )
+        .unwrap();
+
+        PixelBuffer::new_from_func(200, 220, |x, y| {
+            let (px, py) = homography.map(x as f32 + 0.5, y as f32 + 0.5);
+            if px >= 0.0 && py >= 0.0 && px < 140.0 && py < 160.0 {
+                *page.get_pixel(px as u32, py as u32)
+            } else {
+                Rgb::new(0.1, 0.08, 0.06)
+            }
+        })
+    }
+
+    #[test]
+    fn test_detect_page() {
+        let corners = detect_page(&photo()).unwrap();
+        let expected = [(30.0, 20.0), (170.0, 35.0), (160.0, 190.0), (20.0, 175.0)];
+
+        for (c, e) in corners.iter().zip(expected.iter()) {
+            assert!(distance(*c, *e) < 3.0, "{:?} {:?}", corners, expected);
+        }
+
+        let flat = PixelBuffer::new_with_color(20, 20, Rgb::new(0.5, 0.5, 0.5));
+        assert_eq!(detect_page(&flat), None);
+    }
+
+    #[test]
+    fn test_scan_enhance() {
+        let res = scan_enhance(&photo(), ScanMode::Color);
+
+        assert!((res.width() as i32 - 141).abs() <= 3);
+        assert!((res.height() as i32 - 156).abs() <= 3);
+
+        // The paper became white
+        let paper = res
+            .get_pixel(res.width() / 2, res.height() / 5 - 3)
+            .to_srgb();
+        assert!(paper.data[..3].iter().all(|v| *v > 0.95), "{:?}", paper);
+
+        let res = scan_enhance(&photo(), ScanMode::BlackWhite);
+        let values = res
+            .data()
+            .iter()
+            .filter(|c| c.red() != 0.0 && c.red() != 1.0);
+        assert_eq!(values.count(), 0);
+
+        let res = scan_enhance(&photo(), ScanMode::Gray);
+        assert!(res.data().iter().all(|c| c.red() == c.green()));
+
+        // Already cropped pages keep their size
+        let cropped = PixelBuffer::new_with_color(30, 20, Rgb::new(0.6, 0.6, 0.4));
+        let res = scan_enhance(&cropped, ScanMode::Color);
+        assert_eq!((res.width(), res.height()), (30, 20));
+        assert!(res.get_pixel(10, 10).to_srgb().blue() > 0.95);
+    }
+
+    #[test]
+    fn test_parse_scan_mode() {
+        assert_eq!("default".parse::<ScanMode>().unwrap(), ScanMode::Color);
+        assert_eq!("bw".parse::<ScanMode>().unwrap(), ScanMode::BlackWhite);
+        assert!("sepia".parse::<ScanMode>().is_err());
+    }
+}
@@ -0,0 +1,126 @@
+use d10_core::color::Rgb;
+use d10_core::pixelbuffer::PixelBuffer;
+
+use crate::gaussian_blur;
+
+/// Create a tileable texture by crossfading the wrapped borders of the image
+///
+/// The result is smaller than the input by `overlap` pixels in both dimensions.
+/// The image is split into a low and a high frequency band. Low frequencies are blended
+/// over the whole overlap while details are blended in a narrow band to avoid ghosting.
+pub fn make_seamless(buffer: &PixelBuffer<Rgb>, overlap: u32) -> PixelBuffer<Rgb> {
+    let overlap = overlap.min(buffer.width() / 2).min(buffer.height() / 2);
+
+    if overlap == 0 {
+        return buffer.clone();
+    }
+
+    let low = gaussian_blur(buffer, (overlap / 4).max(1), None);
+    let high = PixelBuffer::new_from_func(buffer.width(), buffer.height(), |x, y| {
+        let c = buffer.get_pixel(x, y);
+        let l = low.get_pixel(x, y);
+
+        Rgb {
+            data: [
+                c.data[0] - l.data[0],
+                c.data[1] - l.data[1],
+                c.data[2] - l.data[2],
+                c.data[3] - l.data[3],
+            ],
+        }
+    });
+
+    let low = crossfade_vertical(&crossfade_horizontal(&low, overlap, 1.0), overlap, 1.0);
+    let high = crossfade_vertical(&crossfade_horizontal(&high, overlap, 4.0), overlap, 4.0);
+
+    PixelBuffer::new_from_func(low.width(), low.height(), |x, y| {
+        let l = low.get_pixel(x, y);
+        let h = high.get_pixel(x, y);
+
+        Rgb::new_with_alpha(
+            l.data[0] + h.data[0],
+            l.data[1] + h.data[1],
+            l.data[2] + h.data[2],
+            l.data[3] + h.data[3],
+        )
+    })
+}
+
+/// Weight of the original pixel at position `pos` inside the overlap
+///
+/// A steepness of 1.0 is a linear ramp over the whole overlap,
+/// higher values shorten the transition around the center.
+fn ramp(pos: u32, overlap: u32, steepness: f32) -> f32 {
+    let t = (pos as f32 + 0.5) / overlap as f32;
+    ((t - 0.5) * steepness + 0.5).clamp(0.0, 1.0)
+}
+
+fn mix(a: &Rgb, b: &Rgb, t: f32) -> Rgb {
+    Rgb {
+        data: [
+            a.data[0] * t + b.data[0] * (1.0 - t),
+            a.data[1] * t + b.data[1] * (1.0 - t),
+            a.data[2] * t + b.data[2] * (1.0 - t),
+            a.data[3] * t + b.data[3] * (1.0 - t),
+        ],
+    }
+}
+
+fn crossfade_horizontal(
+    buffer: &PixelBuffer<Rgb>,
+    overlap: u32,
+    steepness: f32,
+) -> PixelBuffer<Rgb> {
+    let width = buffer.width() - overlap;
+
+    PixelBuffer::new_from_func(width, buffer.height(), |x, y| {
+        if x < overlap {
+            mix(
+                buffer.get_pixel(x, y),
+                buffer.get_pixel(x + width, y),
+                ramp(x, overlap, steepness),
+            )
+        } else {
+            *buffer.get_pixel(x, y)
+        }
+    })
+}
+
+fn crossfade_vertical(buffer: &PixelBuffer<Rgb>, overlap: u32, steepness: f32) -> PixelBuffer<Rgb> {
+    let height = buffer.height() - overlap;
+
+    PixelBuffer::new_from_func(buffer.width(), height, |x, y| {
+        if y < overlap {
+            mix(
+                buffer.get_pixel(x, y),
+                buffer.get_pixel(x, y + height),
+                ramp(y, overlap, steepness),
+            )
+        } else {
+            *buffer.get_pixel(x, y)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_make_seamless() {
+        // A horizontal gradient has a hard seam when tiled
+        let buffer = PixelBuffer::new_from_func(40, 30, |x, _| Rgb::new(x as f32 / 39.0, 0.5, 0.0));
+
+        let res = make_seamless(&buffer, 10);
+
+        assert_eq!(res.width(), 30);
+        assert_eq!(res.height(), 20);
+
+        for y in 0..res.height() {
+            let seam = (res.get_pixel(0, y).red() - res.get_pixel(29, y).red()).abs();
+            let step = (res.get_pixel(15, y).red() - res.get_pixel(14, y).red()).abs();
+
+            assert!(seam < step * 4.0, "Visible seam in row {}: {}", y, seam);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Gaussian mixture models of colors
+
+/// Added to the diagonal of the covariance to keep single colored components invertible
+const REGULARIZATION: f64 = 0.01;
+
+const KMEANS_ITERATIONS: usize = 10;
+
+pub(super) type Sample = [f64; 3];
+
+fn distance_squared(a: &Sample, b: &Sample) -> f64 {
+    a.iter().zip(b.iter()).map(|(a, b)| (a - b).powi(2)).sum()
+}
+
+#[derive(Debug, Clone)]
+struct Component {
+    weight: f64,
+    mean: Sample,
+    inverse_covariance: [[f64; 3]; 3],
+    /// Normalization of the density including the determinant of the covariance
+    factor: f64,
+}
+
+impl Component {
+    fn fit(samples: &[&Sample], total: usize) -> Option<Component> {
+        if samples.is_empty() {
+            return None;
+        }
+
+        let n = samples.len() as f64;
+
+        let mut mean = [0.0; 3];
+        for s in samples {
+            for (m, v) in mean.iter_mut().zip(s.iter()) {
+                *m += v / n;
+            }
+        }
+
+        let mut cov = [[0.0; 3]; 3];
+        for s in samples {
+            for i in 0..3 {
+                for j in 0..3 {
+                    cov[i][j] += (s[i] - mean[i]) * (s[j] - mean[j]) / n;
+                }
+            }
+        }
+
+        for (i, row) in cov.iter_mut().enumerate() {
+            row[i] += REGULARIZATION;
+        }
+
+        let det = cov[0][0] * (cov[1][1] * cov[2][2] - cov[1][2] * cov[2][1])
+            - cov[0][1] * (cov[1][0] * cov[2][2] - cov[1][2] * cov[2][0])
+            + cov[0][2] * (cov[1][0] * cov[2][1] - cov[1][1] * cov[2][0]);
+
+        if det <= 0.0 {
+            return None;
+        }
+
+        let mut inverse_covariance = [[0.0; 3]; 3];
+        for (i, row) in inverse_covariance.iter_mut().enumerate() {
+            for (j, v) in row.iter_mut().enumerate() {
+                // Cofactors of the transposed matrix
+                let (r1, r2) = ((j + 1) % 3, (j + 2) % 3);
+                let (c1, c2) = ((i + 1) % 3, (i + 2) % 3);
+                *v = (cov[r1][c1] * cov[r2][c2] - cov[r1][c2] * cov[r2][c1]) / det;
+            }
+        }
+
+        Some(Component {
+            weight: n / total as f64,
+            mean,
+            inverse_covariance,
+            factor: 1.0 / ((2.0 * std::f64::consts::PI).powi(3) * det).sqrt(),
+        })
+    }
+
+    fn density(&self, sample: &Sample) -> f64 {
+        let d = [
+            sample[0] - self.mean[0],
+            sample[1] - self.mean[1],
+            sample[2] - self.mean[2],
+        ];
+
+        let mut exponent = 0.0;
+        for i in 0..3 {
+            for j in 0..3 {
+                exponent += d[i] * self.inverse_covariance[i][j] * d[j];
+            }
+        }
+
+        self.weight * self.factor * (-0.5 * exponent).exp()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Gmm {
+    components: Vec<Component>,
+}
+
+impl Gmm {
+    /// Fit a model to samples that are already assigned to components
+    pub(super) fn fit(samples: &[Sample], assignments: &[usize], count: usize) -> Gmm {
+        let components = (0..count)
+            .filter_map(|k| {
+                let members: Vec<&Sample> = samples
+                    .iter()
+                    .zip(assignments)
+                    .filter(|(_, a)| **a == k)
+                    .map(|(s, _)| s)
+                    .collect();
+
+                Component::fit(&members, samples.len())
+            })
+            .collect();
+
+        Gmm { components }
+    }
+
+    /// Initial assignment of samples to `count` components with k-means clustering
+    pub(super) fn cluster(samples: &[Sample], count: usize) -> Vec<usize> {
+        if samples.is_empty() {
+            return vec![];
+        }
+
+        // Start with centers spread over the brightness range
+        let mut sorted: Vec<&Sample> = samples.iter().collect();
+        sorted.sort_by(|a, b| a.iter().sum::<f64>().total_cmp(&b.iter().sum::<f64>()));
+
+        let mut centers: Vec<Sample> = (0..count)
+            .map(|k| *sorted[(2 * k + 1) * sorted.len() / (2 * count)])
+            .collect();
+
+        let mut assignments = vec![0; samples.len()];
+
+        for _ in 0..KMEANS_ITERATIONS {
+            for (a, s) in assignments.iter_mut().zip(samples) {
+                *a = (0..count)
+                    .min_by(|i, j| {
+                        distance_squared(s, &centers[*i])
+                            .total_cmp(&distance_squared(s, &centers[*j]))
+                    })
+                    .unwrap();
+            }
+
+            for (k, center) in centers.iter_mut().enumerate() {
+                let mut sum = [0.0; 3];
+                let mut n = 0;
+                for (s, _) in samples.iter().zip(&assignments).filter(|(_, a)| **a == k) {
+                    for (sum, v) in sum.iter_mut().zip(s.iter()) {
+                        *sum += v;
+                    }
+                    n += 1;
+                }
+
+                if n > 0 {
+                    *center = sum.map(|v| v / n as f64);
+                }
+            }
+        }
+
+        assignments
+    }
+
+    /// Index of the component that most likely generated the sample
+    pub(super) fn best_component(&self, sample: &Sample) -> usize {
+        (0..self.components.len())
+            .max_by(|a, b| {
+                self.components[*a]
+                    .density(sample)
+                    .total_cmp(&self.components[*b].density(sample))
+            })
+            .unwrap_or(0)
+    }
+
+    /// Negative log likelihood of the sample
+    pub(super) fn cost(&self, sample: &Sample) -> f64 {
+        let density: f64 = self.components.iter().map(|c| c.density(sample)).sum();
+        -density.max(f64::MIN_POSITIVE).ln()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gmm() {
+        let samples: Vec<Sample> = (0..100)
+            .map(|i| {
+                let v = (i % 5) as f64;
+                if i < 50 {
+                    [10.0 + v, 20.0 - v, 30.0]
+                } else {
+                    [200.0 - v, 150.0, 100.0 + v]
+                }
+            })
+            .collect();
+
+        let assignments = Gmm::cluster(&samples, 2);
+        assert!(assignments[..50].iter().all(|a| *a == assignments[0]));
+        assert!(assignments[50..].iter().all(|a| *a == assignments[50]));
+        assert_ne!(assignments[0], assignments[50]);
+
+        let gmm = Gmm::fit(&samples, &assignments, 2);
+        assert_eq!(gmm.best_component(&[12.0, 18.0, 30.0]), assignments[0]);
+        assert!(gmm.cost(&[12.0, 18.0, 30.0]) < gmm.cost(&[100.0, 100.0, 200.0]));
+    }
+}
@@ -0,0 +1,160 @@
+//! Minimum s-t cut of a graph with Dinic's max flow algorithm
+
+const EPSILON: f64 = 1e-9;
+
+pub(super) struct Graph {
+    adjacency: Vec<Vec<usize>>,
+    to: Vec<usize>,
+    capacity: Vec<f64>,
+}
+
+impl Graph {
+    pub(super) fn new(nodes: usize) -> Graph {
+        Graph {
+            adjacency: vec![vec![]; nodes],
+            to: vec![],
+            capacity: vec![],
+        }
+    }
+
+    /// Add an edge from `from` to `to` with a separate capacity for the opposite direction
+    ///
+    /// The edge and its reverse are stored next to each other, so that the index of one is the
+    /// index of the other with the lowest bit flipped.
+    pub(super) fn add_edge(&mut self, from: usize, to: usize, capacity: f64, reverse: f64) {
+        self.adjacency[from].push(self.to.len());
+        self.to.push(to);
+        self.capacity.push(capacity);
+
+        self.adjacency[to].push(self.to.len());
+        self.to.push(from);
+        self.capacity.push(reverse);
+    }
+
+    /// Distances from the source in the residual graph or `None` if the sink is not reachable
+    fn levels(&self, source: usize, sink: usize) -> Option<Vec<i32>> {
+        let mut level = vec![-1; self.adjacency.len()];
+        let mut queue = std::collections::VecDeque::new();
+
+        level[source] = 0;
+        queue.push_back(source);
+
+        while let Some(u) = queue.pop_front() {
+            for e in &self.adjacency[u] {
+                let v = self.to[*e];
+                if level[v] < 0 && self.capacity[*e] > EPSILON {
+                    level[v] = level[u] + 1;
+                    queue.push_back(v);
+                }
+            }
+        }
+
+        (level[sink] >= 0).then_some(level)
+    }
+
+    /// Saturate all shortest paths from the source to the sink
+    fn blocking_flow(&mut self, source: usize, sink: usize, level: &mut [i32]) {
+        let mut next = vec![0; self.adjacency.len()];
+        let mut path: Vec<usize> = vec![];
+        let mut u = source;
+
+        loop {
+            if u == sink {
+                let flow = path
+                    .iter()
+                    .map(|e| self.capacity[*e])
+                    .fold(f64::INFINITY, f64::min);
+
+                for e in &path {
+                    self.capacity[*e] -= flow;
+                    self.capacity[*e ^ 1] += flow;
+                }
+
+                // Continue from the start of the first saturated edge
+                let saturated = path
+                    .iter()
+                    .position(|e| self.capacity[*e] <= EPSILON)
+                    .unwrap_or(0);
+                path.truncate(saturated);
+                u = path.last().map_or(source, |e| self.to[*e]);
+                continue;
+            }
+
+            let edge = loop {
+                let Some(e) = self.adjacency[u].get(next[u]).copied() else {
+                    break None;
+                };
+
+                let v = self.to[e];
+                if self.capacity[e] > EPSILON && level[v] == level[u] + 1 {
+                    break Some(e);
+                }
+
+                next[u] += 1;
+            };
+
+            match edge {
+                Some(e) => {
+                    path.push(e);
+                    u = self.to[e];
+                }
+                None => {
+                    // Dead end, so this node is not needed anymore in this phase
+                    level[u] = -1;
+
+                    let Some(e) = path.pop() else {
+                        return;
+                    };
+
+                    u = self.to[e ^ 1];
+                    next[u] += 1;
+                }
+            }
+        }
+    }
+
+    /// Calculate the minimum cut and return for every node if it is on the side of the source
+    pub(super) fn min_cut(&mut self, source: usize, sink: usize) -> Vec<bool> {
+        while let Some(mut level) = self.levels(source, sink) {
+            self.blocking_flow(source, sink, &mut level);
+        }
+
+        let mut reachable = vec![false; self.adjacency.len()];
+        let mut stack = vec![source];
+        reachable[source] = true;
+
+        while let Some(u) = stack.pop() {
+            for e in &self.adjacency[u] {
+                let v = self.to[*e];
+                if !reachable[v] && self.capacity[*e] > EPSILON {
+                    reachable[v] = true;
+                    stack.push(v);
+                }
+            }
+        }
+
+        reachable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_min_cut() {
+        // Two paths from 0 to 3 with a bottleneck at 1 -> 3 and 0 -> 2
+        let mut graph = Graph::new(4);
+        graph.add_edge(0, 1, 10.0, 0.0);
+        graph.add_edge(0, 2, 2.0, 0.0);
+        graph.add_edge(1, 3, 3.0, 0.0);
+        graph.add_edge(2, 3, 10.0, 0.0);
+        graph.add_edge(1, 2, 4.0, 4.0);
+
+        let cut = graph.min_cut(0, 3);
+        assert_eq!(cut, vec![true, true, false, false]);
+
+        let flow: f64 = graph.adjacency[3].iter().map(|e| graph.capacity[*e]).sum();
+        assert!((flow - 9.0).abs() < 1e-9);
+    }
+}
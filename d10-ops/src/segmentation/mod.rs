@@ -0,0 +1,362 @@
+//! Foreground segmentation with iterated graph cuts similar to GrabCut
+
+mod gmm;
+mod graph_cut;
+
+use d10_core::color::{Color, Rgb};
+use d10_core::pixelbuffer::PixelBuffer;
+use d10_core::region::Region;
+
+use crate::{resize, FilterMode};
+use gmm::{Gmm, Sample};
+use graph_cut::Graph;
+
+/// Segmentation runs on a downscaled copy of the image to keep the graph small
+const MAX_SEGMENTATION_SIZE: u32 = 256;
+
+/// Number of gaussian components used to model the foreground and background colors
+const COMPONENTS: usize = 5;
+
+const ITERATIONS: usize = 4;
+
+/// Weight of the smoothness term relative to the color models
+const SMOOTHNESS: f64 = 50.0;
+
+/// Neighbors with their distance weights, each pair of pixels only once
+const NEIGHBORS: [(i64, i64, f64); 4] = [
+    (1, 0, 1.0),
+    (0, 1, 1.0),
+    (1, 1, std::f64::consts::FRAC_1_SQRT_2),
+    (-1, 1, std::f64::consts::FRAC_1_SQRT_2),
+];
+
+/// Hint about the location of the foreground
+#[derive(Debug, Clone)]
+pub enum SegmentationHint {
+    /// Everything outside of the region is background
+    Rect(Region),
+    /// Bright pixels mark foreground, dark pixels background and transparent pixels are unknown
+    Scribbles(PixelBuffer<Rgb>),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Label {
+    Foreground,
+    Background,
+    ProbableForeground,
+    ProbableBackground,
+}
+
+impl Label {
+    fn is_foreground(self) -> bool {
+        matches!(self, Label::Foreground | Label::ProbableForeground)
+    }
+
+    fn is_fixed(self) -> bool {
+        matches!(self, Label::Foreground | Label::Background)
+    }
+}
+
+fn initial_labels(hint: &SegmentationHint, width: u32, height: u32, scale: f32) -> Vec<Label> {
+    match hint {
+        SegmentationHint::Rect(rect) => {
+            let x0 = (rect.x as f32 * scale).floor() as u32;
+            let y0 = (rect.y as f32 * scale).floor() as u32;
+            let x1 = ((rect.x.saturating_add(rect.width)) as f32 * scale).ceil() as u32;
+            let y1 = ((rect.y.saturating_add(rect.height)) as f32 * scale).ceil() as u32;
+
+            (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| {
+                    if (x0..x1).contains(&x) && (y0..y1).contains(&y) {
+                        Label::ProbableForeground
+                    } else {
+                        Label::Background
+                    }
+                })
+                .collect()
+        }
+        SegmentationHint::Scribbles(scribbles) => {
+            let mut labels = vec![Label::ProbableBackground; (width * height) as usize];
+
+            // Thin scribbles must not get lost when scaling them down
+            for (x, y, c) in scribbles.enumerate() {
+                if c.alpha() < 0.5 {
+                    continue;
+                }
+
+                let x = (x as f32 * scale) as u32;
+                let y = (y as f32 * scale) as u32;
+                if x < width && y < height {
+                    labels[(y * width + x) as usize] = if c.to_gray().red() > 0.5 {
+                        Label::Foreground
+                    } else {
+                        Label::Background
+                    };
+                }
+            }
+
+            labels
+        }
+    }
+}
+
+fn fit_models(
+    samples: &[Sample],
+    labels: &[Label],
+    assignments: Option<(&Gmm, &Gmm)>,
+) -> (Gmm, Gmm) {
+    let fit = |foreground: bool| {
+        let model = assignments.map(|(fg, bg)| if foreground { fg } else { bg });
+
+        let selected: Vec<Sample> = samples
+            .iter()
+            .zip(labels)
+            .filter(|(_, l)| l.is_foreground() == foreground)
+            .map(|(s, _)| *s)
+            .collect();
+
+        let components = match model {
+            Some(model) => selected.iter().map(|s| model.best_component(s)).collect(),
+            None => Gmm::cluster(&selected, COMPONENTS),
+        };
+
+        Gmm::fit(&selected, &components, COMPONENTS)
+    };
+
+    (fit(true), fit(false))
+}
+
+/// Assign the probable labels with a minimum graph cut
+fn cut(
+    samples: &[Sample],
+    labels: &mut [Label],
+    width: usize,
+    height: usize,
+    models: (&Gmm, &Gmm),
+) {
+    let pairs = || {
+        (0..height).flat_map(move |y| {
+            (0..width).flat_map(move |x| {
+                NEIGHBORS.into_iter().filter_map(move |(dx, dy, w)| {
+                    let nx = x as i64 + dx;
+                    let ny = y as i64 + dy;
+                    (nx >= 0 && nx < width as i64 && ny < height as i64)
+                        .then(|| (y * width + x, ny as usize * width + nx as usize, w))
+                })
+            })
+        })
+    };
+
+    let squared_differences = |p: usize, q: usize| {
+        samples[p]
+            .iter()
+            .zip(samples[q].iter())
+            .map(|(a, b)| (a - b).powi(2))
+            .sum::<f64>()
+    };
+
+    // Normalize the color differences by their average in the image
+    let (sum, count) = pairs().fold((0.0, 0), |(sum, count), (p, q, _)| {
+        (sum + squared_differences(p, q), count + 1)
+    });
+    let beta = if sum > 0.0 {
+        count as f64 / (2.0 * sum)
+    } else {
+        0.0
+    };
+
+    let source = samples.len();
+    let sink = source + 1;
+    let mut graph = Graph::new(samples.len() + 2);
+    let mut link_sums = vec![0.0; samples.len()];
+
+    for (p, q, w) in pairs() {
+        let capacity = SMOOTHNESS * w * (-beta * squared_differences(p, q)).exp();
+        graph.add_edge(p, q, capacity, capacity);
+        link_sums[p] += capacity;
+        link_sums[q] += capacity;
+    }
+
+    // Larger than any possible cut through the neighbor links of a pixel
+    let fixed = 1.0 + link_sums.iter().copied().fold(0.0, f64::max);
+
+    for (p, (sample, label)) in samples.iter().zip(labels.iter()).enumerate() {
+        let (to_source, to_sink) = match label {
+            Label::Foreground => (fixed, 0.0),
+            Label::Background => (0.0, fixed),
+            _ => (models.1.cost(sample), models.0.cost(sample)),
+        };
+
+        // Only the difference matters for the cut
+        let min = to_source.min(to_sink);
+        graph.add_edge(source, p, to_source - min, 0.0);
+        graph.add_edge(p, sink, to_sink - min, 0.0);
+    }
+
+    let foreground = graph.min_cut(source, sink);
+
+    for (label, foreground) in labels.iter_mut().zip(foreground) {
+        if !label.is_fixed() {
+            *label = if foreground {
+                Label::ProbableForeground
+            } else {
+                Label::ProbableBackground
+            };
+        }
+    }
+}
+
+/// Separate the foreground of the image from the background
+///
+/// The colors of the foreground and background are modeled by gaussian mixture models and the
+/// image gets split by a minimum graph cut that also prefers boundaries at strong edges.
+/// Both steps are repeated a few times to refine the models, like in the GrabCut algorithm by
+/// Rother, Kolmogorov and Blake.
+///
+/// Returns a mask of the same size as the image that is white for the foreground and can be
+/// used as an alpha channel.
+pub fn segment_foreground(buffer: &PixelBuffer<Rgb>, hint: &SegmentationHint) -> PixelBuffer<Rgb> {
+    if buffer.is_empty() {
+        return buffer.clone();
+    }
+
+    let size = buffer.width().max(buffer.height());
+    let (small, scale) = if size > MAX_SEGMENTATION_SIZE {
+        let scale = MAX_SEGMENTATION_SIZE as f32 / size as f32;
+        let width = ((buffer.width() as f32 * scale).round() as u32).max(1);
+        let height = ((buffer.height() as f32 * scale).round() as u32).max(1);
+        (resize(buffer, width, height, FilterMode::Bilinear), scale)
+    } else {
+        (buffer.clone(), 1.0)
+    };
+
+    let width = small.width() as usize;
+    let height = small.height() as usize;
+
+    let samples: Vec<Sample> = small
+        .data()
+        .iter()
+        .map(|c| {
+            let c = c.to_srgb();
+            [c.red(), c.green(), c.blue()].map(|v| v as f64 * 255.0)
+        })
+        .collect();
+
+    let mut labels = initial_labels(hint, small.width(), small.height(), scale);
+
+    let has_foreground = labels.iter().any(|l| l.is_foreground());
+    let has_background = labels.iter().any(|l| !l.is_foreground());
+
+    if has_foreground && has_background {
+        let mut models = fit_models(&samples, &labels, None);
+
+        for _ in 0..ITERATIONS {
+            cut(&samples, &mut labels, width, height, (&models.0, &models.1));
+            models = fit_models(&samples, &labels, Some((&models.0, &models.1)));
+        }
+    }
+
+    let mask = PixelBuffer::new_from_raw(
+        small.width(),
+        small.height(),
+        labels
+            .iter()
+            .map(|l| {
+                if l.is_foreground() {
+                    Rgb::WHITE
+                } else {
+                    Rgb::BLACK
+                }
+            })
+            .collect(),
+    );
+
+    if scale < 1.0 {
+        resize(&mask, buffer.width(), buffer.height(), FilterMode::Bilinear)
+    } else {
+        mask
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A noisy red disk in front of a noisy gray and green striped background
+    fn test_buffer() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(60, 40, |x, y| {
+            let n = ((x * 7 + y * 13) % 5) as f32 * 0.02;
+            let (dx, dy) = (x as f32 - 30.0, y as f32 - 20.0);
+
+            if dx.hypot(dy) < 12.0 {
+                Rgb::new(0.7 + n, 0.1 + n, 0.1)
+            } else if (x / 6) % 2 == 0 {
+                Rgb::new(0.4 + n, 0.4 + n, 0.4 + n)
+            } else {
+                Rgb::new(0.1, 0.5 + n, 0.2)
+            }
+        })
+    }
+
+    fn is_disk(x: u32, y: u32) -> bool {
+        (x as f32 - 30.0).hypot(y as f32 - 20.0) < 12.0
+    }
+
+    fn errors(mask: &PixelBuffer<Rgb>) -> usize {
+        mask.enumerate()
+            .filter(|(x, y, c)| (c.red() > 0.5) != is_disk(*x, *y))
+            .count()
+    }
+
+    #[test]
+    fn test_segment_foreground_rect() {
+        let buffer = test_buffer();
+
+        let mask = segment_foreground(&buffer, &SegmentationHint::Rect(Region::new(14, 4, 32, 32)));
+
+        assert_eq!(mask.width(), 60);
+        assert_eq!(mask.height(), 40);
+        assert!(errors(&mask) < 10, "{}", errors(&mask));
+    }
+
+    #[test]
+    fn test_segment_foreground_scribbles() {
+        let buffer = test_buffer();
+
+        let scribbles = PixelBuffer::new_from_func(60, 40, |x, y| {
+            if y == 20 && (25..35).contains(&x) {
+                Rgb::WHITE
+            } else if y == 5 || x == 5 {
+                Rgb::BLACK
+            } else {
+                Rgb::NONE
+            }
+        });
+
+        let mask = segment_foreground(&buffer, &SegmentationHint::Scribbles(scribbles));
+        assert!(errors(&mask) < 10, "{}", errors(&mask));
+    }
+
+    #[test]
+    fn test_segment_foreground_without_background() {
+        let buffer = test_buffer();
+
+        let mask = segment_foreground(&buffer, &SegmentationHint::Rect(Region::new(0, 0, 60, 40)));
+        assert!(mask.data().iter().all(|c| *c == Rgb::WHITE));
+    }
+
+    #[test]
+    fn test_segment_foreground_downscaled() {
+        let buffer = resize(&test_buffer(), 600, 400, FilterMode::Nearest);
+
+        let mask = segment_foreground(
+            &buffer,
+            &SegmentationHint::Rect(Region::new(140, 40, 320, 320)),
+        );
+
+        assert_eq!(mask.width(), 600);
+        assert!(mask.get_pixel(300, 200).red() > 0.99);
+        assert!(mask.get_pixel(50, 200).red() < 0.01);
+    }
+}
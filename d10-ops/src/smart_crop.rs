@@ -0,0 +1,261 @@
+use d10_core::color::{Color, Rgb};
+use d10_core::pixelbuffer::PixelBuffer;
+use d10_core::region::Region;
+
+use crate::cover::aspect_crop_size;
+use crate::portrait::skin_weight;
+use crate::{crop, edge_detection, resize, EdgeDetection, FilterMode};
+
+/// Longest side of the downscaled image used to score the crops
+const ANALYSIS_SIZE: u32 = 256;
+
+/// Weight of skin tones compared to edges in the saliency of a pixel
+const SKIN_WEIGHT: f32 = 0.5;
+
+/// Weight of the normalized luma entropy compared to the share of the total saliency
+const ENTROPY_WEIGHT: f32 = 0.25;
+
+/// Number of luma bins used to calculate the entropy of a crop
+const ENTROPY_BINS: usize = 32;
+
+/// Weight of the covered share of the focus regions
+///
+/// This is larger than the maximum of the other scores, so focus regions are always kept in
+/// frame if they fit into the crop.
+const FOCUS_WEIGHT: f32 = 2.0;
+
+/// Area of the intersection of two regions
+fn intersection_area(a: &Region, b: &Region) -> u64 {
+    let x0 = a.x.max(b.x);
+    let y0 = a.y.max(b.y);
+    let x1 = (a.x + a.width).min(b.x + b.width);
+    let y1 = (a.y + a.height).min(b.y + b.height);
+
+    x1.saturating_sub(x0) as u64 * y1.saturating_sub(y0) as u64
+}
+
+/// Shannon entropy of the luma values in `region` normalized to a range of 0 to 1
+fn luma_entropy(luma: &[f32], width: u32, region: Region) -> f32 {
+    let mut bins = [0u32; ENTROPY_BINS];
+
+    for y in region.y..region.y + region.height {
+        for x in region.x..region.x + region.width {
+            let value = luma[(y * width + x) as usize].clamp(0.0, 1.0);
+            bins[((value * ENTROPY_BINS as f32) as usize).min(ENTROPY_BINS - 1)] += 1;
+        }
+    }
+
+    let total = (region.width * region.height) as f32;
+
+    let entropy: f32 = bins
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f32 / total;
+            -p * p.log2()
+        })
+        .sum();
+
+    entropy / (ENTROPY_BINS as f32).log2()
+}
+
+/// Find the largest region with the aspect ratio of `target_width` x `target_height` that
+/// contains the most interesting part of the image
+///
+/// Candidate crops are scored by the density of edges and skin tones they contain and the
+/// entropy of their luma values. Crops with an equal score near the center are preferred.
+///
+/// `focus` contains externally supplied regions of interest like faces found by a
+/// `RegionDetector`. The crop keeps as much of them in frame as possible.
+pub fn smart_crop_region(
+    buffer: &PixelBuffer<Rgb>,
+    target_width: u32,
+    target_height: u32,
+    focus: &[Region],
+) -> Region {
+    let width = buffer.width();
+    let height = buffer.height();
+
+    if buffer.is_empty() || target_width == 0 || target_height == 0 {
+        return Region::new(0, 0, width, height);
+    }
+
+    let (crop_width, crop_height) = aspect_crop_size(width, height, target_width, target_height);
+
+    if crop_width == width && crop_height == height {
+        return Region::new(0, 0, width, height);
+    }
+
+    let scale = (ANALYSIS_SIZE as f32 / width.max(height) as f32).min(1.0);
+    let small_width = ((width as f32 * scale).round() as u32).max(1);
+    let small_height = ((height as f32 * scale).round() as u32).max(1);
+
+    let small = resize(buffer, small_width, small_height, FilterMode::Bilinear);
+    let edges = edge_detection(&small, EdgeDetection::Sobel);
+
+    let saliency: Vec<f32> = edges
+        .data()
+        .iter()
+        .zip(small.data())
+        .map(|(edge, c)| {
+            (edge.red() + edge.green() + edge.blue()) / 3.0 + SKIN_WEIGHT * skin_weight(c)
+        })
+        .collect();
+    let luma: Vec<f32> = small
+        .data()
+        .iter()
+        .map(|c| c.to_gray().to_srgb().red())
+        .collect();
+
+    let total: f32 = saliency.iter().sum();
+    let focus_total: u64 = focus.iter().map(|r| r.width as u64 * r.height as u64).sum();
+
+    let window_width = ((crop_width as f32 * scale).round() as u32).clamp(1, small_width);
+    let window_height = ((crop_height as f32 * scale).round() as u32).clamp(1, small_height);
+
+    // The window only moves along the axis that gets cropped
+    let horizontal = crop_width < width;
+    let max_offset = if horizontal {
+        small_width - window_width
+    } else {
+        small_height - window_height
+    };
+
+    let mut offsets: Vec<u32> = (0..=max_offset).collect();
+    offsets.sort_by_key(|offset| (*offset as i64 * 2 - max_offset as i64).abs());
+
+    let mut best = (f32::NEG_INFINITY, max_offset / 2);
+
+    for offset in offsets {
+        let region = if horizontal {
+            Region::new(offset, 0, window_width, window_height)
+        } else {
+            Region::new(0, offset, window_width, window_height)
+        };
+
+        let mut sum = 0.0;
+        for y in region.y..region.y + region.height {
+            for x in region.x..region.x + region.width {
+                sum += saliency[(y * small_width + x) as usize];
+            }
+        }
+
+        let share = if total > 0.0 { sum / total } else { 0.0 };
+        let mut score = share + ENTROPY_WEIGHT * luma_entropy(&luma, small_width, region);
+
+        if focus_total > 0 {
+            let start = (offset as f32 / scale).round() as u32;
+            let full_region = if horizontal {
+                Region::new(start, 0, crop_width, crop_height)
+            } else {
+                Region::new(0, start, crop_width, crop_height)
+            };
+
+            let covered: u64 = focus
+                .iter()
+                .map(|r| intersection_area(r, &full_region))
+                .sum();
+            score += FOCUS_WEIGHT * covered as f32 / focus_total as f32;
+        }
+
+        if score > best.0 + f32::EPSILON {
+            best = (score, offset);
+        }
+    }
+
+    let offset = (best.1 as f32 / scale).round() as u32;
+
+    if horizontal {
+        Region::new(offset.min(width - crop_width), 0, crop_width, crop_height)
+    } else {
+        Region::new(0, offset.min(height - crop_height), crop_width, crop_height)
+    }
+}
+
+/// Crop the most interesting part of the image and scale it to `target_width` x `target_height`
+///
+/// See `smart_crop_region()` for how the crop gets selected.
+pub fn smart_crop(
+    buffer: &PixelBuffer<Rgb>,
+    target_width: u32,
+    target_height: u32,
+    focus: &[Region],
+    filter: FilterMode,
+) -> PixelBuffer<Rgb> {
+    let region = smart_crop_region(buffer, target_width, target_height, focus);
+
+    let cropped = crop(buffer, region.x, region.y, region.width, region.height);
+
+    if cropped.is_empty() {
+        cropped
+    } else {
+        resize(&cropped, target_width, target_height, filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Flat gray image with a textured square at the given position
+    fn test_buffer(width: u32, height: u32, square: Region) -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(width, height, |x, y| {
+            if square.contains(x, y) {
+                if (x + y) % 2 == 0 {
+                    Rgb::WHITE
+                } else {
+                    Rgb::BLACK
+                }
+            } else {
+                Rgb::new(0.5, 0.5, 0.5)
+            }
+        })
+    }
+
+    #[test]
+    fn test_smart_crop_region() {
+        let buffer = test_buffer(300, 100, Region::new(220, 30, 40, 40));
+        let region = smart_crop_region(&buffer, 1, 1, &[]);
+        assert_eq!((region.width, region.height), (100, 100));
+        assert!(region.x <= 220 && region.x + 100 >= 260, "{:?}", region);
+
+        let buffer = test_buffer(60, 200, Region::new(10, 10, 40, 30));
+        let region = smart_crop_region(&buffer, 3, 2, &[]);
+        assert_eq!((region.width, region.height), (60, 40));
+        assert!(region.y <= 10 && region.y + 40 >= 40, "{:?}", region);
+    }
+
+    #[test]
+    fn test_flat_image_is_centered() {
+        let buffer = PixelBuffer::new_with_color(300, 100, Rgb::RED);
+        assert_eq!(
+            smart_crop_region(&buffer, 1, 1, &[]),
+            Region::new(100, 0, 100, 100)
+        );
+    }
+
+    #[test]
+    fn test_focus_regions() {
+        // The focus region wins over the more detailed square
+        let buffer = test_buffer(300, 100, Region::new(220, 30, 40, 40));
+        let focus = [Region::new(20, 40, 10, 10)];
+
+        let region = smart_crop_region(&buffer, 1, 1, &focus);
+        assert!(region.x <= 20 && region.x + 100 >= 30, "{:?}", region);
+    }
+
+    #[test]
+    fn test_smart_crop() {
+        let buffer = test_buffer(300, 100, Region::new(10, 30, 40, 40));
+
+        let res = smart_crop(&buffer, 50, 50, &[], FilterMode::Nearest);
+        assert_eq!((res.width(), res.height()), (50, 50));
+        assert_ne!(res.get_pixel(15, 25), &Rgb::new(0.5, 0.5, 0.5));
+
+        assert_eq!(
+            smart_crop(&buffer, 300, 100, &[], FilterMode::Nearest).data(),
+            buffer.data()
+        );
+        assert!(smart_crop(&PixelBuffer::new(0, 0), 10, 10, &[], FilterMode::Nearest).is_empty());
+    }
+}
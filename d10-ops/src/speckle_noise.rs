@@ -0,0 +1,35 @@
+use d10_core::color::Color;
+use d10_core::pixelbuffer::PixelBuffer;
+use rand::Rng;
+use rand_distr::StandardNormal;
+
+/// Add multiplicative noise with a standard deviation of `sigma` relative to the channel value
+///
+/// Dark areas stay nearly untouched while bright areas get grainy, like in radar or ultrasound images.
+pub fn speckle_noise<C>(buffer: &PixelBuffer<C>, sigma: f32) -> PixelBuffer<C>
+where
+    C: Color,
+{
+    let mut rng = rand::thread_rng();
+
+    buffer.map_colors(|c| {
+        c.map_color_channels(|v| {
+            let noise: f32 = rng.sample(StandardNormal);
+            v + v * noise * sigma
+        })
+    })
+}
+
+pub fn add_speckle_noise<C>(buffer: &mut PixelBuffer<C>, sigma: f32)
+where
+    C: Color,
+{
+    let mut rng = rand::thread_rng();
+
+    buffer.mod_colors(|c| {
+        c.map_color_channels(|v| {
+            let noise: f32 = rng.sample(StandardNormal);
+            v + v * noise * sigma
+        })
+    });
+}
@@ -0,0 +1,150 @@
+use d10_core::color::Rgb;
+use d10_core::pixelbuffer::PixelBuffer;
+use d10_core::region::Region;
+
+use crate::crop;
+
+/// Largest mean difference of the color channels for rows to count as overlapping
+const MATCH_THRESHOLD: f32 = 0.02;
+
+/// Split a tall image into parts of at most `max_height` rows, i.e. for long screenshots
+///
+/// Consecutive parts share `overlap` rows so nothing gets lost at the cuts. The overlap is
+/// limited to less than `max_height`.
+pub fn split_vertical(
+    buffer: &PixelBuffer<Rgb>,
+    max_height: u32,
+    overlap: u32,
+) -> Vec<PixelBuffer<Rgb>> {
+    let max_height = max_height.max(1);
+    let step = max_height - overlap.min(max_height - 1);
+
+    let mut parts = vec![];
+    let mut y = 0;
+
+    while y < buffer.height() {
+        parts.push(crop(buffer, 0, y, buffer.width(), max_height));
+
+        if y + max_height >= buffer.height() {
+            break;
+        }
+
+        y += step;
+    }
+
+    parts
+}
+
+/// Mean difference of the last `rows` rows of `top` and the first rows of `bottom`
+///
+/// Stops early and returns infinity if the difference can't be below `MATCH_THRESHOLD`.
+fn rows_difference(top: &PixelBuffer<Rgb>, bottom: &PixelBuffer<Rgb>, rows: u32) -> f32 {
+    let count = (rows * top.width()) as usize;
+    let start = top.data().len() - count;
+    let max_sum = MATCH_THRESHOLD * (count * 4) as f32;
+
+    let mut sum = 0.0;
+
+    for (c1, c2) in top.data()[start..].iter().zip(&bottom.data()[..count]) {
+        for (v1, v2) in c1.data.iter().zip(&c2.data) {
+            sum += (v1 - v2).abs();
+        }
+
+        if sum > max_sum {
+            return f32::INFINITY;
+        }
+    }
+
+    sum / (count * 4) as f32
+}
+
+/// Stack images vertically and remove the rows that are repeated at the end of one part and the
+/// start of the next one
+///
+/// The largest repeated block of up to `max_overlap` rows is removed, so this reverses
+/// `split_vertical()` with the same overlap and also joins scans of a long document that overlap
+/// by an unknown amount. Returns `None` if the images have different widths.
+pub fn join_vertical(parts: &[&PixelBuffer<Rgb>], max_overlap: u32) -> Option<PixelBuffer<Rgb>> {
+    let width = parts.first().map_or(0, |part| part.width());
+
+    if parts.iter().any(|part| part.width() != width) {
+        return None;
+    }
+
+    let mut offsets = Vec::with_capacity(parts.len());
+    let mut height = 0;
+
+    for (i, part) in parts.iter().enumerate() {
+        let overlap = match i {
+            0 => 0,
+            _ => {
+                let previous = parts[i - 1];
+                (1..=max_overlap.min(previous.height()).min(part.height()))
+                    .rev()
+                    .find(|rows| rows_difference(previous, part, *rows) <= MATCH_THRESHOLD)
+                    .unwrap_or(0)
+            }
+        };
+
+        offsets.push(height - overlap);
+        height += part.height() - overlap;
+    }
+
+    let mut result = PixelBuffer::new(width, height);
+
+    for (part, y) in parts.iter().zip(offsets) {
+        result.copy_from(part, Region::new(0, 0, width, part.height()), (0, y));
+    }
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_image() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(6, 100, |x, y| {
+            Rgb::new(x as f32 / 6.0, y as f32 / 100.0, (x * y % 7) as f32 / 7.0)
+        })
+    }
+
+    #[test]
+    fn test_split_vertical() {
+        let buffer = test_image();
+
+        let parts = split_vertical(&buffer, 40, 10);
+        let heights: Vec<_> = parts.iter().map(|p| p.height()).collect();
+        assert_eq!(heights, [40, 40, 40]);
+        assert_eq!(parts[1].get_pixel(3, 0), buffer.get_pixel(3, 30));
+        assert_eq!(parts[2].get_pixel(3, 39), buffer.get_pixel(3, 99));
+
+        let parts = split_vertical(&buffer, 30, 0);
+        let heights: Vec<_> = parts.iter().map(|p| p.height()).collect();
+        assert_eq!(heights, [30, 30, 30, 10]);
+
+        assert_eq!(split_vertical(&buffer, 200, 10).len(), 1);
+        assert!(split_vertical(&PixelBuffer::new(0, 0), 10, 0).is_empty());
+    }
+
+    #[test]
+    fn test_join_vertical() {
+        let buffer = test_image();
+
+        for (max_height, overlap) in [(40, 10), (30, 0), (25, 24)] {
+            let parts = split_vertical(&buffer, max_height, overlap);
+            let parts: Vec<_> = parts.iter().collect();
+
+            let joined = join_vertical(&parts, overlap).unwrap();
+            assert_eq!(joined.data(), buffer.data(), "{}/{}", max_height, overlap);
+        }
+
+        // Scans with an unknown overlap
+        let parts = [crop(&buffer, 0, 0, 6, 50), crop(&buffer, 0, 35, 6, 65)];
+        let parts: Vec<_> = parts.iter().collect();
+        assert_eq!(join_vertical(&parts, 20).unwrap().data(), buffer.data());
+
+        let other = PixelBuffer::new(5, 10);
+        assert!(join_vertical(&[&buffer, &other], 0).is_none());
+    }
+}
@@ -0,0 +1,114 @@
+use d10_core::color::Color;
+use d10_core::pixelbuffer::PixelBuffer;
+use d10_core::region::Region;
+
+/// Packed sprite atlas
+#[derive(Debug, Clone)]
+pub struct SpriteSheet<C: Color> {
+    pub buffer: PixelBuffer<C>,
+    /// Position of every sprite in the atlas in the same order as the input
+    pub placements: Vec<Region>,
+}
+
+/// Pack sprites into a single atlas using a shelf algorithm
+///
+/// Sprites are sorted by height and placed left to right into rows (shelves) no wider than `max_width`.
+/// Sprites wider than `max_width` get their own row and widen the atlas.
+/// `padding` is the number of empty pixels between the sprites.
+/// Uncovered areas are filled with the default color, i.e. transparent for Rgb.
+pub fn pack_sprites<C: Color>(
+    buffers: &[&PixelBuffer<C>],
+    max_width: u32,
+    padding: u32,
+) -> SpriteSheet<C> {
+    let mut order: Vec<usize> = (0..buffers.len()).collect();
+    order.sort_by_key(|&i| (std::cmp::Reverse(buffers[i].height()), i));
+
+    let mut placements = vec![Region::new(0, 0, 0, 0); buffers.len()];
+
+    let mut x = 0;
+    let mut shelf_y = 0;
+    let mut shelf_height = 0;
+
+    let mut width = 0;
+
+    for i in order {
+        let sprite = buffers[i];
+
+        if x > 0 && x + sprite.width() > max_width {
+            shelf_y += shelf_height + padding;
+            shelf_height = 0;
+            x = 0;
+        }
+
+        placements[i] = Region::new(x, shelf_y, sprite.width(), sprite.height());
+
+        width = width.max(x + sprite.width());
+        shelf_height = shelf_height.max(sprite.height());
+        x += sprite.width() + padding;
+    }
+
+    let height = shelf_y + shelf_height;
+
+    let mut buffer = PixelBuffer::new(width, height);
+
+    for (sprite, placement) in buffers.iter().zip(placements.iter()) {
+        buffer.copy_from(
+            sprite,
+            Region::new(0, 0, sprite.width(), sprite.height()),
+            (placement.x, placement.y),
+        );
+    }
+
+    SpriteSheet { buffer, placements }
+}
+
+#[cfg(test)]
+mod tests {
+    use d10_core::color::Rgb;
+
+    use super::*;
+
+    #[test]
+    fn test_pack_sprites() {
+        let a = PixelBuffer::new_with_color(10, 5, Rgb::RED);
+        let b = PixelBuffer::new_with_color(8, 8, Rgb::GREEN);
+        let c = PixelBuffer::new_with_color(6, 4, Rgb::BLUE);
+        let d = PixelBuffer::new_with_color(30, 2, Rgb::WHITE);
+
+        let sheet = pack_sprites(&[&a, &b, &c, &d], 24, 1);
+
+        assert_eq!(
+            sheet.placements,
+            vec![
+                Region::new(9, 0, 10, 5),
+                Region::new(0, 0, 8, 8),
+                Region::new(0, 9, 6, 4),
+                Region::new(0, 14, 30, 2),
+            ]
+        );
+
+        assert_eq!(sheet.buffer.width(), 30);
+        assert_eq!(sheet.buffer.height(), 16);
+
+        for (sprite, placement) in [&a, &b, &c, &d].iter().zip(sheet.placements.iter()) {
+            for (x, y, color) in sprite.enumerate() {
+                assert_eq!(
+                    sheet.buffer.get_pixel(placement.x + x, placement.y + y),
+                    &color
+                );
+            }
+        }
+
+        // Padding stays transparent
+        assert_eq!(sheet.buffer.get_pixel(8, 0), &Rgb::NONE);
+    }
+
+    #[test]
+    fn test_pack_no_sprites() {
+        let sheet = pack_sprites::<Rgb>(&[], 100, 2);
+
+        assert!(sheet.placements.is_empty());
+        assert!(sheet.buffer.is_empty());
+    }
+}
@@ -0,0 +1,285 @@
+use d10_core::color::{Color, Rgb, Srgb};
+use d10_core::pixelbuffer::PixelBuffer;
+use std::error::Error;
+use std::fmt;
+
+/// Marker at the start of all embedded payloads
+const MAGIC: &[u8; 4] = b"D10S";
+
+/// Size of the magic and the payload length
+const HEADER_SIZE: usize = MAGIC.len() + 4;
+
+/// Number of channels holding data in every pixel
+const CHANNELS: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StegoError {
+    /// Only 1 to 7 bits per channel can be used
+    InvalidBitsPerChannel(u8),
+    /// The payload doesn't fit into the image
+    TooLarge { size: usize, capacity: usize },
+    /// The image doesn't contain an embedded payload
+    NoData,
+}
+
+impl StegoError {
+    /// Stable identifier of the kind of error
+    pub fn code(&self) -> &'static str {
+        match self {
+            StegoError::InvalidBitsPerChannel(_) => "invalid_bits_per_channel",
+            StegoError::TooLarge { .. } => "payload_too_large",
+            StegoError::NoData => "no_embedded_data",
+        }
+    }
+}
+
+impl fmt::Display for StegoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StegoError::InvalidBitsPerChannel(bits) => {
+                write!(f, "Invalid bits per channel: {} (expected 1-7)", bits)
+            }
+            StegoError::TooLarge { size, capacity } => write!(
+                f,
+                "Payload of {} bytes exceeds capacity of {} bytes",
+                size, capacity
+            ),
+            StegoError::NoData => write!(f, "No embedded data found"),
+        }
+    }
+}
+
+impl Error for StegoError {}
+
+fn check_bits(bits_per_channel: u8) -> Result<(), StegoError> {
+    if (1..=7).contains(&bits_per_channel) {
+        Ok(())
+    } else {
+        Err(StegoError::InvalidBitsPerChannel(bits_per_channel))
+    }
+}
+
+fn to_u8(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Replace the lowest `bits` of `value` with `chunk`
+///
+/// A result of 255 is lowered by one step of the higher bits because the maximal value doesn't
+/// survive the roundtrip to linear RGB and back to 8 bit in all encoders.
+fn set_low_bits(value: u8, bits: u8, chunk: u8) -> u8 {
+    let step = 1u8 << bits;
+    let value = (value & !(step - 1)) | chunk;
+
+    if value == u8::MAX {
+        value - step
+    } else {
+        value
+    }
+}
+
+/// 8 bit sRGB values of the color channels of all pixels
+fn channel_values(buffer: &PixelBuffer<Rgb>) -> Vec<u8> {
+    buffer
+        .data()
+        .iter()
+        .flat_map(|c| {
+            let c = c.to_srgb();
+            [to_u8(c.data[0]), to_u8(c.data[1]), to_u8(c.data[2])]
+        })
+        .collect()
+}
+
+/// Number of payload bytes that fit into an image
+///
+/// The first pixel stores the bits per channel and every payload needs an 8 byte header.
+pub fn data_capacity(buffer: &PixelBuffer<Rgb>, bits_per_channel: u8) -> usize {
+    if check_bits(bits_per_channel).is_err() || buffer.data().len() < 2 {
+        return 0;
+    }
+
+    let bits = (buffer.data().len() - 1) * CHANNELS * bits_per_channel as usize;
+    (bits / 8).saturating_sub(HEADER_SIZE)
+}
+
+/// Hide `bytes` in the least significant bits of the color channels
+///
+/// The data is stored in the 8 bit sRGB values, so the result must be saved in a lossless
+/// format like png or bmp. Using more bits per channel increases the capacity but also the
+/// visible noise. Between 1 and 7 bits per channel can be used. The alpha channel is left unchanged.
+pub fn embed_data(
+    buffer: &PixelBuffer<Rgb>,
+    bytes: &[u8],
+    bits_per_channel: u8,
+) -> Result<PixelBuffer<Rgb>, StegoError> {
+    check_bits(bits_per_channel)?;
+
+    let capacity = data_capacity(buffer, bits_per_channel);
+    if bytes.len() > capacity || bytes.len() > u32::MAX as usize {
+        return Err(StegoError::TooLarge {
+            size: bytes.len(),
+            capacity,
+        });
+    }
+
+    let mut values = channel_values(buffer);
+
+    // The bits per channel are stored in the lowest bit of the channels of the first pixel
+    let header_bits = bits_per_channel - 1;
+    for (i, value) in values.iter_mut().take(CHANNELS).enumerate() {
+        *value = set_low_bits(*value, 1, (header_bits >> (CHANNELS - 1 - i)) & 1);
+    }
+
+    let mut payload = Vec::with_capacity(HEADER_SIZE + bytes.len());
+    payload.extend_from_slice(MAGIC);
+    payload.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    payload.extend_from_slice(bytes);
+
+    let mut payload_bits = payload
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1));
+
+    'outer: for value in values.iter_mut().skip(CHANNELS) {
+        let mut chunk = 0;
+        for i in 0..bits_per_channel {
+            match payload_bits.next() {
+                Some(bit) => chunk |= bit << (bits_per_channel - 1 - i),
+                None if i == 0 => break 'outer,
+                None => {}
+            }
+        }
+        *value = set_low_bits(*value, bits_per_channel, chunk);
+    }
+
+    // Encoders truncate instead of rounding, so the values are slightly offset to survive both
+    let from_u8 = |v: u8| (v as f32 + 0.25) / 255.0;
+
+    let data = buffer
+        .data()
+        .iter()
+        .zip(values.chunks_exact(CHANNELS))
+        .map(|(c, v)| {
+            Srgb::new_with_alpha(from_u8(v[0]), from_u8(v[1]), from_u8(v[2]), c.alpha()).to_rgb()
+        })
+        .collect();
+
+    Ok(PixelBuffer::new_from_raw(
+        buffer.width(),
+        buffer.height(),
+        data,
+    ))
+}
+
+/// Extract data hidden with `embed_data()`
+pub fn extract_data(buffer: &PixelBuffer<Rgb>) -> Result<Vec<u8>, StegoError> {
+    let values = channel_values(buffer);
+    if values.len() < CHANNELS * 2 {
+        return Err(StegoError::NoData);
+    }
+
+    let bits_per_channel = values[..CHANNELS]
+        .iter()
+        .fold(0, |bits, value| (bits << 1) | (value & 1))
+        + 1;
+
+    let mut bits = values[CHANNELS..]
+        .iter()
+        .flat_map(|value| (0..bits_per_channel).rev().map(move |i| (value >> i) & 1));
+
+    let mut next_byte = || -> Option<u8> {
+        let mut byte = 0;
+        for _ in 0..8 {
+            byte = (byte << 1) | bits.next()?;
+        }
+        Some(byte)
+    };
+
+    let mut header = [0u8; HEADER_SIZE];
+    for byte in header.iter_mut() {
+        *byte = next_byte().ok_or(StegoError::NoData)?;
+    }
+
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err(StegoError::NoData);
+    }
+
+    let len = u32::from_be_bytes(header[MAGIC.len()..].try_into().unwrap()) as usize;
+    if len > data_capacity(buffer, bits_per_channel) {
+        return Err(StegoError::NoData);
+    }
+
+    (0..len)
+        .map(|_| next_byte().ok_or(StegoError::NoData))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_buffer() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(16, 8, |x, y| Rgb::new(x as f32 / 16.0, y as f32 / 8.0, 0.5))
+    }
+
+    #[test]
+    fn test_embed_extract() {
+        let buffer = test_buffer();
+        let payload = b"asset-id: 42";
+
+        for bits in 1..=7 {
+            let res = embed_data(&buffer, payload, bits).unwrap();
+            assert_eq!(extract_data(&res).unwrap(), payload);
+        }
+    }
+
+    #[test]
+    fn test_embed_is_subtle() {
+        let buffer = test_buffer();
+
+        let res = embed_data(&buffer, b"hidden", 1).unwrap();
+
+        for (c1, c2) in buffer.data().iter().zip(res.data()) {
+            let c1 = c1.to_srgb();
+            let c2 = c2.to_srgb();
+            for i in 0..3 {
+                assert!((c1.data[i] - c2.data[i]).abs() <= 2.0 / 255.0);
+            }
+            assert_eq!(c1.alpha(), c2.alpha());
+        }
+    }
+
+    #[test]
+    fn test_capacity() {
+        let buffer = test_buffer();
+
+        assert_eq!(data_capacity(&buffer, 1), 127 * 3 / 8 - 8);
+        assert_eq!(data_capacity(&buffer, 4), 127 * 3 / 2 - 8);
+        assert_eq!(data_capacity(&buffer, 8), 0);
+
+        let capacity = data_capacity(&buffer, 2);
+        assert!(embed_data(&buffer, &vec![1; capacity], 2).is_ok());
+        assert_eq!(
+            embed_data(&buffer, &vec![1; capacity + 1], 2).unwrap_err(),
+            StegoError::TooLarge {
+                size: capacity + 1,
+                capacity
+            }
+        );
+        assert_eq!(
+            embed_data(&buffer, b"", 0).unwrap_err(),
+            StegoError::InvalidBitsPerChannel(0)
+        );
+    }
+
+    #[test]
+    fn test_extract_without_data() {
+        assert_eq!(
+            extract_data(&test_buffer()).unwrap_err(),
+            StegoError::NoData
+        );
+        assert_eq!(
+            extract_data(&PixelBuffer::new(1, 1)).unwrap_err(),
+            StegoError::NoData
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use d10_core::color::{Color, Rgb};
+use d10_core::pixelbuffer::PixelBuffer;
+
+use crate::{crop, resize, rotate, FilterMode};
+
+/// Resolution of the angle histogram in bins per degree
+const BINS_PER_DEGREE: f32 = 10.0;
+
+/// Images are downscaled to this size before detecting lines to keep detection fast
+const MAX_DETECTION_SIZE: u32 = 512;
+
+/// Minimal gradient magnitude of a pixel to vote for a line angle
+const MIN_MAGNITUDE: f32 = 0.1;
+
+fn get_detection_buffer(buffer: &PixelBuffer<Rgb>) -> PixelBuffer<Rgb> {
+    let size = buffer.width().max(buffer.height());
+
+    let buffer = if size > MAX_DETECTION_SIZE {
+        let scale = MAX_DETECTION_SIZE as f32 / size as f32;
+        let width = ((buffer.width() as f32 * scale).round() as u32).max(1);
+        let height = ((buffer.height() as f32 * scale).round() as u32).max(1);
+        resize(buffer, width, height, FilterMode::Bilinear)
+    } else {
+        buffer.clone()
+    };
+
+    // Detect lines on perceptual lightness to give dark and bright edges a similar weight
+    buffer.map_colors(|c| {
+        let v = c.to_gray().to_srgb().red();
+        Rgb::new(v, v, v)
+    })
+}
+
+/// Detect the angle of the dominant near horizontal line in degrees
+///
+/// Edge pixels found with a sobel operator vote in a hough accumulator limited to
+/// angles between `-max_angle` and `max_angle`.
+/// Positive values mean the line is rotated clockwise.
+pub fn detect_horizon_angle(buffer: &PixelBuffer<Rgb>, max_angle: f32) -> f32 {
+    let max_angle = max_angle.abs().min(45.0);
+
+    if buffer.width() < 3 || buffer.height() < 3 || max_angle <= f32::EPSILON {
+        return 0.0;
+    }
+
+    let gray = get_detection_buffer(buffer);
+
+    let angle_steps = (max_angle * BINS_PER_DEGREE).ceil() as i32;
+    let angles: Vec<(f32, f32)> = (-angle_steps..=angle_steps)
+        .map(|step| {
+            let radians = (step as f32 / BINS_PER_DEGREE).to_radians();
+            (radians.sin(), radians.cos())
+        })
+        .collect();
+
+    // The distance of a pixel to a line through the origin is within -rho_offset..rho_offset
+    let rho_offset = (gray.width() + gray.height()) as usize;
+    let num_rho = rho_offset * 2 + 1;
+
+    let mut accumulator = vec![0.0f32; angles.len() * num_rho];
+
+    for y in 1..gray.height() - 1 {
+        for x in 1..gray.width() - 1 {
+            let k = gray.get_kernel::<3>(x as i32, y as i32);
+            let v = |tx: usize, ty: usize| k[ty][tx].red();
+
+            let gx = v(2, 0) + 2.0 * v(2, 1) + v(2, 2) - v(0, 0) - 2.0 * v(0, 1) - v(0, 2);
+            let gy = v(0, 2) + 2.0 * v(1, 2) + v(2, 2) - v(0, 0) - 2.0 * v(1, 0) - v(2, 0);
+
+            let magnitude = (gx * gx + gy * gy).sqrt();
+
+            // Only edges of roughly horizontal lines are of interest
+            if magnitude < MIN_MAGNITUDE || gx.abs() > gy.abs() {
+                continue;
+            }
+
+            for (i, (sinf, cosf)) in angles.iter().enumerate() {
+                let rho = y as f32 * cosf - x as f32 * sinf;
+                let bin = (rho.round() as isize + rho_offset as isize) as usize;
+                accumulator[i * num_rho + bin.min(num_rho - 1)] += magnitude;
+            }
+        }
+    }
+
+    let mut best_angle = None;
+    let mut best_value = 0.0;
+
+    for (i, row) in accumulator.chunks(num_rho).enumerate() {
+        let value = row.iter().fold(0.0f32, |a, b| a.max(*b));
+
+        // Prefer the smaller correction if several angles are equally good
+        let step = i as i32 - angle_steps;
+        let is_better = value > best_value
+            || (value == best_value && best_angle.is_some_and(|best: i32| step.abs() < best.abs()));
+
+        if is_better {
+            best_value = value;
+            best_angle = Some(step);
+        }
+    }
+
+    match best_angle {
+        Some(step) => step as f32 / BINS_PER_DEGREE,
+        None => 0.0,
+    }
+}
+
+/// Calculate the size of the largest centered rectangle with the same aspect ratio
+/// that fits into the rotated image
+fn get_crop_size(width: u32, height: u32, angle: f32) -> (u32, u32) {
+    let w = width as f32;
+    let h = height as f32;
+
+    let radians = angle.abs().to_radians();
+    let sinf = radians.sin();
+    let cosf = radians.cos();
+
+    let scale = (w / (w * cosf + h * sinf)).min(h / (w * sinf + h * cosf));
+
+    (
+        ((w * scale).floor() as u32).clamp(1, width),
+        ((h * scale).floor() as u32).clamp(1, height),
+    )
+}
+
+/// Level the dominant horizontal line of the image and crop the rotated borders
+///
+/// # Arguments
+/// max_angle: Maximal correction in degrees
+pub fn straighten(buffer: &PixelBuffer<Rgb>, max_angle: f32) -> PixelBuffer<Rgb> {
+    let angle = detect_horizon_angle(buffer, max_angle);
+
+    if angle.abs() < 1.0 / BINS_PER_DEGREE {
+        return buffer.clone();
+    }
+
+    let rotated = rotate(buffer, -angle, Rgb::NONE, FilterMode::Bicubic);
+
+    let (width, height) = get_crop_size(buffer.width(), buffer.height(), angle);
+
+    crop(
+        &rotated,
+        (buffer.width() - width) / 2,
+        (buffer.height() - height) / 2,
+        width,
+        height,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tilted_horizon(angle: f32) -> PixelBuffer<Rgb> {
+        let tan = angle.to_radians().tan();
+
+        PixelBuffer::new_from_func(200, 100, |x, y| {
+            if (y as f32) > 50.0 + (x as f32 - 100.0) * tan {
+                Rgb::WHITE
+            } else {
+                Rgb::BLACK
+            }
+        })
+    }
+
+    #[test]
+    fn test_detect_horizon_angle() {
+        for angle in [-7.0, -2.5, 0.0, 3.0, 8.0] {
+            let detected = detect_horizon_angle(&tilted_horizon(angle), 10.0);
+            assert!(
+                (detected - angle).abs() < 0.5,
+                "Expected angle {} got {}",
+                angle,
+                detected
+            );
+        }
+    }
+
+    #[test]
+    fn test_straighten() {
+        let res = straighten(&tilted_horizon(5.0), 10.0);
+
+        assert!(res.width() < 200);
+        assert!(res.height() < 100);
+
+        for (x, y, c) in res.enumerate() {
+            assert_eq!(c.alpha(), 1.0, "Transparent border at {}x{}", x, y);
+        }
+    }
+}
@@ -57,7 +57,7 @@ pub fn stretch_contrast(buffer: &PixelBuffer<Rgb>, threshold: f32) -> PixelBuffe
     let min_value = get_min_value(&values, threshold);
     let max_value = get_max_value(&values, threshold);
 
-    if min_value > 0.0 || max_value < 1.0 {
+    if min_value < max_value && (min_value > 0.0 || max_value < 1.0) {
         buffer.map_colors(|c| c.with_level(min_value, max_value, 1.0))
     } else {
         buffer.clone()
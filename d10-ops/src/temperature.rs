@@ -50,7 +50,7 @@ pub fn change_color_temperature(
     res
 }
 
-fn calculate_factors(orig_temp: f32, new_temp: f32) -> [f32; 3] {
+pub(crate) fn calculate_factors(orig_temp: f32, new_temp: f32) -> [f32; 3] {
     let orig = convert_kelvin_to_rgb(orig_temp);
     let new = convert_kelvin_to_rgb(new_temp);
 
@@ -70,7 +70,7 @@ fn convert_kelvin_to_rgb(temperature: f32) -> Rgb {
     let (mut x, mut y) = TEMPERATURE_TABLE[i - 1];
 
     let floor_temp = (i * 1000) as f64;
-    let temperature = temperature  as f64;
+    let temperature = temperature as f64;
 
     if (temperature - floor_temp).abs() > 1.0 {
         let (x2, y2) = TEMPERATURE_TABLE[i];
@@ -0,0 +1,223 @@
+use d10_core::color::Color;
+use d10_core::pixelbuffer::PixelBuffer;
+use d10_core::region::Region;
+
+/// Width of a glyph in pixels without spacing
+const GLYPH_WIDTH: u32 = 5;
+
+/// Height of a glyph in pixels without spacing
+const GLYPH_HEIGHT: u32 = 7;
+
+/// Printable ascii characters from 0x20 to 0x7E in a 5x7 pixel font
+///
+/// Every glyph is stored column wise with the lowest bit being the top row.
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5F, 0x00, 0x00], // !
+    [0x00, 0x07, 0x00, 0x07, 0x00], // "
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], // #
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12], // $
+    [0x23, 0x13, 0x08, 0x64, 0x62], // %
+    [0x36, 0x49, 0x55, 0x22, 0x50], // &
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '
+    [0x00, 0x1C, 0x22, 0x41, 0x00], // (
+    [0x00, 0x41, 0x22, 0x1C, 0x00], // )
+    [0x14, 0x08, 0x3E, 0x08, 0x14], // *
+    [0x08, 0x08, 0x3E, 0x08, 0x08], // +
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ,
+    [0x08, 0x08, 0x08, 0x08, 0x08], // -
+    [0x00, 0x60, 0x60, 0x00, 0x00], // .
+    [0x20, 0x10, 0x08, 0x04, 0x02], // /
+    [0x3E, 0x51, 0x49, 0x45, 0x3E], // 0
+    [0x00, 0x42, 0x7F, 0x40, 0x00], // 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], // 2
+    [0x21, 0x41, 0x45, 0x4B, 0x31], // 3
+    [0x18, 0x14, 0x12, 0x7F, 0x10], // 4
+    [0x27, 0x45, 0x45, 0x45, 0x39], // 5
+    [0x3C, 0x4A, 0x49, 0x49, 0x30], // 6
+    [0x01, 0x71, 0x09, 0x05, 0x03], // 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], // 8
+    [0x06, 0x49, 0x49, 0x29, 0x1E], // 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], // :
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ;
+    [0x08, 0x14, 0x22, 0x41, 0x00], // <
+    [0x14, 0x14, 0x14, 0x14, 0x14], // =
+    [0x00, 0x41, 0x22, 0x14, 0x08], // >
+    [0x02, 0x01, 0x51, 0x09, 0x06], // ?
+    [0x32, 0x49, 0x79, 0x41, 0x3E], // @
+    [0x7E, 0x11, 0x11, 0x11, 0x7E], // A
+    [0x7F, 0x49, 0x49, 0x49, 0x36], // B
+    [0x3E, 0x41, 0x41, 0x41, 0x22], // C
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], // D
+    [0x7F, 0x49, 0x49, 0x49, 0x41], // E
+    [0x7F, 0x09, 0x09, 0x09, 0x01], // F
+    [0x3E, 0x41, 0x49, 0x49, 0x7A], // G
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], // H
+    [0x00, 0x41, 0x7F, 0x41, 0x00], // I
+    [0x20, 0x40, 0x41, 0x3F, 0x01], // J
+    [0x7F, 0x08, 0x14, 0x22, 0x41], // K
+    [0x7F, 0x40, 0x40, 0x40, 0x40], // L
+    [0x7F, 0x02, 0x0C, 0x02, 0x7F], // M
+    [0x7F, 0x04, 0x08, 0x10, 0x7F], // N
+    [0x3E, 0x41, 0x41, 0x41, 0x3E], // O
+    [0x7F, 0x09, 0x09, 0x09, 0x06], // P
+    [0x3E, 0x41, 0x51, 0x21, 0x5E], // Q
+    [0x7F, 0x09, 0x19, 0x29, 0x46], // R
+    [0x46, 0x49, 0x49, 0x49, 0x31], // S
+    [0x01, 0x01, 0x7F, 0x01, 0x01], // T
+    [0x3F, 0x40, 0x40, 0x40, 0x3F], // U
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], // V
+    [0x3F, 0x40, 0x38, 0x40, 0x3F], // W
+    [0x63, 0x14, 0x08, 0x14, 0x63], // X
+    [0x07, 0x08, 0x70, 0x08, 0x07], // Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], // Z
+    [0x00, 0x7F, 0x41, 0x41, 0x00], // [
+    [0x02, 0x04, 0x08, 0x10, 0x20], // \
+    [0x00, 0x41, 0x41, 0x7F, 0x00], // ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], // ^
+    [0x40, 0x40, 0x40, 0x40, 0x40], // _
+    [0x00, 0x01, 0x02, 0x04, 0x00], // `
+    [0x20, 0x54, 0x54, 0x54, 0x78], // a
+    [0x7F, 0x48, 0x44, 0x44, 0x38], // b
+    [0x38, 0x44, 0x44, 0x44, 0x20], // c
+    [0x38, 0x44, 0x44, 0x48, 0x7F], // d
+    [0x38, 0x54, 0x54, 0x54, 0x18], // e
+    [0x08, 0x7E, 0x09, 0x01, 0x02], // f
+    [0x0C, 0x52, 0x52, 0x52, 0x3E], // g
+    [0x7F, 0x08, 0x04, 0x04, 0x78], // h
+    [0x00, 0x44, 0x7D, 0x40, 0x00], // i
+    [0x20, 0x40, 0x44, 0x3D, 0x00], // j
+    [0x7F, 0x10, 0x28, 0x44, 0x00], // k
+    [0x00, 0x41, 0x7F, 0x40, 0x00], // l
+    [0x7C, 0x04, 0x18, 0x04, 0x78], // m
+    [0x7C, 0x08, 0x04, 0x04, 0x78], // n
+    [0x38, 0x44, 0x44, 0x44, 0x38], // o
+    [0x7C, 0x14, 0x14, 0x14, 0x08], // p
+    [0x08, 0x14, 0x14, 0x18, 0x7C], // q
+    [0x7C, 0x08, 0x04, 0x04, 0x08], // r
+    [0x48, 0x54, 0x54, 0x54, 0x20], // s
+    [0x04, 0x3F, 0x44, 0x40, 0x20], // t
+    [0x3C, 0x40, 0x40, 0x20, 0x7C], // u
+    [0x1C, 0x20, 0x40, 0x20, 0x1C], // v
+    [0x3C, 0x40, 0x30, 0x40, 0x3C], // w
+    [0x44, 0x28, 0x10, 0x28, 0x44], // x
+    [0x0C, 0x50, 0x50, 0x50, 0x3C], // y
+    [0x44, 0x64, 0x54, 0x4C, 0x44], // z
+    [0x00, 0x08, 0x36, 0x41, 0x00], // {
+    [0x00, 0x00, 0x7F, 0x00, 0x00], // |
+    [0x00, 0x41, 0x36, 0x08, 0x00], // }
+    [0x08, 0x04, 0x08, 0x10, 0x08], // ~
+];
+
+/// Glyph of a character with unsupported characters being rendered as `?`
+fn glyph(c: char) -> &'static [u8; 5] {
+    match c {
+        ' '..='~' => &FONT[c as usize - 0x20],
+        _ => &FONT['?' as usize - 0x20],
+    }
+}
+
+/// Size of the area covered by `text` when rendered with `draw_text()`
+///
+/// Lines are separated by `\n`.
+pub fn text_size(text: &str, scale: u32) -> (u32, u32) {
+    let scale = scale.max(1);
+
+    let lines = text.split('\n');
+    let line_count = lines.clone().count() as u32;
+    let max_chars = lines.map(|line| line.chars().count()).max().unwrap_or(0) as u32;
+
+    let width = (max_chars * (GLYPH_WIDTH + 1)).saturating_sub(1) * scale;
+    let height = (line_count * (GLYPH_HEIGHT + 1) - 1) * scale;
+
+    (width, height)
+}
+
+/// Draw text with a builtin 5x7 pixel font
+///
+/// `x` and `y` are the top left corner of the first character.
+/// Every font pixel is drawn as a `scale`x`scale` square. Characters outside of printable ascii are drawn as `?`.
+pub fn draw_text<C: Color>(
+    buffer: &mut PixelBuffer<C>,
+    x: i32,
+    y: i32,
+    text: &str,
+    color: C,
+    scale: u32,
+) {
+    let scale = scale.max(1);
+    let advance_x = ((GLYPH_WIDTH + 1) * scale) as i32;
+    let advance_y = ((GLYPH_HEIGHT + 1) * scale) as i32;
+
+    for (line_index, line) in text.split('\n').enumerate() {
+        let line_y = y + line_index as i32 * advance_y;
+
+        for (char_index, c) in line.chars().enumerate() {
+            let char_x = x + char_index as i32 * advance_x;
+
+            for (col, bits) in glyph(c).iter().enumerate() {
+                for row in 0..GLYPH_HEIGHT {
+                    if bits & (1 << row) == 0 {
+                        continue;
+                    }
+
+                    let px = char_x + col as i32 * scale as i32;
+                    let py = line_y + row as i32 * scale as i32;
+
+                    if px + (scale as i32) <= 0 || py + (scale as i32) <= 0 {
+                        continue;
+                    }
+
+                    let left = px.max(0) as u32;
+                    let top = py.max(0) as u32;
+                    let width = (px + scale as i32) as u32 - left;
+                    let height = (py + scale as i32) as u32 - top;
+
+                    buffer.fill_rect(Region::new(left, top, width, height), color);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use d10_core::color::Rgb;
+
+    use super::*;
+
+    #[test]
+    fn test_text_size() {
+        assert_eq!(text_size("A", 1), (5, 7));
+        assert_eq!(text_size("AB", 1), (11, 7));
+        assert_eq!(text_size("AB\nC", 2), (22, 30));
+        assert_eq!(text_size("", 1), (0, 7));
+    }
+
+    #[test]
+    fn test_draw_text() {
+        let mut buffer = PixelBuffer::new_with_color(12, 9, Rgb::BLACK);
+
+        draw_text(&mut buffer, 1, 1, "I", Rgb::WHITE, 1);
+
+        // The vertical bar of the I
+        for y in 1..8 {
+            assert_eq!(buffer.get_pixel(3, y), &Rgb::WHITE);
+        }
+        assert_eq!(buffer.get_pixel(1, 4), &Rgb::BLACK);
+        assert_eq!(buffer.get_pixel(3, 8), &Rgb::BLACK);
+
+        let set = buffer.data().iter().filter(|c| **c == Rgb::WHITE).count();
+        assert_eq!(set, 11);
+    }
+
+    #[test]
+    fn test_draw_text_clipped() {
+        let mut buffer = PixelBuffer::new_with_color(4, 4, Rgb::BLACK);
+
+        draw_text(&mut buffer, -8, -3, "H\u{e4}llo", Rgb::WHITE, 2);
+        draw_text(&mut buffer, 100, 100, "Hello", Rgb::WHITE, 2);
+
+        assert!(buffer.data().contains(&Rgb::WHITE));
+    }
+}
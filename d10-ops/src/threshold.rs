@@ -0,0 +1,178 @@
+use d10_core::color::{Color, Rgb};
+use d10_core::pixelbuffer::PixelBuffer;
+
+/// Gamma encoded Rec. 709 luma of a color
+pub(crate) fn srgb_luma(c: &Rgb) -> f32 {
+    let c = c.to_srgb();
+    c.data[0] * 0.212_656 + c.data[1] * 0.715_158 + c.data[2] * 0.072_186
+}
+
+/// Convert into a black and white image with all pixels having a luma above `value` being white
+pub fn threshold(buffer: &PixelBuffer<Rgb>, value: f32) -> PixelBuffer<Rgb> {
+    buffer.map_colors(|c| {
+        let v = if srgb_luma(c) > value { 1.0 } else { 0.0 };
+        Rgb::new_with_alpha(v, v, v, c.alpha())
+    })
+}
+
+/// Find the luma threshold that best separates the image into a dark and a bright class
+///
+/// Uses Otsu's method which maximizes the variance between both classes. The result can be
+/// passed to `threshold()`.
+pub fn otsu_threshold(buffer: &PixelBuffer<Rgb>) -> f32 {
+    let mut histogram = [0u32; 256];
+    for c in buffer.data() {
+        histogram[(srgb_luma(c).clamp(0.0, 1.0) * 255.0).round() as usize] += 1;
+    }
+
+    let total = buffer.data().len() as f64;
+    let sum: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(i, count)| i as f64 * *count as f64)
+        .sum();
+
+    let mut best = 0;
+    let mut best_variance = -1.0;
+    let mut weight_dark = 0.0;
+    let mut sum_dark = 0.0;
+
+    for (i, count) in histogram.iter().enumerate() {
+        weight_dark += *count as f64;
+        sum_dark += i as f64 * *count as f64;
+
+        let weight_bright = total - weight_dark;
+        if weight_dark == 0.0 || weight_bright == 0.0 {
+            continue;
+        }
+
+        let mean_dark = sum_dark / weight_dark;
+        let mean_bright = (sum - sum_dark) / weight_bright;
+        let variance = weight_dark * weight_bright * (mean_dark - mean_bright).powi(2);
+
+        if variance > best_variance {
+            best_variance = variance;
+            best = i;
+        }
+    }
+
+    (best as f32 + 0.5) / 255.0
+}
+
+/// Boolean version of `adaptive_threshold()` with `true` for white pixels in row major order
+pub(crate) fn adaptive_threshold_mask(
+    buffer: &PixelBuffer<Rgb>,
+    radius: u32,
+    offset: f32,
+) -> Vec<bool> {
+    let width = buffer.width() as usize;
+    let height = buffer.height() as usize;
+
+    let luma: Vec<f32> = buffer.data().iter().map(srgb_luma).collect();
+
+    // Summed area table with an additional leading row and column of zeros
+    let mut sums = vec![0.0f64; (width + 1) * (height + 1)];
+    for y in 0..height {
+        let mut row_sum = 0.0;
+        for x in 0..width {
+            row_sum += luma[y * width + x] as f64;
+            sums[(y + 1) * (width + 1) + x + 1] = sums[y * (width + 1) + x + 1] + row_sum;
+        }
+    }
+
+    let radius = radius as usize;
+
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let x1 = x.saturating_sub(radius);
+            let y1 = y.saturating_sub(radius);
+            let x2 = (x + radius + 1).min(width);
+            let y2 = (y + radius + 1).min(height);
+
+            let sum = sums[y2 * (width + 1) + x2]
+                - sums[y1 * (width + 1) + x2]
+                - sums[y2 * (width + 1) + x1]
+                + sums[y1 * (width + 1) + x1];
+            let mean = sum / ((x2 - x1) * (y2 - y1)) as f64;
+
+            luma[y * width + x] as f64 > mean - offset as f64
+        })
+        .collect()
+}
+
+/// Convert into a black and white image by comparing every pixel with the mean of its neighborhood
+///
+/// Pixels are white if their luma is above the mean of the surrounding `(2 * radius + 1)` square
+/// minus `offset`. Unlike a global threshold this handles uneven lighting like shadows on
+/// scanned documents.
+pub fn adaptive_threshold(buffer: &PixelBuffer<Rgb>, radius: u32, offset: f32) -> PixelBuffer<Rgb> {
+    let mask = adaptive_threshold_mask(buffer, radius, offset);
+
+    let data = buffer
+        .data()
+        .iter()
+        .zip(mask)
+        .map(|(c, white)| {
+            let v = if white { 1.0 } else { 0.0 };
+            Rgb::new_with_alpha(v, v, v, c.alpha())
+        })
+        .collect();
+
+    PixelBuffer::new_from_raw(buffer.width(), buffer.height(), data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threshold() {
+        let buffer = PixelBuffer::new_from_raw(
+            3,
+            1,
+            vec![Rgb::new(0.1, 0.1, 0.1), Rgb::new(0.5, 0.5, 0.5), Rgb::WHITE],
+        );
+
+        let res = threshold(&buffer, 0.5);
+
+        assert_eq!(res.data(), &[Rgb::BLACK, Rgb::WHITE, Rgb::WHITE]);
+    }
+
+    #[test]
+    fn test_otsu_threshold() {
+        let buffer = PixelBuffer::new_from_func(10, 10, |x, _| {
+            if x < 3 {
+                Rgb::new(0.05, 0.05, 0.05)
+            } else {
+                Rgb::new(0.6, 0.6, 0.6)
+            }
+        });
+
+        let value = otsu_threshold(&buffer);
+        let res = threshold(&buffer, value);
+
+        assert_eq!(res.get_pixel(2, 0), &Rgb::BLACK);
+        assert_eq!(res.get_pixel(3, 0), &Rgb::WHITE);
+    }
+
+    #[test]
+    fn test_adaptive_threshold() {
+        // Dark text on a background getting darker from left to right
+        let buffer = PixelBuffer::new_from_func(40, 10, |x, y| {
+            let background = 1.0 - x as f32 / 60.0;
+            if x % 8 == 4 && y > 2 && y < 7 {
+                Rgb::new(background * 0.3, background * 0.3, background * 0.3)
+            } else {
+                Rgb::new(background, background, background)
+            }
+        });
+
+        let res = adaptive_threshold(&buffer, 3, 0.05);
+
+        assert_eq!(res.get_pixel(4, 5), &Rgb::BLACK);
+        assert_eq!(res.get_pixel(36, 5), &Rgb::BLACK);
+        assert_eq!(res.get_pixel(2, 5), &Rgb::WHITE);
+        assert_eq!(res.get_pixel(38, 1), &Rgb::WHITE);
+    }
+}
@@ -0,0 +1,325 @@
+use std::fmt::Write;
+
+use d10_core::color::{Color, Rgb};
+use d10_core::pixelbuffer::PixelBuffer;
+
+use crate::contours::{find_contours, Contour};
+
+/// Options to control how a bitmap gets converted into vector paths
+#[derive(Copy, Clone, Debug)]
+pub struct TraceOptions {
+    /// Contours enclosing less pixels get dropped to remove speckles
+    pub min_area: f32,
+    /// Maximal distance of the simplified outlines from the pixel borders
+    pub tolerance: f32,
+    /// Turns sharper than this angle in degrees are kept as corners instead of getting smoothed
+    pub corner_angle: f32,
+    /// Trace dark instead of bright pixels
+    pub invert: bool,
+    /// Fill color of the paths
+    pub color: Rgb,
+}
+
+impl Default for TraceOptions {
+    fn default() -> Self {
+        TraceOptions {
+            min_area: 2.0,
+            tolerance: 1.0,
+            corner_angle: 60.0,
+            invert: false,
+            color: Rgb::BLACK,
+        }
+    }
+}
+
+/// Segment of a vector path
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PathSegment {
+    MoveTo(f32, f32),
+    LineTo(f32, f32),
+    /// Quadratic bezier curve with a control point and an end point
+    QuadTo(f32, f32, f32, f32),
+    Close,
+}
+
+/// Filled vector path that can consist of multiple closed sub paths
+#[derive(Clone, Debug)]
+pub struct SvgPath {
+    pub segments: Vec<PathSegment>,
+    pub color: Rgb,
+}
+
+/// Vector image that can be serialized to SVG
+#[derive(Clone, Debug)]
+pub struct SvgDocument {
+    pub width: u32,
+    pub height: u32,
+    pub paths: Vec<SvgPath>,
+}
+
+fn format_number(value: f32) -> String {
+    format!("{}", (value * 100.0).round() / 100.0)
+}
+
+fn format_color(color: &Rgb) -> String {
+    let c = color.to_srgb();
+    let value = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        value(c.red()),
+        value(c.green()),
+        value(c.blue())
+    )
+}
+
+impl SvgPath {
+    /// Path data as used in the `d` attribute of a SVG path element
+    pub fn to_path_data(&self) -> String {
+        let mut data = String::new();
+
+        for segment in &self.segments {
+            if !data.is_empty() {
+                data.push(' ');
+            }
+
+            match segment {
+                PathSegment::MoveTo(x, y) => {
+                    let _ = write!(data, "M{} {}", format_number(*x), format_number(*y));
+                }
+                PathSegment::LineTo(x, y) => {
+                    let _ = write!(data, "L{} {}", format_number(*x), format_number(*y));
+                }
+                PathSegment::QuadTo(cx, cy, x, y) => {
+                    let _ = write!(
+                        data,
+                        "Q{} {} {} {}",
+                        format_number(*cx),
+                        format_number(*cy),
+                        format_number(*x),
+                        format_number(*y)
+                    );
+                }
+                PathSegment::Close => data.push('Z'),
+            }
+        }
+
+        data
+    }
+}
+
+impl SvgDocument {
+    /// Serialize the document as SVG
+    pub fn to_svg(&self) -> String {
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n",
+            self.width, self.height
+        );
+
+        for path in &self.paths {
+            let _ = write!(
+                svg,
+                "<path d=\"{}\" fill=\"{}\" fill-rule=\"evenodd\"",
+                path.to_path_data(),
+                format_color(&path.color)
+            );
+
+            if path.color.alpha() < 1.0 {
+                let _ = write!(
+                    svg,
+                    " fill-opacity=\"{}\"",
+                    format_number(path.color.alpha())
+                );
+            }
+
+            svg.push_str("/>\n");
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+/// Angle in degrees between the incoming and the outgoing segment at `p`
+fn turn_angle(prev: (f32, f32), p: (f32, f32), next: (f32, f32)) -> f32 {
+    let (ax, ay) = (p.0 - prev.0, p.1 - prev.1);
+    let (bx, by) = (next.0 - p.0, next.1 - p.1);
+
+    let len = ax.hypot(ay) * bx.hypot(by);
+    if len == 0.0 {
+        return 0.0;
+    }
+
+    ((ax * bx + ay * by) / len)
+        .clamp(-1.0, 1.0)
+        .acos()
+        .to_degrees()
+}
+
+/// Convert a closed polygon into a smooth outline
+///
+/// The curves run through the centers of the edges and use the vertices as control points,
+/// while sharp corners are connected with straight lines.
+fn smooth_contour(contour: &Contour, corner_angle: f32, segments: &mut Vec<PathSegment>) {
+    let points = &contour.points;
+    let len = points.len();
+
+    let mid = |i: usize| {
+        let (a, b) = (points[i % len], points[(i + 1) % len]);
+        ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0)
+    };
+
+    let corners: Vec<bool> = (0..len)
+        .map(|i| {
+            let prev = points[(i + len - 1) % len];
+            let next = points[(i + 1) % len];
+            turn_angle(prev, points[i], next) > corner_angle
+        })
+        .collect();
+
+    let start = mid(len - 1);
+    segments.push(PathSegment::MoveTo(start.0, start.1));
+
+    for (i, p) in points.iter().enumerate() {
+        let m = mid(i);
+
+        if corners[i] {
+            segments.push(PathSegment::LineTo(p.0, p.1));
+
+            // The center of an edge between two corners is on a straight line anyway
+            if !corners[(i + 1) % len] {
+                segments.push(PathSegment::LineTo(m.0, m.1));
+            }
+        } else {
+            segments.push(PathSegment::QuadTo(p.0, p.1, m.0, m.1));
+        }
+    }
+
+    segments.push(PathSegment::Close);
+}
+
+/// Convert the regions of bright pixels into smooth vector paths
+///
+/// The outlines get simplified with the Douglas-Peucker algorithm and smoothed with bezier
+/// curves except at sharp corners, similar to potrace. Holes are kept as sub paths.
+pub fn trace_bitmap(buffer: &PixelBuffer<Rgb>, options: &TraceOptions) -> SvgDocument {
+    let contours = if options.invert {
+        find_contours(&buffer.map_colors(|c| c.invert()))
+    } else {
+        find_contours(buffer)
+    };
+
+    let mut segments = vec![];
+
+    for contour in contours {
+        if contour.area().abs() < options.min_area {
+            continue;
+        }
+
+        let simplified = contour.simplify(options.tolerance);
+
+        // Outlines that collapsed into a line keep their pixel corners
+        let contour = if simplified.points.len() < 3 || simplified.area() == 0.0 {
+            contour
+        } else {
+            simplified
+        };
+
+        smooth_contour(&contour, options.corner_angle, &mut segments);
+    }
+
+    let paths = if segments.is_empty() {
+        vec![]
+    } else {
+        vec![SvgPath {
+            segments,
+            color: options.color,
+        }]
+    };
+
+    SvgDocument {
+        width: buffer.width(),
+        height: buffer.height(),
+        paths,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rectangle() -> PixelBuffer<Rgb> {
+        PixelBuffer::new_from_func(6, 6, |x, y| {
+            if (1..5).contains(&x) && (2..4).contains(&y) {
+                Rgb::WHITE
+            } else {
+                Rgb::BLACK
+            }
+        })
+    }
+
+    #[test]
+    fn test_trace_rectangle() {
+        let doc = trace_bitmap(&rectangle(), &TraceOptions::default());
+
+        assert_eq!(doc.paths.len(), 1);
+        assert_eq!(doc.paths[0].to_path_data(), "M1 3 L1 2 L5 2 L5 4 L1 4 Z");
+    }
+
+    #[test]
+    fn test_trace_disk() {
+        let buffer = PixelBuffer::new_from_func(40, 40, |x, y| {
+            if (x as f32 - 19.5).hypot(y as f32 - 19.5) < 15.0 {
+                Rgb::BLACK
+            } else {
+                Rgb::WHITE
+            }
+        });
+
+        let options = TraceOptions {
+            invert: true,
+            ..Default::default()
+        };
+
+        let doc = trace_bitmap(&buffer, &options);
+        let segments = &doc.paths[0].segments;
+
+        assert!(segments
+            .iter()
+            .all(|s| !matches!(s, PathSegment::LineTo(..))));
+        assert!(segments.len() < 40);
+    }
+
+    #[test]
+    fn test_min_area() {
+        let mut buffer = rectangle();
+        buffer.put_pixel(0, 5, Rgb::WHITE);
+
+        let doc = trace_bitmap(&buffer, &TraceOptions::default());
+        assert_eq!(
+            doc.paths[0]
+                .segments
+                .iter()
+                .filter(|s| matches!(s, PathSegment::MoveTo(..)))
+                .count(),
+            1
+        );
+
+        let doc = trace_bitmap(&PixelBuffer::new(3, 3), &TraceOptions::default());
+        assert!(doc.paths.is_empty());
+    }
+
+    #[test]
+    fn test_to_svg() {
+        let options = TraceOptions {
+            color: Rgb::new_with_alpha(1.0, 0.0, 0.0, 0.5),
+            ..Default::default()
+        };
+
+        let svg = trace_bitmap(&rectangle(), &options).to_svg();
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"6\""));
+        assert!(svg.contains("fill=\"#ff0000\" fill-rule=\"evenodd\" fill-opacity=\"0.5\""));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+}
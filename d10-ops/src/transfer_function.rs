@@ -0,0 +1,88 @@
+use d10_core::color::{gamma_to_linear, Color, Rgb, TransferFunction};
+use d10_core::pixelbuffer::PixelBuffer;
+
+/// Linearize an image whose values are encoded with another transfer function than sRGB
+///
+/// All decoders assume sRGB encoded values. Frames extracted from videos or HDR sources stored
+/// in normal image files end up with wrong colors that way. This reinterprets the values as
+/// encoded with `transfer` and converts them into correct linear values.
+/// HDR highlights above the reference white are clipped.
+pub fn decode_transfer(buffer: &PixelBuffer<Rgb>, transfer: TransferFunction) -> PixelBuffer<Rgb> {
+    if transfer == TransferFunction::Srgb {
+        return buffer.clone();
+    }
+
+    buffer.map_colors(|c| {
+        let s = c.to_srgb();
+
+        Rgb::new_with_alpha(
+            transfer.to_linear(s.data[0]),
+            transfer.to_linear(s.data[1]),
+            transfer.to_linear(s.data[2]),
+            c.alpha(),
+        )
+    })
+}
+
+/// Encode an image with another transfer function than sRGB
+///
+/// This is the inverse of `decode_transfer()`. The values are stored in a way that encoders
+/// writing sRGB values write the values encoded with `transfer` instead.
+pub fn encode_transfer(buffer: &PixelBuffer<Rgb>, transfer: TransferFunction) -> PixelBuffer<Rgb> {
+    if transfer == TransferFunction::Srgb {
+        return buffer.clone();
+    }
+
+    buffer.map_colors(|c| {
+        c.map_channels(|v| gamma_to_linear(transfer.from_linear(v).clamp(0.0, 1.0)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use d10_core::color::Srgb;
+
+    #[test]
+    fn test_decode_transfer() {
+        let buffer = PixelBuffer::new_with_color(2, 2, Srgb::new(0.5, 0.75, 1.0).to_rgb());
+
+        assert_eq!(
+            decode_transfer(&buffer, TransferFunction::Srgb).data(),
+            buffer.data()
+        );
+
+        let result = decode_transfer(&buffer, TransferFunction::Linear);
+        let c = result.get_pixel(0, 0);
+        assert!((c.red() - 0.5).abs() < 0.0001);
+        assert!((c.green() - 0.75).abs() < 0.0001);
+
+        let result = decode_transfer(&buffer, TransferFunction::Hlg);
+        let c = result.get_pixel(0, 0);
+        assert!((c.green() - 1.0).abs() < 0.0001);
+        assert!(c.red() < 0.5);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let buffer = PixelBuffer::new_from_func(8, 1, |x, _| {
+            let v = x as f32 / 8.0;
+            Rgb::new(v, v * 0.5, 1.0 - v)
+        });
+
+        for transfer in [
+            TransferFunction::Linear,
+            TransferFunction::Rec709,
+            TransferFunction::Pq,
+            TransferFunction::Hlg,
+        ] {
+            let result = decode_transfer(&encode_transfer(&buffer, transfer), transfer);
+
+            assert!(
+                buffer.max_channel_difference(&result) < 0.001,
+                "{:?}",
+                transfer
+            );
+        }
+    }
+}
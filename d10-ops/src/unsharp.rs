@@ -2,12 +2,17 @@ use d10_core::color::Rgb;
 use d10_core::kernel_dyn::KernelDyn;
 use d10_core::pixelbuffer::PixelBuffer;
 
+/// Sharpen by adding the difference to a gaussian blurred version
+///
+/// NaN and infinite values are replaced with `PixelBuffer::sanitize()` before sharpening.
 pub fn unsharp(
     buffer: &PixelBuffer<Rgb>,
     radius: u32,
     factor: f32,
     sigma: Option<f32>,
 ) -> PixelBuffer<Rgb> {
+    let buffer = buffer.sanitized();
+
     let kernel_size = radius * 2 + 1;
 
     let sigma = sigma.unwrap_or_else(|| crate::gaussian_blur::get_default_sigma(kernel_size));
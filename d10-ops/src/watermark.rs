@@ -0,0 +1,108 @@
+use d10_core::color::Rgb;
+use d10_core::errors::ParseEnumError;
+use d10_core::pixelbuffer::PixelBuffer;
+use std::str::FromStr;
+
+use crate::{blend_normal, resize, FilterMode};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WatermarkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+    Tile,
+}
+
+impl FromStr for WatermarkPosition {
+    type Err = ParseEnumError;
+
+    fn from_str(value: &str) -> Result<WatermarkPosition, Self::Err> {
+        use WatermarkPosition::*;
+        match value {
+            "top_left" => Ok(TopLeft),
+            "top_right" => Ok(TopRight),
+            "bottom_left" => Ok(BottomLeft),
+            "bottom_right" | "default" => Ok(BottomRight),
+            "center" => Ok(Center),
+            "tile" => Ok(Tile),
+            _ => Err(ParseEnumError::new(value, "WatermarkPosition")),
+        }
+    }
+}
+
+/// Scale the watermark to the given relative width of the target
+fn scale_mark(buffer: &PixelBuffer<Rgb>, mark: &PixelBuffer<Rgb>, scale: f32) -> PixelBuffer<Rgb> {
+    let width = (buffer.width() as f32 * scale).round().max(1.0) as u32;
+    let height = (mark.height() as f32 * width as f32 / mark.width() as f32)
+        .round()
+        .max(1.0) as u32;
+
+    resize(mark, width, height, FilterMode::Auto)
+}
+
+/// Add a watermark to the image
+///
+/// # Arguments
+/// scale: Width of the watermark relative to the width of the image. If `None` the watermark is not scaled
+/// opacity: Value between 0.0 and 1.0 applied to the alpha channel of the watermark
+/// margin: Distance to the image borders in pixels or the spacing between tiles
+pub fn watermark(
+    buffer: &PixelBuffer<Rgb>,
+    mark: &PixelBuffer<Rgb>,
+    position: WatermarkPosition,
+    scale: Option<f32>,
+    opacity: f32,
+    margin: u32,
+) -> PixelBuffer<Rgb> {
+    if buffer.is_empty() || mark.is_empty() {
+        return buffer.clone();
+    }
+
+    let scaled;
+    let mark = match scale {
+        Some(scale) if scale > 0.0 => {
+            scaled = scale_mark(buffer, mark, scale);
+            &scaled
+        }
+        _ => mark,
+    };
+
+    let width = buffer.width() as i32;
+    let height = buffer.height() as i32;
+    let mark_width = mark.width() as i32;
+    let mark_height = mark.height() as i32;
+    let margin = margin as i32;
+
+    if position == WatermarkPosition::Tile {
+        let step_x = (mark_width + margin) as u32;
+        let step_y = (mark_height + margin) as u32;
+
+        return buffer.map_colors_enumerated(|x, y, c| {
+            match mark.get_pixel_optional((x % step_x) as i32, (y % step_y) as i32) {
+                Some(m) => blend_normal(*c, *m, opacity),
+                None => *c,
+            }
+        });
+    }
+
+    let (offset_x, offset_y) = match position {
+        WatermarkPosition::TopLeft => (margin, margin),
+        WatermarkPosition::TopRight => (width - mark_width - margin, margin),
+        WatermarkPosition::BottomLeft => (margin, height - mark_height - margin),
+        WatermarkPosition::BottomRight => {
+            (width - mark_width - margin, height - mark_height - margin)
+        }
+        WatermarkPosition::Center | WatermarkPosition::Tile => {
+            ((width - mark_width) / 2, (height - mark_height) / 2)
+        }
+    };
+
+    buffer.map_colors_enumerated(|x, y, c| {
+        match mark.get_pixel_optional(x as i32 - offset_x, y as i32 - offset_y) {
+            Some(m) => blend_normal(*c, *m, opacity),
+            None => *c,
+        }
+    })
+}
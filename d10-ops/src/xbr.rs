@@ -0,0 +1,326 @@
+use d10_core::color::{Color, Rgb};
+use d10_core::errors::ParseEnumError;
+use d10_core::pixelbuffer::PixelBuffer;
+
+use std::str::FromStr;
+
+/// Colors with a smaller difference in YUV are treated as part of the same area
+const EQUAL_THRESHOLD: f32 = 155.0 / 255.0;
+
+/// Scale factor of the xBR filter
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum XbrScale {
+    X2,
+    X3,
+    X4,
+}
+
+impl XbrScale {
+    pub fn factor(self) -> u32 {
+        match self {
+            XbrScale::X2 => 2,
+            XbrScale::X3 => 3,
+            XbrScale::X4 => 4,
+        }
+    }
+}
+
+impl FromStr for XbrScale {
+    type Err = ParseEnumError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        use XbrScale::*;
+        match value {
+            "2x" | "default" => Ok(X2),
+            "3x" => Ok(X3),
+            "4x" => Ok(X4),
+            _ => Err(ParseEnumError::new(value, "XbrScale")),
+        }
+    }
+}
+
+/// How the corner of a scaled pixel gets blended with the color on the other side of an edge
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Edge {
+    /// Soft edge that only gets smoothed at the corner
+    Soft,
+    Diagonal,
+    /// Shallow edge running along the bottom
+    Left,
+    /// Steep edge running along the right side
+    Up,
+    /// Edge that is both shallow and steep, i.e. a single pixel corner
+    LeftUp,
+}
+
+fn yuv(c: &Rgb) -> [f32; 4] {
+    let [r, g, b, a] = c.to_srgb().data;
+
+    [
+        0.299 * r + 0.587 * g + 0.114 * b,
+        -0.169 * r - 0.331 * g + 0.5 * b,
+        0.5 * r - 0.419 * g - 0.081 * b,
+        a,
+    ]
+}
+
+fn blend(c1: &Rgb, c2: &Rgb, t: f32) -> Rgb {
+    let mut data = c1.data;
+    for (v1, v2) in data.iter_mut().zip(c2.data) {
+        *v1 += (v2 - *v1) * t;
+    }
+    Rgb { data }
+}
+
+/// Scale pixel art by detecting edges and blending only the pixels along them
+///
+/// This is the xBR algorithm by Hyllian. Unlike interpolating filters areas of the same color
+/// keep their exact color and diagonal lines don't get jagged like with nearest neighbor.
+pub fn xbr(buffer: &PixelBuffer<Rgb>, scale: XbrScale) -> PixelBuffer<Rgb> {
+    if buffer.is_empty() {
+        return buffer.clone();
+    }
+
+    let n = scale.factor() as i32;
+    let width = buffer.width() as i32;
+    let height = buffer.height() as i32;
+
+    let colors = buffer.data();
+    let yuv: Vec<_> = colors.iter().map(yuv).collect();
+
+    let df = |a: usize, b: usize| {
+        yuv[a]
+            .iter()
+            .zip(&yuv[b])
+            .map(|(v1, v2)| (v1 - v2).abs())
+            .sum::<f32>()
+    };
+    let eq = |a: usize, b: usize| df(a, b) < EQUAL_THRESHOLD;
+    let same = |a: usize, b: usize| colors[a] == colors[b];
+
+    let mut result = PixelBuffer::new(buffer.width() * n as u32, buffer.height() * n as u32);
+    let mut block = vec![Rgb::NONE; (n * n) as usize];
+
+    for y in 0..height {
+        for x in 0..width {
+            block.fill(colors[(y * width + x) as usize]);
+
+            // The filter is written for the bottom right corner and rotated for the others
+            for rotation in 0..4 {
+                let rotate = |mut dx: i32, mut dy: i32| {
+                    for _ in 0..rotation {
+                        (dx, dy) = (dy, -dx);
+                    }
+                    (dx, dy)
+                };
+
+                let p = |dx: i32, dy: i32| {
+                    let (dx, dy) = rotate(dx, dy);
+                    let x = (x + dx).clamp(0, width - 1);
+                    let y = (y + dy).clamp(0, height - 1);
+                    (y * width + x) as usize
+                };
+
+                let (pe, pf, ph, pi) = (p(0, 0), p(1, 0), p(0, 1), p(1, 1));
+                let (pb, pc, pd, pg) = (p(0, -1), p(1, -1), p(-1, 0), p(-1, 1));
+                let (f4, i4, h5, i5) = (p(2, 0), p(2, 1), p(0, 2), p(1, 2));
+
+                if same(pe, ph) || same(pe, pf) {
+                    continue;
+                }
+
+                let e = df(pe, pc) + df(pe, pg) + df(pi, h5) + df(pi, f4) + 4.0 * df(ph, pf);
+                let i = df(ph, pd) + df(ph, i5) + df(pf, i4) + df(pf, pb) + 4.0 * df(pe, pi);
+
+                if e > i {
+                    continue;
+                }
+
+                let px = colors[if df(pe, pf) <= df(pe, ph) { pf } else { ph }];
+
+                let sharp = e < i
+                    && match scale {
+                        XbrScale::X3 => {
+                            !eq(pf, pb) && !eq(pf, pc)
+                                || !eq(ph, pd) && !eq(ph, pg)
+                                || eq(pe, pi)
+                                    && (!eq(pf, f4) && !eq(pf, i4) || !eq(ph, h5) && !eq(ph, i5))
+                                || eq(pe, pg)
+                                || eq(pe, pc)
+                        }
+                        _ => {
+                            !eq(pf, pb) && !eq(ph, pd)
+                                || eq(pe, pi) && !eq(pf, i4) && !eq(ph, i5)
+                                || eq(pe, pg)
+                                || eq(pe, pc)
+                        }
+                    };
+
+                let edge = if sharp {
+                    let ke = df(pf, pg);
+                    let ki = df(ph, pc);
+                    let left = 2.0 * ke <= ki && !same(pe, pg) && !same(pd, pg);
+                    let up = ke >= 2.0 * ki && !same(pe, pc) && !same(pb, pc);
+
+                    match (left, up) {
+                        (true, true) => Edge::LeftUp,
+                        (true, false) => Edge::Left,
+                        (false, true) => Edge::Up,
+                        (false, false) => Edge::Diagonal,
+                    }
+                } else {
+                    Edge::Soft
+                };
+
+                // Index of a cell of the block with (0, 0) being the top left of the unrotated block
+                let cell = |cx: i32, cy: i32| {
+                    let (u, v) = rotate(2 * cx - (n - 1), 2 * cy - (n - 1));
+                    ((v + n - 1) / 2 * n + (u + n - 1) / 2) as usize
+                };
+
+                blend_corner(&mut block, &cell, scale, edge, &px);
+            }
+
+            for (i, c) in block.iter().enumerate() {
+                let i = i as i32;
+                result.put_pixel((x * n + i % n) as u32, (y * n + i / n) as u32, *c);
+            }
+        }
+    }
+
+    result
+}
+
+/// Blend the cells of the bottom right corner of a block with `px`
+fn blend_corner<F>(block: &mut [Rgb], cell: &F, scale: XbrScale, edge: Edge, px: &Rgb)
+where
+    F: Fn(i32, i32) -> usize,
+{
+    let mut mix = |cx: i32, cy: i32, t: f32| {
+        let i = cell(cx, cy);
+        block[i] = blend(&block[i], px, t);
+    };
+
+    match (scale, edge) {
+        (XbrScale::X2, Edge::Soft | Edge::Diagonal) => mix(1, 1, 0.5),
+        (XbrScale::X2, Edge::Left) => {
+            mix(1, 1, 0.75);
+            mix(0, 1, 0.25);
+        }
+        (XbrScale::X2, Edge::Up) => {
+            mix(1, 1, 0.75);
+            mix(1, 0, 0.25);
+        }
+        (XbrScale::X2, Edge::LeftUp) => {
+            mix(1, 1, 0.875);
+            mix(0, 1, 0.25);
+            block[cell(1, 0)] = block[cell(0, 1)];
+        }
+        (XbrScale::X3, Edge::Soft) => mix(2, 2, 0.5),
+        (XbrScale::X3, Edge::Diagonal) => {
+            mix(2, 2, 0.875);
+            mix(2, 1, 0.125);
+            mix(1, 2, 0.125);
+        }
+        (XbrScale::X3, Edge::Left) => {
+            mix(1, 2, 0.75);
+            mix(2, 1, 0.25);
+            mix(0, 2, 0.25);
+            mix(2, 2, 1.0);
+        }
+        (XbrScale::X3, Edge::Up) => {
+            mix(2, 1, 0.75);
+            mix(1, 2, 0.25);
+            mix(2, 0, 0.25);
+            mix(2, 2, 1.0);
+        }
+        (XbrScale::X3, Edge::LeftUp) => {
+            mix(1, 2, 0.75);
+            mix(0, 2, 0.25);
+            mix(2, 2, 1.0);
+            block[cell(2, 1)] = block[cell(1, 2)];
+            block[cell(2, 0)] = block[cell(0, 2)];
+        }
+        (XbrScale::X4, Edge::Soft) => mix(3, 3, 0.5),
+        (XbrScale::X4, Edge::Diagonal) => {
+            mix(3, 2, 0.5);
+            mix(2, 3, 0.5);
+            mix(3, 3, 1.0);
+        }
+        (XbrScale::X4, Edge::Left) => {
+            mix(3, 2, 0.75);
+            mix(1, 3, 0.75);
+            mix(2, 2, 0.25);
+            mix(0, 3, 0.25);
+            mix(2, 3, 1.0);
+            mix(3, 3, 1.0);
+        }
+        (XbrScale::X4, Edge::Up) => {
+            mix(2, 3, 0.75);
+            mix(3, 1, 0.75);
+            mix(2, 2, 0.25);
+            mix(3, 0, 0.25);
+            mix(3, 2, 1.0);
+            mix(3, 3, 1.0);
+        }
+        (XbrScale::X4, Edge::LeftUp) => {
+            mix(1, 3, 0.75);
+            mix(0, 3, 0.25);
+            mix(3, 3, 1.0);
+            mix(2, 3, 1.0);
+            mix(3, 2, 1.0);
+            block[cell(2, 2)] = block[cell(0, 3)];
+            block[cell(3, 0)] = block[cell(0, 3)];
+            block[cell(3, 1)] = block[cell(1, 3)];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xbr() {
+        for scale in [XbrScale::X2, XbrScale::X3, XbrScale::X4] {
+            let buffer = PixelBuffer::new_with_color(5, 4, Rgb::RED);
+            let result = xbr(&buffer, scale);
+
+            let n = scale.factor();
+            assert_eq!((result.width(), result.height()), (5 * n, 4 * n));
+            assert!(result.data().iter().all(|c| *c == Rgb::RED));
+        }
+
+        assert!(xbr(&PixelBuffer::new(0, 0), XbrScale::X2).is_empty());
+    }
+
+    #[test]
+    fn test_xbr_diagonal() {
+        // A diagonal line gets smoothed while the areas on both sides keep their colors
+        let buffer = PixelBuffer::new_from_func(8, 8, |x, y| {
+            let v = if x + y < 8 { 0.0 } else { 1.0 };
+            Rgb::new(v, v, v)
+        });
+
+        let result = xbr(&buffer, XbrScale::X2);
+
+        assert_eq!(result.get_pixel(0, 0), &Rgb::BLACK);
+        assert_eq!(result.get_pixel(15, 15), &Rgb::WHITE);
+
+        // Nearest neighbor would result in steps of two pixels
+        for y in 3..14 {
+            let edge = (0..16).find(|x| result.get_pixel(*x, y).red() > 0.0);
+            assert_eq!(edge, Some(16 - y), "Row {}", y);
+
+            let c = result.get_pixel(16 - y, y);
+            assert!(c.red() < 1.0, "Row {}: {:?}", y, c);
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("default".parse::<XbrScale>().unwrap(), XbrScale::X2);
+        assert_eq!("4x".parse::<XbrScale>().unwrap(), XbrScale::X4);
+        assert!("5x".parse::<XbrScale>().is_err());
+    }
+}
@@ -0,0 +1,194 @@
+use d10_core::color::{Color, Rgb};
+use d10_core::pixelbuffer::PixelBuffer;
+use d10_core::region::Region;
+
+use crate::threshold::srgb_luma;
+
+/// Luma values below this are counted as clipped shadows
+const SHADOW_CLIPPING: f32 = 0.5 / 255.0;
+
+/// Luma values above this are counted as clipped highlights
+const HIGHLIGHT_CLIPPING: f32 = 254.5 / 255.0;
+
+/// Luma statistics of a single cell of the zone grid
+///
+/// All values are gamma encoded lumas in the range 0 to 1.
+/// Fully transparent pixels are ignored and a cell without any other pixels has all values set to 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneStatistics {
+    /// Column of the cell in the grid
+    pub col: u32,
+    /// Row of the cell in the grid
+    pub row: u32,
+    /// Part of the image covered by this cell
+    pub region: Region,
+    /// Number of pixels that were taken into account
+    pub pixels: u32,
+    pub mean: f32,
+    pub std_dev: f32,
+    pub min: f32,
+    pub max: f32,
+    /// Percentage of pixels that are pure black
+    pub clipped_shadows: f32,
+    /// Percentage of pixels that are pure white
+    pub clipped_highlights: f32,
+}
+
+fn cell_bounds(size: u32, cells: u32, index: u32) -> (u32, u32) {
+    let start = (size as u64 * index as u64 / cells as u64) as u32;
+    let end = (size as u64 * (index as u64 + 1) / cells as u64) as u32;
+
+    (start, end)
+}
+
+fn cell_statistics(
+    buffer: &PixelBuffer<Rgb>,
+    col: u32,
+    row: u32,
+    region: Region,
+) -> ZoneStatistics {
+    let mut pixels = 0;
+    let mut sum = 0.0f64;
+    let mut sum_sq = 0.0f64;
+    let mut min = f32::INFINITY;
+    let mut max = f32::NEG_INFINITY;
+    let mut shadows = 0;
+    let mut highlights = 0;
+
+    for y in region.y..region.y + region.height {
+        for x in region.x..region.x + region.width {
+            let c = buffer.get_pixel(x, y);
+            if c.alpha() <= 0.0 {
+                continue;
+            }
+
+            let luma = srgb_luma(c).clamp(0.0, 1.0);
+
+            pixels += 1;
+            sum += luma as f64;
+            sum_sq += luma as f64 * luma as f64;
+            min = min.min(luma);
+            max = max.max(luma);
+
+            if luma < SHADOW_CLIPPING {
+                shadows += 1;
+            } else if luma > HIGHLIGHT_CLIPPING {
+                highlights += 1;
+            }
+        }
+    }
+
+    if pixels == 0 {
+        return ZoneStatistics {
+            col,
+            row,
+            region,
+            pixels,
+            mean: 0.0,
+            std_dev: 0.0,
+            min: 0.0,
+            max: 0.0,
+            clipped_shadows: 0.0,
+            clipped_highlights: 0.0,
+        };
+    }
+
+    let mean = sum / pixels as f64;
+    let variance = (sum_sq / pixels as f64 - mean * mean).max(0.0);
+
+    ZoneStatistics {
+        col,
+        row,
+        region,
+        pixels,
+        mean: mean as f32,
+        std_dev: variance.sqrt() as f32,
+        min,
+        max,
+        clipped_shadows: shadows as f32 * 100.0 / pixels as f32,
+        clipped_highlights: highlights as f32 * 100.0 / pixels as f32,
+    }
+}
+
+/// Compute luma statistics for every cell of a grid with `cols` columns and `rows` rows
+///
+/// The cells are returned in row major order. If the image size isn't a multiple of the grid
+/// size the cells differ by at most one pixel in size.
+pub fn zone_statistics(buffer: &PixelBuffer<Rgb>, cols: u32, rows: u32) -> Vec<ZoneStatistics> {
+    let cols = cols.clamp(1, buffer.width().max(1));
+    let rows = rows.clamp(1, buffer.height().max(1));
+
+    let mut zones = Vec::with_capacity((cols * rows) as usize);
+
+    for row in 0..rows {
+        let (y1, y2) = cell_bounds(buffer.height(), rows, row);
+
+        for col in 0..cols {
+            let (x1, x2) = cell_bounds(buffer.width(), cols, col);
+            let region = Region::new(x1, y1, x2 - x1, y2 - y1);
+
+            zones.push(cell_statistics(buffer, col, row, region));
+        }
+    }
+
+    zones
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zone_statistics() {
+        let buffer = PixelBuffer::new_from_func(4, 2, |x, y| {
+            if x >= 2 && y == 0 {
+                Rgb::WHITE
+            } else {
+                Rgb::BLACK
+            }
+        });
+
+        let zones = zone_statistics(&buffer, 2, 1);
+        assert_eq!(zones.len(), 2);
+
+        assert_eq!(zones[0].region, Region::new(0, 0, 2, 2));
+        assert_eq!(zones[0].pixels, 4);
+        assert_eq!(zones[0].mean, 0.0);
+        assert_eq!(zones[0].std_dev, 0.0);
+        assert_eq!(zones[0].clipped_shadows, 100.0);
+        assert_eq!(zones[0].clipped_highlights, 0.0);
+
+        assert_eq!((zones[1].col, zones[1].row), (1, 0));
+        assert_eq!(zones[1].region, Region::new(2, 0, 2, 2));
+        assert_eq!(zones[1].min, 0.0);
+        assert!(zones[1].max > 0.999);
+        assert!((zones[1].mean - 0.5).abs() < 0.001);
+        assert!((zones[1].std_dev - 0.5).abs() < 0.001);
+        assert_eq!(zones[1].clipped_shadows, 50.0);
+        assert_eq!(zones[1].clipped_highlights, 50.0);
+    }
+
+    #[test]
+    fn test_uneven_grid() {
+        let mut buffer = PixelBuffer::new_with_color(5, 3, Rgb::new(0.2, 0.2, 0.2));
+        buffer.put_pixel(4, 2, Rgb::NONE);
+
+        let zones = zone_statistics(&buffer, 2, 2);
+        assert_eq!(zones.len(), 4);
+
+        let covered: u32 = zones.iter().map(|z| z.region.width * z.region.height).sum();
+        assert_eq!(covered, 15);
+        assert_eq!(zones.iter().map(|z| z.pixels).sum::<u32>(), 14);
+
+        assert!(zones
+            .iter()
+            .all(|z| z.clipped_shadows == 0.0 && z.std_dev < 0.001));
+
+        // More cells than pixels are limited to the image size
+        assert_eq!(zone_statistics(&buffer, 10, 0).len(), 5);
+
+        let empty = zone_statistics(&PixelBuffer::new(2, 2), 1, 1);
+        assert_eq!(empty[0].pixels, 0);
+        assert_eq!(empty[0].mean, 0.0);
+    }
+}
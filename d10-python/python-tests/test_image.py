@@ -1,7 +1,9 @@
+import os
+import tempfile
 import unittest
 import numpy as np
 
-from d10 import Image, Rgb
+from d10 import EncodingFormat, Image, Palette, Rgb
 
 
 class TestImage(unittest.TestCase):
@@ -25,6 +27,55 @@ class TestImage(unittest.TestCase):
         self.assertEqual(image.get_pixel(0, 0), Rgb(0.0, 0.0, 1.0))
         self.assertEqual(image.get_pixel(1, 2), Rgb(1.0, 0.0, 1.0))
 
+    def test_pack_sprites(self):
+        sprites = [Image(4, 4, Rgb(1.0, 0.0, 0.0)), Image(3, 2, Rgb(0.0, 0.0, 1.0))]
+
+        sheet, placements = Image.pack_sprites(sprites, 16, padding=2)
+
+        self.assertEqual(placements, [(0, 0, 4, 4), (6, 0, 3, 2)])
+        self.assertEqual(sheet.width, 9)
+        self.assertEqual(sheet.height, 4)
+        self.assertEqual(sheet.get_pixel(7, 1), Rgb(0.0, 0.0, 1.0))
+
+    def test_montage(self):
+        images = [Image(4, 4, Rgb(1.0, 0.0, 0.0)), Image(2, 2, Rgb(0.0, 0.0, 1.0))]
+
+        res = Image.montage(images, 2, spacing=1)
+
+        self.assertEqual(res.width, 11)
+        self.assertEqual(res.height, 6)
+        self.assertEqual(res.get_pixel(1, 1), Rgb(1.0, 0.0, 0.0))
+        self.assertEqual(res.get_pixel(7, 2), Rgb(0.0, 0.0, 1.0))
+        self.assertEqual(res.get_pixel(6, 1), Rgb(1.0, 1.0, 1.0))
+
+    def test_diff_visualize(self):
+        image1 = Image(3, 2, Rgb(0.0, 0.0, 1.0))
+        image2 = Image(3, 2, Rgb(0.0, 0.0, 1.0))
+        image2.put_pixel(1, 1, Rgb(1.0, 0.0, 0.0))
+
+        res = image1.diff_visualize(image2)
+        self.assertEqual(res.get_pixel(1, 1), Rgb(1.0, 0.0, 0.0))
+
+        res = image1.diff_visualize(image2, mode="side_by_side")
+        self.assertGreater(res.width, 9)
+
+        with self.assertRaises(OSError):
+            image1.diff_visualize(image2, mode="unknown")
+
+    def test_draw_text(self):
+        image = Image(8, 9, Rgb(1.0, 1.0, 1.0))
+        image.draw_text(1, 1, "I")
+
+        self.assertEqual(image.get_pixel(3, 4), Rgb(0.0, 0.0, 0.0))
+        self.assertEqual(image.get_pixel(0, 0), Rgb(1.0, 1.0, 1.0))
+
+    def test_invalid_size(self):
+        with self.assertRaises(OSError):
+            Image(2 ** 32 - 1, 2 ** 32 - 1)
+
+        with self.assertRaises(OSError):
+            Image.from_list(2, 3, [Rgb(0.0, 0.0, 1.0)])
+
     def test_to_list(self):
         image = Image.from_list(2, 2, [
             Rgb(0.0, 0.0, 1.0), Rgb(1.0, 0.0, 1.0),
@@ -87,6 +138,16 @@ class TestImage(unittest.TestCase):
         image.put_pixel(0, 0, Rgb(1.0, 0.0, 0.0, 1.0))
         self.assertFalse(image.is_grayscale())
 
+    def test_approx_eq(self):
+        image = Image(4, 7, Rgb(1.0, 1.0, 1.0))
+        other = Image(4, 7, Rgb(1.0, 1.0, 1.0))
+
+        self.assertTrue(image.approx_eq(other, 0.0))
+        other.put_pixel(0, 0, Rgb(0.99, 1.0, 1.0))
+        self.assertTrue(image.approx_eq(other, 0.02))
+        self.assertFalse(image.approx_eq(other, 0.001))
+        self.assertFalse(image.approx_eq(Image(7, 4), 1.0))
+
     def test_mod_colors(self):
         image = Image(2, 2, Rgb(1.0, 1.0, 1.0))
 
@@ -204,6 +265,20 @@ class TestImage(unittest.TestCase):
         self.assertEqual(image.get_pixel(1, 1), Rgb(0.0, 1.0, 1.0))
         self.assertEqual(image.get_pixel(1, 0), Rgb(1.0, 0.0, 1.0))
 
+    def test_offset(self):
+        image = Image.from_list(2, 1, [
+            Rgb(0.0, 0.0, 1.0), Rgb(1.0, 0.0, 1.0),
+        ]).offset(1, 0)
+
+        self.assertEqual(image.get_pixel(0, 0), Rgb(1.0, 0.0, 1.0))
+        self.assertEqual(image.get_pixel(1, 0), Rgb(0.0, 0.0, 1.0))
+
+    def test_make_seamless(self):
+        image = Image(20, 10).make_seamless(4)
+
+        self.assertEqual(image.width, 16)
+        self.assertEqual(image.height, 6)
+
     def test_rotate(self):
         image = Image.from_list(2, 2, [
             Rgb(0.0, 0.0, 1.0), Rgb(1.0, 0.0, 1.0),
@@ -221,6 +296,40 @@ class TestImage(unittest.TestCase):
         self.assertEqual(image.width, 7)
         self.assertEqual(image.height, 5)
 
+    def test_resize_options(self):
+        image = Image(2, 3, Rgb(1, 0, 0, 0.5)).resize(7, 5, 'bilinear', False, True, 0.5)
+
+        self.assertEqual(image.width, 7)
+        self.assertEqual(image.height, 5)
+        self.assertEqual(image.get_pixel(3, 2), Rgb(1, 0, 0, 0.5))
+
+    def test_smart_crop(self):
+        image = Image(30, 10).smart_crop(16, 9)
+
+        self.assertEqual(image.width, 16)
+        self.assertEqual(image.height, 9)
+
+        image = Image(30, 10).smart_crop(10, 10, focus=[(0, 0, 5, 5)])
+        self.assertEqual(image.crop_region, (0, 0, 10, 10))
+
+    def test_upscale_enhanced(self):
+        image = Image(2, 3).upscale_enhanced(2)
+
+        self.assertEqual(image.width, 4)
+        self.assertEqual(image.height, 6)
+
+    def test_xbr(self):
+        image = Image(2, 3).xbr("4x")
+
+        self.assertEqual(image.width, 8)
+        self.assertEqual(image.height, 12)
+
+    def test_straighten(self):
+        image = Image(20, 10).straighten(5)
+
+        self.assertEqual(image.width, 20)
+        self.assertEqual(image.height, 10)
+
     def test_resize_pct(self):
         image = Image(2, 3).resize_pct(200)
 
@@ -239,6 +348,12 @@ class TestImage(unittest.TestCase):
         self.assertEqual(image.width, 2)
         self.assertEqual(image.height, 3)
 
+    def test_jpeg_artifact_map(self):
+        image = Image(2, 3).jpeg_artifact_map(70)
+
+        self.assertEqual(image.width, 2)
+        self.assertEqual(image.height, 3)
+
     def test_random_noise(self):
         image = Image(2, 3).random_noise(0.5)
 
@@ -291,12 +406,180 @@ class TestImage(unittest.TestCase):
         self.assertEqual(image.width, 2)
         self.assertEqual(image.height, 3)
 
+    def test_poisson_noise(self):
+        image = Image(2, 3).poisson_noise(100.0)
+
+        self.assertEqual(image.width, 2)
+        self.assertEqual(image.height, 3)
+
+    def test_add_poisson_noise(self):
+        image = Image(2, 3)
+        image.add_poisson_noise(100.0)
+
+        self.assertEqual(image.width, 2)
+        self.assertEqual(image.height, 3)
+
+    def test_augment(self):
+        image = Image(4, 3)
+        variants = image.augment(3, seed=1, crop=0.5, flip_horizontal=0.5, rotation=10.0, brightness=0.1,
+                                 noise=0.05, cutout=(1, 0.25))
+
+        self.assertEqual(len(variants), 3)
+
+        for variant in variants:
+            self.assertEqual(variant.width, 4)
+            self.assertEqual(variant.height, 3)
+
+    def test_content_hash(self):
+        image = Image(2, 3, Rgb(1.0, 0.0, 0.0))
+
+        self.assertEqual(len(image.content_hash()), 64)
+        self.assertEqual(len(image.content_hash("crc32")), 8)
+        self.assertEqual(image.content_hash(), Image(2, 3, Rgb(1.0, 0.0, 0.0)).content_hash())
+        self.assertNotEqual(image.content_hash(), Image(3, 2, Rgb(1.0, 0.0, 0.0)).content_hash())
+
+    def test_embed_data(self):
+        image = Image(10, 10).embed_data(b"d10", bits_per_channel=2)
+
+        self.assertEqual(image.extract_data(), b"d10")
+
+        with self.assertRaises(OSError):
+            Image(10, 10).extract_data()
+
+    def test_invisible_watermark(self):
+        image = Image(64, 64, Rgb(0.5, 0.4, 0.3))
+        marked = image.embed_invisible_watermark(42)
+
+        self.assertGreater(marked.detect_invisible_watermark(42), 6.0)
+        self.assertLess(marked.detect_invisible_watermark(43), 6.0)
+
+    def test_threshold(self):
+        image = Image.from_list(2, 1, [Rgb(0.1, 0.1, 0.1), Rgb(0.8, 0.8, 0.8)])
+
+        value = image.otsu_threshold()
+        self.assertGreater(value, 0.1)
+        self.assertLess(value, 0.8)
+
+        res = image.threshold()
+        self.assertEqual(res.get_pixel(0, 0), Rgb(0.0, 0.0, 0.0))
+        self.assertEqual(res.get_pixel(1, 0), Rgb(1.0, 1.0, 1.0))
+
+        res = image.adaptive_threshold(1, 0.05)
+        self.assertEqual(res.width, 2)
+
+    def test_perspective_warp(self):
+        image = Image(20, 20, Rgb(1.0, 0.0, 0.0))
+        res = image.perspective_warp([(2, 1), (18, 3), (17, 19), (1, 16)], 10, 8, filter="nearest")
+
+        self.assertEqual(res.width, 10)
+        self.assertEqual(res.height, 8)
+        self.assertEqual(res.get_pixel(5, 4), Rgb(1.0, 0.0, 0.0))
+
+    def test_displace(self):
+        image = Image(4, 4, Rgb(0.0, 0.0, 1.0))
+        image.put_pixel(2, 1, Rgb(1.0, 0.0, 0.0))
+        dx_map = Image(4, 4, Rgb(1.0, 1.0, 1.0))
+        dy_map = Image(4, 4, Rgb(0.0, 0.0, 0.0))
+
+        res = image.displace(dx_map, dy_map, scale=1, filter="nearest")
+        self.assertEqual(res.get_pixel(1, 2), Rgb(1.0, 0.0, 0.0))
+        self.assertEqual(res.width, 4)
+
+    def test_polar(self):
+        image = Image(10, 10, Rgb(1.0, 0.0, 0.0))
+
+        res = image.to_polar()
+        self.assertEqual(res.get_pixel(0, 0), Rgb(0.0, 0.0, 0.0, 0.0))
+        self.assertEqual(res.get_pixel(5, 5), Rgb(1.0, 0.0, 0.0))
+
+        res = image.from_polar(filter="nearest")
+        self.assertEqual(res.get_pixel(3, 7), Rgb(1.0, 0.0, 0.0))
+
+        self.assertEqual(image.swirl(90).get_pixel(4, 4), Rgb(1.0, 0.0, 0.0))
+        self.assertEqual(image.fisheye(0.5, "nearest").get_pixel(4, 4), Rgb(1.0, 0.0, 0.0))
+
+    def test_panorama_projections(self):
+        image = Image(40, 20, Rgb(0.0, 1.0, 0.0))
+        for x in range(40):
+            for y in range(10):
+                image.put_pixel(x, y, Rgb(0.0, 0.0, 1.0))
+
+        res = image.stereographic_project()
+        self.assertEqual(res.width, 20)
+        self.assertEqual(res.get_pixel(10, 10), Rgb(0.0, 1.0, 0.0))
+        self.assertEqual(res.get_pixel(0, 0), Rgb(0.0, 0.0, 1.0))
+
+        res = image.rectilinear_project(fov=90, yaw=45)
+        self.assertEqual(res.get_pixel(10, 2), Rgb(0.0, 0.0, 1.0))
+
+    def test_cubemap(self):
+        image = Image(40, 20, Rgb(0.0, 1.0, 0.0))
+        for x in range(40):
+            for y in range(10):
+                image.put_pixel(x, y, Rgb(0.0, 0.0, 1.0))
+
+        faces = image.equirect_to_cubemap(8)
+        self.assertEqual(len(faces), 6)
+        self.assertEqual(faces[2].get_pixel(4, 4), Rgb(0.0, 0.0, 1.0))
+        self.assertEqual(faces[3].get_pixel(4, 4), Rgb(0.0, 1.0, 0.0))
+
+        res = Image.cubemap_to_equirect(faces, 40)
+        self.assertEqual(res.width, 40)
+        self.assertEqual(res.height, 20)
+
+        with self.assertRaises(OSError):
+            Image.cubemap_to_equirect(faces[:5], 40)
+
+    def test_scan_enhance(self):
+        image = Image(30, 20, Rgb(0.6, 0.6, 0.4))
+
+        res = image.scan_enhance()
+        self.assertEqual(res.width, 30)
+        self.assertEqual(res.height, 20)
+
+        res = image.scan_enhance("bw")
+        self.assertEqual(res.get_pixel(10, 10), Rgb(1.0, 1.0, 1.0))
+
+        with self.assertRaises(OSError):
+            image.scan_enhance("sepia")
+
+    def test_detect_qr_codes(self):
+        self.assertEqual(Image(30, 30, Rgb(1.0, 1.0, 1.0)).detect_qr_codes(), [])
+
+    def test_speckle_noise(self):
+        image = Image(2, 3).speckle_noise(0.2)
+
+        self.assertEqual(image.width, 2)
+        self.assertEqual(image.height, 3)
+
+    def test_add_speckle_noise(self):
+        image = Image(2, 3)
+        image.add_speckle_noise(0.2)
+
+        self.assertEqual(image.width, 2)
+        self.assertEqual(image.height, 3)
+
     def test_gaussian_blur(self):
         image = Image(2, 3).gaussian_blur(1, 0.5)
 
         self.assertEqual(image.width, 2)
         self.assertEqual(image.height, 3)
 
+    def test_in_place(self):
+        image = Image(2, 3, Rgb(0.2, 0.4, 0.6))
+        image.brightness_in_place(0.1)
+        image.contrast_in_place(1.2)
+        image.level_in_place(0.0, 0.9)
+        image.sepia_in_place()
+        image.invert_in_place()
+        image.gaussian_blur_in_place(1)
+
+        expected = Rgb(0.2, 0.4, 0.6).with_brightness(0.1).with_contrast(1.2)
+        expected = expected.with_level(0.0, 0.9, 1.0).with_sepia().invert()
+
+        self.assertAlmostEqual(image.get_pixel(1, 1).red, expected.red, places=5)
+        self.assertEqual(image.width, 2)
+
     def test_unsharp(self):
         image = Image(2, 3).unsharp(1, 0.5, 0.5)
 
@@ -333,6 +616,134 @@ class TestImage(unittest.TestCase):
         self.assertEqual(result.get_pixel(1, 1), red)
         self.assertEqual(result.get_pixel(3, 4), none)
 
+    def test_clone_region(self):
+        img = Image(4, 4, Rgb(0, 1, 0))
+        img.put_pixel(0, 0, Rgb(1, 0, 0))
+
+        result = img.clone_region(0, 0, 2, 2, 2, 2)
+        self.assertEqual(result.get_pixel(2, 2), Rgb(1, 0, 0))
+        self.assertEqual(result.get_pixel(3, 3), Rgb(0, 1, 0))
+
+        result = img.clone_region(0, 0, 2, 2, 2, 2, feather=1)
+        self.assertEqual(result.width, 4)
+
+    def test_inpaint(self):
+        img = Image(4, 4, Rgb(0, 1, 0))
+        img.put_pixel(1, 1, Rgb(1, 0, 0))
+
+        mask = Image(4, 4, Rgb(0, 0, 0))
+        mask.put_pixel(1, 1, Rgb(1, 1, 1))
+
+        result = img.inpaint(mask)
+        self.assertEqual(result.get_pixel(1, 1), Rgb(0, 1, 0))
+
+    def test_segment_foreground(self):
+        img = Image(8, 8, Rgb(0, 0, 1))
+        for x in range(3, 5):
+            for y in range(3, 5):
+                img.put_pixel(x, y, Rgb(1, 0, 0))
+
+        mask = img.segment_foreground(rect=(2, 2, 4, 4))
+        self.assertEqual(mask.get_pixel(3, 3), Rgb(1, 1, 1))
+        self.assertEqual(mask.get_pixel(0, 0), Rgb(0, 0, 0))
+
+        result = img.remove_background(rect=(2, 2, 4, 4))
+        self.assertEqual(result.get_pixel(0, 0).alpha, 0.0)
+
+        with self.assertRaises(OSError):
+            img.segment_foreground()
+
+    def test_inpaint_exemplar(self):
+        img = Image(8, 8, Rgb(0, 1, 0))
+        img.put_pixel(4, 4, Rgb(1, 0, 0))
+
+        mask = Image(8, 8, Rgb(0, 0, 0))
+        mask.put_pixel(4, 4, Rgb(1, 1, 1))
+
+        result = img.inpaint_exemplar(mask, 3)
+        self.assertEqual(result.get_pixel(4, 4), Rgb(0, 1, 0))
+
+    def test_segment_kmeans(self):
+        img = Image(8, 8, Rgb(1, 0, 0))
+        for x in range(4, 8):
+            for y in range(8):
+                img.put_pixel(x, y, Rgb(0, 0, 1))
+
+        labels, colors = img.segment_kmeans(2)
+        self.assertEqual(len(labels), 64)
+        self.assertEqual(len(colors), 2)
+        self.assertNotEqual(labels[0], labels[7])
+
+        result = img.flat_art(2, "rgb")
+        self.assertEqual(result.get_pixel(0, 0), Rgb(1, 0, 0))
+        self.assertEqual(result.get_pixel(7, 7), Rgb(0, 0, 1))
+
+        with self.assertRaises(OSError):
+            img.segment_kmeans(2, "hsl")
+
+    def test_find_contours(self):
+        img = Image(6, 6, Rgb(0, 0, 0))
+        for x in range(1, 4):
+            for y in range(2, 4):
+                img.put_pixel(x, y, Rgb(1, 1, 1))
+
+        contours = img.find_contours()
+        self.assertEqual(contours, [[(1, 2), (4, 2), (4, 4), (1, 4)]])
+
+        contours = img.find_contours(epsilon=0.5)
+        self.assertEqual(len(contours[0]), 4)
+
+    def test_trace_bitmap(self):
+        img = Image(6, 6, Rgb(1, 1, 1))
+        for x in range(1, 4):
+            for y in range(2, 4):
+                img.put_pixel(x, y, Rgb(0, 0, 0))
+
+        svg = img.trace_bitmap(invert=True, color=Rgb(1, 0, 0))
+        self.assertTrue(svg.startswith("<svg"))
+        self.assertIn('<path d="M1 3 L1 2 L4 2 L4 4 L1 4 Z" fill="#ff0000"', svg)
+
+    def test_zone_statistics(self):
+        img = Image(4, 2, Rgb(0, 0, 0))
+        img.put_pixel(3, 0, Rgb(1, 1, 1))
+
+        zones = img.zone_statistics(2, 1)
+        self.assertEqual(len(zones), 2)
+
+        col, row, mean, std_dev, min, max, shadows, highlights = zones[1]
+        self.assertEqual((col, row), (1, 0))
+        self.assertAlmostEqual(mean, 0.25, places=3)
+        self.assertAlmostEqual(max, 1.0, places=3)
+        self.assertEqual(shadows, 75.0)
+        self.assertEqual(highlights, 25.0)
+
+    def test_save_gif(self):
+        img = Image(4, 4, Rgb(0.9, 0.1, 0.1))
+        img.put_pixel(0, 0, Rgb(0, 0, 0, 0))
+        palette = Palette([Rgb(1, 0, 0), Rgb(0, 0, 1)])
+
+        with tempfile.TemporaryDirectory() as tmp:
+            path = os.path.join(tmp, "test.gif")
+            img.save(path, EncodingFormat.gif(palette, "none"))
+
+            result = Image.open(path)
+            self.assertEqual(result.get_pixel(0, 0).alpha, 0.0)
+            self.assertEqual(result.get_pixel(1, 1), Rgb(1, 0, 0))
+
+            with self.assertRaises(OSError):
+                EncodingFormat.gif(dither="random")
+
+    def test_save_indexed_png(self):
+        img = Image(4, 4, Rgb(0.1, 0.1, 0.9))
+        palette = Palette([Rgb(1, 0, 0), Rgb(0, 0, 1)])
+
+        with tempfile.TemporaryDirectory() as tmp:
+            path = os.path.join(tmp, "test.png")
+            img.save(path, EncodingFormat.png(palette=palette))
+
+            result = Image.open(path)
+            self.assertEqual(result.get_pixel(1, 1), Rgb(0, 0, 1))
+
     def test_blend(self):
         green = Rgb(0, 1, 0)
         blue = Rgb(0, 0, 1)
@@ -366,6 +777,191 @@ class TestImage(unittest.TestCase):
         self.assertEqual(result.width, 3)
         self.assertEqual(result.height, 4)
 
+    def test_watermark(self):
+        img = Image(10, 10, Rgb(0, 0, 0))
+        mark = Image(2, 2, Rgb(1, 1, 1))
+
+        result = img.watermark(mark, 'top_left', None, 1.0, 1)
+        self.assertEqual(result.width, 10)
+        self.assertEqual(result.height, 10)
+        self.assertEqual(result.get_pixel(1, 1), Rgb(1, 1, 1))
+        self.assertEqual(result.get_pixel(0, 0), Rgb(0, 0, 0))
+
+    def test_halftone(self):
+        img = Image(8, 6)
+
+        result = img.halftone(4, 45, 'square')
+        self.assertEqual(result.width, 8)
+        self.assertEqual(result.height, 6)
+
+    def test_duotone(self):
+        img = Image(3, 4, Rgb(0, 0, 0))
+
+        result = img.duotone(Rgb(0, 0, 1), Rgb(1, 1, 0))
+        self.assertEqual(result.get_pixel(0, 0), Rgb(0, 0, 1))
+
+    def test_add_border(self):
+        img = Image(3, 4, Rgb(0, 0, 0))
+
+        result = img.add_border(2, Rgb(1, 0, 0), style="outset")
+        self.assertEqual(result.width, 7)
+        self.assertEqual(result.height, 8)
+
+        result = img.add_border(2, Rgb(1, 1, 1), Rgb(0, 0, 0))
+        self.assertEqual(result.get_pixel(0, 4), Rgb(0.25, 0.25, 0.25))
+
+    def test_noise(self):
+        img = Image.noise(32, 16, "simplex", scale=2.0, octaves=3, seed=7, tileable=True)
+
+        self.assertEqual(img.width, 32)
+        self.assertEqual(img.height, 16)
+        self.assertEqual(img.get_pixel(3, 4), Image.noise(32, 16, "simplex", 2.0, 3, seed=7, tileable=True).get_pixel(3, 4))
+
+    def test_collage(self):
+        images = [Image(20, 10, Rgb(1, 0, 0)), Image(5, 30, Rgb(0, 0, 1))]
+
+        res = Image.collage(images, 42, 22, columns=2, gap=2)
+        self.assertEqual(res.width, 42)
+        self.assertEqual(res.height, 22)
+        self.assertEqual(res.get_pixel(2, 2), Rgb(1, 0, 0))
+        self.assertEqual(res.get_pixel(22, 2), Rgb(0, 0, 1))
+        self.assertEqual(res.get_pixel(0, 0), Rgb(1, 1, 1))
+
+        res = Image.collage(images, 30, 20, layout="golden_ratio", gravity="top")
+        self.assertEqual(res.width, 30)
+
+    def test_split_join_vertical(self):
+        image = Image(4, 30, Rgb(1, 0, 0))
+
+        parts = image.split_vertical(12, 2)
+        self.assertEqual([p.height for p in parts], [12, 12, 10])
+
+        joined = Image.join_vertical(parts)
+        self.assertEqual(joined.height, 34)
+
+    def test_polaroid(self):
+        result = Image(50, 50).polaroid()
+
+        self.assertEqual(result.width, 56)
+        self.assertEqual(result.height, 65)
+
+    def test_to_monochrome(self):
+        img = Image(3, 4, Rgb(1, 0, 0))
+
+        result = img.to_monochrome(1.0, 0.0, 0.0)
+        self.assertEqual(result.get_pixel(0, 0), Rgb(1, 1, 1))
+
+        result = img.to_monochrome_with_filter('green')
+        self.assertTrue(result.get_pixel(0, 0).is_grayscale())
+
+        result = img.to_monochrome_with_filter('red', Rgb(0.5, 0.3, 0.1))
+        self.assertFalse(result.get_pixel(0, 0).is_grayscale())
+
+        with self.assertRaises(OSError):
+            img.to_monochrome_with_filter('foo')
+
+    def test_hsl_adjust(self):
+        img = Image(3, 4, Rgb(0, 0, 1))
+        img.put_pixel(0, 0, Rgb(1, 0, 0))
+
+        # hue, width, hue shift, saturation, lightness
+        result = img.hsl_adjust([(240, 20, 0, 0, -1)])
+        self.assertEqual(result.get_pixel(1, 0), Rgb(0, 0, 0))
+        self.assertEqual(result.get_pixel(0, 0), Rgb(1, 0, 0))
+
+    def test_apply_look(self):
+        img = Image(3, 4, Rgb(0, 1, 0))
+
+        result = img.apply_look('bw_red_filter')
+        self.assertTrue(result.get_pixel(0, 0).is_grayscale())
+
+        result = img.apply_look()
+        self.assertNotEqual(result.get_pixel(0, 0), img.get_pixel(0, 0))
+
+        with self.assertRaises(OSError):
+            img.apply_look('foo')
+
+    def test_transfer_functions(self):
+        img = Image(3, 4, Rgb(0.2, 0.2, 0.2))
+
+        encoded = img.encode_transfer('hlg')
+        self.assertNotEqual(encoded.get_pixel(0, 0), img.get_pixel(0, 0))
+
+        decoded = encoded.decode_transfer('hlg')
+        self.assertAlmostEqual(decoded.get_pixel(0, 0).red, 0.2, places=3)
+
+        with self.assertRaises(OSError):
+            img.decode_transfer('foo')
+
+    def test_auto_levels(self):
+        img = Image(3, 4, Rgb(0.3, 0.3, 0.3))
+        img.put_pixel(0, 0, Rgb(0.6, 0.5, 0.4))
+
+        result = img.auto_levels()
+        self.assertAlmostEqual(result.get_pixel(1, 1).red, 0.0, places=2)
+        self.assertAlmostEqual(result.get_pixel(0, 0).red, 1.0, places=2)
+        self.assertLess(result.get_pixel(0, 0).blue, 0.9)
+
+        result = img.auto_levels(0.0, 0.0, per_channel=True)
+        self.assertAlmostEqual(result.get_pixel(0, 0).blue, 1.0, places=2)
+
+    def test_auto_enhance(self):
+        img = Image(3, 4)
+
+        result = img.auto_enhance(0.5)
+        self.assertEqual(result.width, 3)
+        self.assertEqual(result.height, 4)
+
+    def test_portrait_smooth(self):
+        img = Image(3, 4, Rgb(0.6, 0.35, 0.25))
+
+        result = img.portrait_smooth()
+        self.assertEqual(result.width, 3)
+        self.assertEqual(result.height, 4)
+
+    def test_graduated_filter(self):
+        img = Image(3, 4, Rgb(0.5, 0.6, 0.8))
+
+        result = img.graduated_filter(exposure=-1.0, saturation=0.2, temperature=500.0)
+        self.assertEqual(result.width, 3)
+        self.assertEqual(result.height, 4)
+        self.assertNotEqual(result.get_pixel(0, 0), img.get_pixel(0, 0))
+
+    def test_emboss(self):
+        img = Image(3, 3, Rgb(0.2, 0.4, 0.6))
+
+        result = img.emboss(elevation=90)
+        self.assertEqual(result.get_pixel(1, 1), Rgb(1, 1, 1))
+
+    def test_height_to_normal_map(self):
+        result = Image(3, 3).height_to_normal_map(2.0)
+
+        self.assertAlmostEqual(result.get_pixel(1, 1).to_srgb().blue, 1.0, places=5)
+
+    def test_height_to_ao(self):
+        img = Image(9, 9, Rgb(1, 1, 1))
+        img.put_pixel(4, 4, Rgb(0, 0, 0))
+
+        result = img.height_to_ao(8, 4.0)
+        self.assertLess(result.get_pixel(4, 4).red, 1.0)
+        self.assertAlmostEqual(result.get_pixel(0, 0).red, 1.0, places=5)
+
+    def test_lighting(self):
+        img = Image(20, 10, Rgb(0.5, 0.5, 0.5))
+
+        result = img.spotlight(0.25, 0.25, radius=0.2, ambient=0.0)
+        self.assertGreater(result.get_pixel(5, 5).red, result.get_pixel(19, 9).red)
+
+        result = img.directional_light(90, elevation=90, falloff=1.0)
+        self.assertGreater(result.get_pixel(0, 5).red, result.get_pixel(19, 5).red)
+
+    def test_enhance_sky(self):
+        img = Image(3, 4, Rgb(0.5, 0.6, 0.8))
+
+        result = img.enhance_sky()
+        self.assertEqual(result.width, 3)
+        self.assertEqual(result.height, 4)
+
     def test_interlace(self):
         img = Image(3, 4)
 
@@ -380,6 +976,45 @@ class TestImage(unittest.TestCase):
         self.assertEqual(result.width, 3)
         self.assertEqual(result.height, 4)
 
+    def test_apply_in_space(self):
+        img = Image(3, 2, Rgb(0.2, 0.3, 0.5))
+
+        result = img.apply_in_space("red", lambda gray: Image(gray.width, gray.height, Rgb(1, 1, 1)))
+        self.assertAlmostEqual(result.get_pixel(1, 1).red, 1.0, places=5)
+        self.assertAlmostEqual(result.get_pixel(1, 1).blue, 0.5, places=5)
+
+        result = img.apply_in_space("lab_lightness", lambda gray: gray.gaussian_blur(1))
+        self.assertEqual(result.width, 3)
+
+        with self.assertRaises(OSError):
+            img.apply_in_space("default", lambda gray: Image(1, 1))
+
+    def test_denoise_chroma(self):
+        img = Image(4, 4, Rgb(0.2, 0.3, 0.5))
+        img.put_pixel(1, 1, Rgb(0.5, 0.2, 0.2))
+
+        result = img.denoise_chroma(1.0)
+        self.assertEqual(result.width, 4)
+        self.assertLess(result.get_pixel(1, 1).red, 0.5)
+
+    def test_repair_hot_pixels(self):
+        img = Image(5, 5, Rgb(0.3, 0.3, 0.3))
+        img.put_pixel(2, 3, Rgb(1, 1, 1))
+
+        result, locations = img.repair_hot_pixels(0.2)
+        self.assertEqual(locations, [(2, 3)])
+        self.assertAlmostEqual(result.get_pixel(2, 3).red, 0.3, places=3)
+
+    def test_deband(self):
+        img = Image(64, 16)
+        for x in range(64):
+            for y in range(16):
+                img.put_pixel(x, y, Rgb(0.2 + (x // 8) / 255, 0.2, 0.2))
+
+        result = img.deband(1.0, dither=False)
+        self.assertEqual(result.width, 64)
+        self.assertLess(result.banding_score(), img.banding_score())
+
 
 class TestNumpy(unittest.TestCase):
 
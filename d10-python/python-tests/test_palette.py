@@ -0,0 +1,46 @@
+import os
+import tempfile
+import unittest
+from d10 import Image, Palette, Rgb
+
+
+class TestPalette(unittest.TestCase):
+
+    def test_new(self):
+        palette = Palette([Rgb(1, 0, 0), Rgb(0, 0, 1)], "Test")
+        palette.push(Rgb(1, 1, 1), "White")
+
+        self.assertEqual(len(palette), 3)
+        self.assertEqual(palette.name, "Test")
+        self.assertEqual(palette.colors[2], Rgb(1, 1, 1))
+        self.assertEqual(palette.names, [None, None, "White"])
+
+    def test_image(self):
+        img = Image.from_list(2, 1, [Rgb(1, 0, 0), Rgb(0, 1, 0)])
+
+        palette = Palette.from_image(img)
+        self.assertEqual(palette.colors, [Rgb(1, 0, 0), Rgb(0, 1, 0)])
+        self.assertEqual(palette.to_image().to_list(), img.to_list())
+
+    def test_files(self):
+        palette = Palette([Rgb(1, 0, 0), Rgb(0, 1, 0), Rgb(0, 0, 1)])
+        palette.push(Rgb(0, 0, 0), "Black")
+
+        with tempfile.TemporaryDirectory() as tmp:
+            for ext in ["gpl", "aco", "ase"]:
+                path = os.path.join(tmp, "palette." + ext)
+                palette.save(path)
+
+                loaded = Palette.open(path)
+                self.assertEqual(loaded.colors, palette.colors)
+                self.assertEqual(loaded.names, palette.names)
+
+            with self.assertRaises(OSError):
+                palette.save(os.path.join(tmp, "palette.txt"))
+
+    def test_apply_palette(self):
+        img = Image.from_list(2, 1, [Rgb(0.9, 0.1, 0), Rgb(0.1, 0, 0.8)])
+        palette = Palette([Rgb(1, 0, 0), Rgb(0, 0, 1)])
+
+        result = img.apply_palette(palette)
+        self.assertEqual(result.to_list(), [Rgb(1, 0, 0), Rgb(0, 0, 1)])
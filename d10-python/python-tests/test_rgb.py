@@ -92,6 +92,12 @@ class TestRgb(unittest.TestCase):
         self.assertChannelValue(color.blue, 1.0 - 0.333)
         self.assertChannelValue(color.alpha, 1.0)
 
+    def test_contrast_ratio(self):
+        self.assertAlmostEqual(Rgb(0.0, 0.0, 0.0).relative_luminance(), 0.0)
+        self.assertAlmostEqual(Rgb(0.0, 0.0, 0.0).contrast_ratio(Rgb(1.0, 1.0, 1.0)), 21.0, places=4)
+        self.assertEqual(Rgb.best_text_color(Rgb(1.0, 1.0, 1.0)), Rgb(0.0, 0.0, 0.0))
+        self.assertEqual(Rgb.best_text_color(Rgb(0.0, 0.0, 0.5)), Rgb(1.0, 1.0, 1.0))
+
     def test_difference(self):
         color1 = Rgb(1.0, 0.666, 0.333)
         color2 = Rgb(0.0, 1.0, 1.0)
@@ -231,6 +231,19 @@ fn difference(&self, color: &Rgb) -> Rgb {
     self.inner.difference(&color.inner).into()
 }
 
+fn relative_luminance(&self) -> f32 {
+    self.inner.relative_luminance()
+}
+
+fn contrast_ratio(&self, color: &Rgb) -> f32 {
+    self.inner.contrast_ratio(&color.inner)
+}
+
+#[staticmethod]
+fn best_text_color(background: &Rgb) -> Rgb {
+    D10Rgb::best_text_color(&background.inner).into()
+}
+
 fn with_gamma(&self, gamma: f32) -> Rgb {
     self.inner.with_gamma(gamma).into()
 }
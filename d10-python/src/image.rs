@@ -1,13 +1,19 @@
 use pyo3::exceptions::PyOSError;
 use pyo3::prelude::*;
-use pyo3::types::{PyFunction, PyList};
+use pyo3::types::{PyBytes, PyFunction, PyList};
 
 use d10::illuminant::D65;
 use d10::observer::O2;
-use d10::ops::{BalanceMode, BlendOp, EdgeDetection, SaturationMode};
+use d10::ops::{
+    Adjustment, Augment, BalanceMode, BlendOp, BorderFill, ClusterSpace, CollageLayout,
+    CollageTemplate, ColorSpaceChannel, DiffMode, EdgeDetection, HashAlgorithm, HueRangeAdjustment,
+    LightSpec, NoiseOptions, SaturationMode, SegmentationHint, TraceOptions,
+    DEFAULT_DIFF_THRESHOLD, DEFAULT_WATERMARK_STRENGTH,
+};
 use d10::{
     BmpColorType, EncodingFormat as D10EncodingFormat, EqualizeMode, FilterMode, IcoColorType,
-    Image as D10Image, PngColorType, PngCompression, PngFilterType, Rgb as D10Rgb, WebPPreset,
+    Image as D10Image, PngColorType, PngCompression, PngFilterType, Region, ResizeOptions,
+    Rgb as D10Rgb, WebPPreset,
 };
 #[cfg(feature = "numpy")]
 use {
@@ -17,8 +23,12 @@ use {
 };
 
 use crate::color::Rgb;
+use crate::palette::Palette;
 use crate::IntoPyErr;
 
+/// Payload, version and corner points of a detected qr code
+type QrCode = (Py<PyBytes>, u32, Vec<(f32, f32)>);
+
 #[pyclass]
 pub struct Image {
     pub inner: D10Image,
@@ -27,23 +37,25 @@ pub struct Image {
 #[pymethods]
 impl Image {
     #[new]
-    fn new(width: u32, height: u32, color: Option<&Rgb>) -> Image {
-        match color {
-            Some(color) => D10Image::new_with_color(width, height, color.inner),
-            None => D10Image::new(width, height),
+    fn new(width: u32, height: u32, color: Option<&Rgb>) -> PyResult<Image> {
+        Ok(match color {
+            Some(color) => D10Image::try_new_with_color(width, height, color.inner),
+            None => D10Image::try_new(width, height),
         }
-        .into()
+        .py_err()?
+        .into())
     }
 
     #[staticmethod]
-    fn from_list(width: u32, height: u32, list: Vec<Rgb>) -> Image {
-        Image {
-            inner: D10Image::new_from_raw(
+    fn from_list(width: u32, height: u32, list: Vec<Rgb>) -> PyResult<Image> {
+        Ok(Image {
+            inner: D10Image::try_new_from_raw(
                 width,
                 height,
                 list.into_iter().map(|c| c.inner).collect(),
-            ),
-        }
+            )
+            .py_err()?,
+        })
     }
 
     fn to_list(&self) -> Vec<Rgb> {
@@ -65,6 +77,20 @@ impl Image {
         Ok(D10Image::open(path).py_err()?.into())
     }
 
+    #[staticmethod]
+    fn open_thumbnail(path: &str, max_edge: u32) -> PyResult<Image> {
+        Ok(D10Image::open_thumbnail(path, max_edge).py_err()?.into())
+    }
+
+    #[staticmethod]
+    fn open_progressive(path: &str) -> PyResult<Vec<Image>> {
+        Ok(D10Image::open_progressive(path)
+            .py_err()?
+            .into_iter()
+            .map(|img| img.into())
+            .collect())
+    }
+
     fn save(&mut self, path: &str, format: Option<&EncodingFormat>) -> PyResult<()> {
         match format {
             Some(format) => self
@@ -84,6 +110,10 @@ impl Image {
         self.inner.is_grayscale()
     }
 
+    pub fn approx_eq(&self, other: &Image, epsilon: f32) -> bool {
+        self.inner.approx_eq(&other.inner, epsilon)
+    }
+
     fn mod_colors(&mut self, func: &PyFunction) -> PyResult<()> {
         let map = |c: &D10Rgb| -> PyResult<D10Rgb> {
             let arg1 = Rgb { inner: *c };
@@ -151,6 +181,14 @@ impl Image {
         self.inner.crop(offset_x, offset_y, width, height).into()
     }
 
+    pub fn offset(&self, dx: i32, dy: i32) -> Image {
+        self.inner.offset(dx, dy).into()
+    }
+
+    pub fn make_seamless(&self, overlap: u32) -> Image {
+        self.inner.make_seamless(overlap).into()
+    }
+
     pub fn flip_horizontal(&self) -> Image {
         self.inner.flip_horizontal().into()
     }
@@ -179,12 +217,35 @@ impl Image {
         Ok(self.inner.rotate(radians, filter).into())
     }
 
-    pub fn resize(&self, new_width: u32, new_height: u32, filter: Option<&str>) -> PyResult<Image> {
+    pub fn straighten(&self, max_angle: Option<f32>) -> Image {
+        self.inner.straighten(max_angle.unwrap_or(10.0)).into()
+    }
+
+    pub fn resize(
+        &self,
+        new_width: u32,
+        new_height: u32,
+        filter: Option<&str>,
+        gamma_correct: Option<bool>,
+        premultiply: Option<bool>,
+        sharpen_after: Option<f32>,
+    ) -> PyResult<Image> {
         let filter = match filter {
             Some(filter) => filter.parse().py_err()?,
             None => FilterMode::Bilinear,
         };
-        Ok(self.inner.resize(new_width, new_height, filter).into())
+
+        let options = ResizeOptions {
+            filter,
+            gamma_correct: gamma_correct.unwrap_or(true),
+            premultiply: premultiply.unwrap_or(true),
+            sharpen_after,
+        };
+
+        Ok(self
+            .inner
+            .resize_with_options(new_width, new_height, &options)
+            .into())
     }
 
     pub fn resize_pct(&self, pct_100: f32, filter: Option<&str>) -> PyResult<Image> {
@@ -195,6 +256,46 @@ impl Image {
         Ok(self.inner.resize_pct(pct_100, filter).into())
     }
 
+    pub fn smart_crop(
+        &self,
+        width: u32,
+        height: u32,
+        filter: Option<&str>,
+        focus: Option<Vec<(u32, u32, u32, u32)>>,
+    ) -> PyResult<Image> {
+        let filter = match filter {
+            Some(filter) => filter.parse().py_err()?,
+            None => FilterMode::Bilinear,
+        };
+        let focus: Vec<Region> = focus
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(x, y, width, height)| Region::new(x, y, width, height))
+            .collect();
+
+        Ok(self
+            .inner
+            .smart_crop_with_focus(width, height, &focus, filter)
+            .into())
+    }
+
+    #[getter]
+    pub fn crop_region(&self) -> Option<(u32, u32, u32, u32)> {
+        self.inner
+            .crop_region()
+            .map(|r| (r.x, r.y, r.width, r.height))
+    }
+
+    pub fn upscale_enhanced(&self, factor: Option<f32>) -> Image {
+        self.inner.upscale_enhanced(factor.unwrap_or(2.0)).into()
+    }
+
+    pub fn xbr(&self, scale: Option<&str>) -> PyResult<Image> {
+        let scale = scale.unwrap_or("default").parse().py_err()?;
+
+        Ok(self.inner.xbr(scale).into())
+    }
+
     pub fn edge_detection(&self, mode: Option<&str>) -> PyResult<Image> {
         let mode = match mode {
             Some(mode) => mode.parse().py_err()?,
@@ -210,6 +311,10 @@ impl Image {
             .into()
     }
 
+    pub fn jpeg_artifact_map(&self, quality: u8) -> Image {
+        self.inner.jpeg_artifact_map(quality).into()
+    }
+
     pub fn random_noise(&self, alpha: f32) -> Image {
         self.inner.random_noise(alpha).into()
     }
@@ -242,31 +347,369 @@ impl Image {
         self.inner.add_gaussian_noise(alpha);
     }
 
+    pub fn poisson_noise(&self, photons: f32) -> Image {
+        self.inner.poisson_noise(photons).into()
+    }
+
+    pub fn add_poisson_noise(&mut self, photons: f32) {
+        self.inner.add_poisson_noise(photons);
+    }
+
+    pub fn speckle_noise(&self, sigma: f32) -> Image {
+        self.inner.speckle_noise(sigma).into()
+    }
+
+    pub fn add_speckle_noise(&mut self, sigma: f32) {
+        self.inner.add_speckle_noise(sigma);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn augment(
+        &self,
+        count: usize,
+        seed: Option<u64>,
+        crop: Option<f32>,
+        flip_horizontal: Option<f32>,
+        flip_vertical: Option<f32>,
+        rotation: Option<f32>,
+        brightness: Option<f32>,
+        contrast: Option<f32>,
+        saturation: Option<f32>,
+        blur: Option<u32>,
+        noise: Option<f32>,
+        cutout: Option<(u32, f32)>,
+    ) -> Vec<Image> {
+        let mut pipeline = match seed {
+            Some(seed) => Augment::new(seed),
+            None => Augment::from_entropy(),
+        };
+
+        if let Some(min_scale) = crop {
+            pipeline = pipeline.random_crop(min_scale);
+        }
+        if let Some(probability) = flip_horizontal {
+            pipeline = pipeline.flip_horizontal(probability);
+        }
+        if let Some(probability) = flip_vertical {
+            pipeline = pipeline.flip_vertical(probability);
+        }
+        if let Some(max_degrees) = rotation {
+            pipeline = pipeline.rotation(max_degrees);
+        }
+        if brightness.is_some() || contrast.is_some() || saturation.is_some() {
+            pipeline = pipeline.color_jitter(
+                brightness.unwrap_or(0.0),
+                contrast.unwrap_or(0.0),
+                saturation.unwrap_or(0.0),
+            );
+        }
+        if let Some(max_radius) = blur {
+            pipeline = pipeline.blur(max_radius);
+        }
+        if let Some(max_sigma) = noise {
+            pipeline = pipeline.noise(max_sigma);
+        }
+        if let Some((count, max_size)) = cutout {
+            pipeline = pipeline.cutout(count, max_size);
+        }
+
+        self.inner
+            .augment(&pipeline, count)
+            .into_iter()
+            .map(|img| img.into())
+            .collect()
+    }
+
     pub fn gaussian_blur(&self, radius: u32, sigma: Option<f32>) -> Image {
         self.inner.gaussian_blur(radius, sigma).into()
     }
 
+    pub fn gaussian_blur_in_place(&mut self, radius: u32, sigma: Option<f32>) {
+        self.inner.gaussian_blur_in_place(radius, sigma);
+    }
+
+    pub fn invert_in_place(&mut self) {
+        self.inner.invert_in_place();
+    }
+
+    pub fn brightness_in_place(&mut self, factor: f32) {
+        self.inner.brightness_in_place(factor);
+    }
+
+    pub fn contrast_in_place(&mut self, factor: f32) {
+        self.inner.contrast_in_place(factor);
+    }
+
+    pub fn level_in_place(&mut self, black_point: f32, white_point: f32, gamma: Option<f32>) {
+        self.inner
+            .level_in_place(black_point, white_point, gamma.unwrap_or(1.0));
+    }
+
+    pub fn sepia_in_place(&mut self) {
+        self.inner.sepia_in_place();
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn clone_region(
+        &self,
+        src_x: u32,
+        src_y: u32,
+        width: u32,
+        height: u32,
+        dst_x: u32,
+        dst_y: u32,
+        feather: Option<u32>,
+    ) -> Image {
+        self.inner
+            .clone_region(
+                Region::new(src_x, src_y, width, height),
+                (dst_x, dst_y),
+                feather.unwrap_or(0),
+            )
+            .into()
+    }
+
+    pub fn inpaint(&self, mask: &Image) -> Image {
+        self.inner.inpaint(&mask.inner).into()
+    }
+
+    pub fn segment_foreground(
+        &self,
+        rect: Option<(u32, u32, u32, u32)>,
+        scribbles: Option<&Image>,
+    ) -> PyResult<Image> {
+        let hint = segmentation_hint(rect, scribbles)?;
+        Ok(self.inner.segment_foreground(&hint).into())
+    }
+
+    pub fn remove_background(
+        &self,
+        rect: Option<(u32, u32, u32, u32)>,
+        scribbles: Option<&Image>,
+    ) -> PyResult<Image> {
+        let hint = segmentation_hint(rect, scribbles)?;
+        Ok(self.inner.remove_background(&hint).into())
+    }
+
+    pub fn segment_kmeans(
+        &self,
+        k: usize,
+        space: Option<&str>,
+    ) -> PyResult<(Vec<usize>, Vec<Rgb>)> {
+        let space: ClusterSpace = space.unwrap_or("default").parse().py_err()?;
+        let segmentation = self.inner.segment_kmeans(k, space);
+
+        Ok((
+            segmentation.labels,
+            segmentation.colors.iter().map(|c| c.into()).collect(),
+        ))
+    }
+
+    pub fn flat_art(&self, k: usize, space: Option<&str>) -> PyResult<Image> {
+        let space: ClusterSpace = space.unwrap_or("default").parse().py_err()?;
+        Ok(self.inner.flat_art(k, space).into())
+    }
+
+    pub fn find_contours(&self, epsilon: Option<f32>) -> Vec<Vec<(f32, f32)>> {
+        self.inner
+            .find_contours()
+            .into_iter()
+            .map(|contour| match epsilon {
+                Some(epsilon) => contour.simplify(epsilon).points,
+                None => contour.points,
+            })
+            .collect()
+    }
+
+    pub fn trace_bitmap(
+        &self,
+        min_area: Option<f32>,
+        tolerance: Option<f32>,
+        corner_angle: Option<f32>,
+        invert: Option<bool>,
+        color: Option<&Rgb>,
+    ) -> String {
+        let defaults = TraceOptions::default();
+
+        let options = TraceOptions {
+            min_area: min_area.unwrap_or(defaults.min_area),
+            tolerance: tolerance.unwrap_or(defaults.tolerance),
+            corner_angle: corner_angle.unwrap_or(defaults.corner_angle),
+            invert: invert.unwrap_or(defaults.invert),
+            color: color.map(|c| c.inner).unwrap_or(defaults.color),
+        };
+
+        self.inner.trace_bitmap(&options).to_svg()
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn zone_statistics(
+        &self,
+        cols: u32,
+        rows: u32,
+    ) -> Vec<(u32, u32, f32, f32, f32, f32, f32, f32)> {
+        self.inner
+            .zone_statistics(cols, rows)
+            .into_iter()
+            .map(|z| {
+                (
+                    z.col,
+                    z.row,
+                    z.mean,
+                    z.std_dev,
+                    z.min,
+                    z.max,
+                    z.clipped_shadows,
+                    z.clipped_highlights,
+                )
+            })
+            .collect()
+    }
+
+    pub fn inpaint_exemplar(&self, mask: &Image, patch_size: Option<u32>) -> Image {
+        self.inner
+            .inpaint_exemplar(&mask.inner, patch_size.unwrap_or(9))
+            .into()
+    }
+
     pub fn unsharp(&self, radius: u32, factor: Option<f32>, sigma: Option<f32>) -> Image {
         self.inner
             .unsharp(radius, factor.unwrap_or(1.0), sigma)
             .into()
     }
 
+    pub fn watermark(
+        &self,
+        mark: &Image,
+        position: Option<&str>,
+        scale: Option<f32>,
+        opacity: Option<f32>,
+        margin: Option<u32>,
+    ) -> PyResult<Image> {
+        let position = position.unwrap_or("default").parse().py_err()?;
+
+        Ok(self
+            .inner
+            .watermark(
+                &mark.inner,
+                position,
+                scale,
+                opacity.unwrap_or(0.5),
+                margin.unwrap_or(0),
+            )
+            .into())
+    }
+
     pub fn drawing(&self, radius: u32, mode: Option<&str>) -> PyResult<Image> {
         let mode = mode.unwrap_or("default").parse().py_err()?;
 
         Ok(self.inner.drawing(radius, mode).into())
     }
 
+    pub fn halftone(
+        &self,
+        cell_size: u32,
+        angle: Option<f32>,
+        shape: Option<&str>,
+    ) -> PyResult<Image> {
+        let shape = shape.unwrap_or("default").parse().py_err()?;
+
+        Ok(self
+            .inner
+            .halftone(cell_size, angle.unwrap_or(45.0), shape)
+            .into())
+    }
+
+    pub fn scan_enhance(&self, mode: Option<&str>) -> PyResult<Image> {
+        let mode = mode.unwrap_or("default").parse().py_err()?;
+
+        Ok(self.inner.scan_enhance(mode).into())
+    }
+
+    pub fn add_border(
+        &self,
+        width: u32,
+        color: &Rgb,
+        outer_color: Option<&Rgb>,
+        style: Option<&str>,
+    ) -> PyResult<Image> {
+        let fill = match outer_color {
+            Some(outer_color) => BorderFill::Gradient {
+                inner: color.inner,
+                outer: outer_color.inner,
+            },
+            None => BorderFill::Color(color.inner),
+        };
+        let style = style.unwrap_or("default").parse().py_err()?;
+
+        Ok(self.inner.add_border(width, fill, style).into())
+    }
+
+    pub fn polaroid(&self) -> Image {
+        self.inner.polaroid().into()
+    }
+
+    pub fn duotone(&self, dark_color: &Rgb, light_color: &Rgb) -> Image {
+        self.inner
+            .duotone(dark_color.inner, light_color.inner)
+            .into()
+    }
+
+    pub fn to_monochrome(
+        &self,
+        red_weight: f32,
+        green_weight: f32,
+        blue_weight: f32,
+        tint: Option<&Rgb>,
+    ) -> Image {
+        self.inner
+            .to_monochrome(red_weight, green_weight, blue_weight, tint.map(|c| c.inner))
+            .into()
+    }
+
+    pub fn to_monochrome_with_filter(
+        &self,
+        filter: Option<&str>,
+        tint: Option<&Rgb>,
+    ) -> PyResult<Image> {
+        let filter = filter.unwrap_or("default").parse().py_err()?;
+        Ok(self
+            .inner
+            .to_monochrome_with_filter(filter, tint.map(|c| c.inner))
+            .into())
+    }
+
+    pub fn hsl_adjust(&self, ranges: Vec<(f32, f32, f32, f32, f32)>) -> Image {
+        let ranges: Vec<HueRangeAdjustment> = ranges
+            .into_iter()
+            .map(
+                |(hue, width, hue_shift, saturation, lightness)| HueRangeAdjustment {
+                    hue,
+                    width,
+                    hue_shift,
+                    saturation,
+                    lightness,
+                },
+            )
+            .collect();
+
+        self.inner.hsl_adjust(&ranges).into()
+    }
+
+    pub fn apply_look(&self, preset: Option<&str>) -> PyResult<Image> {
+        let preset = preset.unwrap_or("default").parse().py_err()?;
+        Ok(self.inner.apply_look(preset).into())
+    }
+
     pub fn interlace(&self, offset: u32) -> PyResult<Image> {
         Ok(self.inner.interlace(offset).into())
     }
 
-    pub fn apply_palette(&self, palette: &Image) -> Image {
+    pub fn apply_palette(&self, palette: &Palette) -> Image {
         self.inner.apply_palette(&palette.inner).into()
     }
 
-    pub fn apply_palette_in_place(&mut self, palette: &Image) {
+    pub fn apply_palette_in_place(&mut self, palette: &Palette) {
         self.inner.apply_palette_in_place(&palette.inner);
     }
 
@@ -276,6 +719,47 @@ impl Image {
             .into()
     }
 
+    pub fn apply_in_space(&self, channel: &str, func: &PyFunction) -> PyResult<Image> {
+        let channel: ColorSpaceChannel = channel.parse().py_err()?;
+
+        let op = |gray: &D10Image| -> PyResult<D10Image> {
+            let result = func
+                .call1((Image::from(gray.clone()),))?
+                .extract::<PyRef<Image>>()?
+                .inner
+                .clone();
+
+            if (result.width(), result.height()) != (gray.width(), gray.height()) {
+                return Err(PyOSError::new_err(
+                    "The function must not change the size of the image",
+                ));
+            }
+
+            Ok(result)
+        };
+
+        Ok(self.inner.try_apply_in_space(channel, op)?.into())
+    }
+
+    pub fn denoise_chroma(&self, strength: Option<f32>) -> Image {
+        self.inner.denoise_chroma(strength.unwrap_or(2.0)).into()
+    }
+
+    pub fn repair_hot_pixels(&self, threshold: Option<f32>) -> (Image, Vec<(u32, u32)>) {
+        let (image, locations) = self.inner.repair_hot_pixels(threshold.unwrap_or(0.2));
+        (image.into(), locations)
+    }
+
+    pub fn deband(&self, strength: Option<f32>, dither: Option<bool>) -> Image {
+        self.inner
+            .deband(strength.unwrap_or(0.5), dither.unwrap_or(true))
+            .into()
+    }
+
+    pub fn banding_score(&self) -> f32 {
+        self.inner.banding_score()
+    }
+
     pub fn symmetric_nearest_neighbor(
         &self,
         radius: Option<usize>,
@@ -312,6 +796,115 @@ impl Image {
         res.map(|i| i.into())
     }
 
+    #[staticmethod]
+    pub fn pack_sprites(
+        images: Vec<PyRef<Image>>,
+        max_width: u32,
+        padding: Option<u32>,
+    ) -> (Image, Vec<(u32, u32, u32, u32)>) {
+        let images: Vec<&D10Image> = images.iter().map(|image| &image.inner).collect();
+
+        let (sheet, placements) = D10Image::pack_sprites(&images, max_width, padding.unwrap_or(0));
+
+        (
+            sheet.into(),
+            placements
+                .iter()
+                .map(|r| (r.x, r.y, r.width, r.height))
+                .collect(),
+        )
+    }
+
+    #[staticmethod]
+    pub fn montage(
+        images: Vec<PyRef<Image>>,
+        columns: u32,
+        spacing: Option<u32>,
+        background: Option<&Rgb>,
+    ) -> Image {
+        let images: Vec<&D10Image> = images.iter().map(|image| &image.inner).collect();
+        let background = background.map(|c| c.inner).unwrap_or(D10Rgb::WHITE);
+
+        D10Image::montage(&images, columns, spacing.unwrap_or(0), background).into()
+    }
+
+    #[staticmethod]
+    #[allow(clippy::too_many_arguments)]
+    pub fn noise(
+        width: u32,
+        height: u32,
+        kind: Option<&str>,
+        scale: Option<f32>,
+        octaves: Option<u32>,
+        persistence: Option<f32>,
+        seed: Option<u64>,
+        tileable: Option<bool>,
+    ) -> PyResult<Image> {
+        let defaults = NoiseOptions::default();
+        let options = NoiseOptions {
+            kind: kind.unwrap_or("default").parse().py_err()?,
+            scale: scale.unwrap_or(defaults.scale),
+            octaves: octaves.unwrap_or(defaults.octaves),
+            persistence: persistence.unwrap_or(defaults.persistence),
+            seed: seed.unwrap_or(defaults.seed),
+            tileable: tileable.unwrap_or(defaults.tileable),
+        };
+
+        Ok(D10Image::new_noise(width, height, &options).into())
+    }
+
+    #[staticmethod]
+    #[allow(clippy::too_many_arguments)]
+    pub fn collage(
+        images: Vec<PyRef<Image>>,
+        width: u32,
+        height: u32,
+        layout: Option<&str>,
+        columns: Option<u32>,
+        gap: Option<u32>,
+        background: Option<&Rgb>,
+        gravity: Option<&str>,
+    ) -> PyResult<Image> {
+        let images: Vec<&D10Image> = images.iter().map(|image| &image.inner).collect();
+        let background = background.map(|c| c.inner).unwrap_or(D10Rgb::WHITE);
+
+        let layout = match (layout.unwrap_or("default").parse().py_err()?, columns) {
+            (CollageLayout::Grid { .. }, Some(columns)) => CollageLayout::Grid { columns },
+            (layout, _) => layout,
+        };
+        let template = CollageTemplate::new(layout, width, height)
+            .with_gravity(gravity.unwrap_or("default").parse().py_err()?);
+
+        Ok(D10Image::collage(&images, &template, gap.unwrap_or(0), background).into())
+    }
+
+    pub fn split_vertical(&self, max_height: u32, overlap: Option<u32>) -> Vec<Image> {
+        self.inner
+            .split_vertical(max_height, overlap.unwrap_or(0))
+            .into_iter()
+            .map(|image| image.into())
+            .collect()
+    }
+
+    #[staticmethod]
+    pub fn join_vertical(images: Vec<PyRef<Image>>, max_overlap: Option<u32>) -> Option<Image> {
+        let images: Vec<&D10Image> = images.iter().map(|image| &image.inner).collect();
+
+        D10Image::join_vertical(&images, max_overlap.unwrap_or(0)).map(|image| image.into())
+    }
+
+    pub fn draw_text(
+        &mut self,
+        x: i32,
+        y: i32,
+        text: &str,
+        color: Option<&Rgb>,
+        scale: Option<u32>,
+    ) {
+        let color = color.map(|c| c.inner).unwrap_or(D10Rgb::BLACK);
+        self.inner.draw_text(x, y, text, color, scale.unwrap_or(1));
+    }
+
     pub fn blend(
         &self,
         image: &Image,
@@ -323,11 +916,228 @@ impl Image {
         Ok(self.inner.blend(&image.inner, blend_op, intensity).into())
     }
 
+    pub fn diff_visualize(
+        &self,
+        other: &Image,
+        mode: Option<&str>,
+        threshold: Option<f32>,
+    ) -> PyResult<Image> {
+        let mode: DiffMode = mode.unwrap_or("default").parse().py_err()?;
+        let threshold = threshold.unwrap_or(DEFAULT_DIFF_THRESHOLD);
+        Ok(self
+            .inner
+            .diff_visualize(&other.inner, mode, threshold)
+            .into())
+    }
+
+    pub fn content_hash(&self, algorithm: Option<&str>) -> PyResult<String> {
+        let algorithm: HashAlgorithm = algorithm.unwrap_or("default").parse().py_err()?;
+        Ok(self.inner.content_hash(algorithm))
+    }
+
+    pub fn embed_data(&self, data: &[u8], bits_per_channel: Option<u8>) -> PyResult<Image> {
+        let bits_per_channel = bits_per_channel.unwrap_or(1);
+        Ok(self
+            .inner
+            .embed_data(data, bits_per_channel)
+            .py_err()?
+            .into())
+    }
+
+    pub fn extract_data(&self, py: Python) -> PyResult<Py<PyBytes>> {
+        let data = self.inner.extract_data().py_err()?;
+        Ok(PyBytes::new(py, &data).into())
+    }
+
+    pub fn embed_invisible_watermark(&self, key: u64, strength: Option<f32>) -> Image {
+        let strength = strength.unwrap_or(DEFAULT_WATERMARK_STRENGTH);
+        self.inner.embed_invisible_watermark(key, strength).into()
+    }
+
+    pub fn detect_invisible_watermark(&self, key: u64) -> f32 {
+        self.inner.detect_invisible_watermark(key)
+    }
+
+    pub fn threshold(&self, value: Option<f32>) -> Image {
+        let value = value.unwrap_or_else(|| self.inner.otsu_threshold());
+        self.inner.threshold(value).into()
+    }
+
+    pub fn otsu_threshold(&self) -> f32 {
+        self.inner.otsu_threshold()
+    }
+
+    pub fn adaptive_threshold(&self, radius: Option<u32>, offset: Option<f32>) -> Image {
+        self.inner
+            .adaptive_threshold(radius.unwrap_or(15), offset.unwrap_or(0.05))
+            .into()
+    }
+
+    pub fn displace(
+        &self,
+        dx_map: &Image,
+        dy_map: Option<&Image>,
+        scale: Option<f32>,
+        filter: Option<&str>,
+    ) -> PyResult<Image> {
+        let filter = match filter {
+            Some(filter) => filter.parse().py_err()?,
+            None => FilterMode::Bilinear,
+        };
+        Ok(self
+            .inner
+            .displace(
+                &dx_map.inner,
+                &dy_map.unwrap_or(dx_map).inner,
+                scale.unwrap_or(10.0),
+                filter,
+            )
+            .into())
+    }
+
+    pub fn to_polar(&self, filter: Option<&str>) -> PyResult<Image> {
+        let filter = match filter {
+            Some(filter) => filter.parse().py_err()?,
+            None => FilterMode::Bilinear,
+        };
+        Ok(self.inner.to_polar(filter).into())
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    pub fn from_polar(&self, filter: Option<&str>) -> PyResult<Image> {
+        let filter = match filter {
+            Some(filter) => filter.parse().py_err()?,
+            None => FilterMode::Bilinear,
+        };
+        Ok(self.inner.from_polar(filter).into())
+    }
+
+    pub fn swirl(&self, degrees: f32, filter: Option<&str>) -> PyResult<Image> {
+        let filter = match filter {
+            Some(filter) => filter.parse().py_err()?,
+            None => FilterMode::Bilinear,
+        };
+        Ok(self.inner.swirl(degrees, filter).into())
+    }
+
+    pub fn fisheye(&self, strength: f32, filter: Option<&str>) -> PyResult<Image> {
+        let filter = match filter {
+            Some(filter) => filter.parse().py_err()?,
+            None => FilterMode::Bilinear,
+        };
+        Ok(self.inner.fisheye(strength, filter).into())
+    }
+
+    pub fn stereographic_project(
+        &self,
+        fov: Option<f32>,
+        yaw: Option<f32>,
+        pitch: Option<f32>,
+    ) -> Image {
+        self.inner
+            .stereographic_project(
+                fov.unwrap_or(270.0),
+                yaw.unwrap_or(0.0),
+                pitch.unwrap_or(-90.0),
+            )
+            .into()
+    }
+
+    pub fn rectilinear_project(
+        &self,
+        fov: Option<f32>,
+        yaw: Option<f32>,
+        pitch: Option<f32>,
+    ) -> Image {
+        self.inner
+            .rectilinear_project(
+                fov.unwrap_or(90.0),
+                yaw.unwrap_or(0.0),
+                pitch.unwrap_or(0.0),
+            )
+            .into()
+    }
+
+    pub fn equirect_to_cubemap(&self, face_size: u32) -> Vec<Image> {
+        self.inner
+            .equirect_to_cubemap(face_size)
+            .into_iter()
+            .map(|image| image.into())
+            .collect()
+    }
+
+    #[staticmethod]
+    pub fn cubemap_to_equirect(faces: Vec<PyRef<Image>>, width: u32) -> PyResult<Image> {
+        let faces: Vec<&D10Image> = faces.iter().map(|image| &image.inner).collect();
+
+        let faces: &[&D10Image; 6] = faces.as_slice().try_into().map_err(|_| {
+            PyOSError::new_err(format!("Expected 6 cube faces, got {}", faces.len()))
+        })?;
+
+        Ok(D10Image::cubemap_to_equirect(faces, width).into())
+    }
+
+    pub fn perspective_warp(
+        &self,
+        corners: [(f32, f32); 4],
+        width: u32,
+        height: u32,
+        filter: Option<&str>,
+    ) -> PyResult<Image> {
+        let filter = match filter {
+            Some(filter) => filter.parse().py_err()?,
+            None => FilterMode::Bilinear,
+        };
+        Ok(self
+            .inner
+            .perspective_warp(corners, width, height, filter)
+            .into())
+    }
+
+    pub fn detect_qr_codes(&self, py: Python) -> Vec<QrCode> {
+        self.inner
+            .detect_qr_codes()
+            .into_iter()
+            .map(|code| {
+                (
+                    PyBytes::new(py, &code.payload).into(),
+                    code.version,
+                    code.corners.to_vec(),
+                )
+            })
+            .collect()
+    }
+
     pub fn stretch_contrast(&self, threshold: Option<f32>) -> PyResult<Image> {
         let threshold = threshold.unwrap_or(0.5);
         Ok(self.inner.stretch_contrast(threshold).into())
     }
 
+    pub fn decode_transfer(&self, transfer: &str) -> PyResult<Image> {
+        let transfer = transfer.parse().py_err()?;
+        Ok(self.inner.decode_transfer(transfer).into())
+    }
+
+    pub fn encode_transfer(&self, transfer: &str) -> PyResult<Image> {
+        let transfer = transfer.parse().py_err()?;
+        Ok(self.inner.encode_transfer(transfer).into())
+    }
+
+    pub fn auto_levels(
+        &self,
+        clip_low_pct: Option<f32>,
+        clip_high_pct: Option<f32>,
+        per_channel: Option<bool>,
+    ) -> Image {
+        self.inner
+            .auto_levels(
+                clip_low_pct.unwrap_or(0.1),
+                clip_high_pct.unwrap_or(0.1),
+                per_channel.unwrap_or(false),
+            )
+            .into()
+    }
+
     pub fn optimize_saturation(&self, offset: Option<f32>, mode: Option<&str>) -> PyResult<Image> {
         let mode: SaturationMode = mode.unwrap_or("hsl").parse().py_err()?;
         let offset = offset.unwrap_or(1.0);
@@ -357,14 +1167,136 @@ impl Image {
             .into())
     }
 
-    pub fn optimize_lightness(
+    pub fn optimize_lightness(&self, factor: f32) -> PyResult<Image> {
+        Ok(self.inner.optimize_lightness(factor).into())
+    }
+
+    pub fn auto_enhance(&self, strength: Option<f32>) -> Image {
+        self.inner.auto_enhance(strength.unwrap_or(1.0)).into()
+    }
+
+    pub fn portrait_smooth(&self, strength: Option<f32>) -> Image {
+        self.inner.portrait_smooth(strength.unwrap_or(0.5)).into()
+    }
+
+    pub fn graduated_filter(
         &self,
-        factor: f32,
-    ) -> PyResult<Image> {
-        Ok(self
-            .inner
-            .optimize_lightness(factor)
-            .into())
+        angle: Option<f32>,
+        start: Option<f32>,
+        end: Option<f32>,
+        exposure: Option<f32>,
+        saturation: Option<f32>,
+        temperature: Option<f32>,
+    ) -> Image {
+        let adjustment = Adjustment {
+            exposure: exposure.unwrap_or(0.0),
+            saturation: saturation.unwrap_or(0.0),
+            temperature: temperature.unwrap_or(0.0),
+        };
+
+        self.inner
+            .graduated_filter(
+                angle.unwrap_or(0.0),
+                start.unwrap_or(0.0),
+                end.unwrap_or(1.0),
+                &adjustment,
+            )
+            .into()
+    }
+
+    pub fn emboss(
+        &self,
+        azimuth: Option<f32>,
+        elevation: Option<f32>,
+        depth: Option<f32>,
+    ) -> Image {
+        self.inner
+            .emboss(
+                azimuth.unwrap_or(135.0),
+                elevation.unwrap_or(45.0),
+                depth.unwrap_or(1.0),
+            )
+            .into()
+    }
+
+    pub fn height_to_normal_map(&self, strength: Option<f32>) -> Image {
+        self.inner
+            .height_to_normal_map(strength.unwrap_or(1.0))
+            .into()
+    }
+
+    pub fn height_to_ao(
+        &self,
+        samples: Option<u32>,
+        radius: Option<f32>,
+        strength: Option<f32>,
+    ) -> Image {
+        self.inner
+            .height_to_ao(
+                samples.unwrap_or(16),
+                radius.unwrap_or(8.0),
+                strength.unwrap_or(1.0),
+            )
+            .into()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn spotlight(
+        &self,
+        x: f32,
+        y: f32,
+        radius: Option<f32>,
+        height: Option<f32>,
+        color: Option<&Rgb>,
+        intensity: Option<f32>,
+        ambient: Option<f32>,
+    ) -> Image {
+        let lights = [
+            LightSpec::Ambient {
+                color: D10Rgb::WHITE,
+                intensity: ambient.unwrap_or(0.2),
+            },
+            LightSpec::Point {
+                x,
+                y,
+                height: height.unwrap_or(0.2),
+                radius: radius.unwrap_or(0.5),
+                color: color.map(|c| c.inner).unwrap_or(D10Rgb::WHITE),
+                intensity: intensity.unwrap_or(1.0),
+            },
+        ];
+
+        self.inner.apply_lighting(&lights).into()
+    }
+
+    pub fn directional_light(
+        &self,
+        azimuth: f32,
+        elevation: Option<f32>,
+        falloff: Option<f32>,
+        color: Option<&Rgb>,
+        intensity: Option<f32>,
+        ambient: Option<f32>,
+    ) -> Image {
+        let lights = [
+            LightSpec::Ambient {
+                color: D10Rgb::WHITE,
+                intensity: ambient.unwrap_or(0.2),
+            },
+            LightSpec::Directional {
+                azimuth,
+                elevation: elevation.unwrap_or(45.0),
+                falloff: falloff.unwrap_or(0.0),
+                color: color.map(|c| c.inner).unwrap_or(D10Rgb::WHITE),
+                intensity: intensity.unwrap_or(1.0),
+            },
+        ];
+
+        self.inner.apply_lighting(&lights).into()
+    }
+
+    pub fn enhance_sky(&self, strength: Option<f32>) -> Image {
+        self.inner.enhance_sky(strength.unwrap_or(0.5)).into()
     }
 
     pub fn white_balance(&self, threshold: Option<f32>) -> PyResult<Image> {
@@ -736,6 +1668,23 @@ impl From<D10Image> for Image {
     }
 }
 
+fn segmentation_hint(
+    rect: Option<(u32, u32, u32, u32)>,
+    scribbles: Option<&Image>,
+) -> PyResult<SegmentationHint> {
+    match (rect, scribbles) {
+        (Some((x, y, width, height)), None) => {
+            Ok(SegmentationHint::Rect(Region::new(x, y, width, height)))
+        }
+        (None, Some(scribbles)) => Ok(SegmentationHint::Scribbles(
+            scribbles.inner.buffer().clone(),
+        )),
+        _ => Err(PyOSError::new_err(
+            "Either a rect or scribbles are required for segmentation",
+        )),
+    }
+}
+
 #[pyclass]
 pub struct EncodingFormat {
     pub inner: D10EncodingFormat,
@@ -772,6 +1721,8 @@ impl EncodingFormat {
         color_type: Option<&str>,
         compression: Option<&str>,
         filter: Option<&str>,
+        interlaced: Option<bool>,
+        palette: Option<&Palette>,
     ) -> PyResult<EncodingFormat> {
         let color_type = match color_type {
             Some(v) => v.parse().py_err()?,
@@ -792,15 +1743,27 @@ impl EncodingFormat {
                 color_type,
                 compression,
                 filter,
+                interlaced: interlaced.unwrap_or(false),
+                palette: palette.map(|p| p.inner.clone()),
             },
         })
     }
 
     #[staticmethod]
-    fn gif() -> EncodingFormat {
-        EncodingFormat {
-            inner: D10EncodingFormat::Gif,
-        }
+    fn gif(
+        palette: Option<&Palette>,
+        dither: Option<&str>,
+        transparency_threshold: Option<f32>,
+    ) -> PyResult<EncodingFormat> {
+        let dither = dither.unwrap_or("default").parse().py_err()?;
+
+        Ok(EncodingFormat {
+            inner: D10EncodingFormat::Gif {
+                palette: palette.map(|p| p.inner.clone()),
+                dither,
+                transparency_threshold: transparency_threshold.unwrap_or(0.5),
+            },
+        })
     }
 
     #[staticmethod]
@@ -841,6 +1804,24 @@ impl EncodingFormat {
             },
         })
     }
+
+    #[staticmethod]
+    fn dds() -> EncodingFormat {
+        EncodingFormat {
+            inner: D10EncodingFormat::Dds,
+        }
+    }
+
+    #[staticmethod]
+    fn jxl(quality: Option<u8>, effort: Option<u8>, lossless: Option<bool>) -> EncodingFormat {
+        EncodingFormat {
+            inner: D10EncodingFormat::Jxl {
+                quality: quality.unwrap_or(90),
+                effort: effort.unwrap_or(7),
+                lossless: lossless.unwrap_or(true),
+            },
+        }
+    }
 }
 
 #[cfg(feature = "numpy")]
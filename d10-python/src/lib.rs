@@ -3,6 +3,7 @@
 
 mod color;
 mod image;
+mod palette;
 
 use pyo3::exceptions::PyOSError;
 use pyo3::prelude::*;
@@ -48,6 +49,7 @@ fn d10(_py: Python, m: &PyModule) -> PyResult<()> {
 
     m.add_class::<image::Image>()?;
     m.add_class::<image::EncodingFormat>()?;
+    m.add_class::<palette::Palette>()?;
 
     #[pyfn(m)]
     #[pyo3(name = "Lab")]
@@ -0,0 +1,81 @@
+use pyo3::prelude::*;
+
+use d10::{load_palette, save_palette, Image as D10Image, Palette as D10Palette};
+
+use crate::color::Rgb;
+use crate::image::Image;
+use crate::IntoPyErr;
+
+#[pyclass]
+#[derive(Clone)]
+pub struct Palette {
+    pub inner: D10Palette,
+}
+
+#[pymethods]
+impl Palette {
+    #[new]
+    fn new(colors: Option<Vec<Rgb>>, name: Option<String>) -> Palette {
+        let colors = colors.unwrap_or_default();
+
+        let mut palette = D10Palette::from_colors(colors.into_iter().map(|c| c.inner).collect());
+        palette.name = name;
+
+        palette.into()
+    }
+
+    #[staticmethod]
+    fn open(path: &str) -> PyResult<Palette> {
+        Ok(load_palette(path).py_err()?.into())
+    }
+
+    fn save(&self, path: &str) -> PyResult<()> {
+        save_palette(path, &self.inner).py_err()
+    }
+
+    #[staticmethod]
+    fn from_image(image: &Image) -> Palette {
+        D10Palette::from_buffer(image.inner.buffer()).into()
+    }
+
+    fn to_image(&self) -> Image {
+        D10Image::new_from_buffer(self.inner.to_buffer()).into()
+    }
+
+    fn push(&mut self, color: &Rgb, name: Option<&str>) {
+        self.inner.push(color.inner, name);
+    }
+
+    #[getter]
+    fn get_name(&self) -> Option<String> {
+        self.inner.name.clone()
+    }
+
+    #[setter]
+    fn set_name(&mut self, name: Option<String>) {
+        self.inner.name = name;
+    }
+
+    #[getter]
+    fn get_colors(&self) -> Vec<Rgb> {
+        self.inner.colors().iter().map(|c| c.into()).collect()
+    }
+
+    #[getter]
+    fn get_names(&self) -> Vec<Option<String>> {
+        self.inner
+            .iter()
+            .map(|(_, name)| name.map(|n| n.to_owned()))
+            .collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+impl From<D10Palette> for Palette {
+    fn from(palette: D10Palette) -> Palette {
+        Palette { inner: palette }
+    }
+}
@@ -0,0 +1,392 @@
+use std::fs::File;
+use std::io::{BufWriter, Cursor, Read, Seek, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::codecs::{decode_animation, encode_animation, DecodedAnimation};
+use crate::ops::{interpolate_frames, FrameInterpolation};
+use crate::{DecodingError, EncodingError, EncodingFormat, FilterMode, Image};
+
+/// A single frame of an `Animation`
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub image: Image,
+    /// How long the frame is shown
+    pub delay: Duration,
+}
+
+impl Frame {
+    pub fn new(image: Image, delay: Duration) -> Frame {
+        Frame { image, delay }
+    }
+}
+
+/// Frames of an animated image like an animated gif or APNG
+///
+/// Every frame is a fully composed image, so all ops for still images can be applied to it.
+/// Encoders only store the changed areas between the frames, which makes the in memory
+/// representation independent from the optimizations of the file.
+#[derive(Clone, Debug)]
+pub struct Animation {
+    pub frames: Vec<Frame>,
+    /// Number of times the animation is played with 0 meaning forever
+    pub loop_count: u32,
+}
+
+impl Animation {
+    pub fn new(frames: Vec<Frame>) -> Animation {
+        Animation {
+            frames,
+            loop_count: 0,
+        }
+    }
+
+    /// Create an animation with a single frame
+    pub fn from_image(image: Image) -> Animation {
+        Animation::new(vec![Frame::new(image, Duration::ZERO)])
+    }
+
+    /// Open all frames of an animated gif or APNG
+    ///
+    /// Still images result in a single frame.
+    pub fn open<P>(path: P) -> Result<Animation, DecodingError>
+    where
+        P: AsRef<Path>,
+    {
+        Self::read_from(File::open(path)?)
+    }
+
+    pub fn read_from_buffer(buffer: &[u8]) -> Result<Animation, DecodingError> {
+        Self::read_from(Cursor::new(buffer))
+    }
+
+    fn read_from<T>(reader: T) -> Result<Animation, DecodingError>
+    where
+        T: Read + Seek,
+    {
+        let DecodedAnimation { frames, loop_count } = decode_animation(reader)?;
+
+        let frames = frames
+            .into_iter()
+            .map(|frame| Frame::new(Image::new_from_buffer(frame.buffer), frame.delay))
+            .collect();
+
+        Ok(Animation { frames, loop_count })
+    }
+
+    /// Save the animation with the format detected from the file extension
+    ///
+    /// See `save_to_buffer()` for the supported formats.
+    pub fn save<P>(&self, path: P) -> Result<(), EncodingError>
+    where
+        P: AsRef<Path>,
+    {
+        let format = EncodingFormat::from_path(path.as_ref())?;
+
+        let mut w = BufWriter::new(File::create(path)?);
+        self.save_to_writer(&mut w, format)?;
+        w.flush()?;
+
+        Ok(())
+    }
+
+    pub fn save_to_writer<W>(&self, w: &mut W, format: EncodingFormat) -> Result<(), EncodingError>
+    where
+        W: Write,
+    {
+        let frames: Vec<_> = self
+            .frames
+            .iter()
+            .map(|frame| (frame.image.buffer(), frame.delay))
+            .collect();
+
+        encode_animation(w, &frames, self.loop_count, format)
+    }
+
+    /// Encode all frames which must have the same size
+    ///
+    /// Only gif and png, which results in an APNG, are supported.
+    pub fn save_to_buffer(&self, format: EncodingFormat) -> Result<Vec<u8>, EncodingError> {
+        let mut out = vec![];
+        self.save_to_writer(&mut out, format)?;
+        Ok(out)
+    }
+
+    /// Width of the first frame or 0 for empty animations
+    pub fn width(&self) -> u32 {
+        self.frames.first().map_or(0, |frame| frame.image.width())
+    }
+
+    /// Height of the first frame or 0 for empty animations
+    pub fn height(&self) -> u32 {
+        self.frames.first().map_or(0, |frame| frame.image.height())
+    }
+
+    /// Sum of the delays of all frames
+    pub fn duration(&self) -> Duration {
+        self.frames.iter().map(|frame| frame.delay).sum()
+    }
+
+    pub fn with_loop_count(mut self, loop_count: u32) -> Animation {
+        self.loop_count = loop_count;
+        self
+    }
+
+    /// Apply `func` to the image of every frame while keeping the delays
+    pub fn map_frames<F>(&self, mut func: F) -> Animation
+    where
+        F: FnMut(&Image) -> Image,
+    {
+        Animation {
+            frames: self
+                .frames
+                .iter()
+                .map(|frame| Frame::new(func(&frame.image), frame.delay))
+                .collect(),
+            loop_count: self.loop_count,
+        }
+    }
+
+    pub fn resize(&self, new_width: u32, new_height: u32, filter: FilterMode) -> Animation {
+        self.map_frames(|image| image.resize(new_width, new_height, filter))
+    }
+
+    pub fn crop(&self, offset_x: u32, offset_y: u32, width: u32, height: u32) -> Animation {
+        self.map_frames(|image| image.crop(offset_x, offset_y, width, height))
+    }
+
+    /// Crop black bars at the borders of all frames
+    ///
+    /// The bars are detected in every frame and only the area that is a bar in all frames gets
+    /// removed, so dark scenes don't cut away content of the others.
+    pub fn remove_black_bars(&self, threshold: f32) -> Animation {
+        let Some(first) = self.frames.first() else {
+            return self.clone();
+        };
+
+        let r = first.image.detect_black_bars(threshold);
+        let (left, top, right, bottom) = self.frames[1..].iter().fold(
+            (r.x, r.y, r.x + r.width, r.y + r.height),
+            |(left, top, right, bottom), frame| {
+                let r = frame.image.detect_black_bars(threshold);
+                (
+                    left.min(r.x),
+                    top.min(r.y),
+                    right.max(r.x + r.width),
+                    bottom.max(r.y + r.height),
+                )
+            },
+        );
+
+        self.crop(left, top, right - left, bottom - top)
+    }
+
+    /// Resample the animation to a constant frame rate
+    ///
+    /// New frames are computed between the original frames with `method`, e.g. to smooth low fps
+    /// gifs. The last frame is interpolated towards the first one if the animation loops.
+    /// Frames without delay are skipped. Gif stores delays in 1/100 seconds, so frame rates that
+    /// don't divide 100 can't be kept exactly.
+    pub fn retime(&self, fps: f32, method: FrameInterpolation) -> Animation {
+        let frames: Vec<&Frame> = self.frames.iter().filter(|f| !f.delay.is_zero()).collect();
+        let duration = self.duration().as_secs_f64();
+
+        if frames.is_empty() || !fps.is_finite() || fps <= 0.0 {
+            return self.clone();
+        }
+
+        let step = 1.0 / fps as f64;
+        let count = ((duration / step).round() as usize).max(1);
+
+        let mut result = Vec::with_capacity(count);
+        let mut index = 0;
+        let mut start = 0.0;
+
+        for i in 0..count {
+            let time = i as f64 * step;
+
+            while index + 1 < frames.len() && time >= start + frames[index].delay.as_secs_f64() {
+                start += frames[index].delay.as_secs_f64();
+                index += 1;
+            }
+
+            let frame = frames[index];
+            let next = match frames.get(index + 1) {
+                Some(next) => next,
+                None if self.loop_count != 1 => frames[0],
+                None => frame,
+            };
+
+            let t = ((time - start) / frame.delay.as_secs_f64()).clamp(0.0, 1.0) as f32;
+
+            let image = if t <= 0.0 {
+                frame.image.clone()
+            } else {
+                interpolate_frames(frame.image.buffer(), next.image.buffer(), t, method)
+                    .map_or_else(
+                        || frame.image.clone(),
+                        |buffer| Image::new_from_buffer_with_meta(&frame.image, buffer),
+                    )
+            };
+
+            result.push(Frame::new(image, Duration::from_secs_f64(step)));
+        }
+
+        Animation {
+            frames: result,
+            loop_count: self.loop_count,
+        }
+    }
+
+    /// Merge consecutive frames with identical pixels into one frame showing them for their
+    /// combined delays
+    ///
+    /// Ops like `resize()` or a reduced palette often make frames identical that differed before.
+    pub fn optimize(&self) -> Animation {
+        let mut frames: Vec<Frame> = Vec::with_capacity(self.frames.len());
+
+        for frame in &self.frames {
+            match frames.last_mut() {
+                Some(last)
+                    if last.image.width() == frame.image.width()
+                        && last.image.data() == frame.image.data() =>
+                {
+                    last.delay += frame.delay;
+                }
+                _ => frames.push(frame.clone()),
+            }
+        }
+
+        Animation {
+            frames,
+            loop_count: self.loop_count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rgb;
+
+    fn test_animation() -> Animation {
+        let colors = [Rgb::RED, Rgb::RED, Rgb::BLUE, Rgb::GREEN, Rgb::GREEN];
+
+        Animation::new(
+            colors
+                .iter()
+                .map(|c| {
+                    let mut image = Image::new_with_color(8, 6, Rgb::WHITE);
+                    image.put_pixel(2, 3, *c);
+                    Frame::new(image, Duration::from_millis(50))
+                })
+                .collect(),
+        )
+        .with_loop_count(2)
+    }
+
+    #[test]
+    fn test_map_frames() {
+        let animation = test_animation();
+
+        let result = animation.resize(4, 3, FilterMode::Nearest).crop(1, 1, 2, 2);
+        assert_eq!((result.width(), result.height()), (2, 2));
+        assert_eq!(result.frames.len(), 5);
+        assert_eq!(result.loop_count, 2);
+        assert_eq!(result.duration(), Duration::from_millis(250));
+
+        let flipped = animation.map_frames(|image| image.flip_horizontal());
+        assert_eq!(flipped.frames[2].image.get_pixel(5, 3), &Rgb::BLUE);
+    }
+
+    #[test]
+    fn test_optimize() {
+        let result = test_animation().optimize();
+
+        let delays: Vec<_> = result.frames.iter().map(|f| f.delay.as_millis()).collect();
+        assert_eq!(delays, [100, 50, 100]);
+        assert_eq!(result.frames[1].image.get_pixel(2, 3), &Rgb::BLUE);
+        assert_eq!(result.duration(), test_animation().duration());
+    }
+
+    #[test]
+    fn test_remove_black_bars() {
+        let frame = |x, y| {
+            let mut image = Image::new_with_color(20, 10, Rgb::BLACK);
+            image.put_pixel(x, y, Rgb::WHITE);
+            Frame::new(image, Duration::from_millis(100))
+        };
+
+        let animation = Animation::new(vec![frame(4, 2), frame(12, 6), frame(8, 4)]);
+
+        let result = animation.remove_black_bars(0.1);
+        assert_eq!((result.width(), result.height()), (9, 5));
+        assert_eq!(result.frames[1].image.get_pixel(8, 4), &Rgb::WHITE);
+    }
+
+    #[test]
+    fn test_retime() {
+        let animation = Animation::new(vec![
+            Frame::new(
+                Image::new_with_color(2, 2, Rgb::BLACK),
+                Duration::from_millis(200),
+            ),
+            Frame::new(Image::new_with_color(2, 2, Rgb::RED), Duration::ZERO),
+            Frame::new(
+                Image::new_with_color(2, 2, Rgb::WHITE),
+                Duration::from_millis(200),
+            ),
+        ]);
+
+        let result = animation.retime(10.0, FrameInterpolation::CrossFade);
+        assert_eq!(result.frames.len(), 4);
+        assert_eq!(result.duration(), animation.duration());
+
+        let values: Vec<_> = result
+            .frames
+            .iter()
+            .map(|f| f.image.get_pixel(0, 0).red())
+            .collect();
+        // The looping animation fades back to black
+        assert_eq!(values, [0.0, 0.5, 1.0, 0.5]);
+
+        let result = animation
+            .with_loop_count(1)
+            .retime(10.0, FrameInterpolation::OpticalFlow);
+        assert_eq!(result.frames[3].image.get_pixel(1, 1), &Rgb::WHITE);
+
+        let empty = Animation::new(vec![]).retime(30.0, FrameInterpolation::CrossFade);
+        assert!(empty.frames.is_empty());
+    }
+
+    #[test]
+    fn test_save_animation() {
+        let animation = test_animation();
+
+        for format in [EncodingFormat::gif_default(), EncodingFormat::png_default()] {
+            let data = animation.save_to_buffer(format).unwrap();
+            let result = Animation::read_from_buffer(&data).unwrap();
+
+            assert_eq!(result.loop_count, 2);
+            assert_eq!(result.frames.len(), 5);
+            for (frame, expected) in result.frames.iter().zip(&animation.frames) {
+                assert_eq!(frame.image.data(), expected.image.data());
+                assert_eq!(frame.delay, expected.delay);
+            }
+        }
+
+        let err = animation
+            .save_to_buffer(EncodingFormat::jpeg_default())
+            .unwrap_err();
+        assert_eq!(err.code(), "invalid_config");
+
+        let still = Animation::read_from_buffer(
+            &Image::new_with_color(3, 2, Rgb::RED)
+                .save_to_buffer(EncodingFormat::bmp_default())
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(still.frames.len(), 1);
+        assert_eq!((still.width(), still.height()), (3, 2));
+    }
+}
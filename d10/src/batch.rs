@@ -0,0 +1,289 @@
+//! Process many images in parallel
+//!
+//! ```no_run
+//! use d10::batch::{process, BatchOptions};
+//! use d10::{D10Error, FilterMode};
+//!
+//! let jobs = vec![("in/a.jpg", "out/a.png"), ("in/b.jpg", "out/b.png")];
+//!
+//! let report = process(jobs, &BatchOptions::new(), |img| {
+//!     Ok::<_, D10Error>(img.resize(100, 100, FilterMode::Auto))
+//! });
+//!
+//! for failure in &report.failures {
+//!     eprintln!("{}: {}", failure.input.display(), failure.error);
+//! }
+//! ```
+
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use thiserror::Error;
+
+use crate::{DecodingError, EncodeOptions, EncodingError, EncodingFormat, Image};
+
+type ProgressCallback = Box<dyn Fn(&Progress) + Send + Sync>;
+
+/// Error of a single image of a batch
+#[derive(Debug, Error)]
+pub enum BatchError {
+    #[error(transparent)]
+    Decoding(#[from] DecodingError),
+    #[error(transparent)]
+    Encoding(#[from] EncodingError),
+    #[error(transparent)]
+    Process(Box<dyn Error + Send + Sync>),
+}
+
+impl BatchError {
+    /// Stable identifier of the kind of error
+    pub fn code(&self) -> &'static str {
+        match self {
+            BatchError::Decoding(err) => err.code(),
+            BatchError::Encoding(err) => err.code(),
+            BatchError::Process(_) => "process",
+        }
+    }
+}
+
+/// State of a batch after an image was finished
+#[derive(Debug)]
+pub struct Progress<'a> {
+    /// Number of finished images including failed ones
+    pub done: usize,
+    pub total: usize,
+    pub input: &'a Path,
+    pub error: Option<&'a BatchError>,
+}
+
+pub struct BatchOptions {
+    threads: usize,
+    format: Option<EncodingFormat>,
+    encode_options: EncodeOptions,
+    progress: Option<ProgressCallback>,
+}
+
+impl BatchOptions {
+    pub fn new() -> BatchOptions {
+        BatchOptions {
+            threads: 0,
+            format: None,
+            encode_options: EncodeOptions::default(),
+            progress: None,
+        }
+    }
+
+    /// Number of worker threads with `0` using the available parallelism
+    pub fn threads(mut self, threads: usize) -> BatchOptions {
+        self.threads = threads;
+        self
+    }
+
+    /// Encode all images with this format instead of detecting it from the output path
+    pub fn format(mut self, format: EncodingFormat) -> BatchOptions {
+        self.format = Some(format);
+        self
+    }
+
+    pub fn encode_options(mut self, encode_options: EncodeOptions) -> BatchOptions {
+        self.encode_options = encode_options;
+        self
+    }
+
+    /// Called from the worker threads whenever an image was saved or failed
+    pub fn on_progress<F>(mut self, callback: F) -> BatchOptions
+    where
+        F: Fn(&Progress) + Send + Sync + 'static,
+    {
+        self.progress = Some(Box::new(callback));
+        self
+    }
+
+    fn thread_count(&self, jobs: usize) -> usize {
+        let threads = if self.threads == 0 {
+            thread::available_parallelism().map_or(1, |n| n.get())
+        } else {
+            self.threads
+        };
+
+        threads.clamp(1, jobs.max(1))
+    }
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug)]
+pub struct BatchFailure {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub error: BatchError,
+}
+
+/// Outcome of a batch with all paths in input order
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    /// Output paths of all successfully saved images
+    pub saved: Vec<PathBuf>,
+    pub failures: Vec<BatchFailure>,
+}
+
+impl BatchReport {
+    /// Returns true if all images were processed without an error
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+fn process_one<F, E>(
+    input: &Path,
+    output: &Path,
+    options: &BatchOptions,
+    func: &F,
+) -> Result<(), BatchError>
+where
+    F: Fn(Image) -> Result<Image, E>,
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    let img = Image::open(input)?;
+    let img = func(img).map_err(|err| BatchError::Process(err.into()))?;
+    img.save_with_options(output, options.format.clone(), &options.encode_options)?;
+
+    Ok(())
+}
+
+/// Open, process and save pairs of input and output paths in parallel
+///
+/// Failing images don't stop the batch. All errors are collected in the returned report.
+pub fn process<I, P, Q, F, E>(inputs: I, options: &BatchOptions, func: F) -> BatchReport
+where
+    I: IntoIterator<Item = (P, Q)>,
+    P: Into<PathBuf>,
+    Q: Into<PathBuf>,
+    F: Fn(Image) -> Result<Image, E> + Sync,
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    let jobs: Vec<(PathBuf, PathBuf)> = inputs
+        .into_iter()
+        .map(|(input, output)| (input.into(), output.into()))
+        .collect();
+
+    let total = jobs.len();
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Result<(), BatchError>>>> =
+        Mutex::new((0..total).map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..options.thread_count(total) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some((input, output)) = jobs.get(index) else {
+                    break;
+                };
+
+                let res = process_one(input, output, options, &func);
+
+                if let Some(callback) = &options.progress {
+                    callback(&Progress {
+                        done: done.fetch_add(1, Ordering::SeqCst) + 1,
+                        total,
+                        input,
+                        error: res.as_ref().err(),
+                    });
+                }
+
+                results.lock().unwrap()[index] = Some(res);
+            });
+        }
+    });
+
+    let results = results.into_inner().unwrap();
+    let mut report = BatchReport::default();
+
+    for ((input, output), res) in jobs.into_iter().zip(results) {
+        match res {
+            Some(Err(error)) => report.failures.push(BatchFailure {
+                input,
+                output,
+                error,
+            }),
+            _ => report.saved.push(output),
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{D10Error, Rgb};
+
+    #[test]
+    fn test_process() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let mut jobs = vec![];
+        for i in 0..5 {
+            let input = dir.join(format!("in{}.png", i));
+            Image::new_with_color(4 + i, 3, Rgb::RED)
+                .save(&input)
+                .unwrap();
+            jobs.push((input, dir.join(format!("out{}.bmp", i))));
+        }
+        jobs.push((dir.join("missing.png"), dir.join("missing.bmp")));
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let progress_calls = calls.clone();
+
+        let options = BatchOptions::new().threads(3).on_progress(move |progress| {
+            assert_eq!(progress.total, 6);
+            progress_calls.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let report = process(jobs, &options, |img| {
+            Ok::<_, D10Error>(img.flip_horizontal())
+        });
+
+        assert_eq!(calls.load(Ordering::SeqCst), 6);
+        assert_eq!(report.saved.len(), 5);
+        assert_eq!(report.saved[3], dir.join("out3.bmp"));
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].input, dir.join("missing.png"));
+        assert_eq!(report.failures[0].error.code(), "io");
+
+        let img = Image::open(dir.join("out3.bmp")).unwrap();
+        assert_eq!((img.width(), img.height()), (7, 3));
+    }
+
+    #[test]
+    fn test_process_error() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let input = dir.join("in.png");
+        Image::new_with_color(2, 2, Rgb::RED).save(&input).unwrap();
+
+        let report = process(
+            [(&input, dir.join("out.png"))],
+            &BatchOptions::new(),
+            |_| Err("broken"),
+        );
+
+        assert!(!report.is_success());
+        assert!(report.saved.is_empty());
+        assert_eq!(report.failures[0].error.code(), "process");
+        assert_eq!(report.failures[0].error.to_string(), "broken");
+        assert!(!dir.join("out.png").exists());
+    }
+}
@@ -0,0 +1,95 @@
+use thiserror::Error;
+
+#[cfg(feature = "http")]
+use crate::HttpError;
+
+use crate::ops::StegoError;
+use crate::{BufferError, DecodingError, EncodingError, PaletteError, ParseEnumError};
+
+pub type D10Result<T> = Result<T, D10Error>;
+
+/// Common error type for all fallible operations of d10
+#[derive(Debug, Error)]
+pub enum D10Error {
+    #[error(transparent)]
+    Decoding(#[from] DecodingError),
+    #[error(transparent)]
+    Encoding(#[from] EncodingError),
+    #[error(transparent)]
+    Palette(#[from] PaletteError),
+    #[error(transparent)]
+    ParseEnum(#[from] ParseEnumError),
+    #[error(transparent)]
+    Buffer(#[from] BufferError),
+    #[error(transparent)]
+    Steganography(#[from] StegoError),
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    Http(#[from] HttpError),
+}
+
+impl D10Error {
+    /// Stable identifier of the kind of error
+    pub fn code(&self) -> &'static str {
+        match self {
+            D10Error::Decoding(err) => err.code(),
+            D10Error::Encoding(err) => err.code(),
+            D10Error::Palette(err) => err.code(),
+            D10Error::ParseEnum(_) => "parse_enum",
+            D10Error::Buffer(BufferError::InvalidSize { .. }) => "invalid_buffer_size",
+            D10Error::Buffer(BufferError::WrongDataLength { .. }) => "wrong_data_length",
+            D10Error::Steganography(err) => err.code(),
+            #[cfg(feature = "http")]
+            D10Error::Http(err) => err.code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+    use crate::{FilterMode, Image};
+
+    fn open_and_parse(buffer: &[u8], filter: &str) -> D10Result<Image> {
+        let filter: FilterMode = filter.parse()?;
+        let img = Image::read_from_buffer(buffer)?;
+
+        Ok(img.resize(1, 1, filter))
+    }
+
+    #[test]
+    fn test_error_causes() {
+        let err = open_and_parse(&[], "bad_filter").unwrap_err();
+        assert!(matches!(err, D10Error::ParseEnum(_)));
+        assert_eq!(err.code(), "parse_enum");
+
+        let err = open_and_parse(&[1, 2, 3], "nearest").unwrap_err();
+        assert!(matches!(
+            err,
+            D10Error::Decoding(DecodingError::UnknownFormat)
+        ));
+        assert_eq!(err.code(), "unknown_format");
+
+        // Png with a broken checksum in the header chunk
+        let broken_png = [
+            0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 13, b'I', b'H', b'D', b'R', 0,
+            0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0, 0, 0, 0, 0,
+        ];
+
+        let err = open_and_parse(&broken_png, "nearest").unwrap_err();
+        assert_eq!(err.code(), "codec");
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn test_buffer_error() {
+        let err: D10Error = Image::try_new_from_raw(2, 2, vec![]).unwrap_err().into();
+        assert_eq!(err.code(), "wrong_data_length");
+        assert_eq!(err.to_string(), "Data has wrong length: 2x2=4 data has 0");
+
+        let err: D10Error = Image::try_new(u32::MAX, u32::MAX).unwrap_err().into();
+        assert_eq!(err.code(), "invalid_buffer_size");
+    }
+}
@@ -0,0 +1,186 @@
+use std::io::{self, Read};
+
+use thiserror::Error;
+
+use crate::{D10Result, Format, Image};
+
+/// Default limit for the size of a downloaded image: 64 MiB
+pub const DEFAULT_MAX_DOWNLOAD_SIZE: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Error)]
+pub enum HttpError {
+    #[error("Request failed: {0}")]
+    Request(#[source] Box<ureq::Error>),
+    #[error("Response is larger than the limit of {limit} bytes")]
+    TooLarge { limit: u64 },
+    #[error("IO error: {0}")]
+    IoError(#[from] io::Error),
+}
+
+impl HttpError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            HttpError::Request(_) => "http_request",
+            HttpError::TooLarge { .. } => "too_large",
+            HttpError::IoError(_) => "io",
+        }
+    }
+}
+
+impl From<ureq::Error> for HttpError {
+    fn from(err: ureq::Error) -> Self {
+        HttpError::Request(Box::new(err))
+    }
+}
+
+/// Download the body of `url` and a format hint derived from the content type
+fn fetch(url: &str, max_size: u64) -> Result<(Vec<u8>, Option<Format>), HttpError> {
+    let response = ureq::get(url).call()?;
+
+    // Reject early if the server announces a body that is too large
+    if let Some(len) = response
+        .header("Content-Length")
+        .and_then(|len| len.trim().parse::<u64>().ok())
+    {
+        if len > max_size {
+            return Err(HttpError::TooLarge { limit: max_size });
+        }
+    }
+
+    let hint = response
+        .header("Content-Type")
+        .and_then(Format::from_mime_type);
+
+    let mut data = vec![];
+    response
+        .into_reader()
+        .take(max_size + 1)
+        .read_to_end(&mut data)?;
+
+    if data.len() as u64 > max_size {
+        return Err(HttpError::TooLarge { limit: max_size });
+    }
+
+    Ok((data, hint))
+}
+
+impl Image {
+    /// Download and decode an image
+    ///
+    /// The format is detected from the content and falls back to the content type of the response.
+    /// Responses larger than `DEFAULT_MAX_DOWNLOAD_SIZE` are rejected.
+    pub fn open_url(url: &str) -> D10Result<Image> {
+        Self::open_url_with_limit(url, DEFAULT_MAX_DOWNLOAD_SIZE)
+    }
+
+    pub fn open_url_with_limit(url: &str, max_size: u64) -> D10Result<Image> {
+        let (data, hint) = fetch(url, max_size)?;
+        let buffer = crate::codecs::decode_buffer_with_hint(&data, hint)?.buffer;
+
+        Ok(Self::new_from_buffer(buffer))
+    }
+
+    /// Async version of `open_url()`
+    ///
+    /// Download and decoding run on the blocking thread pool of the current tokio runtime.
+    pub async fn open_url_async(url: &str) -> D10Result<Image> {
+        Self::open_url_with_limit_async(url, DEFAULT_MAX_DOWNLOAD_SIZE).await
+    }
+
+    pub async fn open_url_with_limit_async(url: &str, max_size: u64) -> D10Result<Image> {
+        let url = url.to_owned();
+
+        match tokio::task::spawn_blocking(move || Self::open_url_with_limit(&url, max_size)).await {
+            Ok(result) => result,
+            Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+            Err(err) => Err(HttpError::from(io::Error::other(err)).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+    use crate::{D10Error, EncodingFormat, Rgb};
+
+    /// Serve a single response on a local port and return the url
+    fn serve(content_type: &'static str, body: Vec<u8>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/image", listener.local_addr().unwrap());
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                content_type,
+                body.len()
+            )
+            .unwrap();
+            stream.write_all(&body).unwrap();
+        });
+
+        url
+    }
+
+    fn png_data() -> Vec<u8> {
+        Image::new_with_color(3, 2, Rgb::RED)
+            .save_to_buffer(EncodingFormat::png_default())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_open_url() {
+        let data = png_data();
+        let img = Image::open_url(&serve("image/png", data.clone())).unwrap();
+
+        assert_eq!(img.width(), 3);
+        assert_eq!(img.height(), 2);
+        assert_eq!(img.data(), Image::read_from_buffer(&data).unwrap().data());
+    }
+
+    #[test]
+    fn test_open_url_too_large() {
+        let url = serve("image/png", png_data());
+        let err = Image::open_url_with_limit(&url, 10).unwrap_err();
+
+        assert!(matches!(
+            err,
+            D10Error::Http(HttpError::TooLarge { limit: 10 })
+        ));
+        assert_eq!(err.code(), "too_large");
+    }
+
+    #[test]
+    fn test_open_url_content_type_hint() {
+        let err = Image::open_url(&serve("image/png", vec![1; 32])).unwrap_err();
+        assert_eq!(err.code(), "codec");
+
+        let err = Image::open_url(&serve("text/html", vec![1; 32])).unwrap_err();
+        assert_eq!(err.code(), "unknown_format");
+    }
+
+    #[test]
+    fn test_open_url_async() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let url = serve("image/png", png_data());
+        let img = runtime.block_on(Image::open_url_async(&url)).unwrap();
+
+        assert_eq!(img.width(), 3);
+        assert_eq!(img.height(), 2);
+    }
+}
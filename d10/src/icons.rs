@@ -0,0 +1,274 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::codecs::{encode, encode_multi_size_ico};
+use crate::{
+    EncodingError, EncodingFormat, FilterMode, Format, IcoColorType, Image, ParseEnumError,
+    PixelBuffer, Region, ResizeOptions, Rgb,
+};
+
+/// Sizes used for ico and png icons if none are specified
+pub const DEFAULT_ICON_SIZES: [u32; 6] = [16, 32, 48, 64, 128, 256];
+
+/// Launcher icon sizes for the android density buckets
+const ANDROID_ICONS: [(&str, u32); 5] = [
+    ("mdpi", 48),
+    ("hdpi", 72),
+    ("xhdpi", 96),
+    ("xxhdpi", 144),
+    ("xxxhdpi", 192),
+];
+
+/// App icon sizes in points, their scale factors and the resulting pixel sizes for iOS
+const IOS_ICONS: [(&str, u32, u32); 12] = [
+    ("20", 2, 40),
+    ("20", 3, 60),
+    ("29", 2, 58),
+    ("29", 3, 87),
+    ("40", 2, 80),
+    ("40", 3, 120),
+    ("60", 2, 120),
+    ("60", 3, 180),
+    ("76", 1, 76),
+    ("76", 2, 152),
+    ("83.5", 2, 167),
+    ("1024", 1, 1024),
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IconSet {
+    /// A single favicon.ico with all sizes up to 256
+    Ico,
+    /// One icon-{size}x{size}.png per size
+    Png,
+    /// Launcher icons in mipmap-{density}/ic_launcher.png with the standard android sizes
+    Android,
+    /// AppIcon-{points}@{scale}x.png with the standard iOS sizes
+    Ios,
+}
+
+impl FromStr for IconSet {
+    type Err = ParseEnumError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        use IconSet::*;
+        match value {
+            "ico" | "favicon" => Ok(Ico),
+            "png" => Ok(Png),
+            "android" => Ok(Android),
+            "ios" => Ok(Ios),
+            _ => Err(ParseEnumError::new(value, "IconSet")),
+        }
+    }
+}
+
+/// An encoded icon file
+#[derive(Clone, Debug)]
+pub struct Icon {
+    /// Relative path of the file following the naming convention of the icon set
+    pub path: String,
+    pub data: Vec<u8>,
+}
+
+/// Resize an image into a square icon with transparent borders for non square images
+fn resize_icon(image: &Image, size: u32) -> PixelBuffer<Rgb> {
+    let scale = size as f32 / image.width().max(image.height()) as f32;
+    let width = ((image.width() as f32 * scale).round() as u32).clamp(1, size);
+    let height = ((image.height() as f32 * scale).round() as u32).clamp(1, size);
+
+    let options = if scale >= 1.0 {
+        ResizeOptions::new(FilterMode::Bicubic)
+    } else if size <= 32 {
+        // Small icons get blurry without sharpening
+        ResizeOptions {
+            sharpen_after: Some(0.5),
+            ..ResizeOptions::new(FilterMode::Lanczos3)
+        }
+    } else {
+        ResizeOptions::new(FilterMode::Lanczos3)
+    };
+
+    let resized = image.resize_with_options(width, height, &options);
+
+    if width == size && height == size {
+        return resized.buffer().clone();
+    }
+
+    let mut buffer = PixelBuffer::new_with_color(size, size, Rgb::NONE);
+    buffer.copy_from(
+        resized.buffer(),
+        Region::new(0, 0, width, height),
+        ((size - width) / 2, (size - height) / 2),
+    );
+    buffer
+}
+
+fn encode_png(buffer: &PixelBuffer<Rgb>) -> Result<Vec<u8>, EncodingError> {
+    let mut data = vec![];
+    encode(&mut data, buffer, EncodingFormat::png_default())?;
+    Ok(data)
+}
+
+/// Generate icons for all given icon sets
+///
+/// `sizes` is used for ico and png sets. Android and iOS sets always use their platform sizes.
+/// Every size is only resized once, even if it's used by multiple sets.
+pub fn generate_icons(
+    image: &Image,
+    sizes: &[u32],
+    sets: &[IconSet],
+) -> Result<Vec<Icon>, EncodingError> {
+    let sizes = if sizes.is_empty() {
+        &DEFAULT_ICON_SIZES[..]
+    } else {
+        sizes
+    };
+
+    let mut cache: BTreeMap<u32, PixelBuffer<Rgb>> = BTreeMap::new();
+    let mut resized = |size: u32| -> PixelBuffer<Rgb> {
+        cache
+            .entry(size)
+            .or_insert_with(|| resize_icon(image, size))
+            .clone()
+    };
+
+    let mut icons = vec![];
+
+    for set in sets {
+        match set {
+            IconSet::Ico => {
+                let mut ico_sizes: Vec<u32> = sizes
+                    .iter()
+                    .copied()
+                    .filter(|size| (1..=256).contains(size))
+                    .collect();
+                ico_sizes.sort_unstable();
+                ico_sizes.dedup();
+
+                if ico_sizes.is_empty() {
+                    return Err(EncodingError::InvalidConfig {
+                        format: Format::Ico,
+                        message: "No icon size between 1 and 256".to_owned(),
+                    });
+                }
+
+                let buffers: Vec<PixelBuffer<Rgb>> =
+                    ico_sizes.iter().map(|size| resized(*size)).collect();
+                let buffers: Vec<&PixelBuffer<Rgb>> = buffers.iter().collect();
+
+                let mut data = vec![];
+                encode_multi_size_ico(&mut data, &buffers, IcoColorType::Rgba8)?;
+
+                icons.push(Icon {
+                    path: "favicon.ico".to_owned(),
+                    data,
+                });
+            }
+            IconSet::Png => {
+                for &size in sizes {
+                    icons.push(Icon {
+                        path: format!("icon-{}x{}.png", size, size),
+                        data: encode_png(&resized(size))?,
+                    });
+                }
+            }
+            IconSet::Android => {
+                for (density, size) in ANDROID_ICONS {
+                    icons.push(Icon {
+                        path: format!("mipmap-{}/ic_launcher.png", density),
+                        data: encode_png(&resized(size))?,
+                    });
+                }
+            }
+            IconSet::Ios => {
+                for (points, scale, size) in IOS_ICONS {
+                    icons.push(Icon {
+                        path: format!("AppIcon-{}@{}x.png", points, scale),
+                        data: encode_png(&resized(size))?,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(icons)
+}
+
+/// Write icons into a directory creating subdirectories as needed
+pub fn save_icons<P>(icons: &[Icon], dir: P) -> io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    for icon in icons {
+        let path = dir.as_ref().join(&icon.path);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, &icon.data)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+
+    #[test]
+    fn test_generate_icons() {
+        let img = Image::new_with_color(300, 200, Rgb::RED);
+
+        let icons = generate_icons(
+            &img,
+            &[16, 32, 512],
+            &[IconSet::Ico, IconSet::Png, IconSet::Android, IconSet::Ios],
+        )
+        .unwrap();
+
+        let paths: Vec<&str> = icons.iter().map(|icon| icon.path.as_str()).collect();
+
+        assert_eq!(paths.len(), 1 + 3 + 5 + 12);
+        assert_eq!(
+            &paths[..4],
+            &[
+                "favicon.ico",
+                "icon-16x16.png",
+                "icon-32x32.png",
+                "icon-512x512.png"
+            ]
+        );
+        assert!(paths.contains(&"mipmap-xxxhdpi/ic_launcher.png"));
+        assert!(paths.contains(&"AppIcon-83.5@2x.png"));
+        assert!(paths.contains(&"AppIcon-60@3x.png"));
+
+        let ico = Image::read_from_buffer(&icons[0].data).unwrap();
+        assert_eq!(ico.width(), 32);
+
+        let png = Image::read_from_buffer(&icons[1].data).unwrap();
+        assert_eq!((png.width(), png.height()), (16, 16));
+
+        // Non square images are centered with transparent borders
+        assert_eq!(png.get_pixel(8, 0).alpha(), 0.0);
+        assert!(png.get_pixel(8, 8).alpha() > 0.9);
+    }
+
+    #[test]
+    fn test_invalid_ico_sizes() {
+        let img = Image::new_with_color(16, 16, Rgb::RED);
+
+        let err = generate_icons(&img, &[512], &[IconSet::Ico]).unwrap_err();
+        assert_eq!(err.code(), "invalid_config");
+    }
+
+    #[test]
+    fn test_icon_set_from_str() {
+        assert_eq!("ios".parse::<IconSet>().unwrap(), IconSet::Ios);
+        assert!("windows".parse::<IconSet>().is_err());
+    }
+}
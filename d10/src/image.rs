@@ -1,19 +1,26 @@
 use std::convert::TryInto;
+use std::fs::File;
+use std::io::Cursor;
 use std::io::Write;
 use std::path::Path;
 
-use d10_codecs::{DecodingError, EncodingError, EncodingFormat};
+use d10_codecs::{DecodingError, EncodeOptions, EncodingError, EncodingFormat};
 use d10_ops::{
-    blend_image, BalanceMode, BlendOp, DrawingMode, EdgeDetection, EqualizeMode, FilterMode,
-    SaturationMode,
+    blend_image, Adjustment, BalanceMode, BlendOp, BorderFill, BorderStyle, ClusterSpace,
+    CollageTemplate, ColorSpaceChannel, Contour, DiffMode, DrawingMode, EdgeDetection,
+    EqualizeMode, FilterMode, Gravity, HalftoneShape, HueRangeAdjustment, KMeansSegmentation,
+    LightSpec, LookPreset, MonochromeFilter, NoiseOptions, RegionDetector, ResizeOptions,
+    SaturationMode, ScanMode, SegmentationHint, SvgDocument, TraceOptions, WatermarkPosition,
+    XbrScale,
 };
 
-use crate::{ops, PixelBuffer, Rgb};
+use crate::{ops, BufferError, Color, Palette, PixelBuffer, Region, Rgb, TransferFunction};
 
 #[derive(Clone, Debug)]
 pub struct Image {
     buffer: PixelBuffer<Rgb>,
     bg_color: Option<Rgb>,
+    crop_region: Option<Region>,
 }
 
 impl Image {
@@ -21,6 +28,7 @@ impl Image {
         Image {
             buffer: PixelBuffer::new(width, height),
             bg_color: None,
+            crop_region: None,
         }
     }
 
@@ -28,6 +36,7 @@ impl Image {
         Image {
             buffer: PixelBuffer::new_with_color(width, height, color),
             bg_color: None,
+            crop_region: None,
         }
     }
 
@@ -35,13 +44,40 @@ impl Image {
         Image {
             buffer: PixelBuffer::new_from_raw(width, height, data),
             bg_color: None,
+            crop_region: None,
         }
     }
 
+    /// Creates a new image or returns an error if the size is invalid
+    pub fn try_new(width: u32, height: u32) -> Result<Image, BufferError> {
+        Ok(Self::new_from_buffer(PixelBuffer::try_new(width, height)?))
+    }
+
+    pub fn try_new_with_color(width: u32, height: u32, color: Rgb) -> Result<Image, BufferError> {
+        Ok(Self::new_from_buffer(PixelBuffer::try_new_with_color(
+            width, height, color,
+        )?))
+    }
+
+    /// Creates a new image from raw data or returns an error if the length of `data` doesn't match the size
+    pub fn try_new_from_raw(width: u32, height: u32, data: Vec<Rgb>) -> Result<Image, BufferError> {
+        Ok(Self::new_from_buffer(PixelBuffer::try_new_from_raw(
+            width, height, data,
+        )?))
+    }
+
+    /// Creates a grayscale noise texture
+    ///
+    /// See `ops::generate_noise()` for details.
+    pub fn new_noise(width: u32, height: u32, options: &NoiseOptions) -> Image {
+        Self::new_from_buffer(ops::generate_noise(width, height, options))
+    }
+
     pub fn new_from_buffer(buffer: PixelBuffer<Rgb>) -> Image {
         Image {
             buffer,
             bg_color: None,
+            crop_region: None,
         }
     }
 
@@ -58,6 +94,18 @@ impl Image {
         Image {
             buffer,
             bg_color: orig_image.bg_color,
+            crop_region: orig_image.crop_region,
+        }
+    }
+
+    /// Like `new_from_buffer_with_meta()` for operations that move pixels or change the size
+    ///
+    /// The crop region refers to the pixel positions of `orig_image` and is dropped.
+    fn new_from_buffer_with_new_geometry(orig_image: &Image, buffer: PixelBuffer<Rgb>) -> Image {
+        Image {
+            buffer,
+            bg_color: orig_image.bg_color,
+            crop_region: None,
         }
     }
 
@@ -74,6 +122,85 @@ impl Image {
         Ok(Self::new_from_buffer(buffer))
     }
 
+    /// Open a downscaled version of an image with `max_edge` pixels on its longer side
+    ///
+    /// This is much faster than opening and resizing the full image for jpeg and interlaced png files.
+    /// Images smaller than `max_edge` are not enlarged.
+    pub fn open_thumbnail<P>(path: P, max_edge: u32) -> Result<Image, DecodingError>
+    where
+        P: AsRef<Path>,
+    {
+        let buffer = crate::codecs::decode_thumbnail(File::open(path)?, max_edge)?.buffer;
+        Ok(Self::new_from_buffer(buffer).fit_thumbnail(max_edge))
+    }
+
+    pub fn read_thumbnail_from_buffer(
+        buffer: &[u8],
+        max_edge: u32,
+    ) -> Result<Image, DecodingError> {
+        let buffer = crate::codecs::decode_thumbnail(Cursor::new(buffer), max_edge)?.buffer;
+        Ok(Self::new_from_buffer(buffer).fit_thumbnail(max_edge))
+    }
+
+    /// Open all intermediate stages of an interlaced png or progressive jpeg
+    ///
+    /// Every stage has the full image size and the last one is the fully decoded image.
+    /// See `decode_progressive()` for details.
+    pub fn open_progressive<P>(path: P) -> Result<Vec<Image>, DecodingError>
+    where
+        P: AsRef<Path>,
+    {
+        let passes = crate::codecs::decode_progressive(File::open(path)?)?;
+        Ok(passes
+            .into_iter()
+            .map(|pass| Self::new_from_buffer(pass.buffer))
+            .collect())
+    }
+
+    pub fn read_progressive_from_buffer(buffer: &[u8]) -> Result<Vec<Image>, DecodingError> {
+        let passes = crate::codecs::decode_progressive(Cursor::new(buffer))?;
+        Ok(passes
+            .into_iter()
+            .map(|pass| Self::new_from_buffer(pass.buffer))
+            .collect())
+    }
+
+    fn fit_thumbnail(self, max_edge: u32) -> Image {
+        let size = self.width().max(self.height());
+
+        if size <= max_edge {
+            return self;
+        }
+
+        let scale = max_edge as f32 / size as f32;
+        let width = ((self.width() as f32 * scale).round() as u32).max(1);
+        let height = ((self.height() as f32 * scale).round() as u32).max(1);
+
+        self.resize(width, height, FilterMode::Auto)
+    }
+
+    /// Read an image from an async reader and decode it on the blocking thread pool
+    #[cfg(feature = "async")]
+    pub async fn read_async<R>(reader: R) -> Result<Image, DecodingError>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+    {
+        let buffer = crate::codecs::decode_async(reader).await?.buffer;
+        Ok(Self::new_from_buffer(buffer))
+    }
+
+    /// Open an image by mapping the file into memory
+    ///
+    /// See `codecs::decode_mmap()` for details.
+    #[cfg(feature = "mmap")]
+    pub fn open_mmap<P>(path: P) -> Result<Image, DecodingError>
+    where
+        P: AsRef<Path>,
+    {
+        let buffer = crate::codecs::decode_mmap(path)?.buffer;
+        Ok(Self::new_from_buffer(buffer))
+    }
+
     pub fn save<P>(&self, path: P) -> Result<(), EncodingError>
     where
         P: AsRef<Path>,
@@ -88,6 +215,21 @@ impl Image {
         crate::codecs::encode_to_file(path, &self.buffer, Some(format))
     }
 
+    /// Save with explicit metadata handling, e.g. to strip private data of user uploads
+    ///
+    /// The format is detected from the file extension if `format` is `None`.
+    pub fn save_with_options<P>(
+        &self,
+        path: P,
+        format: Option<EncodingFormat>,
+        options: &EncodeOptions,
+    ) -> Result<(), EncodingError>
+    where
+        P: AsRef<Path>,
+    {
+        crate::codecs::encode_to_file_with_options(path, &self.buffer, format, options)
+    }
+
     pub fn save_to_writer<W>(&self, w: &mut W, format: EncodingFormat) -> Result<(), EncodingError>
     where
         W: Write,
@@ -101,6 +243,43 @@ impl Image {
         Ok(out)
     }
 
+    /// Encode the image on the blocking thread pool and write it to an async writer
+    #[cfg(feature = "async")]
+    pub async fn save_to_async_writer<W>(
+        &self,
+        w: W,
+        format: EncodingFormat,
+    ) -> Result<(), EncodingError>
+    where
+        W: tokio::io::AsyncWrite + Unpin,
+    {
+        crate::codecs::encode_async(w, &self.buffer, format).await
+    }
+
+    /// Save by writing directly into a memory mapped file
+    ///
+    /// See `codecs::encode_mmap()` for details.
+    #[cfg(feature = "mmap")]
+    pub fn save_mmap<P>(&self, path: P, format: EncodingFormat) -> Result<(), EncodingError>
+    where
+        P: AsRef<Path>,
+    {
+        crate::codecs::encode_mmap(path, &self.buffer, format)
+    }
+
+    /// Encode with the highest quality that results in at most `max_bytes`
+    ///
+    /// See `d10_codecs::encode_with_target_size()` for details.
+    pub fn save_to_buffer_with_target_size(
+        &self,
+        format: EncodingFormat,
+        max_bytes: usize,
+    ) -> Result<Vec<u8>, EncodingError> {
+        let mut out = vec![];
+        crate::codecs::encode_with_target_size(&mut out, &self.buffer, format, max_bytes)?;
+        Ok(out)
+    }
+
     pub fn width(&self) -> u32 {
         self.buffer.width()
     }
@@ -137,6 +316,39 @@ impl Image {
         self.buffer.is_grayscale()
     }
 
+    /// Check if both images have the same size and no channel value differs by more than `epsilon`
+    ///
+    /// This is meant for tests where results might differ slightly because of rounding.
+    /// Use `diff_visualize()` to see where two images differ.
+    pub fn approx_eq(&self, other: &Image, epsilon: f32) -> bool {
+        self.buffer.max_channel_difference(&other.buffer) <= epsilon
+    }
+
+    /// Mean of all pixels with every channel averaged separately
+    ///
+    /// Returns transparent black for empty images.
+    pub fn average_color(&self) -> Rgb {
+        if self.buffer.is_empty() {
+            return Rgb::NONE;
+        }
+
+        let mut sum = [0.0f64; 4];
+        for c in self.buffer.data() {
+            for (s, v) in sum.iter_mut().zip(c.data) {
+                *s += v as f64;
+            }
+        }
+
+        let count = self.buffer.data().len() as f64;
+
+        Rgb::new_with_alpha(
+            (sum[0] / count) as f32,
+            (sum[1] / count) as f32,
+            (sum[2] / count) as f32,
+            (sum[3] / count) as f32,
+        )
+    }
+
     pub fn mod_colors<F: Fn(&Rgb) -> Rgb>(&mut self, func: F) {
         self.buffer.mod_colors(func);
     }
@@ -204,40 +416,52 @@ impl Image {
 
     /// Return cropped image
     pub fn crop(&self, offset_x: u32, offset_y: u32, width: u32, height: u32) -> Image {
-        Self::new_from_buffer_with_meta(
+        Self::new_from_buffer_with_new_geometry(
             self,
             ops::crop(&self.buffer, offset_x, offset_y, width, height),
         )
     }
 
+    /// Shift the image by the given amount of pixels and wrap pixels around the borders
+    pub fn offset(&self, dx: i32, dy: i32) -> Image {
+        Self::new_from_buffer_with_new_geometry(self, ops::offset(&self.buffer, dx, dy))
+    }
+
+    /// Create a tileable texture by blending the wrapped borders
+    ///
+    /// The resulting image is smaller by `overlap` pixels in both dimensions.
+    pub fn make_seamless(&self, overlap: u32) -> Image {
+        Self::new_from_buffer_with_new_geometry(self, ops::make_seamless(&self.buffer, overlap))
+    }
+
     /// Flip image horizontally
     pub fn flip_horizontal(&self) -> Image {
-        Self::new_from_buffer_with_meta(self, ops::flip_horizontal(&self.buffer))
+        Self::new_from_buffer_with_new_geometry(self, ops::flip_horizontal(&self.buffer))
     }
 
     /// Flip image vertically
     pub fn flip_vertical(&self) -> Image {
-        Self::new_from_buffer_with_meta(self, ops::flip_vertical(&self.buffer))
+        Self::new_from_buffer_with_new_geometry(self, ops::flip_vertical(&self.buffer))
     }
 
     /// Rotate image 90 degrees clockwise
     pub fn rotate90(&self) -> Image {
-        Self::new_from_buffer_with_meta(self, ops::rotate90(&self.buffer))
+        Self::new_from_buffer_with_new_geometry(self, ops::rotate90(&self.buffer))
     }
 
     /// Rotate image 180 degrees clockwise
     pub fn rotate180(&self) -> Image {
-        Self::new_from_buffer_with_meta(self, ops::rotate180(&self.buffer))
+        Self::new_from_buffer_with_new_geometry(self, ops::rotate180(&self.buffer))
     }
 
     /// Rotate image 270 degrees clockwise
     pub fn rotate270(&self) -> Image {
-        Self::new_from_buffer_with_meta(self, ops::rotate270(&self.buffer))
+        Self::new_from_buffer_with_new_geometry(self, ops::rotate270(&self.buffer))
     }
 
     /// Rotate image clockwise with the given filter
     pub fn rotate(&self, radians: f32, filter: FilterMode) -> Self {
-        Self::new_from_buffer_with_meta(
+        Self::new_from_buffer_with_new_geometry(
             self,
             ops::rotate(
                 &self.buffer,
@@ -248,6 +472,27 @@ impl Image {
         )
     }
 
+    /// Find the content surrounded by letterbox or pillarbox bars
+    ///
+    /// See `ops::detect_black_bars()` for details.
+    pub fn detect_black_bars(&self, threshold: f32) -> Region {
+        ops::detect_black_bars(&self.buffer, threshold)
+    }
+
+    /// Crop black bars at the borders of the image, i.e. of video frames or screenshots
+    pub fn remove_black_bars(&self, threshold: f32) -> Image {
+        let r = self.detect_black_bars(threshold);
+        self.crop(r.x, r.y, r.width, r.height)
+    }
+
+    /// Level the dominant horizontal line of the image and crop the rotated borders
+    ///
+    /// # Arguments
+    /// max_angle: Maximal correction in degrees
+    pub fn straighten(&self, max_angle: f32) -> Self {
+        Self::new_from_buffer_with_new_geometry(self, ops::straighten(&self.buffer, max_angle))
+    }
+
     /// Detect edges in the image
     pub fn edge_detection(&self, mode: EdgeDetection) -> Image {
         Self::new_from_buffer_with_meta(self, ops::edge_detection(&self.buffer, mode))
@@ -255,12 +500,112 @@ impl Image {
 
     /// Resize image
     pub fn resize(&self, new_width: u32, new_height: u32, filter: FilterMode) -> Image {
-        Self::new_from_buffer_with_meta(
+        Self::new_from_buffer_with_new_geometry(
             self,
             ops::resize(&self.buffer, new_width, new_height, filter),
         )
     }
 
+    /// Resize image with explicit control over gamma, alpha handling and sharpening
+    pub fn resize_with_options(
+        &self,
+        new_width: u32,
+        new_height: u32,
+        options: &ResizeOptions,
+    ) -> Image {
+        Self::new_from_buffer_with_new_geometry(
+            self,
+            ops::resize_with_options(&self.buffer, new_width, new_height, options),
+        )
+    }
+
+    /// Shrink the image to fit into `max_width` x `max_height` while keeping the aspect ratio
+    ///
+    /// Images that already fit are returned unchanged.
+    pub fn fit(&self, max_width: u32, max_height: u32, filter: FilterMode) -> Image {
+        if self.width() <= max_width && self.height() <= max_height {
+            return self.clone();
+        }
+
+        let scale =
+            (max_width as f32 / self.width() as f32).min(max_height as f32 / self.height() as f32);
+
+        let width = ((self.width() as f32 * scale).round() as u32).max(1);
+        let height = ((self.height() as f32 * scale).round() as u32).max(1);
+
+        self.resize(width, height, filter)
+    }
+
+    /// Scale the image to cover `width` x `height` and crop the overlapping parts in the center
+    pub fn cover(&self, width: u32, height: u32, filter: FilterMode) -> Image {
+        self.cover_with_gravity(width, height, Gravity::Center, filter)
+    }
+
+    /// Scale the image to cover `width` x `height` and keep the part selected by `gravity`
+    ///
+    /// The kept part of the original image is stored as `crop_region()` of the result.
+    pub fn cover_with_gravity(
+        &self,
+        width: u32,
+        height: u32,
+        gravity: Gravity,
+        filter: FilterMode,
+    ) -> Image {
+        let region = ops::cover_region(&self.buffer, width, height, gravity);
+        self.with_crop_region(
+            ops::cover(&self.buffer, width, height, gravity, filter),
+            region,
+        )
+    }
+
+    /// Crop the most interesting part of the image and scale it to `width` x `height`
+    ///
+    /// See `ops::smart_crop()` for details.
+    pub fn smart_crop(&self, width: u32, height: u32, filter: FilterMode) -> Image {
+        self.smart_crop_with_focus(width, height, &[], filter)
+    }
+
+    /// Like `smart_crop()` but keeps the given regions of interest in frame if possible
+    ///
+    /// The regions can be found with `detect_regions()` or supplied by the user. The kept part
+    /// of the original image is stored as `crop_region()` of the result.
+    pub fn smart_crop_with_focus(
+        &self,
+        width: u32,
+        height: u32,
+        focus: &[Region],
+        filter: FilterMode,
+    ) -> Image {
+        let region = ops::smart_crop_region(&self.buffer, width, height, focus);
+        let cropped = ops::crop(
+            &self.buffer,
+            region.x,
+            region.y,
+            region.width,
+            region.height,
+        );
+
+        let buffer = if cropped.is_empty() {
+            cropped
+        } else {
+            ops::resize(&cropped, width, height, filter)
+        };
+
+        self.with_crop_region(buffer, region)
+    }
+
+    /// Region of the source image that was kept by `cover_with_gravity()` or `smart_crop()`
+    pub fn crop_region(&self) -> Option<Region> {
+        self.crop_region
+    }
+
+    fn with_crop_region(&self, buffer: PixelBuffer<Rgb>, region: Region) -> Image {
+        Image {
+            crop_region: Some(region),
+            ..Self::new_from_buffer_with_new_geometry(self, buffer)
+        }
+    }
+
     /// Resize image using the given percentage
     pub fn resize_pct(&self, pct_100: f32, filter: FilterMode) -> Image {
         let factor = pct_100 / 100.0;
@@ -271,6 +616,47 @@ impl Image {
         self.resize(width.max(1), height.max(1), filter)
     }
 
+    /// Upscale image by the given factor using iterative back-projection
+    ///
+    /// This is slower than `resize()` but produces sharper results for upscaling
+    pub fn upscale_enhanced(&self, factor: f32) -> Image {
+        Self::new_from_buffer_with_new_geometry(self, ops::upscale_enhanced(&self.buffer, factor))
+    }
+
+    /// Upscale pixel art with the xBR filter which smooths edges without blurring flat areas
+    pub fn xbr(&self, scale: XbrScale) -> Image {
+        Self::new_from_buffer_with_new_geometry(self, ops::xbr(&self.buffer, scale))
+    }
+
+    /// Correct radial lens distortion, chromatic aberration and vignetting
+    ///
+    /// `center` is the relative position of the optical center with (0.5, 0.5) being the image center
+    #[allow(clippy::too_many_arguments)]
+    pub fn lens_correct(
+        &self,
+        k1: f32,
+        k2: f32,
+        center: (f32, f32),
+        ca_red: f32,
+        ca_blue: f32,
+        vignette: f32,
+        filter: FilterMode,
+    ) -> Image {
+        Self::new_from_buffer_with_new_geometry(
+            self,
+            ops::lens_correct(
+                &self.buffer,
+                k1,
+                k2,
+                center,
+                ca_red,
+                ca_blue,
+                vignette,
+                filter,
+            ),
+        )
+    }
+
     /// Returns a new image with a simulated jpeg quality
     ///
     /// If `preserve_alpha` is not set, all alpha values will be set to 1.0
@@ -281,6 +667,13 @@ impl Image {
         )
     }
 
+    /// Heatmap of the errors introduced by encoding the image as jpeg with the given quality
+    ///
+    /// See `ops::jpeg_artifact_map()` for details.
+    pub fn jpeg_artifact_map(&self, quality: u8) -> Image {
+        Self::new_from_buffer(ops::jpeg_artifact_map(&self.buffer, quality))
+    }
+
     /// Add random noise to the image
     pub fn random_noise(&self, alpha: f32) -> Image {
         Self::new_from_buffer_with_meta(self, ops::random_noise(&self.buffer, alpha))
@@ -321,11 +714,176 @@ impl Image {
         ops::add_gaussian_noise(&mut self.buffer, alpha);
     }
 
+    /// Return a new image with photon shot noise
+    ///
+    /// `photons` is the number of photons of a fully saturated channel. Lower values add more noise.
+    pub fn poisson_noise(&self, photons: f32) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::poisson_noise(&self.buffer, photons))
+    }
+
+    /// Add photon shot noise to the image
+    pub fn add_poisson_noise(&mut self, photons: f32) {
+        ops::add_poisson_noise(&mut self.buffer, photons);
+    }
+
+    /// Return a new image with multiplicative speckle noise
+    pub fn speckle_noise(&self, sigma: f32) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::speckle_noise(&self.buffer, sigma))
+    }
+
+    /// Add multiplicative speckle noise to the image
+    pub fn add_speckle_noise(&mut self, sigma: f32) {
+        ops::add_speckle_noise(&mut self.buffer, sigma);
+    }
+
+    /// Create `count` randomly transformed variants of the image
+    ///
+    /// See `ops::Augment` for the available transformations.
+    pub fn augment(&self, pipeline: &ops::Augment, count: usize) -> Vec<Image> {
+        pipeline
+            .apply(&self.buffer, count)
+            .into_iter()
+            .map(|buffer| Self::new_from_buffer_with_new_geometry(self, buffer))
+            .collect()
+    }
+
+    /// Return a new image with all steps of the pipeline applied
+    ///
+    /// Consecutive per pixel steps are executed in a single pass, see `ops::Pipeline`.
+    pub fn apply_pipeline(&self, pipeline: &ops::Pipeline) -> Image {
+        let buffer = pipeline.apply(&self.buffer);
+        if pipeline
+            .steps()
+            .iter()
+            .all(ops::PipelineStep::keeps_geometry)
+        {
+            Self::new_from_buffer_with_meta(self, buffer)
+        } else {
+            Self::new_from_buffer_with_new_geometry(self, buffer)
+        }
+    }
+
     /// Return a new image with gaussian blur
     pub fn gaussian_blur(&self, radius: u32, sigma: Option<f32>) -> Image {
         Self::new_from_buffer_with_meta(self, ops::gaussian_blur(&self.buffer, radius, sigma))
     }
 
+    /// Blur the image without allocating a second buffer
+    ///
+    /// See `ops::gaussian_blur_in_place()` for details.
+    pub fn gaussian_blur_in_place(&mut self, radius: u32, sigma: Option<f32>) {
+        ops::gaussian_blur_in_place(&mut self.buffer, radius, sigma);
+    }
+
+    pub fn invert_in_place(&mut self) {
+        self.buffer.invert_in_place();
+    }
+
+    pub fn brightness_in_place(&mut self, factor: f32) {
+        self.buffer.brightness_in_place(factor);
+    }
+
+    pub fn contrast_in_place(&mut self, factor: f32) {
+        self.buffer.contrast_in_place(factor);
+    }
+
+    pub fn brightness_contrast_in_place(&mut self, brightness: f32, contrast: f32) {
+        self.buffer
+            .brightness_contrast_in_place(brightness, contrast);
+    }
+
+    pub fn level_in_place(&mut self, black_point: f32, white_point: f32, gamma: f32) {
+        self.buffer.level_in_place(black_point, white_point, gamma);
+    }
+
+    pub fn sepia_in_place(&mut self) {
+        self.buffer.sepia_in_place();
+    }
+
+    /// Blur the given regions of the image, i.e. to redact faces
+    pub fn blur_regions(&self, regions: &[Region], radius: u32) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::blur_regions(&self.buffer, regions, radius))
+    }
+
+    /// Pixelate the given regions of the image with blocks of the given size
+    pub fn pixelate_regions(&self, regions: &[Region], block_size: u32) -> Image {
+        Self::new_from_buffer_with_meta(
+            self,
+            ops::pixelate_regions(&self.buffer, regions, block_size),
+        )
+    }
+
+    /// Copy a region of the image to another position with a soft border of `feather` pixels
+    pub fn clone_region(&self, src_rect: Region, dst_pos: (u32, u32), feather: u32) -> Image {
+        Self::new_from_buffer_with_meta(
+            self,
+            ops::clone_region(&self.buffer, src_rect, dst_pos, feather),
+        )
+    }
+
+    /// Fill the area marked by bright pixels in the mask with the surrounding colors
+    pub fn inpaint(&self, mask: &Image) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::inpaint(&self.buffer, &mask.buffer))
+    }
+
+    /// Fill the area marked by bright pixels in the mask with patches from the rest of the image
+    ///
+    /// In contrast to `inpaint` this continues textures and edges and can remove larger objects.
+    pub fn inpaint_exemplar(&self, mask: &Image, patch_size: u32) -> Image {
+        Self::new_from_buffer_with_meta(
+            self,
+            ops::inpaint_exemplar(&self.buffer, &mask.buffer, patch_size),
+        )
+    }
+
+    /// Create a mask that is white for the foreground and black for the background
+    pub fn segment_foreground(&self, hint: &SegmentationHint) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::segment_foreground(&self.buffer, hint))
+    }
+
+    /// Make the background transparent using the mask of `segment_foreground`
+    pub fn remove_background(&self, hint: &SegmentationHint) -> Image {
+        let mask = ops::segment_foreground(&self.buffer, hint);
+
+        let data = self
+            .data()
+            .iter()
+            .zip(mask.data())
+            .map(|(c, m)| c.with_alpha(c.alpha() * m.red()))
+            .collect();
+
+        Self::new_from_buffer_with_meta(
+            self,
+            PixelBuffer::new_from_raw(self.width(), self.height(), data),
+        )
+    }
+
+    /// Cluster the colors of the image into `k` groups with the k-means algorithm
+    pub fn segment_kmeans(&self, k: usize, space: ClusterSpace) -> KMeansSegmentation {
+        ops::segment_kmeans(&self.buffer, k, space)
+    }
+
+    /// Replace every pixel with the mean color of its k-means cluster for a flat, poster like look
+    pub fn flat_art(&self, k: usize, space: ClusterSpace) -> Image {
+        let segmentation = ops::segment_kmeans(&self.buffer, k, space);
+        Self::new_from_buffer_with_meta(self, segmentation.render(&self.buffer))
+    }
+
+    /// Find the outlines of all regions of bright pixels, i.e. of a thresholded image or a mask
+    pub fn find_contours(&self) -> Vec<Contour> {
+        ops::find_contours(&self.buffer)
+    }
+
+    /// Convert the regions of bright pixels into smooth vector paths that can be saved as SVG
+    pub fn trace_bitmap(&self, options: &TraceOptions) -> SvgDocument {
+        ops::trace_bitmap(&self.buffer, options)
+    }
+
+    /// Find regions of interest with the given detector
+    pub fn detect_regions<D: RegionDetector>(&self, detector: &D) -> Vec<Region> {
+        detector.detect(&self.buffer)
+    }
+
     /// Return a new image with an unsharp mask applied
     pub fn unsharp(&self, radius: u32, factor: f32, sigma: Option<f32>) -> Image {
         Self::new_from_buffer_with_meta(self, ops::unsharp(&self.buffer, radius, factor, sigma))
@@ -396,41 +954,465 @@ impl Image {
         Self::new_from_buffer_with_meta(images[0], result)
     }
 
-    pub fn blend(&self, other: &Image, blend_op: BlendOp, intensity: f32) -> Image {
-        Self::new_from_buffer_with_meta(
-            self,
-            blend_image(&self.buffer, other.buffer(), blend_op, intensity),
-        )
+    /// Pack images into a sprite atlas and return it with the position of every image
+    ///
+    /// See `ops::pack_sprites()` for details.
+    pub fn pack_sprites(images: &[&Image], max_width: u32, padding: u32) -> (Image, Vec<Region>) {
+        let buffers: Vec<_> = images.iter().map(|image| &image.buffer).collect();
+        let sheet = ops::pack_sprites(&buffers, max_width, padding);
+        (Self::new_from_buffer(sheet.buffer), sheet.placements)
     }
 
-    pub fn drawing(&self, radius: u32, mode: DrawingMode) -> Image {
-        Self::new_from_buffer_with_meta(self, ops::drawing(&self.buffer, radius, mode))
+    /// Arrange images in a grid with the given number of columns
+    ///
+    /// See `ops::montage()` for details.
+    pub fn montage(images: &[&Image], columns: u32, spacing: u32, background: Rgb) -> Image {
+        let buffers: Vec<_> = images.iter().map(|image| &image.buffer).collect();
+        Self::new_from_buffer(ops::montage(&buffers, columns, spacing, background))
     }
 
-    pub fn interlace(&self, offset: u32) -> Image {
-        Self::new_from_buffer_with_meta(self, ops::interlace(&self.buffer, offset))
+    /// Arrange images on a canvas using a collage template
+    ///
+    /// See `ops::collage()` for details.
+    pub fn collage(
+        images: &[&Image],
+        template: &CollageTemplate,
+        gap: u32,
+        background: Rgb,
+    ) -> Image {
+        let buffers: Vec<_> = images.iter().map(|image| &image.buffer).collect();
+        Self::new_from_buffer(ops::collage(&buffers, template, gap, background))
     }
 
-    pub fn apply_palette(&self, palette: &Image) -> Image {
-        Self::new_from_buffer_with_meta(self, ops::apply_palette(&self.buffer, &palette.buffer))
+    /// Split a tall image into parts of at most `max_height` rows sharing `overlap` rows
+    ///
+    /// See `ops::split_vertical()` for details.
+    pub fn split_vertical(&self, max_height: u32, overlap: u32) -> Vec<Image> {
+        ops::split_vertical(&self.buffer, max_height, overlap)
+            .into_iter()
+            .map(|buffer| Self::new_from_buffer_with_new_geometry(self, buffer))
+            .collect()
     }
 
-    pub fn apply_palette_in_place(&mut self, palette: &Image) {
-        ops::apply_palette_in_place(&mut self.buffer, &palette.buffer);
+    /// Stack images vertically and remove rows repeated between consecutive images
+    ///
+    /// See `ops::join_vertical()` for details.
+    pub fn join_vertical(images: &[&Image], max_overlap: u32) -> Option<Image> {
+        let buffers: Vec<_> = images.iter().map(|image| &image.buffer).collect();
+        ops::join_vertical(&buffers, max_overlap).map(Self::new_from_buffer)
     }
 
-    pub fn despeckle(&self, threshold: f32, amount: u8) -> Image {
-        Self::new_from_buffer_with_meta(self, ops::despeckle(&self.buffer, threshold, amount))
+    /// Count the gamma encoded values of the color channels and the luma
+    pub fn histogram(&self) -> ops::Histogram {
+        ops::Histogram::new(&self.buffer)
     }
 
-    /// Automatically stretch contrast
+    /// Compute luma statistics for every cell of a grid with `cols` columns and `rows` rows
     ///
-    /// # Arguments
+    /// See `ops::zone_statistics()` for details.
+    pub fn zone_statistics(&self, cols: u32, rows: u32) -> Vec<ops::ZoneStatistics> {
+        ops::zone_statistics(&self.buffer, cols, rows)
+    }
+
+    /// Compute how similar the image is to another image of the same size
+    ///
+    /// Returns `None` if the sizes differ. See `ops::compare()` for details.
+    pub fn compare(&self, other: &Image, metric: ops::Metric) -> Option<f32> {
+        ops::compare(&self.buffer, &other.buffer, metric)
+    }
+
+    /// Hash the pixel data independent of file format and metadata
+    ///
+    /// See `ops::content_hash()` for details.
+    pub fn content_hash(&self, algorithm: ops::HashAlgorithm) -> String {
+        ops::content_hash(&self.buffer, algorithm)
+    }
+
+    /// Hide data in the least significant bits of the color channels
+    ///
+    /// The image must be saved in a lossless format. See `ops::embed_data()` for details.
+    pub fn embed_data(&self, bytes: &[u8], bits_per_channel: u8) -> Result<Image, ops::StegoError> {
+        Ok(Self::new_from_buffer_with_meta(
+            self,
+            ops::embed_data(&self.buffer, bytes, bits_per_channel)?,
+        ))
+    }
+
+    /// Extract data hidden with `embed_data()`
+    pub fn extract_data(&self) -> Result<Vec<u8>, ops::StegoError> {
+        ops::extract_data(&self.buffer)
+    }
+
+    /// Embed an invisible watermark that survives mild jpeg compression
+    ///
+    /// See `ops::embed_invisible_watermark()` for details.
+    pub fn embed_invisible_watermark(&self, key: u64, strength: f32) -> Image {
+        Self::new_from_buffer_with_meta(
+            self,
+            ops::embed_invisible_watermark(&self.buffer, key, strength),
+        )
+    }
+
+    /// Score of the invisible watermark with the given key
+    ///
+    /// Values above `ops::DEFAULT_WATERMARK_THRESHOLD` indicate that the watermark is present.
+    pub fn detect_invisible_watermark(&self, key: u64) -> f32 {
+        ops::detect_invisible_watermark(&self.buffer, key)
+    }
+
+    /// Convert into a black and white image with all pixels having a luma above `value` being white
+    pub fn threshold(&self, value: f32) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::threshold(&self.buffer, value))
+    }
+
+    /// Find the luma threshold that best separates dark and bright pixels with Otsu's method
+    pub fn otsu_threshold(&self) -> f32 {
+        ops::otsu_threshold(&self.buffer)
+    }
+
+    /// Convert into a black and white image by comparing pixels with their neighborhood
+    ///
+    /// See `ops::adaptive_threshold()` for details.
+    pub fn adaptive_threshold(&self, radius: u32, offset: f32) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::adaptive_threshold(&self.buffer, radius, offset))
+    }
+
+    /// Map the quadrilateral given by its corners onto a rectangle of the given size
+    ///
+    /// The corners are expected in the order top left, top right, bottom right and bottom left.
+    pub fn perspective_warp(
+        &self,
+        corners: [(f32, f32); 4],
+        width: u32,
+        height: u32,
+        filter: FilterMode,
+    ) -> Image {
+        Self::new_from_buffer_with_new_geometry(
+            self,
+            ops::perspective_warp(&self.buffer, corners, width, height, filter),
+        )
+    }
+
+    /// Move every pixel by the offsets read from the displacement maps
+    ///
+    /// The red channel of `dx_map` holds the horizontal and the green channel of `dy_map` the
+    /// vertical offsets. See `ops::displace()` for details.
+    pub fn displace(
+        &self,
+        dx_map: &Image,
+        dy_map: &Image,
+        scale: f32,
+        filter: FilterMode,
+    ) -> Image {
+        Self::new_from_buffer_with_new_geometry(
+            self,
+            ops::displace(&self.buffer, &dx_map.buffer, &dy_map.buffer, scale, filter),
+        )
+    }
+
+    /// Wrap the image with the angle in the x and the radius in the y direction into a circle
+    ///
+    /// See `ops::to_polar()` for details.
+    pub fn to_polar(&self, filter: FilterMode) -> Image {
+        Self::new_from_buffer_with_new_geometry(self, ops::to_polar(&self.buffer, filter))
+    }
+
+    /// Unwrap the circle around the center into an image with the angle in the x and the radius
+    /// in the y direction
+    ///
+    /// This is the inverse of `to_polar()`, see `ops::from_polar()` for details.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn from_polar(&self, filter: FilterMode) -> Image {
+        Self::new_from_buffer_with_new_geometry(self, ops::from_polar(&self.buffer, filter))
+    }
+
+    /// Rotate the pixels around the center with `degrees` in the center and none at the border
+    pub fn swirl(&self, degrees: f32, filter: FilterMode) -> Image {
+        Self::new_from_buffer_with_new_geometry(self, ops::swirl(&self.buffer, degrees, filter))
+    }
+
+    /// Bulge out (positive strength) or pinch (negative strength) the center of the image
+    pub fn fisheye(&self, strength: f32, filter: FilterMode) -> Image {
+        Self::new_from_buffer_with_new_geometry(self, ops::fisheye(&self.buffer, strength, filter))
+    }
+
+    /// Create a stereographic ("little planet") view of an equirectangular panorama
+    ///
+    /// See `ops::stereographic_project()` for details.
+    pub fn stereographic_project(&self, fov: f32, yaw: f32, pitch: f32) -> Image {
+        Self::new_from_buffer_with_new_geometry(
+            self,
+            ops::stereographic_project(&self.buffer, fov, yaw, pitch),
+        )
+    }
+
+    /// Create a perspective view of an equirectangular panorama
+    ///
+    /// See `ops::rectilinear_project()` for details.
+    pub fn rectilinear_project(&self, fov: f32, yaw: f32, pitch: f32) -> Image {
+        Self::new_from_buffer_with_new_geometry(
+            self,
+            ops::rectilinear_project(&self.buffer, fov, yaw, pitch),
+        )
+    }
+
+    /// Convert an equirectangular panorama into the six faces of a cube map
+    ///
+    /// See `ops::equirect_to_cubemap()` for details.
+    pub fn equirect_to_cubemap(&self, face_size: u32) -> [Image; 6] {
+        ops::equirect_to_cubemap(&self.buffer, face_size)
+            .map(|buffer| Self::new_from_buffer_with_new_geometry(self, buffer))
+    }
+
+    /// Convert the six faces of a cube map into an equirectangular panorama
+    ///
+    /// See `ops::cubemap_to_equirect()` for details.
+    pub fn cubemap_to_equirect(faces: &[&Image; 6], width: u32) -> Image {
+        let buffers = faces.map(|image| &image.buffer);
+        Self::new_from_buffer(ops::cubemap_to_equirect(&buffers, width))
+    }
+
+    /// Locate and decode all QR codes in the image
+    pub fn detect_qr_codes(&self) -> Vec<ops::QrCode> {
+        ops::detect_qr_codes(&self.buffer)
+    }
+
+    /// Draw text with the builtin 5x7 pixel font
+    ///
+    /// See `ops::draw_text()` for details.
+    pub fn draw_text(&mut self, x: i32, y: i32, text: &str, color: Rgb, scale: u32) {
+        ops::draw_text(&mut self.buffer, x, y, text, color, scale);
+    }
+
+    pub fn blend(&self, other: &Image, blend_op: BlendOp, intensity: f32) -> Image {
+        Self::new_from_buffer_with_meta(
+            self,
+            blend_image(&self.buffer, other.buffer(), blend_op, intensity),
+        )
+    }
+
+    /// Add a watermark to the image
+    ///
+    /// If `scale` is set the watermark gets resized to this width relative to the image width.
+    /// The `margin` is the distance to the image borders or the spacing between tiles in pixels.
+    pub fn watermark(
+        &self,
+        mark: &Image,
+        position: WatermarkPosition,
+        scale: Option<f32>,
+        opacity: f32,
+        margin: u32,
+    ) -> Image {
+        Self::new_from_buffer_with_meta(
+            self,
+            ops::watermark(&self.buffer, &mark.buffer, position, scale, opacity, margin),
+        )
+    }
+
+    /// Surround the image with a border of `width` pixels on every side
+    pub fn add_border(&self, width: u32, fill: BorderFill, style: BorderStyle) -> Image {
+        Self::new_from_buffer_with_new_geometry(
+            self,
+            ops::add_border(&self.buffer, width, fill, style),
+        )
+    }
+
+    /// Put the image on an off-white matte with a wide bottom like a polaroid photo
+    pub fn polaroid(&self) -> Image {
+        Self::new_from_buffer_with_new_geometry(self, ops::polaroid(&self.buffer))
+    }
+
+    /// Visualize the differences to another image
+    ///
+    /// `threshold` is the minimal CIE76 color difference for a pixel to count as changed.
+    /// See `ops::diff_visualize()` for details.
+    pub fn diff_visualize(&self, other: &Image, mode: DiffMode, threshold: f32) -> Image {
+        Self::new_from_buffer(ops::diff_visualize(
+            &self.buffer,
+            &other.buffer,
+            mode,
+            threshold,
+        ))
+    }
+
+    pub fn drawing(&self, radius: u32, mode: DrawingMode) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::drawing(&self.buffer, radius, mode))
+    }
+
+    /// Convert the image into a black and white halftone screen
+    ///
+    /// `angle` is the rotation of the screen in degrees
+    pub fn halftone(&self, cell_size: u32, angle: f32, shape: HalftoneShape) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::halftone(&self.buffer, cell_size, angle, shape))
+    }
+
+    /// Turn a photo of a document into a clean scan
+    ///
+    /// See `ops::scan_enhance()` for details.
+    pub fn scan_enhance(&self, mode: ScanMode) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::scan_enhance(&self.buffer, mode))
+    }
+
+    /// Map the tones of the image to a gradient between two colors
+    pub fn duotone(&self, dark_color: Rgb, light_color: Rgb) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::duotone(&self.buffer, dark_color, light_color))
+    }
+
+    /// Convert into a black and white image with a channel mixer
+    ///
+    /// See `ops::to_monochrome()` for details.
+    pub fn to_monochrome(
+        &self,
+        red_weight: f32,
+        green_weight: f32,
+        blue_weight: f32,
+        tint: Option<Rgb>,
+    ) -> Image {
+        Self::new_from_buffer_with_meta(
+            self,
+            ops::to_monochrome(&self.buffer, red_weight, green_weight, blue_weight, tint),
+        )
+    }
+
+    /// Convert into a black and white image simulating a colored lens filter
+    ///
+    /// See `ops::to_monochrome_with_filter()` for details.
+    pub fn to_monochrome_with_filter(&self, filter: MonochromeFilter, tint: Option<Rgb>) -> Image {
+        Self::new_from_buffer_with_meta(
+            self,
+            ops::to_monochrome_with_filter(&self.buffer, filter, tint),
+        )
+    }
+
+    /// Change the hue, saturation and lightness of specific color ranges
+    ///
+    /// See `ops::hsl_adjust()` for details.
+    pub fn hsl_adjust(&self, ranges: &[HueRangeAdjustment]) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::hsl_adjust(&self.buffer, ranges))
+    }
+
+    /// Apply a named look like a film emulation
+    ///
+    /// See `ops::apply_look()` for details.
+    pub fn apply_look(&self, preset: LookPreset) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::apply_look(&self.buffer, preset))
+    }
+
+    pub fn interlace(&self, offset: u32) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::interlace(&self.buffer, offset))
+    }
+
+    pub fn apply_palette(&self, palette: &Palette) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::apply_palette(&self.buffer, palette))
+    }
+
+    pub fn apply_palette_in_place(&mut self, palette: &Palette) {
+        ops::apply_palette_in_place(&mut self.buffer, palette);
+    }
+
+    pub fn despeckle(&self, threshold: f32, amount: u8) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::despeckle(&self.buffer, threshold, amount))
+    }
+
+    /// Run an operation on a single channel of the image, i.e. the lightness
+    ///
+    /// The channel is passed to `op` as a gray image and the result gets merged back.
+    /// See `ops::apply_in_space()` for details.
+    ///
+    /// # Panics
+    ///
+    /// If `op` returns an image with a different size.
+    pub fn apply_in_space<F>(&self, channel: ColorSpaceChannel, op: F) -> Image
+    where
+        F: FnOnce(&Image) -> Image,
+    {
+        let gray = Image::new_from_buffer(ops::extract_channel(&self.buffer, channel));
+        let result = op(&gray);
+
+        Self::new_from_buffer_with_meta(
+            self,
+            ops::replace_channel(&self.buffer, channel, &result.buffer),
+        )
+    }
+
+    /// Same as `apply_in_space()` but with an operation that can fail
+    ///
+    /// # Panics
+    ///
+    /// If `op` returns an image with a different size.
+    pub fn try_apply_in_space<E, F>(&self, channel: ColorSpaceChannel, op: F) -> Result<Image, E>
+    where
+        F: FnOnce(&Image) -> Result<Image, E>,
+    {
+        let gray = Image::new_from_buffer(ops::extract_channel(&self.buffer, channel));
+        let result = op(&gray)?;
+
+        Ok(Self::new_from_buffer_with_meta(
+            self,
+            ops::replace_channel(&self.buffer, channel, &result.buffer),
+        ))
+    }
+
+    /// Reduce color noise by blurring only the chroma channels
+    ///
+    /// See `ops::denoise_chroma()` for details.
+    pub fn denoise_chroma(&self, strength: f32) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::denoise_chroma(&self.buffer, strength))
+    }
+
+    /// Replace single pixels that are much brighter or darker than all of their neighbors
+    ///
+    /// Returns the repaired image and the locations of all replaced pixels.
+    /// See `ops::repair_hot_pixels()` for details.
+    pub fn repair_hot_pixels(&self, threshold: f32) -> (Image, Vec<(u32, u32)>) {
+        let (buffer, locations) = ops::repair_hot_pixels(&self.buffer, threshold);
+        (Self::new_from_buffer_with_meta(self, buffer), locations)
+    }
+
+    /// Smooth posterization banding in gradients
+    ///
+    /// See `ops::deband()` for details.
+    pub fn deband(&self, strength: f32, dither: bool) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::deband(&self.buffer, strength, dither))
+    }
+
+    /// Estimate how strong the image suffers from banding from 0 (none) to 1
+    pub fn banding_score(&self) -> f32 {
+        ops::banding_score(&self.buffer)
+    }
+
+    /// Automatically stretch contrast
+    ///
+    /// # Arguments
     /// threshold: Value between 0 and 1000. Sane values are between 0.0 and 1.0
     pub fn stretch_contrast(&self, threshold: f32) -> Image {
         Self::new_from_buffer_with_meta(self, ops::stretch_contrast(&self.buffer, threshold))
     }
 
+    /// Linearize an image whose values are encoded with another transfer function than sRGB
+    ///
+    /// See `ops::decode_transfer()` for details.
+    pub fn decode_transfer(&self, transfer: TransferFunction) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::decode_transfer(&self.buffer, transfer))
+    }
+
+    /// Encode an image with another transfer function than sRGB
+    ///
+    /// See `ops::encode_transfer()` for details.
+    pub fn encode_transfer(&self, transfer: TransferFunction) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::encode_transfer(&self.buffer, transfer))
+    }
+
+    /// Stretch the tones with black and white points from histogram percentiles
+    ///
+    /// See `ops::auto_levels()` for details.
+    pub fn auto_levels(&self, clip_low_pct: f32, clip_high_pct: f32, per_channel: bool) -> Image {
+        Self::new_from_buffer_with_meta(
+            self,
+            ops::auto_levels(&self.buffer, clip_low_pct, clip_high_pct, per_channel),
+        )
+    }
+
     pub fn optimize_saturation(&self, offset: f32, mode: SaturationMode) -> Image {
         Self::new_from_buffer_with_meta(self, ops::optimize_saturation(&self.buffer, offset, mode))
     }
@@ -455,27 +1437,107 @@ impl Image {
     }
 
     pub fn optimize_lightness(&self, factor: f32) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::optimize_lightness(&self.buffer, factor))
+    }
+
+    /// Automatically improve white balance, contrast, lightness and saturation
+    ///
+    /// # Arguments
+    /// strength: Value between 0.0 (no change) and 1.0 (full enhancement)
+    pub fn auto_enhance(&self, strength: f32) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::auto_enhance(&self.buffer, strength))
+    }
+
+    /// Smooth skin with frequency separation restricted to skin tones
+    ///
+    /// # Arguments
+    /// strength: Value between 0.0 (no change) and 1.0 (full smoothing)
+    pub fn portrait_smooth(&self, strength: f32) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::portrait_smooth(&self.buffer, strength))
+    }
+
+    /// Apply an adjustment that fades out along a linear gradient
+    ///
+    /// # Arguments
+    /// angle: Direction of the gradient in degrees, 0.0 fades from top to bottom
+    /// start: Relative position where the adjustment starts to fade out
+    /// end: Relative position where the adjustment is faded out completely
+    pub fn graduated_filter(
+        &self,
+        angle: f32,
+        start: f32,
+        end: f32,
+        adjustment: &Adjustment,
+    ) -> Image {
+        Self::new_from_buffer_with_meta(
+            self,
+            ops::graduated_filter(&self.buffer, angle, start, end, adjustment),
+        )
+    }
+
+    /// Illuminate the image with spotlights, directional and ambient lights
+    ///
+    /// See `ops::apply_lighting()` for details.
+    pub fn apply_lighting(&self, lights: &[LightSpec]) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::apply_lighting(&self.buffer, lights))
+    }
+
+    /// Render the image as a gray relief lit from the given direction
+    ///
+    /// See `ops::emboss()` for details.
+    pub fn emboss(&self, azimuth: f32, elevation: f32, depth: f32) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::emboss(&self.buffer, azimuth, elevation, depth))
+    }
+
+    /// Convert the image used as height map into a normal map
+    ///
+    /// See `ops::height_to_normal_map()` for details.
+    pub fn height_to_normal_map(&self, strength: f32) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::height_to_normal_map(&self.buffer, strength))
+    }
+
+    /// Bake an ambient occlusion map from the image used as height map
+    ///
+    /// See `ops::height_to_ao()` for details.
+    pub fn height_to_ao(&self, samples: u32, radius: f32, strength: f32) -> Image {
         Self::new_from_buffer_with_meta(
             self,
-            ops::optimize_lightness(&self.buffer, factor),
+            ops::height_to_ao(&self.buffer, samples, radius, strength),
         )
     }
 
+    /// Darken and saturate bright areas in the upper part of the image
+    ///
+    /// # Arguments
+    /// strength: Value between 0.0 (no change) and 1.0 (full effect)
+    pub fn enhance_sky(&self, strength: f32) -> Image {
+        Self::new_from_buffer_with_meta(self, ops::enhance_sky(&self.buffer, strength))
+    }
+
     pub fn equalize(&self, mode: EqualizeMode) -> Image {
         Self::new_from_buffer_with_meta(self, ops::equalize(&self.buffer, mode))
     }
 
-    pub fn symmetric_nearest_neighbor(&self, radius: usize, with_center: bool,) -> Image {
-        Self::new_from_buffer_with_meta(self, ops::symmetric_nearest_neighbor(&self.buffer, radius, with_center))
+    pub fn symmetric_nearest_neighbor(&self, radius: usize, with_center: bool) -> Image {
+        Self::new_from_buffer_with_meta(
+            self,
+            ops::symmetric_nearest_neighbor(&self.buffer, radius, with_center),
+        )
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use d10_ops::{DrawingMode, FilterMode};
+    use d10_ops::{
+        Adjustment, Augment, BorderFill, BorderStyle, ClusterSpace, CollageTemplate,
+        ColorSpaceChannel, DiffMode, DrawingMode, FilterMode, Gravity, HalftoneShape,
+        HashAlgorithm, HueRangeAdjustment, LightSpec, LookPreset, Metric, MonochromeFilter,
+        NoiseKind, NoiseOptions, Pipeline, ResizeOptions, ScanMode, SegmentationHint, TraceOptions,
+        WatermarkPosition, XbrScale, DEFAULT_WATERMARK_STRENGTH, DEFAULT_WATERMARK_THRESHOLD,
+    };
 
     use crate::ops::BlendOp;
-    use crate::{Color, Rgb};
+    use crate::{Color, Palette, PixelBuffer, Region, Rgb, TransferFunction};
 
     use super::Image;
 
@@ -618,6 +1680,16 @@ mod tests {
         assert_eq!(img_in.get_pixel(3, 1), img_out.get_pixel(0, 0));
     }
 
+    #[test]
+    fn straighten() {
+        let img_in = test_image_4_2();
+
+        let img_out = img_in.straighten(10.0);
+
+        assert!(img_out.width() <= img_in.width());
+        assert!(img_out.height() <= img_in.height());
+    }
+
     #[test]
     fn resize() {
         let img_in = test_image_3_2();
@@ -653,33 +1725,73 @@ mod tests {
     }
 
     #[test]
-    fn with_jpeg_quality() {
-        let img_in = test_image_3_2();
+    fn lens_correct() {
+        let img_in = test_image_4_2();
 
-        let img_out = img_in.with_jpeg_quality(100, true);
+        let img_out = img_in.lens_correct(0.0, 0.0, (0.5, 0.5), 1.0, 1.0, 0.0, FilterMode::Bicubic);
 
         assert_eq!(img_in.width(), img_out.width());
         assert_eq!(img_in.height(), img_out.height());
 
         for (c1, c2) in img_in.data().iter().zip(img_out.data().iter()) {
-            for i in 0..=2 {
-                let diff = (c1.data[i] - c2.data[i]).abs();
+            assert_eq!(c1, c2);
+        }
 
-                // Not exact but if it fails it's definitely not a quality of 100
-                assert!(diff < 0.1)
-            }
+        let img_out =
+            img_in.lens_correct(0.2, 0.05, (0.5, 0.5), 1.01, 0.99, 0.5, FilterMode::Bicubic);
+
+        assert_eq!(img_in.width(), img_out.width());
+        assert_eq!(img_in.height(), img_out.height());
+
+        for filter in [
+            FilterMode::Nearest,
+            FilterMode::Bilinear,
+            FilterMode::Lanczos3,
+        ] {
+            let img_out = img_in.lens_correct(0.0, 0.0, (0.5, 0.5), 1.0, 1.0, 0.0, filter);
+            assert_eq!(img_in.data(), img_out.data());
         }
     }
 
     #[test]
-    fn random_noise() {
-        //TODO:  Add real test that checks if there is actually a noise added
-
+    fn jpeg_artifact_map() {
         let img_in = test_image_3_2();
 
-        let img_out = img_in.random_noise(0.5);
+        let map = img_in.jpeg_artifact_map(100);
 
-        assert_eq!(img_in.width(), img_out.width());
+        assert_eq!(img_in.width(), map.width());
+        assert_eq!(img_in.height(), map.height());
+        assert!(map.get_pixel(0, 0).red() < 0.5);
+    }
+
+    #[test]
+    fn with_jpeg_quality() {
+        let img_in = test_image_3_2();
+
+        let img_out = img_in.with_jpeg_quality(100, true);
+
+        assert_eq!(img_in.width(), img_out.width());
+        assert_eq!(img_in.height(), img_out.height());
+
+        for (c1, c2) in img_in.data().iter().zip(img_out.data().iter()) {
+            for i in 0..=2 {
+                let diff = (c1.data[i] - c2.data[i]).abs();
+
+                // Not exact but if it fails it's definitely not a quality of 100
+                assert!(diff < 0.1)
+            }
+        }
+    }
+
+    #[test]
+    fn random_noise() {
+        //TODO:  Add real test that checks if there is actually a noise added
+
+        let img_in = test_image_3_2();
+
+        let img_out = img_in.random_noise(0.5);
+
+        assert_eq!(img_in.width(), img_out.width());
         assert_eq!(img_in.height(), img_out.height());
 
         let mut img_in = test_image_3_2();
@@ -719,6 +1831,312 @@ mod tests {
         img_in.add_rgb_noise(0.5);
     }
 
+    #[test]
+    fn poisson_noise() {
+        let img_in = Image::new_with_color(100, 100, Rgb::new(0.5, 0.0, 1.0));
+
+        let img_out = img_in.poisson_noise(100.0);
+        assert_eq!(img_in.width(), img_out.width());
+        assert_eq!(img_in.height(), img_out.height());
+
+        let len = img_out.data().len() as f32;
+        let mean = img_out.data().iter().map(|c| c.red()).sum::<f32>() / len;
+        let variance = img_out
+            .data()
+            .iter()
+            .map(|c| (c.red() - mean).powi(2))
+            .sum::<f32>()
+            / len;
+
+        // The variance of shot noise is the mean divided by the photon count
+        assert!((mean - 0.5).abs() < 0.01);
+        assert!((variance - 0.005).abs() < 0.001);
+
+        // Black stays black and alpha is untouched
+        assert!(img_out.data().iter().all(|c| c.green() == 0.0));
+        assert!(img_out.data().iter().all(|c| c.alpha() == 1.0));
+
+        let mut img_in = test_image_3_2();
+        img_in.add_poisson_noise(10.0);
+        assert!(img_in.data().iter().all(|c| c.red() >= 0.0));
+    }
+
+    #[test]
+    fn speckle_noise() {
+        let img_in = Image::new_from_raw(2, 1, vec![Rgb::BLACK, Rgb::new(0.8, 0.8, 0.8)]);
+
+        let img_out = img_in.speckle_noise(0.5);
+
+        assert_eq!(img_in.width(), img_out.width());
+        assert_eq!(img_out.get_pixel(0, 0), &Rgb::BLACK);
+        assert_ne!(img_out.get_pixel(1, 0), &Rgb::new(0.8, 0.8, 0.8));
+
+        let mut img_in = test_image_3_2();
+        img_in.add_speckle_noise(0.0);
+        assert_eq!(img_in.data(), test_image_3_2().data());
+    }
+
+    #[test]
+    fn average_color() {
+        let transparent = Rgb::new_with_alpha(1.0, 0.5, 0.0, 0.0);
+        let img = Image::new_from_raw(2, 1, vec![Rgb::BLACK, transparent]);
+
+        let expected = Rgb::new_with_alpha(0.5, 0.25, 0.0, 0.5);
+        assert_eq!(img.average_color(), expected);
+        assert_eq!(Image::new(0, 0).average_color(), Rgb::NONE);
+    }
+
+    #[test]
+    fn fit() {
+        let img = Image::new_with_color(400, 200, Rgb::RED);
+
+        let res = img.fit(100, 100, FilterMode::Bilinear);
+        assert_eq!((res.width(), res.height()), (100, 50));
+
+        let res = img.fit(1000, 50, FilterMode::Bilinear);
+        assert_eq!((res.width(), res.height()), (100, 50));
+
+        let res = img.fit(1000, 1000, FilterMode::Bilinear);
+        assert_eq!((res.width(), res.height()), (400, 200));
+    }
+
+    #[test]
+    fn cover() {
+        let mut img = Image::new_with_color(400, 200, Rgb::RED);
+        img.buffer_mut()
+            .fill_rect(Region::new(0, 0, 100, 200), Rgb::BLUE);
+
+        let res = img.cover(100, 100, FilterMode::Nearest);
+        assert_eq!((res.width(), res.height()), (100, 100));
+
+        // The left quarter is cropped away
+        assert_eq!(res.get_pixel(0, 50), &Rgb::RED);
+
+        let res = img.cover(800, 100, FilterMode::Nearest);
+        assert_eq!((res.width(), res.height()), (800, 100));
+        assert_eq!(res.get_pixel(0, 50), &Rgb::BLUE);
+
+        let res = img.cover_with_gravity(100, 100, Gravity::Left, FilterMode::Nearest);
+        assert_eq!(res.get_pixel(0, 50), &Rgb::BLUE);
+        assert_eq!(res.get_pixel(49, 50), &Rgb::BLUE);
+        assert_eq!(res.get_pixel(50, 50), &Rgb::RED);
+    }
+
+    #[test]
+    fn smart_crop() {
+        let mut img = Image::new_with_color(400, 200, Rgb::RED);
+        img.buffer_mut()
+            .fill_rect(Region::new(330, 80, 40, 40), Rgb::BLUE);
+
+        let res = img.smart_crop(100, 100, FilterMode::Nearest);
+        assert_eq!((res.width(), res.height()), (100, 100));
+        assert!(res.data().contains(&Rgb::BLUE));
+        let region = res.crop_region().unwrap();
+        assert_eq!((region.width, region.height), (200, 200));
+        // The blue square at (330, 80, 40, 40) is inside the kept region
+        assert!(region.contains(330, 80) && region.contains(369, 119));
+
+        let focus = [Region::new(10, 10, 20, 20)];
+        let res = img.smart_crop_with_focus(100, 100, &focus, FilterMode::Nearest);
+        let region = res.crop_region().unwrap();
+        assert!(region.contains(10, 10) && region.contains(29, 29));
+
+        // The region is kept by operations that don't move pixels
+        assert_eq!(res.gaussian_blur(1, None).crop_region(), res.crop_region());
+        assert_eq!(
+            res.apply_pipeline(&Pipeline::new().invert()).crop_region(),
+            res.crop_region()
+        );
+        // and dropped by operations that change the geometry
+        assert_eq!(res.resize(50, 50, FilterMode::Nearest).crop_region(), None);
+        assert_eq!(res.crop(10, 10, 50, 50).crop_region(), None);
+        assert_eq!(res.rotate90().crop_region(), None);
+        assert_eq!(
+            res.apply_pipeline(&Pipeline::new().invert().rotate90())
+                .crop_region(),
+            None
+        );
+
+        let res = img.cover_with_gravity(100, 100, Gravity::focus(&focus), FilterMode::Nearest);
+        assert_eq!(res.crop_region(), Some(Region::new(0, 0, 200, 200)));
+        assert_eq!(img.crop_region(), None);
+    }
+
+    #[test]
+    fn histogram() {
+        let img = test_image_3_2();
+
+        let histogram = img.histogram();
+
+        assert_eq!(histogram.total(), 6);
+        assert_eq!(histogram.red.iter().sum::<u32>(), 6);
+    }
+
+    #[test]
+    fn zone_statistics() {
+        let img = test_image_3_2();
+
+        let zones = img.zone_statistics(3, 2);
+
+        assert_eq!(zones.len(), 6);
+        assert!(zones.iter().all(|z| z.pixels == 1 && z.std_dev == 0.0));
+        assert_eq!(zones[5].region, Region::new(2, 1, 1, 1));
+    }
+
+    #[test]
+    fn apply_in_space() {
+        let img = test_image_3_2();
+
+        let res = img.apply_in_space(ColorSpaceChannel::Blue, |gray| {
+            Image::new_with_color(gray.width(), gray.height(), Rgb::WHITE)
+        });
+
+        assert_eq!(res.get_pixel(0, 0), &Rgb::WHITE);
+        assert_eq!(res.get_pixel(0, 1), &Rgb::MAGENTA);
+        assert_eq!(res.get_pixel(2, 1), &Rgb::BLUE);
+
+        let res: Result<Image, &str> =
+            img.try_apply_in_space(ColorSpaceChannel::LabLightness, |_| Err("failed"));
+        assert_eq!(res.err(), Some("failed"));
+    }
+
+    #[test]
+    fn denoise_chroma() {
+        let img = test_image_3_2();
+
+        let result = img.denoise_chroma(1.0);
+
+        assert_eq!((result.width(), result.height()), (3, 2));
+        assert_ne!(result.data(), img.data());
+    }
+
+    #[test]
+    fn repair_hot_pixels() {
+        let mut img = Image::new_with_color(5, 5, Rgb::new(0.3, 0.3, 0.3));
+        img.put_pixel(2, 3, Rgb::WHITE);
+
+        let (result, locations) = img.repair_hot_pixels(0.2);
+
+        assert_eq!(locations, vec![(2, 3)]);
+        assert_eq!(result.get_pixel(2, 3), img.get_pixel(0, 0));
+    }
+
+    #[test]
+    fn deband() {
+        let img = Image::new_from_buffer(PixelBuffer::new_from_func(64, 16, |x, _| {
+            Rgb::new(0.2 + (x / 8) as f32 / 255.0, 0.2, 0.2)
+        }));
+
+        let result = img.deband(1.0, true);
+
+        assert_eq!((result.width(), result.height()), (64, 16));
+        assert!(result.banding_score() < img.banding_score());
+    }
+
+    #[test]
+    fn compare() {
+        let img = test_image_3_2();
+        let inverted = Image::new_from_buffer(img.buffer().map_colors(|c| c.invert()));
+
+        assert_eq!(img.compare(&img, Metric::Mse), Some(0.0));
+        assert!(img.compare(&inverted, Metric::Mse).unwrap() > 0.1);
+        assert_eq!(img.compare(&img.rotate90(), Metric::Ssim), None);
+    }
+
+    #[test]
+    fn content_hash() {
+        let img = test_image_3_2();
+
+        let png = img
+            .save_to_buffer(crate::EncodingFormat::png_default())
+            .unwrap();
+        let bmp = img
+            .save_to_buffer(crate::EncodingFormat::bmp_default())
+            .unwrap();
+        assert_ne!(png, bmp);
+
+        let from_png = Image::read_from_buffer(&png).unwrap();
+        let from_bmp = Image::read_from_buffer(&bmp).unwrap();
+
+        assert_eq!(
+            from_png.content_hash(HashAlgorithm::Sha256),
+            from_bmp.content_hash(HashAlgorithm::Sha256)
+        );
+        assert_ne!(
+            from_png.content_hash(HashAlgorithm::Crc32),
+            from_png
+                .flip_horizontal()
+                .content_hash(HashAlgorithm::Crc32)
+        );
+    }
+
+    #[test]
+    fn embed_data() {
+        let img = test_image_3_2().resize(20, 10, FilterMode::Bilinear);
+
+        let res = img.embed_data(b"d10", 2).unwrap();
+        let png = res
+            .save_to_buffer(crate::EncodingFormat::png_default())
+            .unwrap();
+
+        let loaded = Image::read_from_buffer(&png).unwrap();
+        assert_eq!(loaded.extract_data().unwrap(), b"d10");
+
+        assert!(img.embed_data(&[0; 100], 1).is_err());
+        assert!(img.extract_data().is_err());
+    }
+
+    #[test]
+    fn invisible_watermark() {
+        let img = Image::new_from_buffer(PixelBuffer::new_from_func(96, 64, |x, y| {
+            let v = ((x * 7 + y * 13) % 31) as f32 / 31.0;
+            Rgb::new(v * 0.5 + 0.2, y as f32 / 64.0, 0.4)
+        }));
+
+        let marked = img.embed_invisible_watermark(1234, DEFAULT_WATERMARK_STRENGTH);
+        let jpeg = marked
+            .save_to_buffer(crate::EncodingFormat::jpeg_with_quality(85))
+            .unwrap();
+        let loaded = Image::read_from_buffer(&jpeg).unwrap();
+
+        assert!(loaded.detect_invisible_watermark(1234) > DEFAULT_WATERMARK_THRESHOLD);
+        assert!(loaded.detect_invisible_watermark(4321) < DEFAULT_WATERMARK_THRESHOLD);
+        assert!(img.detect_invisible_watermark(1234) < DEFAULT_WATERMARK_THRESHOLD);
+    }
+
+    #[test]
+    fn augment() {
+        let img_in = test_image_3_2();
+        let pipeline = Augment::new(7).flip_horizontal(0.5).noise(0.1);
+
+        let variants = img_in.augment(&pipeline, 4);
+
+        assert_eq!(variants.len(), 4);
+        for img in &variants {
+            assert_eq!((img.width(), img.height()), (3, 2));
+        }
+
+        let again = img_in.augment(&pipeline, 4);
+        assert_eq!(variants[3].data(), again[3].data());
+    }
+
+    #[test]
+    fn apply_pipeline() {
+        let img_in = test_image_3_2();
+        let pipeline = Pipeline::new().brightness(0.2).contrast(1.1).rotate90();
+
+        let img_out = img_in.apply_pipeline(&pipeline);
+
+        assert_eq!((img_out.width(), img_out.height()), (2, 3));
+        assert_eq!(
+            img_out.get_pixel(1, 0),
+            &img_in
+                .get_pixel(0, 0)
+                .with_brightness(0.2)
+                .with_contrast(1.1)
+        );
+    }
+
     #[test]
     fn gaussian_noise() {
         //TODO:  Add real test that checks if there is actually a noise added
@@ -840,6 +2258,376 @@ mod tests {
         }
     }
 
+    #[test]
+    fn resize_with_options() {
+        let img_in = test_image_3_2();
+
+        let options = ResizeOptions {
+            gamma_correct: false,
+            sharpen_after: Some(0.5),
+            ..ResizeOptions::new(FilterMode::Bicubic)
+        };
+
+        let img_out = img_in.resize_with_options(30, 21, &options);
+        assert_eq!(img_out.width(), 30);
+        assert_eq!(img_out.height(), 21);
+    }
+
+    #[test]
+    fn upscale_enhanced() {
+        let img_in = test_image_3_2();
+
+        let img_out = img_in.upscale_enhanced(2.0);
+        assert_eq!(img_out.width(), 6);
+        assert_eq!(img_out.height(), 4);
+
+        let img_out = img_in.upscale_enhanced(0.5);
+        assert_eq!(img_out.width(), 2);
+        assert_eq!(img_out.height(), 1);
+    }
+
+    #[test]
+    fn xbr() {
+        let img_out = test_image_3_2().xbr(XbrScale::X3);
+        assert_eq!(img_out.width(), 9);
+        assert_eq!(img_out.height(), 6);
+    }
+
+    #[test]
+    fn test_offset() {
+        let img_in = test_image_3_2();
+
+        let img_out = img_in.offset(1, 1);
+
+        assert_eq!(img_in.get_pixel(0, 0), img_out.get_pixel(1, 1));
+        assert_eq!(img_in.get_pixel(2, 1), img_out.get_pixel(0, 0));
+    }
+
+    #[test]
+    fn test_make_seamless() {
+        let img_in = Image::new_with_color(20, 10, Rgb::RED);
+
+        let img_out = img_in.make_seamless(4);
+
+        assert_eq!(img_out.width(), 16);
+        assert_eq!(img_out.height(), 6);
+        assert_eq!(img_out.get_pixel(0, 0), &Rgb::RED);
+    }
+
+    #[test]
+    fn test_redact_regions() {
+        let img = test_image_4_2();
+
+        let regions = img.detect_regions(&|_: &PixelBuffer<Rgb>| vec![Region::new(1, 0, 2, 2)]);
+
+        let blurred = img.blur_regions(&regions, 1);
+        assert_eq!(blurred.get_pixel(0, 0), img.get_pixel(0, 0));
+        assert_eq!(blurred.get_pixel(3, 1), img.get_pixel(3, 1));
+
+        let pixelated = img.pixelate_regions(&regions, 2);
+        assert_eq!(pixelated.get_pixel(1, 0), pixelated.get_pixel(2, 1));
+        assert_eq!(pixelated.get_pixel(0, 0), img.get_pixel(0, 0));
+    }
+
+    #[test]
+    fn test_clone_region() {
+        let img = test_image_4_2();
+
+        let res = img.clone_region(Region::new(0, 0, 2, 1), (2, 1), 0);
+        assert_eq!(res.get_pixel(2, 1), img.get_pixel(0, 0));
+        assert_eq!(res.get_pixel(3, 1), img.get_pixel(1, 0));
+        assert_eq!(res.get_pixel(0, 1), img.get_pixel(0, 1));
+    }
+
+    #[test]
+    fn test_inpaint() {
+        let img = Image::new_with_color(5, 5, Rgb::BLUE);
+
+        let mut damaged = img.clone();
+        damaged.put_pixel(2, 2, Rgb::RED);
+
+        let mut mask = Image::new_with_color(5, 5, Rgb::BLACK);
+        mask.put_pixel(2, 2, Rgb::WHITE);
+
+        let res = damaged.inpaint(&mask);
+        assert_eq!(res.data(), img.data());
+
+        let res = damaged.inpaint_exemplar(&mask, 3);
+        assert_eq!(res.data(), img.data());
+    }
+
+    #[test]
+    fn test_segment_foreground() {
+        let img = Image::new_from_buffer(PixelBuffer::new_from_func(20, 20, |x, y| {
+            if (6..14).contains(&x) && (6..14).contains(&y) {
+                Rgb::RED
+            } else {
+                Rgb::BLUE
+            }
+        }));
+
+        let hint = SegmentationHint::Rect(Region::new(3, 3, 14, 14));
+
+        let mask = img.segment_foreground(&hint);
+        assert_eq!(mask.get_pixel(10, 10), &Rgb::WHITE);
+        assert_eq!(mask.get_pixel(4, 10), &Rgb::BLACK);
+
+        let res = img.remove_background(&hint);
+        assert_eq!(res.get_pixel(10, 10), &Rgb::RED);
+        assert_eq!(res.get_pixel(4, 10).alpha(), 0.0);
+    }
+
+    #[test]
+    fn test_segment_kmeans() {
+        let img = Image::new_from_buffer(PixelBuffer::new_from_func(20, 20, |x, y| {
+            let n = ((x + y) % 3) as f32 * 0.02;
+            if x < 10 {
+                Rgb::new(0.8 + n, 0.2, 0.1)
+            } else {
+                Rgb::new(0.1, 0.2, 0.8 + n)
+            }
+        }));
+
+        let segmentation = img.segment_kmeans(2, ClusterSpace::Lab);
+        assert_eq!(segmentation.colors.len(), 2);
+        assert_ne!(segmentation.labels[0], segmentation.labels[19]);
+
+        let res = img.flat_art(2, ClusterSpace::Lab);
+        assert_eq!(res.get_pixel(0, 0), res.get_pixel(9, 19));
+        assert_eq!(res.get_pixel(10, 0), res.get_pixel(19, 19));
+        assert!(res.get_pixel(0, 0).red() > 0.8);
+    }
+
+    #[test]
+    fn test_find_contours() {
+        let img = Image::new_from_buffer(PixelBuffer::new_from_func(10, 10, |x, y| {
+            if (2..8).contains(&x) && (3..6).contains(&y) {
+                Rgb::WHITE
+            } else {
+                Rgb::BLACK
+            }
+        }));
+
+        let contours = img.find_contours();
+        assert_eq!(contours.len(), 1);
+        assert_eq!(
+            contours[0].points,
+            vec![(2.0, 3.0), (8.0, 3.0), (8.0, 6.0), (2.0, 6.0)]
+        );
+    }
+
+    #[test]
+    fn test_trace_bitmap() {
+        let img = Image::new_from_buffer(PixelBuffer::new_from_func(10, 10, |x, y| {
+            if (2..8).contains(&x) && (3..6).contains(&y) {
+                Rgb::BLACK
+            } else {
+                Rgb::WHITE
+            }
+        }));
+
+        let options = TraceOptions {
+            invert: true,
+            ..Default::default()
+        };
+
+        let svg = img.trace_bitmap(&options).to_svg();
+        assert!(svg.contains("<path d=\"M2 4.5 L2 3 L8 3 L8 6 L2 6 Z\" fill=\"#000000\""));
+    }
+
+    #[test]
+    fn test_apply_palette() {
+        let mut img = Image::new_from_buffer(PixelBuffer::new_from_func(4, 4, |x, _| {
+            if x < 2 {
+                Rgb::new(0.7, 0.2, 0.1)
+            } else {
+                Rgb::new(0.1, 0.3, 0.9)
+            }
+        }));
+
+        let palette = Palette::from_colors(vec![Rgb::RED, Rgb::BLUE]);
+
+        let res = img.apply_palette(&palette);
+        assert_eq!(res.get_pixel(0, 0), &Rgb::RED);
+        assert_eq!(res.get_pixel(3, 3), &Rgb::BLUE);
+
+        img.apply_palette_in_place(&palette);
+        assert_eq!(img.data(), res.data());
+    }
+
+    #[test]
+    fn test_in_place() {
+        let img_in = test_image_4_2();
+
+        let mut img = img_in.clone();
+        img.brightness_contrast_in_place(0.1, 1.2);
+        img.level_in_place(0.05, 0.95, 1.1);
+        img.sepia_in_place();
+        img.invert_in_place();
+
+        let expected = img_in.map_colors(|c| {
+            c.with_brightness_contrast(0.1, 1.2)
+                .with_level(0.05, 0.95, 1.1)
+                .with_sepia()
+                .invert()
+        });
+        assert_eq!(img.data(), expected.data());
+
+        let mut img = img_in.clone();
+        img.gaussian_blur_in_place(1, None);
+        assert!(img.approx_eq(&img_in.gaussian_blur(1, None), 1e-5));
+    }
+
+    #[test]
+    fn test_remove_black_bars() {
+        let mut img = Image::new_with_color(40, 30, Rgb::BLACK);
+        img.buffer.fill_rect(Region::new(0, 4, 40, 20), Rgb::WHITE);
+
+        assert_eq!(img.detect_black_bars(0.1), Region::new(0, 4, 40, 20));
+
+        let res = img.remove_black_bars(0.1);
+        assert_eq!((res.width(), res.height()), (40, 20));
+        assert_eq!(res.get_pixel(0, 0), &Rgb::WHITE);
+    }
+
+    #[test]
+    fn test_scan_enhance() {
+        let img = Image::new_from_buffer(PixelBuffer::new_from_func(40, 30, |x, y| {
+            if y % 6 == 3 && x > 4 && x < 36 {
+                Rgb::new(0.02, 0.02, 0.02)
+            } else {
+                Rgb::new(0.7, 0.6, 0.5)
+            }
+        }));
+
+        let res = img.scan_enhance(ScanMode::BlackWhite);
+        assert_eq!(res.width(), 40);
+        assert_eq!(res.height(), 30);
+        assert_eq!(res.get_pixel(20, 9), &Rgb::BLACK);
+        assert_eq!(res.get_pixel(20, 11), &Rgb::WHITE);
+    }
+
+    #[test]
+    fn test_threshold_and_perspective_warp() {
+        let img = Image::new_from_raw(
+            4,
+            1,
+            vec![
+                Rgb::new(0.1, 0.1, 0.1),
+                Rgb::new(0.2, 0.2, 0.2),
+                Rgb::new(0.7, 0.7, 0.7),
+                Rgb::new(0.8, 0.8, 0.8),
+            ],
+        );
+
+        let value = img.otsu_threshold();
+        assert!(value > 0.2 && value < 0.7);
+
+        let res = img.threshold(value);
+        assert_eq!(res.get_pixel(1, 0), &Rgb::BLACK);
+        assert_eq!(res.get_pixel(2, 0), &Rgb::WHITE);
+
+        let res = img.perspective_warp(
+            [(2.0, 0.0), (4.0, 0.0), (4.0, 1.0), (2.0, 1.0)],
+            2,
+            1,
+            FilterMode::Nearest,
+        );
+        assert_eq!(res.width(), 2);
+        assert_eq!(res.get_pixel(0, 0), img.get_pixel(2, 0));
+        assert_eq!(res.get_pixel(1, 0), img.get_pixel(3, 0));
+
+        assert!(img.detect_qr_codes().is_empty());
+    }
+
+    #[test]
+    fn test_displace() {
+        let img = test_image_3_2();
+        let map = Image::new_with_color(1, 1, Rgb::WHITE);
+
+        let res = img.displace(&map, &map, 1.0, FilterMode::Nearest);
+
+        assert_eq!(res.get_pixel(0, 0), img.get_pixel(1, 1));
+        assert_eq!(res.get_pixel(2, 1), img.get_pixel(2, 1));
+    }
+
+    #[test]
+    fn test_polar() {
+        let img = Image::new_with_color(10, 10, Rgb::RED);
+
+        let res = img.to_polar(FilterMode::Bilinear);
+        assert_eq!(res.get_pixel(0, 0), &Rgb::NONE);
+        assert_eq!(res.get_pixel(5, 5), &Rgb::RED);
+
+        let res = img.from_polar(FilterMode::Nearest);
+        assert_eq!(res.data(), img.data());
+
+        assert_eq!(img.swirl(90.0, FilterMode::Nearest).data(), img.data());
+        assert_eq!(img.fisheye(0.5, FilterMode::Nearest).data(), img.data());
+    }
+
+    #[test]
+    fn test_panorama_projections() {
+        let img = Image::new_from_buffer(PixelBuffer::new_from_func(40, 20, |_, y| {
+            if y < 10 {
+                Rgb::BLUE
+            } else {
+                Rgb::GREEN
+            }
+        }));
+
+        let res = img.stereographic_project(270.0, 0.0, -90.0);
+        assert_eq!((res.width(), res.height()), (20, 20));
+        assert_eq!(res.get_pixel(10, 10), &Rgb::GREEN);
+        assert_eq!(res.get_pixel(0, 0), &Rgb::BLUE);
+
+        let res = img.rectilinear_project(90.0, 45.0, 0.0);
+        assert_eq!(res.get_pixel(10, 2), &Rgb::BLUE);
+        assert_eq!(res.get_pixel(10, 17), &Rgb::GREEN);
+    }
+
+    #[test]
+    fn test_cubemap() {
+        let img = Image::new_from_buffer(PixelBuffer::new_from_func(40, 20, |_, y| {
+            if y < 10 {
+                Rgb::BLUE
+            } else {
+                Rgb::GREEN
+            }
+        }));
+
+        let faces = img.equirect_to_cubemap(8);
+        assert_eq!((faces[2].width(), faces[2].height()), (8, 8));
+        assert_eq!(faces[2].get_pixel(4, 4), &Rgb::BLUE);
+        assert_eq!(faces[3].get_pixel(4, 4), &Rgb::GREEN);
+
+        let res = Image::cubemap_to_equirect(&faces.each_ref(), 40);
+        assert_eq!((res.width(), res.height()), (40, 20));
+        assert_eq!(res.get_pixel(5, 2), &Rgb::BLUE);
+        assert_eq!(res.get_pixel(30, 17), &Rgb::GREEN);
+    }
+
+    #[test]
+    fn test_watermark() {
+        let img = Image::new_with_color(10, 10, Rgb::BLACK);
+        let mark = Image::new_with_color(2, 2, Rgb::WHITE);
+
+        let res = img.watermark(&mark, WatermarkPosition::BottomRight, None, 1.0, 1);
+        assert_eq!(res.get_pixel(7, 7), &Rgb::WHITE);
+        assert_eq!(res.get_pixel(8, 8), &Rgb::WHITE);
+        assert_eq!(res.get_pixel(9, 9), &Rgb::BLACK);
+        assert_eq!(res.get_pixel(6, 6), &Rgb::BLACK);
+
+        let res = img.watermark(&mark, WatermarkPosition::TopLeft, Some(0.5), 1.0, 0);
+        assert_eq!(res.get_pixel(4, 4), &Rgb::WHITE);
+        assert_eq!(res.get_pixel(5, 5), &Rgb::BLACK);
+
+        let res = img.watermark(&mark, WatermarkPosition::Tile, None, 0.5, 1);
+        assert_eq!(res.get_pixel(0, 0), &Rgb::new(0.5, 0.5, 0.5));
+        assert_eq!(res.get_pixel(2, 2), &Rgb::BLACK);
+        assert_eq!(res.get_pixel(3, 3), &Rgb::new(0.5, 0.5, 0.5));
+    }
+
     #[test]
     fn test_drawing() {
         let img = test_image_4_2();
@@ -857,6 +2645,239 @@ mod tests {
         assert_eq!(img.height(), res.height());
     }
 
+    #[test]
+    fn test_halftone() {
+        let img = Image::new_with_color(16, 16, Rgb::WHITE);
+
+        for shape in [
+            HalftoneShape::Dot,
+            HalftoneShape::Square,
+            HalftoneShape::Line,
+        ] {
+            let res = img.halftone(4, 45.0, shape);
+            assert_eq!(img.width(), res.width());
+            assert_eq!(img.height(), res.height());
+
+            for c in res.data() {
+                assert_eq!(c, &Rgb::WHITE);
+            }
+        }
+
+        let img = Image::new_with_color(16, 16, Rgb::BLACK);
+        let res = img.halftone(4, 0.0, HalftoneShape::Square);
+
+        for c in res.data() {
+            assert_eq!(c, &Rgb::BLACK);
+        }
+    }
+
+    #[test]
+    fn test_duotone() {
+        let img = test_image_4_2();
+
+        let res = img.duotone(Rgb::BLUE, Rgb::YELLOW);
+
+        assert_eq!(res.get_pixel(0, 0), &Rgb::YELLOW);
+        assert_eq!(res.get_pixel(1, 0), &Rgb::BLUE);
+    }
+
+    #[test]
+    fn test_add_border() {
+        let img = test_image_4_2();
+
+        let res = img.add_border(3, BorderFill::Color(Rgb::GREEN), BorderStyle::Double);
+        assert_eq!((res.width(), res.height()), (10, 8));
+        assert_eq!(res.get_pixel(0, 0), &Rgb::GREEN);
+        assert_eq!(res.get_pixel(3, 3), img.get_pixel(0, 0));
+
+        let res = img.polaroid();
+        assert!(res.height() > res.width() - 4);
+    }
+
+    #[test]
+    fn test_collage() {
+        let img = test_image_4_2();
+
+        let template = CollageTemplate::golden_ratio(60, 40).with_gravity(Gravity::Top);
+        let res = Image::collage(&[&img, &img, &img], &template, 2, Rgb::WHITE);
+        assert_eq!((res.width(), res.height()), (60, 40));
+        assert_eq!(res.get_pixel(0, 0), &Rgb::WHITE);
+        assert_ne!(res.get_pixel(2, 2), &Rgb::WHITE);
+    }
+
+    #[test]
+    fn test_split_join_vertical() {
+        let img = Image::new_from_buffer(PixelBuffer::new_from_func(3, 20, |_, y| {
+            Rgb::new(y as f32 / 20.0, 0.0, 0.0)
+        }));
+
+        let parts = img.split_vertical(8, 2);
+        assert_eq!(parts.len(), 3);
+
+        let parts: Vec<_> = parts.iter().collect();
+        let joined = Image::join_vertical(&parts, 2).unwrap();
+        assert_eq!(joined.data(), img.data());
+    }
+
+    #[test]
+    fn test_to_monochrome() {
+        let img = test_image_4_2();
+
+        let res = img.to_monochrome(1.0, 0.0, 0.0, None);
+        assert!(res.data().iter().all(|c| c.is_grayscale()));
+        assert_eq!(res.get_pixel(0, 1), &Rgb::WHITE);
+        assert_eq!(res.get_pixel(1, 1), &Rgb::BLACK);
+
+        let res = img.to_monochrome_with_filter(MonochromeFilter::Red, Some(Rgb::RED));
+        assert_eq!(res.get_pixel(0, 0), &Rgb::WHITE);
+        assert_eq!(res.get_pixel(1, 0), &Rgb::BLACK);
+    }
+
+    #[test]
+    fn test_hsl_adjust() {
+        let img = test_image_4_2();
+
+        let res = img.hsl_adjust(&[HueRangeAdjustment {
+            lightness: -1.0,
+            ..HueRangeAdjustment::new(240.0, 20.0)
+        }]);
+
+        assert_eq!(res.get_pixel(2, 1), &Rgb::BLACK);
+        assert_eq!(res.get_pixel(0, 1), &Rgb::RED);
+        assert_eq!(res.get_pixel(0, 0), &Rgb::WHITE);
+    }
+
+    #[test]
+    fn test_apply_look() {
+        let img = test_image_4_2();
+
+        let res = img.apply_look(LookPreset::BwYellowFilter);
+
+        assert!(res.data().iter().all(|c| c.is_grayscale()));
+        assert_eq!(res.get_pixel(0, 0), &Rgb::WHITE);
+    }
+
+    #[test]
+    fn test_transfer_functions() {
+        let img = test_image_4_2();
+
+        let res = img.decode_transfer(TransferFunction::Rec709);
+        assert_eq!(res.get_pixel(0, 0), &Rgb::WHITE);
+        assert_eq!(res.get_pixel(1, 0), &Rgb::BLACK);
+
+        let gray = Image::new_with_color(1, 1, Rgb::new(0.2, 0.2, 0.2));
+        let res = gray.encode_transfer(TransferFunction::Pq);
+        assert_ne!(res.get_pixel(0, 0), gray.get_pixel(0, 0));
+
+        let res = res.decode_transfer(TransferFunction::Pq);
+        assert!((res.get_pixel(0, 0).red() - 0.2).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_auto_levels() {
+        let img = Image::new_from_buffer(PixelBuffer::new_from_func(4, 1, |x, _| {
+            let v = 0.25 + x as f32 * 0.1;
+            Rgb::new(v, v, v)
+        }));
+
+        let res = img.auto_levels(0.0, 0.0, false);
+        assert!(res.get_pixel(0, 0).max() < 0.001);
+        assert!(res.get_pixel(3, 0).min() > 0.99);
+        assert!(res.get_pixel(1, 0).is_grayscale());
+    }
+
+    #[test]
+    fn test_auto_enhance() {
+        let img = test_image_4_2();
+
+        let res = img.auto_enhance(0.0);
+        for (c1, c2) in img.data().iter().zip(res.data().iter()) {
+            assert_eq!(c1, c2);
+        }
+
+        let res = img.auto_enhance(1.0);
+        assert_eq!(img.width(), res.width());
+        assert_eq!(img.height(), res.height());
+    }
+
+    #[test]
+    fn test_portrait_smooth() {
+        let img = test_image_4_2();
+
+        let res = img.portrait_smooth(0.0);
+        assert_eq!(img.data(), res.data());
+
+        let res = img.portrait_smooth(1.0);
+        assert_eq!(img.width(), res.width());
+        assert_eq!(img.height(), res.height());
+    }
+
+    #[test]
+    fn test_graduated_filter() {
+        let img = test_image_4_2();
+
+        let adjustment = Adjustment {
+            exposure: -1.0,
+            ..Adjustment::default()
+        };
+
+        let res = img.graduated_filter(0.0, 0.0, 1.0, &adjustment);
+        assert_eq!(img.width(), res.width());
+        assert_eq!(img.height(), res.height());
+        assert_eq!(img.enhance_sky(0.0).data(), img.data());
+    }
+
+    #[test]
+    fn test_new_noise() {
+        let options = NoiseOptions {
+            tileable: true,
+            ..NoiseOptions::new(NoiseKind::Worley)
+        };
+
+        let img = Image::new_noise(16, 8, &options);
+        assert_eq!((img.width(), img.height()), (16, 8));
+        assert!(img.data().iter().all(|c| c.is_grayscale()));
+    }
+
+    #[test]
+    fn test_emboss() {
+        let img = test_image_4_2();
+
+        let res = img.emboss(135.0, 45.0, 2.0);
+        assert_eq!((res.width(), res.height()), (4, 2));
+        assert!(res.data().iter().all(|c| c.is_grayscale()));
+
+        let res = Image::new_with_color(3, 3, Rgb::WHITE).height_to_normal_map(1.0);
+        let c = res.get_pixel(1, 1).to_srgb();
+        assert!((c.red() - 0.5).abs() < 1e-5 && (c.blue() - 1.0).abs() < 1e-5);
+
+        let res = img.height_to_ao(8, 2.0, 1.0);
+        assert_eq!((res.width(), res.height()), (4, 2));
+        assert!(res.data().iter().all(|c| c.is_grayscale()));
+    }
+
+    #[test]
+    fn test_apply_lighting() {
+        let img = test_image_4_2();
+
+        let ambient = LightSpec::Ambient {
+            color: Rgb::WHITE,
+            intensity: 1.0,
+        };
+        assert_eq!(img.apply_lighting(&[ambient]).data(), img.data());
+
+        let spot = LightSpec::Point {
+            x: 0.0,
+            y: 0.0,
+            height: 0.2,
+            radius: 0.1,
+            color: Rgb::WHITE,
+            intensity: 1.0,
+        };
+        let res = img.apply_lighting(&[spot]);
+        assert!(res.get_pixel(3, 1).red() <= img.get_pixel(3, 1).red());
+    }
+
     #[test]
     fn test_interlace() {
         let img = test_image_4_2();
@@ -869,4 +2890,197 @@ mod tests {
         assert_eq!(img_out.get_pixel(0, 1), img.get_pixel(1, 1));
         assert_eq!(img_out.get_pixel(3, 1), img.get_pixel(3, 1));
     }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_mmap() {
+        let img = test_image_4_2();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("image.bmp");
+
+        img.save_mmap(&path, crate::EncodingFormat::bmp_default())
+            .unwrap();
+        let res = Image::open_mmap(&path).unwrap();
+
+        assert!(res.approx_eq(&img, 0.0));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_io() {
+        let img = test_image_4_2();
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let mut data = vec![];
+        runtime
+            .block_on(img.save_to_async_writer(&mut data, crate::EncodingFormat::png_default()))
+            .unwrap();
+
+        let res = runtime.block_on(Image::read_async(&data[..])).unwrap();
+
+        assert_eq!(res.width(), 4);
+        assert_eq!(res.height(), 2);
+        assert_eq!(res.data(), Image::read_from_buffer(&data).unwrap().data());
+    }
+
+    #[test]
+    fn test_thumbnail() {
+        let img = Image::new_from_raw(
+            64,
+            32,
+            (0..64 * 32)
+                .map(|i| Rgb::new((i % 64) as f32 / 63.0, 0.5, 0.5))
+                .collect(),
+        );
+
+        let data = img
+            .save_to_buffer(crate::EncodingFormat::jpeg_default())
+            .unwrap();
+
+        let res = Image::read_thumbnail_from_buffer(&data, 10).unwrap();
+        assert_eq!(res.width(), 10);
+        assert_eq!(res.height(), 5);
+
+        let res = Image::read_thumbnail_from_buffer(&data, 100).unwrap();
+        assert_eq!(res.width(), 64);
+        assert_eq!(res.height(), 32);
+    }
+
+    #[test]
+    fn test_read_progressive() {
+        let img = Image::new_with_color(20, 10, Rgb::RED);
+
+        let data = img
+            .save_to_buffer(crate::EncodingFormat::Png {
+                color_type: crate::PngColorType::Rgb8,
+                compression: crate::PngCompression::Fast,
+                filter: crate::PngFilterType::Up,
+                interlaced: true,
+                palette: None,
+            })
+            .unwrap();
+
+        let passes = Image::read_progressive_from_buffer(&data).unwrap();
+        assert_eq!(passes.len(), 7);
+        assert!(passes
+            .iter()
+            .all(|pass| pass.width() == 20 && pass.height() == 10));
+
+        let data = img
+            .save_to_buffer(crate::EncodingFormat::jpeg_default())
+            .unwrap();
+        assert_eq!(Image::read_progressive_from_buffer(&data).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_save_with_target_size() {
+        let img = Image::new_from_raw(
+            32,
+            32,
+            (0..32 * 32)
+                .map(|i| Rgb::new((i % 7) as f32 / 6.0, (i % 13) as f32 / 12.0, 0.5))
+                .collect(),
+        );
+
+        let format = crate::EncodingFormat::jpeg_with_quality(100);
+        let full = img.save_to_buffer(format.clone()).unwrap();
+
+        let data = img
+            .save_to_buffer_with_target_size(format, full.len() / 2)
+            .unwrap();
+
+        assert!(data.len() <= full.len() / 2);
+    }
+
+    #[test]
+    fn test_pack_sprites() {
+        let img1 = Image::new_with_color(4, 4, Rgb::RED);
+        let img2 = Image::new_with_color(3, 2, Rgb::BLUE);
+
+        let (sheet, placements) = Image::pack_sprites(&[&img1, &img2], 16, 2);
+
+        assert_eq!(
+            placements,
+            vec![Region::new(0, 0, 4, 4), Region::new(6, 0, 3, 2)]
+        );
+        assert_eq!(sheet.width(), 9);
+        assert_eq!(sheet.height(), 4);
+        assert_eq!(sheet.get_pixel(7, 1), &Rgb::BLUE);
+    }
+
+    #[test]
+    fn test_montage() {
+        let img1 = Image::new_with_color(4, 4, Rgb::RED);
+        let img2 = Image::new_with_color(2, 2, Rgb::BLUE);
+
+        let res = Image::montage(&[&img1, &img2, &img1], 2, 2, Rgb::WHITE);
+
+        assert_eq!(res.width(), 14);
+        assert_eq!(res.height(), 14);
+        assert_eq!(res.get_pixel(2, 2), &Rgb::RED);
+        assert_eq!(res.get_pixel(9, 3), &Rgb::BLUE);
+        assert_eq!(res.get_pixel(2, 8), &Rgb::RED);
+        assert_eq!(res.get_pixel(8, 8), &Rgb::WHITE);
+    }
+
+    #[test]
+    fn test_diff_visualize() {
+        let img1 = Image::new_with_color(4, 3, Rgb::BLUE);
+        let mut img2 = img1.clone();
+        img2.put_pixel(1, 1, Rgb::RED);
+
+        let res = img1.diff_visualize(&img2, DiffMode::Heatmap, 2.3);
+
+        assert_eq!((res.width(), res.height()), (4, 3));
+        assert_eq!(res.get_pixel(1, 1).green(), 0.0);
+        assert!(res.get_pixel(0, 0).green() > 0.5);
+    }
+
+    #[test]
+    fn test_approx_eq() {
+        let img1 = test_image_4_2();
+        let mut img2 = img1.clone();
+
+        assert!(img1.approx_eq(&img2, 0.0));
+
+        img2.put_pixel(2, 1, Rgb::new(0.0, 0.0, 0.99));
+        assert!(img1.approx_eq(&img2, 0.01));
+        assert!(!img1.approx_eq(&img2, 0.001));
+
+        assert!(!img1.approx_eq(&test_image_3_2(), 1.0));
+    }
+
+    #[test]
+    fn test_golden() {
+        use crate::test_util::assert_golden;
+
+        let img = test_image_4_2();
+        let large = img.resize(16, 8, FilterMode::Nearest);
+
+        assert_golden(
+            &img.resize(16, 8, FilterMode::Bicubic),
+            "tests/golden/resize_bicubic.png",
+        );
+        assert_golden(
+            &large.gaussian_blur(2, None),
+            "tests/golden/gaussian_blur.png",
+        );
+        assert_golden(
+            &img.apply_look(LookPreset::TealOrange),
+            "tests/golden/look_teal_orange.png",
+        );
+    }
+
+    #[test]
+    fn test_draw_text() {
+        let mut img = Image::new_with_color(20, 10, Rgb::BLACK);
+
+        img.draw_text(1, 1, "d10", Rgb::WHITE, 1);
+
+        assert!(img.data().contains(&Rgb::WHITE));
+        assert_eq!(img.get_pixel(0, 0), &Rgb::BLACK);
+    }
 }
@@ -0,0 +1,267 @@
+use std::fs::File;
+use std::io::{BufWriter, Cursor, Read, Seek, Write};
+use std::path::Path;
+
+use crate::codecs::{decode_layers, encode_ora, ImageLayer, LayeredImage};
+use crate::ops::{BlendOp, Layer, LayerStack};
+use crate::{DecodingError, EncodingError, Format, PixelBuffer, Rgb};
+
+/// Open the layers of a layered image like psd as a `LayerStack`
+///
+/// Images without layers result in a stack with a single layer. Blend modes without an
+/// equivalent `BlendOp` fall back to `BlendOp::Normal`, so `flatten()` might differ from the
+/// composite image stored in the file.
+pub fn open_layers<P>(path: P) -> Result<LayerStack, DecodingError>
+where
+    P: AsRef<Path>,
+{
+    read_layers_from(File::open(path)?)
+}
+
+pub fn read_layers(buffer: &[u8]) -> Result<LayerStack, DecodingError> {
+    read_layers_from(Cursor::new(buffer))
+}
+
+fn read_layers_from<T>(reader: T) -> Result<LayerStack, DecodingError>
+where
+    T: Read + Seek,
+{
+    Ok(to_layer_stack(decode_layers(reader)?))
+}
+
+/// Save all layers of `stack` together with the flattened image
+///
+/// Only ora files are supported. See `save_layers_to_buffer()` for the limitations.
+pub fn save_layers<P>(stack: &LayerStack, path: P) -> Result<(), EncodingError>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+
+    let Some(format) = Format::from_path(path) else {
+        return Err(EncodingError::BadFileExtension(
+            path.to_string_lossy().to_string(),
+        ));
+    };
+
+    let mut w = BufWriter::new(File::create(path)?);
+    write_layers(&mut w, stack, format)?;
+    w.flush()?;
+
+    Ok(())
+}
+
+/// Encode all layers of `stack` together with the flattened image
+///
+/// Only `Format::Ora` is supported. Masks are applied to the alpha values of the pixels and a
+/// background color is stored as an additional bottom layer. Adjustment layers can't be stored
+/// and result in an error.
+pub fn save_layers_to_buffer(stack: &LayerStack, format: Format) -> Result<Vec<u8>, EncodingError> {
+    let mut out = vec![];
+    write_layers(&mut out, stack, format)?;
+    Ok(out)
+}
+
+fn write_layers<W>(w: W, stack: &LayerStack, format: Format) -> Result<(), EncodingError>
+where
+    W: Write,
+{
+    if format != Format::Ora {
+        return Err(EncodingError::InvalidConfig {
+            format,
+            message: "Layers can only be saved as ora".to_owned(),
+        });
+    }
+
+    encode_ora(w, &to_layered_image(stack)?, &stack.flatten())
+}
+
+/// Get the blend op for a psd blend mode key
+fn blend_op(blend_mode: &str) -> BlendOp {
+    match blend_mode {
+        "lddg" => BlendOp::Addition,
+        "fsub" => BlendOp::Subtract,
+        "dark" => BlendOp::Darken,
+        "lite" => BlendOp::Lighten,
+        "dkCl" => BlendOp::LchDarken,
+        "lgCl" => BlendOp::LchLighten,
+        "hue " => BlendOp::LchHue,
+        "sat " => BlendOp::LchSaturation,
+        "colr" => BlendOp::LchColor,
+        _ => BlendOp::Normal,
+    }
+}
+
+/// Get the psd blend mode key for a blend op
+///
+/// The hsl variants have no equivalent and use the closest key.
+fn blend_mode(blend_op: BlendOp) -> &'static str {
+    match blend_op {
+        BlendOp::Normal => "norm",
+        BlendOp::Addition => "lddg",
+        BlendOp::Subtract => "fsub",
+        BlendOp::Darken | BlendOp::HslDarken => "dark",
+        BlendOp::Lighten | BlendOp::HslLighten => "lite",
+        BlendOp::LchDarken => "dkCl",
+        BlendOp::LchLighten => "lgCl",
+        BlendOp::LchHue => "hue ",
+        BlendOp::LchSaturation => "sat ",
+        BlendOp::LchColor => "colr",
+    }
+}
+
+fn to_layered_image(stack: &LayerStack) -> Result<LayeredImage, EncodingError> {
+    let mut layers = vec![];
+
+    if stack.background != Rgb::NONE {
+        layers.push(ImageLayer {
+            name: "Background".to_owned(),
+            buffer: PixelBuffer::new_with_color(stack.width(), stack.height(), stack.background),
+            x: 0,
+            y: 0,
+            opacity: 1.0,
+            visible: true,
+            blend_mode: "norm".to_owned(),
+        });
+    }
+
+    for layer in &stack.layers {
+        let Some(buffer) = layer.masked_pixels() else {
+            return Err(EncodingError::InvalidConfig {
+                format: Format::Ora,
+                message: format!("Adjustment layer {:?} can't be saved", layer.name),
+            });
+        };
+
+        layers.push(ImageLayer {
+            name: layer.name.clone(),
+            buffer,
+            x: layer.x,
+            y: layer.y,
+            opacity: layer.opacity,
+            visible: layer.visible,
+            blend_mode: blend_mode(layer.blend_op).to_owned(),
+        });
+    }
+
+    Ok(LayeredImage {
+        width: stack.width(),
+        height: stack.height(),
+        layers,
+    })
+}
+
+fn to_layer_stack(decoded: LayeredImage) -> LayerStack {
+    let mut stack = LayerStack::new(decoded.width, decoded.height);
+
+    for layer in decoded.layers {
+        stack.push(
+            Layer::pixels(layer.buffer)
+                .with_name(&layer.name)
+                .with_position(layer.x, layer.y)
+                .with_opacity(layer.opacity)
+                .with_visible(layer.visible)
+                .with_blend_op(blend_op(&layer.blend_mode)),
+        );
+    }
+
+    stack
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color, Image};
+
+    #[test]
+    fn test_open_layers() {
+        let stack =
+            open_layers("../d10-codecs/tests/images/psd/transparent-top-layer-2x1.psd").unwrap();
+
+        assert_eq!((stack.width(), stack.height()), (2, 1));
+
+        let names: Vec<_> = stack.layers.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, ["Red Layer", "Green Layer", "Blue Layer"]);
+        assert_eq!((stack.layers[2].x, stack.layers[2].y), (1, 0));
+
+        let flattened = stack.flatten();
+        assert_eq!(flattened.get_pixel(0, 0), &Rgb::GREEN);
+        assert_eq!(flattened.get_pixel(1, 0), &Rgb::BLUE);
+    }
+
+    #[test]
+    fn test_read_layers_without_layers() {
+        let image = Image::new_with_color(3, 2, Rgb::RED);
+        let data = image.save_to_buffer(crate::EncodingFormat::Dds).unwrap();
+
+        let stack = read_layers(&data).unwrap();
+        assert_eq!(stack.layers.len(), 1);
+        assert_eq!(stack.flatten().data(), image.data());
+    }
+
+    #[test]
+    fn test_save_layers() {
+        let mask =
+            PixelBuffer::new_from_func(4, 3, |x, _| if x == 0 { Rgb::BLACK } else { Rgb::WHITE });
+
+        let stack = LayerStack::new(4, 3)
+            .with_background(Rgb::WHITE)
+            .with(
+                Layer::pixels(PixelBuffer::new_with_color(2, 2, Rgb::RED))
+                    .with_name("red <&>")
+                    .with_position(-1, 1)
+                    .with_opacity(0.5)
+                    .with_mask(mask),
+            )
+            .with(
+                Layer::pixels(PixelBuffer::new_with_color(1, 1, Rgb::BLUE))
+                    .with_name("hidden")
+                    .with_visible(false)
+                    .with_blend_op(BlendOp::Darken),
+            );
+
+        let data = save_layers_to_buffer(&stack, Format::Ora).unwrap();
+
+        let composite = Image::read_from_buffer(&data).unwrap();
+        assert_eq!(composite.data(), stack.flatten().data());
+
+        let result = read_layers(&data).unwrap();
+        assert_eq!((result.width(), result.height()), (4, 3));
+        assert_eq!(result.layers.len(), 3);
+        assert_eq!(result.flatten().data(), stack.flatten().data());
+
+        let red = &result.layers[1];
+        assert_eq!(red.name, "red <&>");
+        assert_eq!((red.x, red.y, red.opacity), (-1, 1, 0.5));
+        assert_eq!(red.masked_pixels().unwrap().get_pixel(0, 0).alpha(), 0.0);
+
+        let hidden = &result.layers[2];
+        assert!(!hidden.visible);
+        assert_eq!(hidden.blend_op, BlendOp::Darken);
+    }
+
+    #[test]
+    fn test_save_layers_errors() {
+        let stack = LayerStack::new(2, 2).with(Layer::adjustment(crate::ops::Pipeline::new()));
+
+        for format in [Format::Ora, Format::Png] {
+            let err = save_layers_to_buffer(&stack, format).unwrap_err();
+            assert_eq!(err.code(), "invalid_config");
+        }
+
+        let err = save_layers(&LayerStack::new(1, 1), "layers.unknown").unwrap_err();
+        assert_eq!(err.code(), "bad_file_extension");
+    }
+
+    #[test]
+    fn test_blend_op() {
+        assert_eq!(blend_op("norm"), BlendOp::Normal);
+        assert_eq!(blend_op("lddg"), BlendOp::Addition);
+        assert_eq!(blend_op("hue "), BlendOp::LchHue);
+        assert_eq!(blend_op("unknown"), BlendOp::Normal);
+
+        for op in [BlendOp::Normal, BlendOp::Subtract, BlendOp::LchColor] {
+            assert_eq!(blend_op(blend_mode(op)), op);
+        }
+    }
+}
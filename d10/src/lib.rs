@@ -6,13 +6,35 @@ pub use crate::core::color::*;
 pub use crate::core::errors::*;
 pub use crate::core::kernel::*;
 pub use crate::core::kernel_dyn::*;
+pub use crate::core::palette::*;
 pub use crate::core::pixelbuffer::*;
+pub use crate::core::region::*;
+pub use crate::core::tile::*;
 
+mod animation;
+pub mod batch;
+mod errors;
+#[cfg(feature = "http")]
+mod http;
+mod icons;
 mod image;
+mod layers;
+mod storyboard;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
 
+pub use animation::{Animation, Frame};
 pub use codecs::{
-    BmpColorType, DecodingError, EncodingError, EncodingFormat, IcoColorType, JpegSamplingFactor,
-    PngColorType, PngCompression, PngFilterType, WebPPreset,
+    decode_palette, encode_palette, load_palette, save_palette, BmpColorType, DecodingError,
+    EncodeOptions, EncodingError, EncodingFormat, Format, GifDither, IcoColorType,
+    JpegSamplingFactor, JpegStreamEncoder, PaletteError, PaletteFormat, PngColorType,
+    PngCompression, PngFilterType, PngStreamEncoder, WebPPreset,
 };
+pub use errors::{D10Error, D10Result};
+#[cfg(feature = "http")]
+pub use http::{HttpError, DEFAULT_MAX_DOWNLOAD_SIZE};
+pub use icons::{generate_icons, save_icons, Icon, IconSet, DEFAULT_ICON_SIZES};
 pub use image::Image;
-pub use ops::{EqualizeMode, FilterMode, EdgeDetection};
+pub use layers::{open_layers, read_layers, save_layers, save_layers_to_buffer};
+pub use ops::{EdgeDetection, EqualizeMode, FilterMode, FrameInterpolation, ResizeOptions};
+pub use storyboard::{generate_storyboard, Storyboard, StoryboardCue, StoryboardOptions};
@@ -0,0 +1,278 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::{EncodingError, EncodingFormat, FilterMode, Frame, Image, PixelBuffer, Region, Rgb};
+
+/// Layout of the sprite sheets of a storyboard
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct StoryboardOptions {
+    /// Width of every thumbnail, the height follows the aspect ratio of the first frame
+    pub thumbnail_width: u32,
+    /// Number of thumbnails per row of a sheet
+    pub columns: u32,
+    /// Maximal number of rows per sheet
+    pub rows: u32,
+}
+
+impl Default for StoryboardOptions {
+    fn default() -> Self {
+        Self {
+            thumbnail_width: 160,
+            columns: 5,
+            rows: 5,
+        }
+    }
+}
+
+/// A thumbnail of a storyboard and the time range it covers
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StoryboardCue {
+    pub start: Duration,
+    pub end: Duration,
+    /// Index of the sprite sheet containing the thumbnail
+    pub sheet: usize,
+    /// Position of the thumbnail in the sprite sheet
+    pub region: Region,
+}
+
+/// Thumbnails of a video packed into sprite sheets, i.e. for previews when seeking
+#[derive(Clone, Debug)]
+pub struct Storyboard {
+    /// Sprite sheets filled from left to right and top to bottom
+    ///
+    /// The last sheet only contains the rows that are used.
+    pub sheets: Vec<Image>,
+    pub cues: Vec<StoryboardCue>,
+}
+
+impl Storyboard {
+    /// Create a WebVTT file with one cue per thumbnail
+    ///
+    /// `sheet_url` gets the index of a sheet and returns the url used in the cues. The position of
+    /// a thumbnail is appended as media fragment, e.g. `sheet-0.jpeg#xywh=160,0,160,90`.
+    pub fn to_webvtt<F>(&self, sheet_url: F) -> String
+    where
+        F: Fn(usize) -> String,
+    {
+        let mut out = "WEBVTT\n".to_owned();
+
+        for cue in &self.cues {
+            let r = &cue.region;
+
+            // Writing into a string can't fail
+            let _ = write!(
+                out,
+                "\n{} --> {}\n{}#xywh={},{},{},{}\n",
+                timestamp(cue.start),
+                timestamp(cue.end),
+                sheet_url(cue.sheet),
+                r.x,
+                r.y,
+                r.width,
+                r.height
+            );
+        }
+
+        out
+    }
+
+    /// Save the sheets as `{name}-{index}.{extension}` and the cues as `{name}.vtt` into `dir`
+    ///
+    /// The cues reference the sheets by their file names, so both have to be served from the
+    /// same location.
+    pub fn save<P>(&self, dir: P, name: &str, format: EncodingFormat) -> Result<(), EncodingError>
+    where
+        P: AsRef<Path>,
+    {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        let sheet_name = |i: usize| format!("{}-{}.{}", name, i, format.format().name());
+
+        for (i, sheet) in self.sheets.iter().enumerate() {
+            sheet.save_with_format(dir.join(sheet_name(i)), format.clone())?;
+        }
+
+        fs::write(
+            dir.join(format!("{}.vtt", name)),
+            self.to_webvtt(sheet_name),
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Format a duration as WebVTT timestamp like `00:01:02.500`
+fn timestamp(duration: Duration) -> String {
+    let millis = duration.as_millis();
+
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Pack thumbnails of frames into sprite sheets for a storyboard
+///
+/// Every frame covers the time range of its delay starting at the end of the previous frame.
+/// Frames are consumed one by one and only their thumbnails are kept, which allows to pass the
+/// frames of a long video directly from a decoder.
+pub fn generate_storyboard<I>(frames: I, options: &StoryboardOptions) -> Storyboard
+where
+    I: IntoIterator<Item = Frame>,
+{
+    let columns = options.columns.max(1);
+    let rows = options.rows.max(1);
+    let per_sheet = (columns * rows) as usize;
+    let width = options.thumbnail_width.max(1);
+
+    let mut height = 0;
+    let mut sheets = vec![];
+    let mut cues = vec![];
+    let mut sheet = PixelBuffer::new(0, 0);
+    let mut start = Duration::ZERO;
+
+    for (i, frame) in frames.into_iter().enumerate() {
+        if i == 0 {
+            let aspect = frame.image.height() as f32 / frame.image.width().max(1) as f32;
+            height = ((width as f32 * aspect).round() as u32).max(1);
+        }
+
+        let index = i % per_sheet;
+
+        if index == 0 {
+            if i > 0 {
+                sheets.push(Image::new_from_buffer(sheet));
+            }
+            sheet = PixelBuffer::new_with_color(columns * width, rows * height, Rgb::NONE);
+        }
+
+        let region = Region::new(
+            index as u32 % columns * width,
+            index as u32 / columns * height,
+            width,
+            height,
+        );
+
+        let thumbnail = frame.image.resize(width, height, FilterMode::Auto);
+        sheet.copy_from(
+            thumbnail.buffer(),
+            Region::new(0, 0, width, height),
+            (region.x, region.y),
+        );
+
+        cues.push(StoryboardCue {
+            start,
+            end: start + frame.delay,
+            sheet: i / per_sheet,
+            region,
+        });
+
+        start += frame.delay;
+    }
+
+    if let Some(last) = cues.last() {
+        let used_height = last.region.y + height;
+        let image = Image::new_from_buffer(sheet);
+
+        sheets.push(if used_height < image.height() {
+            image.crop(0, 0, image.width(), used_height)
+        } else {
+            image
+        });
+    }
+
+    Storyboard { sheets, cues }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(count: usize) -> impl Iterator<Item = Frame> {
+        (0..count).map(|i| {
+            let color = if i % 2 == 0 { Rgb::RED } else { Rgb::BLUE };
+            Frame::new(
+                Image::new_with_color(320, 180, color),
+                Duration::from_millis(2500),
+            )
+        })
+    }
+
+    #[test]
+    fn test_generate_storyboard() {
+        let options = StoryboardOptions {
+            thumbnail_width: 80,
+            columns: 3,
+            rows: 2,
+        };
+
+        let storyboard = generate_storyboard(frames(8), &options);
+
+        let sizes: Vec<_> = storyboard
+            .sheets
+            .iter()
+            .map(|s| (s.width(), s.height()))
+            .collect();
+        assert_eq!(sizes, [(240, 90), (240, 45)]);
+
+        assert_eq!(storyboard.cues.len(), 8);
+        assert_eq!(
+            storyboard.cues[4],
+            StoryboardCue {
+                start: Duration::from_secs(10),
+                end: Duration::from_millis(12500),
+                sheet: 0,
+                region: Region::new(80, 45, 80, 45),
+            }
+        );
+        assert_eq!(storyboard.cues[7].sheet, 1);
+        assert_eq!(storyboard.cues[7].region, Region::new(80, 0, 80, 45));
+
+        let red = storyboard.sheets[0].get_pixel(100, 60);
+        assert!(red.red() > 0.95 && red.blue() < 0.05, "{:?}", red);
+        let blue = storyboard.sheets[1].get_pixel(100, 20);
+        assert!(blue.blue() > 0.95 && blue.red() < 0.05, "{:?}", blue);
+        assert_eq!(storyboard.sheets[1].get_pixel(200, 20), &Rgb::NONE);
+
+        let empty = generate_storyboard(frames(0), &options);
+        assert!(empty.sheets.is_empty() && empty.cues.is_empty());
+    }
+
+    #[test]
+    fn test_webvtt() {
+        let storyboard = generate_storyboard(frames(2), &StoryboardOptions::default());
+
+        let vtt = storyboard.to_webvtt(|i| format!("thumbs/{}.jpg", i));
+        assert_eq!(
+            vtt,
+            "WEBVTT\n\
+             \n00:00:00.000 --> 00:00:02.500\nthumbs/0.jpg#xywh=0,0,160,90\n\
+             \n00:00:02.500 --> 00:00:05.000\nthumbs/0.jpg#xywh=160,0,160,90\n"
+        );
+
+        assert_eq!(timestamp(Duration::from_millis(3_723_042)), "01:02:03.042");
+    }
+
+    #[test]
+    fn test_save_storyboard() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("storyboard");
+
+        let storyboard = generate_storyboard(frames(3), &StoryboardOptions::default());
+        storyboard
+            .save(&dir, "preview", EncodingFormat::jpeg_default())
+            .unwrap();
+
+        let vtt = fs::read_to_string(dir.join("preview.vtt")).unwrap();
+        assert!(vtt.contains("preview-0.jpeg#xywh=320,0,160,90"));
+
+        let sheet = Image::open(dir.join("preview-0.jpeg")).unwrap();
+        assert_eq!((sheet.width(), sheet.height()), (800, 90));
+    }
+}
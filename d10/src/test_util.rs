@@ -0,0 +1,206 @@
+//! Snapshot tests comparing images with stored golden files
+//!
+//! Enable the `test-util` feature in the dev-dependencies to use this module in other crates.
+//! Golden files are written as 16 bit png files when the `D10_UPDATE_GOLDEN` environment
+//! variable is set, e.g. with `D10_UPDATE_GOLDEN=1 cargo test`.
+//!
+//! On a mismatch the output is saved next to the golden file as `<name>.actual.png` together with
+//! a heatmap of the differences as `<name>.diff.png`.
+
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::ops::{DiffMode, DEFAULT_DIFF_THRESHOLD};
+use crate::{DecodingError, EncodingError, EncodingFormat, Image};
+use crate::{PngColorType, PngCompression, PngFilterType};
+
+/// Environment variable to overwrite golden files with the current output
+pub const UPDATE_GOLDEN_ENV: &str = "D10_UPDATE_GOLDEN";
+
+/// Tolerance of `assert_golden()` that covers the precision of the stored files
+/// and small float differences between platforms
+pub const DEFAULT_GOLDEN_TOLERANCE: f32 = 1.0 / 1024.0;
+
+#[derive(Debug, Error)]
+pub enum GoldenError {
+    #[error(
+        "Golden file {} is missing, run with {}=1 to create it",
+        .path.display(),
+        UPDATE_GOLDEN_ENV
+    )]
+    Missing { path: PathBuf },
+    #[error(
+        "Expected size {:?} of golden file {} but got {:?}",
+        .expected,
+        .path.display(),
+        .actual
+    )]
+    SizeMismatch {
+        path: PathBuf,
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    #[error(
+        "Image differs from golden file {} by {}, see {}",
+        .path.display(),
+        .max_difference,
+        .diff_path.display()
+    )]
+    Mismatch {
+        path: PathBuf,
+        max_difference: f32,
+        diff_path: PathBuf,
+    },
+    #[error(transparent)]
+    Decoding(#[from] DecodingError),
+    #[error(transparent)]
+    Encoding(#[from] EncodingError),
+}
+
+fn golden_format() -> EncodingFormat {
+    EncodingFormat::Png {
+        color_type: PngColorType::Rgba16,
+        compression: PngCompression::Best,
+        filter: PngFilterType::Paeth,
+        interlaced: false,
+        palette: None,
+    }
+}
+
+fn update_requested() -> bool {
+    std::env::var(UPDATE_GOLDEN_ENV).is_ok_and(|value| !value.is_empty() && value != "0")
+}
+
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    path.with_extension(format!("{}.png", suffix))
+}
+
+/// Compare `image` with the golden file at `path`
+///
+/// Fails if the size differs or any channel value differs by more than `tolerance`.
+pub fn check_golden<P>(image: &Image, path: P, tolerance: f32) -> Result<(), GoldenError>
+where
+    P: AsRef<Path>,
+{
+    compare_golden(image, path.as_ref(), tolerance, update_requested())
+}
+
+fn compare_golden(
+    image: &Image,
+    path: &Path,
+    tolerance: f32,
+    update: bool,
+) -> Result<(), GoldenError> {
+    let actual_path = sibling_path(path, "actual");
+    let diff_path = sibling_path(path, "diff");
+
+    if update {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(EncodingError::IoError)?;
+        }
+
+        image.save_with_format(path, golden_format())?;
+    }
+
+    if !path.exists() {
+        image.save_with_format(&actual_path, golden_format())?;
+        return Err(GoldenError::Missing {
+            path: path.to_owned(),
+        });
+    }
+
+    let golden = Image::open(path)?;
+
+    if golden.width() != image.width() || golden.height() != image.height() {
+        image.save_with_format(&actual_path, golden_format())?;
+        return Err(GoldenError::SizeMismatch {
+            path: path.to_owned(),
+            expected: (golden.width(), golden.height()),
+            actual: (image.width(), image.height()),
+        });
+    }
+
+    let max_difference = golden.buffer().max_channel_difference(image.buffer());
+
+    if max_difference > tolerance {
+        image.save_with_format(&actual_path, golden_format())?;
+        golden
+            .diff_visualize(image, DiffMode::Heatmap, DEFAULT_DIFF_THRESHOLD)
+            .save_with_format(&diff_path, EncodingFormat::png_default())?;
+
+        return Err(GoldenError::Mismatch {
+            path: path.to_owned(),
+            max_difference,
+            diff_path,
+        });
+    }
+
+    // Files of previous failures would be misleading
+    let _ = std::fs::remove_file(actual_path);
+    let _ = std::fs::remove_file(diff_path);
+
+    Ok(())
+}
+
+/// Panic if `image` doesn't match the golden file at `path`
+///
+/// Uses `DEFAULT_GOLDEN_TOLERANCE`, see `check_golden()` for details.
+#[track_caller]
+pub fn assert_golden<P>(image: &Image, path: P)
+where
+    P: AsRef<Path>,
+{
+    assert_golden_with_tolerance(image, path, DEFAULT_GOLDEN_TOLERANCE);
+}
+
+/// Panic if `image` doesn't match the golden file at `path` within `tolerance`
+#[track_caller]
+pub fn assert_golden_with_tolerance<P>(image: &Image, path: P, tolerance: f32)
+where
+    P: AsRef<Path>,
+{
+    if let Err(err) = check_golden(image, path, tolerance) {
+        panic!("{}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rgb;
+
+    #[test]
+    fn test_check_golden() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let path = dir.join("image.png");
+
+        let image = Image::new_from_raw(2, 1, vec![Rgb::RED, Rgb::new(0.2, 0.4, 0.6)]);
+
+        assert!(matches!(
+            compare_golden(&image, &path, DEFAULT_GOLDEN_TOLERANCE, false),
+            Err(GoldenError::Missing { .. })
+        ));
+        assert!(dir.join("image.actual.png").exists());
+
+        compare_golden(&image, &path, DEFAULT_GOLDEN_TOLERANCE, true).unwrap();
+        assert!(path.exists());
+        assert!(!dir.join("image.actual.png").exists());
+
+        let mut changed = image.clone();
+        changed.put_pixel(1, 0, Rgb::new(0.2, 0.5, 0.6));
+
+        assert!(matches!(
+            compare_golden(&changed, &path, DEFAULT_GOLDEN_TOLERANCE, false),
+            Err(GoldenError::Mismatch { .. })
+        ));
+        assert!(dir.join("image.diff.png").exists());
+        compare_golden(&changed, &path, 0.2, false).unwrap();
+
+        assert!(matches!(
+            compare_golden(&Image::new(1, 1), &path, 1.0, false),
+            Err(GoldenError::SizeMismatch { .. })
+        ));
+    }
+}